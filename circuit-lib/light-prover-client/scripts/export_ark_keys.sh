@@ -0,0 +1,30 @@
+#!/usr/bin/env bash
+
+# Exports the given light-prover circuits (e.g. inclusion_26_1) for the
+# arkworks prover, as <circuit>.ark files next to the proving keys. Circuits
+# which are already exported are skipped.
+
+if [[ $# -eq 0 ]]; then
+  echo "Error: Please provide at least one circuit, e.g. inclusion_26_1."
+  exit 1
+fi
+
+root_dir="$(cd "$(dirname "${BASH_SOURCE[0]}")/../../.." && pwd)"
+keys_dir="$root_dir/light-prover/proving-keys"
+
+cd "$root_dir/light-prover" || exit 1
+go build || {
+  echo "light-prover build failed. Check for errors."
+  exit 1
+}
+
+for circuit in "$@"; do
+  if [ -f "$keys_dir/$circuit.ark" ]; then
+    continue
+  fi
+  ./light-prover export-ark --keys-file "$keys_dir/$circuit.key" --output "$keys_dir/$circuit.ark" || {
+    rm -f "$keys_dir/$circuit.ark"
+    echo "Failed to export $circuit."
+    exit 1
+  }
+done
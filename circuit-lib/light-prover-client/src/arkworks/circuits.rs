@@ -0,0 +1,350 @@
+//! R1CS versions of the `light-prover` circuits.
+//!
+//! The circuits enforce the same statements as their gnark counterparts and
+//! expose the public inputs in the same order, so a proof can be verified
+//! with `light-verifier` once it's given the verifying key of the
+//! corresponding arkworks setup.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use num_bigint::BigInt;
+
+use crate::{
+    arkworks::poseidon::poseidon_hash,
+    combined::merkle_combined_proof_inputs::CombinedProofInputs,
+    errors::CircuitsError,
+    inclusion::merkle_inclusion_proof_inputs::{InclusionMerkleProofInputs, InclusionProofInputs},
+    non_inclusion::merkle_non_inclusion_proof_inputs::{
+        NonInclusionMerkleProofInputs, NonInclusionProofInputs,
+    },
+};
+
+/// Number of bits of values in indexed Merkle trees. Addresses are truncated
+/// to 31 bytes.
+const INDEXED_VALUE_BITS: usize = 248;
+
+/// Roots and leaves (or values) allocated as public inputs.
+type PublicInputVars = (Vec<FpVar<Fr>>, Vec<FpVar<Fr>>);
+
+/// Converts a non-negative `BigInt` to a field element, failing if it's not
+/// lower than the field modulus.
+fn bigint_to_fr(n: &BigInt) -> Result<Fr, CircuitsError> {
+    let n = n.to_biguint().ok_or(CircuitsError::InputsParsingError)?;
+    if n >= Fr::MODULUS.into() {
+        return Err(CircuitsError::InputsParsingError);
+    }
+    Ok(Fr::from(n))
+}
+
+fn bigints_to_fr(ns: &[BigInt]) -> Result<Vec<Fr>, CircuitsError> {
+    ns.iter().map(bigint_to_fr).collect()
+}
+
+/// Allocates the `num_bits` lowest bits of `value` as witnesses and enforces
+/// that they add up to `value`, so `value` is lower than `2^num_bits`.
+fn to_bits(
+    cs: ConstraintSystemRef<Fr>,
+    value: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<Vec<Boolean<Fr>>, SynthesisError> {
+    let bigint = value.value().map(|value| value.into_bigint());
+    let bits = (0..num_bits)
+        .map(|i| {
+            Boolean::new_witness(cs.clone(), || {
+                bigint
+                    .as_ref()
+                    .map(|value| value.get_bit(i))
+                    .map_err(|e| *e)
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(value)?;
+    Ok(bits)
+}
+
+/// Enforces `a < b` and that both values are lower than `2^num_bits`.
+fn enforce_less_than(
+    cs: ConstraintSystemRef<Fr>,
+    a: &FpVar<Fr>,
+    b: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    // Without the range checks, values wrapping around the field modulus
+    // (e.g. `a = -1`) pass the check below.
+    to_bits(cs.clone(), a, num_bits)?;
+    to_bits(cs.clone(), b, num_bits)?;
+    // `a - b + 2^num_bits` fits in `num_bits` only if `a < b`.
+    let num = a - b + Fr::from(2u64).pow([num_bits as u64]);
+    to_bits(cs, &num, num_bits)?;
+    Ok(())
+}
+
+/// Computes the root of a Merkle tree from the given `leaf`, index of the
+/// leaf and its path.
+fn merkle_root(
+    cs: ConstraintSystemRef<Fr>,
+    leaf: FpVar<Fr>,
+    path_index: Fr,
+    path_elements: &[Fr],
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let path_index = FpVar::new_witness(cs.clone(), || Ok(path_index))?;
+    let bits = to_bits(cs.clone(), &path_index, path_elements.len())?;
+
+    let mut hash = leaf;
+    for (bit, sibling) in bits.iter().zip(path_elements.iter()) {
+        let sibling = FpVar::new_witness(cs.clone(), || Ok(*sibling))?;
+        let left = bit.select(&sibling, &hash)?;
+        let right = bit.select(&hash, &sibling)?;
+        hash = poseidon_hash(&[left, right])?;
+    }
+    Ok(hash)
+}
+
+#[derive(Clone, Debug)]
+pub struct InclusionWitness {
+    pub root: Fr,
+    pub leaf: Fr,
+    pub path_index: Fr,
+    pub path_elements: Vec<Fr>,
+}
+
+impl TryFrom<&InclusionMerkleProofInputs> for InclusionWitness {
+    type Error = CircuitsError;
+
+    fn try_from(inputs: &InclusionMerkleProofInputs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            root: bigint_to_fr(&inputs.root)?,
+            leaf: bigint_to_fr(&inputs.leaf)?,
+            path_index: bigint_to_fr(&inputs.path_index)?,
+            path_elements: bigints_to_fr(&inputs.path_elements)?,
+        })
+    }
+}
+
+/// Proves that `leaves` are part of Merkle trees with the given `roots`.
+///
+/// Public inputs: roots, leaves.
+#[derive(Clone, Debug)]
+pub struct InclusionCircuit {
+    pub height: usize,
+    pub proofs: Vec<InclusionWitness>,
+}
+
+impl InclusionCircuit {
+    pub fn new(height: usize, inputs: &InclusionProofInputs) -> Result<Self, CircuitsError> {
+        let proofs = inputs
+            .0
+            .iter()
+            .map(InclusionWitness::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        if proofs.iter().any(|p| p.path_elements.len() != height) {
+            return Err(CircuitsError::InputsParsingError);
+        }
+        Ok(Self { height, proofs })
+    }
+
+    /// Circuit with zeroed inputs, used for the setup.
+    pub fn empty(height: usize, num_proofs: usize) -> Self {
+        let proof = InclusionWitness {
+            root: Fr::from(0u64),
+            leaf: Fr::from(0u64),
+            path_index: Fr::from(0u64),
+            path_elements: vec![Fr::from(0u64); height],
+        };
+        Self {
+            height,
+            proofs: vec![proof; num_proofs],
+        }
+    }
+
+    /// Allocates roots and leaves as public inputs.
+    fn alloc_public_inputs(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+    ) -> Result<PublicInputVars, SynthesisError> {
+        let roots = self
+            .proofs
+            .iter()
+            .map(|p| FpVar::new_input(cs.clone(), || Ok(p.root)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let leaves = self
+            .proofs
+            .iter()
+            .map(|p| FpVar::new_input(cs.clone(), || Ok(p.leaf)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((roots, leaves))
+    }
+
+    fn enforce(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        roots: Vec<FpVar<Fr>>,
+        leaves: Vec<FpVar<Fr>>,
+    ) -> Result<(), SynthesisError> {
+        for ((proof, root), leaf) in self.proofs.iter().zip(roots).zip(leaves) {
+            merkle_root(cs.clone(), leaf, proof.path_index, &proof.path_elements)?
+                .enforce_equal(&root)?;
+        }
+        Ok(())
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for InclusionCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let (roots, leaves) = self.alloc_public_inputs(cs.clone())?;
+        self.enforce(cs, roots, leaves)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NonInclusionWitness {
+    pub root: Fr,
+    pub value: Fr,
+    pub leaf_lower_range_value: Fr,
+    pub leaf_higher_range_value: Fr,
+    pub next_index: Fr,
+    pub path_index: Fr,
+    pub path_elements: Vec<Fr>,
+}
+
+impl TryFrom<&NonInclusionMerkleProofInputs> for NonInclusionWitness {
+    type Error = CircuitsError;
+
+    fn try_from(inputs: &NonInclusionMerkleProofInputs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            root: bigint_to_fr(&inputs.root)?,
+            value: bigint_to_fr(&inputs.value)?,
+            leaf_lower_range_value: bigint_to_fr(&inputs.leaf_lower_range_value)?,
+            leaf_higher_range_value: bigint_to_fr(&inputs.leaf_higher_range_value)?,
+            next_index: bigint_to_fr(&inputs.next_index)?,
+            path_index: bigint_to_fr(&inputs.index_hashed_indexed_element_leaf)?,
+            path_elements: bigints_to_fr(&inputs.merkle_proof_hashed_indexed_element_leaf)?,
+        })
+    }
+}
+
+/// Proves that `values` are not part of indexed Merkle trees with the given
+/// `roots`, by proving inclusion of low elements whose ranges contain the
+/// values.
+///
+/// Public inputs: roots, values.
+#[derive(Clone, Debug)]
+pub struct NonInclusionCircuit {
+    pub height: usize,
+    pub proofs: Vec<NonInclusionWitness>,
+}
+
+impl NonInclusionCircuit {
+    pub fn new(height: usize, inputs: &NonInclusionProofInputs) -> Result<Self, CircuitsError> {
+        let proofs = inputs
+            .0
+            .iter()
+            .map(NonInclusionWitness::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        if proofs.iter().any(|p| p.path_elements.len() != height) {
+            return Err(CircuitsError::InputsParsingError);
+        }
+        Ok(Self { height, proofs })
+    }
+
+    /// Circuit with zeroed inputs, used for the setup.
+    pub fn empty(height: usize, num_proofs: usize) -> Self {
+        let proof = NonInclusionWitness {
+            root: Fr::from(0u64),
+            value: Fr::from(0u64),
+            leaf_lower_range_value: Fr::from(0u64),
+            leaf_higher_range_value: Fr::from(0u64),
+            next_index: Fr::from(0u64),
+            path_index: Fr::from(0u64),
+            path_elements: vec![Fr::from(0u64); height],
+        };
+        Self {
+            height,
+            proofs: vec![proof; num_proofs],
+        }
+    }
+
+    /// Allocates roots and values as public inputs.
+    fn alloc_public_inputs(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+    ) -> Result<PublicInputVars, SynthesisError> {
+        let roots = self
+            .proofs
+            .iter()
+            .map(|p| FpVar::new_input(cs.clone(), || Ok(p.root)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = self
+            .proofs
+            .iter()
+            .map(|p| FpVar::new_input(cs.clone(), || Ok(p.value)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((roots, values))
+    }
+
+    fn enforce(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        roots: Vec<FpVar<Fr>>,
+        values: Vec<FpVar<Fr>>,
+    ) -> Result<(), SynthesisError> {
+        for ((proof, root), value) in self.proofs.iter().zip(roots).zip(values) {
+            let lower = FpVar::new_witness(cs.clone(), || Ok(proof.leaf_lower_range_value))?;
+            let higher = FpVar::new_witness(cs.clone(), || Ok(proof.leaf_higher_range_value))?;
+            let next_index = FpVar::new_witness(cs.clone(), || Ok(proof.next_index))?;
+
+            lower.enforce_not_equal(&value)?;
+            enforce_less_than(cs.clone(), &lower, &value, INDEXED_VALUE_BITS)?;
+            enforce_less_than(cs.clone(), &value, &higher, INDEXED_VALUE_BITS)?;
+
+            let leaf = poseidon_hash(&[lower, next_index, higher])?;
+            merkle_root(cs.clone(), leaf, proof.path_index, &proof.path_elements)?
+                .enforce_equal(&root)?;
+        }
+        Ok(())
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for NonInclusionCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let (roots, values) = self.alloc_public_inputs(cs.clone())?;
+        self.enforce(cs, roots, values)
+    }
+}
+
+/// Inclusion and non-inclusion proofs in one circuit.
+///
+/// Public inputs: roots, leaves, address roots, addresses.
+#[derive(Clone, Debug)]
+pub struct CombinedCircuit {
+    pub inclusion: InclusionCircuit,
+    pub non_inclusion: NonInclusionCircuit,
+}
+
+impl CombinedCircuit {
+    pub fn new(height: usize, inputs: &CombinedProofInputs) -> Result<Self, CircuitsError> {
+        Ok(Self {
+            inclusion: InclusionCircuit::new(height, &inputs.inclusion_parameters)?,
+            non_inclusion: NonInclusionCircuit::new(height, &inputs.non_inclusion_parameters)?,
+        })
+    }
+
+    /// Circuit with zeroed inputs, used for the setup.
+    pub fn empty(height: usize, num_inclusion: usize, num_non_inclusion: usize) -> Self {
+        Self {
+            inclusion: InclusionCircuit::empty(height, num_inclusion),
+            non_inclusion: NonInclusionCircuit::empty(height, num_non_inclusion),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for CombinedCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let (roots, leaves) = self.inclusion.alloc_public_inputs(cs.clone())?;
+        let (address_roots, addresses) = self.non_inclusion.alloc_public_inputs(cs.clone())?;
+        self.inclusion.enforce(cs.clone(), roots, leaves)?;
+        self.non_inclusion.enforce(cs, address_roots, addresses)
+    }
+}
@@ -0,0 +1,600 @@
+//! Circuits of `light-prover`, exported for the arkworks prover with
+//! `scripts/export_ark_keys.sh`.
+//!
+//! An exported circuit holds the gnark proving key and the constraint system
+//! of the circuit. The values of all wires are computed from the circuit
+//! inputs the way the gnark solver does, and the proof uses the R1CS to QAP
+//! reduction of gnark, so proofs verify against the verifying keys of
+//! `light-verifier`.
+
+use std::{collections::HashMap, io::Read};
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, Field, PrimeField, UniformRand, Zero};
+use ark_groth16::{
+    r1cs_to_qap::{evaluate_constraint, LibsnarkReduction, R1CSToQAP},
+    Groth16, Proof, ProvingKey, VerifyingKey,
+};
+use ark_poly::EvaluationDomain;
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSystemRef, Matrix, Result as R1CSResult, SynthesisError,
+};
+use rand::Rng;
+
+use crate::errors::CircuitsError;
+
+use super::circuits::{CombinedCircuit, InclusionCircuit, NonInclusionCircuit};
+
+/// Magic bytes of an exported circuit.
+pub const MAGIC: &[u8; 8] = b"LIGHTARK";
+/// Version of the export format, written by `light-prover export-ark`.
+pub const VERSION: u32 = 1;
+
+/// Suffix of the name of the gnark hint computing the bits of its input,
+/// used by `api.ToBinary`.
+const BITS_HINT_SUFFIX: &str = ".nBits";
+
+/// Linear combination of wires, as `(coefficient, wire)` terms.
+type LinearExpression = Vec<(Fr, usize)>;
+
+/// Hint computing the bits of `input`, least significant first, into
+/// `outputs`.
+#[derive(Debug)]
+struct BitsHint {
+    input: LinearExpression,
+    outputs: Vec<usize>,
+}
+
+/// Reads the values of the export format. Integers are big-endian `u32`,
+/// field elements 32-byte big-endian integers, G1 points `x || y`, G2
+/// points `x.c1 || x.c0 || y.c1 || y.c0` and the point at infinity zeros.
+struct ExportReader<R> {
+    reader: R,
+}
+
+impl<R: Read> ExportReader<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], CircuitsError> {
+        let mut bytes = [0u8; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<usize, CircuitsError> {
+        Ok(u32::from_be_bytes(self.bytes()?) as usize)
+    }
+
+    /// Reads a canonical field element.
+    fn field<F: PrimeField>(&mut self) -> Result<F, CircuitsError> {
+        let mut bytes = self.bytes::<32>()?;
+        bytes.reverse();
+        F::deserialize_uncompressed(&bytes[..]).map_err(|_| {
+            CircuitsError::InvalidExportedCircuit("field element out of range".to_string())
+        })
+    }
+
+    fn g1(&mut self) -> Result<G1Affine, CircuitsError> {
+        let x: Fq = self.field()?;
+        let y: Fq = self.field()?;
+        if x.is_zero() && y.is_zero() {
+            return Ok(G1Affine::identity());
+        }
+        let point = G1Affine::new_unchecked(x, y);
+        if !point.is_on_curve() {
+            return Err(CircuitsError::InvalidExportedCircuit(
+                "G1 point not on curve".to_string(),
+            ));
+        }
+        Ok(point)
+    }
+
+    fn g2(&mut self) -> Result<G2Affine, CircuitsError> {
+        let x_c1: Fq = self.field()?;
+        let x_c0: Fq = self.field()?;
+        let y_c1: Fq = self.field()?;
+        let y_c0: Fq = self.field()?;
+        let x = Fq2::new(x_c0, x_c1);
+        let y = Fq2::new(y_c0, y_c1);
+        if x.is_zero() && y.is_zero() {
+            return Ok(G2Affine::identity());
+        }
+        let point = G2Affine::new_unchecked(x, y);
+        if !point.is_on_curve() {
+            return Err(CircuitsError::InvalidExportedCircuit(
+                "G2 point not on curve".to_string(),
+            ));
+        }
+        Ok(point)
+    }
+
+    fn g1s(&mut self, expected_len: usize) -> Result<Vec<G1Affine>, CircuitsError> {
+        let len = self.u32()?;
+        if len != expected_len {
+            return Err(CircuitsError::InvalidExportedCircuit(format!(
+                "expected {} G1 points, got {}",
+                expected_len, len
+            )));
+        }
+        (0..len).map(|_| self.g1()).collect()
+    }
+
+    fn g2s(&mut self, expected_len: usize) -> Result<Vec<G2Affine>, CircuitsError> {
+        let len = self.u32()?;
+        if len != expected_len {
+            return Err(CircuitsError::InvalidExportedCircuit(format!(
+                "expected {} G2 points, got {}",
+                expected_len, len
+            )));
+        }
+        (0..len).map(|_| self.g2()).collect()
+    }
+
+    fn wire(&mut self, num_wires: usize) -> Result<usize, CircuitsError> {
+        let wire = self.u32()?;
+        if wire >= num_wires {
+            return Err(CircuitsError::InvalidExportedCircuit(format!(
+                "wire {} out of range",
+                wire
+            )));
+        }
+        Ok(wire)
+    }
+
+    fn linear_expression(&mut self, num_wires: usize) -> Result<LinearExpression, CircuitsError> {
+        let len = self.u32()?;
+        (0..len)
+            .map(|_| {
+                let wire = self.wire(num_wires)?;
+                Ok((self.field()?, wire))
+            })
+            .collect()
+    }
+}
+
+/// `light-prover` circuit with its proving key.
+pub struct LightProverCircuit {
+    proving_key: ProvingKey<Bn254>,
+    /// Number of public wires, including the constant wire.
+    num_public: usize,
+    num_secret: usize,
+    matrices: ConstraintMatrices<Fr>,
+    hints: Vec<BitsHint>,
+    /// Index of the hint computing the wire, by wire.
+    hinted_wires: HashMap<usize, usize>,
+    /// Constraints in the order of solving.
+    solve_order: Vec<usize>,
+}
+
+impl LightProverCircuit {
+    /// Reads a circuit exported with `light-prover export-ark`.
+    pub fn read<R: Read>(reader: R) -> Result<Self, CircuitsError> {
+        let mut reader = ExportReader { reader };
+        if &reader.bytes::<8>()? != MAGIC {
+            return Err(CircuitsError::InvalidExportedCircuit(
+                "invalid magic bytes".to_string(),
+            ));
+        }
+        let version = reader.u32()?;
+        if version != VERSION as usize {
+            return Err(CircuitsError::InvalidExportedCircuit(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let num_public = reader.u32()?;
+        let num_secret = reader.u32()?;
+        let num_internal = reader.u32()?;
+        let domain_size = reader.u32()?;
+        if num_public == 0 {
+            return Err(CircuitsError::InvalidExportedCircuit(
+                "missing constant wire".to_string(),
+            ));
+        }
+        let num_wires = num_public + num_secret + num_internal;
+
+        let alpha_g1 = reader.g1()?;
+        let beta_g1 = reader.g1()?;
+        let delta_g1 = reader.g1()?;
+        let beta_g2 = reader.g2()?;
+        let gamma_g2 = reader.g2()?;
+        let delta_g2 = reader.g2()?;
+        let a_query = reader.g1s(num_wires)?;
+        let b_g1_query = reader.g1s(num_wires)?;
+        let b_g2_query = reader.g2s(num_wires)?;
+        let h_query = reader.g1s(domain_size)?;
+        let l_query = reader.g1s(num_secret + num_internal)?;
+        let gamma_abc_g1 = reader.g1s(num_public)?;
+        let proving_key = ProvingKey {
+            vk: VerifyingKey {
+                alpha_g1,
+                beta_g2,
+                gamma_g2,
+                delta_g2,
+                gamma_abc_g1,
+            },
+            beta_g1,
+            delta_g1,
+            a_query,
+            b_g1_query,
+            b_g2_query,
+            h_query,
+            l_query,
+        };
+
+        let num_constraints = reader.u32()?;
+        let mut a = Matrix::new();
+        let mut b = Matrix::new();
+        let mut c = Matrix::new();
+        for _ in 0..num_constraints {
+            a.push(reader.linear_expression(num_wires)?);
+            b.push(reader.linear_expression(num_wires)?);
+            c.push(reader.linear_expression(num_wires)?);
+        }
+        let domain = ark_poly::GeneralEvaluationDomain::<Fr>::new(num_constraints).ok_or(
+            CircuitsError::InvalidExportedCircuit("too many constraints".to_string()),
+        )?;
+        if domain.size() != domain_size {
+            return Err(CircuitsError::InvalidExportedCircuit(format!(
+                "domain of {} constraints has size {}, expected {}",
+                num_constraints,
+                domain.size(),
+                domain_size
+            )));
+        }
+        let matrices = ConstraintMatrices {
+            num_instance_variables: num_public,
+            num_witness_variables: num_secret + num_internal,
+            num_constraints,
+            a_num_non_zero: a.iter().map(Vec::len).sum(),
+            b_num_non_zero: b.iter().map(Vec::len).sum(),
+            c_num_non_zero: c.iter().map(Vec::len).sum(),
+            a,
+            b,
+            c,
+        };
+
+        let num_hints = reader.u32()?;
+        let mut hints = Vec::new();
+        let mut hinted_wires = HashMap::new();
+        for index in 0..num_hints {
+            let name_len = reader.u32()?;
+            let mut name = vec![0u8; name_len];
+            reader.reader.read_exact(&mut name)?;
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let num_inputs = reader.u32()?;
+            let mut inputs = (0..num_inputs)
+                .map(|_| reader.linear_expression(num_wires))
+                .collect::<Result<Vec<_>, _>>()?;
+            let num_outputs = reader.u32()?;
+            let outputs = (0..num_outputs)
+                .map(|_| reader.wire(num_wires))
+                .collect::<Result<Vec<_>, _>>()?;
+            if !name.ends_with(BITS_HINT_SUFFIX) || inputs.len() != 1 {
+                return Err(CircuitsError::InvalidExportedCircuit(format!(
+                    "unsupported hint {}",
+                    name
+                )));
+            }
+            for wire in outputs.iter() {
+                hinted_wires.insert(*wire, index);
+            }
+            hints.push(BitsHint {
+                input: inputs.remove(0),
+                outputs,
+            });
+        }
+
+        let num_solved = reader.u32()?;
+        let solve_order = (0..num_solved)
+            .map(|_| {
+                let constraint = reader.u32()?;
+                if constraint >= num_constraints {
+                    return Err(CircuitsError::InvalidExportedCircuit(format!(
+                        "constraint {} out of range",
+                        constraint
+                    )));
+                }
+                Ok(constraint)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            proving_key,
+            num_public,
+            num_secret,
+            matrices,
+            hints,
+            hinted_wires,
+            solve_order,
+        })
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.proving_key
+    }
+
+    /// Proves the circuit for the given public and secret inputs, in the
+    /// order of the gnark circuit.
+    pub fn prove<R: Rng>(
+        &self,
+        public_inputs: &[Fr],
+        secret_inputs: &[Fr],
+        rng: &mut R,
+    ) -> Result<Proof<Bn254>, CircuitsError> {
+        let assignment = self.solve(public_inputs, secret_inputs)?;
+        Ok(
+            Groth16::<Bn254, GnarkReduction>::create_proof_with_reduction_and_matrices(
+                &self.proving_key,
+                Fr::rand(rng),
+                Fr::rand(rng),
+                &self.matrices,
+                self.num_public,
+                self.matrices.num_constraints,
+                &assignment,
+            )?,
+        )
+    }
+
+    /// Computes the values of all wires, failing if a constraint isn't
+    /// satisfied.
+    fn solve(&self, public_inputs: &[Fr], secret_inputs: &[Fr]) -> Result<Vec<Fr>, CircuitsError> {
+        if public_inputs.len() + 1 != self.num_public || secret_inputs.len() != self.num_secret {
+            return Err(CircuitsError::InputsParsingError);
+        }
+        let num_wires = self.num_public + self.matrices.num_witness_variables;
+        let mut values = vec![None; num_wires];
+        values[0] = Some(Fr::from(1u64));
+        for (value, input) in values[1..]
+            .iter_mut()
+            .zip(public_inputs.iter().chain(secret_inputs.iter()))
+        {
+            *value = Some(*input);
+        }
+        for constraint in self.solve_order.iter() {
+            self.solve_constraint(*constraint, &mut values)?;
+        }
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(wire, value)| {
+                value.ok_or_else(|| {
+                    CircuitsError::ArkworksProverError(format!("unsolved wire {}", wire))
+                })
+            })
+            .collect()
+    }
+
+    /// Solves the at most one unknown wire of the constraint `L * R = O`,
+    /// like the gnark solver.
+    fn solve_constraint(
+        &self,
+        constraint: usize,
+        values: &mut [Option<Fr>],
+    ) -> Result<(), CircuitsError> {
+        let rows = [
+            &self.matrices.a[constraint],
+            &self.matrices.b[constraint],
+            &self.matrices.c[constraint],
+        ];
+        let mut sums = [Fr::zero(); 3];
+        let mut unknown = None;
+        for (row, terms) in rows.iter().enumerate() {
+            for (coeff, wire) in terms.iter() {
+                if values[*wire].is_none() {
+                    if let Some(hint) = self.hinted_wires.get(wire) {
+                        self.solve_hint(*hint, values)?;
+                    }
+                }
+                match values[*wire] {
+                    Some(value) => sums[row] += *coeff * value,
+                    None if unknown.is_none() => unknown = Some((row, *coeff, *wire)),
+                    None => {
+                        return Err(CircuitsError::ArkworksProverError(format!(
+                            "constraint {} has more than one unknown wire",
+                            constraint
+                        )))
+                    }
+                }
+            }
+        }
+
+        if let Some((row, coeff, wire)) = unknown {
+            let [a, b, c] = sums;
+            // With a zero factor the wire is undetermined, gnark sets the
+            // term to zero.
+            let term = match row {
+                0 => b.inverse().map(|b_inv| c * b_inv - a),
+                1 => a.inverse().map(|a_inv| c * a_inv - b),
+                _ => Some(a * b - c),
+            }
+            .unwrap_or_default();
+            let coeff_inv = coeff.inverse().ok_or_else(|| {
+                CircuitsError::ArkworksProverError(format!(
+                    "zero coefficient in constraint {}",
+                    constraint
+                ))
+            })?;
+            values[wire] = Some(term * coeff_inv);
+            sums[row] += term;
+        }
+
+        if sums[0] * sums[1] != sums[2] {
+            return Err(CircuitsError::ArkworksProverError(format!(
+                "unsatisfied constraint {}",
+                constraint
+            )));
+        }
+        Ok(())
+    }
+
+    fn solve_hint(&self, hint: usize, values: &mut [Option<Fr>]) -> Result<(), CircuitsError> {
+        let hint = &self.hints[hint];
+        let mut input = Fr::zero();
+        for (coeff, wire) in hint.input.iter() {
+            let value = values[*wire].ok_or_else(|| {
+                CircuitsError::ArkworksProverError(format!("unsolved hint input {}", wire))
+            })?;
+            input += *coeff * value;
+        }
+        let input = input.into_bigint();
+        for (i, wire) in hint.outputs.iter().enumerate() {
+            values[*wire] = Some(Fr::from(input.get_bit(i)));
+        }
+        Ok(())
+    }
+}
+
+/// Public and secret inputs of a circuit, in the order of the wires of the
+/// gnark circuit.
+pub trait GnarkInputs {
+    fn public_inputs(&self) -> Vec<Fr>;
+
+    fn secret_inputs(&self) -> Vec<Fr>;
+}
+
+impl GnarkInputs for InclusionCircuit {
+    /// Roots, leaves.
+    fn public_inputs(&self) -> Vec<Fr> {
+        let roots = self.proofs.iter().map(|p| p.root);
+        let leaves = self.proofs.iter().map(|p| p.leaf);
+        roots.chain(leaves).collect()
+    }
+
+    /// Path indices, path elements.
+    fn secret_inputs(&self) -> Vec<Fr> {
+        let path_indices = self.proofs.iter().map(|p| p.path_index);
+        let path_elements = self.proofs.iter().flat_map(|p| p.path_elements.clone());
+        path_indices.chain(path_elements).collect()
+    }
+}
+
+impl GnarkInputs for NonInclusionCircuit {
+    /// Roots, values.
+    fn public_inputs(&self) -> Vec<Fr> {
+        let roots = self.proofs.iter().map(|p| p.root);
+        let values = self.proofs.iter().map(|p| p.value);
+        roots.chain(values).collect()
+    }
+
+    /// Lower range values, higher range values, next indices, path indices,
+    /// path elements.
+    fn secret_inputs(&self) -> Vec<Fr> {
+        let lower = self.proofs.iter().map(|p| p.leaf_lower_range_value);
+        let higher = self.proofs.iter().map(|p| p.leaf_higher_range_value);
+        let next_indices = self.proofs.iter().map(|p| p.next_index);
+        let path_indices = self.proofs.iter().map(|p| p.path_index);
+        let path_elements = self.proofs.iter().flat_map(|p| p.path_elements.clone());
+        lower
+            .chain(higher)
+            .chain(next_indices)
+            .chain(path_indices)
+            .chain(path_elements)
+            .collect()
+    }
+}
+
+impl GnarkInputs for CombinedCircuit {
+    /// Inclusion inputs, non-inclusion inputs.
+    fn public_inputs(&self) -> Vec<Fr> {
+        [
+            self.inclusion.public_inputs(),
+            self.non_inclusion.public_inputs(),
+        ]
+        .concat()
+    }
+
+    /// Inclusion inputs, non-inclusion inputs.
+    fn secret_inputs(&self) -> Vec<Fr> {
+        [
+            self.inclusion.secret_inputs(),
+            self.non_inclusion.secret_inputs(),
+        ]
+        .concat()
+    }
+}
+
+/// R1CS to QAP reduction of gnark. Unlike [`LibsnarkReduction`], the
+/// evaluation domain only covers the constraints, without extra rows for the
+/// public inputs.
+pub struct GnarkReduction;
+
+impl R1CSToQAP for GnarkReduction {
+    #[allow(clippy::type_complexity)]
+    fn instance_map_with_evaluation<F: PrimeField, D: EvaluationDomain<F>>(
+        cs: ConstraintSystemRef<F>,
+        t: &F,
+    ) -> R1CSResult<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize)> {
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+        let domain =
+            D::new(cs.num_constraints()).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let zt = domain.evaluate_vanishing_polynomial(*t);
+        let u = domain.evaluate_all_lagrange_coefficients(*t);
+
+        let num_variables = cs.num_instance_variables() + cs.num_witness_variables();
+        let mut a = vec![F::zero(); num_variables];
+        let mut b = vec![F::zero(); num_variables];
+        let mut c = vec![F::zero(); num_variables];
+        for (i, u_i) in u.iter().enumerate().take(cs.num_constraints()) {
+            for (coeff, index) in matrices.a[i].iter() {
+                a[*index] += *u_i * coeff;
+            }
+            for (coeff, index) in matrices.b[i].iter() {
+                b[*index] += *u_i * coeff;
+            }
+            for (coeff, index) in matrices.c[i].iter() {
+                c[*index] += *u_i * coeff;
+            }
+        }
+
+        Ok((a, b, c, zt, num_variables - 1, domain.size()))
+    }
+
+    fn witness_map_from_matrices<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        _num_inputs: usize,
+        num_constraints: usize,
+        full_assignment: &[F],
+    ) -> R1CSResult<Vec<F>> {
+        let domain = D::new(num_constraints).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let coset_domain = domain
+            .get_coset(F::GENERATOR)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        // Evaluations of L, R and O on the domain, as coefficients and then
+        // as evaluations on the coset.
+        let evaluations = |rows: &Matrix<F>| {
+            let mut evaluations = vec![F::zero(); domain.size()];
+            for (evaluation, row) in evaluations.iter_mut().zip(rows.iter()) {
+                *evaluation = evaluate_constraint(row, full_assignment);
+            }
+            domain.ifft_in_place(&mut evaluations);
+            coset_domain.fft_in_place(&mut evaluations);
+            evaluations
+        };
+        let a = evaluations(&matrices.a);
+        let b = evaluations(&matrices.b);
+        let c = evaluations(&matrices.c);
+
+        // H = (L * R - O) / Z, where Z is constant on the coset.
+        let vanishing_polynomial_inverse = domain
+            .evaluate_vanishing_polynomial(F::GENERATOR)
+            .inverse()
+            .ok_or(SynthesisError::UnexpectedIdentity)?;
+        let mut h = domain.mul_polynomials_in_evaluation_domain(&a, &b);
+        for (h_i, c_i) in h.iter_mut().zip(c) {
+            *h_i -= c_i;
+            *h_i *= vanishing_polynomial_inverse;
+        }
+        coset_domain.ifft_in_place(&mut h);
+
+        Ok(h)
+    }
+
+    fn h_query_scalars<F: PrimeField, D: EvaluationDomain<F>>(
+        max_power: usize,
+        t: F,
+        zt: F,
+        delta_inverse: F,
+    ) -> Result<Vec<F>, SynthesisError> {
+        LibsnarkReduction::h_query_scalars::<F, D>(max_power, t, zt, delta_inverse)
+    }
+}
@@ -0,0 +1,459 @@
+//! In-process Groth16 prover based on arkworks.
+//!
+//! Proves the same statements as `light-prover` without running a separate
+//! process. [`ArkworksProver::load`] loads circuits exported from the
+//! `light-prover` proving keys (see [`light_prover`]), so the proofs verify
+//! against the verifying keys of `light-verifier`. The exported circuits are
+//! stored as `<circuit>.ark` files (e.g. `inclusion_26_1.ark`) next to the
+//! gnark keys.
+//!
+//! For tests of the R1CS ports in [`circuits`], keys can be generated with
+//! [`ArkworksProver::setup`]. Proofs verify against verifying keys of the
+//! same setup, which can be exported in the `groth16-solana` format with
+//! [`VerifyingKeyBytes`].
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::BufReader,
+    ops::Neg,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_groth16::{
+    prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_serialize::CanonicalSerialize;
+use groth16_solana::groth16::Groth16Verifyingkey;
+use rand::{thread_rng, Rng};
+use solana_program::alt_bn128::compression::prelude::{
+    alt_bn128_g1_compress, alt_bn128_g2_compress, convert_endianness,
+};
+
+use crate::{
+    combined::merkle_combined_proof_inputs::CombinedProofInputs,
+    errors::CircuitsError,
+    inclusion::merkle_inclusion_proof_inputs::InclusionProofInputs,
+    non_inclusion::merkle_non_inclusion_proof_inputs::NonInclusionProofInputs,
+    prove_utils::{ProofCompressed, ProofResult},
+};
+
+use self::{
+    circuits::{CombinedCircuit, InclusionCircuit, NonInclusionCircuit},
+    light_prover::{GnarkInputs, LightProverCircuit},
+};
+
+pub mod circuits;
+pub mod light_prover;
+pub mod poseidon;
+
+/// Directory of the `light-prover` proving keys and the circuits exported
+/// from them.
+pub fn light_prover_keys_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../light-prover/proving-keys")
+}
+
+/// Exports the given circuits from the `light-prover` proving keys to
+/// [`light_prover_keys_dir`], skipping circuits which are already exported.
+/// Builds `light-prover`, which requires Go.
+pub fn export_light_prover_circuits(circuits: &[CircuitId]) -> Result<(), CircuitsError> {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("scripts/export_ark_keys.sh");
+    let status = Command::new(script)
+        .args(circuits.iter().map(|circuit| circuit.to_string()))
+        .status()?;
+    if !status.success() {
+        return Err(CircuitsError::IoError(format!(
+            "exporting circuits failed: {}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Circuit shapes, named like the `light-prover` keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CircuitId {
+    Inclusion {
+        height: usize,
+        num_proofs: usize,
+    },
+    NonInclusion {
+        height: usize,
+        num_proofs: usize,
+    },
+    Combined {
+        height: usize,
+        num_inclusion: usize,
+        num_non_inclusion: usize,
+    },
+}
+
+impl CircuitId {
+    /// Circuits with keys provided by `light-prover`.
+    pub fn light_prover_circuits(height: usize) -> Vec<CircuitId> {
+        let mut circuits = Vec::new();
+        for num_proofs in [1, 2, 3, 4, 8] {
+            circuits.push(CircuitId::Inclusion { height, num_proofs });
+        }
+        for num_proofs in [1, 2] {
+            circuits.push(CircuitId::NonInclusion { height, num_proofs });
+        }
+        for num_inclusion in [1, 2, 3, 4] {
+            for num_non_inclusion in [1, 2] {
+                circuits.push(CircuitId::Combined {
+                    height,
+                    num_inclusion,
+                    num_non_inclusion,
+                });
+            }
+        }
+        circuits
+    }
+
+    /// Circuit with zeroed inputs, used for the setup.
+    fn empty_circuit(&self) -> SetupCircuit {
+        match *self {
+            CircuitId::Inclusion { height, num_proofs } => {
+                SetupCircuit::Inclusion(InclusionCircuit::empty(height, num_proofs))
+            }
+            CircuitId::NonInclusion { height, num_proofs } => {
+                SetupCircuit::NonInclusion(NonInclusionCircuit::empty(height, num_proofs))
+            }
+            CircuitId::Combined {
+                height,
+                num_inclusion,
+                num_non_inclusion,
+            } => SetupCircuit::Combined(CombinedCircuit::empty(
+                height,
+                num_inclusion,
+                num_non_inclusion,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CircuitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitId::Inclusion { height, num_proofs } => {
+                write!(f, "inclusion_{}_{}", height, num_proofs)
+            }
+            CircuitId::NonInclusion { height, num_proofs } => {
+                write!(f, "non-inclusion_{}_{}", height, num_proofs)
+            }
+            CircuitId::Combined {
+                height,
+                num_inclusion,
+                num_non_inclusion,
+            } => write!(
+                f,
+                "combined_{}_{}_{}",
+                height, num_inclusion, num_non_inclusion
+            ),
+        }
+    }
+}
+
+enum SetupCircuit {
+    Inclusion(InclusionCircuit),
+    NonInclusion(NonInclusionCircuit),
+    Combined(CombinedCircuit),
+}
+
+impl ConstraintSynthesizer<Fr> for SetupCircuit {
+    fn generate_constraints(
+        self,
+        cs: ark_relations::r1cs::ConstraintSystemRef<Fr>,
+    ) -> ark_relations::r1cs::Result<()> {
+        match self {
+            SetupCircuit::Inclusion(circuit) => circuit.generate_constraints(cs),
+            SetupCircuit::NonInclusion(circuit) => circuit.generate_constraints(cs),
+            SetupCircuit::Combined(circuit) => circuit.generate_constraints(cs),
+        }
+    }
+}
+
+enum Keys {
+    /// Circuit exported from `light-prover`.
+    LightProver(Box<LightProverCircuit>),
+    /// Keys of a local setup of the R1CS port of the circuit.
+    Setup(Box<ProvingKey<Bn254>>),
+}
+
+impl Keys {
+    fn proving_key(&self) -> &ProvingKey<Bn254> {
+        match self {
+            Keys::LightProver(circuit) => circuit.proving_key(),
+            Keys::Setup(proving_key) => proving_key,
+        }
+    }
+}
+
+struct CircuitKeys {
+    keys: Keys,
+    prepared_verifying_key: PreparedVerifyingKey<Bn254>,
+}
+
+impl CircuitKeys {
+    fn new(keys: Keys) -> Self {
+        let prepared_verifying_key = prepare_verifying_key(&keys.proving_key().vk);
+        Self {
+            keys,
+            prepared_verifying_key,
+        }
+    }
+}
+
+/// Groth16 prover holding proving keys of the supported circuits.
+#[derive(Default)]
+pub struct ArkworksProver {
+    keys: HashMap<CircuitId, CircuitKeys>,
+}
+
+impl fmt::Debug for ArkworksProver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArkworksProver")
+            .field("circuits", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ArkworksProver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the given circuits exported from `light-prover` from
+    /// `keys_dir`.
+    pub fn load<P: AsRef<Path>>(
+        keys_dir: P,
+        circuits: &[CircuitId],
+    ) -> Result<Self, CircuitsError> {
+        let mut prover = Self::new();
+        for circuit in circuits {
+            let path = keys_dir.as_ref().join(format!("{}.ark", circuit));
+            let file = File::open(&path).map_err(|e| {
+                CircuitsError::MissingProvingKey(format!("{}: {}", path.display(), e))
+            })?;
+            let light_prover_circuit = LightProverCircuit::read(BufReader::new(file))?;
+            prover.add_light_prover_circuit(*circuit, light_prover_circuit);
+        }
+        Ok(prover)
+    }
+
+    pub fn add_light_prover_circuit(&mut self, circuit: CircuitId, keys: LightProverCircuit) {
+        self.keys
+            .insert(circuit, CircuitKeys::new(Keys::LightProver(Box::new(keys))));
+    }
+
+    /// Generates new keys for the R1CS port of the given circuit.
+    ///
+    /// The setup is circuit-specific and performed by a single party, so
+    /// the keys are meant for tests and local development only.
+    pub fn setup<R: Rng>(&mut self, circuit: CircuitId, rng: &mut R) -> Result<(), CircuitsError> {
+        let proving_key = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            circuit.empty_circuit(),
+            rng,
+        )?;
+        self.keys
+            .insert(circuit, CircuitKeys::new(Keys::Setup(Box::new(proving_key))));
+        Ok(())
+    }
+
+    pub fn verifying_key(&self, circuit: &CircuitId) -> Option<&VerifyingKey<Bn254>> {
+        self.keys
+            .get(circuit)
+            .map(|keys| &keys.keys.proving_key().vk)
+    }
+
+    pub fn prove_inclusion(
+        &self,
+        inputs: &InclusionProofInputs,
+    ) -> Result<ProofResult, CircuitsError> {
+        let height = height(inputs.0.iter().map(|i| i.path_elements.len()))?;
+        let circuit_id = CircuitId::Inclusion {
+            height,
+            num_proofs: inputs.0.len(),
+        };
+        let circuit = InclusionCircuit::new(height, inputs)?;
+        self.prove(circuit_id, circuit, inputs.public_inputs())
+    }
+
+    pub fn prove_non_inclusion(
+        &self,
+        inputs: &NonInclusionProofInputs,
+    ) -> Result<ProofResult, CircuitsError> {
+        let height = height(
+            inputs
+                .0
+                .iter()
+                .map(|i| i.merkle_proof_hashed_indexed_element_leaf.len()),
+        )?;
+        let circuit_id = CircuitId::NonInclusion {
+            height,
+            num_proofs: inputs.0.len(),
+        };
+        let circuit = NonInclusionCircuit::new(height, inputs)?;
+        self.prove(circuit_id, circuit, inputs.public_inputs())
+    }
+
+    pub fn prove_combined(
+        &self,
+        inputs: &CombinedProofInputs,
+    ) -> Result<ProofResult, CircuitsError> {
+        let height = height(
+            inputs
+                .inclusion_parameters
+                .0
+                .iter()
+                .map(|i| i.path_elements.len())
+                .chain(
+                    inputs
+                        .non_inclusion_parameters
+                        .0
+                        .iter()
+                        .map(|i| i.merkle_proof_hashed_indexed_element_leaf.len()),
+                ),
+        )?;
+        let circuit_id = CircuitId::Combined {
+            height,
+            num_inclusion: inputs.inclusion_parameters.0.len(),
+            num_non_inclusion: inputs.non_inclusion_parameters.0.len(),
+        };
+        let circuit = CombinedCircuit::new(height, inputs)?;
+        self.prove(circuit_id, circuit, inputs.public_inputs())
+    }
+
+    fn prove<C: ConstraintSynthesizer<Fr> + GnarkInputs + Clone>(
+        &self,
+        circuit_id: CircuitId,
+        circuit: C,
+        public_inputs: Vec<[u8; 32]>,
+    ) -> Result<ProofResult, CircuitsError> {
+        let keys = self
+            .keys
+            .get(&circuit_id)
+            .ok_or_else(|| CircuitsError::MissingProvingKey(circuit_id.to_string()))?;
+        let invalid_inputs = || {
+            CircuitsError::ArkworksProverError(format!("invalid inputs for circuit {}", circuit_id))
+        };
+
+        let public_input_elements = circuit.public_inputs();
+        let proof = match &keys.keys {
+            Keys::LightProver(light_prover_circuit) => light_prover_circuit.prove(
+                &public_input_elements,
+                &circuit.secret_inputs(),
+                &mut thread_rng(),
+            )?,
+            Keys::Setup(proving_key) => {
+                // The arkworks prover checks the constraints only with a
+                // debug assertion.
+                let cs = ConstraintSystem::new_ref();
+                circuit.clone().generate_constraints(cs.clone())?;
+                if !cs.is_satisfied()? {
+                    return Err(invalid_inputs());
+                }
+                Groth16::<Bn254>::create_random_proof_with_reduction(
+                    circuit,
+                    proving_key,
+                    &mut thread_rng(),
+                )?
+            }
+        };
+
+        // Verify the proof to not return an invalid one.
+        if !Groth16::<Bn254>::verify_proof(
+            &keys.prepared_verifying_key,
+            &proof,
+            &public_input_elements,
+        )? {
+            return Err(invalid_inputs());
+        }
+
+        Ok(ProofResult {
+            proof: compress_proof(&proof)?,
+            public_inputs,
+        })
+    }
+}
+
+/// Returns the common height of all provided Merkle proofs.
+fn height<I: Iterator<Item = usize>>(mut heights: I) -> Result<usize, CircuitsError> {
+    let height = heights.next().ok_or(CircuitsError::WrongNumberOfUtxos)?;
+    if heights.any(|h| h != height) {
+        return Err(CircuitsError::InputsParsingError);
+    }
+    Ok(height)
+}
+
+/// Serializes a G1 point as big-endian `x || y`.
+fn g1_to_be_bytes(point: &G1Affine) -> Result<[u8; 64], CircuitsError> {
+    let mut bytes = [0u8; 64];
+    point.serialize_uncompressed(&mut bytes[..])?;
+    Ok(convert_endianness::<32, 64>(&bytes))
+}
+
+/// Serializes a G2 point as big-endian `x.c1 || x.c0 || y.c1 || y.c0`.
+fn g2_to_be_bytes(point: &G2Affine) -> Result<[u8; 128], CircuitsError> {
+    let mut bytes = [0u8; 128];
+    point.serialize_uncompressed(&mut bytes[..])?;
+    Ok(convert_endianness::<64, 128>(&bytes))
+}
+
+/// Converts an arkworks proof to the compressed format expected by
+/// `light-verifier`, with the negated `a` point.
+pub fn compress_proof(proof: &Proof<Bn254>) -> Result<ProofCompressed, CircuitsError> {
+    let a = g1_to_be_bytes(&proof.a.neg())?;
+    let b = g2_to_be_bytes(&proof.b)?;
+    let c = g1_to_be_bytes(&proof.c)?;
+    Ok(ProofCompressed {
+        a: alt_bn128_g1_compress(&a).map_err(|_| CircuitsError::ChangeEndiannessError)?,
+        b: alt_bn128_g2_compress(&b).map_err(|_| CircuitsError::ChangeEndiannessError)?,
+        c: alt_bn128_g1_compress(&c).map_err(|_| CircuitsError::ChangeEndiannessError)?,
+    })
+}
+
+/// Verifying key in the big-endian layout of `groth16-solana`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKeyBytes {
+    pub vk_alpha_g1: [u8; 64],
+    pub vk_beta_g2: [u8; 128],
+    pub vk_gamme_g2: [u8; 128],
+    pub vk_delta_g2: [u8; 128],
+    pub vk_ic: Vec<[u8; 64]>,
+}
+
+impl VerifyingKeyBytes {
+    pub fn as_groth16_verifyingkey(&self) -> Groth16Verifyingkey<'_> {
+        Groth16Verifyingkey {
+            nr_pubinputs: self.vk_ic.len() - 1,
+            vk_alpha_g1: self.vk_alpha_g1,
+            vk_beta_g2: self.vk_beta_g2,
+            vk_gamme_g2: self.vk_gamme_g2,
+            vk_delta_g2: self.vk_delta_g2,
+            vk_ic: self.vk_ic.as_slice(),
+        }
+    }
+}
+
+impl TryFrom<&VerifyingKey<Bn254>> for VerifyingKeyBytes {
+    type Error = CircuitsError;
+
+    fn try_from(vk: &VerifyingKey<Bn254>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            vk_alpha_g1: g1_to_be_bytes(&vk.alpha_g1)?,
+            vk_beta_g2: g2_to_be_bytes(&vk.beta_g2)?,
+            vk_gamme_g2: g2_to_be_bytes(&vk.gamma_g2)?,
+            vk_delta_g2: g2_to_be_bytes(&vk.delta_g2)?,
+            vk_ic: vk
+                .gamma_abc_g1
+                .iter()
+                .map(g1_to_be_bytes)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
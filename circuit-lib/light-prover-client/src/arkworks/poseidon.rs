@@ -0,0 +1,65 @@
+use ark_bn254::Fr;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::SynthesisError;
+use light_poseidon::{parameters::bn254_x5::get_poseidon_parameters, PoseidonParameters};
+use once_cell::sync::Lazy;
+
+static POSEIDON_PARAMETERS_X5_3: Lazy<PoseidonParameters<Fr>> =
+    Lazy::new(|| get_poseidon_parameters::<Fr>(3).unwrap());
+static POSEIDON_PARAMETERS_X5_4: Lazy<PoseidonParameters<Fr>> =
+    Lazy::new(|| get_poseidon_parameters::<Fr>(4).unwrap());
+
+/// Poseidon gadget with the circom-compatible bn254 x^5 parameters, producing
+/// the same hashes as `light_hasher::Poseidon` and the `poseidon` package of
+/// `light-prover`.
+///
+/// Supports 2 and 3 inputs, which are used by the Merkle tree and the indexed
+/// Merkle tree leaf circuits.
+pub fn poseidon_hash(inputs: &[FpVar<Fr>]) -> Result<FpVar<Fr>, SynthesisError> {
+    let params = match inputs.len() {
+        2 => &*POSEIDON_PARAMETERS_X5_3,
+        3 => &*POSEIDON_PARAMETERS_X5_4,
+        _ => return Err(SynthesisError::Unsatisfiable),
+    };
+
+    let mut state = Vec::with_capacity(params.width);
+    // Domain tag.
+    state.push(FpVar::Constant(Fr::from(0u64)));
+    state.extend_from_slice(inputs);
+
+    let half_rounds = params.full_rounds / 2;
+    let all_rounds = params.full_rounds + params.partial_rounds;
+    for round in 0..all_rounds {
+        for (i, element) in state.iter_mut().enumerate() {
+            *element += params.ark[round * params.width + i];
+        }
+
+        if round < half_rounds || round >= half_rounds + params.partial_rounds {
+            for element in state.iter_mut() {
+                *element = sbox(element)?;
+            }
+        } else {
+            state[0] = sbox(&state[0])?;
+        }
+
+        state = params
+            .mds
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state.iter())
+                    .fold(FpVar::Constant(Fr::from(0u64)), |acc, (m, element)| {
+                        acc + element * *m
+                    })
+            })
+            .collect();
+    }
+
+    Ok(state.swap_remove(0))
+}
+
+fn sbox(element: &FpVar<Fr>) -> Result<FpVar<Fr>, SynthesisError> {
+    let square = element.square()?;
+    let quad = square.square()?;
+    Ok(quad * element)
+}
@@ -8,3 +8,13 @@ pub struct CombinedProofInputs<'a> {
     pub inclusion_parameters: InclusionProofInputs<'a>,
     pub non_inclusion_parameters: NonInclusionProofInputs<'a>,
 }
+
+impl CombinedProofInputs<'_> {
+    pub fn public_inputs(&self) -> Vec<[u8; 32]> {
+        [
+            self.inclusion_parameters.public_inputs(),
+            self.non_inclusion_parameters.public_inputs(),
+        ]
+        .concat()
+    }
+}
@@ -26,6 +26,21 @@ pub enum CircuitsError {
 
     #[error("Wrong number of UTXO's")]
     WrongNumberOfUtxos,
+
+    #[error("Missing proving key: {0}")]
+    MissingProvingKey(String),
+
+    #[error("IO error: {0}")]
+    IoError(String),
+
+    #[error("Prover server error: {0}")]
+    ProverServerError(String),
+
+    #[error("Root is not in the root history of its tree")]
+    StaleRoot,
+
+    #[error("Invalid exported circuit: {0}")]
+    InvalidExportedCircuit(String),
 }
 
 impl From<SerializationError> for CircuitsError {
@@ -40,6 +55,12 @@ impl From<SynthesisError> for CircuitsError {
     }
 }
 
+impl From<std::io::Error> for CircuitsError {
+    fn from(error: std::io::Error) -> Self {
+        CircuitsError::IoError(error.to_string())
+    }
+}
+
 impl From<Report> for CircuitsError {
     fn from(error: Report) -> Self {
         CircuitsError::GenericError(error.to_string())
@@ -98,7 +98,7 @@ pub fn non_inclusion_merkle_tree_inputs_26() -> NonInclusionMerkleProofInputs {
     non_included_value[31] = 2;
 
     let leaf_lower_range_value = new_low_element.value.to_bytes_be();
-    let next_index = new_element.next_index;
+    let next_index = new_low_element.next_index;
     let leaf_higher_range_value = new_element.value.to_bytes_be();
     let merkle_proof_hashed_indexed_element_leaf = indexed_tree
         .get_proof_of_leaf(new_low_element.index, true)
@@ -1,3 +1,5 @@
+#[cfg(feature = "arkworks")]
+pub mod arkworks;
 pub mod combined;
 pub mod errors;
 #[cfg(feature = "gnark")]
@@ -8,3 +10,4 @@ pub mod inclusion;
 pub mod init_merkle_tree;
 pub mod non_inclusion;
 pub mod prove_utils;
+pub mod prover;
@@ -27,6 +27,19 @@ impl NonInclusionMerkleProofInputs {
 #[derive(Clone, Debug)]
 pub struct NonInclusionProofInputs<'a>(pub &'a [NonInclusionMerkleProofInputs]);
 
+impl NonInclusionProofInputs<'_> {
+    pub fn public_inputs(&self) -> Vec<[u8; 32]> {
+        let mut roots = Vec::new();
+        let mut values = Vec::new();
+        for input in self.0 {
+            let input_arr = input.public_inputs_arr();
+            roots.push(input_arr[0]);
+            values.push(input_arr[1]);
+        }
+        [roots, values].concat()
+    }
+}
+
 // TODO: eliminate use of BigInt in favor of BigUint
 pub fn get_non_inclusion_proof_inputs(
     value: &[u8; 32],
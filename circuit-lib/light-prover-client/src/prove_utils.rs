@@ -2,12 +2,13 @@ use groth16_solana::decompression::{decompress_g1, decompress_g2};
 
 use crate::errors::CircuitsError;
 
+#[derive(Clone, Debug)]
 pub struct ProofResult {
     pub proof: ProofCompressed,
     pub public_inputs: Vec<[u8; 32]>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ProofCompressed {
     pub a: [u8; 32],
     pub b: [u8; 64],
@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use log::warn;
+use reqwest::Client;
+
+use crate::{
+    errors::CircuitsError,
+    gnark::{
+        combined_json_formatter::CombinedJsonStruct,
+        constants::{HEALTH_CHECK, PROVE_PATH, SERVER_ADDRESS},
+        helpers::{spawn_prover, ProofType},
+        inclusion_json_formatter::BatchInclusionJsonStruct,
+        non_inclusion_json_formatter::BatchNonInclusionJsonStruct,
+        proof_helpers::{compress_proof, deserialize_gnark_proof_json, proof_from_json_struct},
+    },
+    prove_utils::{ProofCompressed, ProofResult},
+    prover::{ProofInputs, Prover},
+};
+
+#[derive(Clone, Debug)]
+pub struct GnarkProverConfig {
+    /// Address of the server, without the path.
+    pub url: String,
+    /// Timeout of a single request.
+    pub timeout: Duration,
+    /// Number of attempts after a failed request.
+    pub retries: usize,
+    /// Delay between attempts.
+    pub retry_delay: Duration,
+    /// Proof types of a local server, restarted with `spawn_prover` after a
+    /// failed request. `None` for servers managed by someone else.
+    pub local_proof_types: Option<Vec<ProofType>>,
+}
+
+impl Default for GnarkProverConfig {
+    fn default() -> Self {
+        Self {
+            url: SERVER_ADDRESS.to_string(),
+            timeout: Duration::from_secs(300),
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+            local_proof_types: None,
+        }
+    }
+}
+
+/// Client of the `light-prover` server.
+#[derive(Clone, Debug)]
+pub struct GnarkProver {
+    config: GnarkProverConfig,
+    client: Client,
+}
+
+impl GnarkProver {
+    pub fn new<U: ToString>(url: U) -> Self {
+        Self::with_config(GnarkProverConfig {
+            url: url.to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_config(config: GnarkProverConfig) -> Self {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("Failed to build the HTTP client");
+        Self { config, client }
+    }
+
+    /// Spawns a local server with the given proof types and returns its
+    /// client, which restarts the server after failed requests.
+    pub async fn spawn_local(proof_types: &[ProofType]) -> Self {
+        spawn_prover(true, proof_types).await;
+        Self::with_config(GnarkProverConfig {
+            local_proof_types: Some(proof_types.to_vec()),
+            ..Default::default()
+        })
+    }
+
+    pub fn config(&self) -> &GnarkProverConfig {
+        &self.config
+    }
+
+    pub async fn health_check(&self) -> bool {
+        self.client
+            .get(format!("{}{}", self.config.url, HEALTH_CHECK))
+            .send()
+            .await
+            .map(|response| response.status().is_success())
+            .unwrap_or(false)
+    }
+
+    async fn request_proof(&self, payload: &str) -> Result<ProofCompressed, CircuitsError> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.config.url, PROVE_PATH))
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(payload.to_string())
+            .send()
+            .await
+            .map_err(|e| CircuitsError::ProverServerError(e.to_string()))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| CircuitsError::ProverServerError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(CircuitsError::ProverServerError(format!(
+                "{}: {}",
+                status, body
+            )));
+        }
+
+        let proof_json = deserialize_gnark_proof_json(&body)
+            .map_err(|e| CircuitsError::ProverServerError(e.to_string()))?;
+        let (proof_a, proof_b, proof_c) = proof_from_json_struct(proof_json);
+        let (a, b, c) = compress_proof(&proof_a, &proof_b, &proof_c);
+        Ok(ProofCompressed { a, b, c })
+    }
+}
+
+impl Default for GnarkProver {
+    fn default() -> Self {
+        Self::with_config(GnarkProverConfig::default())
+    }
+}
+
+/// Serializes the inputs to the JSON expected by the `/prove` endpoint.
+pub fn proof_inputs_json(inputs: &ProofInputs<'_>) -> String {
+    match inputs {
+        ProofInputs::Inclusion(inputs) => {
+            BatchInclusionJsonStruct::from_inclusion_proof_inputs(inputs).to_string()
+        }
+        ProofInputs::NonInclusion(inputs) => {
+            BatchNonInclusionJsonStruct::from_non_inclusion_proof_inputs(inputs).to_string()
+        }
+        ProofInputs::Combined(inputs) => {
+            CombinedJsonStruct::from_combined_inputs(inputs).to_string()
+        }
+    }
+}
+
+impl Prover for GnarkProver {
+    async fn prove(&self, inputs: &ProofInputs<'_>) -> Result<ProofResult, CircuitsError> {
+        let payload = proof_inputs_json(inputs);
+
+        let mut attempt = 0;
+        loop {
+            match self.request_proof(&payload).await {
+                Ok(proof) => {
+                    return Ok(ProofResult {
+                        proof,
+                        public_inputs: inputs.public_inputs(),
+                    })
+                }
+                Err(e) if attempt < self.config.retries => {
+                    warn!("Proof request failed: {}", e);
+                    attempt += 1;
+                    tokio::time::sleep(self.config.retry_delay).await;
+                    if let Some(proof_types) = self.config.local_proof_types.as_ref() {
+                        spawn_prover(true, proof_types).await;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
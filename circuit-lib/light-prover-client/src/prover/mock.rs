@@ -0,0 +1,48 @@
+use light_hasher::{Hasher, Keccak};
+
+use crate::{
+    errors::CircuitsError,
+    prove_utils::{ProofCompressed, ProofResult},
+    prover::{ProofInputs, Prover},
+};
+
+/// Value of `b` in mock proofs. Must match `MOCK_PROOF_MARKER` of the test
+/// build of `light-system-program`.
+pub const MOCK_PROOF_MARKER: [u8; 64] =
+    *b"light-protocol-mock-proof-marker-light-protocol-mock-proof-marke";
+
+/// Returns the mock proof of the given public inputs.
+///
+/// `a` is the Keccak hash of the public inputs and `c` is the hash of `a`,
+/// which binds the proof to the inputs without performing any proving.
+pub fn mock_proof(public_inputs: &[[u8; 32]]) -> Result<ProofCompressed, CircuitsError> {
+    let inputs = public_inputs
+        .iter()
+        .map(|input| input.as_slice())
+        .collect::<Vec<_>>();
+    let a = Keccak::hashv(&inputs).map_err(|e| CircuitsError::GenericError(e.to_string()))?;
+    let c = Keccak::hash(&a).map_err(|e| CircuitsError::GenericError(e.to_string()))?;
+    Ok(ProofCompressed {
+        a,
+        b: MOCK_PROOF_MARKER,
+        c,
+    })
+}
+
+/// Prover returning [`mock_proof`]s, accepted only by the test build of
+/// `light-system-program` (`--cfg mock_proofs`), which is deployed by
+/// `setup_mock_proofs_test_programs` of `light-test-utils`.
+///
+/// Doesn't check the validity of the inputs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MockProver;
+
+impl Prover for MockProver {
+    async fn prove(&self, inputs: &ProofInputs<'_>) -> Result<ProofResult, CircuitsError> {
+        let public_inputs = inputs.public_inputs();
+        Ok(ProofResult {
+            proof: mock_proof(&public_inputs)?,
+            public_inputs,
+        })
+    }
+}
@@ -0,0 +1,136 @@
+//! Backends generating proofs for the `light-prover` circuits.
+//!
+//! [`Prover`] is implemented by:
+//!
+//! * [`GnarkProver`] - a client of the `light-prover` HTTP server,
+//! * `ArkworksProver` - the in-process prover (requires the `arkworks`
+//!   feature),
+//! * [`MockProver`] - a deterministic prover, which proofs are accepted only
+//!   by the test build of `light-system-program`.
+//!
+//! [`ProverBackend`] wraps any of them, so it can be stored and injected
+//! without making the user generic over the backend, and `ProofService`
+//! adds queueing and caching on top of a backend.
+
+use std::future::Future;
+
+#[cfg(feature = "arkworks")]
+use std::sync::Arc;
+
+#[cfg(feature = "arkworks")]
+use crate::arkworks::ArkworksProver;
+use crate::{
+    combined::merkle_combined_proof_inputs::CombinedProofInputs,
+    errors::CircuitsError,
+    inclusion::merkle_inclusion_proof_inputs::{InclusionMerkleProofInputs, InclusionProofInputs},
+    non_inclusion::merkle_non_inclusion_proof_inputs::{
+        NonInclusionMerkleProofInputs, NonInclusionProofInputs,
+    },
+    prove_utils::ProofResult,
+};
+
+#[cfg(feature = "gnark")]
+pub mod gnark;
+pub mod mock;
+#[cfg(feature = "proof-service")]
+pub mod service;
+
+#[cfg(feature = "gnark")]
+pub use gnark::{GnarkProver, GnarkProverConfig};
+pub use mock::MockProver;
+#[cfg(feature = "proof-service")]
+pub use service::{ProofRequest, ProofService, ProofServiceConfig};
+
+/// Inputs of one of the circuits.
+#[derive(Clone, Debug)]
+pub enum ProofInputs<'a> {
+    Inclusion(InclusionProofInputs<'a>),
+    NonInclusion(NonInclusionProofInputs<'a>),
+    Combined(CombinedProofInputs<'a>),
+}
+
+impl<'a> ProofInputs<'a> {
+    /// Selects the circuit based on which of the proofs are provided.
+    pub fn new(
+        inclusion: &'a [InclusionMerkleProofInputs],
+        non_inclusion: &'a [NonInclusionMerkleProofInputs],
+    ) -> Result<Self, CircuitsError> {
+        match (inclusion.is_empty(), non_inclusion.is_empty()) {
+            (false, true) => Ok(Self::Inclusion(InclusionProofInputs(inclusion))),
+            (true, false) => Ok(Self::NonInclusion(NonInclusionProofInputs(non_inclusion))),
+            (false, false) => Ok(Self::Combined(CombinedProofInputs {
+                inclusion_parameters: InclusionProofInputs(inclusion),
+                non_inclusion_parameters: NonInclusionProofInputs(non_inclusion),
+            })),
+            (true, true) => Err(CircuitsError::WrongNumberOfUtxos),
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<[u8; 32]> {
+        match self {
+            Self::Inclusion(inputs) => inputs.public_inputs(),
+            Self::NonInclusion(inputs) => inputs.public_inputs(),
+            Self::Combined(inputs) => inputs.public_inputs(),
+        }
+    }
+}
+
+pub trait Prover: Send + Sync {
+    fn prove(
+        &self,
+        inputs: &ProofInputs<'_>,
+    ) -> impl Future<Output = Result<ProofResult, CircuitsError>> + Send;
+}
+
+#[cfg(feature = "arkworks")]
+impl Prover for ArkworksProver {
+    /// Proves on the calling thread, blocking it until the proof is ready.
+    async fn prove(&self, inputs: &ProofInputs<'_>) -> Result<ProofResult, CircuitsError> {
+        match inputs {
+            ProofInputs::Inclusion(inputs) => self.prove_inclusion(inputs),
+            ProofInputs::NonInclusion(inputs) => self.prove_non_inclusion(inputs),
+            ProofInputs::Combined(inputs) => self.prove_combined(inputs),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ProverBackend {
+    #[cfg(feature = "gnark")]
+    Gnark(GnarkProver),
+    #[cfg(feature = "arkworks")]
+    Arkworks(Arc<ArkworksProver>),
+    Mock(MockProver),
+}
+
+impl Prover for ProverBackend {
+    async fn prove(&self, inputs: &ProofInputs<'_>) -> Result<ProofResult, CircuitsError> {
+        match self {
+            #[cfg(feature = "gnark")]
+            Self::Gnark(prover) => prover.prove(inputs).await,
+            #[cfg(feature = "arkworks")]
+            Self::Arkworks(prover) => prover.prove(inputs).await,
+            Self::Mock(prover) => prover.prove(inputs).await,
+        }
+    }
+}
+
+#[cfg(feature = "gnark")]
+impl From<GnarkProver> for ProverBackend {
+    fn from(prover: GnarkProver) -> Self {
+        Self::Gnark(prover)
+    }
+}
+
+#[cfg(feature = "arkworks")]
+impl From<Arc<ArkworksProver>> for ProverBackend {
+    fn from(prover: Arc<ArkworksProver>) -> Self {
+        Self::Arkworks(prover)
+    }
+}
+
+impl From<MockProver> for ProverBackend {
+    fn from(prover: MockProver) -> Self {
+        Self::Mock(prover)
+    }
+}
@@ -0,0 +1,238 @@
+//! Proof service, which wraps a [`Prover`] and:
+//!
+//! * queues requests and runs at most
+//!   [`ProofServiceConfig::max_concurrent_requests`] of them at once,
+//! * caches results of the last [`ProofServiceConfig::cache_capacity`]
+//!   requests, keyed by their public inputs (roots, leaves, address roots
+//!   and addresses),
+//! * cancels requests with a root which is no longer in the root history of
+//!   its tree, reported with [`ProofService::update_root_history`], both
+//!   while they are queued and while they are being proven.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+use futures::future::join_all;
+use solana_program::pubkey::Pubkey;
+use tokio::sync::{watch, Semaphore};
+
+use crate::{
+    errors::CircuitsError,
+    helpers::bigint_to_u8_32,
+    inclusion::merkle_inclusion_proof_inputs::InclusionMerkleProofInputs,
+    non_inclusion::merkle_non_inclusion_proof_inputs::NonInclusionMerkleProofInputs,
+    prove_utils::ProofResult,
+    prover::{ProofInputs, Prover},
+};
+
+#[derive(Clone, Debug)]
+pub struct ProofServiceConfig {
+    pub max_concurrent_requests: usize,
+    pub cache_capacity: usize,
+}
+
+impl Default for ProofServiceConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 4,
+            cache_capacity: 64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProofRequest {
+    pub inclusion: Vec<InclusionMerkleProofInputs>,
+    pub non_inclusion: Vec<NonInclusionMerkleProofInputs>,
+    /// State trees of the inclusion proofs. Requests without trees are never
+    /// cancelled.
+    pub state_trees: Vec<Pubkey>,
+    /// Address trees of the non-inclusion proofs.
+    pub address_trees: Vec<Pubkey>,
+}
+
+impl ProofRequest {
+    pub fn new(
+        inclusion: Vec<InclusionMerkleProofInputs>,
+        non_inclusion: Vec<NonInclusionMerkleProofInputs>,
+    ) -> Self {
+        Self {
+            inclusion,
+            non_inclusion,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_trees(mut self, state_trees: Vec<Pubkey>, address_trees: Vec<Pubkey>) -> Self {
+        self.state_trees = state_trees;
+        self.address_trees = address_trees;
+        self
+    }
+
+    pub fn inputs(&self) -> Result<ProofInputs<'_>, CircuitsError> {
+        ProofInputs::new(&self.inclusion, &self.non_inclusion)
+    }
+
+    /// Roots of the proofs paired with their trees.
+    fn roots(&self) -> Result<Vec<(Pubkey, [u8; 32])>, CircuitsError> {
+        let inclusion_roots = self
+            .state_trees
+            .iter()
+            .zip(self.inclusion.iter().map(|proof| &proof.root));
+        let non_inclusion_roots = self
+            .address_trees
+            .iter()
+            .zip(self.non_inclusion.iter().map(|proof| &proof.root));
+        inclusion_roots
+            .chain(non_inclusion_roots)
+            .map(|(tree, root)| {
+                bigint_to_u8_32(root)
+                    .map(|root| (*tree, root))
+                    .map_err(|_| CircuitsError::InputsParsingError)
+            })
+            .collect()
+    }
+}
+
+/// Number of inclusion proofs and the public inputs. The number is needed to
+/// distinguish combined requests with the same number of inputs.
+type ProofKey = (usize, Vec<[u8; 32]>);
+
+#[derive(Debug)]
+struct ProofCache {
+    capacity: usize,
+    results: HashMap<ProofKey, ProofResult>,
+    /// Keys in the insertion order, the oldest one is evicted first.
+    keys: VecDeque<ProofKey>,
+}
+
+impl ProofCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            results: HashMap::with_capacity(capacity),
+            keys: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&self, key: &ProofKey) -> Option<ProofResult> {
+        self.results.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: ProofKey, result: ProofResult) {
+        if self.capacity == 0 || self.results.contains_key(&key) {
+            return;
+        }
+        if self.keys.len() == self.capacity {
+            if let Some(oldest) = self.keys.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+        self.keys.push_back(key.clone());
+        self.results.insert(key, result);
+    }
+}
+
+#[derive(Debug)]
+pub struct ProofService<P: Prover> {
+    prover: P,
+    permits: Semaphore,
+    cache: Mutex<ProofCache>,
+    root_histories: Mutex<HashMap<Pubkey, HashSet<[u8; 32]>>>,
+    /// Incremented on every root history update.
+    root_history_version: watch::Sender<u64>,
+}
+
+impl<P: Prover> ProofService<P> {
+    pub fn new(prover: P, config: ProofServiceConfig) -> Self {
+        Self {
+            prover,
+            permits: Semaphore::new(config.max_concurrent_requests),
+            cache: Mutex::new(ProofCache::new(config.cache_capacity)),
+            root_histories: Mutex::new(HashMap::new()),
+            root_history_version: watch::Sender::new(0),
+        }
+    }
+
+    pub fn prover(&self) -> &P {
+        &self.prover
+    }
+
+    /// Replaces the known root history of `tree`, usually with the roots
+    /// fetched from its account. Pending requests with roots outside of the
+    /// new history are cancelled.
+    pub fn update_root_history<I: IntoIterator<Item = [u8; 32]>>(&self, tree: Pubkey, roots: I) {
+        self.root_histories
+            .lock()
+            .unwrap()
+            .insert(tree, roots.into_iter().collect());
+        self.root_history_version
+            .send_modify(|version| *version = version.wrapping_add(1));
+    }
+
+    /// Returns a cached proof or generates a new one, failing with
+    /// [`CircuitsError::StaleRoot`] if any of the roots is stale or the
+    /// request is cancelled.
+    pub async fn prove(&self, request: &ProofRequest) -> Result<ProofResult, CircuitsError> {
+        let inputs = request.inputs()?;
+        let roots = request.roots()?;
+        // Cached proofs of stale roots are as useless as new ones.
+        if self.has_stale_root(&roots) {
+            return Err(CircuitsError::StaleRoot);
+        }
+        let key = (request.inclusion.len(), inputs.public_inputs());
+        if let Some(result) = self.cache.lock().unwrap().get(&key) {
+            return Ok(result);
+        }
+
+        let result = tokio::select! {
+            biased;
+            _ = self.wait_for_stale_root(&roots) => return Err(CircuitsError::StaleRoot),
+            result = async {
+                let _permit = self
+                    .permits
+                    .acquire()
+                    .await
+                    .map_err(|e| CircuitsError::GenericError(e.to_string()))?;
+                self.prover.prove(&inputs).await
+            } => result?,
+        };
+
+        self.cache.lock().unwrap().insert(key, result.clone());
+        Ok(result)
+    }
+
+    /// Proves all requests, running them concurrently up to the limit of the
+    /// service. Results are in the order of `requests`.
+    pub async fn prove_batch(
+        &self,
+        requests: &[ProofRequest],
+    ) -> Vec<Result<ProofResult, CircuitsError>> {
+        join_all(requests.iter().map(|request| self.prove(request))).await
+    }
+
+    fn has_stale_root(&self, roots: &[(Pubkey, [u8; 32])]) -> bool {
+        let root_histories = self.root_histories.lock().unwrap();
+        roots.iter().any(|(tree, root)| {
+            root_histories
+                .get(tree)
+                .is_some_and(|history| !history.contains(root))
+        })
+    }
+
+    /// Resolves once any of the `roots` is out of the root history of its
+    /// tree.
+    async fn wait_for_stale_root(&self, roots: &[(Pubkey, [u8; 32])]) {
+        let mut updates = self.root_history_version.subscribe();
+        loop {
+            if self.has_stale_root(roots) {
+                return;
+            }
+            if updates.changed().await.is_err() {
+                return std::future::pending().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,387 @@
+#![cfg(feature = "arkworks")]
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{prepare_verifying_key, Groth16, ProvingKey};
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+use ark_relations::{
+    lc,
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal,
+        SynthesisError, SynthesisMode, Variable,
+    },
+};
+use light_hasher::{Hasher, Poseidon};
+use light_prover_client::{
+    arkworks::{
+        circuits::NonInclusionCircuit,
+        light_prover::{GnarkReduction, LightProverCircuit, MAGIC, VERSION},
+        poseidon::poseidon_hash,
+        ArkworksProver, CircuitId, VerifyingKeyBytes,
+    },
+    combined::merkle_combined_proof_inputs::CombinedProofInputs,
+    errors::CircuitsError,
+    groth16_solana_verifier::groth16_solana_verify,
+    inclusion::{
+        merkle_inclusion_proof_inputs::InclusionProofInputs, merkle_tree_info::MerkleTreeInfo,
+    },
+    init_merkle_tree::{inclusion_merkle_tree_inputs, non_inclusion_merkle_tree_inputs_26},
+    non_inclusion::merkle_non_inclusion_proof_inputs::{
+        NonInclusionMerkleProofInputs, NonInclusionProofInputs,
+    },
+    prove_utils::ProofResult,
+};
+use num_bigint::{BigInt, Sign};
+use rand::thread_rng;
+
+fn verify<const N: usize>(prover: &ArkworksProver, circuit: &CircuitId, result: &ProofResult) {
+    let vk = VerifyingKeyBytes::try_from(prover.verifying_key(circuit).unwrap()).unwrap();
+    let public_inputs: [[u8; 32]; N] = result.public_inputs.clone().try_into().unwrap();
+    assert!(
+        groth16_solana_verify(&result.proof, &public_inputs, vk.as_groth16_verifyingkey()).unwrap()
+    );
+}
+
+/// Tests that the Poseidon gadget matches `light_hasher::Poseidon` for 2 and
+/// 3 inputs and rejects other numbers of inputs.
+#[test]
+fn poseidon_gadget() {
+    for num_inputs in 1..=4 {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let inputs = (0..num_inputs)
+            .map(|i| Fr::from(i as u64 + 1))
+            .collect::<Vec<_>>();
+        let input_vars = inputs
+            .iter()
+            .map(|input| FpVar::new_witness(cs.clone(), || Ok(*input)).unwrap())
+            .collect::<Vec<_>>();
+
+        match num_inputs {
+            2 | 3 => {
+                let hash = poseidon_hash(&input_vars).unwrap().value().unwrap();
+                let input_bytes = inputs
+                    .iter()
+                    .map(|input| input.into_bigint().to_bytes_be())
+                    .collect::<Vec<_>>();
+                let input_slices = input_bytes.iter().map(|b| b.as_slice()).collect::<Vec<_>>();
+                let expected_hash = Poseidon::hashv(&input_slices).unwrap();
+                assert_eq!(hash.into_bigint().to_bytes_be(), expected_hash.to_vec());
+                assert!(cs.is_satisfied().unwrap());
+            }
+            _ => assert!(poseidon_hash(&input_vars).is_err()),
+        }
+    }
+}
+
+/// Tests:
+/// 1. inclusion, non-inclusion and combined proofs verify with
+///    `groth16-solana`
+/// 2. inputs with a wrong root fail
+/// 3. proving without the circuit keys fails
+#[test]
+fn prove_and_verify() {
+    let inclusion_circuit = CircuitId::Inclusion {
+        height: 26,
+        num_proofs: 1,
+    };
+    let non_inclusion_circuit = CircuitId::NonInclusion {
+        height: 26,
+        num_proofs: 1,
+    };
+    let combined_circuit = CircuitId::Combined {
+        height: 26,
+        num_inclusion: 1,
+        num_non_inclusion: 1,
+    };
+
+    let mut rng = thread_rng();
+    let mut prover = ArkworksProver::new();
+    for circuit in [inclusion_circuit, non_inclusion_circuit, combined_circuit] {
+        prover.setup(circuit, &mut rng).unwrap();
+    }
+
+    let inclusion_inputs = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+    let non_inclusion_inputs = [non_inclusion_merkle_tree_inputs_26()];
+    let combined_inputs = CombinedProofInputs {
+        inclusion_parameters: InclusionProofInputs(&inclusion_inputs),
+        non_inclusion_parameters: NonInclusionProofInputs(&non_inclusion_inputs),
+    };
+
+    // 1. prove and verify
+    let result = prover
+        .prove_inclusion(&InclusionProofInputs(&inclusion_inputs))
+        .unwrap();
+    verify::<2>(&prover, &inclusion_circuit, &result);
+    let result = prover
+        .prove_non_inclusion(&NonInclusionProofInputs(&non_inclusion_inputs))
+        .unwrap();
+    verify::<2>(&prover, &non_inclusion_circuit, &result);
+    let result = prover.prove_combined(&combined_inputs).unwrap();
+    verify::<4>(&prover, &combined_circuit, &result);
+
+    // 2. wrong root
+    let mut invalid_inputs = inclusion_inputs.clone();
+    invalid_inputs[0].root += BigInt::from(1);
+    assert!(matches!(
+        prover.prove_inclusion(&InclusionProofInputs(&invalid_inputs)),
+        Err(CircuitsError::ArkworksProverError(_))
+    ));
+    let mut invalid_inputs = non_inclusion_inputs.clone();
+    invalid_inputs[0].root += BigInt::from(1);
+    assert!(matches!(
+        prover.prove_non_inclusion(&NonInclusionProofInputs(&invalid_inputs)),
+        Err(CircuitsError::ArkworksProverError(_))
+    ));
+
+    // 3. missing keys
+    let mut inclusion_prover = ArkworksProver::new();
+    inclusion_prover.setup(inclusion_circuit, &mut rng).unwrap();
+    assert!(matches!(
+        inclusion_prover.prove_non_inclusion(&NonInclusionProofInputs(&non_inclusion_inputs)),
+        Err(CircuitsError::MissingProvingKey(_))
+    ));
+    let keys_dir = std::env::temp_dir().join(format!("light-ark-keys-{}", std::process::id()));
+    assert!(matches!(
+        ArkworksProver::load(&keys_dir, &[inclusion_circuit]),
+        Err(CircuitsError::MissingProvingKey(_))
+    ));
+}
+
+/// Non-inclusion inputs of a tree of height 2 with the single leaf
+/// `H(lower, 0, higher)`.
+fn non_inclusion_inputs(lower: BigInt, value: u64, higher: u64) -> NonInclusionMerkleProofInputs {
+    let to_bytes = |n: &BigInt| {
+        let mut bytes = [0u8; 32];
+        let (_, be_bytes) = n.to_bytes_be();
+        bytes[32 - be_bytes.len()..].copy_from_slice(&be_bytes);
+        bytes
+    };
+    let higher = BigInt::from(higher);
+    let mut hash = Poseidon::hashv(&[&to_bytes(&lower), &[0u8; 32], &to_bytes(&higher)]).unwrap();
+    for _ in 0..2 {
+        hash = Poseidon::hashv(&[&hash, &[0u8; 32]]).unwrap();
+    }
+    NonInclusionMerkleProofInputs {
+        root: BigInt::from_bytes_be(Sign::Plus, &hash),
+        value: BigInt::from(value),
+        leaf_lower_range_value: lower,
+        leaf_higher_range_value: higher,
+        next_index: BigInt::from(0),
+        merkle_proof_hashed_indexed_element_leaf: vec![BigInt::from(0); 2],
+        index_hashed_indexed_element_leaf: BigInt::from(0),
+    }
+}
+
+fn is_satisfied(inputs: NonInclusionMerkleProofInputs) -> bool {
+    let circuit = NonInclusionCircuit::new(2, &NonInclusionProofInputs(&[inputs])).unwrap();
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    cs.is_satisfied().unwrap()
+}
+
+/// Tests:
+/// 1. a value inside the range of the low element satisfies the
+///    non-inclusion circuit
+/// 2. a lower range value wrapping around the field modulus fails the
+///    range check
+#[test]
+fn non_inclusion_range_check() {
+    // 1. value inside the range
+    assert!(is_satisfied(non_inclusion_inputs(BigInt::from(1), 5, 10)));
+
+    // 2. `lower = -1` passes `lower - value + 2^248 < 2^248` without the
+    //    range check of `lower`
+    let modulus = BigInt::from_bytes_be(Sign::Plus, &Fr::MODULUS.to_bytes_be());
+    assert!(!is_satisfied(non_inclusion_inputs(modulus - 1, 5, 10)));
+}
+
+/// Circuit in the shape of a gnark circuit, with public input `x` and secret
+/// input `y`, proving `x = y^2`, `y < 16` and `y != 1`.
+///
+/// Wires: one, x | y | 4 bits of y, inverse of `y - 1`, `y^2`.
+struct SquareCircuit;
+
+impl SquareCircuit {
+    const NUM_BITS: usize = 4;
+}
+
+impl ConstraintSynthesizer<Fr> for SquareCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let one = Variable::One;
+        let x = cs.new_input_variable(|| Ok(Fr::from(0u64)))?;
+        let y = cs.new_witness_variable(|| Ok(Fr::from(0u64)))?;
+        let bits = (0..Self::NUM_BITS)
+            .map(|_| cs.new_witness_variable(|| Ok(Fr::from(0u64))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inverse = cs.new_witness_variable(|| Ok(Fr::from(0u64)))?;
+        let square = cs.new_witness_variable(|| Ok(Fr::from(0u64)))?;
+
+        for bit in bits.iter() {
+            cs.enforce_constraint(lc!() + bit, lc!() + one - bit, lc!())?;
+        }
+        let mut sum = lc!();
+        for (i, bit) in bits.iter().enumerate() {
+            sum += (Fr::from(1u64 << i), *bit);
+        }
+        cs.enforce_constraint(sum, lc!() + one, lc!() + y)?;
+        cs.enforce_constraint(lc!() + inverse, lc!() + y - one, lc!() + one)?;
+        cs.enforce_constraint(lc!() + y, lc!() + y, lc!() + square)?;
+        cs.enforce_constraint(lc!() + square, lc!() + one, lc!() + x)?;
+        Ok(())
+    }
+}
+
+/// Writes the circuit in the format of `light-prover export-ark`, with
+/// the bits of `y` computed by the gnark bits hint.
+fn export_square_circuit(proving_key: &ProvingKey<Bn254>) -> Vec<u8> {
+    fn u32(out: &mut Vec<u8>, value: usize) {
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    fn field<F: PrimeField>(out: &mut Vec<u8>, value: &F) {
+        let bytes = value.into_bigint().to_bytes_be();
+        out.extend_from_slice(&[0u8; 32][bytes.len()..]);
+        out.extend_from_slice(&bytes);
+    }
+    fn g1(out: &mut Vec<u8>, point: &G1Affine) {
+        match point.xy() {
+            Some((x, y)) => {
+                field(out, x);
+                field(out, y);
+            }
+            None => out.extend_from_slice(&[0u8; 64]),
+        }
+    }
+    fn g2(out: &mut Vec<u8>, point: &G2Affine) {
+        match point.xy() {
+            Some((x, y)) => {
+                field(out, &x.c1);
+                field(out, &x.c0);
+                field(out, &y.c1);
+                field(out, &y.c0);
+            }
+            None => out.extend_from_slice(&[0u8; 128]),
+        }
+    }
+    fn g1s(out: &mut Vec<u8>, points: &[G1Affine]) {
+        u32(out, points.len());
+        points.iter().for_each(|point| g1(out, point));
+    }
+    fn linear_expression(out: &mut Vec<u8>, terms: &[(Fr, usize)]) {
+        u32(out, terms.len());
+        for (coeff, wire) in terms {
+            u32(out, *wire);
+            field(out, coeff);
+        }
+    }
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    SquareCircuit.generate_constraints(cs.clone()).unwrap();
+    cs.finalize();
+    let matrices = cs.to_matrices().unwrap();
+    let domain_size = matrices.num_constraints.next_power_of_two();
+
+    let mut out = MAGIC.to_vec();
+    u32(&mut out, VERSION as usize);
+    u32(&mut out, 2);
+    u32(&mut out, 1);
+    u32(&mut out, SquareCircuit::NUM_BITS + 2);
+    u32(&mut out, domain_size);
+    g1(&mut out, &proving_key.vk.alpha_g1);
+    g1(&mut out, &proving_key.beta_g1);
+    g1(&mut out, &proving_key.delta_g1);
+    g2(&mut out, &proving_key.vk.beta_g2);
+    g2(&mut out, &proving_key.vk.gamma_g2);
+    g2(&mut out, &proving_key.vk.delta_g2);
+    g1s(&mut out, &proving_key.a_query);
+    g1s(&mut out, &proving_key.b_g1_query);
+    u32(&mut out, proving_key.b_g2_query.len());
+    proving_key
+        .b_g2_query
+        .iter()
+        .for_each(|point| g2(&mut out, point));
+    // gnark has one more point, for the zero coefficient of degree
+    // `domain_size - 1`.
+    let mut h_query = proving_key.h_query.clone();
+    h_query.resize(domain_size, G1Affine::zero());
+    g1s(&mut out, &h_query);
+    g1s(&mut out, &proving_key.l_query);
+    g1s(&mut out, &proving_key.vk.gamma_abc_g1);
+
+    u32(&mut out, matrices.num_constraints);
+    for i in 0..matrices.num_constraints {
+        linear_expression(&mut out, &matrices.a[i]);
+        linear_expression(&mut out, &matrices.b[i]);
+        linear_expression(&mut out, &matrices.c[i]);
+    }
+
+    let name = "github.com/consensys/gnark/std/math/bits.nBits";
+    u32(&mut out, 1);
+    u32(&mut out, name.len());
+    out.extend_from_slice(name.as_bytes());
+    u32(&mut out, 1);
+    linear_expression(&mut out, &[(Fr::from(1u64), 2)]);
+    u32(&mut out, SquareCircuit::NUM_BITS);
+    (0..SquareCircuit::NUM_BITS).for_each(|i| u32(&mut out, 3 + i));
+
+    u32(&mut out, matrices.num_constraints);
+    (0..matrices.num_constraints).for_each(|i| u32(&mut out, i));
+    out
+}
+
+/// Tests:
+/// 1. wires of an exported circuit are solved from its inputs, including
+///    hinted wires, and the proof verifies
+/// 2. inputs which don't satisfy the constraints fail
+/// 3. invalid exports fail to load
+#[test]
+fn light_prover_circuit() {
+    let mut rng = thread_rng();
+    let proving_key = Groth16::<Bn254, GnarkReduction>::generate_random_parameters_with_reduction(
+        SquareCircuit,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key(&proving_key.vk);
+    let export = export_square_circuit(&proving_key);
+    let circuit = LightProverCircuit::read(&export[..]).unwrap();
+
+    // 1. prove and verify
+    for y in [0u64, 3, 15] {
+        let x = Fr::from(y * y);
+        let proof = circuit.prove(&[x], &[Fr::from(y)], &mut rng).unwrap();
+        assert!(Groth16::<Bn254>::verify_proof(&pvk, &proof, &[x]).unwrap());
+    }
+
+    // 2. wrong square, `y = 1` without inverse, `y` not in 4 bits
+    for (x, y) in [(10u64, 3u64), (1, 1), (256, 16)] {
+        assert!(matches!(
+            circuit.prove(&[Fr::from(x)], &[Fr::from(y)], &mut rng),
+            Err(CircuitsError::ArkworksProverError(_))
+        ));
+    }
+    assert!(matches!(
+        circuit.prove(&[Fr::from(9u64)], &[], &mut rng),
+        Err(CircuitsError::InputsParsingError)
+    ));
+
+    // 3. invalid magic bytes, version and truncated export
+    let mut invalid_export = export.clone();
+    invalid_export[0] ^= 1;
+    assert!(matches!(
+        LightProverCircuit::read(&invalid_export[..]),
+        Err(CircuitsError::InvalidExportedCircuit(_))
+    ));
+    let mut invalid_export = export.clone();
+    invalid_export[11] += 1;
+    assert!(matches!(
+        LightProverCircuit::read(&invalid_export[..]),
+        Err(CircuitsError::InvalidExportedCircuit(_))
+    ));
+    assert!(matches!(
+        LightProverCircuit::read(&export[..export.len() - 1]),
+        Err(CircuitsError::IoError(_))
+    ));
+}
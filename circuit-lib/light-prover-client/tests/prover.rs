@@ -0,0 +1,232 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use light_prover_client::{
+    errors::CircuitsError,
+    helpers::bigint_to_u8_32,
+    inclusion::merkle_tree_info::MerkleTreeInfo,
+    init_merkle_tree::{inclusion_merkle_tree_inputs, non_inclusion_merkle_tree_inputs_26},
+    prove_utils::ProofResult,
+    prover::{
+        mock::mock_proof, GnarkProver, GnarkProverConfig, MockProver, ProofInputs, ProofRequest,
+        ProofService, ProofServiceConfig, Prover, ProverBackend,
+    },
+};
+use num_bigint::BigInt;
+use solana_program::pubkey::Pubkey;
+
+/// Tests:
+/// 1. the circuit is selected based on the provided proofs
+/// 2. public inputs are ordered as roots, leaves, address roots, addresses
+/// 3. empty inputs fail
+#[test]
+fn proof_inputs() {
+    let inclusion = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+    let non_inclusion = [non_inclusion_merkle_tree_inputs_26()];
+
+    let inputs = ProofInputs::new(&inclusion, &[]).unwrap();
+    assert!(matches!(inputs, ProofInputs::Inclusion(_)));
+    assert_eq!(inputs.public_inputs(), inclusion[0].public_inputs_arr());
+
+    let inputs = ProofInputs::new(&[], &non_inclusion).unwrap();
+    assert!(matches!(inputs, ProofInputs::NonInclusion(_)));
+    assert_eq!(inputs.public_inputs(), non_inclusion[0].public_inputs_arr());
+
+    let inputs = ProofInputs::new(&inclusion, &non_inclusion).unwrap();
+    assert!(matches!(inputs, ProofInputs::Combined(_)));
+    assert_eq!(
+        inputs.public_inputs(),
+        [
+            inclusion[0].public_inputs_arr(),
+            non_inclusion[0].public_inputs_arr()
+        ]
+        .concat()
+    );
+
+    assert!(matches!(
+        ProofInputs::new(&[], &[]),
+        Err(CircuitsError::WrongNumberOfUtxos)
+    ));
+}
+
+/// Tests that mock proofs are deterministic, depend on the public inputs and
+/// are returned through `ProverBackend`.
+#[tokio::test]
+async fn mock_prover() {
+    let inclusion = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+    let non_inclusion = [non_inclusion_merkle_tree_inputs_26()];
+    let inclusion_inputs = ProofInputs::new(&inclusion, &[]).unwrap();
+    let non_inclusion_inputs = ProofInputs::new(&[], &non_inclusion).unwrap();
+
+    let result = MockProver.prove(&inclusion_inputs).await.unwrap();
+    assert_eq!(result.public_inputs, inclusion_inputs.public_inputs());
+    assert_eq!(result.proof, mock_proof(&result.public_inputs).unwrap());
+
+    let backend = ProverBackend::from(MockProver);
+    let backend_result = backend.prove(&inclusion_inputs).await.unwrap();
+    assert_eq!(backend_result.proof, result.proof);
+
+    let other_result = backend.prove(&non_inclusion_inputs).await.unwrap();
+    assert_ne!(other_result.proof, result.proof);
+}
+
+/// Tests that requests to an unavailable server fail after all retries.
+#[tokio::test]
+async fn gnark_prover_unavailable() {
+    let prover = GnarkProver::with_config(GnarkProverConfig {
+        url: "http://127.0.0.1:1".to_string(),
+        timeout: Duration::from_secs(1),
+        retries: 2,
+        retry_delay: Duration::from_millis(10),
+        local_proof_types: None,
+    });
+    assert!(!prover.health_check().await);
+
+    let inclusion = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+    let inputs = ProofInputs::new(&inclusion, &[]).unwrap();
+    assert!(matches!(
+        prover.prove(&inputs).await,
+        Err(CircuitsError::ProverServerError(_))
+    ));
+}
+
+/// Mock prover which records the number of calls and concurrently running
+/// requests.
+#[derive(Default)]
+struct CountingProver {
+    calls: AtomicUsize,
+    running: AtomicUsize,
+    max_running: AtomicUsize,
+    delay: Duration,
+}
+
+impl Prover for CountingProver {
+    async fn prove(&self, inputs: &ProofInputs<'_>) -> Result<ProofResult, CircuitsError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        MockProver.prove(inputs).await
+    }
+}
+
+/// Returns an inclusion request with a distinct leaf.
+fn inclusion_request(i: u64) -> ProofRequest {
+    let mut inputs = inclusion_merkle_tree_inputs(MerkleTreeInfo::H26);
+    inputs.leaf += BigInt::from(i);
+    ProofRequest::new(vec![inputs], Vec::new())
+}
+
+/// Tests:
+/// 1. identical requests are proven once
+/// 2. the oldest result is evicted when the cache is full
+#[tokio::test]
+async fn proof_service_cache() {
+    let service = ProofService::new(
+        CountingProver::default(),
+        ProofServiceConfig {
+            max_concurrent_requests: 1,
+            cache_capacity: 1,
+        },
+    );
+
+    // 1. identical requests
+    let result = service.prove(&inclusion_request(0)).await.unwrap();
+    let cached_result = service.prove(&inclusion_request(0)).await.unwrap();
+    assert_eq!(result.proof, cached_result.proof);
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 1);
+
+    // 2. eviction
+    service.prove(&inclusion_request(1)).await.unwrap();
+    service.prove(&inclusion_request(0)).await.unwrap();
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 3);
+}
+
+/// Tests that batches run concurrently up to the limit and results are in
+/// the order of requests.
+#[tokio::test]
+async fn proof_service_concurrency_limit() {
+    let service = ProofService::new(
+        CountingProver {
+            delay: Duration::from_millis(20),
+            ..Default::default()
+        },
+        ProofServiceConfig {
+            max_concurrent_requests: 2,
+            cache_capacity: 0,
+        },
+    );
+    let requests = (0..6).map(inclusion_request).collect::<Vec<_>>();
+
+    let results = service.prove_batch(&requests).await;
+    for (request, result) in requests.iter().zip(results) {
+        assert_eq!(
+            result.unwrap().public_inputs,
+            request.inputs().unwrap().public_inputs()
+        );
+    }
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 6);
+    assert_eq!(service.prover().max_running.load(Ordering::SeqCst), 2);
+}
+
+/// Tests:
+/// 1. a request with a root out of the root history fails
+/// 2. a queued request is cancelled once its root leaves the root history
+/// 3. requests without trees are not cancelled
+/// 4. a cached proof isn't returned once its root leaves the root history
+#[tokio::test]
+async fn proof_service_stale_roots() {
+    let service = ProofService::new(
+        CountingProver {
+            delay: Duration::from_millis(50),
+            ..Default::default()
+        },
+        ProofServiceConfig {
+            max_concurrent_requests: 1,
+            cache_capacity: 0,
+        },
+    );
+    let tree = Pubkey::new_unique();
+    let request = inclusion_request(0).with_trees(vec![tree], Vec::new());
+    let root = bigint_to_u8_32(&request.inclusion[0].root).unwrap();
+
+    // 1. stale root
+    service.update_root_history(tree, [[1u8; 32]]);
+    assert!(matches!(
+        service.prove(&request).await,
+        Err(CircuitsError::StaleRoot)
+    ));
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 0);
+
+    // 2. cancelled while queued behind another request
+    service.update_root_history(tree, [root, [1u8; 32]]);
+    let blocking_request = inclusion_request(1);
+    let (blocking_result, queued_result, _) = tokio::join!(
+        service.prove(&blocking_request),
+        service.prove(&request),
+        async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            service.update_root_history(tree, [[1u8; 32], [2u8; 32]]);
+        }
+    );
+    blocking_result.unwrap();
+    assert!(matches!(queued_result, Err(CircuitsError::StaleRoot)));
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 1);
+
+    // 3. no trees
+    service.prove(&inclusion_request(0)).await.unwrap();
+
+    // 4. cached proof of a stale root
+    let service = ProofService::new(CountingProver::default(), ProofServiceConfig::default());
+    service.update_root_history(tree, [root]);
+    service.prove(&request).await.unwrap();
+    service.update_root_history(tree, [[1u8; 32]]);
+    assert!(matches!(
+        service.prove(&request).await,
+        Err(CircuitsError::StaleRoot)
+    ));
+    assert_eq!(service.prover().calls.load(Ordering::SeqCst), 1);
+}
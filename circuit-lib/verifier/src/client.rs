@@ -0,0 +1,152 @@
+//! Client-side verification of proofs, so wallets and relayers can reject
+//! invalid proofs before sending a transaction.
+//!
+//! [`verify_proof`] takes the same inputs as the system program and selects
+//! the verifying key the same way as [`verify_merkle_proof_zkp`],
+//! [`verify_create_addresses_zkp`] and
+//! [`verify_create_addresses_and_merkle_proof_zkp`].
+//!
+//! [`verify_merkle_proof_zkp`]: crate::verify_merkle_proof_zkp
+//! [`verify_create_addresses_zkp`]: crate::verify_create_addresses_zkp
+//! [`verify_create_addresses_and_merkle_proof_zkp`]: crate::verify_create_addresses_and_merkle_proof_zkp
+
+use std::fmt;
+
+use groth16_solana::groth16::Groth16Verifyingkey;
+
+use crate::{verify_zkp, verifying_keys::*, CompressedProof, VerifierError};
+
+/// Height of the trees supported by the verifying keys.
+pub const TREE_HEIGHT: usize = 26;
+
+/// Circuit of a verifying key, displayed as the name of its module in
+/// [`verifying_keys`](crate::verifying_keys).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VerifyingKeyId {
+    Inclusion {
+        num_leaves: usize,
+    },
+    NonInclusion {
+        num_addresses: usize,
+    },
+    Combined {
+        num_leaves: usize,
+        num_addresses: usize,
+    },
+}
+
+impl VerifyingKeyId {
+    /// Selects the key for the given numbers of leaves and addresses. As in
+    /// the system program, the combined circuit is used when both are
+    /// non-zero.
+    pub fn select(num_leaves: usize, num_addresses: usize) -> Result<Self, VerifierError> {
+        let id = match (num_leaves, num_addresses) {
+            (0, 0) => return Err(VerifierError::InvalidPublicInputsLength),
+            (num_leaves, 0) => Self::Inclusion { num_leaves },
+            (0, num_addresses) => Self::NonInclusion { num_addresses },
+            (num_leaves, num_addresses) => Self::Combined {
+                num_leaves,
+                num_addresses,
+            },
+        };
+        // Fail for shapes without a key.
+        id.verifying_key()?;
+        Ok(id)
+    }
+
+    pub fn verifying_key(&self) -> Result<&'static Groth16Verifyingkey<'static>, VerifierError> {
+        let verifying_key = match *self {
+            Self::Inclusion { num_leaves: 1 } => &inclusion_26_1::VERIFYINGKEY,
+            Self::Inclusion { num_leaves: 2 } => &inclusion_26_2::VERIFYINGKEY,
+            Self::Inclusion { num_leaves: 3 } => &inclusion_26_3::VERIFYINGKEY,
+            Self::Inclusion { num_leaves: 4 } => &inclusion_26_4::VERIFYINGKEY,
+            Self::Inclusion { num_leaves: 8 } => &inclusion_26_8::VERIFYINGKEY,
+            Self::NonInclusion { num_addresses: 1 } => &non_inclusion_26_1::VERIFYINGKEY,
+            Self::NonInclusion { num_addresses: 2 } => &non_inclusion_26_2::VERIFYINGKEY,
+            Self::Combined {
+                num_leaves,
+                num_addresses,
+            } => match (num_leaves, num_addresses) {
+                (1, 1) => &combined_26_1_1::VERIFYINGKEY,
+                (1, 2) => &combined_26_1_2::VERIFYINGKEY,
+                (2, 1) => &combined_26_2_1::VERIFYINGKEY,
+                (2, 2) => &combined_26_2_2::VERIFYINGKEY,
+                (3, 1) => &combined_26_3_1::VERIFYINGKEY,
+                (3, 2) => &combined_26_3_2::VERIFYINGKEY,
+                (4, 1) => &combined_26_4_1::VERIFYINGKEY,
+                (4, 2) => &combined_26_4_2::VERIFYINGKEY,
+                _ => return Err(VerifierError::InvalidPublicInputsLength),
+            },
+            _ => return Err(VerifierError::InvalidPublicInputsLength),
+        };
+        Ok(verifying_key)
+    }
+}
+
+impl fmt::Display for VerifyingKeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inclusion { num_leaves } => write!(f, "inclusion_{}_{}", TREE_HEIGHT, num_leaves),
+            Self::NonInclusion { num_addresses } => {
+                write!(f, "non_inclusion_{}_{}", TREE_HEIGHT, num_addresses)
+            }
+            Self::Combined {
+                num_leaves,
+                num_addresses,
+            } => write!(
+                f,
+                "combined_{}_{}_{}",
+                TREE_HEIGHT, num_leaves, num_addresses
+            ),
+        }
+    }
+}
+
+/// Verifying key and public inputs of a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofVerification {
+    pub verifying_key: VerifyingKeyId,
+    /// Roots, leaves, address roots and addresses, in this order.
+    pub public_inputs: Vec<[u8; 32]>,
+}
+
+impl ProofVerification {
+    /// Selects the verifying key and lays out the public inputs, failing if
+    /// there are no keys for the given numbers of inputs.
+    pub fn new(
+        roots: &[[u8; 32]],
+        leaves: &[[u8; 32]],
+        address_roots: &[[u8; 32]],
+        addresses: &[[u8; 32]],
+    ) -> Result<Self, VerifierError> {
+        if roots.len() != leaves.len() || address_roots.len() != addresses.len() {
+            return Err(VerifierError::InvalidPublicInputsLength);
+        }
+        Ok(Self {
+            verifying_key: VerifyingKeyId::select(leaves.len(), addresses.len())?,
+            public_inputs: [roots, leaves, address_roots, addresses].concat(),
+        })
+    }
+
+    pub fn verify(&self, proof: &CompressedProof) -> Result<(), VerifierError> {
+        verify_zkp(
+            &self.public_inputs,
+            proof,
+            self.verifying_key.verifying_key()?,
+        )
+    }
+}
+
+/// Verifies the proof of a system program invocation, returning the
+/// selected verifying key.
+pub fn verify_proof(
+    roots: &[[u8; 32]],
+    leaves: &[[u8; 32]],
+    address_roots: &[[u8; 32]],
+    addresses: &[[u8; 32]],
+    proof: &CompressedProof,
+) -> Result<VerifyingKeyId, VerifierError> {
+    let verification = ProofVerification::new(roots, leaves, address_roots, addresses)?;
+    verification.verify(proof)?;
+    Ok(verification.verifying_key)
+}
@@ -3,6 +3,7 @@ use groth16_solana::decompression::{decompress_g1, decompress_g2};
 use groth16_solana::groth16::{Groth16Verifier, Groth16Verifyingkey};
 
 use thiserror::Error;
+pub mod client;
 pub mod verifying_keys;
 #[derive(Debug, Error)]
 pub enum VerifierError {
@@ -218,6 +219,38 @@ pub fn verify_merkle_proof_zkp(
     }
 }
 
+/// Verifies a proof with the given verifying key, e.g. one loaded from the
+/// verifying key registry.
+///
+/// Supports up to 16 public inputs.
+#[inline(never)]
+pub fn verify_zkp(
+    public_inputs: &[[u8; 32]],
+    compressed_proof: &CompressedProof,
+    verifying_key: &Groth16Verifyingkey,
+) -> Result<(), VerifierError> {
+    if public_inputs.len() + 1 != verifying_key.vk_ic.len() {
+        return Err(InvalidPublicInputsLength);
+    }
+    match public_inputs.len() {
+        2 => verify::<2>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        4 => verify::<4>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        6 => verify::<6>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        8 => verify::<8>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        10 => verify::<10>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        12 => verify::<12>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        14 => verify::<14>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        16 => verify::<16>(&to_array(public_inputs)?, compressed_proof, verifying_key),
+        _ => Err(InvalidPublicInputsLength),
+    }
+}
+
+fn to_array<const N: usize>(public_inputs: &[[u8; 32]]) -> Result<[[u8; 32]; N], VerifierError> {
+    public_inputs
+        .try_into()
+        .map_err(|_| PublicInputsTryIntoFailed)
+}
+
 #[inline(never)]
 fn verify<const N: usize>(
     public_inputs: &[[u8; 32]; N],
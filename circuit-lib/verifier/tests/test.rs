@@ -52,4 +52,258 @@ mod test {
         }
         kill_prover();
     }
+
+    /// Tests that proofs of the arkworks prover with the circuits exported
+    /// from the `light-prover` keys verify against the verifying keys of
+    /// `light-verifier`.
+    #[test]
+    fn prove_arkworks() {
+        use light_prover_client::{
+            arkworks::{
+                export_light_prover_circuits, light_prover_keys_dir, ArkworksProver, CircuitId,
+                VerifyingKeyBytes,
+            },
+            combined::merkle_combined_proof_inputs::CombinedProofInputs,
+            inclusion::{
+                merkle_inclusion_proof_inputs::InclusionProofInputs,
+                merkle_tree_info::MerkleTreeInfo,
+            },
+            init_merkle_tree::{inclusion_merkle_tree_inputs, non_inclusion_merkle_tree_inputs_26},
+            non_inclusion::merkle_non_inclusion_proof_inputs::NonInclusionProofInputs,
+        };
+        use light_verifier::{
+            verify_create_addresses_and_merkle_proof_zkp, verify_create_addresses_zkp,
+            verifying_keys::{combined_26_1_1, inclusion_26_1, non_inclusion_26_1},
+        };
+
+        let inclusion_circuit = CircuitId::Inclusion {
+            height: 26,
+            num_proofs: 1,
+        };
+        let non_inclusion_circuit = CircuitId::NonInclusion {
+            height: 26,
+            num_proofs: 1,
+        };
+        let combined_circuit = CircuitId::Combined {
+            height: 26,
+            num_inclusion: 1,
+            num_non_inclusion: 1,
+        };
+        let circuits = [inclusion_circuit, non_inclusion_circuit, combined_circuit];
+        export_light_prover_circuits(&circuits).unwrap();
+        let prover = ArkworksProver::load(light_prover_keys_dir(), &circuits).unwrap();
+
+        for (circuit, vk) in [
+            (inclusion_circuit, inclusion_26_1::VERIFYINGKEY),
+            (non_inclusion_circuit, non_inclusion_26_1::VERIFYINGKEY),
+            (combined_circuit, combined_26_1_1::VERIFYINGKEY),
+        ] {
+            let exported_vk =
+                VerifyingKeyBytes::try_from(prover.verifying_key(&circuit).unwrap()).unwrap();
+            assert_eq!(exported_vk.vk_alpha_g1, vk.vk_alpha_g1);
+            assert_eq!(exported_vk.vk_delta_g2, vk.vk_delta_g2);
+            assert_eq!(exported_vk.vk_ic, vk.vk_ic);
+        }
+
+        let inclusion = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+        let non_inclusion = [non_inclusion_merkle_tree_inputs_26()];
+        let [root, leaf] = inclusion[0].public_inputs_arr();
+        let [address_root, address] = non_inclusion[0].public_inputs_arr();
+        let compressed_proof =
+            |proof: light_prover_client::prove_utils::ProofCompressed| CompressedProof {
+                a: proof.a,
+                b: proof.b,
+                c: proof.c,
+            };
+
+        let result = prover
+            .prove_inclusion(&InclusionProofInputs(&inclusion))
+            .unwrap();
+        verify_merkle_proof_zkp(&[root], &[leaf], &compressed_proof(result.proof)).unwrap();
+
+        let result = prover
+            .prove_non_inclusion(&NonInclusionProofInputs(&non_inclusion))
+            .unwrap();
+        verify_create_addresses_zkp(&[address_root], &[address], &compressed_proof(result.proof))
+            .unwrap();
+
+        let result = prover
+            .prove_combined(&CombinedProofInputs {
+                inclusion_parameters: InclusionProofInputs(&inclusion),
+                non_inclusion_parameters: NonInclusionProofInputs(&non_inclusion),
+            })
+            .unwrap();
+        verify_create_addresses_and_merkle_proof_zkp(
+            &[root],
+            &[leaf],
+            &[address_root],
+            &[address],
+            &compressed_proof(result.proof),
+        )
+        .unwrap();
+    }
+
+    /// Tests that mock proofs, which only the test build of the system program
+    /// accepts, are rejected by the verifier.
+    #[tokio::test]
+    async fn reject_mock_proof() {
+        use light_prover_client::{
+            inclusion::{
+                merkle_inclusion_proof_inputs::InclusionProofInputs,
+                merkle_tree_info::MerkleTreeInfo,
+            },
+            init_merkle_tree::inclusion_merkle_tree_inputs,
+            prover::{MockProver, ProofInputs, Prover},
+        };
+
+        let inputs = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+        let result = MockProver
+            .prove(&ProofInputs::Inclusion(InclusionProofInputs(&inputs)))
+            .await
+            .unwrap();
+        let proof = CompressedProof {
+            a: result.proof.a,
+            b: result.proof.b,
+            c: result.proof.c,
+        };
+        let [root, leaf] = inputs[0].public_inputs_arr();
+        assert!(verify_merkle_proof_zkp(&[root], &[leaf], &proof).is_err());
+    }
+
+    /// Tests that keys are selected as in the on-chain dispatch and shapes
+    /// without keys fail.
+    #[test]
+    fn select_verifying_key() {
+        use light_verifier::{
+            client::{ProofVerification, VerifyingKeyId},
+            VerifierError,
+        };
+
+        for num_leaves in [1, 2, 3, 4, 8] {
+            let id = VerifyingKeyId::select(num_leaves, 0).unwrap();
+            assert_eq!(id.to_string(), format!("inclusion_26_{}", num_leaves));
+        }
+        for num_addresses in [1, 2] {
+            let id = VerifyingKeyId::select(0, num_addresses).unwrap();
+            assert_eq!(
+                id.to_string(),
+                format!("non_inclusion_26_{}", num_addresses)
+            );
+            for num_leaves in 1..=4 {
+                let id = VerifyingKeyId::select(num_leaves, num_addresses).unwrap();
+                assert_eq!(
+                    id.to_string(),
+                    format!("combined_26_{}_{}", num_leaves, num_addresses)
+                );
+            }
+        }
+        for (num_leaves, num_addresses) in [(0, 0), (5, 0), (16, 0), (0, 3), (8, 1), (1, 3)] {
+            assert!(matches!(
+                VerifyingKeyId::select(num_leaves, num_addresses),
+                Err(VerifierError::InvalidPublicInputsLength)
+            ));
+        }
+
+        assert!(matches!(
+            ProofVerification::new(&[[1; 32]; 2], &[[2; 32]], &[], &[]),
+            Err(VerifierError::InvalidPublicInputsLength)
+        ));
+        assert!(matches!(
+            ProofVerification::new(&[[1; 32]], &[[2; 32]], &[[3; 32]], &[]),
+            Err(VerifierError::InvalidPublicInputsLength)
+        ));
+        let verification =
+            ProofVerification::new(&[[1; 32]], &[[2; 32]], &[[3; 32]], &[[4; 32]]).unwrap();
+        assert_eq!(
+            verification.public_inputs,
+            vec![[1; 32], [2; 32], [3; 32], [4; 32]]
+        );
+        assert!(verification.verify(&CompressedProof::default()).is_err());
+    }
+
+    /// Tests that the client-side verification selects the key of every circuit
+    /// and rejects mock proofs.
+    #[tokio::test]
+    async fn verify_proof_client() {
+        use light_prover_client::{
+            inclusion::merkle_tree_info::MerkleTreeInfo,
+            init_merkle_tree::{inclusion_merkle_tree_inputs, non_inclusion_merkle_tree_inputs_26},
+            prover::{MockProver, ProofInputs, Prover},
+        };
+        use light_verifier::client::{verify_proof, ProofVerification, VerifyingKeyId};
+
+        let inclusion = [inclusion_merkle_tree_inputs(MerkleTreeInfo::H26)];
+        let non_inclusion = [non_inclusion_merkle_tree_inputs_26()];
+        let [root, leaf] = inclusion[0].public_inputs_arr();
+        let [address_root, address] = non_inclusion[0].public_inputs_arr();
+
+        for (inclusion, non_inclusion, expected_key) in [
+            (
+                &inclusion[..],
+                &[][..],
+                VerifyingKeyId::Inclusion { num_leaves: 1 },
+            ),
+            (
+                &[][..],
+                &non_inclusion[..],
+                VerifyingKeyId::NonInclusion { num_addresses: 1 },
+            ),
+            (
+                &inclusion[..],
+                &non_inclusion[..],
+                VerifyingKeyId::Combined {
+                    num_leaves: 1,
+                    num_addresses: 1,
+                },
+            ),
+        ] {
+            let inputs = ProofInputs::new(inclusion, non_inclusion).unwrap();
+            let result = MockProver.prove(&inputs).await.unwrap();
+            let proof = CompressedProof {
+                a: result.proof.a,
+                b: result.proof.b,
+                c: result.proof.c,
+            };
+            let (roots, leaves) = if inclusion.is_empty() {
+                (vec![], vec![])
+            } else {
+                (vec![root], vec![leaf])
+            };
+            let (address_roots, addresses) = if non_inclusion.is_empty() {
+                (vec![], vec![])
+            } else {
+                (vec![address_root], vec![address])
+            };
+
+            let verification =
+                ProofVerification::new(&roots, &leaves, &address_roots, &addresses).unwrap();
+            assert_eq!(verification.verifying_key, expected_key);
+            assert_eq!(verification.public_inputs, result.public_inputs);
+            assert!(verify_proof(&roots, &leaves, &address_roots, &addresses, &proof).is_err());
+        }
+    }
+
+    /// Tests that `verify_zkp` rejects public inputs which don't match the
+    /// verifying key.
+    #[test]
+    fn verify_zkp_public_inputs_length() {
+        use light_verifier::{verify_zkp, verifying_keys::inclusion_26_1, VerifierError};
+
+        for public_inputs in [vec![], vec![[1u8; 32]], vec![[1u8; 32]; 4]] {
+            assert!(matches!(
+                verify_zkp(
+                    &public_inputs,
+                    &CompressedProof::default(),
+                    &inclusion_26_1::VERIFYINGKEY
+                ),
+                Err(VerifierError::InvalidPublicInputsLength)
+            ));
+        }
+        assert!(verify_zkp(
+            &[[1u8; 32]; 2],
+            &CompressedProof::default(),
+            &inclusion_26_1::VERIFYINGKEY
+        )
+        .is_err());
+    }
 }
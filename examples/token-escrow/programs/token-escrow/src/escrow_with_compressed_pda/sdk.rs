@@ -12,6 +12,7 @@ use light_system_program::{
     sdk::{
         address::{add_and_get_remaining_account_indices, pack_new_address_params},
         compressed_account::{pack_merkle_context, MerkleContext},
+        invoke::get_verifying_key_account_meta,
         CompressedCpiContext,
     },
     NewAddressParams,
@@ -115,7 +116,11 @@ pub fn create_escrow_instruction(
         unwrapped_token_account: solana_sdk::system_program::id(),
         wrapped_mint_backpointer: solana_sdk::system_program::id(),
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    remaining_accounts.extend(get_verifying_key_account_meta(
+        input_params.input_token_data.len(),
+        1,
+    ));
 
     Instruction {
         program_id: crate::ID,
@@ -243,7 +248,12 @@ pub fn create_withdrawal_instruction(
         unwrapped_token_account: solana_sdk::system_program::id(),
         wrapped_mint_backpointer: solana_sdk::system_program::id(),
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    // The compressed pda is proven with the escrowed token accounts.
+    remaining_accounts.extend(get_verifying_key_account_meta(
+        input_params.input_token_data.len() + 1,
+        0,
+    ));
 
     Instruction {
         program_id: crate::ID,
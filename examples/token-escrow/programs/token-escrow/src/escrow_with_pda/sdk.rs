@@ -13,7 +13,10 @@ use light_compressed_token::{
 };
 use light_system_program::{
     invoke::processor::CompressedProof,
-    sdk::{address::add_and_get_remaining_account_indices, compressed_account::MerkleContext},
+    sdk::{
+        address::add_and_get_remaining_account_indices, compressed_account::MerkleContext,
+        invoke::get_verifying_key_account_meta,
+    },
 };
 use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 
@@ -94,7 +97,11 @@ pub fn create_escrow_instruction(
         system_program: solana_sdk::system_program::ID,
         token_owner_pda: token_owner_pda.0,
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    remaining_accounts.extend(get_verifying_key_account_meta(
+        input_params.input_token_data.len(),
+        0,
+    ));
 
     Instruction {
         program_id: crate::ID,
@@ -162,7 +169,11 @@ pub fn create_withdrawal_escrow_instruction(
         timelock_pda,
         system_program: solana_sdk::system_program::ID,
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    remaining_accounts.extend(get_verifying_key_account_meta(
+        input_params.input_token_data.len(),
+        0,
+    ));
 
     Instruction {
         program_id: crate::ID,
@@ -238,7 +238,8 @@ pub async fn perform_escrow<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
 
     let create_ix_inputs = CreateEscrowInstructionInputs {
         input_token_data: &[input_compressed_token_account_data.token_data],
@@ -292,7 +293,9 @@ pub async fn perform_escrow_with_event<R: RpcConnection>(
         )
         .await?
         .unwrap();
-    test_indexer.add_compressed_accounts_with_token_data(&event.0);
+    test_indexer
+        .add_compressed_accounts_with_token_data(rpc, &event.0)
+        .await;
     Ok(())
 }
 
@@ -391,7 +394,8 @@ pub async fn perform_withdrawal<R: RpcConnection>(
             None,
             context,
         )
-        .await;
+        .await
+        .unwrap();
 
     let create_ix_inputs = CreateEscrowInstructionInputs {
         input_token_data: &[escrow_token_data_with_context.token_data],
@@ -443,7 +447,9 @@ pub async fn perform_withdrawal_with_event<R: RpcConnection>(
         )
         .await?
         .unwrap();
-    test_indexer.add_compressed_accounts_with_token_data(&event.0);
+    test_indexer
+        .add_compressed_accounts_with_token_data(rpc, &event.0)
+        .await;
     Ok(())
 }
 
@@ -198,7 +198,9 @@ pub async fn perform_escrow_with_event<R: RpcConnection>(
             }),
         )
         .await?;
-    test_indexer.add_compressed_accounts_with_token_data(&event.unwrap().0);
+    test_indexer
+        .add_compressed_accounts_with_token_data(rpc, &event.unwrap().0)
+        .await;
     Ok(())
 }
 
@@ -239,7 +241,8 @@ async fn create_escrow_ix<R: RpcConnection>(
             Some(vec![env.address_merkle_tree_pubkey]),
             context,
         )
-        .await;
+        .await
+        .unwrap();
 
     let new_address_params = NewAddressParams {
         seed,
@@ -363,7 +366,9 @@ pub async fn perform_withdrawal_with_event<R: RpcConnection>(
             None,
         )
         .await?;
-    test_indexer.add_compressed_accounts_with_token_data(&event.unwrap().0);
+    test_indexer
+        .add_compressed_accounts_with_token_data(rpc, &event.unwrap().0)
+        .await;
     Ok(())
 }
 
@@ -451,7 +456,8 @@ pub async fn perform_withdrawal<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
 
     let create_withdrawal_ix_inputs = CreateCompressedPdaWithdrawalInstructionInputs {
         input_token_data: &[token_escrow.token_data],
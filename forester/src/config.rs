@@ -1,4 +1,5 @@
 use crate::external_services_config::ExternalServicesConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 
@@ -16,6 +17,9 @@ pub struct ForesterConfig {
     pub batch_size: usize,
     pub max_retries: usize,
     pub rpc_pool_size: usize,
+    /// Commitment at which queue updates are acted on and transactions are
+    /// confirmed.
+    pub commitment: CommitmentConfig,
 }
 
 impl Clone for ForesterConfig {
@@ -33,6 +37,7 @@ impl Clone for ForesterConfig {
             batch_size: self.batch_size,
             max_retries: self.max_retries,
             rpc_pool_size: self.rpc_pool_size,
+            commitment: self.commitment,
         }
     }
 }
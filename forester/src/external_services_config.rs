@@ -1,8 +1,73 @@
+use std::str::FromStr;
+
+use light_prover_client::prover::{GnarkProver, ProverBackend};
+
+use crate::errors::ForesterError;
+
+/// Backend generating validity proofs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProverBackendKind {
+    /// Client of the `light-prover` server.
+    #[default]
+    Gnark,
+    /// In-process arkworks prover, with circuits exported from the
+    /// `light-prover` keys.
+    Arkworks,
+}
+
+impl FromStr for ProverBackendKind {
+    type Err = ForesterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gnark" => Ok(ProverBackendKind::Gnark),
+            "arkworks" => Ok(ProverBackendKind::Arkworks),
+            _ => Err(ForesterError::Custom(format!(
+                "unknown prover backend {}, expected gnark or arkworks",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExternalServicesConfig {
     pub rpc_url: String,
     pub ws_rpc_url: String,
     pub indexer_url: String,
     pub prover_url: String,
+    pub prover_backend: ProverBackendKind,
+    /// Directory of the circuits exported for the arkworks prover.
+    pub prover_keys_dir: String,
     pub derivation: String,
 }
+
+impl ExternalServicesConfig {
+    /// Prover selected by `prover_backend`: a client of the prover server at
+    /// `prover_url`, or the arkworks prover with the circuits in
+    /// `prover_keys_dir`, which requires the `arkworks-prover` feature.
+    pub fn prover(&self) -> Result<ProverBackend, ForesterError> {
+        match self.prover_backend {
+            ProverBackendKind::Gnark => Ok(GnarkProver::new(&self.prover_url).into()),
+            ProverBackendKind::Arkworks => self.arkworks_prover(),
+        }
+    }
+
+    #[cfg(feature = "arkworks-prover")]
+    fn arkworks_prover(&self) -> Result<ProverBackend, ForesterError> {
+        use account_compression::utils::constants::STATE_MERKLE_TREE_HEIGHT;
+        use light_prover_client::arkworks::{ArkworksProver, CircuitId};
+
+        let circuits = CircuitId::light_prover_circuits(STATE_MERKLE_TREE_HEIGHT as usize);
+        let prover = ArkworksProver::load(&self.prover_keys_dir, &circuits)
+            .map_err(|e| ForesterError::Custom(e.to_string()))?;
+        Ok(std::sync::Arc::new(prover).into())
+    }
+
+    #[cfg(not(feature = "arkworks-prover"))]
+    fn arkworks_prover(&self) -> Result<ProverBackend, ForesterError> {
+        Err(ForesterError::Custom(
+            "the arkworks prover requires the arkworks-prover feature".to_string(),
+        ))
+    }
+}
@@ -1,16 +1,80 @@
-use crate::utils::decode_hash;
 use account_compression::initialize_address_merkle_tree::Pubkey;
-use light_test_utils::indexer::{Indexer, IndexerError, MerkleProof, NewAddressProofWithContext};
+use account_compression::StateMerkleTreeAccount;
+use anchor_lang::AnchorDeserialize;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use light_compressed_token::{token_data::AccountState, TokenData};
+use light_concurrent_merkle_tree::copy::ConcurrentMerkleTreeCopy;
+use light_hasher::Poseidon;
+use light_system_program::invoke::processor::CompressedProof;
+use light_system_program::sdk::compressed_account::{
+    CompressedAccount, CompressedAccountData, CompressedAccountWithMerkleContext, MerkleContext,
+};
+use light_test_utils::indexer::test_indexer::ProofRpcResult;
+use light_test_utils::indexer::{
+    Indexer, IndexerError, MerkleProof, NewAddressProofWithContext, TokenDataWithContext,
+};
 use light_test_utils::rpc::rpc_connection::RpcConnection;
 use log::{debug, info};
 use photon_api::apis::configuration::Configuration;
-use photon_api::models::GetCompressedAccountsByOwnerPostRequestParams;
+use photon_api::apis::default_api;
+use photon_api::apis::Error as PhotonApiError;
+use photon_api::models::{self, GetCompressedAccountPost200ResponseError};
+use serde::Deserialize;
 use solana_sdk::bs58;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::mem;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
+/// Height of the state and address Merkle trees indexed by Photon.
+const MERKLE_TREE_HEIGHT: usize = 26;
+
+/// Metadata of a Merkle tree account, which isn't returned by Photon.
+#[derive(Debug, Clone, Copy)]
+struct MerkleTreeInfo {
+    canopy_depth: usize,
+    associated_queue: Pubkey,
+}
+
+/// Response of `getValidityProof`.
+///
+/// The `photon-api` model of the compressed proof types its elements as
+/// binary strings, while Photon returns them as byte arrays, so the response
+/// is decoded into these types instead.
+#[derive(Debug, Deserialize)]
+struct ValidityProofResponse {
+    error: Option<Box<GetCompressedAccountPost200ResponseError>>,
+    result: Option<Box<ValidityProof>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidityProof {
+    compressed_proof: ValidityProofBytes,
+    merkle_trees: Vec<String>,
+    root_indices: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidityProofBytes {
+    a: Vec<u8>,
+    b: Vec<u8>,
+    c: Vec<u8>,
+}
+
+/// [`Indexer`] backed by the Photon API.
+///
+/// Photon indexes transactions itself, so the methods which update or expose
+/// the local state of [`TestIndexer`](light_test_utils::indexer::TestIndexer),
+/// like `add_event_and_compressed_accounts` or `get_state_merkle_trees`, are
+/// not implemented. Failed requests are returned as errors.
 pub struct PhotonIndexer<R: RpcConnection> {
     configuration: Configuration,
-    rpc: R,
+    rpc: Arc<tokio::sync::Mutex<R>>,
+    /// Merkle tree accounts don't change their canopy depth and queue, so they
+    /// are read once.
+    merkle_trees: Arc<Mutex<HashMap<Pubkey, MerkleTreeInfo>>>,
 }
 
 impl<R: RpcConnection> PhotonIndexer<R> {
@@ -20,14 +84,263 @@ impl<R: RpcConnection> PhotonIndexer<R> {
             ..Default::default()
         };
 
-        PhotonIndexer { configuration, rpc }
+        PhotonIndexer {
+            configuration,
+            rpc: Arc::new(tokio::sync::Mutex::new(rpc)),
+            merkle_trees: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the canopy depth and queue of the Merkle tree. State and
+    /// address Merkle tree accounts share the layout of the metadata and of
+    /// the beginning of the tree.
+    async fn merkle_tree_info(&self, merkle_tree: Pubkey) -> Result<MerkleTreeInfo, IndexerError> {
+        if let Some(info) = self.merkle_trees.lock().unwrap().get(&merkle_tree) {
+            return Ok(*info);
+        }
+        // The trait requires `Sync` futures, which `RpcConnection` futures are
+        // not, the account is fetched in a task instead.
+        let rpc = self.rpc.clone();
+        let account = tokio::spawn(async move { rpc.lock().await.get_account(merkle_tree).await })
+            .await
+            .map_err(|e| IndexerError::Custom(e.to_string()))??
+            .ok_or(IndexerError::MerkleTreeNotFound(merkle_tree))?;
+        let metadata_end = 8 + mem::size_of::<StateMerkleTreeAccount>();
+        if account.data.len() < metadata_end {
+            return Err(IndexerError::InvalidResponse(format!(
+                "account {} is not a Merkle tree",
+                merkle_tree
+            )));
+        }
+        let metadata = StateMerkleTreeAccount::deserialize(&mut &account.data[8..metadata_end])
+            .map_err(|e| IndexerError::Custom(e.to_string()))?;
+        let (tree, _) =
+            ConcurrentMerkleTreeCopy::<Poseidon, MERKLE_TREE_HEIGHT>::struct_from_bytes_copy(
+                &account.data[metadata_end..],
+            )
+            .map_err(|e| IndexerError::Custom(e.to_string()))?;
+        let info = MerkleTreeInfo {
+            canopy_depth: tree.canopy_depth,
+            associated_queue: metadata.metadata.associated_queue,
+        };
+        self.merkle_trees.lock().unwrap().insert(merkle_tree, info);
+        Ok(info)
+    }
+
+    /// Removes the nodes of the canopy, which are stored in the tree account,
+    /// from a full proof returned by Photon.
+    async fn remove_canopy(
+        &self,
+        merkle_tree: Pubkey,
+        proof: &[String],
+    ) -> Result<Vec<[u8; 32]>, IndexerError> {
+        let canopy_depth = self.merkle_tree_info(merkle_tree).await?.canopy_depth;
+        let proof_len = proof.len().checked_sub(canopy_depth).ok_or_else(|| {
+            IndexerError::InvalidResponse(format!(
+                "proof of length {} is shorter than the canopy of {}",
+                proof.len(),
+                merkle_tree
+            ))
+        })?;
+        proof[..proof_len].iter().map(|x| decode_hash(x)).collect()
+    }
+
+    async fn compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<models::Account>, IndexerError> {
+        let mut accounts = Vec::new();
+        let mut cursor = None;
+        loop {
+            let request = models::GetCompressedAccountsByOwnerPostRequest {
+                params: Box::new(models::GetCompressedAccountsByOwnerPostRequestParams {
+                    cursor: cursor.map(Some),
+                    limit: None,
+                    owner: owner.to_string(),
+                }),
+                ..Default::default()
+            };
+            let response =
+                default_api::get_compressed_accounts_by_owner_post(&self.configuration, request)
+                    .await?;
+            let page = photon_result(response.error, response.result)?.value;
+            accounts.extend(page.items);
+            cursor = page.cursor;
+            if cursor.is_none() {
+                return Ok(accounts);
+            }
+        }
+    }
+
+    async fn compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<TokenDataWithContext>, IndexerError> {
+        let mut token_accounts = Vec::new();
+        let mut cursor = None;
+        loop {
+            let request = models::GetCompressedTokenAccountsByOwnerPostRequest {
+                params: Box::new(models::GetCompressedTokenAccountsByOwnerPostRequestParams {
+                    cursor: cursor.map(Some),
+                    limit: None,
+                    mint: None,
+                    owner: owner.to_string(),
+                }),
+                ..Default::default()
+            };
+            let response = default_api::get_compressed_token_accounts_by_owner_post(
+                &self.configuration,
+                request,
+            )
+            .await?;
+            let page = photon_result(response.error, response.result)?.value;
+            for token_account in page.items {
+                token_accounts.push(TokenDataWithContext {
+                    token_data: to_token_data(&token_account.token_data)?,
+                    compressed_account: self.to_compressed_account(&token_account.account).await?,
+                });
+            }
+            cursor = page.cursor;
+            if cursor.is_none() {
+                return Ok(token_accounts);
+            }
+        }
+    }
+
+    async fn to_compressed_account(
+        &self,
+        account: &models::Account,
+    ) -> Result<CompressedAccountWithMerkleContext, IndexerError> {
+        let merkle_tree_pubkey = decode_pubkey(&account.tree)?;
+        let nullifier_queue_pubkey = self
+            .merkle_tree_info(merkle_tree_pubkey)
+            .await?
+            .associated_queue;
+        let data = account
+            .data
+            .as_ref()
+            .map(|data| {
+                Ok::<_, IndexerError>(CompressedAccountData {
+                    discriminator: (data.discriminator as u64).to_le_bytes(),
+                    data: STANDARD
+                        .decode(&data.data)
+                        .map_err(|e| IndexerError::InvalidResponse(e.to_string()))?,
+                    data_hash: decode_hash(&data.data_hash)?,
+                })
+            })
+            .transpose()?;
+        Ok(CompressedAccountWithMerkleContext {
+            compressed_account: CompressedAccount {
+                owner: decode_pubkey(&account.owner)?,
+                lamports: to_u64(account.lamports)?,
+                address: account.address.as_deref().map(decode_hash).transpose()?,
+                data,
+            },
+            merkle_context: MerkleContext {
+                merkle_tree_pubkey,
+                nullifier_queue_pubkey,
+                leaf_index: u32::try_from(account.leaf_index)
+                    .map_err(|e| IndexerError::InvalidResponse(e.to_string()))?,
+            },
+        })
+    }
+
+    /// Sends a `getValidityProof` request. The request is built like
+    /// `default_api::get_validity_proof_post`, but the response is decoded
+    /// into [`ValidityProofResponse`].
+    async fn post_validity_proof(
+        &self,
+        request: &models::GetValidityProofPostRequest,
+    ) -> Result<ValidityProofResponse, IndexerError> {
+        let mut builder = self
+            .configuration
+            .client
+            .post(format!("{}/getValidityProof", self.configuration.base_path))
+            .json(request);
+        if let Some(user_agent) = self.configuration.user_agent.as_ref() {
+            builder = builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+        }
+        let response = builder
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(PhotonApiError::<default_api::GetValidityProofPostError>::from)?;
+        response
+            .json()
+            .await
+            .map_err(|e| PhotonApiError::<default_api::GetValidityProofPostError>::from(e).into())
+    }
+
+    async fn validity_proof(
+        &self,
+        compressed_accounts: Option<&[[u8; 32]]>,
+        state_merkle_tree_pubkeys: Option<&[Pubkey]>,
+        new_addresses: Option<&[[u8; 32]]>,
+        address_merkle_tree_pubkeys: Option<Vec<Pubkey>>,
+    ) -> Result<ProofRpcResult, IndexerError> {
+        let hashes = compressed_accounts
+            .unwrap_or_default()
+            .iter()
+            .map(|hash| bs58::encode(hash).into_string())
+            .collect::<Vec<_>>();
+        let new_addresses = new_addresses
+            .unwrap_or_default()
+            .iter()
+            .map(|address| bs58::encode(address).into_string())
+            .collect::<Vec<_>>();
+        let num_hashes = hashes.len();
+        let request = models::GetValidityProofPostRequest {
+            params: Box::new(models::GetValidityProofPostRequestParams::new(
+                hashes,
+                new_addresses,
+            )),
+            ..Default::default()
+        };
+        let response = self.post_validity_proof(&request).await?;
+        let result = photon_result(response.error, response.result)?;
+
+        // Photon picks the trees itself, they have to be the expected ones.
+        let expected_merkle_trees = state_merkle_tree_pubkeys
+            .unwrap_or_default()
+            .iter()
+            .chain(address_merkle_tree_pubkeys.unwrap_or_default().iter())
+            .map(|pubkey| pubkey.to_string())
+            .collect::<Vec<_>>();
+        if result.merkle_trees != expected_merkle_trees {
+            return Err(IndexerError::InvalidResponse(format!(
+                "proof for Merkle trees {:?} instead of {:?}",
+                result.merkle_trees, expected_merkle_trees
+            )));
+        }
+        if result.root_indices.len() != result.merkle_trees.len() {
+            return Err(IndexerError::InvalidResponse(
+                "number of root indices doesn't match the number of Merkle trees".to_string(),
+            ));
+        }
+        let mut root_indices = result
+            .root_indices
+            .iter()
+            .map(|index| {
+                u16::try_from(*index).map_err(|e| IndexerError::InvalidResponse(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let address_root_indices = root_indices.split_off(num_hashes);
+        let proof = &result.compressed_proof;
+        Ok(ProofRpcResult {
+            proof: CompressedProof {
+                a: to_array(&proof.a)?,
+                b: to_array(&proof.b)?,
+                c: to_array(&proof.c)?,
+            },
+            root_indices,
+            address_root_indices,
+        })
     }
 }
 
 impl<R: RpcConnection> Debug for PhotonIndexer<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PhotonIndexer")
-            .field("configuration", &self.configuration)
             .field("configuration", &self.configuration)
             .finish()
     }
@@ -38,6 +351,7 @@ impl<R: RpcConnection> Clone for PhotonIndexer<R> {
         PhotonIndexer {
             configuration: self.configuration.clone(),
             rpc: self.rpc.clone(),
+            merkle_trees: self.merkle_trees.clone(),
         }
     }
 }
@@ -48,80 +362,47 @@ impl<R: RpcConnection> Indexer<R> for PhotonIndexer<R> {
         hashes: Vec<String>,
     ) -> Result<Vec<MerkleProof>, IndexerError> {
         debug!("Getting proofs for {:?}", hashes);
-        let request = photon_api::models::GetMultipleCompressedAccountProofsPostRequest {
+        let request = models::GetMultipleCompressedAccountProofsPostRequest {
             params: hashes,
             ..Default::default()
         };
 
-        let result = photon_api::apis::default_api::get_multiple_compressed_account_proofs_post(
-            &self.configuration,
-            request,
-        )
-        .await;
-
-        match result {
-            Ok(response) => {
-                match response.result {
-                    Some(result) => {
-                        let proofs = result
-                            .value
-                            .iter()
-                            .map(|x| {
-                                let mut proof_result_value = x.proof.clone();
-                                proof_result_value.truncate(proof_result_value.len() - 10); // Remove canopy
-                                let proof: Vec<[u8; 32]> =
-                                    proof_result_value.iter().map(|x| decode_hash(x)).collect();
-                                MerkleProof {
-                                    hash: x.hash.clone(),
-                                    leaf_index: x.leaf_index,
-                                    merkle_tree: x.merkle_tree.clone(),
-                                    proof,
-                                    root_seq: x.root_seq,
-                                }
-                            })
-                            .collect();
-
-                        Ok(proofs)
-                    }
-                    None => Err(IndexerError::Custom("No result".to_string())),
-                }
-            }
-            Err(e) => Err(IndexerError::Custom(e.to_string())),
+        let response =
+            default_api::get_multiple_compressed_account_proofs_post(&self.configuration, request)
+                .await?;
+        let result = photon_result(response.error, response.result)?;
+
+        let mut proofs = Vec::with_capacity(result.value.len());
+        for x in result.value {
+            let proof = self
+                .remove_canopy(decode_pubkey(&x.merkle_tree)?, &x.proof)
+                .await?;
+            proofs.push(MerkleProof {
+                hash: x.hash,
+                leaf_index: x.leaf_index,
+                merkle_tree: x.merkle_tree,
+                proof,
+                root_seq: x.root_seq,
+            });
         }
+        Ok(proofs)
     }
 
     async fn get_rpc_compressed_accounts_by_owner(
         &self,
         owner: &Pubkey,
     ) -> Result<Vec<String>, IndexerError> {
-        let request = photon_api::models::GetCompressedAccountsByOwnerPostRequest {
-            params: Box::from(GetCompressedAccountsByOwnerPostRequestParams {
-                cursor: None,
-                limit: None,
-                owner: owner.to_string(),
-            }),
-            ..Default::default()
-        };
-
-        let result = photon_api::apis::default_api::get_compressed_accounts_by_owner_post(
-            &self.configuration,
-            request,
-        )
-        .await
-        .unwrap();
-
-        let accs = result.result.unwrap().value;
-        let mut hashes = Vec::new();
-        for acc in accs.items {
-            hashes.push(acc.hash);
-        }
-
-        Ok(hashes)
+        Ok(self
+            .compressed_accounts_by_owner(owner)
+            .await?
+            .into_iter()
+            .map(|account| account.hash)
+            .collect())
     }
 
     async fn get_multiple_new_address_proofs(
         &self,
-        _merkle_tree_pubkey: [u8; 32],
+        merkle_tree_pubkey: [u8; 32],
         addresses: Vec<[u8; 32]>,
     ) -> Result<Vec<NewAddressProofWithContext>, IndexerError> {
         let addresses_bs58 = addresses
@@ -129,49 +410,43 @@ impl<R: RpcConnection> Indexer<R> for PhotonIndexer<R> {
             .map(|x| bs58::encode(x).into_string())
             .collect();
 
-        let request = photon_api::models::GetMultipleNewAddressProofsPostRequest {
+        let request = models::GetMultipleNewAddressProofsPostRequest {
             params: addresses_bs58,
             ..Default::default()
         };
 
         info!("Request: {:?}", request);
 
-        let result = photon_api::apis::default_api::get_multiple_new_address_proofs_post(
-            &self.configuration,
-            request,
-        )
-        .await;
-
-        if result.is_err() {
-            return Err(IndexerError::Custom(result.err().unwrap().to_string()));
-        }
-
-        info!("Result: {:?}", result);
+        let response =
+            default_api::get_multiple_new_address_proofs_post(&self.configuration, request).await?;
+        let photon_proofs = photon_result(response.error, response.result)?.value;
 
-        let photon_proofs = result.unwrap().result.unwrap().value;
         let mut proofs: Vec<NewAddressProofWithContext> = Vec::new();
         for photon_proof in photon_proofs {
-            let tree_pubkey = decode_hash(&photon_proof.merkle_tree);
-            let low_address_value = decode_hash(&photon_proof.lower_range_address);
-            let next_address_value = decode_hash(&photon_proof.higher_range_address);
+            let tree_pubkey = decode_hash(&photon_proof.merkle_tree)?;
+            if tree_pubkey != merkle_tree_pubkey {
+                return Err(IndexerError::InvalidResponse(format!(
+                    "proof for address Merkle tree {} instead of {}",
+                    photon_proof.merkle_tree,
+                    Pubkey::from(merkle_tree_pubkey)
+                )));
+            }
+            let proof = self
+                .remove_canopy(Pubkey::from(tree_pubkey), &photon_proof.proof)
+                .await?;
             let proof = NewAddressProofWithContext {
                 merkle_tree: tree_pubkey,
                 low_address_index: photon_proof.low_element_leaf_index as u64,
-                low_address_value,
+                low_address_value: decode_hash(&photon_proof.lower_range_address)?,
                 low_address_next_index: photon_proof.next_index as u64,
-                low_address_next_value: next_address_value,
-                low_address_proof: {
-                    let mut proof_vec: Vec<[u8; 32]> = photon_proof
-                        .proof
-                        .iter()
-                        .map(|x: &String| decode_hash(x))
-                        .collect();
-                    proof_vec.truncate(proof_vec.len() - 10); // Remove canopy
-                    let mut proof_arr = [[0u8; 32]; 16];
-                    proof_arr.copy_from_slice(&proof_vec);
-                    proof_arr
-                },
-                root: decode_hash(&photon_proof.root),
+                low_address_next_value: decode_hash(&photon_proof.higher_range_address)?,
+                low_address_proof: proof.try_into().map_err(|proof: Vec<[u8; 32]>| {
+                    IndexerError::InvalidResponse(format!(
+                        "address proof of length {} instead of 16",
+                        proof.len()
+                    ))
+                })?,
+                root: decode_hash(&photon_proof.root)?,
                 root_seq: photon_proof.root_seq,
                 new_low_element: None,
                 new_element: None,
@@ -182,4 +457,99 @@ impl<R: RpcConnection> Indexer<R> for PhotonIndexer<R> {
 
         Ok(proofs)
     }
+
+    async fn create_proof_for_compressed_accounts(
+        &mut self,
+        compressed_accounts: Option<&[[u8; 32]]>,
+        state_merkle_tree_pubkeys: Option<&[Pubkey]>,
+        new_addresses: Option<&[[u8; 32]]>,
+        address_merkle_tree_pubkeys: Option<Vec<Pubkey>>,
+        _rpc: &mut R,
+    ) -> Result<ProofRpcResult, IndexerError> {
+        self.validity_proof(
+            compressed_accounts,
+            state_merkle_tree_pubkeys,
+            new_addresses,
+            address_merkle_tree_pubkeys,
+        )
+        .await
+    }
+
+    async fn get_compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<CompressedAccountWithMerkleContext>, IndexerError> {
+        let mut compressed_accounts = Vec::new();
+        for account in self.compressed_accounts_by_owner(owner).await? {
+            compressed_accounts.push(self.to_compressed_account(&account).await?);
+        }
+        Ok(compressed_accounts)
+    }
+
+    async fn get_compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<TokenDataWithContext>, IndexerError> {
+        self.compressed_token_accounts_by_owner(owner).await
+    }
+}
+
+/// Returns the result of a JSON-RPC response of Photon.
+fn photon_result<T>(
+    error: Option<Box<GetCompressedAccountPost200ResponseError>>,
+    result: Option<Box<T>>,
+) -> Result<T, IndexerError> {
+    if let Some(error) = error {
+        return Err(IndexerError::PhotonError {
+            code: error.code,
+            message: error.message,
+        });
+    }
+    result
+        .map(|result| *result)
+        .ok_or(IndexerError::MissingResult)
+}
+
+fn decode_hash(value: &str) -> Result<[u8; 32], IndexerError> {
+    bs58::decode(value)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| IndexerError::InvalidResponse(format!("invalid hash {}", value)))
+}
+
+fn decode_pubkey(value: &str) -> Result<Pubkey, IndexerError> {
+    Pubkey::from_str(value)
+        .map_err(|e| IndexerError::InvalidResponse(format!("invalid pubkey {}: {}", value, e)))
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], IndexerError> {
+    bytes.try_into().map_err(|_| {
+        IndexerError::InvalidResponse(format!(
+            "proof element of length {} instead of {}",
+            bytes.len(),
+            N
+        ))
+    })
+}
+
+fn to_u64(value: i64) -> Result<u64, IndexerError> {
+    u64::try_from(value).map_err(|e| IndexerError::InvalidResponse(e.to_string()))
+}
+
+fn to_token_data(token_data: &models::TokenData) -> Result<TokenData, IndexerError> {
+    Ok(TokenData {
+        mint: decode_pubkey(&token_data.mint)?,
+        owner: decode_pubkey(&token_data.owner)?,
+        amount: to_u64(token_data.amount)?,
+        delegate: token_data
+            .delegate
+            .as_deref()
+            .map(decode_pubkey)
+            .transpose()?,
+        state: match token_data.state {
+            models::AccountState::Initialized => AccountState::Initialized,
+            models::AccountState::Frozen => AccountState::Frozen,
+        },
+    })
 }
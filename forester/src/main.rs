@@ -20,7 +20,13 @@ fn setup_logger() {
 #[tokio::main]
 async fn main() {
     setup_logger();
-    let config: Arc<ForesterConfig> = Arc::new(init_config());
+    let config: Arc<ForesterConfig> = match init_config() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            error!("Invalid forester config: {:?}", e);
+            return;
+        }
+    };
     println!("Connection pool created");
     let cli = Cli::parse();
     match &cli.command {
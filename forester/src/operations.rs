@@ -14,7 +14,6 @@ use light_test_utils::rpc::rpc_connection::RpcConnection;
 use log::{debug, info, warn};
 use solana_client::pubsub_client::PubsubClient;
 use solana_client::rpc_config::RpcAccountInfoConfig;
-use solana_sdk::commitment_config::CommitmentConfig;
 use std::mem;
 use std::sync::Arc;
 use std::time::Duration;
@@ -38,7 +37,7 @@ pub async fn subscribe_state<I: Indexer<R>, R: RpcConnection>(
                 Some(RpcAccountInfoConfig {
                     encoding: None,
                     data_slice: None,
-                    commitment: Some(CommitmentConfig::confirmed()),
+                    commitment: Some(config.commitment),
                     min_context_slot: None,
                 }),
             ) {
@@ -83,7 +82,7 @@ pub async fn subscribe_addresses<I: Indexer<R>, R: RpcConnection>(
                 Some(RpcAccountInfoConfig {
                     encoding: None,
                     data_slice: None,
-                    commitment: Some(CommitmentConfig::confirmed()),
+                    commitment: Some(config.commitment),
                     min_context_slot: None,
                 }),
             ) {
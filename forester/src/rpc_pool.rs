@@ -1,7 +1,6 @@
 use crate::ForesterConfig;
 use light_test_utils::rpc::rpc_connection::RpcConnection;
 use rand::seq::SliceRandom;
-use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
 use solana_sdk::signature::Signer;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -33,9 +32,7 @@ impl<R: RpcConnection> RpcPool<R> {
 pub async fn init_rpc<R: RpcConnection>(config: Arc<ForesterConfig>, airdrop: bool) -> R {
     let mut rpc = R::new(
         config.external_services.rpc_url.clone(),
-        Some(CommitmentConfig {
-            commitment: CommitmentLevel::Confirmed,
-        }),
+        Some(config.commitment),
     );
 
     if airdrop {
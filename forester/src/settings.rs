@@ -1,7 +1,9 @@
-use crate::external_services_config::ExternalServicesConfig;
+use crate::errors::ForesterError;
+use crate::external_services_config::{ExternalServicesConfig, ProverBackendKind};
 use crate::ForesterConfig;
 use account_compression::initialize_address_merkle_tree::Pubkey;
 use config::Config;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::{Keypair, Signer};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
@@ -18,11 +20,14 @@ pub enum SettingsKey {
     WsRpcUrl,
     IndexerUrl,
     ProverUrl,
+    ProverBackend,
+    ProverKeysDir,
     BatchSize,
     MaxRetries,
     ConcurrencyLimit,
     CULimit,
     RpcPoolSize,
+    Commitment,
 }
 
 impl Display for SettingsKey {
@@ -41,11 +46,14 @@ impl Display for SettingsKey {
                 SettingsKey::WsRpcUrl => "WS_RPC_URL",
                 SettingsKey::IndexerUrl => "INDEXER_URL",
                 SettingsKey::ProverUrl => "PROVER_URL",
+                SettingsKey::ProverBackend => "PROVER_BACKEND",
+                SettingsKey::ProverKeysDir => "PROVER_KEYS_DIR",
                 SettingsKey::ConcurrencyLimit => "CONCURRENCY_LIMIT",
                 SettingsKey::BatchSize => "BATCH_SIZE",
                 SettingsKey::MaxRetries => "MAX_RETRIES",
                 SettingsKey::CULimit => "CU_LIMIT",
                 SettingsKey::RpcPoolSize => "RPC_POOL_SIZE",
+                SettingsKey::Commitment => "COMMITMENT",
             }
         )
     }
@@ -68,7 +76,7 @@ fn convert(json: &str) -> serde_json::Result<Vec<u8>> {
     serde_json::from_str(json)
 }
 
-pub fn init_config() -> ForesterConfig {
+pub fn init_config() -> Result<ForesterConfig, ForesterError> {
     let config_path = locate_config_file();
 
     let settings = Config::builder()
@@ -110,6 +118,13 @@ pub fn init_config() -> ForesterConfig {
     let prover_url = settings
         .get_string(&SettingsKey::ProverUrl.to_string())
         .unwrap();
+    let prover_backend = settings
+        .get_string(&SettingsKey::ProverBackend.to_string())
+        .map(|backend| ProverBackendKind::from_str(&backend).unwrap())
+        .unwrap_or_default();
+    let prover_keys_dir = settings
+        .get_string(&SettingsKey::ProverKeysDir.to_string())
+        .unwrap_or_else(|_| "./proving-keys/".to_string());
     let concurrency_limit = settings
         .get_int(&SettingsKey::ConcurrencyLimit.to_string())
         .unwrap();
@@ -121,12 +136,23 @@ pub fn init_config() -> ForesterConfig {
         .unwrap();
     let cu_limit = settings.get_int(&SettingsKey::CULimit.to_string()).unwrap();
     let rpc_pool_size = settings.get_int(&SettingsKey::CULimit.to_string()).unwrap();
-    ForesterConfig {
+    let commitment = settings
+        .get_string(&SettingsKey::Commitment.to_string())
+        .unwrap_or_else(|_| "confirmed".to_string());
+    let commitment = CommitmentConfig::from_str(&commitment).map_err(|_| {
+        ForesterError::Custom(format!(
+            "unknown commitment {}, expected processed, confirmed or finalized",
+            commitment
+        ))
+    })?;
+    Ok(ForesterConfig {
         external_services: ExternalServicesConfig {
             rpc_url,
             ws_rpc_url,
             indexer_url,
             prover_url,
+            prover_backend,
+            prover_keys_dir,
             derivation: payer.pubkey().to_string(),
         },
         nullifier_queue_pubkey: Pubkey::from_str(&nullifier_queue_pubkey).unwrap(),
@@ -141,5 +167,6 @@ pub fn init_config() -> ForesterConfig {
         max_retries: max_retries as usize,
         cu_limit: cu_limit as u32,
         rpc_pool_size: rpc_pool_size as usize,
-    }
+        commitment,
+    })
 }
@@ -28,7 +28,8 @@ async fn test_state_tree_nullifier() {
         .await
         .unwrap();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &config.external_services,
         &config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
@@ -89,7 +90,8 @@ async fn test_1_all() {
         .await
         .unwrap();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &config.external_services,
         &config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
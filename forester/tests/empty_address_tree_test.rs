@@ -25,7 +25,8 @@ async fn empty_address_tree_test() {
         .await
         .unwrap();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &forester_config.external_services,
         &forester_config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
@@ -40,7 +40,8 @@ async fn test_photon_interop_nullify_account() {
 
     let forester_config = forester_config();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &forester_config.external_services,
         &forester_config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
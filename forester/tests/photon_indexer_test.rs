@@ -0,0 +1,165 @@
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use forester::indexer::PhotonIndexer;
+use forester::utils::LightValidatorConfig;
+use light_test_utils::indexer::{Indexer, PhotonServer, TestIndexer};
+use light_test_utils::rpc::rpc_connection::RpcConnection;
+use light_test_utils::rpc::solana_rpc::SolanaRpcUrl;
+use light_test_utils::rpc::SolanaRpcConnection;
+use light_test_utils::system_program::compress_sol_test;
+use light_test_utils::test_env::get_test_env_accounts;
+use solana_sdk::signature::Signer;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+mod test_utils;
+use test_utils::*;
+
+/// Queries a PhotonServer serving the TestIndexer with the PhotonIndexer and
+/// compares the results with the TestIndexer.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn photon_indexer_test() {
+    init(Some(LightValidatorConfig {
+        enable_indexer: false,
+        ..LightValidatorConfig::default()
+    }))
+    .await;
+    let env_accounts = get_test_env_accounts();
+
+    let mut rpc = SolanaRpcConnection::new(SolanaRpcUrl::Localnet, None);
+    let payer = rpc.get_payer().insecure_clone();
+    rpc.airdrop_lamports(&payer.pubkey(), LAMPORTS_PER_SOL * 1000)
+        .await
+        .unwrap();
+
+    let test_indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &forester_config().external_services,
+        &payer,
+        &env_accounts,
+        true,
+        true,
+    )
+    .await;
+    let test_indexer = Arc::new(Mutex::new(test_indexer));
+    for compress_amount in [1_000_000, 2_000_000] {
+        compress_sol_test(
+            &mut rpc,
+            &mut *test_indexer.lock().await,
+            &payer,
+            &[],
+            false,
+            compress_amount,
+            &env_accounts.merkle_tree_pubkey,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    let server = PhotonServer::new(test_indexer.clone());
+    let handle = server.serve(([127, 0, 0, 1], 0).into()).unwrap();
+    let mut photon_indexer = PhotonIndexer::new(
+        handle.url().to_string(),
+        SolanaRpcConnection::new(SolanaRpcUrl::Localnet, None),
+    );
+
+    // Accounts by owner. The served indexer is locked only after Photon
+    // requests are answered.
+    let mut photon_hashes = photon_indexer
+        .get_rpc_compressed_accounts_by_owner(&payer.pubkey())
+        .await
+        .unwrap();
+    photon_hashes.sort();
+    let mut test_hashes = test_indexer
+        .lock()
+        .await
+        .get_rpc_compressed_accounts_by_owner(&payer.pubkey())
+        .await
+        .unwrap();
+    test_hashes.sort();
+    assert_eq!(photon_hashes, test_hashes);
+    let photon_accounts = photon_indexer
+        .get_compressed_accounts_by_owner(&payer.pubkey())
+        .await
+        .unwrap();
+    let test_accounts = test_indexer
+        .lock()
+        .await
+        .get_compressed_accounts_by_owner(&payer.pubkey());
+    assert_eq!(photon_accounts, test_accounts);
+    assert!(photon_indexer
+        .get_compressed_token_accounts_by_owner(&payer.pubkey())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Account proofs without the canopy.
+    let photon_proofs = photon_indexer
+        .get_multiple_compressed_account_proofs(test_hashes.clone())
+        .await
+        .unwrap();
+    let test_proofs = test_indexer
+        .lock()
+        .await
+        .get_multiple_compressed_account_proofs(test_hashes.clone())
+        .await
+        .unwrap();
+    assert_eq!(photon_proofs.len(), test_proofs.len());
+    for (photon_proof, test_proof) in photon_proofs.iter().zip(test_proofs.iter()) {
+        assert_eq!(photon_proof.hash, test_proof.hash);
+        assert_eq!(photon_proof.leaf_index, test_proof.leaf_index);
+        assert_eq!(photon_proof.merkle_tree, test_proof.merkle_tree);
+        assert_eq!(photon_proof.proof, test_proof.proof);
+        assert_eq!(photon_proof.root_seq, test_proof.root_seq);
+    }
+
+    // New address proofs.
+    let address = generate_pubkey_254();
+    // The helper queries Photon, so it compares with a copy of the indexer
+    // instead of holding the lock.
+    let mut test_indexer_copy = test_indexer.lock().await.clone();
+    assert_new_address_proofs_for_photon_and_test_indexer(
+        &mut test_indexer_copy,
+        &[env_accounts.address_merkle_tree_pubkey],
+        &[address],
+        &photon_indexer,
+    )
+    .await;
+
+    // Validity proof of an account and a new address.
+    let account_hash = test_accounts[0].hash().unwrap();
+    let photon_result = photon_indexer
+        .create_proof_for_compressed_accounts(
+            Some(&[account_hash]),
+            Some(&[env_accounts.merkle_tree_pubkey]),
+            Some(&[address.to_bytes()]),
+            Some(vec![env_accounts.address_merkle_tree_pubkey]),
+            &mut rpc,
+        )
+        .await
+        .unwrap();
+    let test_result = test_indexer
+        .lock()
+        .await
+        .create_proof_for_compressed_accounts(
+            Some(&[account_hash]),
+            Some(&[env_accounts.merkle_tree_pubkey]),
+            Some(&[address.to_bytes()]),
+            Some(vec![env_accounts.address_merkle_tree_pubkey]),
+            &mut rpc,
+        )
+        .await
+        .unwrap();
+    assert_eq!(photon_result.root_indices, test_result.root_indices);
+    assert_eq!(
+        photon_result.address_root_indices,
+        test_result.address_root_indices
+    );
+
+    handle.shutdown().await.unwrap();
+
+    // Requests to an unreachable Photon fail instead of panicking.
+    assert!(photon_indexer
+        .get_compressed_accounts_by_owner(&payer.pubkey())
+        .await
+        .is_err());
+}
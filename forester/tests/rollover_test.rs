@@ -32,7 +32,8 @@ async fn test_address_tree_rollover() {
         .await
         .unwrap();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &config.external_services,
         &config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
@@ -93,7 +94,8 @@ async fn test_state_tree_rollover() {
         .await
         .unwrap();
 
-    let indexer: TestIndexer<SolanaRpcConnection> = TestIndexer::init_from_env(
+    let indexer: TestIndexer<SolanaRpcConnection> = init_test_indexer(
+        &config.external_services,
         &config.payer_keypair,
         &env_accounts,
         keypair_action_config().inclusion(),
@@ -1,13 +1,17 @@
 use account_compression::initialize_address_merkle_tree::Pubkey;
 use forester::indexer::PhotonIndexer;
 use forester::utils::{spawn_validator, LightValidatorConfig};
-use forester::{external_services_config::ExternalServicesConfig, ForesterConfig};
+use forester::{
+    external_services_config::{ExternalServicesConfig, ProverBackendKind},
+    ForesterConfig,
+};
 use light_test_utils::e2e_test_env::{GeneralActionConfig, KeypairActionConfig, User};
 use light_test_utils::indexer::{Indexer, NewAddressProofWithContext, TestIndexer};
 use light_test_utils::rpc::rpc_connection::RpcConnection;
 use light_test_utils::rpc::SolanaRpcConnection;
-use light_test_utils::test_env::{get_test_env_accounts, REGISTRY_ID_TEST_KEYPAIR};
+use light_test_utils::test_env::{get_test_env_accounts, EnvAccounts, REGISTRY_ID_TEST_KEYPAIR};
 use log::{info, LevelFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::{Keypair, Signer};
 
 #[allow(dead_code)]
@@ -76,6 +80,10 @@ pub fn forester_config() -> ForesterConfig {
             ws_rpc_url: "ws://localhost:8900".to_string(),
             indexer_url: "http://localhost:8784".to_string(),
             prover_url: "http://localhost:3001".to_string(),
+            prover_backend: std::env::var("FORESTER_PROVER_BACKEND")
+                .map(|backend| backend.parse().unwrap())
+                .unwrap_or_default(),
+            prover_keys_dir: "../light-prover/proving-keys".to_string(),
             derivation: "En9a97stB3Ek2n6Ey3NJwCUJnmTzLMMEA5C69upGDuQP".to_string(),
         },
         nullifier_queue_pubkey: env_accounts.nullifier_queue_pubkey,
@@ -89,6 +97,35 @@ pub fn forester_config() -> ForesterConfig {
         max_retries: 5,
         cu_limit: 1_000_000,
         rpc_pool_size: 20,
+        commitment: CommitmentConfig::confirmed(),
+    }
+}
+
+/// Test indexer generating proofs with the prover backend of `config`. With
+/// the gnark backend, spawns a local prover server for the given proof
+/// types.
+#[allow(dead_code)]
+pub async fn init_test_indexer(
+    config: &ExternalServicesConfig,
+    payer: &Keypair,
+    env_accounts: &EnvAccounts,
+    inclusion: bool,
+    non_inclusion: bool,
+) -> TestIndexer<SolanaRpcConnection> {
+    match config.prover_backend {
+        ProverBackendKind::Gnark => {
+            TestIndexer::init_from_env(payer, env_accounts, inclusion, non_inclusion).await
+        }
+        ProverBackendKind::Arkworks => {
+            #[cfg(feature = "arkworks-prover")]
+            light_prover_client::arkworks::export_light_prover_circuits(
+                &light_prover_client::arkworks::CircuitId::light_prover_circuits(
+                    account_compression::utils::constants::STATE_MERKLE_TREE_HEIGHT as usize,
+                ),
+            )
+            .unwrap();
+            TestIndexer::init_from_env_with_prover(payer, env_accounts, config.prover().unwrap())
+        }
     }
 }
 
@@ -0,0 +1,45 @@
+use std::io;
+
+use anchor_lang::prelude::Pubkey;
+use light_indexed_merkle_tree::{
+    errors::IndexedMerkleTreeError, reference::IndexedReferenceMerkleTreeError,
+};
+use light_merkle_tree_reference::ReferenceMerkleTreeError;
+use light_sdk::event::EventError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LightIndexerError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("The store is corrupted")]
+    CorruptedStore,
+    #[error("The store has writes which haven't been flushed")]
+    UnflushedWrites,
+    #[error(transparent)]
+    ReferenceMerkleTree(#[from] ReferenceMerkleTreeError),
+    #[error(transparent)]
+    IndexedReferenceMerkleTree(#[from] IndexedReferenceMerkleTreeError),
+    #[error(transparent)]
+    IndexedMerkleTree(#[from] IndexedMerkleTreeError),
+    #[error(transparent)]
+    Event(#[from] EventError),
+    #[error("Unknown Merkle tree {0}")]
+    UnknownMerkleTree(Pubkey),
+    #[error("Merkle tree {0} is already indexed")]
+    MerkleTreeAlreadyExists(Pubkey),
+    #[error("Merkle tree {0} is not a {1} tree")]
+    InvalidTreeType(Pubkey, &'static str),
+    #[error("Compressed account {0} not found")]
+    AccountNotFound(String),
+    #[error("Invalid event: {0}")]
+    InvalidEvent(&'static str),
+    #[error("Expected leaf index {0} in Merkle tree {1}, got {2}")]
+    LeafIndexGap(u64, Pubkey, u64),
+    #[error("Leaf {1} of Merkle tree {0} doesn't match the event")]
+    LeafMismatch(Pubkey, u64),
+    #[error("Expected address index {0} in Merkle tree {1}, got {2}")]
+    AddressIndexGap(u64, Pubkey, u64),
+    #[error("Block at slot {0} is older than the last indexed slot {1}")]
+    SlotOutOfOrder(u64, u64),
+}
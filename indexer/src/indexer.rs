@@ -0,0 +1,693 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize};
+use light_compressed_token::{
+    constants::TOKEN_COMPRESSED_ACCOUNT_DISCRIMINATOR, TokenData, ID as COMPRESSED_TOKEN_PROGRAM_ID,
+};
+use light_concurrent_merkle_tree::event::{IndexedMerkleTreeEvent, MerkleTreeEvent};
+use light_hasher::Poseidon;
+use light_indexed_merkle_tree::{
+    array::{IndexedArray, IndexedElement, IndexedElementBundle},
+    reference::IndexedMerkleTree,
+};
+use light_merkle_tree_reference::{sparse::SparseMerkleTree, ReferenceMerkleTreeError};
+use light_sdk::event::LightEvent;
+use light_system_program::sdk::{
+    compressed_account::{CompressedAccount, CompressedAccountWithMerkleContext, MerkleContext},
+    event::PublicTransactionEvent,
+};
+use log::{debug, warn};
+use num_bigint::BigUint;
+use solana_sdk::bs58;
+
+use crate::{
+    error::LightIndexerError,
+    source::{Block, BlockSource},
+    store::{AccountRecord, IndexerStore, MemoryStore, TreeInfo, TreeType},
+};
+
+pub type StateTree<S> = SparseMerkleTree<Poseidon, S>;
+
+/// Address tree with its indexed array, rebuilt from the stored addresses.
+#[derive(Debug, Clone)]
+pub struct AddressTree {
+    pub merkle_tree: Box<IndexedMerkleTree<Poseidon, usize>>,
+    pub indexed_array: Box<IndexedArray<Poseidon, usize>>,
+}
+
+impl AddressTree {
+    fn new(tree: &TreeInfo) -> Result<Self, LightIndexerError> {
+        let mut merkle_tree = Box::new(IndexedMerkleTree::<Poseidon, usize>::new(
+            tree.height as usize,
+            tree.canopy_depth as usize,
+        )?);
+        merkle_tree.init()?;
+        let mut indexed_array = Box::<IndexedArray<Poseidon, usize>>::default();
+        indexed_array.init()?;
+        Ok(Self {
+            merkle_tree,
+            indexed_array,
+        })
+    }
+
+    /// Index of the next inserted address.
+    fn next_index(&self) -> u64 {
+        self.indexed_array.len() as u64 + 1
+    }
+
+    fn contains(&self, address: &[u8; 32]) -> bool {
+        self.indexed_array
+            .find_element(&BigUint::from_bytes_be(address))
+            .is_some()
+    }
+
+    fn insert(&mut self, address: &[u8; 32]) -> Result<(), LightIndexerError> {
+        let bundle = self
+            .indexed_array
+            .append(&BigUint::from_bytes_be(address))?;
+        self.merkle_tree.update(
+            &bundle.new_low_element,
+            &bundle.new_element,
+            &bundle.new_element_next_value,
+        )?;
+        Ok(())
+    }
+}
+
+/// Merkle proof of a compressed account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountProof {
+    pub hash: [u8; 32],
+    pub merkle_tree: Pubkey,
+    pub leaf_index: u32,
+    /// Full Merkle proof, including the nodes of the canopy.
+    pub proof: Vec<[u8; 32]>,
+    pub root: [u8; 32],
+    pub root_seq: u64,
+}
+
+/// Non-inclusion proof of an address and the elements which insert it.
+#[derive(Debug, Clone)]
+pub struct NewAddressProof {
+    pub merkle_tree: Pubkey,
+    pub root: [u8; 32],
+    pub root_seq: u64,
+    pub low_element: IndexedElement<usize>,
+    pub low_element_next_value: BigUint,
+    /// Full Merkle proof of the low element, including the nodes of the
+    /// canopy.
+    pub low_element_proof: Vec<[u8; 32]>,
+    pub new_element: IndexedElementBundle<usize>,
+}
+
+#[derive(Debug)]
+struct IndexerState<S: IndexerStore> {
+    store: S,
+    trees: HashMap<Pubkey, TreeInfo>,
+    state_trees: HashMap<Pubkey, StateTree<S::NodeStore>>,
+    address_trees: HashMap<Pubkey, AddressTree>,
+}
+
+impl<S: IndexerStore> IndexerState<S> {
+    fn open(store: S) -> Result<Self, LightIndexerError> {
+        let mut state = Self {
+            store,
+            trees: HashMap::new(),
+            state_trees: HashMap::new(),
+            address_trees: HashMap::new(),
+        };
+        for tree in state.store.trees()? {
+            state.open_tree(&tree)?;
+        }
+        // Node stores can be ahead of the store if the indexer stopped
+        // between their flushes.
+        for (merkle_tree, sequence_number) in state.store.sequence_numbers()? {
+            let tree = state.state_tree_mut(&merkle_tree)?;
+            let sequence_number = sequence_number as usize;
+            if tree.sequence_number < sequence_number {
+                return Err(LightIndexerError::CorruptedStore);
+            }
+            if tree.sequence_number > sequence_number {
+                tree.rollback(sequence_number)?;
+                tree.flush()?;
+            }
+        }
+        Ok(state)
+    }
+
+    fn open_tree(&mut self, tree: &TreeInfo) -> Result<(), LightIndexerError> {
+        match tree.tree_type {
+            TreeType::State => {
+                let node_store = self.store.node_store(&tree.merkle_tree)?;
+                let merkle_tree = StateTree::open_or_new(node_store, tree.height as usize)?;
+                self.state_trees.insert(tree.merkle_tree, merkle_tree);
+            }
+            TreeType::Address => {
+                let mut address_tree = AddressTree::new(tree)?;
+                for (index, address) in self.store.addresses(&tree.merkle_tree)? {
+                    if index != address_tree.next_index() {
+                        return Err(LightIndexerError::AddressIndexGap(
+                            address_tree.next_index(),
+                            tree.merkle_tree,
+                            index,
+                        ));
+                    }
+                    address_tree.insert(&address)?;
+                }
+                self.address_trees.insert(tree.merkle_tree, address_tree);
+            }
+        }
+        self.trees.insert(tree.merkle_tree, *tree);
+        Ok(())
+    }
+
+    fn tree_info(&self, merkle_tree: &Pubkey) -> Result<&TreeInfo, LightIndexerError> {
+        self.trees
+            .get(merkle_tree)
+            .ok_or(LightIndexerError::UnknownMerkleTree(*merkle_tree))
+    }
+
+    fn state_tree(
+        &self,
+        merkle_tree: &Pubkey,
+    ) -> Result<&StateTree<S::NodeStore>, LightIndexerError> {
+        self.state_trees
+            .get(merkle_tree)
+            .ok_or_else(|| tree_error(&self.trees, merkle_tree, "state"))
+    }
+
+    fn state_tree_mut(
+        &mut self,
+        merkle_tree: &Pubkey,
+    ) -> Result<&mut StateTree<S::NodeStore>, LightIndexerError> {
+        self.state_trees
+            .get_mut(merkle_tree)
+            .ok_or_else(|| tree_error(&self.trees, merkle_tree, "state"))
+    }
+
+    fn address_tree(&self, merkle_tree: &Pubkey) -> Result<&AddressTree, LightIndexerError> {
+        self.address_trees
+            .get(merkle_tree)
+            .ok_or_else(|| tree_error(&self.trees, merkle_tree, "address"))
+    }
+
+    fn address_tree_mut(
+        &mut self,
+        merkle_tree: &Pubkey,
+    ) -> Result<&mut AddressTree, LightIndexerError> {
+        self.address_trees
+            .get_mut(merkle_tree)
+            .ok_or_else(|| tree_error(&self.trees, merkle_tree, "address"))
+    }
+
+    fn index_event(&mut self, slot: u64, event: &LightEvent) -> Result<(), LightIndexerError> {
+        match event {
+            LightEvent::PublicTransaction(event) => {
+                self.index_transaction_event(slot, event)?;
+            }
+            LightEvent::MerkleTree(event) => self.index_merkle_tree_event(event)?,
+        }
+        Ok(())
+    }
+
+    /// Marks the inputs as nullified and indexes the outputs. Events which
+    /// have already been indexed are skipped.
+    fn index_transaction_event(
+        &mut self,
+        slot: u64,
+        event: &PublicTransactionEvent,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        if event.output_compressed_accounts.len() != event.output_leaf_indices.len()
+            || event.output_compressed_accounts.len()
+                != event.output_compressed_account_hashes.len()
+        {
+            return Err(LightIndexerError::InvalidEvent(
+                "numbers of output accounts, hashes and leaf indices don't match",
+            ));
+        }
+
+        for hash in event.input_compressed_account_hashes.iter() {
+            match self.store.get_account(hash)? {
+                Some(mut account) => {
+                    if !account.is_nullified() {
+                        account.nullified_slot = Some(slot);
+                        self.store.put_account(&account)?;
+                    }
+                }
+                // Accounts created before the indexer was started.
+                None => warn!(
+                    "Input compressed account {} is not indexed",
+                    bs58::encode(hash).into_string()
+                ),
+            }
+        }
+
+        let merkle_trees = event
+            .output_compressed_accounts
+            .iter()
+            .map(|output| {
+                event
+                    .pubkey_array
+                    .get(output.merkle_tree_index as usize)
+                    .copied()
+                    .ok_or(LightIndexerError::InvalidEvent(
+                        "Merkle tree index is out of bounds",
+                    ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Outputs of one tree are appended in a single batch, with one
+        // sequence number.
+        let mut start = 0;
+        while start < merkle_trees.len() {
+            let end = merkle_trees[start..]
+                .iter()
+                .position(|merkle_tree| *merkle_tree != merkle_trees[start])
+                .map_or(merkle_trees.len(), |len| start + len);
+            self.append_leaves(
+                &merkle_trees[start],
+                &event.output_leaf_indices[start..end],
+                &event.output_compressed_account_hashes[start..end],
+            )?;
+            start = end;
+        }
+
+        let mut accounts = Vec::with_capacity(event.output_compressed_accounts.len());
+        for (i, output) in event.output_compressed_accounts.iter().enumerate() {
+            let merkle_tree = merkle_trees[i];
+            let nullifier_queue = self.tree_info(&merkle_tree)?.queue;
+            let hash = event.output_compressed_account_hashes[i];
+            let leaf_index = event.output_leaf_indices[i];
+
+            let account = match self.store.get_account(&hash)? {
+                Some(account) => account,
+                None => {
+                    let account = AccountRecord {
+                        hash,
+                        account: CompressedAccountWithMerkleContext {
+                            compressed_account: output.compressed_account.clone(),
+                            merkle_context: MerkleContext {
+                                merkle_tree_pubkey: merkle_tree,
+                                nullifier_queue_pubkey: nullifier_queue,
+                                leaf_index,
+                            },
+                        },
+                        token_data: token_data(&output.compressed_account),
+                        slot,
+                        nullified_slot: None,
+                    };
+                    self.store.put_account(&account)?;
+                    account
+                }
+            };
+            accounts.push(account);
+        }
+        Ok(accounts)
+    }
+
+    /// Appends the leaves of a batch, which have consecutive indices.
+    fn append_leaves(
+        &mut self,
+        merkle_tree: &Pubkey,
+        leaf_indices: &[u32],
+        leaves: &[[u8; 32]],
+    ) -> Result<(), LightIndexerError> {
+        let first_leaf_index = leaf_indices[0] as usize;
+        if leaf_indices
+            .iter()
+            .enumerate()
+            .any(|(i, leaf_index)| *leaf_index as usize != first_leaf_index + i)
+        {
+            return Err(LightIndexerError::InvalidEvent(
+                "leaf indices of a Merkle tree are not consecutive",
+            ));
+        }
+        let tree = self.state_tree_mut(merkle_tree)?;
+        if first_leaf_index < tree.next_index {
+            // The leaves have already been appended, they might have been
+            // nullified since.
+            for (i, leaf) in leaves.iter().enumerate() {
+                let current_leaf = tree.leaf(first_leaf_index + i)?;
+                if current_leaf != *leaf && current_leaf != [0u8; 32] {
+                    return Err(LightIndexerError::LeafMismatch(
+                        *merkle_tree,
+                        (first_leaf_index + i) as u64,
+                    ));
+                }
+            }
+            return Ok(());
+        }
+        if first_leaf_index > tree.next_index {
+            return Err(LightIndexerError::LeafIndexGap(
+                tree.next_index as u64,
+                *merkle_tree,
+                first_leaf_index as u64,
+            ));
+        }
+        tree.append_batch_subtrees(&leaves.iter().collect::<Vec<_>>())?;
+        Ok(())
+    }
+
+    fn index_merkle_tree_event(
+        &mut self,
+        event: &MerkleTreeEvent,
+    ) -> Result<(), LightIndexerError> {
+        match event {
+            MerkleTreeEvent::V1(event) => {
+                self.state_tree_mut(&Pubkey::from(event.id))?
+                    .apply_changelog_event(event)?;
+            }
+            MerkleTreeEvent::V2(event) => {
+                self.state_tree_mut(&Pubkey::from(event.id))?
+                    .apply_nullifier_event(event)?;
+            }
+            MerkleTreeEvent::V3(event) => self.index_address_event(event)?,
+        }
+        Ok(())
+    }
+
+    fn index_address_event(
+        &mut self,
+        event: &IndexedMerkleTreeEvent,
+    ) -> Result<(), LightIndexerError> {
+        let merkle_tree = Pubkey::from(event.id);
+        for update in event.updates.iter() {
+            let index = update.new_high_element.index as u64;
+            let next_index = self.address_tree(&merkle_tree)?.next_index();
+            if index < next_index {
+                // Already inserted.
+                continue;
+            }
+            if index > next_index {
+                return Err(LightIndexerError::AddressIndexGap(
+                    next_index,
+                    merkle_tree,
+                    index,
+                ));
+            }
+            self.insert_address(&merkle_tree, &update.new_high_element.value)?;
+        }
+        Ok(())
+    }
+
+    fn insert_address(
+        &mut self,
+        merkle_tree: &Pubkey,
+        address: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        let address_tree = self.address_tree_mut(merkle_tree)?;
+        if address_tree.contains(address) {
+            return Ok(());
+        }
+        let index = address_tree.next_index();
+        address_tree.insert(address)?;
+        self.store.put_address(merkle_tree, index, address)
+    }
+
+    fn nullify_leaf(
+        &mut self,
+        merkle_tree: &Pubkey,
+        hash: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        let account = self
+            .store
+            .get_account(hash)?
+            .ok_or_else(|| LightIndexerError::AccountNotFound(bs58::encode(hash).into_string()))?;
+        let leaf_index = account.account.merkle_context.leaf_index as usize;
+        let tree = self.state_tree_mut(merkle_tree)?;
+        // The leaf is zero if it has already been nullified.
+        if tree.leaf(leaf_index)? == *hash {
+            tree.update(&[0u8; 32], leaf_index)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the node stores, then the store with the sequence numbers
+    /// of the trees.
+    fn flush(&mut self) -> Result<(), LightIndexerError> {
+        let mut sequence_numbers = Vec::with_capacity(self.state_trees.len());
+        for (merkle_tree, tree) in self.state_trees.iter_mut() {
+            tree.flush()?;
+            sequence_numbers.push((*merkle_tree, tree.sequence_number as u64));
+        }
+        self.store.flush(&sequence_numbers)?;
+        // Trees are never rolled back further than to the last flush.
+        for tree in self.state_trees.values_mut() {
+            tree.prune_history(tree.sequence_number)?;
+        }
+        Ok(())
+    }
+}
+
+fn tree_error(
+    trees: &HashMap<Pubkey, TreeInfo>,
+    merkle_tree: &Pubkey,
+    expected: &'static str,
+) -> LightIndexerError {
+    if trees.contains_key(merkle_tree) {
+        LightIndexerError::InvalidTreeType(*merkle_tree, expected)
+    } else {
+        LightIndexerError::UnknownMerkleTree(*merkle_tree)
+    }
+}
+
+/// Deserializes the token data of compressed token accounts.
+fn token_data(account: &CompressedAccount) -> Option<TokenData> {
+    let data = account.data.as_ref()?;
+    if account.owner != COMPRESSED_TOKEN_PROGRAM_ID
+        || data.discriminator != TOKEN_COMPRESSED_ACCOUNT_DISCRIMINATOR
+    {
+        return None;
+    }
+    TokenData::deserialize(&mut data.data.as_slice()).ok()
+}
+
+/// Indexer of compressed accounts, state trees and address trees.
+///
+/// Trees have to be added with [`LightIndexer::add_tree`] before their
+/// events are indexed. Clones share the same state, so one clone can index
+/// blocks while others serve queries.
+#[derive(Debug)]
+pub struct LightIndexer<S: IndexerStore = MemoryStore> {
+    state: Arc<RwLock<IndexerState<S>>>,
+}
+
+impl<S: IndexerStore> Clone for LightIndexer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S: IndexerStore> LightIndexer<S> {
+    /// Opens the indexer with the trees and accounts in `store`.
+    pub fn new(store: S) -> Result<Self, LightIndexerError> {
+        Ok(Self {
+            state: Arc::new(RwLock::new(IndexerState::open(store)?)),
+        })
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, IndexerState<S>> {
+        self.state.read().expect("indexer lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, IndexerState<S>> {
+        self.state.write().expect("indexer lock poisoned")
+    }
+
+    pub fn add_tree(&self, tree: TreeInfo) -> Result<(), LightIndexerError> {
+        let mut state = self.write();
+        if state.trees.contains_key(&tree.merkle_tree) {
+            return Err(LightIndexerError::MerkleTreeAlreadyExists(tree.merkle_tree));
+        }
+        state.store.put_tree(&tree)?;
+        state.open_tree(&tree)?;
+        state.flush()
+    }
+
+    pub fn trees(&self) -> Vec<TreeInfo> {
+        self.read().trees.values().copied().collect()
+    }
+
+    pub fn tree(&self, merkle_tree: &Pubkey) -> Option<TreeInfo> {
+        self.read().trees.get(merkle_tree).copied()
+    }
+
+    /// Returns the last fully indexed slot.
+    pub fn last_slot(&self) -> Result<Option<u64>, LightIndexerError> {
+        self.read().store.last_slot()
+    }
+
+    /// Indexes blocks from `source` until it is exhausted.
+    pub async fn run<B: BlockSource>(&self, mut source: B) -> Result<(), LightIndexerError> {
+        while let Some(block) = source.next_block().await? {
+            debug!(
+                "Indexing {} events of slot {}",
+                block.events.len(),
+                block.slot
+            );
+            self.index_block(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Indexes the events of a block and persists the result.
+    pub fn index_block(&self, block: &Block) -> Result<(), LightIndexerError> {
+        let mut state = self.write();
+        if let Some(last_slot) = state.store.last_slot()? {
+            if block.slot < last_slot {
+                return Err(LightIndexerError::SlotOutOfOrder(block.slot, last_slot));
+            }
+        }
+        for event in block.events.iter() {
+            state.index_event(block.slot, event)?;
+        }
+        state.store.put_last_slot(block.slot)?;
+        state.flush()
+    }
+
+    /// Indexes a transaction event, returns the output accounts.
+    pub fn index_transaction_event(
+        &self,
+        slot: u64,
+        event: &PublicTransactionEvent,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        self.write().index_transaction_event(slot, event)
+    }
+
+    pub fn index_merkle_tree_event(
+        &self,
+        event: &MerkleTreeEvent,
+    ) -> Result<(), LightIndexerError> {
+        self.write().index_merkle_tree_event(event)
+    }
+
+    /// Replaces the leaf of the account `hash` with zero bytes, as done by
+    /// the nullification of the account by the forester.
+    pub fn nullify_leaf(
+        &self,
+        merkle_tree: &Pubkey,
+        hash: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        self.write().nullify_leaf(merkle_tree, hash)
+    }
+
+    /// Inserts `address` into the address tree, as done by the update of
+    /// the address tree by the forester. Inserted addresses are skipped.
+    pub fn insert_address(
+        &self,
+        merkle_tree: &Pubkey,
+        address: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        self.write().insert_address(merkle_tree, address)
+    }
+
+    /// Persists all pending writes.
+    pub fn flush(&self) -> Result<(), LightIndexerError> {
+        self.write().flush()
+    }
+
+    pub fn root(&self, merkle_tree: &Pubkey) -> Result<[u8; 32], LightIndexerError> {
+        let state = self.read();
+        match state.tree_info(merkle_tree)?.tree_type {
+            TreeType::State => Ok(state.state_tree(merkle_tree)?.root()?),
+            TreeType::Address => Ok(state.address_tree(merkle_tree)?.merkle_tree.root()),
+        }
+    }
+
+    /// Returns the leaves of the state tree `merkle_tree`, nullified leaves
+    /// are zero.
+    pub fn state_tree_leaves(
+        &self,
+        merkle_tree: &Pubkey,
+    ) -> Result<Vec<[u8; 32]>, LightIndexerError> {
+        let state = self.read();
+        let tree = state.state_tree(merkle_tree)?;
+        (0..tree.next_index)
+            .map(|leaf_index| Ok(tree.leaf(leaf_index)?))
+            .collect()
+    }
+
+    /// Returns a copy of the address tree `merkle_tree`.
+    pub fn address_tree(&self, merkle_tree: &Pubkey) -> Result<AddressTree, LightIndexerError> {
+        Ok(self.read().address_tree(merkle_tree)?.clone())
+    }
+
+    pub fn get_account(&self, hash: &[u8; 32]) -> Result<Option<AccountRecord>, LightIndexerError> {
+        self.read().store.get_account(hash)
+    }
+
+    /// Returns accounts owned by `owner` which aren't nullified, newest
+    /// first.
+    pub fn get_compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        self.read().store.accounts_by_owner(owner)
+    }
+
+    /// Returns token accounts of the token owner `owner` which aren't
+    /// nullified, newest first.
+    pub fn get_compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        self.read().store.token_accounts_by_owner(owner)
+    }
+
+    pub fn get_account_proof(&self, hash: &[u8; 32]) -> Result<AccountProof, LightIndexerError> {
+        let state = self.read();
+        let account = state
+            .store
+            .get_account(hash)?
+            .ok_or_else(|| LightIndexerError::AccountNotFound(bs58::encode(hash).into_string()))?;
+        let merkle_context = account.account.merkle_context;
+        let tree = state.state_tree(&merkle_context.merkle_tree_pubkey)?;
+        let proof = tree
+            .get_proof_of_leaf(merkle_context.leaf_index as usize)?
+            .to_vec();
+        Ok(AccountProof {
+            hash: *hash,
+            merkle_tree: merkle_context.merkle_tree_pubkey,
+            leaf_index: merkle_context.leaf_index,
+            proof,
+            root: tree.root()?,
+            root_seq: tree.sequence_number as u64,
+        })
+    }
+
+    /// Returns the non-inclusion proof of `address`, fails if the address
+    /// exists.
+    pub fn get_new_address_proof(
+        &self,
+        merkle_tree: &Pubkey,
+        address: &[u8; 32],
+    ) -> Result<NewAddressProof, LightIndexerError> {
+        let state = self.read();
+        let address_tree = state.address_tree(merkle_tree)?;
+        let value = BigUint::from_bytes_be(address);
+        let (low_element, low_element_next_value) = address_tree
+            .indexed_array
+            .find_low_element_for_nonexistent(&value)?;
+        let new_element = address_tree
+            .indexed_array
+            .new_element_with_low_element_index(low_element.index, &value)?;
+        let low_element_proof = address_tree
+            .merkle_tree
+            .get_proof_of_leaf(low_element.index, true)
+            .map_err(ReferenceMerkleTreeError::from)?
+            .to_vec();
+        Ok(NewAddressProof {
+            merkle_tree: *merkle_tree,
+            root: address_tree.merkle_tree.root(),
+            root_seq: address_tree.merkle_tree.merkle_tree.sequence_number as u64,
+            low_element,
+            low_element_next_value,
+            low_element_proof,
+            new_element,
+        })
+    }
+}
@@ -0,0 +1,26 @@
+//! Indexer of compressed accounts and Merkle trees.
+//!
+//! [`LightIndexer`] ingests [`PublicTransactionEvent`]s and Merkle tree
+//! events from a [`BlockSource`](source::BlockSource), maintains mirrors of
+//! the state and address trees and tables of compressed (token) accounts, and
+//! serves Merkle proofs and non-inclusion proofs of new addresses.
+//!
+//! State is kept in an [`IndexerStore`](store::IndexerStore):
+//!
+//! * [`MemoryStore`](store::MemoryStore) - everything in memory, for tests,
+//! * [`FileStore`](store::FileStore) - embedded on-disk store, which survives
+//!   restarts.
+//!
+//! `light-test-utils` wraps `LightIndexer` into an implementation of its
+//! `Indexer` trait, so that it can be used in tests in place of
+//! `TestIndexer`.
+//!
+//! [`PublicTransactionEvent`]: light_system_program::sdk::event::PublicTransactionEvent
+
+pub mod error;
+pub mod indexer;
+pub mod source;
+pub mod store;
+
+pub use error::LightIndexerError;
+pub use indexer::{AccountProof, LightIndexer, NewAddressProof};
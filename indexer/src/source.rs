@@ -0,0 +1,46 @@
+use std::future::Future;
+
+use light_sdk::event::{decode_event, LightEvent};
+use tokio::sync::mpsc;
+
+use crate::error::LightIndexerError;
+
+/// Events emitted by the Light Protocol programs in one slot, in the order
+/// of emission.
+#[derive(Debug)]
+pub struct Block {
+    pub slot: u64,
+    pub events: Vec<LightEvent>,
+}
+
+impl Block {
+    /// Decodes the enveloped event data of noop program instructions.
+    pub fn decode(slot: u64, event_data: &[Vec<u8>]) -> Result<Self, LightIndexerError> {
+        let events = event_data
+            .iter()
+            .map(|data| decode_event(data))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { slot, events })
+    }
+}
+
+/// Source of blocks, e.g. an RPC or Geyser subscription. Blocks have to be
+/// returned in the order of slots.
+pub trait BlockSource: Send {
+    /// Returns the next block, `None` once the source is exhausted.
+    fn next_block(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<Block>, LightIndexerError>> + Send;
+}
+
+impl BlockSource for mpsc::Receiver<Block> {
+    async fn next_block(&mut self) -> Result<Option<Block>, LightIndexerError> {
+        Ok(self.recv().await)
+    }
+}
+
+impl BlockSource for mpsc::UnboundedReceiver<Block> {
+    async fn next_block(&mut self) -> Result<Option<Block>, LightIndexerError> {
+        Ok(self.recv().await)
+    }
+}
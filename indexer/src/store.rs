@@ -0,0 +1,551 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use account_compression::utils::constants::{
+    ADDRESS_MERKLE_TREE_CANOPY_DEPTH, ADDRESS_MERKLE_TREE_HEIGHT, STATE_MERKLE_TREE_CANOPY_DEPTH,
+    STATE_MERKLE_TREE_HEIGHT,
+};
+use anchor_lang::{
+    prelude::{borsh, Pubkey},
+    AnchorDeserialize, AnchorSerialize,
+};
+use light_compressed_token::TokenData;
+use light_merkle_tree_reference::node_store::{FileNodeStore, MemoryNodeStore, NodeStore};
+use light_system_program::sdk::compressed_account::CompressedAccountWithMerkleContext;
+
+use crate::error::LightIndexerError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub enum TreeType {
+    State,
+    Address,
+}
+
+/// Merkle tree indexed by the [`LightIndexer`](crate::LightIndexer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub struct TreeInfo {
+    pub tree_type: TreeType,
+    pub merkle_tree: Pubkey,
+    /// Nullifier queue of a state tree, address queue of an address tree.
+    pub queue: Pubkey,
+    /// CPI context account of a state tree.
+    pub cpi_context: Option<Pubkey>,
+    pub height: u32,
+    pub canopy_depth: u32,
+}
+
+impl TreeInfo {
+    /// State tree with the default height and canopy depth.
+    pub fn state(merkle_tree: Pubkey, nullifier_queue: Pubkey, cpi_context: Pubkey) -> Self {
+        Self {
+            tree_type: TreeType::State,
+            merkle_tree,
+            queue: nullifier_queue,
+            cpi_context: Some(cpi_context),
+            height: STATE_MERKLE_TREE_HEIGHT as u32,
+            canopy_depth: STATE_MERKLE_TREE_CANOPY_DEPTH as u32,
+        }
+    }
+
+    /// Address tree with the default height and canopy depth.
+    pub fn address(merkle_tree: Pubkey, queue: Pubkey) -> Self {
+        Self {
+            tree_type: TreeType::Address,
+            merkle_tree,
+            queue,
+            cpi_context: None,
+            height: ADDRESS_MERKLE_TREE_HEIGHT as u32,
+            canopy_depth: ADDRESS_MERKLE_TREE_CANOPY_DEPTH as u32,
+        }
+    }
+}
+
+/// Compressed account created by an output of a transaction.
+#[derive(Debug, Clone, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub struct AccountRecord {
+    pub hash: [u8; 32],
+    pub account: CompressedAccountWithMerkleContext,
+    /// Token data of compressed token accounts.
+    pub token_data: Option<TokenData>,
+    /// Slot of the transaction which created the account.
+    pub slot: u64,
+    /// Slot of the transaction which used the account as input.
+    pub nullified_slot: Option<u64>,
+}
+
+impl AccountRecord {
+    pub fn is_nullified(&self) -> bool {
+        self.nullified_slot.is_some()
+    }
+}
+
+/// Storage of the indexed trees, compressed accounts and addresses.
+///
+/// Nodes of state trees are kept in a [`NodeStore`] per tree, address trees
+/// are rebuilt from their addresses when the indexer is opened.
+///
+/// Writes are persisted by [`IndexerStore::flush`] as one unit, together
+/// with the sequence numbers of the state trees, whose node stores are
+/// flushed before. When the indexer is opened, writes after the last flush
+/// are discarded and the state trees are rolled back to these sequence
+/// numbers, so that trees and accounts are restored at the same flush.
+pub trait IndexerStore: Debug + Send + Sync {
+    type NodeStore: NodeStore + Debug + Send + Sync;
+
+    fn trees(&self) -> Result<Vec<TreeInfo>, LightIndexerError>;
+    fn put_tree(&mut self, tree: &TreeInfo) -> Result<(), LightIndexerError>;
+    /// Opens the node store of the state tree `merkle_tree`.
+    fn node_store(&mut self, merkle_tree: &Pubkey) -> Result<Self::NodeStore, LightIndexerError>;
+
+    fn get_account(&self, hash: &[u8; 32]) -> Result<Option<AccountRecord>, LightIndexerError>;
+    /// Inserts the account or replaces the account with the same hash.
+    fn put_account(&mut self, account: &AccountRecord) -> Result<(), LightIndexerError>;
+    /// Returns accounts owned by `owner` which aren't nullified, newest
+    /// first.
+    fn accounts_by_owner(&self, owner: &Pubkey) -> Result<Vec<AccountRecord>, LightIndexerError>;
+    /// Returns token accounts of the token owner `owner` which aren't
+    /// nullified, newest first.
+    fn token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError>;
+
+    /// Returns the addresses of the address tree `merkle_tree` with their
+    /// leaf indices, ordered by leaf index.
+    fn addresses(&self, merkle_tree: &Pubkey) -> Result<Vec<(u64, [u8; 32])>, LightIndexerError>;
+    fn put_address(
+        &mut self,
+        merkle_tree: &Pubkey,
+        index: u64,
+        address: &[u8; 32],
+    ) -> Result<(), LightIndexerError>;
+
+    /// Returns the last fully indexed slot.
+    fn last_slot(&self) -> Result<Option<u64>, LightIndexerError>;
+    fn put_last_slot(&mut self, slot: u64) -> Result<(), LightIndexerError>;
+
+    /// Returns the sequence numbers of the state trees at the last flush.
+    fn sequence_numbers(&self) -> Result<Vec<(Pubkey, u64)>, LightIndexerError>;
+
+    /// Persists all pending writes and the sequence numbers of the state
+    /// trees, atomically.
+    fn flush(&mut self, sequence_numbers: &[(Pubkey, u64)]) -> Result<(), LightIndexerError>;
+}
+
+/// In-memory store.
+///
+/// Node stores are created empty, so a `MemoryStore` which already contains
+/// state trees can't be reopened by another indexer.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    trees: Vec<TreeInfo>,
+    accounts: HashMap<[u8; 32], AccountRecord>,
+    /// Account hashes by owner, in insertion order.
+    accounts_by_owner: HashMap<Pubkey, Vec<[u8; 32]>>,
+    /// Token account hashes by token owner, in insertion order.
+    token_accounts_by_owner: HashMap<Pubkey, Vec<[u8; 32]>>,
+    addresses: HashMap<Pubkey, BTreeMap<u64, [u8; 32]>>,
+    last_slot: Option<u64>,
+    sequence_numbers: Vec<(Pubkey, u64)>,
+}
+
+impl MemoryStore {
+    fn newest_first(&self, hashes: Option<&Vec<[u8; 32]>>) -> Vec<AccountRecord> {
+        hashes
+            .into_iter()
+            .flatten()
+            .rev()
+            .filter_map(|hash| self.accounts.get(hash))
+            .filter(|account| !account.is_nullified())
+            .cloned()
+            .collect()
+    }
+
+    fn apply(&mut self, operation: StoreOperation) {
+        match operation {
+            StoreOperation::PutTree(tree) => {
+                self.trees.retain(|x| x.merkle_tree != tree.merkle_tree);
+                self.trees.push(tree);
+            }
+            StoreOperation::PutAccount(account) => {
+                let hash = account.hash;
+                if !self.accounts.contains_key(&hash) {
+                    self.accounts_by_owner
+                        .entry(account.account.compressed_account.owner)
+                        .or_default()
+                        .push(hash);
+                    if let Some(token_data) = account.token_data.as_ref() {
+                        self.token_accounts_by_owner
+                            .entry(token_data.owner)
+                            .or_default()
+                            .push(hash);
+                    }
+                }
+                self.accounts.insert(hash, account);
+            }
+            StoreOperation::PutAddress {
+                merkle_tree,
+                index,
+                address,
+            } => {
+                self.addresses
+                    .entry(merkle_tree)
+                    .or_default()
+                    .insert(index, address);
+            }
+            StoreOperation::PutLastSlot(slot) => self.last_slot = Some(slot),
+            StoreOperation::Flush { sequence_numbers } => self.sequence_numbers = sequence_numbers,
+        }
+    }
+
+    /// Operations which recreate the content of the store.
+    fn operations(&self) -> Vec<StoreOperation> {
+        let mut operations = self
+            .trees
+            .iter()
+            .map(|tree| StoreOperation::PutTree(*tree))
+            .collect::<Vec<_>>();
+        // Insert accounts in the original order to keep the order of the
+        // owner indices.
+        for hash in self.accounts_by_owner.values().flatten() {
+            operations.push(StoreOperation::PutAccount(self.accounts[hash].clone()));
+        }
+        for (merkle_tree, addresses) in self.addresses.iter() {
+            for (index, address) in addresses.iter() {
+                operations.push(StoreOperation::PutAddress {
+                    merkle_tree: *merkle_tree,
+                    index: *index,
+                    address: *address,
+                });
+            }
+        }
+        if let Some(slot) = self.last_slot {
+            operations.push(StoreOperation::PutLastSlot(slot));
+        }
+        operations.push(StoreOperation::Flush {
+            sequence_numbers: self.sequence_numbers.clone(),
+        });
+        operations
+    }
+}
+
+impl IndexerStore for MemoryStore {
+    type NodeStore = MemoryNodeStore;
+
+    fn trees(&self) -> Result<Vec<TreeInfo>, LightIndexerError> {
+        Ok(self.trees.clone())
+    }
+
+    fn put_tree(&mut self, tree: &TreeInfo) -> Result<(), LightIndexerError> {
+        self.apply(StoreOperation::PutTree(*tree));
+        Ok(())
+    }
+
+    fn node_store(&mut self, _merkle_tree: &Pubkey) -> Result<MemoryNodeStore, LightIndexerError> {
+        Ok(MemoryNodeStore::default())
+    }
+
+    fn get_account(&self, hash: &[u8; 32]) -> Result<Option<AccountRecord>, LightIndexerError> {
+        Ok(self.accounts.get(hash).cloned())
+    }
+
+    fn put_account(&mut self, account: &AccountRecord) -> Result<(), LightIndexerError> {
+        self.apply(StoreOperation::PutAccount(account.clone()));
+        Ok(())
+    }
+
+    fn accounts_by_owner(&self, owner: &Pubkey) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        Ok(self.newest_first(self.accounts_by_owner.get(owner)))
+    }
+
+    fn token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        Ok(self.newest_first(self.token_accounts_by_owner.get(owner)))
+    }
+
+    fn addresses(&self, merkle_tree: &Pubkey) -> Result<Vec<(u64, [u8; 32])>, LightIndexerError> {
+        Ok(self
+            .addresses
+            .get(merkle_tree)
+            .map(|addresses| {
+                addresses
+                    .iter()
+                    .map(|(index, address)| (*index, *address))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn put_address(
+        &mut self,
+        merkle_tree: &Pubkey,
+        index: u64,
+        address: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        self.apply(StoreOperation::PutAddress {
+            merkle_tree: *merkle_tree,
+            index,
+            address: *address,
+        });
+        Ok(())
+    }
+
+    fn last_slot(&self) -> Result<Option<u64>, LightIndexerError> {
+        Ok(self.last_slot)
+    }
+
+    fn put_last_slot(&mut self, slot: u64) -> Result<(), LightIndexerError> {
+        self.apply(StoreOperation::PutLastSlot(slot));
+        Ok(())
+    }
+
+    fn sequence_numbers(&self) -> Result<Vec<(Pubkey, u64)>, LightIndexerError> {
+        Ok(self.sequence_numbers.clone())
+    }
+
+    fn flush(&mut self, sequence_numbers: &[(Pubkey, u64)]) -> Result<(), LightIndexerError> {
+        self.apply(StoreOperation::Flush {
+            sequence_numbers: sequence_numbers.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+/// Write operation of a [`FileStore`], records of the log.
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+enum StoreOperation {
+    PutTree(TreeInfo),
+    PutAccount(AccountRecord),
+    PutAddress {
+        merkle_tree: Pubkey,
+        index: u64,
+        address: [u8; 32],
+    },
+    PutLastSlot(u64),
+    /// End of the writes persisted by a flush.
+    Flush {
+        sequence_numbers: Vec<(Pubkey, u64)>,
+    },
+}
+
+/// On-disk store, backed by a directory:
+///
+/// * `log` - append-only log of write operations, each record is prefixed
+///   with its length as `u32`. The log is replayed into a [`MemoryStore`]
+///   on open, which serves all reads.
+/// * `trees/<pubkey>` - [`FileNodeStore`] of every state tree.
+///
+/// A flush appends a record with the sequence numbers of the state trees and
+/// syncs the log, it is the only point at which writes become durable.
+/// Records after the last flush record are discarded on open.
+///
+/// The log keeps superseded records (e.g. accounts before they were
+/// nullified) until it is rewritten with [`FileStore::compact`].
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    log: File,
+    state: MemoryStore,
+    /// Whether records have been written since the last flush.
+    unflushed: bool,
+}
+
+impl FileStore {
+    const LOG_FILE: &'static str = "log";
+    const TREES_DIR: &'static str = "trees";
+
+    /// Opens the store in the directory `path`, creates the directory if it
+    /// doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, LightIndexerError> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(path.join(Self::TREES_DIR))?;
+        let mut log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.join(Self::LOG_FILE))?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+
+        let mut state = MemoryStore::default();
+        let mut unflushed_operations = Vec::new();
+        let mut offset = 0;
+        let mut flushed_len = 0;
+        while let Some(len_bytes) = bytes.get(offset..offset + 4) {
+            let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+            let Some(record) = bytes.get(offset + 4..offset + 4 + len) else {
+                break;
+            };
+            let operation = StoreOperation::try_from_slice(record)
+                .map_err(|_| LightIndexerError::CorruptedStore)?;
+            offset += 4 + len;
+            if let StoreOperation::Flush { .. } = operation {
+                for operation in unflushed_operations.drain(..) {
+                    state.apply(operation);
+                }
+                state.apply(operation);
+                flushed_len = offset;
+            } else {
+                unflushed_operations.push(operation);
+            }
+        }
+        // Records after the last flush and a partially written trailing
+        // record (e.g. after a crash) are discarded, so that new records are
+        // appended after the last flush.
+        log.set_len(flushed_len as u64)?;
+        log.sync_data()?;
+
+        Ok(Self {
+            path,
+            log,
+            state,
+            unflushed: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rewrites the log with only the current content of the store. Fails
+    /// if there are writes which haven't been flushed.
+    pub fn compact(&mut self) -> Result<(), LightIndexerError> {
+        if self.unflushed {
+            return Err(LightIndexerError::UnflushedWrites);
+        }
+        let mut bytes = Vec::new();
+        for operation in self.state.operations() {
+            write_record(&mut bytes, &operation)?;
+        }
+        // Write to a temporary file and rename it, so that the log is never
+        // partially rewritten.
+        let tmp_path = self.path.join(format!("{}.tmp", Self::LOG_FILE));
+        let mut tmp_file = File::create(&tmp_path)?;
+        tmp_file.write_all(&bytes)?;
+        tmp_file.sync_all()?;
+        fs::rename(&tmp_path, self.path.join(Self::LOG_FILE))?;
+        File::open(&self.path)?.sync_all()?;
+        self.log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(self.path.join(Self::LOG_FILE))?;
+        Ok(())
+    }
+
+    fn write(&mut self, operation: StoreOperation) -> Result<(), LightIndexerError> {
+        let mut bytes = Vec::new();
+        write_record(&mut bytes, &operation)?;
+        self.log.seek(SeekFrom::End(0))?;
+        self.log.write_all(&bytes)?;
+        self.state.apply(operation);
+        self.unflushed = true;
+        Ok(())
+    }
+}
+
+fn write_record(bytes: &mut Vec<u8>, operation: &StoreOperation) -> Result<(), LightIndexerError> {
+    let record = operation.try_to_vec()?;
+    bytes.extend_from_slice(&(record.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&record);
+    Ok(())
+}
+
+impl IndexerStore for FileStore {
+    type NodeStore = FileNodeStore;
+
+    fn trees(&self) -> Result<Vec<TreeInfo>, LightIndexerError> {
+        self.state.trees()
+    }
+
+    fn put_tree(&mut self, tree: &TreeInfo) -> Result<(), LightIndexerError> {
+        // Nodes of a tree which was added after the last flush are
+        // discarded, the tree is indexed from the start.
+        let tree_path = self
+            .path
+            .join(Self::TREES_DIR)
+            .join(tree.merkle_tree.to_string());
+        if tree_path.exists()
+            && self
+                .state
+                .trees
+                .iter()
+                .all(|x| x.merkle_tree != tree.merkle_tree)
+        {
+            fs::remove_dir_all(tree_path)?;
+        }
+        self.write(StoreOperation::PutTree(*tree))
+    }
+
+    fn node_store(&mut self, merkle_tree: &Pubkey) -> Result<FileNodeStore, LightIndexerError> {
+        Ok(FileNodeStore::open(
+            self.path
+                .join(Self::TREES_DIR)
+                .join(merkle_tree.to_string()),
+        )?)
+    }
+
+    fn get_account(&self, hash: &[u8; 32]) -> Result<Option<AccountRecord>, LightIndexerError> {
+        self.state.get_account(hash)
+    }
+
+    fn put_account(&mut self, account: &AccountRecord) -> Result<(), LightIndexerError> {
+        self.write(StoreOperation::PutAccount(account.clone()))
+    }
+
+    fn accounts_by_owner(&self, owner: &Pubkey) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        self.state.accounts_by_owner(owner)
+    }
+
+    fn token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<AccountRecord>, LightIndexerError> {
+        self.state.token_accounts_by_owner(owner)
+    }
+
+    fn addresses(&self, merkle_tree: &Pubkey) -> Result<Vec<(u64, [u8; 32])>, LightIndexerError> {
+        self.state.addresses(merkle_tree)
+    }
+
+    fn put_address(
+        &mut self,
+        merkle_tree: &Pubkey,
+        index: u64,
+        address: &[u8; 32],
+    ) -> Result<(), LightIndexerError> {
+        self.write(StoreOperation::PutAddress {
+            merkle_tree: *merkle_tree,
+            index,
+            address: *address,
+        })
+    }
+
+    fn last_slot(&self) -> Result<Option<u64>, LightIndexerError> {
+        self.state.last_slot()
+    }
+
+    fn put_last_slot(&mut self, slot: u64) -> Result<(), LightIndexerError> {
+        self.write(StoreOperation::PutLastSlot(slot))
+    }
+
+    fn sequence_numbers(&self) -> Result<Vec<(Pubkey, u64)>, LightIndexerError> {
+        self.state.sequence_numbers()
+    }
+
+    fn flush(&mut self, sequence_numbers: &[(Pubkey, u64)]) -> Result<(), LightIndexerError> {
+        self.write(StoreOperation::Flush {
+            sequence_numbers: sequence_numbers.to_vec(),
+        })?;
+        self.log.sync_data()?;
+        self.unflushed = false;
+        Ok(())
+    }
+}
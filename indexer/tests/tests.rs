@@ -0,0 +1,551 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::{prelude::Pubkey, AnchorSerialize};
+use light_compressed_token::{
+    constants::TOKEN_COMPRESSED_ACCOUNT_DISCRIMINATOR, token_data::AccountState, TokenData,
+};
+use light_concurrent_merkle_tree::event::{
+    IndexedMerkleTreeEvent, IndexedMerkleTreeUpdate, MerkleTreeEvent, NullifierEvent,
+    RawIndexedElement,
+};
+use light_hasher::Poseidon;
+use light_indexed_merkle_tree::{array::IndexedArray, reference::IndexedMerkleTree};
+use light_indexer::{
+    source::Block,
+    store::{FileStore, IndexerStore, MemoryStore, TreeInfo},
+    LightIndexer, LightIndexerError,
+};
+use light_merkle_tree_reference::MerkleTree;
+use light_sdk::event::LightEvent;
+use light_system_program::{
+    sdk::{
+        compressed_account::{CompressedAccount, CompressedAccountData},
+        event::PublicTransactionEvent,
+    },
+    OutputCompressedAccountWithPackedContext,
+};
+use num_bigint::BigUint;
+use tokio::sync::mpsc;
+
+const HEIGHT: usize = 26;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!(
+        "light-indexer-{}-{}-{}",
+        name,
+        std::process::id(),
+        nanos
+    ))
+}
+
+fn state_tree() -> TreeInfo {
+    TreeInfo::state(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    )
+}
+
+fn compressed_account(owner: Pubkey, lamports: u64) -> CompressedAccount {
+    CompressedAccount {
+        owner,
+        lamports,
+        address: None,
+        data: None,
+    }
+}
+
+/// Returns an event which spends `inputs` and appends `outputs` to
+/// `merkle_tree` starting at `first_leaf_index`.
+fn transaction_event(
+    merkle_tree: Pubkey,
+    inputs: &[[u8; 32]],
+    outputs: &[CompressedAccount],
+    first_leaf_index: u32,
+) -> PublicTransactionEvent {
+    let output_leaf_indices = (0..outputs.len() as u32)
+        .map(|i| first_leaf_index + i)
+        .collect::<Vec<_>>();
+    PublicTransactionEvent {
+        input_compressed_account_hashes: inputs.to_vec(),
+        output_compressed_account_hashes: outputs
+            .iter()
+            .zip(output_leaf_indices.iter())
+            .map(|(account, leaf_index)| {
+                account.hash::<Poseidon>(&merkle_tree, leaf_index).unwrap()
+            })
+            .collect(),
+        output_compressed_accounts: outputs
+            .iter()
+            .map(|account| OutputCompressedAccountWithPackedContext {
+                compressed_account: account.clone(),
+                merkle_tree_index: 0,
+            })
+            .collect(),
+        output_leaf_indices,
+        pubkey_array: vec![merkle_tree],
+        ..Default::default()
+    }
+}
+
+fn address(i: u64) -> [u8; 32] {
+    let mut address = [0u8; 32];
+    address[24..].copy_from_slice(&(i * 1_000_000_007).to_be_bytes());
+    address
+}
+
+fn address_event(merkle_tree: Pubkey, index: usize, address: [u8; 32]) -> MerkleTreeEvent {
+    MerkleTreeEvent::V3(IndexedMerkleTreeEvent {
+        id: merkle_tree.to_bytes(),
+        updates: vec![IndexedMerkleTreeUpdate {
+            new_low_element: RawIndexedElement::default(),
+            new_low_element_hash: [0u8; 32],
+            new_high_element: RawIndexedElement {
+                value: address,
+                next_index: 0,
+                next_value: [0u8; 32],
+                index,
+            },
+            new_high_element_hash: [0u8; 32],
+        }],
+        seq: 0,
+    })
+}
+
+/// Tests:
+/// 1. blocks are indexed from a block source
+/// 2. inputs are nullified, outputs are returned newest first
+/// 3. roots and proofs equal the reference tree
+/// 4. indexing an event again doesn't change the state
+/// 5. blocks older than the last slot and leaf index gaps fail
+#[tokio::test]
+async fn index_transaction_events() {
+    let indexer = LightIndexer::new(MemoryStore::default()).unwrap();
+    let tree = state_tree();
+    indexer.add_tree(tree).unwrap();
+    assert!(matches!(
+        indexer.add_tree(tree),
+        Err(LightIndexerError::MerkleTreeAlreadyExists(_))
+    ));
+    let owner_a = Pubkey::new_unique();
+    let owner_b = Pubkey::new_unique();
+
+    // 1. index two blocks
+    let event_1 = transaction_event(
+        tree.merkle_tree,
+        &[],
+        &[
+            compressed_account(owner_a, 1),
+            compressed_account(owner_a, 2),
+            compressed_account(owner_b, 3),
+        ],
+        0,
+    );
+    let spent_hash = event_1.output_compressed_account_hashes[0];
+    let event_2 = transaction_event(
+        tree.merkle_tree,
+        &[spent_hash],
+        &[compressed_account(owner_b, 1)],
+        3,
+    );
+    let (sender, receiver) = mpsc::channel(2);
+    sender
+        .send(Block {
+            slot: 1,
+            events: vec![LightEvent::PublicTransaction(event_1.clone())],
+        })
+        .await
+        .unwrap();
+    sender
+        .send(Block {
+            slot: 2,
+            events: vec![LightEvent::PublicTransaction(event_2.clone())],
+        })
+        .await
+        .unwrap();
+    drop(sender);
+    indexer.run(receiver).await.unwrap();
+    assert_eq!(indexer.last_slot().unwrap(), Some(2));
+
+    // 2. accounts
+    let accounts_a = indexer.get_compressed_accounts_by_owner(&owner_a).unwrap();
+    assert_eq!(accounts_a.len(), 1);
+    assert_eq!(accounts_a[0].account.compressed_account.lamports, 2);
+    assert_eq!(
+        accounts_a[0].account.merkle_context.nullifier_queue_pubkey,
+        tree.queue
+    );
+    let accounts_b = indexer.get_compressed_accounts_by_owner(&owner_b).unwrap();
+    assert_eq!(
+        accounts_b
+            .iter()
+            .map(|account| account.account.merkle_context.leaf_index)
+            .collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+    let spent_account = indexer.get_account(&spent_hash).unwrap().unwrap();
+    assert_eq!(spent_account.slot, 1);
+    assert_eq!(spent_account.nullified_slot, Some(2));
+
+    // 3. roots and proofs
+    let mut reference_tree = MerkleTree::<Poseidon>::new(HEIGHT, 0);
+    let hashes = [
+        event_1.output_compressed_account_hashes.as_slice(),
+        event_2.output_compressed_account_hashes.as_slice(),
+    ]
+    .concat();
+    for hash in hashes.iter() {
+        reference_tree.append(hash).unwrap();
+    }
+    assert_eq!(
+        indexer.root(&tree.merkle_tree).unwrap(),
+        reference_tree.root()
+    );
+    for (leaf_index, hash) in hashes.iter().enumerate() {
+        let proof = indexer.get_account_proof(hash).unwrap();
+        assert_eq!(proof.leaf_index as usize, leaf_index);
+        assert_eq!(proof.root, reference_tree.root());
+        // Outputs of every event are appended in one batch.
+        assert_eq!(proof.root_seq, 2);
+        assert_eq!(
+            proof.proof,
+            reference_tree
+                .get_proof_of_leaf(leaf_index, true)
+                .unwrap()
+                .to_vec()
+        );
+    }
+    assert!(matches!(
+        indexer.get_account_proof(&[1u8; 32]),
+        Err(LightIndexerError::AccountNotFound(_))
+    ));
+
+    // 4. idempotence
+    indexer.index_transaction_event(2, &event_2).unwrap();
+    assert_eq!(
+        indexer.root(&tree.merkle_tree).unwrap(),
+        reference_tree.root()
+    );
+    assert_eq!(
+        indexer
+            .get_compressed_accounts_by_owner(&owner_b)
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // 5. failures
+    assert!(matches!(
+        indexer.index_block(&Block {
+            slot: 1,
+            events: Vec::new(),
+        }),
+        Err(LightIndexerError::SlotOutOfOrder(1, 2))
+    ));
+    let gap_event = transaction_event(tree.merkle_tree, &[], &[compressed_account(owner_a, 1)], 5);
+    assert!(matches!(
+        indexer.index_transaction_event(3, &gap_event),
+        Err(LightIndexerError::LeafIndexGap(4, _, 5))
+    ));
+    let unknown_tree_event = transaction_event(
+        Pubkey::new_unique(),
+        &[],
+        &[compressed_account(owner_a, 1)],
+        0,
+    );
+    assert!(matches!(
+        indexer.index_transaction_event(3, &unknown_tree_event),
+        Err(LightIndexerError::UnknownMerkleTree(_))
+    ));
+}
+
+/// Tests that token data of compressed token accounts is indexed, other
+/// accounts of the token program are indexed without token data.
+#[test]
+fn index_token_accounts() {
+    let indexer = LightIndexer::new(MemoryStore::default()).unwrap();
+    let tree = state_tree();
+    indexer.add_tree(tree).unwrap();
+    let token_data = TokenData {
+        mint: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        amount: 100,
+        delegate: None,
+        state: AccountState::Initialized,
+    };
+    let token_account = CompressedAccount {
+        owner: light_compressed_token::ID,
+        lamports: 0,
+        address: None,
+        data: Some(CompressedAccountData {
+            discriminator: TOKEN_COMPRESSED_ACCOUNT_DISCRIMINATOR,
+            data: token_data.try_to_vec().unwrap(),
+            data_hash: [1u8; 32],
+        }),
+    };
+    let other_account = CompressedAccount {
+        data: Some(CompressedAccountData {
+            discriminator: [1u8; 8],
+            data: Vec::new(),
+            data_hash: [2u8; 32],
+        }),
+        ..token_account.clone()
+    };
+    let event = transaction_event(tree.merkle_tree, &[], &[token_account, other_account], 0);
+    let accounts = indexer.index_transaction_event(0, &event).unwrap();
+    assert_eq!(accounts[0].token_data, Some(token_data));
+    assert_eq!(accounts[1].token_data, None);
+
+    let token_accounts = indexer
+        .get_compressed_token_accounts_by_owner(&token_data.owner)
+        .unwrap();
+    assert_eq!(token_accounts, vec![accounts[0].clone()]);
+    assert_eq!(
+        indexer
+            .get_compressed_accounts_by_owner(&light_compressed_token::ID)
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+/// Tests:
+/// 1. nullifier events and forester nullifications zero leaves
+/// 2. addresses are inserted from events and forester updates
+/// 3. new address proofs equal the reference indexed tree
+/// 4. proofs of existing addresses and address index gaps fail
+#[test]
+fn nullify_leaves_and_insert_addresses() {
+    let indexer = LightIndexer::new(MemoryStore::default()).unwrap();
+    let tree = state_tree();
+    let address_tree = TreeInfo::address(Pubkey::new_unique(), Pubkey::new_unique());
+    indexer.add_tree(tree).unwrap();
+    indexer.add_tree(address_tree).unwrap();
+    let owner = Pubkey::new_unique();
+    let event = transaction_event(
+        tree.merkle_tree,
+        &[],
+        &[compressed_account(owner, 1), compressed_account(owner, 2)],
+        0,
+    );
+    indexer.index_transaction_event(0, &event).unwrap();
+    let hashes = &event.output_compressed_account_hashes;
+
+    // 1. nullification
+    let mut reference_tree = MerkleTree::<Poseidon>::new(HEIGHT, 0);
+    for hash in hashes.iter() {
+        reference_tree.append(hash).unwrap();
+    }
+    indexer
+        .index_merkle_tree_event(&MerkleTreeEvent::V2(NullifierEvent {
+            id: tree.merkle_tree.to_bytes(),
+            nullified_leaves_indices: vec![0],
+            seq: 2,
+        }))
+        .unwrap();
+    reference_tree.update(&[0u8; 32], 0).unwrap();
+    assert_eq!(
+        indexer.root(&tree.merkle_tree).unwrap(),
+        reference_tree.root()
+    );
+    indexer.nullify_leaf(&tree.merkle_tree, &hashes[1]).unwrap();
+    indexer.nullify_leaf(&tree.merkle_tree, &hashes[1]).unwrap();
+    reference_tree.update(&[0u8; 32], 1).unwrap();
+    assert_eq!(
+        indexer.root(&tree.merkle_tree).unwrap(),
+        reference_tree.root()
+    );
+    assert!(matches!(
+        indexer.nullify_leaf(&address_tree.merkle_tree, &hashes[1]),
+        Err(LightIndexerError::InvalidTreeType(_, "state"))
+    ));
+
+    // 2. addresses
+    let mut reference_tree = IndexedMerkleTree::<Poseidon, usize>::new(HEIGHT, 0).unwrap();
+    reference_tree.init().unwrap();
+    let mut reference_array = IndexedArray::<Poseidon, usize>::default();
+    reference_array.init().unwrap();
+    indexer
+        .index_merkle_tree_event(&address_event(address_tree.merkle_tree, 2, address(3)))
+        .unwrap();
+    indexer
+        .insert_address(&address_tree.merkle_tree, &address(1))
+        .unwrap();
+    // Already inserted by the forester.
+    indexer
+        .index_merkle_tree_event(&address_event(address_tree.merkle_tree, 3, address(1)))
+        .unwrap();
+    for i in [3, 1] {
+        reference_tree
+            .append(&BigUint::from_bytes_be(&address(i)), &mut reference_array)
+            .unwrap();
+    }
+    assert_eq!(
+        indexer.root(&address_tree.merkle_tree).unwrap(),
+        reference_tree.root()
+    );
+
+    // 3. new address proof
+    let new_address = BigUint::from_bytes_be(&address(2));
+    let proof = indexer
+        .get_new_address_proof(&address_tree.merkle_tree, &address(2))
+        .unwrap();
+    let (low_element, low_element_next_value) = reference_array
+        .find_low_element_for_nonexistent(&new_address)
+        .unwrap();
+    assert_eq!(proof.root, reference_tree.root());
+    assert_eq!(proof.low_element.index, low_element.index);
+    assert_eq!(proof.low_element.value, BigUint::from_bytes_be(&address(1)));
+    assert_eq!(proof.low_element_next_value, low_element_next_value);
+    assert_eq!(
+        proof.low_element_proof,
+        reference_tree
+            .get_proof_of_leaf(low_element.index, true)
+            .unwrap()
+            .to_vec()
+    );
+    assert_eq!(proof.new_element.new_element.value, new_address);
+    assert_eq!(proof.new_element.new_element.index, 4);
+
+    // 4. failures
+    assert!(indexer
+        .get_new_address_proof(&address_tree.merkle_tree, &address(1))
+        .is_err());
+    assert!(matches!(
+        indexer.index_merkle_tree_event(&address_event(address_tree.merkle_tree, 5, address(5))),
+        Err(LightIndexerError::AddressIndexGap(4, _, 5))
+    ));
+}
+
+fn assert_store_content<S: IndexerStore>(
+    indexer: &LightIndexer<S>,
+    roots: &[(Pubkey, [u8; 32])],
+    owner: &Pubkey,
+    num_accounts: usize,
+) {
+    for (merkle_tree, root) in roots.iter() {
+        assert_eq!(indexer.root(merkle_tree).unwrap(), *root);
+    }
+    assert_eq!(
+        indexer
+            .get_compressed_accounts_by_owner(owner)
+            .unwrap()
+            .len(),
+        num_accounts
+    );
+    assert_eq!(indexer.last_slot().unwrap(), Some(2));
+}
+
+/// Tests:
+/// 1. trees, accounts, addresses and the last slot are restored on open
+/// 2. records after the last flush and a partially written record are
+///    discarded
+/// 3. trees are rolled back to the last flush
+/// 4. compaction fails with unflushed writes, the compacted log restores the
+///    same state
+#[test]
+fn file_store_persistence() {
+    let dir = temp_dir("persistence");
+    let tree = state_tree();
+    let address_tree = TreeInfo::address(Pubkey::new_unique(), Pubkey::new_unique());
+    let owner = Pubkey::new_unique();
+    let roots = {
+        let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+        indexer.add_tree(tree).unwrap();
+        indexer.add_tree(address_tree).unwrap();
+        let event_1 = transaction_event(
+            tree.merkle_tree,
+            &[],
+            &[compressed_account(owner, 1), compressed_account(owner, 2)],
+            0,
+        );
+        let event_2 = transaction_event(
+            tree.merkle_tree,
+            &[event_1.output_compressed_account_hashes[0]],
+            &[compressed_account(owner, 3)],
+            2,
+        );
+        indexer
+            .index_block(&Block {
+                slot: 1,
+                events: vec![
+                    LightEvent::PublicTransaction(event_1),
+                    LightEvent::MerkleTree(address_event(address_tree.merkle_tree, 2, address(1))),
+                ],
+            })
+            .unwrap();
+        indexer
+            .index_block(&Block {
+                slot: 2,
+                events: vec![LightEvent::PublicTransaction(event_2)],
+            })
+            .unwrap();
+        [tree.merkle_tree, address_tree.merkle_tree]
+            .map(|merkle_tree| (merkle_tree, indexer.root(&merkle_tree).unwrap()))
+    };
+
+    // 1. reopen
+    let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+    assert_eq!(indexer.tree(&tree.merkle_tree), Some(tree));
+    assert_eq!(indexer.tree(&address_tree.merkle_tree), Some(address_tree));
+    assert_store_content(&indexer, &roots, &owner, 2);
+    assert!(indexer
+        .get_new_address_proof(&address_tree.merkle_tree, &address(1))
+        .is_err());
+    drop(indexer);
+
+    // 2. unflushed and partially written records
+    let mut store = FileStore::open(&dir).unwrap();
+    store.put_last_slot(3).unwrap();
+    drop(store);
+    let mut log = OpenOptions::new()
+        .append(true)
+        .open(dir.join("log"))
+        .unwrap();
+    log.write_all(&[100, 0, 0, 0, 1, 2]).unwrap();
+    drop(log);
+    let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+    assert_store_content(&indexer, &roots, &owner, 2);
+    drop(indexer);
+
+    // 3. tree ahead of the last flush
+    let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+    let event_3 = transaction_event(tree.merkle_tree, &[], &[compressed_account(owner, 4)], 3);
+    indexer.index_transaction_event(3, &event_3).unwrap();
+    assert_ne!(indexer.root(&tree.merkle_tree).unwrap(), roots[0].1);
+    drop(indexer);
+    let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+    assert_store_content(&indexer, &roots, &owner, 2);
+    assert!(indexer
+        .get_account(&event_3.output_compressed_account_hashes[0])
+        .unwrap()
+        .is_none());
+    drop(indexer);
+
+    // 4. compaction
+    let log_len = std::fs::metadata(dir.join("log")).unwrap().len();
+    let mut store = FileStore::open(&dir).unwrap();
+    store.put_last_slot(3).unwrap();
+    assert!(matches!(
+        store.compact(),
+        Err(LightIndexerError::UnflushedWrites)
+    ));
+    drop(store);
+    let mut store = FileStore::open(&dir).unwrap();
+    store.compact().unwrap();
+    assert!(std::fs::metadata(dir.join("log")).unwrap().len() < log_len);
+    drop(store);
+    let indexer = LightIndexer::new(FileStore::open(&dir).unwrap()).unwrap();
+    assert_store_content(&indexer, &roots, &owner, 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
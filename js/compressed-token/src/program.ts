@@ -22,6 +22,7 @@ import {
     validateSameOwner,
     validateSufficientBalance,
     defaultTestStateTreeAccounts,
+    getVerifyingKeyAccountMeta,
 } from '@lightprotocol/stateless.js';
 import {
     MINT_SIZE,
@@ -603,6 +604,13 @@ export class CompressedTokenProgram {
             tokenTransferOutputs,
         });
 
+        const verifyingKeyAccount = getVerifyingKeyAccountMeta(
+            inputCompressedTokenAccounts.length,
+            0,
+        );
+        if (verifyingKeyAccount)
+            remainingAccountMetas.push(verifyingKeyAccount);
+
         const { mint, currentOwner } = parseTokenData(
             inputCompressedTokenAccounts,
         );
@@ -766,6 +774,13 @@ export class CompressedTokenProgram {
             tokenTransferOutputs: tokenTransferOutputs,
         });
 
+        const verifyingKeyAccount = getVerifyingKeyAccountMeta(
+            inputCompressedTokenAccounts.length,
+            0,
+        );
+        if (verifyingKeyAccount)
+            remainingAccountMetas.push(verifyingKeyAccount);
+
         const { mint, currentOwner } = parseTokenData(
             inputCompressedTokenAccounts,
         );
@@ -1,6 +1,6 @@
 import { BN } from '@coral-xyz/anchor';
 import { Buffer } from 'buffer';
-import { ConfirmOptions, PublicKey } from '@solana/web3.js';
+import { AccountMeta, ConfirmOptions, PublicKey } from '@solana/web3.js';
 
 export const FIELD_SIZE = new BN(
     '21888242871839275222246405745257275088548364400416034343698204186575808495617',
@@ -15,6 +15,34 @@ export const lightProgram = 'H5sFv8VwWmjxHYS2GB4fTDsK7uTtnRT4WiixtHrET3bN';
 export const accountCompressionProgram = // also: merkletree program
     'CbjvJc1SNx1aav8tU49dJGHu8EUdzQJSMtkjDmV8miqK';
 
+export const lightRegistryProgram =
+    '7Z9Yuy3HkBCc2Wf3xzMGnz6qpV4n7ciwcoEMGKqhAnj1';
+
+/**
+ * Returns the verifying key account, which instructions with a proof of
+ * `numLeaves` input compressed accounts and `numAddresses` new addresses have
+ * to pass as a remaining account. `null` if nothing is proven.
+ */
+export const getVerifyingKeyAccountMeta = (
+    numLeaves: number,
+    numAddresses: number,
+): AccountMeta | null => {
+    if (numLeaves === 0 && numAddresses === 0) return null;
+    /// Circuit type as in `CircuitShape` of the registry program.
+    const circuitType = numAddresses === 0 ? 0 : numLeaves === 0 ? 1 : 2;
+    const shape = Buffer.from([
+        circuitType,
+        DEFAULT_MERKLE_TREE_HEIGHT,
+        numLeaves,
+        numAddresses,
+    ]);
+    const [pubkey] = PublicKey.findProgramAddressSync(
+        [Buffer.from('verifying_key'), shape],
+        new PublicKey(lightRegistryProgram),
+    );
+    return { pubkey, isWritable: false, isSigner: false };
+};
+
 export const getRegisteredProgramPda = () =>
     new PublicKey('ApB3ASTFzS1RrccrB1cnNDPAAWrm1X4KSeNh2G3qSaxx'); // TODO: better labelling. gov authority pda
 
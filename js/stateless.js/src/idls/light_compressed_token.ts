@@ -1129,6 +1129,14 @@ export type LightCompressedToken = {
                         ];
                         type: 'u16';
                     },
+                    {
+                        name: 'closeAddress';
+                        docs: [
+                            'Closes the address of the compressed account. A closed address cannot',
+                            'be assigned to an output compressed account.',
+                        ];
+                        type: 'bool';
+                    },
                 ];
             };
         },
@@ -1249,6 +1257,14 @@ export type LightCompressedToken = {
                             option: 'bytes';
                         };
                     },
+                    {
+                        name: 'closedAddresses';
+                        type: {
+                            vec: {
+                                array: ['u8', 32];
+                            };
+                        };
+                    },
                 ];
             };
         },
@@ -2500,6 +2516,14 @@ export const IDL: LightCompressedToken = {
                         ],
                         type: 'u16',
                     },
+                    {
+                        name: 'closeAddress',
+                        docs: [
+                            'Closes the address of the compressed account. A closed address cannot',
+                            'be assigned to an output compressed account.',
+                        ],
+                        type: 'bool',
+                    },
                 ],
             },
         },
@@ -2621,6 +2645,14 @@ export const IDL: LightCompressedToken = {
                             option: 'bytes',
                         },
                     },
+                    {
+                        name: 'closedAddresses',
+                        type: {
+                            vec: {
+                                array: ['u8', 32],
+                            },
+                        },
+                    },
                 ],
             },
         },
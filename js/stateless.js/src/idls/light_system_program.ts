@@ -322,6 +322,15 @@ export type LightSystemProgram = {
                             };
                         };
                     },
+                    {
+                        docs: [
+                            'Slot in which the current cpi context session has been opened.',
+                            'Appended after the context to keep the layout of existing accounts,',
+                            'the slot is set whenever a session is opened.',
+                        ];
+                        name: 'slot';
+                        type: 'u64';
+                    },
                 ];
             };
         },
@@ -731,6 +740,14 @@ export type LightSystemProgram = {
                         ];
                         type: 'u16';
                     },
+                    {
+                        name: 'closeAddress';
+                        docs: [
+                            'Closes the address of the compressed account. A closed address cannot',
+                            'be assigned to an output compressed account.',
+                        ];
+                        type: 'bool';
+                    },
                 ];
             };
         },
@@ -841,6 +858,14 @@ export type LightSystemProgram = {
                             option: 'bytes';
                         };
                     },
+                    {
+                        name: 'closedAddresses';
+                        type: {
+                            vec: {
+                                array: ['u8', 32];
+                            };
+                        };
+                    },
                 ];
             };
         },
@@ -1011,6 +1036,41 @@ export type LightSystemProgram = {
             name: 'OutputMerkleTreeIndicesNotInOrder';
             msg: 'Output merkle tree indices are not in ascending order.';
         },
+        {
+            code: 6033;
+            name: 'AddressOwnerMismatch';
+            msg: 'Address of an input compressed account can only be assigned to an output of the same owner.';
+        },
+        {
+            code: 6034;
+            name: 'AddressAssignedToMultipleOutputs';
+            msg: 'Address is assigned to more than one output compressed account.';
+        },
+        {
+            code: 6035;
+            name: 'CpiContextSlotMismatch';
+            msg: 'CpiContextSlotMismatch';
+        },
+        {
+            code: 6036;
+            name: 'VerifyingKeyAccountMissing';
+            msg: 'The verifying key account of the proof is missing.';
+        },
+        {
+            code: 6037;
+            name: 'VerifyingKeyNotActivated';
+            msg: 'VerifyingKeyNotActivated';
+        },
+        {
+            code: 6038;
+            name: 'CloseAddressWithoutAddress';
+            msg: 'Only the address of a compressed account with an address can be closed.';
+        },
+        {
+            code: 6039;
+            name: 'EventTooLarge';
+            msg: 'Event data exceeds the max instruction data of a cpi.';
+        },
     ];
 };
 
@@ -1338,6 +1398,15 @@ export const IDL: LightSystemProgram = {
                             },
                         },
                     },
+                    {
+                        docs: [
+                            'Slot in which the current cpi context session has been opened.',
+                            'Appended after the context to keep the layout of existing accounts,',
+                            'the slot is set whenever a session is opened.',
+                        ],
+                        name: 'slot',
+                        type: 'u64',
+                    },
                 ],
             },
         },
@@ -1751,6 +1820,14 @@ export const IDL: LightSystemProgram = {
                         ],
                         type: 'u16',
                     },
+                    {
+                        name: 'closeAddress',
+                        docs: [
+                            'Closes the address of the compressed account. A closed address cannot',
+                            'be assigned to an output compressed account.',
+                        ],
+                        type: 'bool',
+                    },
                 ],
             },
         },
@@ -1862,6 +1939,14 @@ export const IDL: LightSystemProgram = {
                             option: 'bytes',
                         },
                     },
+                    {
+                        name: 'closedAddresses',
+                        type: {
+                            vec: {
+                                array: ['u8', 32],
+                            },
+                        },
+                    },
                 ],
             },
         },
@@ -2032,5 +2117,40 @@ export const IDL: LightSystemProgram = {
             name: 'OutputMerkleTreeIndicesNotInOrder',
             msg: 'Output merkle tree indices are not in ascending order.',
         },
+        {
+            code: 6033,
+            name: 'AddressOwnerMismatch',
+            msg: 'Address of an input compressed account can only be assigned to an output of the same owner.',
+        },
+        {
+            code: 6034,
+            name: 'AddressAssignedToMultipleOutputs',
+            msg: 'Address is assigned to more than one output compressed account.',
+        },
+        {
+            code: 6035,
+            name: 'CpiContextSlotMismatch',
+            msg: 'CpiContextSlotMismatch',
+        },
+        {
+            code: 6036,
+            name: 'VerifyingKeyAccountMissing',
+            msg: 'The verifying key account of the proof is missing.',
+        },
+        {
+            code: 6037,
+            name: 'VerifyingKeyNotActivated',
+            msg: 'VerifyingKeyNotActivated',
+        },
+        {
+            code: 6038,
+            name: 'CloseAddressWithoutAddress',
+            msg: 'Only the address of a compressed account with an address can be closed.',
+        },
+        {
+            code: 6039,
+            name: 'EventTooLarge',
+            msg: 'Event data exceeds the max instruction data of a cpi.',
+        },
     ],
 };
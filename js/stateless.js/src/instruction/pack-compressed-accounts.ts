@@ -137,6 +137,7 @@ export function packCompressedAccounts(
                 leafIndex: account.leafIndex,
             },
             rootIndex: inputStateRootIndices[index],
+            closeAddress: false,
         });
     });
 
@@ -5,6 +5,7 @@ import {
     Connection,
     TransactionInstruction,
     SystemProgram,
+    AccountMeta,
 } from '@solana/web3.js';
 import { Buffer } from 'buffer';
 
@@ -25,6 +26,7 @@ import { packCompressedAccounts, toAccountMetas } from '../instruction';
 import {
     defaultStaticAccountsStruct,
     defaultTestStateTreeAccounts,
+    getVerifyingKeyAccountMeta,
 } from '../constants';
 import {
     validateSameOwner,
@@ -191,6 +193,21 @@ type DecompressParams = {
 
 const SOL_POOL_PDA_SEED = Buffer.from('sol_pool_pda');
 
+/** Appends the verifying key account of the proof to the remaining accounts */
+const withVerifyingKeyAccount = (
+    accountMetas: AccountMeta[],
+    numLeaves: number,
+    numAddresses: number,
+): AccountMeta[] => {
+    const verifyingKeyAccount = getVerifyingKeyAccountMeta(
+        numLeaves,
+        numAddresses,
+    );
+    return verifyingKeyAccount
+        ? [...accountMetas, verifyingKeyAccount]
+        : accountMetas;
+};
+
 export class LightSystemProgram {
     /**
      * @internal
@@ -402,7 +419,13 @@ export class LightSystemProgram {
                 decompressionRecipient: null,
                 systemProgram: SystemProgram.programId,
             })
-            .remainingAccounts(toAccountMetas(remainingAccounts))
+            .remainingAccounts(
+                withVerifyingKeyAccount(
+                    toAccountMetas(remainingAccounts),
+                    inputCompressedAccounts?.length ?? 0,
+                    1,
+                ),
+            )
             .instruction();
 
         return instruction;
@@ -462,7 +485,13 @@ export class LightSystemProgram {
                 decompressionRecipient: null,
                 systemProgram: SystemProgram.programId,
             })
-            .remainingAccounts(toAccountMetas(remainingAccounts))
+            .remainingAccounts(
+                withVerifyingKeyAccount(
+                    toAccountMetas(remainingAccounts),
+                    inputCompressedAccounts.length,
+                    0,
+                ),
+            )
             .instruction();
 
         return instruction;
@@ -590,7 +619,13 @@ export class LightSystemProgram {
                 decompressionRecipient: toAddress,
                 systemProgram: SystemProgram.programId,
             })
-            .remainingAccounts(toAccountMetas(remainingAccounts))
+            .remainingAccounts(
+                withVerifyingKeyAccount(
+                    toAccountMetas(remainingAccounts),
+                    inputCompressedAccounts.length,
+                    0,
+                ),
+            )
             .instruction();
 
         return instruction;
@@ -7,6 +7,7 @@ export interface PackedCompressedAccountWithMerkleContext {
     compressedAccount: CompressedAccount;
     merkleContext: PackedMerkleContext;
     rootIndex: number; // u16
+    closeAddress: boolean;
 }
 
 export interface PackedMerkleContext {
@@ -58,6 +59,7 @@ export interface PublicTransactionEvent {
     compressOrDecompressLamports: BN | null; // Option<u64>
     pubkeyArray: PublicKey[]; // Vec<PublicKey>
     message: Uint8Array | null; // Option<bytes>
+    closedAddresses: number[][]; // Vec<[u8; 32]>
 }
 
 export interface InstructionDataInvoke {
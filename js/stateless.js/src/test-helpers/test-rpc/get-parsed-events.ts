@@ -2,14 +2,50 @@ import {
     ParsedMessageAccount,
     ParsedTransactionWithMeta,
 } from '@solana/web3.js';
+import { BorshCoder, Idl } from '@coral-xyz/anchor';
 import { bs58 } from '@coral-xyz/anchor/dist/cjs/utils/bytes';
 import { defaultStaticAccountsStruct } from '../../constants';
+import { IDL } from '../../idls/light_system_program';
 import { LightSystemProgram } from '../../programs';
 import { Rpc } from '../../rpc';
 import { PublicTransactionEvent } from '../../state';
 
 type Deserializer<T> = (data: Buffer, tx: ParsedTransactionWithMeta) => T;
 
+/** sha256("event:PublicTransactionEvent")[..8] */
+const PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR = Buffer.from([
+    107, 27, 168, 13, 217, 235, 183, 75,
+]);
+/** Event envelope: discriminator (8 bytes) and version (1 byte) */
+const EVENT_HEADER_LEN = 9;
+const PUBLIC_TRANSACTION_EVENT_VERSION = 1;
+
+/**
+ * Coder for legacy events, which have no envelope. The legacy layout of
+ * PublicTransactionEvent has no closedAddresses.
+ */
+const idl: Idl = IDL;
+const legacyEventCoder = new BorshCoder({
+    ...idl,
+    types: idl.types?.map(typeDef => {
+        if (
+            typeDef.name !== 'PublicTransactionEvent' ||
+            typeDef.type.kind !== 'struct'
+        ) {
+            return typeDef;
+        }
+        return {
+            ...typeDef,
+            type: {
+                ...typeDef.type,
+                fields: typeDef.type.fields.filter(
+                    field => field.name !== 'closedAddresses',
+                ),
+            },
+        };
+    }),
+});
+
 /**
  * @internal
  * Returns newest first.
@@ -115,10 +151,30 @@ export const parsePublicTransactionEventWithIdl = (
 ): PublicTransactionEvent | null => {
     const numericData = Buffer.from(data.map(byte => byte));
 
+    const hasEnvelope =
+        numericData.length >= EVENT_HEADER_LEN &&
+        numericData
+            .subarray(0, PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR.length)
+            .equals(PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR);
+
     try {
+        if (!hasEnvelope) {
+            // Events without envelope are legacy events.
+            const event = legacyEventCoder.types.decode(
+                'PublicTransactionEvent',
+                numericData,
+            );
+            return { ...event, closedAddresses: [] };
+        }
+        const version =
+            numericData[PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR.length];
+        if (version !== PUBLIC_TRANSACTION_EVENT_VERSION) {
+            console.error('Unsupported event version:', version);
+            return null;
+        }
         return LightSystemProgram.program.coder.types.decode(
             'PublicTransactionEvent',
-            numericData,
+            numericData.subarray(EVENT_HEADER_LEN),
         );
     } catch (error) {
         console.error('Error deserializing event:', error);
@@ -4,6 +4,7 @@ import {
     CompressedAccount,
     PublicTransactionEvent,
     bn,
+    parsePublicTransactionEventWithIdl,
     useWallet,
 } from '../../src';
 import { Connection, Keypair, PublicKey } from '@solana/web3.js';
@@ -65,7 +66,8 @@ describe('serde', () => {
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 1, 0, 0, 0, 68, 77, 125, 32, 76, 128, 61, 180, 1, 207, 69,
             44, 121, 118, 153, 17, 179, 183, 115, 34, 163, 127, 102, 214, 1, 87,
-            175, 177, 95, 49, 65, 69, 0,
+            175, 177, 95, 49, 65, 69, 0, 1, 0, 0, 0, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
         ];
         const event: PublicTransactionEvent =
             LightSystemProgram.program.coder.types.decode(
@@ -83,5 +85,51 @@ describe('serde', () => {
                 bn(refOutputCompressedAccountHash),
             ),
         ).toBe(true);
+        expect(event.closedAddresses).toStrictEqual([Array(32).fill(7)]);
+    });
+
+    it('parse legacy and enveloped event ', async () => {
+        // Legacy event without envelope and closedAddresses.
+        const legacyData = [
+            0, 0, 0, 0, 1, 0, 0, 0, 33, 32, 204, 221, 5, 83, 170, 139, 228, 191,
+            81, 173, 10, 116, 229, 191, 155, 209, 23, 164, 28, 64, 188, 34, 248,
+            127, 110, 97, 26, 188, 139, 164, 0, 0, 0, 0, 1, 0, 0, 0, 22, 143,
+            135, 215, 254, 121, 58, 95, 241, 202, 91, 53, 255, 47, 224, 255, 67,
+            218, 48, 172, 51, 208, 29, 102, 177, 187, 207, 73, 108, 18, 59, 255,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 1, 0, 0, 0, 68, 77, 125, 32, 76, 128, 61, 180, 1, 207, 69,
+            44, 121, 118, 153, 17, 179, 183, 115, 34, 163, 127, 102, 214, 1, 87,
+            175, 177, 95, 49, 65, 69, 0,
+        ];
+        const legacyEvent = parsePublicTransactionEventWithIdl(
+            Buffer.from(legacyData),
+        );
+        expect(legacyEvent).not.toBe(null);
+        expect(legacyEvent!.outputCompressedAccountHashes.length).toBe(1);
+        expect(legacyEvent!.closedAddresses).toStrictEqual([]);
+
+        // Event with envelope (discriminator and version 1).
+        const header = [107, 27, 168, 13, 217, 235, 183, 75, 1];
+        const closedAddresses = [1, 0, 0, 0, ...Array(32).fill(7)];
+        const event = parsePublicTransactionEventWithIdl(
+            Buffer.from([...header, ...legacyData, ...closedAddresses]),
+        );
+        expect(event).not.toBe(null);
+        expect(event!.outputCompressedAccountHashes).toStrictEqual(
+            legacyEvent!.outputCompressedAccountHashes,
+        );
+        expect(event!.closedAddresses).toStrictEqual([Array(32).fill(7)]);
+
+        // Unsupported versions are not decoded.
+        const unsupportedHeader = [107, 27, 168, 13, 217, 235, 183, 75, 2];
+        expect(
+            parsePublicTransactionEventWithIdl(
+                Buffer.from([
+                    ...unsupportedHeader,
+                    ...legacyData,
+                    ...closedAddresses,
+                ]),
+            ),
+        ).toBe(null);
     });
 });
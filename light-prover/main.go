@@ -257,6 +257,20 @@ func runCli() {
 					return err
 				},
 			},
+			{
+				Name:  "export-ark",
+				Usage: "Export a proving system for the arkworks prover of light-prover-client",
+				Flags: []cli.Flag{
+					&cli.StringFlag{Name: "keys-file", Aliases: []string{"k"}, Usage: "proving system file", Required: true},
+					&cli.StringFlag{Name: "output", Usage: "output file", Required: true},
+				},
+				Action: func(context *cli.Context) error {
+					keys := context.String("keys-file")
+					outPath := context.String("output")
+					logging.Logger().Info().Str("keysFile", keys).Msg("Exporting proving system")
+					return prover.ExportArk(keys, outPath)
+				},
+			},
 			{
 				Name: "gen-test-params",
 				Flags: []cli.Flag{
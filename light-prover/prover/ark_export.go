@@ -0,0 +1,347 @@
+package prover
+
+import (
+	"encoding/binary"
+	"fmt"
+	"io"
+	"math/bits"
+	"os"
+	"reflect"
+	"sort"
+
+	"github.com/consensys/gnark-crypto/ecc/bn254"
+	"github.com/consensys/gnark-crypto/ecc/bn254/fp"
+	"github.com/consensys/gnark-crypto/ecc/bn254/fr"
+	"github.com/consensys/gnark-crypto/ecc/bn254/fr/fft"
+	"github.com/consensys/gnark/constraint"
+	cs_bn254 "github.com/consensys/gnark/constraint/bn254"
+)
+
+// Format of the files read by the arkworks prover of light-prover-client
+// (`light_prover_client::arkworks::light_prover`). Bump the version on every
+// change of the layout.
+const (
+	arkExportMagic   = "LIGHTARK"
+	arkExportVersion = 1
+)
+
+// arkWriter writes the values of the export format and keeps the first error,
+// so the layout reads top to bottom without checking every write.
+//
+// Integers are big-endian uint32, field elements are 32-byte big-endian
+// integers, G1 points are encoded as x || y, G2 points as
+// x.A1 || x.A0 || y.A1 || y.A0 and the point at infinity as zeros.
+type arkWriter struct {
+	w   io.Writer
+	err error
+}
+
+func (aw *arkWriter) bytes(b []byte) {
+	if aw.err != nil {
+		return
+	}
+	_, aw.err = aw.w.Write(b)
+}
+
+func (aw *arkWriter) uint32(v int) {
+	var buf [4]byte
+	binary.BigEndian.PutUint32(buf[:], uint32(v))
+	aw.bytes(buf[:])
+}
+
+func (aw *arkWriter) fr(e *fr.Element) {
+	b := e.Bytes()
+	aw.bytes(b[:])
+}
+
+func (aw *arkWriter) fp(e *fp.Element) {
+	b := e.Bytes()
+	aw.bytes(b[:])
+}
+
+func (aw *arkWriter) g1(p *bn254.G1Affine) {
+	if p.IsInfinity() {
+		aw.bytes(make([]byte, 2*fp.Bytes))
+		return
+	}
+	aw.fp(&p.X)
+	aw.fp(&p.Y)
+}
+
+func (aw *arkWriter) g2(p *bn254.G2Affine) {
+	if p.IsInfinity() {
+		aw.bytes(make([]byte, 4*fp.Bytes))
+		return
+	}
+	aw.fp(&p.X.A1)
+	aw.fp(&p.X.A0)
+	aw.fp(&p.Y.A1)
+	aw.fp(&p.Y.A0)
+}
+
+func (aw *arkWriter) g1s(points []bn254.G1Affine) {
+	aw.uint32(len(points))
+	for i := range points {
+		aw.g1(&points[i])
+	}
+}
+
+// withInfinity inserts the points at infinity, which gnark drops from the
+// proving key, so there is one point per wire.
+func withInfinity[T any](points []T, infinity []bool) ([]T, error) {
+	result := make([]T, len(infinity))
+	j := 0
+	for i, isInfinity := range infinity {
+		if isInfinity {
+			continue
+		}
+		if j == len(points) {
+			return nil, fmt.Errorf("proving key has less points than non-infinity wires")
+		}
+		result[i] = points[j]
+		j++
+	}
+	if j != len(points) {
+		return nil, fmt.Errorf("proving key has more points than non-infinity wires")
+	}
+	return result, nil
+}
+
+// bitReverse permutes the points to bit-reversed indices. gnark stores G1.Z in
+// bit-reversed order, which matches the order of its FFT output. The arkworks
+// prover computes the coefficients of H in natural order.
+func bitReverse[T any](points []T) {
+	n := uint64(len(points))
+	shift := uint64(64 - bits.TrailingZeros64(n))
+	for i := uint64(0); i < n; i++ {
+		j := bits.Reverse64(i) >> shift
+		if j > i {
+			points[i], points[j] = points[j], points[i]
+		}
+	}
+}
+
+func (aw *arkWriter) linearExpression(r1cs *cs_bn254.R1CS, l constraint.LinearExpression) {
+	aw.uint32(len(l))
+	for _, t := range l {
+		aw.uint32(t.WireID())
+		aw.fr(&r1cs.Coefficients[t.CoeffID()])
+	}
+}
+
+// field returns the exported field at the given path. The bn254 Groth16 keys
+// are internal types of gnark, so their fields are accessed by reflection.
+func field[T any](v interface{}, path ...string) (T, error) {
+	var zero T
+	value := reflect.ValueOf(v)
+	for _, name := range path {
+		value = reflect.Indirect(value).FieldByName(name)
+		if !value.IsValid() {
+			return zero, fmt.Errorf("missing field %s", name)
+		}
+	}
+	result, ok := value.Interface().(T)
+	if !ok {
+		return zero, fmt.Errorf("unexpected type of field %v: %s", path, value.Type())
+	}
+	return result, nil
+}
+
+type arkProvingKey struct {
+	domainSize                   int
+	alphaG1, betaG1, deltaG1     bn254.G1Affine
+	betaG2, gammaG2, deltaG2     bn254.G2Affine
+	aG1, bG1, zG1, kG1, gammaABC []bn254.G1Affine
+	bG2                          []bn254.G2Affine
+	infinityA, infinityB         []bool
+}
+
+func readArkProvingKey(ps *ProvingSystem) (*arkProvingKey, error) {
+	var pk arkProvingKey
+	domain, err := field[fft.Domain](ps.ProvingKey, "Domain")
+	if err != nil {
+		return nil, err
+	}
+	pk.domainSize = int(domain.Cardinality)
+	g1Fields := []struct {
+		dst  *bn254.G1Affine
+		path []string
+	}{
+		{&pk.alphaG1, []string{"G1", "Alpha"}},
+		{&pk.betaG1, []string{"G1", "Beta"}},
+		{&pk.deltaG1, []string{"G1", "Delta"}},
+	}
+	for _, f := range g1Fields {
+		if *f.dst, err = field[bn254.G1Affine](ps.ProvingKey, f.path...); err != nil {
+			return nil, err
+		}
+	}
+	if pk.betaG2, err = field[bn254.G2Affine](ps.ProvingKey, "G2", "Beta"); err != nil {
+		return nil, err
+	}
+	if pk.deltaG2, err = field[bn254.G2Affine](ps.ProvingKey, "G2", "Delta"); err != nil {
+		return nil, err
+	}
+	if pk.gammaG2, err = field[bn254.G2Affine](ps.VerifyingKey, "G2", "Gamma"); err != nil {
+		return nil, err
+	}
+	g1SliceFields := []struct {
+		dst  *[]bn254.G1Affine
+		key  interface{}
+		path []string
+	}{
+		{&pk.aG1, ps.ProvingKey, []string{"G1", "A"}},
+		{&pk.bG1, ps.ProvingKey, []string{"G1", "B"}},
+		{&pk.zG1, ps.ProvingKey, []string{"G1", "Z"}},
+		{&pk.kG1, ps.ProvingKey, []string{"G1", "K"}},
+		{&pk.gammaABC, ps.VerifyingKey, []string{"G1", "K"}},
+	}
+	for _, f := range g1SliceFields {
+		if *f.dst, err = field[[]bn254.G1Affine](f.key, f.path...); err != nil {
+			return nil, err
+		}
+	}
+	if pk.bG2, err = field[[]bn254.G2Affine](ps.ProvingKey, "G2", "B"); err != nil {
+		return nil, err
+	}
+	if pk.infinityA, err = field[[]bool](ps.ProvingKey, "InfinityA"); err != nil {
+		return nil, err
+	}
+	if pk.infinityB, err = field[[]bool](ps.ProvingKey, "InfinityB"); err != nil {
+		return nil, err
+	}
+	if len(pk.zG1) != pk.domainSize {
+		return nil, fmt.Errorf("proving key has %d Z points, domain size is %d", len(pk.zG1), pk.domainSize)
+	}
+	bitReverse(pk.zG1)
+	if pk.aG1, err = withInfinity(pk.aG1, pk.infinityA); err != nil {
+		return nil, err
+	}
+	if pk.bG1, err = withInfinity(pk.bG1, pk.infinityB); err != nil {
+		return nil, err
+	}
+	if pk.bG2, err = withInfinity(pk.bG2, pk.infinityB); err != nil {
+		return nil, err
+	}
+	return &pk, nil
+}
+
+// WriteArkTo writes the proving key and the constraint system in the format
+// of the arkworks prover. Besides the keys, the prover needs the constraints
+// and the hints to compute the values of all wires from the circuit inputs.
+func (ps *ProvingSystem) WriteArkTo(w io.Writer) error {
+	r1cs, ok := ps.ConstraintSystem.(*cs_bn254.R1CS)
+	if !ok {
+		return fmt.Errorf("expected a bn254 R1CS, got %T", ps.ConstraintSystem)
+	}
+	pk, err := readArkProvingKey(ps)
+	if err != nil {
+		return err
+	}
+
+	nbPublic := r1cs.GetNbPublicVariables()
+	nbSecret := r1cs.GetNbSecretVariables()
+	nbInternal := r1cs.GetNbInternalVariables()
+	if nbWires := nbPublic + nbSecret + nbInternal; nbWires != len(pk.infinityA) {
+		return fmt.Errorf("constraint system has %d wires, proving key %d", nbWires, len(pk.infinityA))
+	}
+
+	aw := &arkWriter{w: w}
+	aw.bytes([]byte(arkExportMagic))
+	aw.uint32(arkExportVersion)
+	aw.uint32(nbPublic)
+	aw.uint32(nbSecret)
+	aw.uint32(nbInternal)
+	aw.uint32(pk.domainSize)
+
+	aw.g1(&pk.alphaG1)
+	aw.g1(&pk.betaG1)
+	aw.g1(&pk.deltaG1)
+	aw.g2(&pk.betaG2)
+	aw.g2(&pk.gammaG2)
+	aw.g2(&pk.deltaG2)
+	aw.g1s(pk.aG1)
+	aw.g1s(pk.bG1)
+	aw.uint32(len(pk.bG2))
+	for i := range pk.bG2 {
+		aw.g2(&pk.bG2[i])
+	}
+	aw.g1s(pk.zG1)
+	aw.g1s(pk.kG1)
+	aw.g1s(pk.gammaABC)
+
+	aw.uint32(len(r1cs.Constraints))
+	for _, c := range r1cs.Constraints {
+		aw.linearExpression(r1cs, c.L)
+		aw.linearExpression(r1cs, c.R)
+		aw.linearExpression(r1cs, c.O)
+	}
+
+	// Several output wires map to the same hint, write each hint once, in
+	// the order of its first output wire.
+	wires := make([]int, 0, len(r1cs.MHints))
+	for wire := range r1cs.MHints {
+		wires = append(wires, wire)
+	}
+	sort.Ints(wires)
+	hints := make([]*constraint.Hint, 0)
+	written := make(map[*constraint.Hint]bool)
+	for _, wire := range wires {
+		hint := r1cs.MHints[wire]
+		if !written[hint] {
+			written[hint] = true
+			hints = append(hints, hint)
+		}
+	}
+	aw.uint32(len(hints))
+	for _, hint := range hints {
+		name, ok := r1cs.MHintsDependencies[hint.ID]
+		if !ok {
+			return fmt.Errorf("unknown hint %d", hint.ID)
+		}
+		aw.uint32(len(name))
+		aw.bytes([]byte(name))
+		aw.uint32(len(hint.Inputs))
+		for _, input := range hint.Inputs {
+			aw.linearExpression(r1cs, input)
+		}
+		aw.uint32(len(hint.Wires))
+		for _, wire := range hint.Wires {
+			aw.uint32(wire)
+		}
+	}
+
+	// Constraints in the order of solving.
+	nbSolved := 0
+	for _, level := range r1cs.Levels {
+		nbSolved += len(level)
+	}
+	aw.uint32(nbSolved)
+	for _, level := range r1cs.Levels {
+		for _, c := range level {
+			aw.uint32(c)
+		}
+	}
+
+	return aw.err
+}
+
+// ExportArk converts the proving system in keysFile to the format of the
+// arkworks prover and writes it to output.
+func ExportArk(keysFile string, output string) (err error) {
+	ps, err := ReadSystemFromFile(keysFile)
+	if err != nil {
+		return err
+	}
+	file, err := os.Create(output)
+	if err != nil {
+		return err
+	}
+	defer func() {
+		closeErr := file.Close()
+		if closeErr != nil && err == nil {
+			err = closeErr
+		}
+	}()
+	return ps.WriteArkTo(file)
+}
@@ -26,6 +26,7 @@ generate() {
     CIRCUIT_FILE="./proving-keys/${CIRCUIT_TYPE}_${DEPTH}_${COMPRESSED_ACCOUNTS}.key"
     CIRCUIT_VKEY_FILE="./proving-keys/${CIRCUIT_TYPE}_${DEPTH}_${COMPRESSED_ACCOUNTS}.vkey"
     CIRCUIT_VKEY_RS_FILE="../circuit-lib/verifier/src/verifying_keys/${CIRCUIT_TYPE_RS}_${DEPTH}_${COMPRESSED_ACCOUNTS}.rs"
+    CIRCUIT_VKEY_PAYLOAD_FILE="./proving-keys/${CIRCUIT_TYPE}_${DEPTH}_${COMPRESSED_ACCOUNTS}.vkey-payload"
 
     echo "Generating ${CIRCUIT_TYPE} circuit for ${COMPRESSED_ACCOUNTS} COMPRESSED_ACCOUNTS..."
     echo "go run . setup --circuit ${CIRCUIT_TYPE} --inclusion-compressed-accounts ${INCLUSION_COMPRESSED_ACCOUNTS} --non-inclusion-compressed-accounts ${NON_INCLUSION_COMPRESSED_ACCOUNTS} --inclusion-tree-depth ${DEPTH} --non-inclusion-tree-depth ${DEPTH} --output ${CIRCUIT_FILE} --output-vkey ${CIRCUIT_VKEY_FILE}"
@@ -38,7 +39,7 @@ generate() {
       --non-inclusion-tree-depth "$DEPTH" \
       --output "${CIRCUIT_FILE}" \
       --output-vkey "${CIRCUIT_VKEY_FILE}"
-    cargo xtask generate-vkey-rs --input-path "${CIRCUIT_VKEY_FILE}" --output-path "${CIRCUIT_VKEY_RS_FILE}"
+    cargo xtask generate-vkey-rs --input-path "${CIRCUIT_VKEY_FILE}" --output-path "${CIRCUIT_VKEY_RS_FILE}" --payload-path "${CIRCUIT_VKEY_PAYLOAD_FILE}"
 }
 
 declare -a inclusion_compressed_accounts_arr=("1" "2" "3" "4" "8")
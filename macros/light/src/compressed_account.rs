@@ -0,0 +1,147 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::{Digest, Sha256};
+use syn::{Data, DeriveInput, Fields, Result};
+
+/// Maximum number of inputs of the Poseidon hash function (circom parameters).
+const MAX_HASHED_FIELDS: usize = 12;
+
+pub(crate) fn process_light_account(input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+
+    let fields = match input.data {
+        Data::Struct(ref data_struct) => match data_struct.fields {
+            Fields::Named(ref fields) => fields,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input,
+                    "`LightAccount` can only be derived for structs with named fields.",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "`LightAccount` can only be derived for structs.",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`LightAccount` can't be derived for generic structs.",
+        ));
+    }
+    if fields.named.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "`LightAccount` requires at least one field.",
+        ));
+    }
+    if fields.named.len() > MAX_HASHED_FIELDS {
+        return Err(syn::Error::new_spanned(
+            &input,
+            format!(
+                "`LightAccount` supports at most {} fields, found {}.",
+                MAX_HASHED_FIELDS,
+                fields.named.len()
+            ),
+        ));
+    }
+
+    let discriminator = light_discriminator(&name.to_string());
+
+    let field_idents = fields
+        .named
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let hashed_field_idents = field_idents
+        .iter()
+        .map(|ident| quote::format_ident!("hashed_{}", ident))
+        .collect::<Vec<_>>();
+
+    Ok(quote! {
+        impl ::light_sdk::compressed_account::LightDiscriminator for #name {
+            const LIGHT_DISCRIMINATOR: [u8; 8] = [#(#discriminator),*];
+        }
+
+        impl ::light_sdk::compressed_account::DataHasher for #name {
+            fn hash<H: ::light_sdk::compressed_account::Hasher>(
+                &self,
+            ) -> ::std::result::Result<[u8; 32], ::light_sdk::compressed_account::HasherError> {
+                #(
+                    let #hashed_field_idents =
+                        ::light_sdk::compressed_account::hash_field(&self.#field_idents)?;
+                )*
+                H::hashv(&[#(#hashed_field_idents.as_slice()),*])
+            }
+        }
+
+        impl ::light_sdk::compressed_account::LightAccount for #name {}
+    })
+}
+
+/// Discriminator of a compressed account, first 8 bytes of
+/// `sha256("compressed_account:<name>")`.
+fn light_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("compressed_account:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_process_light_account() {
+        let input: DeriveInput = parse_quote! {
+            struct MyCompressedAccount {
+                owner: Pubkey,
+                amount: u64,
+            }
+        };
+
+        let output = process_light_account(input).unwrap();
+        let output_string = output.to_string();
+
+        assert!(output_string.contains("LightDiscriminator for MyCompressedAccount"));
+        assert!(output_string.contains("DataHasher for MyCompressedAccount"));
+        assert!(output_string.contains("LightAccount for MyCompressedAccount"));
+        assert!(output_string.contains("hashed_owner"));
+        assert!(output_string.contains("hashed_amount"));
+    }
+
+    #[test]
+    fn test_light_discriminator() {
+        assert_ne!(light_discriminator("A"), light_discriminator("B"));
+        assert_eq!(light_discriminator("A"), light_discriminator("A"));
+    }
+
+    #[test]
+    fn test_process_light_account_fails() {
+        let input: DeriveInput = parse_quote! {
+            struct MyCompressedAccount(u64);
+        };
+        assert!(process_light_account(input).is_err());
+
+        let input: DeriveInput = parse_quote! {
+            enum MyCompressedAccount {
+                A,
+            }
+        };
+        assert!(process_light_account(input).is_err());
+
+        let input: DeriveInput = parse_quote! {
+            struct MyCompressedAccount {
+                a: u8, b: u8, c: u8, d: u8, e: u8, f: u8, g: u8,
+                h: u8, i: u8, j: u8, k: u8, l: u8, m: u8,
+            }
+        };
+        let error_message = process_light_account(input).unwrap_err().to_string();
+        assert!(error_message.contains("at most 12 fields, found 13"));
+    }
+}
@@ -1,11 +1,15 @@
 extern crate proc_macro;
 use accounts::process_light_accounts;
+use compressed_account::process_light_account;
 use proc_macro::TokenStream;
 use quote::quote;
+use sponge_hasher::process_sponge_hasher;
 use syn::{parse_macro_input, parse_quote, DeriveInput, ItemFn};
 use traits::process_light_traits;
 mod accounts;
+mod compressed_account;
 mod pubkey;
+mod sponge_hasher;
 mod traits;
 
 /// Converts a base58 encoded public key into a byte array.
@@ -152,6 +156,84 @@ pub fn light_traits_derive(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Implements the traits required to store the given struct in a compressed
+/// account.
+///
+/// ## Usage
+/// Add `#[derive(LightAccount)]` to a struct with named fields which also
+/// derives `AnchorSerialize` and `AnchorDeserialize`. The generated code
+/// refers to `light_sdk`, hence the program has to depend on `light-sdk`.
+///
+/// The following is generated:
+/// - `LightDiscriminator`: the first 8 bytes of
+///                         `sha256("compressed_account:<StructName>")`.
+/// - `DataHasher`:         hashes every field with
+///                         `hash_to_bn254_field_size_be` of its borsh
+///                         serialization and hashes the results with the
+///                         given hasher. At most 12 fields are supported.
+/// - `LightAccount`:       serialization into `CompressedAccountData` and
+///                         address derivation helpers.
+///
+/// ## Example
+/// ```ignore
+/// #[derive(AnchorSerialize, AnchorDeserialize, LightAccount)]
+/// pub struct MyCompressedAccount {
+///     pub owner: Pubkey,
+///     pub amount: u64,
+/// }
+///
+/// let address_seed = MyCompressedAccount::address_seed(&crate::ID, &[b"my_seed"])?;
+/// let output_account = my_compressed_account.new_compressed_account(
+///     &crate::ID,
+///     &[b"my_seed"],
+///     &new_address_params,
+///     ctx.remaining_accounts,
+///     0,
+/// )?;
+/// ```
+#[proc_macro_derive(LightAccount)]
+pub fn light_account_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match process_light_account(input) {
+        Ok(token_stream) => token_stream.into(),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+/// Implements `DataHasher` for the given struct by absorbing its fields into
+/// a `light_hasher::sponge::Sponge`, which hashes arbitrary-length data.
+///
+/// ## Usage
+/// Add `#[derive(SpongeHasher)]` to a struct with named fields. Every field
+/// has to implement `AnchorSerialize`. The generated code refers to
+/// `light_sdk`, hence the program has to depend on `light-sdk`.
+///
+/// The borsh serialization of every field is absorbed in the order of
+/// declaration. The domain separator is the first 8 bytes of
+/// `sha256("sponge_hasher:<StructName>")`. Unlike `LightAccount`, there is
+/// no limit on the number of fields.
+///
+/// ## Example
+/// ```ignore
+/// #[derive(AnchorSerialize, AnchorDeserialize, SpongeHasher)]
+/// pub struct MyData {
+///     pub owner: Pubkey,
+///     pub data: Vec<u8>,
+/// }
+///
+/// let data_hash = my_data.hash::<Poseidon>()?;
+/// ```
+#[proc_macro_derive(SpongeHasher)]
+pub fn sponge_hasher_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match process_sponge_hasher(input) {
+        Ok(token_stream) => token_stream.into(),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
 // #[proc_macro_derive(
 //     LightTraits,
 //     attributes(self_program, fee_payer, authority, cpi_context)
@@ -0,0 +1,108 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::{Digest, Sha256};
+use syn::{Data, DeriveInput, Fields, Result};
+
+pub(crate) fn process_sponge_hasher(input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+
+    let fields = match input.data {
+        Data::Struct(ref data_struct) => match data_struct.fields {
+            Fields::Named(ref fields) => fields,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input,
+                    "`SpongeHasher` can only be derived for structs with named fields.",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "`SpongeHasher` can only be derived for structs.",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`SpongeHasher` can't be derived for generic structs.",
+        ));
+    }
+
+    let domain = sponge_domain(&name.to_string());
+    let field_idents = fields
+        .named
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+
+    Ok(quote! {
+        impl ::light_sdk::compressed_account::DataHasher for #name {
+            fn hash<H: ::light_sdk::compressed_account::Hasher>(
+                &self,
+            ) -> ::std::result::Result<[u8; 32], ::light_sdk::compressed_account::HasherError> {
+                let mut sponge =
+                    ::light_sdk::compressed_account::Sponge::<H>::new(&[#(#domain),*])?;
+                #(
+                    ::light_sdk::compressed_account::absorb_field(&mut sponge, &self.#field_idents)?;
+                )*
+                sponge.finalize()
+            }
+        }
+    })
+}
+
+/// Domain separator of a struct, first 8 bytes of
+/// `sha256("sponge_hasher:<name>")`.
+fn sponge_domain(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("sponge_hasher:{}", name).as_bytes());
+    let mut domain = [0u8; 8];
+    domain.copy_from_slice(&hash[..8]);
+    domain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_process_sponge_hasher() {
+        let input: DeriveInput = parse_quote! {
+            struct MyStruct {
+                owner: Pubkey,
+                data: Vec<u8>,
+            }
+        };
+
+        let output = process_sponge_hasher(input).unwrap();
+        let output_string = output.to_string();
+
+        assert!(output_string.contains("DataHasher for MyStruct"));
+        assert!(output_string.contains("self . owner"));
+        assert!(output_string.contains("self . data"));
+    }
+
+    #[test]
+    fn test_process_sponge_hasher_fails() {
+        let input: DeriveInput = parse_quote! {
+            struct MyStruct(u64);
+        };
+        assert!(process_sponge_hasher(input).is_err());
+
+        let input: DeriveInput = parse_quote! {
+            enum MyStruct {
+                A,
+            }
+        };
+        assert!(process_sponge_hasher(input).is_err());
+
+        let input: DeriveInput = parse_quote! {
+            struct MyStruct<T> {
+                a: T,
+            }
+        };
+        assert!(process_sponge_hasher(input).is_err());
+    }
+}
@@ -9,6 +9,8 @@ use std::{
 use memoffset::span_of;
 use thiserror::Error;
 
+pub mod zero_copy;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum BoundedVecError {
     #[error("The vector is full, cannot push any new elements")]
@@ -17,6 +19,12 @@ pub enum BoundedVecError {
     ArraySize(usize, usize),
     #[error("The requested start index is out of bounds.")]
     IterFromOutOfBounds,
+    #[error("Buffer too small, expected at least {0} bytes, got {1}")]
+    BufferSize(usize, usize),
+    #[error("Buffer is not aligned to {0} bytes")]
+    UnalignedBuffer(usize),
+    #[error("Metadata stored in the buffer is invalid")]
+    InvalidMetadata,
 }
 
 #[cfg(feature = "solana")]
@@ -26,6 +34,9 @@ impl From<BoundedVecError> for u32 {
             BoundedVecError::Full => 8001,
             BoundedVecError::ArraySize(_, _) => 8002,
             BoundedVecError::IterFromOutOfBounds => 8003,
+            BoundedVecError::BufferSize(_, _) => 8004,
+            BoundedVecError::UnalignedBuffer(_) => 8005,
+            BoundedVecError::InvalidMetadata => 8006,
         }
     }
 }
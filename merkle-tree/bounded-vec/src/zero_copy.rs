@@ -0,0 +1,866 @@
+//! Zero-copy views of [`BoundedVec`] and [`CyclicBoundedVec`] backed by byte
+//! buffers (e.g. account data).
+//!
+//! The constructors check the size and alignment of the provided buffers and
+//! the consistency of the stored metadata, so that the views can be created
+//! without `unsafe` code at the call site. Each constructor returns the
+//! remaining part of the buffer, which allows to describe a layout of
+//! multiple vectors as a sequence of calls:
+//!
+//! ```
+//! use light_bounded_vec::zero_copy::{BoundedVecZeroCopyMut, CyclicBoundedVecZeroCopyMut};
+//!
+//! let mut buf = vec![0u64; 64];
+//! let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+//!
+//! let (mut leaves, bytes) = BoundedVecZeroCopyMut::<[u8; 32]>::init(bytes, 4).unwrap();
+//! let (mut roots, _) = CyclicBoundedVecZeroCopyMut::<[u8; 32]>::init(bytes, 2).unwrap();
+//! leaves.push([1u8; 32]).unwrap();
+//! roots.push([2u8; 32]);
+//! ```
+//!
+//! Element types have to implement [`Pod`], which guarantees that any bit
+//! pattern stored in the buffer is a valid value.
+//!
+//! Single values and arrays of non-[`Pod`] types can be located in the
+//! buffer with [`ptr_from_bytes`] and [`ptr_from_bytes_mut`], which perform
+//! the same size and alignment checks, but leave the validity of the pointed
+//! values up to the caller.
+
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::{Deref, Index, IndexMut},
+    slice::{IterMut, SliceIndex},
+};
+
+use bytemuck::Pod;
+
+use crate::{
+    BoundedVec, BoundedVecError, BoundedVecMetadata, CyclicBoundedVec, CyclicBoundedVecMetadata,
+};
+
+/// Checks whether `bytes` start with `len` properly aligned values of type `T`
+/// and returns their size.
+fn check_value<T>(bytes: &[u8], len: usize) -> Result<usize, BoundedVecError> {
+    let size = mem::size_of::<T>()
+        .checked_mul(len)
+        .ok_or(BoundedVecError::InvalidMetadata)?;
+    if bytes.len() < size {
+        return Err(BoundedVecError::BufferSize(size, bytes.len()));
+    }
+    if bytes.as_ptr().align_offset(mem::align_of::<T>()) != 0 {
+        return Err(BoundedVecError::UnalignedBuffer(mem::align_of::<T>()));
+    }
+    Ok(size)
+}
+
+/// Returns a pointer to `len` values of type `T` stored at the beginning of
+/// `bytes` and the remaining part of `bytes`. Fails if `bytes` are too small
+/// or not aligned for `T`.
+///
+/// The pointer must not be written to. Whether the bytes are valid values of
+/// `T` is up to the caller to ensure before dereferencing it.
+pub fn ptr_from_bytes<T>(bytes: &[u8], len: usize) -> Result<(*mut T, &[u8]), BoundedVecError> {
+    let size = check_value::<T>(bytes, len)?;
+    let (value, rest) = bytes.split_at(size);
+    Ok((value.as_ptr() as *mut T, rest))
+}
+
+/// Returns a mutable pointer to `len` values of type `T` stored at the
+/// beginning of `bytes` and the remaining part of `bytes`. Fails if `bytes`
+/// are too small or not aligned for `T`.
+///
+/// Whether the bytes are valid values of `T` is up to the caller to ensure
+/// before dereferencing the pointer.
+pub fn ptr_from_bytes_mut<T>(
+    bytes: &mut [u8],
+    len: usize,
+) -> Result<(*mut T, &mut [u8]), BoundedVecError> {
+    let size = check_value::<T>(bytes, len)?;
+    let (value, rest) = bytes.split_at_mut(size);
+    Ok((value.as_mut_ptr() as *mut T, rest))
+}
+
+impl BoundedVecMetadata {
+    fn check(&self) -> Result<(), BoundedVecError> {
+        if self.length > self.capacity {
+            return Err(BoundedVecError::InvalidMetadata);
+        }
+        Ok(())
+    }
+}
+
+impl CyclicBoundedVecMetadata {
+    fn check(&self) -> Result<(), BoundedVecError> {
+        // Pushing to a cyclic vector with zero capacity writes out of bounds.
+        if self.capacity == 0
+            || self.length > self.capacity
+            || self.first_index >= self.capacity
+            || self.last_index >= self.capacity
+        {
+            return Err(BoundedVecError::InvalidMetadata);
+        }
+        Ok(())
+    }
+}
+
+/// Immutable zero-copy view of a [`BoundedVec`].
+#[derive(Debug)]
+pub struct BoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    vec: mem::ManuallyDrop<BoundedVec<T>>,
+    // Ensures that the view does not outlive the buffer.
+    _bytes: PhantomData<&'a [u8]>,
+}
+
+impl<'a, T> BoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    /// Size of the metadata and `capacity` elements stored together.
+    pub fn size_in_bytes(capacity: usize) -> usize {
+        mem::size_of::<BoundedVecMetadata>() + mem::size_of::<T>() * capacity
+    }
+
+    /// Creates a view of the vector stored in `bytes` as metadata followed
+    /// by the data. Returns the view and the remaining part of `bytes`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes::<BoundedVecMetadata>(bytes, 1)?;
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Creates a view of the vector with metadata stored in `metadata_bytes`
+    /// and the data stored in `data_bytes`. Returns the view and the
+    /// remaining parts of both buffers.
+    ///
+    /// Suitable for layouts which store metadata of all vectors before
+    /// their data.
+    pub fn from_split_bytes(
+        metadata_bytes: &'a [u8],
+        data_bytes: &'a [u8],
+    ) -> Result<(Self, &'a [u8], &'a [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) = ptr_from_bytes::<BoundedVecMetadata>(metadata_bytes, 1)?;
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    fn from_metadata_ptr(
+        metadata: *mut BoundedVecMetadata,
+        data_bytes: &'a [u8],
+    ) -> Result<(Self, &'a [u8]), BoundedVecError> {
+        // SAFETY: The pointer was checked for size and alignment and
+        // `BoundedVecMetadata` consists only of integers.
+        let capacity = unsafe {
+            (*metadata).check()?;
+            (*metadata).capacity
+        };
+        let (data, data_bytes) = ptr_from_bytes::<T>(data_bytes, capacity)?;
+        // SAFETY: Both pointers were checked for size and alignment, the
+        // length doesn't exceed the capacity and `T` is `Pod`. The vector is
+        // never dropped.
+        let vec = unsafe { BoundedVec::from_raw_parts(metadata, data) };
+        Ok((
+            Self {
+                vec: mem::ManuallyDrop::new(vec),
+                _bytes: PhantomData,
+            },
+            data_bytes,
+        ))
+    }
+
+    /// Returns the underlying vector, backed by the buffer.
+    ///
+    /// Meant for zero-copy types which store [`BoundedVec`]s as fields and
+    /// manage their lifetime themselves.
+    ///
+    /// # Safety
+    ///
+    /// The returned vector must not outlive the buffer and must never be
+    /// dropped, since its data is not allocated by it. Wrapping it (or the
+    /// structure containing it) in [`mem::ManuallyDrop`] fulfills the latter.
+    pub unsafe fn into_inner(self) -> BoundedVec<T> {
+        mem::ManuallyDrop::into_inner(self.vec)
+    }
+}
+
+impl<'a, T> Deref for BoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    type Target = BoundedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+/// Mutable zero-copy view of a [`BoundedVec`].
+#[derive(Debug)]
+pub struct BoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    vec: mem::ManuallyDrop<BoundedVec<T>>,
+    // Ensures that the view does not outlive the buffer.
+    _bytes: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a, T> BoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    /// Creates a mutable view of the vector stored in `bytes` as metadata
+    /// followed by the data. Returns the view and the remaining part of
+    /// `bytes`.
+    pub fn from_bytes(bytes: &'a mut [u8]) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes_mut::<BoundedVecMetadata>(bytes, 1)?;
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Creates a mutable view of the vector with metadata stored in
+    /// `metadata_bytes` and the data stored in `data_bytes`. Returns the
+    /// view and the remaining parts of both buffers.
+    pub fn from_split_bytes(
+        metadata_bytes: &'a mut [u8],
+        data_bytes: &'a mut [u8],
+    ) -> Result<(Self, &'a mut [u8], &'a mut [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) =
+            ptr_from_bytes_mut::<BoundedVecMetadata>(metadata_bytes, 1)?;
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    /// Initializes an empty vector with the given `capacity` in `bytes` as
+    /// metadata followed by the data. Returns the view and the remaining
+    /// part of `bytes`.
+    pub fn init(
+        bytes: &'a mut [u8],
+        capacity: usize,
+    ) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes_mut::<BoundedVecMetadata>(bytes, 1)?;
+        // SAFETY: The pointer was checked for size and alignment.
+        unsafe { metadata.write(BoundedVecMetadata::new(capacity)) };
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Initializes an empty vector with the given `capacity`, with metadata
+    /// stored in `metadata_bytes` and the data stored in `data_bytes`.
+    /// Returns the view and the remaining parts of both buffers.
+    pub fn init_split(
+        metadata_bytes: &'a mut [u8],
+        data_bytes: &'a mut [u8],
+        capacity: usize,
+    ) -> Result<(Self, &'a mut [u8], &'a mut [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) =
+            ptr_from_bytes_mut::<BoundedVecMetadata>(metadata_bytes, 1)?;
+        // SAFETY: The pointer was checked for size and alignment.
+        unsafe { metadata.write(BoundedVecMetadata::new(capacity)) };
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    fn from_metadata_ptr(
+        metadata: *mut BoundedVecMetadata,
+        data_bytes: &'a mut [u8],
+    ) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        // SAFETY: The pointer was checked for size and alignment and
+        // `BoundedVecMetadata` consists only of integers.
+        let capacity = unsafe {
+            (*metadata).check()?;
+            (*metadata).capacity
+        };
+        let (data, data_bytes) = ptr_from_bytes_mut::<T>(data_bytes, capacity)?;
+        // SAFETY: Both pointers were checked for size and alignment, the
+        // length doesn't exceed the capacity and `T` is `Pod`. The vector is
+        // never dropped.
+        let vec = unsafe { BoundedVec::from_raw_parts(metadata, data) };
+        Ok((
+            Self {
+                vec: mem::ManuallyDrop::new(vec),
+                _bytes: PhantomData,
+            },
+            data_bytes,
+        ))
+    }
+
+    /// Returns the underlying vector, backed by the buffer.
+    ///
+    /// Meant for zero-copy types which store [`BoundedVec`]s as fields and
+    /// manage their lifetime themselves.
+    ///
+    /// # Safety
+    ///
+    /// The returned vector must not outlive the buffer and must never be
+    /// dropped, since its data is not allocated by it. Wrapping it (or the
+    /// structure containing it) in [`mem::ManuallyDrop`] fulfills the latter.
+    pub unsafe fn into_inner(self) -> BoundedVec<T> {
+        mem::ManuallyDrop::into_inner(self.vec)
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: T) -> Result<(), BoundedVecError> {
+        self.vec.push(value)
+    }
+
+    #[inline]
+    pub fn extend<U: IntoIterator<Item = T>>(&mut self, iter: U) -> Result<(), BoundedVecError> {
+        self.vec.extend(iter)
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.vec.as_mut_slice()
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.vec.get_mut(index)
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.vec.iter_mut()
+    }
+
+    #[inline]
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.vec.last_mut()
+    }
+}
+
+impl<'a, T> Deref for BoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    type Target = BoundedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+// Mutable access is provided only through the methods above and indexing, so
+// the vector can't be replaced with one which is not backed by the buffer.
+impl<'a, T, I> Index<I> for BoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+    I: SliceIndex<[T]>,
+{
+    type Output = I::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        self.vec.index(index)
+    }
+}
+
+impl<'a, T, I> IndexMut<I> for BoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+    I: SliceIndex<[T]>,
+{
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        self.vec.index_mut(index)
+    }
+}
+
+/// Immutable zero-copy view of a [`CyclicBoundedVec`].
+#[derive(Debug)]
+pub struct CyclicBoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    vec: mem::ManuallyDrop<CyclicBoundedVec<T>>,
+    // Ensures that the view does not outlive the buffer.
+    _bytes: PhantomData<&'a [u8]>,
+}
+
+impl<'a, T> CyclicBoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    /// Size of the metadata and `capacity` elements stored together.
+    pub fn size_in_bytes(capacity: usize) -> usize {
+        mem::size_of::<CyclicBoundedVecMetadata>() + mem::size_of::<T>() * capacity
+    }
+
+    /// Creates a view of the vector stored in `bytes` as metadata followed
+    /// by the data. Returns the view and the remaining part of `bytes`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes::<CyclicBoundedVecMetadata>(bytes, 1)?;
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Creates a view of the vector with metadata stored in `metadata_bytes`
+    /// and the data stored in `data_bytes`. Returns the view and the
+    /// remaining parts of both buffers.
+    pub fn from_split_bytes(
+        metadata_bytes: &'a [u8],
+        data_bytes: &'a [u8],
+    ) -> Result<(Self, &'a [u8], &'a [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) =
+            ptr_from_bytes::<CyclicBoundedVecMetadata>(metadata_bytes, 1)?;
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    fn from_metadata_ptr(
+        metadata: *mut CyclicBoundedVecMetadata,
+        data_bytes: &'a [u8],
+    ) -> Result<(Self, &'a [u8]), BoundedVecError> {
+        // SAFETY: The pointer was checked for size and alignment and
+        // `CyclicBoundedVecMetadata` consists only of integers.
+        let capacity = unsafe {
+            (*metadata).check()?;
+            (*metadata).capacity
+        };
+        let (data, data_bytes) = ptr_from_bytes::<T>(data_bytes, capacity)?;
+        // SAFETY: Both pointers were checked for size and alignment, the
+        // length and indices don't exceed the capacity and `T` is `Pod`. The
+        // vector is never dropped.
+        let vec = unsafe { CyclicBoundedVec::from_raw_parts(metadata, data) };
+        Ok((
+            Self {
+                vec: mem::ManuallyDrop::new(vec),
+                _bytes: PhantomData,
+            },
+            data_bytes,
+        ))
+    }
+
+    /// Returns the underlying vector, backed by the buffer.
+    ///
+    /// Meant for zero-copy types which store [`CyclicBoundedVec`]s as fields and
+    /// manage their lifetime themselves.
+    ///
+    /// # Safety
+    ///
+    /// The returned vector must not outlive the buffer and must never be
+    /// dropped, since its data is not allocated by it. Wrapping it (or the
+    /// structure containing it) in [`mem::ManuallyDrop`] fulfills the latter.
+    pub unsafe fn into_inner(self) -> CyclicBoundedVec<T> {
+        mem::ManuallyDrop::into_inner(self.vec)
+    }
+}
+
+impl<'a, T> Deref for CyclicBoundedVecZeroCopy<'a, T>
+where
+    T: Pod,
+{
+    type Target = CyclicBoundedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+/// Mutable zero-copy view of a [`CyclicBoundedVec`].
+#[derive(Debug)]
+pub struct CyclicBoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    vec: mem::ManuallyDrop<CyclicBoundedVec<T>>,
+    // Ensures that the view does not outlive the buffer.
+    _bytes: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a, T> CyclicBoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    /// Creates a mutable view of the vector stored in `bytes` as metadata
+    /// followed by the data. Returns the view and the remaining part of
+    /// `bytes`.
+    pub fn from_bytes(bytes: &'a mut [u8]) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes_mut::<CyclicBoundedVecMetadata>(bytes, 1)?;
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Creates a mutable view of the vector with metadata stored in
+    /// `metadata_bytes` and the data stored in `data_bytes`. Returns the
+    /// view and the remaining parts of both buffers.
+    pub fn from_split_bytes(
+        metadata_bytes: &'a mut [u8],
+        data_bytes: &'a mut [u8],
+    ) -> Result<(Self, &'a mut [u8], &'a mut [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) =
+            ptr_from_bytes_mut::<CyclicBoundedVecMetadata>(metadata_bytes, 1)?;
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    /// Initializes an empty vector with the given `capacity` in `bytes` as
+    /// metadata followed by the data. Returns the view and the remaining
+    /// part of `bytes`.
+    pub fn init(
+        bytes: &'a mut [u8],
+        capacity: usize,
+    ) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        let (metadata, bytes) = ptr_from_bytes_mut::<CyclicBoundedVecMetadata>(bytes, 1)?;
+        // SAFETY: The pointer was checked for size and alignment.
+        unsafe { metadata.write(CyclicBoundedVecMetadata::new(capacity)) };
+        let (vec, bytes) = Self::from_metadata_ptr(metadata, bytes)?;
+        Ok((vec, bytes))
+    }
+
+    /// Initializes an empty vector with the given `capacity`, with metadata
+    /// stored in `metadata_bytes` and the data stored in `data_bytes`.
+    /// Returns the view and the remaining parts of both buffers.
+    pub fn init_split(
+        metadata_bytes: &'a mut [u8],
+        data_bytes: &'a mut [u8],
+        capacity: usize,
+    ) -> Result<(Self, &'a mut [u8], &'a mut [u8]), BoundedVecError> {
+        let (metadata, metadata_bytes) =
+            ptr_from_bytes_mut::<CyclicBoundedVecMetadata>(metadata_bytes, 1)?;
+        // SAFETY: The pointer was checked for size and alignment.
+        unsafe { metadata.write(CyclicBoundedVecMetadata::new(capacity)) };
+        let (vec, data_bytes) = Self::from_metadata_ptr(metadata, data_bytes)?;
+        Ok((vec, metadata_bytes, data_bytes))
+    }
+
+    fn from_metadata_ptr(
+        metadata: *mut CyclicBoundedVecMetadata,
+        data_bytes: &'a mut [u8],
+    ) -> Result<(Self, &'a mut [u8]), BoundedVecError> {
+        // SAFETY: The pointer was checked for size and alignment and
+        // `CyclicBoundedVecMetadata` consists only of integers.
+        let capacity = unsafe {
+            (*metadata).check()?;
+            (*metadata).capacity
+        };
+        let (data, data_bytes) = ptr_from_bytes_mut::<T>(data_bytes, capacity)?;
+        // SAFETY: Both pointers were checked for size and alignment, the
+        // length and indices don't exceed the capacity and `T` is `Pod`. The
+        // vector is never dropped.
+        let vec = unsafe { CyclicBoundedVec::from_raw_parts(metadata, data) };
+        Ok((
+            Self {
+                vec: mem::ManuallyDrop::new(vec),
+                _bytes: PhantomData,
+            },
+            data_bytes,
+        ))
+    }
+
+    /// Returns the underlying vector, backed by the buffer.
+    ///
+    /// Meant for zero-copy types which store [`CyclicBoundedVec`]s as fields and
+    /// manage their lifetime themselves.
+    ///
+    /// # Safety
+    ///
+    /// The returned vector must not outlive the buffer and must never be
+    /// dropped, since its data is not allocated by it. Wrapping it (or the
+    /// structure containing it) in [`mem::ManuallyDrop`] fulfills the latter.
+    pub unsafe fn into_inner(self) -> CyclicBoundedVec<T> {
+        mem::ManuallyDrop::into_inner(self.vec)
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.vec.push(value)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.vec.get_mut(index)
+    }
+
+    #[inline]
+    pub fn first_mut(&mut self) -> Option<&mut T> {
+        self.vec.first_mut()
+    }
+
+    #[inline]
+    pub fn last_mut(&mut self) -> Option<&mut T> {
+        self.vec.last_mut()
+    }
+}
+
+impl<'a, T> Deref for CyclicBoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    type Target = CyclicBoundedVec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+// Mutable access is provided only through the methods above and indexing, so
+// the vector can't be replaced with one which is not backed by the buffer.
+impl<'a, T> Index<usize> for CyclicBoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        self.vec.index(index)
+    }
+}
+
+impl<'a, T> IndexMut<usize> for CyclicBoundedVecZeroCopyMut<'a, T>
+where
+    T: Pod,
+{
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.vec.index_mut(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns an 8-byte aligned buffer of (at least) the given size.
+    fn aligned_buffer(size: usize) -> Vec<u64> {
+        vec![0u64; size.div_ceil(8)]
+    }
+
+    /// Tests:
+    /// 1. init + push, then reading the vectors from the same buffer with
+    ///    immutable and mutable views
+    /// 2. layouts with metadata of all vectors stored before the data
+    /// 3. too small buffer fails
+    /// 4. unaligned buffer fails
+    /// 5. inconsistent metadata fails
+    #[test]
+    fn test_bounded_vec_zero_copy() {
+        let size = BoundedVecZeroCopy::<[u8; 32]>::size_in_bytes(4)
+            + CyclicBoundedVecZeroCopy::<[u8; 32]>::size_in_bytes(3);
+        let mut buf = aligned_buffer(size);
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+
+        // 1. contiguous layout
+        {
+            let (mut vec, bytes) = BoundedVecZeroCopyMut::<[u8; 32]>::init(bytes, 4).unwrap();
+            let (mut cyclic_vec, bytes) =
+                CyclicBoundedVecZeroCopyMut::<[u8; 32]>::init(bytes, 3).unwrap();
+            assert!(bytes.len() < 8);
+            for i in 0..4 {
+                vec.push([i; 32]).unwrap();
+                cyclic_vec.push([i; 32]);
+            }
+            assert_eq!(vec.push([4; 32]), Err(BoundedVecError::Full));
+        }
+        {
+            let (vec, bytes) = BoundedVecZeroCopy::<[u8; 32]>::from_bytes(bytes).unwrap();
+            let (cyclic_vec, _) = CyclicBoundedVecZeroCopy::<[u8; 32]>::from_bytes(bytes).unwrap();
+            assert_eq!(vec.as_slice(), &[[0; 32], [1; 32], [2; 32], [3; 32]]);
+            assert_eq!(
+                cyclic_vec.iter().copied().collect::<Vec<_>>(),
+                vec![[1; 32], [2; 32], [3; 32]]
+            );
+        }
+        {
+            let (mut vec, bytes) = BoundedVecZeroCopyMut::<[u8; 32]>::from_bytes(bytes).unwrap();
+            let (mut cyclic_vec, _) =
+                CyclicBoundedVecZeroCopyMut::<[u8; 32]>::from_bytes(bytes).unwrap();
+            vec[0] = [5; 32];
+            cyclic_vec.push([5; 32]);
+        }
+        let (vec, bytes) = BoundedVecZeroCopy::<[u8; 32]>::from_bytes(bytes).unwrap();
+        let (cyclic_vec, _) = CyclicBoundedVecZeroCopy::<[u8; 32]>::from_bytes(bytes).unwrap();
+        assert_eq!(vec[0], [5; 32]);
+        assert_eq!(cyclic_vec.first(), Some(&[2; 32]));
+        assert_eq!(cyclic_vec.last(), Some(&[5; 32]));
+
+        // 2. split layout
+        let metadata_size =
+            mem::size_of::<BoundedVecMetadata>() + mem::size_of::<CyclicBoundedVecMetadata>();
+        let mut buf = aligned_buffer(size);
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+        {
+            let (metadata_bytes, data_bytes) = bytes.split_at_mut(metadata_size);
+            let (mut vec, metadata_bytes, data_bytes) =
+                BoundedVecZeroCopyMut::<u64>::init_split(metadata_bytes, data_bytes, 4).unwrap();
+            let (mut cyclic_vec, metadata_bytes, _) =
+                CyclicBoundedVecZeroCopyMut::<u64>::init_split(metadata_bytes, data_bytes, 3)
+                    .unwrap();
+            assert!(metadata_bytes.is_empty());
+            vec.push(1).unwrap();
+            cyclic_vec.push(2);
+        }
+        let (metadata_bytes, data_bytes) = bytes.split_at(metadata_size);
+        let (vec, metadata_bytes, data_bytes) =
+            BoundedVecZeroCopy::<u64>::from_split_bytes(metadata_bytes, data_bytes).unwrap();
+        let (cyclic_vec, _, _) =
+            CyclicBoundedVecZeroCopy::<u64>::from_split_bytes(metadata_bytes, data_bytes).unwrap();
+        assert_eq!(vec.as_slice(), &[1]);
+        assert_eq!(cyclic_vec.first(), Some(&2));
+        assert_eq!(
+            &bytes[metadata_size..metadata_size + 8],
+            &1u64.to_ne_bytes()
+        );
+        assert_eq!(
+            &bytes[metadata_size + 32..metadata_size + 40],
+            &2u64.to_ne_bytes()
+        );
+
+        // 3. too small buffer
+        let mut buf = aligned_buffer(size);
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+        assert!(matches!(
+            BoundedVecZeroCopyMut::<[u8; 32]>::init(&mut bytes[..8], 4),
+            Err(BoundedVecError::BufferSize(16, 8))
+        ));
+        assert!(matches!(
+            BoundedVecZeroCopyMut::<[u8; 32]>::init(&mut bytes[..100], 4),
+            Err(BoundedVecError::BufferSize(128, 84))
+        ));
+
+        // 4. unaligned buffer
+        assert!(matches!(
+            BoundedVecZeroCopyMut::<[u8; 32]>::init(&mut bytes[1..], 4),
+            Err(BoundedVecError::UnalignedBuffer(8))
+        ));
+        assert!(matches!(
+            BoundedVecZeroCopy::<u64>::from_bytes(&bytes[4..]),
+            Err(BoundedVecError::UnalignedBuffer(8))
+        ));
+
+        // 5. inconsistent metadata
+        bytes[..16].copy_from_slice(&BoundedVecMetadata::new_with_length(2, 3).to_ne_bytes());
+        assert!(matches!(
+            BoundedVecZeroCopy::<u64>::from_bytes(bytes),
+            Err(BoundedVecError::InvalidMetadata)
+        ));
+        bytes[..16].copy_from_slice(&BoundedVecMetadata::new(usize::MAX).to_ne_bytes());
+        assert!(matches!(
+            BoundedVecZeroCopy::<u64>::from_bytes(bytes),
+            Err(BoundedVecError::InvalidMetadata)
+        ));
+        for metadata in [
+            CyclicBoundedVecMetadata::new(0),
+            CyclicBoundedVecMetadata::new_with_indices(2, 3, 0, 1),
+            CyclicBoundedVecMetadata::new_with_indices(2, 2, 2, 1),
+            CyclicBoundedVecMetadata::new_with_indices(2, 2, 0, 2),
+        ] {
+            bytes[..32].copy_from_slice(&metadata.to_ne_bytes());
+            assert!(matches!(
+                CyclicBoundedVecZeroCopy::<u64>::from_bytes(bytes),
+                Err(BoundedVecError::InvalidMetadata)
+            ));
+            assert!(matches!(
+                CyclicBoundedVecZeroCopyMut::<u64>::from_bytes(bytes),
+                Err(BoundedVecError::InvalidMetadata)
+            ));
+        }
+    }
+
+    /// Tests:
+    /// 1. mutable views of bounded vectors modify the buffer through the
+    ///    wrapper methods and indexing
+    /// 2. mutable views of cyclic bounded vectors modify the buffer through
+    ///    the wrapper methods and indexing
+    /// 3. vectors returned by `into_inner` are backed by the buffer
+    #[test]
+    fn test_bounded_vec_zero_copy_mut_methods() {
+        let size = BoundedVecZeroCopy::<u64>::size_in_bytes(8)
+            + CyclicBoundedVecZeroCopy::<u64>::size_in_bytes(4);
+        let mut buf = aligned_buffer(size);
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+
+        // 1. bounded vector
+        {
+            let (mut vec, _) = BoundedVecZeroCopyMut::<u64>::init(bytes, 8).unwrap();
+            vec.push(1).unwrap();
+            vec.extend([2, 3, 4]).unwrap();
+            *vec.get_mut(0).unwrap() = 10;
+            assert!(vec.get_mut(4).is_none());
+            *vec.last_mut().unwrap() = 40;
+            vec[1] = 20;
+            vec[2..3].copy_from_slice(&[30]);
+            for element in vec.iter_mut() {
+                *element += 1;
+            }
+            vec.as_mut_slice().reverse();
+            assert_eq!(vec.extend([0; 5]), Err(BoundedVecError::Full));
+        }
+        let (vec, bytes) = BoundedVecZeroCopyMut::<u64>::from_bytes(bytes).unwrap();
+        assert_eq!(vec.as_slice(), &[41, 31, 21, 11, 0, 0, 0, 0]);
+        assert_eq!(vec[..2], [41, 31]);
+
+        // 2. cyclic bounded vector
+        {
+            let (mut cyclic_vec, _) = CyclicBoundedVecZeroCopyMut::<u64>::init(bytes, 4).unwrap();
+            for i in 0..6 {
+                cyclic_vec.push(i);
+            }
+            *cyclic_vec.first_mut().unwrap() = 20;
+            *cyclic_vec.last_mut().unwrap() = 50;
+            *cyclic_vec.get_mut(3).unwrap() = 30;
+            cyclic_vec[0] = 40;
+            assert!(cyclic_vec.get_mut(4).is_none());
+        }
+        let (cyclic_vec, _) = CyclicBoundedVecZeroCopy::<u64>::from_bytes(bytes).unwrap();
+        assert_eq!(
+            cyclic_vec.iter().copied().collect::<Vec<_>>(),
+            vec![20, 30, 40, 50]
+        );
+
+        // 3. `into_inner`
+        {
+            let (vec, _) = BoundedVecZeroCopyMut::<u64>::from_bytes(bytes).unwrap();
+            let mut vec = mem::ManuallyDrop::new(unsafe { vec.into_inner() });
+            vec[0] = 100;
+        }
+        let (vec, _) = BoundedVecZeroCopy::<u64>::from_bytes(bytes).unwrap();
+        assert_eq!(vec[0], 100);
+    }
+
+    /// Tests:
+    /// 1. pointers to values stored at the beginning of the buffer
+    /// 2. too small buffer fails
+    /// 3. unaligned buffer fails
+    #[test]
+    fn test_ptr_from_bytes() {
+        let mut buf = aligned_buffer(32);
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut buf);
+
+        // 1. values
+        {
+            let (value, bytes) = ptr_from_bytes_mut::<u64>(bytes, 1).unwrap();
+            let (array, bytes) = ptr_from_bytes_mut::<[u8; 8]>(bytes, 2).unwrap();
+            assert_eq!(bytes.len(), 8);
+            unsafe {
+                *value = 1;
+                *array.add(1) = [2; 8];
+            }
+        }
+        let (value, bytes_rest) = ptr_from_bytes::<u64>(bytes, 1).unwrap();
+        assert_eq!(unsafe { *value }, 1);
+        assert_eq!(&bytes_rest[8..16], &[2; 8]);
+
+        // 2. too small buffer
+        assert!(matches!(
+            ptr_from_bytes::<u64>(&bytes[..16], 3),
+            Err(BoundedVecError::BufferSize(24, 16))
+        ));
+        assert!(matches!(
+            ptr_from_bytes::<u64>(bytes, usize::MAX),
+            Err(BoundedVecError::InvalidMetadata)
+        ));
+
+        // 3. unaligned buffer
+        assert!(matches!(
+            ptr_from_bytes_mut::<u64>(&mut bytes[2..], 1),
+            Err(BoundedVecError::UnalignedBuffer(8))
+        ));
+    }
+}
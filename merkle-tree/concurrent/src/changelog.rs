@@ -1,18 +1,46 @@
+use bytemuck::{Pod, Zeroable};
 use light_bounded_vec::BoundedVec;
 
 use crate::errors::ConcurrentMerkleTreeError;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Number of low bits of [`ChangelogEntry::index`] which store the leaf index.
+/// The remaining high bits store the number of leaves of a batch append.
+const LEAF_INDEX_BITS: u32 = 40;
+const LEAF_INDEX_MASK: u64 = (1 << LEAF_INDEX_BITS) - 1;
+/// Maximum number of leaves of a batch append which can be stored in a
+/// changelog entry.
+pub const MAX_BATCH_SIZE: usize = (1 << (u64::BITS - LEAF_INDEX_BITS)) - 1;
+
+/// Changelog entry of a single operation on the tree.
+///
+/// There are two kinds of entries:
+///
+/// * Entries of leaf updates and single leaf appends, with the `path` of the
+///   affected leaf.
+/// * Entries of batch appends, which cover all leaves of the batch. The
+///   `index` is the index of the first appended leaf and the number of
+///   appended leaves is stored in the high bits of `index`, which keeps the
+///   size of the entry (and of tree accounts) unchanged. The `path` contains,
+///   on every level, the nodes which replace nodes of Merkle proofs of
+///   preceding leaves. See [`ChangelogEntry::new_batch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(C)]
 pub struct ChangelogEntry<const HEIGHT: usize> {
     /// Root.
     pub root: [u8; 32],
     // Path of the changelog.
     pub path: [[u8; 32]; HEIGHT],
-    // Index of the affected leaf.
+    // Index of the affected leaf, and the number of leaves of a batch append
+    // in the high bits.
     pub index: u64,
 }
 
+// SAFETY: The struct is `repr(C)` and consists only of byte arrays and an
+// `u64` following them, all of which have sizes divisible by 8, so there is
+// no padding. Any bit pattern is a valid value.
+unsafe impl<const HEIGHT: usize> Zeroable for ChangelogEntry<HEIGHT> {}
+unsafe impl<const HEIGHT: usize> Pod for ChangelogEntry<HEIGHT> {}
+
 pub type ChangelogEntry22 = ChangelogEntry<22>;
 pub type ChangelogEntry26 = ChangelogEntry<26>;
 pub type ChangelogEntry32 = ChangelogEntry<32>;
@@ -32,8 +60,41 @@ impl<const HEIGHT: usize> ChangelogEntry<HEIGHT> {
         }
     }
 
+    /// Creates an entry of a batch append of `batch_size` leaves, starting at
+    /// `first_index`.
+    ///
+    /// On every level, the `path` has to contain:
+    ///
+    /// * The ancestor of the first leaf, if it is a right child. It replaces
+    ///   the node of proofs of preceding leaves which share the path with the
+    ///   first leaf up to the level above.
+    /// * Otherwise the right sibling of the ancestor of the first leaf. It
+    ///   replaces the node of proofs of preceding leaves which share the path
+    ///   with the first leaf up to this level. If the sibling doesn't contain
+    ///   any appended leaf, it is a zero node.
+    pub fn new_batch(
+        root: [u8; 32],
+        path: [[u8; 32]; HEIGHT],
+        first_index: usize,
+        batch_size: usize,
+    ) -> Self {
+        let index = first_index as u64 | ((batch_size as u64) << LEAF_INDEX_BITS);
+        Self { root, path, index }
+    }
+
+    /// Returns the index of the affected leaf, for batch appends the index of
+    /// the first appended leaf.
     pub fn index(&self) -> usize {
-        self.index as usize
+        (self.index & LEAF_INDEX_MASK) as usize
+    }
+
+    /// Returns the number of appended leaves if the entry belongs to a batch
+    /// append.
+    pub fn batch_size(&self) -> Option<usize> {
+        match (self.index >> LEAF_INDEX_BITS) as usize {
+            0 => None,
+            batch_size => Some(batch_size),
+        }
     }
 
     /// Returns an intersection index in the changelog entry which affects the
@@ -60,6 +121,9 @@ impl<const HEIGHT: usize> ChangelogEntry<HEIGHT> {
         leaf_index: usize,
         proof: &mut BoundedVec<[u8; 32]>,
     ) -> Result<(), ConcurrentMerkleTreeError> {
+        if self.batch_size().is_some() {
+            return self.update_proof_batch(leaf_index, proof);
+        }
         if leaf_index != self.index() {
             let intersection_index = self.intersection_index(leaf_index);
             proof[intersection_index] = self.path[intersection_index];
@@ -73,6 +137,33 @@ impl<const HEIGHT: usize> ChangelogEntry<HEIGHT> {
 
         Ok(())
     }
+
+    /// Updates the proof of a leaf preceding a batch append.
+    ///
+    /// Above the intersection with the path of the first appended leaf, the
+    /// proof shares the path with the first appended leaf. Nodes of the proof
+    /// are right siblings of that path on every level on which the path is a
+    /// left child, and they might contain appended leaves.
+    fn update_proof_batch(
+        &self,
+        leaf_index: usize,
+        proof: &mut BoundedVec<[u8; 32]>,
+    ) -> Result<(), ConcurrentMerkleTreeError> {
+        let first_index = self.index();
+        // Leaves of the batch were empty before the batch, they can't have
+        // any older proof.
+        if leaf_index >= first_index {
+            return Err(ConcurrentMerkleTreeError::CannotUpdateLeaf);
+        }
+        let intersection_index = self.intersection_index(leaf_index);
+        proof[intersection_index] = self.path[intersection_index];
+        for level in intersection_index + 1..proof.len().min(HEIGHT) {
+            if (first_index >> level) % 2 == 0 {
+                proof[level] = self.path[level];
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
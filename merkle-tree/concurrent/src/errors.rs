@@ -32,6 +32,12 @@ pub enum ConcurrentMerkleTreeError {
     EmptyLeaves,
     #[error("Invalid buffer size, expected {0}, got {1}")]
     BufferSize(usize, usize),
+    #[error("Invalid sequence number {0}, the current sequence number is {1}")]
+    InvalidSequenceNumber(usize, usize),
+    #[error("Changelog window exceeded, the proof computed for sequence number {0} can't be updated, the oldest sequence number in the changelog is {1}")]
+    ChangelogWindowExceeded(usize, usize),
+    #[error("Number of leaves ({0}) exceeds the maximum batch size ({1}).")]
+    BatchTooLarge(usize, usize),
     #[error("Hasher error: {0}")]
     Hasher(#[from] HasherError),
     #[error("Bounded vector error: {0}")]
@@ -58,6 +64,9 @@ impl From<ConcurrentMerkleTreeError> for u32 {
             ConcurrentMerkleTreeError::CannotUpdateEmpty => 10012,
             ConcurrentMerkleTreeError::EmptyLeaves => 10013,
             ConcurrentMerkleTreeError::BufferSize(_, _) => 10014,
+            ConcurrentMerkleTreeError::InvalidSequenceNumber(_, _) => 10015,
+            ConcurrentMerkleTreeError::ChangelogWindowExceeded(_, _) => 10016,
+            ConcurrentMerkleTreeError::BatchTooLarge(_, _) => 10017,
             ConcurrentMerkleTreeError::Hasher(e) => e.into(),
             ConcurrentMerkleTreeError::BoundedVec(e) => e.into(),
         }
@@ -21,7 +21,7 @@ pub mod hash;
 pub mod zero_copy;
 
 use crate::{
-    changelog::ChangelogEntry,
+    changelog::{ChangelogEntry, MAX_BATCH_SIZE},
     errors::ConcurrentMerkleTreeError,
     event::PathNode,
     hash::{compute_parent_node, compute_root},
@@ -276,6 +276,16 @@ where
         Ok(())
     }
 
+    fn inc_next_index_by(&mut self, num_leaves: usize) -> Result<(), ConcurrentMerkleTreeError> {
+        unsafe {
+            *self.next_index = self
+                .next_index()
+                .checked_add(num_leaves)
+                .ok_or(ConcurrentMerkleTreeError::IntegerOverflow)?;
+        }
+        Ok(())
+    }
+
     pub fn sequence_number(&self) -> usize {
         unsafe { *self.sequence_number }
     }
@@ -375,6 +385,84 @@ where
         Ok(())
     }
 
+    /// Returns the index of the changelog entry created by the operation with
+    /// the given `sequence_number`.
+    ///
+    /// Fails if the entry was already overwritten, which means that the
+    /// changelog window has been exceeded.
+    pub fn changelog_index_of(
+        &self,
+        sequence_number: usize,
+    ) -> Result<usize, ConcurrentMerkleTreeError> {
+        let current_sequence_number = self.sequence_number();
+        if sequence_number > current_sequence_number {
+            return Err(ConcurrentMerkleTreeError::InvalidSequenceNumber(
+                sequence_number,
+                current_sequence_number,
+            ));
+        }
+        // Every operation creates exactly one changelog entry, the newest
+        // entry belongs to the current sequence number.
+        let changes = current_sequence_number - sequence_number;
+        if changes >= self.changelog.len() {
+            return Err(ConcurrentMerkleTreeError::ChangelogWindowExceeded(
+                sequence_number,
+                current_sequence_number + 1 - self.changelog.len(),
+            ));
+        }
+        let capacity = self.changelog.capacity();
+        Ok((self.changelog_index() + capacity - changes) % capacity)
+    }
+
+    /// Updates a stale Merkle `proof` of `leaf`, computed against the root
+    /// with the given `sequence_number`, to a proof valid against the current
+    /// root. Meant to be used off-chain, on a tree fetched from an account.
+    ///
+    /// The `proof` can either be a full proof or a proof without the canopy
+    /// nodes, which are then taken from the canopy of the tree.
+    ///
+    /// # Errors
+    ///
+    /// * [`ConcurrentMerkleTreeError::ChangelogWindowExceeded`] if the
+    ///   changelog doesn't contain all changes made after `sequence_number`.
+    ///   The proof has to be fetched again.
+    /// * [`ConcurrentMerkleTreeError::CannotUpdateLeaf`] if the leaf itself
+    ///   was modified after `sequence_number`.
+    /// * [`ConcurrentMerkleTreeError::InvalidProof`] if the updated proof
+    ///   doesn't produce the current root, i.e. the provided proof wasn't
+    ///   valid for `sequence_number`.
+    pub fn update_proof_from_sequence_number(
+        &self,
+        leaf: &[u8; 32],
+        leaf_index: usize,
+        proof: &[[u8; 32]],
+        sequence_number: usize,
+    ) -> Result<BoundedVec<[u8; 32]>, ConcurrentMerkleTreeError> {
+        if leaf_index >= self.next_index() {
+            return Err(ConcurrentMerkleTreeError::CannotUpdateEmpty);
+        }
+        let proof_len_without_canopy = self.height - self.canopy_depth;
+        if proof.len() != self.height && proof.len() != proof_len_without_canopy {
+            return Err(ConcurrentMerkleTreeError::InvalidProofLength(
+                self.height,
+                proof.len(),
+            ));
+        }
+        let changelog_index = self.changelog_index_of(sequence_number)?;
+
+        let mut updated_proof = BoundedVec::with_capacity(self.height);
+        for node in proof.iter() {
+            updated_proof.push(*node)?;
+        }
+        if updated_proof.len() == proof_len_without_canopy && self.canopy_depth > 0 {
+            self.update_proof_from_canopy(leaf_index, &mut updated_proof)?;
+        }
+        self.update_proof_from_changelog(changelog_index, leaf_index, &mut updated_proof)?;
+        self.validate_proof(leaf, leaf_index, &updated_proof)?;
+
+        Ok(updated_proof)
+    }
+
     /// Checks whether the given Merkle `proof` for the given `node` (with index
     /// `i`) is valid. The proof is valid when computing parent node hashes using
     /// the whole path of the proof gives the same result as the given `root`.
@@ -654,6 +742,156 @@ where
         Ok((first_changelog_index, first_sequence_number))
     }
 
+    /// Appends a batch of new leaves to the tree by hashing aligned subtrees
+    /// of the batch.
+    ///
+    /// The batch is split into the largest subtrees aligned to their size,
+    /// e.g. appending 7 leaves at `next_index` 3 results in subtrees
+    /// `[3]`, `[4..8]` and `[8..10]`. Every subtree is hashed once and merged
+    /// into `filled_subtrees` as a single node.
+    ///
+    /// The whole batch is a single operation: it pushes one batch changelog
+    /// entry (see [`ChangelogEntry::new_batch`]) and one root, and increments
+    /// the sequence number once.
+    ///
+    /// Returns the index of the changelog entry and the sequence number of
+    /// the batch.
+    pub fn append_batch_subtrees(
+        &mut self,
+        leaves: &[&[u8; 32]],
+    ) -> Result<(usize, usize), ConcurrentMerkleTreeError> {
+        if leaves.is_empty() {
+            return Err(ConcurrentMerkleTreeError::EmptyLeaves);
+        }
+        if (self.next_index() + leaves.len() - 1) >= 1 << self.height {
+            return Err(ConcurrentMerkleTreeError::TreeFull);
+        }
+        if leaves.len() > MAX_BATCH_SIZE {
+            return Err(ConcurrentMerkleTreeError::BatchTooLarge(
+                leaves.len(),
+                MAX_BATCH_SIZE,
+            ));
+        }
+
+        let first_leaf_index = self.next_index();
+
+        // Ancestors of the new leaves on each level (including the leaves
+        // and the root), ordered by their index. The first node on `level`
+        // is the ancestor of the first leaf, with index
+        // `first_leaf_index >> level`. Every ancestor is computed exactly
+        // once, after all new leaves below it are known.
+        let mut nodes: Vec<Vec<[u8; 32]>> = vec![Vec::new(); self.height + 1];
+
+        let mut subtree_start = 0;
+        while subtree_start < leaves.len() {
+            let subtree_index = first_leaf_index + subtree_start;
+            let subtree_level = self.subtree_level(subtree_index, leaves.len() - subtree_start);
+            let subtree_end = subtree_start + (1 << subtree_level);
+
+            nodes[0].extend(leaves[subtree_start..subtree_end].iter().map(|leaf| **leaf));
+            for level in 0..subtree_level {
+                let layer = &nodes[level][nodes[level].len() - (1 << (subtree_level - level))..];
+                self.filled_subtrees[level] = layer[layer.len() - 2];
+                let parents = layer
+                    .chunks(2)
+                    .map(|pair| H::hashv(&[&pair[0], &pair[1]]))
+                    .collect::<Result<Vec<_>, _>>()?;
+                nodes[level + 1].extend(parents);
+            }
+
+            // PANICS: The subtree root was pushed above.
+            let mut current_node = *nodes[subtree_level].last().unwrap();
+            let mut current_index = subtree_index >> subtree_level;
+            for level in subtree_level..self.height {
+                let is_left = current_index % 2 == 0;
+
+                if is_left {
+                    self.filled_subtrees[level] = current_node;
+
+                    // For all non-terminal subtrees, stop computing parents
+                    // as soon as we are on the left side. The parent is
+                    // computed by one of the next subtrees.
+                    if subtree_end < leaves.len() {
+                        break;
+                    }
+
+                    current_node = H::hashv(&[&current_node, &H::zero_bytes()[level]])?;
+                } else {
+                    current_node = H::hashv(&[&self.filled_subtrees[level], &current_node])?;
+                }
+                nodes[level + 1].push(current_node);
+
+                current_index /= 2;
+            }
+
+            subtree_start = subtree_end;
+        }
+
+        // PANICS: The last subtree always computes the root.
+        let root = *nodes[self.height].last().unwrap();
+        let mut path = [[0u8; 32]; HEIGHT];
+        for (level, node) in path.iter_mut().enumerate().take(self.height) {
+            let first_node_index = first_leaf_index >> level;
+            *node = if first_node_index % 2 == 1 {
+                nodes[level][0]
+            } else {
+                nodes[level]
+                    .get(1)
+                    .copied()
+                    .unwrap_or(H::zero_bytes()[level])
+            };
+        }
+        self.changelog.push(ChangelogEntry::new_batch(
+            root,
+            path,
+            first_leaf_index,
+            leaves.len(),
+        ));
+        self.roots.push(root);
+
+        self.inc_next_index_by(leaves.len())?;
+        self.inc_sequence_number()?;
+
+        // PANICS: `leaves` is not empty.
+        self.set_rightmost_leaf(leaves.last().unwrap());
+
+        if self.canopy_depth > 0 {
+            self.update_canopy_from_nodes(first_leaf_index, &nodes);
+        }
+
+        Ok((self.changelog.last_index(), self.sequence_number()))
+    }
+
+    /// Returns the level of the largest subtree which can be appended at
+    /// `next_index`, being aligned to its size and having at most
+    /// `num_leaves` leaves.
+    fn subtree_level(&self, next_index: usize, num_leaves: usize) -> usize {
+        let alignment_level = if next_index == 0 {
+            self.height
+        } else {
+            next_index.trailing_zeros() as usize
+        };
+        alignment_level.min(num_leaves.ilog2() as usize)
+    }
+
+    /// Writes the ancestors of appended leaves, computed by
+    /// [`append_batch_subtrees`](ConcurrentMerkleTree::append_batch_subtrees),
+    /// to the canopy.
+    fn update_canopy_from_nodes(&mut self, first_leaf_index: usize, nodes: &[Vec<[u8; 32]>]) {
+        for (level, level_nodes) in nodes
+            .iter()
+            .enumerate()
+            .take(self.height)
+            .skip(self.height - self.canopy_depth)
+        {
+            let first_node_index = (1 << (self.height - level)) + (first_leaf_index >> level);
+            for (i, node) in level_nodes.iter().enumerate() {
+                // `index - 2` maps to the canopy index.
+                self.canopy[first_node_index + i - 2] = *node;
+            }
+        }
+    }
+
     fn update_canopy(&mut self, first_changelog_index: usize, num_leaves: usize) {
         for i in 0..num_leaves {
             let changelog_index = (first_changelog_index + i) % self.changelog.capacity();
@@ -665,10 +903,10 @@ where
                 .enumerate()
             {
                 let level = self.height - i - 1;
-                let index =
-                    (1 << (self.height - level)) + (self.changelog[changelog_index].index >> level);
+                let index = (1 << (self.height - level))
+                    + (self.changelog[changelog_index].index() >> level);
                 // `index - 2` maps to the canopy index.
-                self.canopy[(index - 2) as usize] = *path_node;
+                self.canopy[index - 2] = *path_node;
             }
         }
     }
@@ -691,7 +929,7 @@ where
                 let level =
                     u32::try_from(level).map_err(|_| ConcurrentMerkleTreeError::IntegerOverflow)?;
                 let index = (1 << (self.height as u32 - level))
-                    + (self.changelog[changelog_index].index as u32 >> level);
+                    + (self.changelog[changelog_index].index() as u32 >> level);
                 path.push(PathNode {
                     node: node.to_owned(),
                     index,
@@ -708,7 +946,7 @@ where
         }
 
         let index: u32 = self.changelog[first_changelog_index]
-            .index
+            .index()
             .try_into()
             .map_err(|_| ConcurrentMerkleTreeError::IntegerOverflow)?;
         Ok(MerkleTreeEvent::V1(ChangelogEvent {
@@ -5,14 +5,80 @@ use std::{
 };
 
 use light_bounded_vec::{
-    BoundedVec, BoundedVecMetadata, CyclicBoundedVec, CyclicBoundedVecMetadata,
+    zero_copy::{
+        ptr_from_bytes, ptr_from_bytes_mut, BoundedVecZeroCopy, BoundedVecZeroCopyMut,
+        CyclicBoundedVecZeroCopy, CyclicBoundedVecZeroCopyMut,
+    },
+    BoundedVecMetadata, CyclicBoundedVecMetadata,
 };
 use light_hasher::Hasher;
-use light_utils::offset::zero_copy::{read_array_like_ptr_at, read_ptr_at, write_at};
+use light_utils::offset::zero_copy::write_at;
 use memoffset::{offset_of, span_of};
 
 use crate::{errors::ConcurrentMerkleTreeError, ConcurrentMerkleTree};
 
+/// Checks whether `bytes` are large enough to store the tree described by
+/// the metadata they contain. Returns the height and canopy depth.
+fn check_size<H, const HEIGHT: usize>(
+    bytes: &[u8],
+) -> Result<(usize, usize), ConcurrentMerkleTreeError>
+where
+    H: Hasher,
+{
+    let expected_size = ConcurrentMerkleTree::<H, HEIGHT>::non_dyn_fields_size();
+    if bytes.len() < expected_size {
+        return Err(ConcurrentMerkleTreeError::BufferSize(
+            expected_size,
+            bytes.len(),
+        ));
+    }
+
+    let height = usize::from_ne_bytes(
+        bytes[span_of!(ConcurrentMerkleTree<H, HEIGHT>, height)]
+            .try_into()
+            .unwrap(),
+    );
+    let canopy_depth = usize::from_ne_bytes(
+        bytes[span_of!(ConcurrentMerkleTree<H, HEIGHT>, canopy_depth)]
+            .try_into()
+            .unwrap(),
+    );
+
+    let changelog_metadata_offset = offset_of!(ConcurrentMerkleTree<H, HEIGHT>, next_index)
+        + mem::size_of::<usize>()
+        + mem::size_of::<usize>()
+        + mem::size_of::<[u8; 32]>()
+        + mem::size_of::<BoundedVecMetadata>();
+    let roots_metadata_offset =
+        changelog_metadata_offset + mem::size_of::<CyclicBoundedVecMetadata>();
+    let changelog_metadata = CyclicBoundedVecMetadata::from_ne_bytes(
+        bytes[changelog_metadata_offset..roots_metadata_offset]
+            .try_into()
+            .unwrap(),
+    );
+    let roots_metadata = CyclicBoundedVecMetadata::from_ne_bytes(
+        bytes[roots_metadata_offset
+            ..roots_metadata_offset + mem::size_of::<CyclicBoundedVecMetadata>()]
+            .try_into()
+            .unwrap(),
+    );
+
+    let expected_size = ConcurrentMerkleTree::<H, HEIGHT>::size_in_account(
+        height,
+        changelog_metadata.capacity(),
+        roots_metadata.capacity(),
+        canopy_depth,
+    );
+    if bytes.len() < expected_size {
+        return Err(ConcurrentMerkleTreeError::BufferSize(
+            expected_size,
+            bytes.len(),
+        ));
+    }
+
+    Ok((height, canopy_depth))
+}
+
 #[derive(Debug)]
 pub struct ConcurrentMerkleTreeZeroCopy<'a, H, const HEIGHT: usize>
 where
@@ -28,98 +94,56 @@ impl<'a, H, const HEIGHT: usize> ConcurrentMerkleTreeZeroCopy<'a, H, HEIGHT>
 where
     H: Hasher,
 {
+    /// Creates `ConcurrentMerkleTree` backed by `bytes`. Returns the tree and
+    /// the remaining part of `bytes`.
+    ///
+    /// The returned tree must not be dropped, nor outlive `bytes`.
     pub fn struct_from_bytes_zero_copy(
         bytes: &'a [u8],
-    ) -> Result<(ConcurrentMerkleTree<H, HEIGHT>, usize), ConcurrentMerkleTreeError> {
-        let expected_size = ConcurrentMerkleTree::<H, HEIGHT>::non_dyn_fields_size();
-        if bytes.len() < expected_size {
-            return Err(ConcurrentMerkleTreeError::BufferSize(
-                expected_size,
-                bytes.len(),
-            ));
-        }
-
-        let height = usize::from_ne_bytes(
-            bytes[span_of!(ConcurrentMerkleTree<H, HEIGHT>, height)]
-                .try_into()
-                .unwrap(),
-        );
-        let canopy_depth = usize::from_ne_bytes(
-            bytes[span_of!(ConcurrentMerkleTree<H, HEIGHT>, canopy_depth)]
-                .try_into()
-                .unwrap(),
-        );
-
-        let mut offset = offset_of!(ConcurrentMerkleTree<H, HEIGHT>, next_index);
-
-        let next_index = unsafe { read_ptr_at(bytes, &mut offset) };
-        let sequence_number = unsafe { read_ptr_at(bytes, &mut offset) };
-        let rightmost_leaf = unsafe { read_ptr_at(bytes, &mut offset) };
-        let filled_subtrees_metadata = unsafe { read_ptr_at(bytes, &mut offset) };
-        let changelog_metadata: *mut CyclicBoundedVecMetadata =
-            unsafe { read_ptr_at(bytes, &mut offset) };
-        let roots_metadata: *mut CyclicBoundedVecMetadata =
-            unsafe { read_ptr_at(bytes, &mut offset) };
-        let canopy_metadata = unsafe { read_ptr_at(bytes, &mut offset) };
-
-        let expected_size = ConcurrentMerkleTree::<H, HEIGHT>::size_in_account(
-            height,
-            unsafe { (*changelog_metadata).capacity() },
-            unsafe { (*roots_metadata).capacity() },
-            canopy_depth,
-        );
-        if bytes.len() < expected_size {
-            return Err(ConcurrentMerkleTreeError::BufferSize(
-                expected_size,
-                bytes.len(),
-            ));
-        }
-
-        let filled_subtrees = unsafe {
-            BoundedVec::from_raw_parts(
-                filled_subtrees_metadata,
-                read_array_like_ptr_at(bytes, &mut offset, height),
-            )
-        };
-        let changelog = unsafe {
-            CyclicBoundedVec::from_raw_parts(
-                changelog_metadata,
-                read_array_like_ptr_at(bytes, &mut offset, (*changelog_metadata).capacity()),
-            )
-        };
-        let roots = unsafe {
-            CyclicBoundedVec::from_raw_parts(
-                roots_metadata,
-                read_array_like_ptr_at(bytes, &mut offset, (*roots_metadata).capacity()),
-            )
-        };
-        let canopy = unsafe {
-            BoundedVec::from_raw_parts(
-                canopy_metadata,
-                read_array_like_ptr_at(bytes, &mut offset, (*canopy_metadata).capacity()),
-            )
-        };
-
-        let merkle_tree = ConcurrentMerkleTree {
-            height,
-            canopy_depth,
-            next_index,
-            sequence_number,
-            rightmost_leaf,
-            filled_subtrees,
-            changelog,
-            roots,
-            canopy,
-            _hasher: PhantomData,
-        };
+    ) -> Result<(ConcurrentMerkleTree<H, HEIGHT>, &'a [u8]), ConcurrentMerkleTreeError> {
+        let (height, canopy_depth) = check_size::<H, HEIGHT>(bytes)?;
+
+        let (metadata_bytes, data_bytes) =
+            bytes.split_at(ConcurrentMerkleTree::<H, HEIGHT>::non_dyn_fields_size());
+        let metadata_bytes =
+            &metadata_bytes[offset_of!(ConcurrentMerkleTree<H, HEIGHT>, next_index)..];
+
+        let (next_index, metadata_bytes) = ptr_from_bytes(metadata_bytes, 1)?;
+        let (sequence_number, metadata_bytes) = ptr_from_bytes(metadata_bytes, 1)?;
+        let (rightmost_leaf, metadata_bytes) = ptr_from_bytes(metadata_bytes, 1)?;
+        let (filled_subtrees, metadata_bytes, data_bytes) =
+            BoundedVecZeroCopy::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (changelog, metadata_bytes, data_bytes) =
+            CyclicBoundedVecZeroCopy::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (roots, metadata_bytes, data_bytes) =
+            CyclicBoundedVecZeroCopy::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (canopy, _, data_bytes) =
+            BoundedVecZeroCopy::from_split_bytes(metadata_bytes, data_bytes)?;
+
+        // SAFETY: The vectors are backed by `bytes`. The tree is not dropped
+        // here and the caller is not supposed to drop it, nor to let it
+        // outlive `bytes`.
+        let merkle_tree = mem::ManuallyDrop::new(unsafe {
+            ConcurrentMerkleTree {
+                height,
+                canopy_depth,
+                next_index,
+                sequence_number,
+                rightmost_leaf,
+                filled_subtrees: filled_subtrees.into_inner(),
+                changelog: changelog.into_inner(),
+                roots: roots.into_inner(),
+                canopy: canopy.into_inner(),
+                _hasher: PhantomData,
+            }
+        });
         merkle_tree.check_size_constraints()?;
 
-        Ok((merkle_tree, offset))
+        Ok((mem::ManuallyDrop::into_inner(merkle_tree), data_bytes))
     }
 
     pub fn from_bytes_zero_copy(bytes: &'a [u8]) -> Result<Self, ConcurrentMerkleTreeError> {
         let (merkle_tree, _) = Self::struct_from_bytes_zero_copy(bytes)?;
-        merkle_tree.check_size_constraints()?;
 
         Ok(Self {
             merkle_tree: mem::ManuallyDrop::new(merkle_tree),
@@ -159,22 +183,77 @@ where
 }
 
 #[derive(Debug)]
-pub struct ConcurrentMerkleTreeZeroCopyMut<'a, H, const HEIGHT: usize>(
-    ConcurrentMerkleTreeZeroCopy<'a, H, HEIGHT>,
-)
+pub struct ConcurrentMerkleTreeZeroCopyMut<'a, H, const HEIGHT: usize>
 where
-    H: Hasher;
+    H: Hasher,
+{
+    merkle_tree: mem::ManuallyDrop<ConcurrentMerkleTree<H, HEIGHT>>,
+    // The purpose of this field is ensuring that the wrapper does not outlive
+    // the buffer.
+    _bytes: PhantomData<&'a mut [u8]>,
+}
 
 impl<'a, H, const HEIGHT: usize> ConcurrentMerkleTreeZeroCopyMut<'a, H, HEIGHT>
 where
     H: Hasher,
 {
+    /// Creates mutable `ConcurrentMerkleTree` backed by `bytes`. Returns the
+    /// tree and the remaining part of `bytes`.
+    ///
+    /// The returned tree must not be dropped, nor outlive `bytes`.
+    pub fn struct_from_bytes_zero_copy_mut(
+        bytes: &'a mut [u8],
+    ) -> Result<(ConcurrentMerkleTree<H, HEIGHT>, &'a mut [u8]), ConcurrentMerkleTreeError> {
+        let (height, canopy_depth) = check_size::<H, HEIGHT>(bytes)?;
+
+        let (metadata_bytes, data_bytes) =
+            bytes.split_at_mut(ConcurrentMerkleTree::<H, HEIGHT>::non_dyn_fields_size());
+        let metadata_bytes =
+            &mut metadata_bytes[offset_of!(ConcurrentMerkleTree<H, HEIGHT>, next_index)..];
+
+        let (next_index, metadata_bytes) = ptr_from_bytes_mut(metadata_bytes, 1)?;
+        let (sequence_number, metadata_bytes) = ptr_from_bytes_mut(metadata_bytes, 1)?;
+        let (rightmost_leaf, metadata_bytes) = ptr_from_bytes_mut(metadata_bytes, 1)?;
+        let (filled_subtrees, metadata_bytes, data_bytes) =
+            BoundedVecZeroCopyMut::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (changelog, metadata_bytes, data_bytes) =
+            CyclicBoundedVecZeroCopyMut::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (roots, metadata_bytes, data_bytes) =
+            CyclicBoundedVecZeroCopyMut::from_split_bytes(metadata_bytes, data_bytes)?;
+        let (canopy, _, data_bytes) =
+            BoundedVecZeroCopyMut::from_split_bytes(metadata_bytes, data_bytes)?;
+
+        // SAFETY: The vectors are backed by `bytes`. The tree is not dropped
+        // here and the caller is not supposed to drop it, nor to let it
+        // outlive `bytes`.
+        let merkle_tree = mem::ManuallyDrop::new(unsafe {
+            ConcurrentMerkleTree {
+                height,
+                canopy_depth,
+                next_index,
+                sequence_number,
+                rightmost_leaf,
+                filled_subtrees: filled_subtrees.into_inner(),
+                changelog: changelog.into_inner(),
+                roots: roots.into_inner(),
+                canopy: canopy.into_inner(),
+                _hasher: PhantomData,
+            }
+        });
+        merkle_tree.check_size_constraints()?;
+
+        Ok((mem::ManuallyDrop::into_inner(merkle_tree), data_bytes))
+    }
+
     pub fn from_bytes_zero_copy_mut(
         bytes: &'a mut [u8],
     ) -> Result<Self, ConcurrentMerkleTreeError> {
-        Ok(Self(ConcurrentMerkleTreeZeroCopy::from_bytes_zero_copy(
-            bytes,
-        )?))
+        let (merkle_tree, _) = Self::struct_from_bytes_zero_copy_mut(bytes)?;
+
+        Ok(Self {
+            merkle_tree: mem::ManuallyDrop::new(merkle_tree),
+            _bytes: PhantomData,
+        })
     }
 
     pub fn fill_non_dyn_fields_in_buffer(
@@ -251,7 +330,7 @@ where
     type Target = ConcurrentMerkleTree<H, HEIGHT>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0.merkle_tree
+        &self.merkle_tree
     }
 }
 impl<'a, H, const HEIGHT: usize> DerefMut for ConcurrentMerkleTreeZeroCopyMut<'a, H, HEIGHT>
@@ -259,7 +338,7 @@ where
     H: Hasher,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0.merkle_tree
+        &mut self.merkle_tree
     }
 }
 
@@ -267,6 +346,8 @@ where
 mod test {
     use super::*;
 
+    use light_bounded_vec::BoundedVecError;
+
     use ark_bn254::Fr;
     use ark_ff::{BigInteger, PrimeField, UniformRand};
     use light_hasher::Poseidon;
@@ -366,4 +447,88 @@ mod test {
     fn test_load_from_bytes_22_256_256_10_1024() {
         load_from_bytes::<22, 256, 256, 10, 1024>()
     }
+
+    /// Tests:
+    /// 1. too small buffer fails
+    /// 2. unaligned buffer fails
+    /// 3. buffer with invalid changelog metadata fails
+    #[test]
+    fn test_load_from_invalid_bytes() {
+        const HEIGHT: usize = 26;
+        const CHANGELOG: usize = 16;
+        const ROOTS: usize = 16;
+        const CANOPY_DEPTH: usize = 4;
+
+        let size = ConcurrentMerkleTree::<Poseidon, HEIGHT>::size_in_account(
+            HEIGHT,
+            CHANGELOG,
+            ROOTS,
+            CANOPY_DEPTH,
+        );
+        // Leave space for making the buffer unaligned.
+        let mut buf = vec![0u8; size + 16];
+        let offset = buf.as_ptr().align_offset(mem::align_of::<usize>());
+        let bytes = &mut buf[offset..offset + size + 8];
+        ConcurrentMerkleTreeZeroCopyMut::<Poseidon, HEIGHT>::from_bytes_zero_copy_init(
+            &mut bytes[..size],
+            HEIGHT,
+            CANOPY_DEPTH,
+            CHANGELOG,
+            ROOTS,
+        )
+        .unwrap()
+        .init()
+        .unwrap();
+
+        // 1. too small buffer
+        assert!(matches!(
+            ConcurrentMerkleTreeZeroCopy::<Poseidon, HEIGHT>::from_bytes_zero_copy(
+                &bytes[..size - 1]
+            ),
+            Err(ConcurrentMerkleTreeError::BufferSize(expected, actual))
+                if expected == size && actual == size - 1
+        ));
+        assert!(matches!(
+            ConcurrentMerkleTreeZeroCopyMut::<Poseidon, HEIGHT>::from_bytes_zero_copy_mut(
+                &mut bytes[..64]
+            ),
+            Err(ConcurrentMerkleTreeError::BufferSize(_, 64))
+        ));
+
+        // 2. unaligned buffer
+        bytes.copy_within(..size, 1);
+        assert!(matches!(
+            ConcurrentMerkleTreeZeroCopy::<Poseidon, HEIGHT>::from_bytes_zero_copy(
+                &bytes[1..size + 1]
+            ),
+            Err(ConcurrentMerkleTreeError::BoundedVec(
+                BoundedVecError::UnalignedBuffer(_)
+            ))
+        ));
+        bytes.copy_within(1..size + 1, 0);
+        assert!(
+            ConcurrentMerkleTreeZeroCopy::<Poseidon, HEIGHT>::from_bytes_zero_copy(&bytes[..size])
+                .is_ok()
+        );
+
+        // 3. invalid changelog metadata
+        let changelog_metadata_offset = offset_of!(ConcurrentMerkleTree<Poseidon, HEIGHT>, next_index)
+            + mem::size_of::<usize>()
+            + mem::size_of::<usize>()
+            + mem::size_of::<[u8; 32]>()
+            + mem::size_of::<BoundedVecMetadata>();
+        let invalid_metadata =
+            CyclicBoundedVecMetadata::new_with_indices(CHANGELOG, CHANGELOG + 1, 0, 0);
+        bytes[changelog_metadata_offset
+            ..changelog_metadata_offset + mem::size_of::<CyclicBoundedVecMetadata>()]
+            .copy_from_slice(&invalid_metadata.to_ne_bytes());
+        assert!(matches!(
+            ConcurrentMerkleTreeZeroCopyMut::<Poseidon, HEIGHT>::from_bytes_zero_copy_mut(
+                &mut bytes[..size]
+            ),
+            Err(ConcurrentMerkleTreeError::BoundedVec(
+                BoundedVecError::InvalidMetadata
+            ))
+        ));
+    }
 }
@@ -2,15 +2,13 @@ use ark_bn254::Fr;
 use ark_ff::{BigInteger, PrimeField, UniformRand};
 use light_bounded_vec::{BoundedVec, BoundedVecError, CyclicBoundedVec};
 use light_concurrent_merkle_tree::{
-    changelog::ChangelogEntry, errors::ConcurrentMerkleTreeError, event::MerkleTreeEvent,
-    zero_copy::ConcurrentMerkleTreeZeroCopyMut, ConcurrentMerkleTree,
+    changelog::ChangelogEntry, copy::ConcurrentMerkleTreeCopy, errors::ConcurrentMerkleTreeError,
+    event::MerkleTreeEvent, zero_copy::ConcurrentMerkleTreeZeroCopyMut, ConcurrentMerkleTree,
 };
 use light_hash_set::HashSet;
 use light_hasher::{Hasher, Keccak, Poseidon, Sha256};
 use light_merkle_tree_reference::store::Store;
 use light_utils::rand::gen_range_exclude;
-use num_bigint::BigUint;
-use num_traits::FromBytes;
 use rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng, Rng};
 use solana_program::pubkey::Pubkey;
 use std::cmp;
@@ -2212,9 +2210,8 @@ pub fn test_100_nullify_mt() {
             onchain_merkle_tree.append(&leaf).unwrap();
             crank_merkle_tree.append(&leaf).unwrap();
             // onchain the equivalent is nullify state (compressed pda program)
-            let leaf_bn = BigUint::from_be_bytes(&leaf);
-            queue.insert(&leaf_bn, 1).unwrap();
-            let (_, index) = queue.find_element(&leaf_bn, None).unwrap().unwrap();
+            queue.insert(&leaf, 1).unwrap();
+            let (_, index) = queue.find_element(&leaf, None).unwrap().unwrap();
             queue_indices.push(index);
         }
         assert_eq!(onchain_merkle_tree.root(), crank_merkle_tree.root());
@@ -3415,3 +3412,358 @@ fn test_update_with_canopy_poseidon() {
 fn test_update_with_canopy_sha256() {
     update_with_canopy::<Sha256>()
 }
+
+/// Tests whether stale proofs are updated to proofs valid against the current
+/// root:
+///
+/// 1. Proofs computed against older roots are updated with the changelog,
+///    both on the tree and on a copy of the account data.
+/// 2. Proofs of leaves updated in the meantime can't be updated.
+/// 3. Proofs older than the changelog window can't be updated.
+/// 4. Sequence numbers from the future are rejected.
+fn update_proof_from_sequence_number<H, const CANOPY: usize>()
+where
+    H: Hasher,
+{
+    const HEIGHT: usize = 8;
+    const CHANGELOG: usize = 16;
+    const ROOTS: usize = 16;
+
+    let mut bytes =
+        vec![
+            0u8;
+            ConcurrentMerkleTree::<H, HEIGHT>::size_in_account(HEIGHT, CHANGELOG, ROOTS, CANOPY)
+        ];
+    let mut merkle_tree = ConcurrentMerkleTreeZeroCopyMut::<H, HEIGHT>::from_bytes_zero_copy_init(
+        bytes.as_mut_slice(),
+        HEIGHT,
+        CANOPY,
+        CHANGELOG,
+        ROOTS,
+    )
+    .unwrap();
+    merkle_tree.init().unwrap();
+    let mut reference_tree = light_merkle_tree_reference::MerkleTree::<H>::new(HEIGHT, CANOPY);
+
+    let mut rng = thread_rng();
+    let mut rand_leaf = || -> [u8; 32] {
+        Fr::rand(&mut rng)
+            .into_bigint()
+            .to_bytes_be()
+            .try_into()
+            .unwrap()
+    };
+
+    for _ in 0..8 {
+        let leaf = rand_leaf();
+        merkle_tree.append(&leaf).unwrap();
+        reference_tree.append(&leaf).unwrap();
+    }
+
+    // Proofs of leaf 3, full and without canopy nodes, computed at the
+    // current sequence number.
+    let leaf_index = 3;
+    let leaf = reference_tree.leaf(leaf_index);
+    let sequence_number = merkle_tree.sequence_number();
+    let full_proof = reference_tree.get_proof_of_leaf(leaf_index, true).unwrap();
+    let proof = reference_tree.get_proof_of_leaf(leaf_index, false).unwrap();
+
+    // Appends (single and batched) and updates of other leaves.
+    for _ in 0..4 {
+        let leaf = rand_leaf();
+        merkle_tree.append(&leaf).unwrap();
+        reference_tree.append(&leaf).unwrap();
+    }
+    let leaves = [rand_leaf(), rand_leaf(), rand_leaf()];
+    merkle_tree
+        .append_batch(leaves.iter().collect::<Vec<_>>().as_slice())
+        .unwrap();
+    for leaf in leaves.iter() {
+        reference_tree.append(leaf).unwrap();
+    }
+    for i in [0, 2, 4, 9] {
+        let new_leaf = rand_leaf();
+        let changelog_index = merkle_tree.changelog_index();
+        let mut proof = reference_tree.get_proof_of_leaf(i, false).unwrap();
+        merkle_tree
+            .update(
+                changelog_index,
+                &reference_tree.leaf(i),
+                &new_leaf,
+                i,
+                &mut proof,
+            )
+            .unwrap();
+        reference_tree.update(&new_leaf, i).unwrap();
+    }
+    assert_eq!(merkle_tree.root(), reference_tree.root());
+
+    // 1. Proofs computed against older roots are updated with the changelog.
+    let expected_proof = reference_tree.get_proof_of_leaf(leaf_index, true).unwrap();
+    let updated_proof = merkle_tree
+        .update_proof_from_sequence_number(&leaf, leaf_index, &full_proof[..], sequence_number)
+        .unwrap();
+    assert_eq!(updated_proof, expected_proof);
+    let updated_proof = merkle_tree
+        .update_proof_from_sequence_number(&leaf, leaf_index, &proof[..], sequence_number)
+        .unwrap();
+    assert_eq!(updated_proof, expected_proof);
+    drop(merkle_tree);
+    let merkle_tree_copy = ConcurrentMerkleTreeCopy::<H, HEIGHT>::from_bytes_copy(&bytes).unwrap();
+    let updated_proof = merkle_tree_copy
+        .update_proof_from_sequence_number(&leaf, leaf_index, &proof[..], sequence_number)
+        .unwrap();
+    assert_eq!(updated_proof, expected_proof);
+
+    // 2. Proofs of leaves updated in the meantime can't be updated.
+    let mut merkle_tree =
+        ConcurrentMerkleTreeZeroCopyMut::<H, HEIGHT>::from_bytes_zero_copy_mut(&mut bytes).unwrap();
+    let new_leaf = rand_leaf();
+    let changelog_index = merkle_tree.changelog_index();
+    let mut current_proof = reference_tree.get_proof_of_leaf(leaf_index, false).unwrap();
+    merkle_tree
+        .update(
+            changelog_index,
+            &leaf,
+            &new_leaf,
+            leaf_index,
+            &mut current_proof,
+        )
+        .unwrap();
+    reference_tree.update(&new_leaf, leaf_index).unwrap();
+    assert!(matches!(
+        merkle_tree.update_proof_from_sequence_number(
+            &leaf,
+            leaf_index,
+            &full_proof[..],
+            sequence_number
+        ),
+        Err(ConcurrentMerkleTreeError::CannotUpdateLeaf)
+    ));
+
+    // 3. Proofs older than the changelog window can't be updated.
+    let leaf_index = 1;
+    let leaf = reference_tree.leaf(leaf_index);
+    let proof = reference_tree.get_proof_of_leaf(leaf_index, true).unwrap();
+    let sequence_number = merkle_tree.sequence_number();
+    for _ in 0..CHANGELOG - 1 {
+        let leaf = rand_leaf();
+        merkle_tree.append(&leaf).unwrap();
+        reference_tree.append(&leaf).unwrap();
+    }
+    // The whole changelog is still available.
+    let updated_proof = merkle_tree
+        .update_proof_from_sequence_number(&leaf, leaf_index, &proof[..], sequence_number)
+        .unwrap();
+    assert_eq!(
+        updated_proof,
+        reference_tree.get_proof_of_leaf(leaf_index, true).unwrap()
+    );
+    merkle_tree.append(&rand_leaf()).unwrap();
+    assert!(matches!(
+        merkle_tree.update_proof_from_sequence_number(
+            &leaf,
+            leaf_index,
+            &proof[..],
+            sequence_number
+        ),
+        Err(ConcurrentMerkleTreeError::ChangelogWindowExceeded(seq, oldest))
+            if seq == sequence_number && oldest == sequence_number + 1
+    ));
+
+    // 4. Sequence numbers from the future are rejected.
+    let sequence_number = merkle_tree.sequence_number() + 1;
+    assert!(matches!(
+        merkle_tree.update_proof_from_sequence_number(
+            &leaf,
+            leaf_index,
+            &proof[..],
+            sequence_number
+        ),
+        Err(ConcurrentMerkleTreeError::InvalidSequenceNumber(_, _))
+    ));
+}
+
+#[test]
+fn test_update_proof_from_sequence_number_keccak_canopy_0() {
+    update_proof_from_sequence_number::<Keccak, 0>()
+}
+
+#[test]
+fn test_update_proof_from_sequence_number_poseidon_canopy_0() {
+    update_proof_from_sequence_number::<Poseidon, 0>()
+}
+
+#[test]
+fn test_update_proof_from_sequence_number_poseidon_canopy_4() {
+    update_proof_from_sequence_number::<Poseidon, 4>()
+}
+
+#[test]
+fn test_update_proof_from_sequence_number_sha256_canopy_2() {
+    update_proof_from_sequence_number::<Sha256, 2>()
+}
+
+/// Checks whether `append_batch_subtrees` is equivalent to `append_batch` and
+/// the reference tree:
+///
+/// 1. Roots, next index, rightmost leaf, filled subtrees and canopy are the
+///    same as after `append_batch`.
+/// 2. The batch produces a single changelog entry and root, and increments
+///    the sequence number once.
+/// 3. The changelog entry contains the index of the first leaf, the batch
+///    size and, on every level, the node which replaces nodes of proofs of
+///    preceding leaves.
+/// 4. Proofs of leaves appended before the batch can be updated with the
+///    changelog, proofs of leaves of the batch can't.
+fn append_batch_subtrees<H, const HEIGHT: usize, const CANOPY: usize>()
+where
+    H: Hasher,
+{
+    const CHANGELOG: usize = 64;
+    const ROOTS: usize = 256;
+
+    let mut rng = thread_rng();
+
+    for _ in 0..4 {
+        let mut concurrent_mt_1 =
+            ConcurrentMerkleTree::<H, HEIGHT>::new(HEIGHT, CHANGELOG, ROOTS, CANOPY).unwrap();
+        concurrent_mt_1.init().unwrap();
+
+        // Tree to which we are going to append with `append_batch`.
+        let mut concurrent_mt_2 =
+            ConcurrentMerkleTree::<H, HEIGHT>::new(HEIGHT, CHANGELOG, ROOTS, CANOPY).unwrap();
+        concurrent_mt_2.init().unwrap();
+
+        let mut reference_mt = light_merkle_tree_reference::MerkleTree::<H>::new(HEIGHT, CANOPY);
+
+        while reference_mt.leaves().len() < 1 << HEIGHT {
+            let remaining = (1 << HEIGHT) - reference_mt.leaves().len();
+            // `append_batch` can't append more leaves than the changelog
+            // capacity.
+            let batch_size = rng.gen_range(1..=cmp::min(remaining, CHANGELOG));
+            let leaves: Vec<[u8; 32]> = (0..batch_size)
+                .map(|_| {
+                    Fr::rand(&mut rng)
+                        .into_bigint()
+                        .to_bytes_be()
+                        .try_into()
+                        .unwrap()
+                })
+                .collect();
+            let leaves: Vec<&[u8; 32]> = leaves.iter().collect();
+
+            // Proofs of existing leaves, computed before the batch.
+            let changelog_index = concurrent_mt_1.changelog_index();
+            let num_leaves = reference_mt.leaves().len();
+            let old_proofs: Vec<(usize, BoundedVec<[u8; 32]>)> = (0..cmp::min(num_leaves, 16))
+                .map(|_| {
+                    let leaf_index = rng.gen_range(0..num_leaves);
+                    (
+                        leaf_index,
+                        reference_mt.get_proof_of_leaf(leaf_index, false).unwrap(),
+                    )
+                })
+                .collect();
+
+            let old_sequence_number = concurrent_mt_1.sequence_number();
+            let old_root = concurrent_mt_1.root();
+            let (changelog_index_batch, sequence_number) = concurrent_mt_1
+                .append_batch_subtrees(leaves.as_slice())
+                .unwrap();
+            concurrent_mt_2.append_batch(leaves.as_slice()).unwrap();
+            reference_mt.append_batch(leaves.as_slice()).unwrap();
+
+            // 1. Equivalence with `append_batch` and the reference tree.
+            assert_eq!(concurrent_mt_1.root(), reference_mt.root());
+            assert_eq!(concurrent_mt_1.root(), concurrent_mt_2.root());
+            assert_eq!(concurrent_mt_1.next_index(), concurrent_mt_2.next_index());
+            assert_eq!(
+                concurrent_mt_1.rightmost_leaf(),
+                concurrent_mt_2.rightmost_leaf()
+            );
+            assert_eq!(
+                concurrent_mt_1.filled_subtrees.as_slice(),
+                concurrent_mt_2.filled_subtrees.as_slice()
+            );
+            assert_eq!(
+                concurrent_mt_1.canopy.as_slice(),
+                reference_mt.get_canopy().unwrap().as_slice()
+            );
+
+            // 2. One changelog entry, one root and one sequence number.
+            assert_eq!(sequence_number, old_sequence_number + 1);
+            assert_eq!(concurrent_mt_1.sequence_number(), sequence_number);
+            assert_eq!(changelog_index_batch, (changelog_index + 1) % CHANGELOG);
+            assert_eq!(concurrent_mt_1.changelog_index(), changelog_index_batch);
+            assert_eq!(
+                concurrent_mt_1.roots[(concurrent_mt_1.root_index() + ROOTS - 1) % ROOTS],
+                old_root
+            );
+
+            // 3. The batch changelog entry.
+            let changelog_entry = &concurrent_mt_1.changelog[changelog_index_batch];
+            assert_eq!(changelog_entry.index(), num_leaves);
+            assert_eq!(changelog_entry.batch_size(), Some(batch_size));
+            assert_eq!(changelog_entry.root, reference_mt.root());
+            for level in 0..HEIGHT {
+                let first_node_index = num_leaves >> level;
+                let node_index = if first_node_index % 2 == 1 {
+                    first_node_index
+                } else {
+                    first_node_index + 1
+                };
+                let expected_node = reference_mt.layers[level]
+                    .get(node_index)
+                    .copied()
+                    .unwrap_or(H::zero_bytes()[level]);
+                assert_eq!(changelog_entry.path[level], expected_node);
+            }
+
+            // 4. Proofs of existing leaves.
+            for (leaf_index, mut proof) in old_proofs {
+                if CANOPY > 0 {
+                    concurrent_mt_1
+                        .update_proof_from_canopy(leaf_index, &mut proof)
+                        .unwrap();
+                }
+                concurrent_mt_1
+                    .update_proof_from_changelog(changelog_index, leaf_index, &mut proof)
+                    .unwrap();
+                concurrent_mt_1
+                    .validate_proof(&reference_mt.leaf(leaf_index), leaf_index, &proof)
+                    .unwrap();
+            }
+            let leaf_index = num_leaves + rng.gen_range(0..batch_size);
+            let mut proof = reference_mt.get_proof_of_leaf(leaf_index, false).unwrap();
+            assert!(matches!(
+                concurrent_mt_1.update_proof_from_changelog(
+                    changelog_index,
+                    leaf_index,
+                    &mut proof
+                ),
+                Err(ConcurrentMerkleTreeError::CannotUpdateLeaf)
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_append_batch_subtrees_keccak_8_canopy_0() {
+    append_batch_subtrees::<Keccak, 8, 0>()
+}
+
+#[test]
+fn test_append_batch_subtrees_poseidon_8_canopy_0() {
+    append_batch_subtrees::<Poseidon, 8, 0>()
+}
+
+#[test]
+fn test_append_batch_subtrees_poseidon_8_canopy_4() {
+    append_batch_subtrees::<Poseidon, 8, 4>()
+}
+
+#[test]
+fn test_append_batch_subtrees_sha256_6_canopy_6() {
+    append_batch_subtrees::<Sha256, 6, 6>()
+}
@@ -1,10 +1,11 @@
-use light_utils::{bigint::bigint_to_be_bytes_array, UtilsError};
-use num_bigint::{BigUint, ToBigUint};
-use num_traits::{FromBytes, ToPrimitive};
+use light_bounded_vec::BoundedVecError;
+use light_utils::UtilsError;
+use num_bigint::BigUint;
 use std::{
     alloc::{self, handle_alloc_error, Layout},
-    cmp::Ordering,
+    cmp::{self, Ordering},
     mem,
+    ops::Range,
     ptr::NonNull,
 };
 use thiserror::Error;
@@ -27,6 +28,8 @@ pub enum HashSetError {
     BufferSize(usize, usize),
     #[error("Utils: big integer conversion error")]
     Utils(#[from] UtilsError),
+    #[error("Bounded vector error: {0}")]
+    BoundedVec(#[from] BoundedVecError),
 }
 
 #[cfg(feature = "solana")]
@@ -40,6 +43,7 @@ impl From<HashSetError> for u32 {
             HashSetError::IntegerOverflow => 9005,
             HashSetError::BufferSize(_, _) => 9006,
             HashSetError::Utils(e) => e.into(),
+            HashSetError::BoundedVec(e) => e.into(),
         }
     }
 }
@@ -100,6 +104,89 @@ impl HashSetCell {
     }
 }
 
+/// Occupancy of a hash set at a given sequence number.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HashSetMetrics {
+    /// Number of buckets.
+    pub capacity: usize,
+    /// Number of valid (not expired) elements.
+    pub occupied: usize,
+    /// Number of valid elements which were not marked yet.
+    pub unmarked: usize,
+    /// Number of valid elements marked with a sequence number, which are
+    /// not expired yet.
+    pub marked: usize,
+}
+
+impl HashSetMetrics {
+    /// Load factor in basis points (1/10000).
+    pub fn load_factor_bps(&self) -> u16 {
+        if self.capacity == 0 {
+            return 0;
+        }
+        (self.occupied * 10_000 / self.capacity) as u16
+    }
+
+    /// Number of buckets which can still be used for new elements.
+    pub fn available(&self) -> usize {
+        self.capacity - self.occupied
+    }
+}
+
+/// Returns `value % capacity`, where `value` is a big-endian 256-bit
+/// integer. It's the starting point of the probe sequence of `value`.
+///
+/// The value is processed in fixed-width limbs, without allocating a big
+/// integer. The result is the same as of `BigUint` arithmetic, so it's
+/// deterministic across on-chain and client code.
+pub fn value_index(value: &[u8; 32], capacity: usize) -> usize {
+    if capacity <= u32::MAX as usize {
+        // 32-bit limbs, the remainder fits in 32 bits, so the intermediate
+        // value fits in `u64`.
+        let capacity = capacity as u64;
+        let mut remainder = 0u64;
+        for limb in value.chunks_exact(4) {
+            // PANICS: `chunks_exact` guarantees the size.
+            let limb = u32::from_be_bytes(limb.try_into().unwrap()) as u64;
+            remainder = ((remainder << 32) | limb) % capacity;
+        }
+        remainder as usize
+    } else {
+        let capacity = capacity as u128;
+        let mut remainder = 0u128;
+        for limb in value.chunks_exact(8) {
+            // PANICS: `chunks_exact` guarantees the size.
+            let limb = u64::from_be_bytes(limb.try_into().unwrap()) as u128;
+            remainder = ((remainder << 64) | limb) % capacity;
+        }
+        remainder as usize
+    }
+}
+
+/// Returns the bucket index visited in the given `iteration` of quadratic
+/// probing, i.e. `(value + iteration^2) % capacity`, where `value_index` is
+/// `value % capacity` (see [`value_index`]).
+///
+/// The probe sequence is identical to the one computed with `BigUint`
+/// arithmetic by earlier versions, so hash sets stored in existing accounts
+/// don't need to be migrated.
+pub fn probe_index(value_index: usize, iteration: usize, capacity: usize) -> usize {
+    let iteration = iteration % capacity;
+    let offset = if capacity <= u32::MAX as usize {
+        (iteration as u64 * iteration as u64 % capacity as u64) as usize
+    } else {
+        (iteration as u128 * iteration as u128 % capacity as u128) as usize
+    };
+    // Both operands are lower than `capacity`. Avoid computing their sum,
+    // which could overflow for capacities close to `usize::MAX`.
+    let distance_to_end = capacity - value_index;
+    if offset >= distance_to_end {
+        offset - distance_to_end
+    } else {
+        value_index + offset
+    }
+}
+
 #[derive(Debug)]
 pub struct HashSet {
     /// Capacity of the buckets.
@@ -209,11 +296,8 @@ impl HashSet {
         })
     }
 
-    fn probe_index(&self, value: &BigUint, iteration: usize) -> usize {
-        let probe_index = (value.clone()
-            + iteration.to_biguint().unwrap() * iteration.to_biguint().unwrap())
-            % self.capacity.to_biguint().unwrap();
-        probe_index.to_usize().unwrap()
+    fn probe_index(&self, value_index: usize, iteration: usize) -> usize {
+        probe_index(value_index, iteration, self.capacity)
     }
 
     /// Returns a reference to a bucket under the given `index`. Does not check
@@ -255,7 +339,7 @@ impl HashSet {
     fn insert_into_occupied_cell(
         &mut self,
         value_index: usize,
-        value: &BigUint,
+        value: &[u8; 32],
         current_sequence_number: usize,
     ) -> Result<bool, HashSetError> {
         // PANICS: We trust the bounds of `value_index` here.
@@ -271,7 +355,7 @@ impl HashSet {
                 if let Some(element_sequence_number) = bucket.sequence_number {
                     if current_sequence_number >= element_sequence_number {
                         *bucket = HashSetCell {
-                            value: bigint_to_be_bytes_array(value)?,
+                            value: *value,
                             sequence_number: None,
                         };
                         return Ok(true);
@@ -279,7 +363,7 @@ impl HashSet {
                 }
                 // Otherwise, we need to prevent having multiple valid
                 // elements with the same value.
-                if &BigUint::from_be_bytes(bucket.value.as_slice()) == value {
+                if &bucket.value == value {
                     return Err(HashSetError::ElementAlreadyExists);
                 }
             }
@@ -300,7 +384,7 @@ impl HashSet {
     /// be overwritten.
     pub fn insert(
         &mut self,
-        value: &BigUint,
+        value: &[u8; 32],
         current_sequence_number: usize,
     ) -> Result<usize, HashSetError> {
         let index_bucket = self.find_element_iter(value, current_sequence_number, 0, 20)?;
@@ -323,7 +407,7 @@ impl HashSet {
                 let bucket = self.get_bucket_mut(index).unwrap();
 
                 *bucket = Some(HashSetCell {
-                    value: bigint_to_be_bytes_array(value)?,
+                    value: *value,
                     sequence_number: None,
                 });
                 return Ok(index);
@@ -338,17 +422,18 @@ impl HashSet {
     /// validity of the element.
     pub fn find_element_index(
         &self,
-        value: &BigUint,
+        value: &[u8; 32],
         current_sequence_number: Option<usize>,
     ) -> Result<Option<usize>, HashSetError> {
+        let value_index = value_index(value, self.capacity);
         for i in 0..self.capacity {
-            let probe_index = self.probe_index(value, i);
+            let probe_index = self.probe_index(value_index, i);
             // PANICS: `probe_index()` ensures the bounds.
             let bucket = self.get_bucket(probe_index).unwrap();
 
             match bucket {
                 Some(bucket) => {
-                    if &bucket.value_biguint() == value {
+                    if &bucket.value == value {
                         match current_sequence_number {
                             // If the caller provided `current_sequence_number`,
                             // check the validity of the bucket.
@@ -376,7 +461,7 @@ impl HashSet {
 
     pub fn find_element(
         &self,
-        value: &BigUint,
+        value: &[u8; 32],
         current_sequence_number: Option<usize>,
     ) -> Result<Option<(&HashSetCell, usize)>, HashSetError> {
         let index = self.find_element_index(value, current_sequence_number)?;
@@ -394,7 +479,7 @@ impl HashSet {
 
     pub fn find_element_mut(
         &mut self,
-        value: &BigUint,
+        value: &[u8; 32],
         current_sequence_number: Option<usize>,
     ) -> Result<Option<(&mut HashSetCell, usize)>, HashSetError> {
         let index = self.find_element_index(value, current_sequence_number)?;
@@ -416,15 +501,16 @@ impl HashSet {
     /// to make sure that the value is not in the hash-set.
     /// Returns the position of the first free value.
     pub fn find_element_iter(
-        &mut self,
-        value: &BigUint,
+        &self,
+        value: &[u8; 32],
         current_sequence_number: usize,
         start_iter: usize,
         num_iterations: usize,
     ) -> Result<Option<(usize, bool)>, HashSetError> {
+        let value_index = value_index(value, self.capacity);
         let mut first_free_element: Option<(usize, bool)> = None;
         for i in start_iter..num_iterations {
-            let probe_index = self.probe_index(value, i);
+            let probe_index = self.probe_index(value_index, i);
             let bucket = self.get_bucket(probe_index).unwrap();
 
             match bucket {
@@ -433,7 +519,7 @@ impl HashSet {
                     if first_free_element.is_none() && !is_valid {
                         first_free_element = Some((probe_index, false));
                     }
-                    if is_valid && &bucket.value_biguint() == value {
+                    if is_valid && &bucket.value == value {
                         return Err(HashSetError::ElementAlreadyExists);
                     } else {
                         continue;
@@ -490,7 +576,7 @@ impl HashSet {
     /// Checks if the hash set contains a value.
     pub fn contains(
         &self,
-        value: &BigUint,
+        value: &[u8; 32],
         sequence_number: Option<usize>,
     ) -> Result<bool, HashSetError> {
         let element = self.find_element(value, sequence_number)?;
@@ -517,6 +603,67 @@ impl HashSet {
         }
     }
 
+    /// Inserts an existing cell into the hash set, preserving its sequence
+    /// number. Used when moving elements between hash sets, where nullified
+    /// elements have to stay marked until their sequence number expires.
+    pub fn insert_cell(
+        &mut self,
+        cell: &HashSetCell,
+        current_sequence_number: usize,
+    ) -> Result<usize, HashSetError> {
+        let index = self.insert(&cell.value, current_sequence_number)?;
+        // PANICS: `insert()` returns only valid indices.
+        if let Some(bucket) = self.get_bucket_mut(index).unwrap() {
+            bucket.sequence_number = cell.sequence_number;
+        }
+        Ok(index)
+    }
+
+    /// Copies valid elements (according to `current_sequence_number`) of the
+    /// given range of buckets into the `target` hash set. Expired elements
+    /// are dropped. Buckets beyond the capacity are ignored, so that large
+    /// hash sets can be migrated in chunks.
+    ///
+    /// Returns the number of migrated elements.
+    pub fn migrate(
+        &self,
+        target: &mut HashSet,
+        buckets: Range<usize>,
+        current_sequence_number: usize,
+    ) -> Result<usize, HashSetError> {
+        let mut migrated = 0;
+        for index in buckets.start..cmp::min(buckets.end, self.capacity) {
+            // PANICS: `index` is lower than the capacity.
+            if let Some(cell) = self.get_bucket(index).unwrap() {
+                if cell.is_valid(current_sequence_number) {
+                    target.insert_cell(cell, current_sequence_number)?;
+                    migrated += 1;
+                }
+            }
+        }
+        Ok(migrated)
+    }
+
+    /// Returns occupancy metrics according to `current_sequence_number`.
+    /// Expired elements are counted as free, since they can be overwritten.
+    pub fn metrics(&self, current_sequence_number: usize) -> HashSetMetrics {
+        let mut metrics = HashSetMetrics {
+            capacity: self.capacity,
+            ..Default::default()
+        };
+        for (_, cell) in self.iter() {
+            if cell.is_valid(current_sequence_number) {
+                metrics.occupied += 1;
+                if cell.is_marked() {
+                    metrics.marked += 1;
+                } else {
+                    metrics.unmarked += 1;
+                }
+            }
+        }
+        metrics
+    }
+
     /// Returns an iterator over elements.
     pub fn iter(&self) -> HashSetIterator {
         HashSetIterator {
@@ -572,10 +719,19 @@ mod test {
     use ark_ff::UniformRand;
     use rand::{thread_rng, Rng};
 
+    use light_utils::bigint::bigint_to_be_bytes_array;
+    use num_bigint::{BigUint, ToBigUint};
+    use num_traits::ToPrimitive;
+
     use crate::zero_copy::HashSetZeroCopy;
 
     use super::*;
 
+    /// Returns the big-endian bytes of the given integer.
+    fn be_bytes<T: ToBigUint>(value: T) -> [u8; 32] {
+        bigint_to_be_bytes_array::<32>(&value.to_biguint().unwrap()).unwrap()
+    }
+
     #[test]
     fn test_is_valid() {
         let mut rng = thread_rng();
@@ -612,7 +768,7 @@ mod test {
 
         // Insert an element and immediately mark it with a sequence number.
         // An equivalent to a single insertion in Light Protocol
-        let element_1_1 = 1.to_biguint().unwrap();
+        let element_1_1 = be_bytes(1);
         let index_1_1 = hs.insert(&element_1_1, 0).unwrap();
         hs.mark_with_sequence_number(index_1_1, 1).unwrap();
 
@@ -628,10 +784,10 @@ mod test {
         // Insert multiple elements and mark them with one sequence number.
         // An equivalent to a batched insertion in Light Protocol.
 
-        let element_2_3 = 3.to_biguint().unwrap();
-        let element_2_6 = 6.to_biguint().unwrap();
-        let element_2_8 = 8.to_biguint().unwrap();
-        let element_2_9 = 9.to_biguint().unwrap();
+        let element_2_3 = be_bytes(3);
+        let element_2_6 = be_bytes(6);
+        let element_2_8 = be_bytes(8);
+        let element_2_9 = be_bytes(9);
         let index_2_3 = hs.insert(&element_2_3, 1).unwrap();
         let index_2_6 = hs.insert(&element_2_6, 1).unwrap();
         let index_2_8 = hs.insert(&element_2_8, 1).unwrap();
@@ -661,10 +817,10 @@ mod test {
             Err(HashSetError::ElementAlreadyExists)
         ));
 
-        let element_3_11 = 11.to_biguint().unwrap();
-        let element_3_13 = 13.to_biguint().unwrap();
-        let element_3_21 = 21.to_biguint().unwrap();
-        let element_3_29 = 29.to_biguint().unwrap();
+        let element_3_11 = be_bytes(11);
+        let element_3_13 = be_bytes(13);
+        let element_3_21 = be_bytes(21);
+        let element_3_29 = be_bytes(29);
         let index_3_11 = hs.insert(&element_3_11, 2).unwrap();
         let index_3_13 = hs.insert(&element_3_13, 2).unwrap();
         let index_3_21 = hs.insert(&element_3_21, 2).unwrap();
@@ -694,10 +850,10 @@ mod test {
             Err(HashSetError::ElementAlreadyExists)
         ));
 
-        let element_4_93 = 93.to_biguint().unwrap();
-        let element_4_65 = 64.to_biguint().unwrap();
-        let element_4_72 = 72.to_biguint().unwrap();
-        let element_4_15 = 15.to_biguint().unwrap();
+        let element_4_93 = be_bytes(93);
+        let element_4_65 = be_bytes(64);
+        let element_4_72 = be_bytes(72);
+        let element_4_15 = be_bytes(15);
         let index_4_93 = hs.insert(&element_4_93, 3).unwrap();
         let index_4_65 = hs.insert(&element_4_65, 3).unwrap();
         let index_4_72 = hs.insert(&element_4_72, 3).unwrap();
@@ -753,18 +909,18 @@ mod test {
         assert_eq!(hs.first(0).unwrap(), None);
         let mut rng = thread_rng();
         let mut seq = 0;
-        let nullifiers: [BigUint; 24000] =
-            std::array::from_fn(|_| BigUint::from(Fr::rand(&mut rng)));
+        let nullifiers: [[u8; 32]; 24000] =
+            std::array::from_fn(|_| be_bytes(BigUint::from(Fr::rand(&mut rng))));
         for nf_chunk in nullifiers.chunks(2400) {
             for nullifier in nf_chunk.iter() {
-                assert_eq!(hs.contains(&nullifier, Some(seq)).unwrap(), false);
-                let index = hs.insert(&nullifier, seq as usize).unwrap();
-                assert_eq!(hs.contains(&nullifier, Some(seq)).unwrap(), true);
+                assert_eq!(hs.contains(nullifier, Some(seq)).unwrap(), false);
+                let index = hs.insert(nullifier, seq as usize).unwrap();
+                assert_eq!(hs.contains(nullifier, Some(seq)).unwrap(), true);
 
-                let nullifier_bytes = bigint_to_be_bytes_array(&nullifier).unwrap();
+                let nullifier_bytes = *nullifier;
 
                 let element = hs
-                    .find_element(&nullifier, Some(seq))
+                    .find_element(nullifier, Some(seq))
                     .unwrap()
                     .unwrap()
                     .0
@@ -772,19 +928,19 @@ mod test {
                 assert_eq!(
                     element,
                     HashSetCell {
-                        value: bigint_to_be_bytes_array(&nullifier).unwrap(),
+                        value: *nullifier,
                         sequence_number: None,
                     }
                 );
                 assert_eq!(element.value_bytes(), nullifier_bytes);
-                assert_eq!(&element.value_biguint(), nullifier);
+                assert_eq!(&element.value, nullifier);
                 assert_eq!(element.sequence_number(), None);
                 assert!(!element.is_marked());
                 assert!(element.is_valid(seq));
 
                 hs.mark_with_sequence_number(index, seq).unwrap();
                 let element = hs
-                    .find_element(&nullifier, Some(seq))
+                    .find_element(nullifier, Some(seq))
                     .unwrap()
                     .unwrap()
                     .0
@@ -798,7 +954,7 @@ mod test {
                     }
                 );
                 assert_eq!(element.value_bytes(), nullifier_bytes);
-                assert_eq!(&element.value_biguint(), nullifier);
+                assert_eq!(&element.value, nullifier);
                 assert_eq!(element.sequence_number(), Some(2400 + seq));
                 assert!(element.is_marked());
                 assert!(element.is_valid(seq));
@@ -806,7 +962,7 @@ mod test {
                 // Trying to insert the same nullifier, before reaching the
                 // sequence threshold, should fail.
                 assert!(matches!(
-                    hs.insert(&nullifier, seq as usize + 2399),
+                    hs.insert(nullifier, seq as usize + 2399),
                     Err(HashSetError::ElementAlreadyExists),
                 ));
                 seq += 1;
@@ -836,7 +992,7 @@ mod test {
             };
 
             for seq in 0..OPERATIONS {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
                 hs_1.insert(&value, seq).unwrap();
                 hs_2.insert(&value, seq).unwrap();
             }
@@ -870,7 +1026,7 @@ mod test {
             // Insert as many values as possible. The important point is to
             // encounter the `HashSetError::Full` at some point
             for i in 0..CAPACITY {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
                 match hs.insert(&value, 0) {
                     Ok(index) => hs.mark_with_sequence_number(index, 0).unwrap(),
                     Err(e) => {
@@ -885,7 +1041,7 @@ mod test {
             // also some successful insertions - there might be values which
             // will end up in unused buckets.
             for i in 0..1000 {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
                 let res = hs.insert(&value, 0);
                 if res.is_err() {
                     assert!(matches!(res, Err(HashSetError::Full)));
@@ -897,7 +1053,7 @@ mod test {
             // Try again with defined sequence numbers, but still too small to
             // vacate any cell.
             for i in 0..1000 {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
                 // Sequence numbers lower than the threshold should not vacate
                 // any cell.
                 let sequence_number = rng.gen_range(0..hs.sequence_threshold);
@@ -912,7 +1068,7 @@ mod test {
             // Use sequence numbers which are going to vacate cells. All
             // insertions should be successful now.
             for i in 0..CAPACITY {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
                 if let Err(e) = hs.insert(&value, SEQUENCE_THRESHOLD + i) {
                     assert!(matches!(e, HashSetError::Full));
                     println!("insertions after fillup: {i}: failed, stopping");
@@ -949,7 +1105,7 @@ mod test {
         for _ in 0..1000 {
             // After actually appending the value, the same operation should be
             // possible
-            let value = BigUint::from(Fr::rand(&mut rng));
+            let value = be_bytes(BigUint::from(Fr::rand(&mut rng)));
             let index = hs.insert(&value, 0).unwrap();
             hs.mark_with_sequence_number(index, 1).unwrap();
         }
@@ -959,16 +1115,16 @@ mod test {
     fn test_hash_set_iter_manual() {
         let mut hs = HashSet::new(6857, 2400).unwrap();
 
-        let nullifier_1 = 945635_u32.to_biguint().unwrap();
-        let nullifier_2 = 3546656654734254353455_u128.to_biguint().unwrap();
-        let nullifier_3 = 543543656564_u64.to_biguint().unwrap();
-        let nullifier_4 = 43_u8.to_biguint().unwrap();
-        let nullifier_5 = 0_u8.to_biguint().unwrap();
-        let nullifier_6 = 65423_u32.to_biguint().unwrap();
-        let nullifier_7 = 745654665_u32.to_biguint().unwrap();
-        let nullifier_8 = 97664353453465354645645465_u128.to_biguint().unwrap();
-        let nullifier_9 = 453565465464565635475_u128.to_biguint().unwrap();
-        let nullifier_10 = 543645654645_u64.to_biguint().unwrap();
+        let nullifier_1 = be_bytes(945635_u32);
+        let nullifier_2 = be_bytes(3546656654734254353455_u128);
+        let nullifier_3 = be_bytes(543543656564_u64);
+        let nullifier_4 = be_bytes(43_u8);
+        let nullifier_5 = be_bytes(0_u8);
+        let nullifier_6 = be_bytes(65423_u32);
+        let nullifier_7 = be_bytes(745654665_u32);
+        let nullifier_8 = be_bytes(97664353453465354645645465_u128);
+        let nullifier_9 = be_bytes(453565465464565635475_u128);
+        let nullifier_10 = be_bytes(543645654645_u64);
 
         hs.insert(&nullifier_1, 0).unwrap();
         hs.insert(&nullifier_2, 0).unwrap();
@@ -983,7 +1139,7 @@ mod test {
 
         let inserted_nullifiers = hs
             .iter()
-            .map(|(_, nullifier)| nullifier.value_biguint())
+            .map(|(_, nullifier)| nullifier.value)
             .collect::<Vec<_>>();
         assert_eq!(inserted_nullifiers.len(), 10);
         assert_eq!(inserted_nullifiers[0], nullifier_5);
@@ -1008,14 +1164,14 @@ mod test {
         let mut expected_nullifiers = Vec::with_capacity(INSERTIONS);
 
         for i in 0..INSERTIONS {
-            let nullifier = i.to_biguint().unwrap();
+            let nullifier = be_bytes(i);
             hs.insert(&nullifier, 0).unwrap();
             expected_nullifiers.push(nullifier);
         }
 
         let inserted_nullifiers = hs
             .iter()
-            .map(|(_, nullifier)| nullifier.value_biguint())
+            .map(|(_, nullifier)| nullifier.value)
             .collect::<Vec<_>>();
         assert_eq!(inserted_nullifiers.len(), INSERTIONS);
         assert_eq!(
@@ -1042,22 +1198,22 @@ mod test {
         let mut hs = HashSet::new(CAPACITY, SEQUENCE_THRESHOLD).unwrap();
         let mut rng = thread_rng();
 
-        let nullifiers: [BigUint; INSERTIONS] =
-            std::array::from_fn(|_| BigUint::from(Fr::rand(&mut rng)));
+        let nullifiers: [[u8; 32]; INSERTIONS] =
+            std::array::from_fn(|_| be_bytes(BigUint::from(Fr::rand(&mut rng))));
 
         for nullifier in nullifiers.iter() {
-            hs.insert(&nullifier, 0).unwrap();
+            hs.insert(nullifier, 0).unwrap();
         }
 
         let mut sorted_nullifiers = nullifiers.iter().collect::<Vec<_>>();
         let mut inserted_nullifiers = hs
             .iter()
-            .map(|(_, nullifier)| nullifier.value_biguint())
+            .map(|(_, nullifier)| nullifier.value)
             .collect::<Vec<_>>();
         sorted_nullifiers.sort();
         inserted_nullifiers.sort();
 
-        let inserted_nullifiers = inserted_nullifiers.iter().collect::<Vec<&BigUint>>();
+        let inserted_nullifiers = inserted_nullifiers.iter().collect::<Vec<&[u8; 32]>>();
         assert_eq!(inserted_nullifiers.len(), INSERTIONS);
         assert_eq!(sorted_nullifiers.as_slice(), inserted_nullifiers.as_slice());
     }
@@ -1079,14 +1235,14 @@ mod test {
         // Insert incremental elements, so they end up being in the same
         // sequence in the hash set.
         for i in 0..3600 {
-            let bn_i = i.to_biguint().unwrap();
+            let bn_i = be_bytes(i);
             hs.insert(&bn_i, i).unwrap();
         }
 
         for i in 0..3600 {
-            let bn_i = i.to_biguint().unwrap();
+            let bn_i = be_bytes(i);
             let element = hs.get_bucket(i).unwrap().unwrap();
-            assert_eq!(element.value_biguint(), bn_i);
+            assert_eq!(element.value, bn_i);
         }
         // Unused cells within the capacity should be `Some(None)`.
         for i in 3600..6857 {
@@ -1105,14 +1261,14 @@ mod test {
         // Insert incremental elements, so they end up being in the same
         // sequence in the hash set.
         for i in 0..3600 {
-            let bn_i = i.to_biguint().unwrap();
+            let bn_i = be_bytes(i);
             hs.insert(&bn_i, i).unwrap();
         }
 
         for i in 0..3600 {
-            let bn_i = i.to_biguint().unwrap();
+            let bn_i = be_bytes(i);
             let element = hs.get_bucket_mut(i).unwrap();
-            assert_eq!(element.unwrap().value_biguint(), bn_i);
+            assert_eq!(element.unwrap().value, bn_i);
 
             // "Nullify" the element.
             *element = Some(HashSetCell {
@@ -1142,7 +1298,7 @@ mod test {
         // sequence in the hash set.
         let indices = (0..3600)
             .map(|i| {
-                let bn_i = i.to_biguint().unwrap();
+                let bn_i = be_bytes(i);
                 hs.insert(&bn_i, i).unwrap()
             })
             .collect::<Vec<_>>();
@@ -1170,11 +1326,166 @@ mod test {
         // Insert incremental elements, so they end up being in the same
         // sequence in the hash set.
         for i in 0..3600 {
-            let bn_i = i.to_biguint().unwrap();
+            let bn_i = be_bytes(i);
             hs.insert(&bn_i, i).unwrap();
 
             let element = hs.first_no_seq().unwrap().unwrap();
-            assert_eq!(element.0.value_biguint(), 0.to_biguint().unwrap());
+            assert_eq!(element.0.value, be_bytes(0));
+        }
+    }
+
+    /// Reference implementation of quadratic probing with big integer
+    /// arithmetic, used by hash sets before switching to fixed-width
+    /// integers.
+    fn probe_index_biguint(value: &BigUint, iteration: usize, capacity: usize) -> usize {
+        let probe_index = (value.clone()
+            + iteration.to_biguint().unwrap() * iteration.to_biguint().unwrap())
+            % capacity.to_biguint().unwrap();
+        probe_index.to_usize().unwrap()
+    }
+
+    /// Checks whether fixed-width probing visits exactly the same buckets as
+    /// big integer probing, so hash sets in existing accounts remain valid.
+    #[test]
+    fn test_probe_index_matches_biguint() {
+        let mut rng = thread_rng();
+        let capacities = [
+            1,
+            2,
+            4800,
+            6857,
+            9601,
+            u16::MAX as usize,
+            u32::MAX as usize,
+            u32::MAX as usize + 1,
+            usize::MAX,
+        ];
+        let mut values = vec![[0u8; 32], [u8::MAX; 32]];
+        for _ in 0..100 {
+            values.push(rng.gen());
+        }
+        for _ in 0..100 {
+            values.push(be_bytes(BigUint::from(Fr::rand(&mut rng))));
+        }
+
+        for capacity in capacities {
+            for value in values.iter() {
+                let value_bn = BigUint::from_bytes_be(value);
+                let value_index = value_index(value, capacity);
+                assert_eq!(value_index, probe_index_biguint(&value_bn, 0, capacity));
+                for iteration in
+                    (0..40).chain([capacity - 1, capacity, capacity.saturating_add(1), 1 << 20])
+                {
+                    assert_eq!(
+                        probe_index(value_index, iteration, capacity),
+                        probe_index_biguint(&value_bn, iteration, capacity),
+                        "value: {:?}, capacity: {}, iteration: {}",
+                        value,
+                        capacity,
+                        iteration
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_set_migrate_and_metrics() {
+        let mut hs = HashSet::new(6857, 2400).unwrap();
+
+        // Insert 100 elements, mark 50 of them at sequence numbers 0..50.
+        let indices = (0..100)
+            .map(|i| {
+                let bn_i = be_bytes(i);
+                hs.insert(&bn_i, 0).unwrap()
+            })
+            .collect::<Vec<_>>();
+        for (i, index) in indices.iter().take(50).enumerate() {
+            hs.mark_with_sequence_number(*index, i).unwrap();
+        }
+
+        let metrics = hs.metrics(0);
+        assert_eq!(
+            metrics,
+            HashSetMetrics {
+                capacity: 6857,
+                occupied: 100,
+                unmarked: 50,
+                marked: 50,
+            }
+        );
+        assert_eq!(metrics.load_factor_bps(), 145);
+        assert_eq!(metrics.available(), 6757);
+
+        // At sequence number 2409, the first 10 marked elements are expired.
+        let current_sequence_number = 2409;
+        assert_eq!(hs.metrics(current_sequence_number).occupied, 90);
+
+        // Migrate in chunks of 1000 buckets, the last chunk exceeds the
+        // capacity.
+        let mut new_hs = HashSet::new(13711, 2400).unwrap();
+        let mut migrated = 0;
+        for start in (0..hs.capacity).step_by(1000) {
+            migrated += hs
+                .migrate(&mut new_hs, start..start + 1000, current_sequence_number)
+                .unwrap();
+        }
+        assert_eq!(migrated, 90);
+        assert_eq!(
+            new_hs.metrics(current_sequence_number),
+            HashSetMetrics {
+                capacity: 13711,
+                occupied: 90,
+                unmarked: 50,
+                marked: 40,
+            }
+        );
+
+        // Sequence numbers are preserved, so marked elements expire in the
+        // new hash set at the same time as in the old one.
+        for i in 0..100 {
+            let bn_i = be_bytes(i);
+            let old = hs.find_element(&bn_i, None).unwrap().unwrap().0;
+            let new = new_hs.find_element(&bn_i, Some(current_sequence_number));
+            if old.is_valid(current_sequence_number) {
+                let new = new.unwrap().unwrap().0;
+                assert_eq!(old.sequence_number, new.sequence_number);
+            } else {
+                assert!(new.unwrap().is_none());
+            }
+        }
+        assert_eq!(new_hs.metrics(2450).occupied, 50);
+
+        // Migrating into a hash set which already contains the elements
+        // fails.
+        assert_eq!(
+            hs.migrate(&mut new_hs, 0..hs.capacity, current_sequence_number),
+            Err(HashSetError::ElementAlreadyExists)
+        );
+    }
+
+    #[test]
+    fn test_hash_set_migrate_near_full() {
+        let mut hs = HashSet::new(4800, 2400).unwrap();
+        let mut rng = thread_rng();
+        // Insert elements until probing fails.
+        let mut values = Vec::new();
+        loop {
+            let mut value = [0u8; 32];
+            rng.fill(&mut value[1..]);
+            match hs.insert(&value, 0) {
+                Ok(_) => values.push(value),
+                Err(HashSetError::Full) => break,
+                Err(e) => panic!("unexpected error: {:?}", e),
+            }
+        }
+        assert_eq!(hs.metrics(0).occupied, values.len());
+
+        let mut new_hs = HashSet::new(9600, 2400).unwrap();
+        let migrated = hs.migrate(&mut new_hs, 0..hs.capacity, 0).unwrap();
+        assert_eq!(migrated, values.len());
+        for value in values.iter() {
+            assert!(new_hs.contains(value, Some(0)).unwrap());
         }
     }
 }
@@ -5,6 +5,8 @@ use std::{
     ptr::NonNull,
 };
 
+use light_bounded_vec::zero_copy::ptr_from_bytes_mut;
+
 use crate::{HashSet, HashSetCell, HashSetError};
 
 /// A `HashSet` wrapper which can be instantiated from Solana account bytes
@@ -12,7 +14,7 @@ use crate::{HashSet, HashSetCell, HashSetError};
 #[derive(Debug)]
 pub struct HashSetZeroCopy<'a> {
     pub hash_set: mem::ManuallyDrop<HashSet>,
-    _marker: PhantomData<&'a ()>,
+    _marker: PhantomData<&'a mut [u8]>,
 }
 
 impl<'a> HashSetZeroCopy<'a> {
@@ -27,11 +29,10 @@ impl<'a> HashSetZeroCopy<'a> {
     ///
     /// # Safety
     ///
-    /// This is highly unsafe. Ensuring the alignment and that the slice
-    /// provides actual data of the hash set is the caller's responsibility.
-    ///
-    /// Calling it in async context (or anyhwere where the underlying data can
-    /// be moved in the memory) is certainly going to cause undefined behavior.
+    /// The size and alignment of the buckets are checked, but ensuring that
+    /// the slice provides actual data of the hash set (buckets initialized
+    /// by [`from_bytes_zero_copy_init`](Self::from_bytes_zero_copy_init)) is
+    /// the caller's responsibility.
     pub unsafe fn from_bytes_zero_copy_mut(bytes: &'a mut [u8]) -> Result<Self, HashSetError> {
         if bytes.len() < HashSet::non_dyn_fields_size() {
             return Err(HashSetError::BufferSize(
@@ -43,23 +44,20 @@ impl<'a> HashSetZeroCopy<'a> {
         let capacity_values = usize::from_ne_bytes(bytes[0..8].try_into().unwrap());
         let sequence_threshold = usize::from_ne_bytes(bytes[8..16].try_into().unwrap());
 
-        let offset = HashSet::non_dyn_fields_size() + mem::size_of::<usize>();
-
-        let values_size = mem::size_of::<Option<HashSetCell>>() * capacity_values;
-
-        let expected_size = HashSet::non_dyn_fields_size() + values_size;
+        let expected_size = HashSet::size_in_account(capacity_values);
         if bytes.len() < expected_size {
             return Err(HashSetError::BufferSize(expected_size, bytes.len()));
         }
 
-        let buckets =
-            NonNull::new(bytes.as_mut_ptr().add(offset) as *mut Option<HashSetCell>).unwrap();
+        let offset = HashSet::non_dyn_fields_size() + mem::size_of::<usize>();
+        let (buckets, _) =
+            ptr_from_bytes_mut::<Option<HashSetCell>>(&mut bytes[offset..], capacity_values)?;
 
         Ok(Self {
             hash_set: mem::ManuallyDrop::new(HashSet {
                 capacity: capacity_values,
                 sequence_threshold,
-                buckets,
+                buckets: NonNull::new(buckets).unwrap(),
             }),
             _marker: PhantomData,
         })
@@ -87,22 +85,18 @@ impl<'a> HashSetZeroCopy<'a> {
     ///
     /// # Safety
     ///
-    /// This is highly unsafe. Ensuring the alignment and that the slice has
-    /// a correct size, which is able to fit the hash set, is the caller's
-    /// responsibility.
-    ///
-    /// Calling it in async context (or anywhere where the underlying data can
-    /// be moved in memory) is certainly going to cause undefined behavior.
+    /// The size and alignment of the buffer are checked. The previous content
+    /// of the buckets is overwritten without being read, so the function is
+    /// unsafe only for consistency with
+    /// [`from_bytes_zero_copy_mut`](Self::from_bytes_zero_copy_mut).
     pub unsafe fn from_bytes_zero_copy_init(
         bytes: &'a mut [u8],
         capacity_values: usize,
         sequence_threshold: usize,
     ) -> Result<Self, HashSetError> {
-        if bytes.len() < HashSet::non_dyn_fields_size() {
-            return Err(HashSetError::BufferSize(
-                HashSet::non_dyn_fields_size(),
-                bytes.len(),
-            ));
+        let expected_size = HashSet::size_in_account(capacity_values);
+        if bytes.len() < expected_size {
+            return Err(HashSetError::BufferSize(expected_size, bytes.len()));
         }
 
         bytes[0..8].copy_from_slice(&capacity_values.to_ne_bytes());
@@ -151,8 +145,8 @@ impl<'a> DerefMut for HashSetZeroCopy<'a> {
 #[cfg(test)]
 mod test {
     use ark_bn254::Fr;
-    use ark_ff::UniformRand;
-    use num_bigint::BigUint;
+    use ark_ff::{BigInteger, PrimeField, UniformRand};
+    use light_bounded_vec::BoundedVecError;
     use rand::{thread_rng, Rng};
 
     use super::*;
@@ -168,8 +162,13 @@ mod test {
 
         // Create random nullifiers.
         let mut rng = thread_rng();
-        let nullifiers: [BigUint; 2400] =
-            std::array::from_fn(|_| BigUint::from(Fr::rand(&mut rng)));
+        let nullifiers: [[u8; 32]; 2400] = std::array::from_fn(|_| {
+            Fr::rand(&mut rng)
+                .into_bigint()
+                .to_bytes_be()
+                .try_into()
+                .unwrap()
+        });
 
         // Initialize a hash set on top of a byte slice.
         {
@@ -190,7 +189,7 @@ mod test {
             }
 
             for (seq, nullifier) in nullifiers.iter().enumerate() {
-                let index = hs.insert(&nullifier, seq).unwrap();
+                let index = hs.insert(nullifier, seq).unwrap();
                 hs.mark_with_sequence_number(index, seq).unwrap();
             }
         }
@@ -205,7 +204,7 @@ mod test {
             }
 
             for (seq, nullifier) in nullifiers.iter().enumerate() {
-                hs.insert(&nullifier, 2400 + seq as usize).unwrap();
+                hs.insert(nullifier, 2400 + seq as usize).unwrap();
             }
             drop(hs);
         }
@@ -239,4 +238,28 @@ mod test {
         };
         assert!(matches!(res, Err(HashSetError::BufferSize(_, _))));
     }
+
+    #[test]
+    fn test_unaligned_buffer_error() {
+        const VALUES: usize = 4800;
+        const SEQUENCE_THRESHOLD: usize = 2400;
+
+        let size = HashSet::size_in_account(VALUES);
+        let mut bytes = vec![0u8; size + 16];
+        let offset = bytes.as_ptr().align_offset(mem::align_of::<usize>()) + 1;
+
+        let res = unsafe {
+            HashSetZeroCopy::from_bytes_zero_copy_init(
+                &mut bytes[offset..offset + size],
+                VALUES,
+                SEQUENCE_THRESHOLD,
+            )
+        };
+        assert!(matches!(
+            res,
+            Err(HashSetError::BoundedVec(BoundedVecError::UnalignedBuffer(
+                _
+            )))
+        ));
+    }
 }
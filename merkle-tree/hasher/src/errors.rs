@@ -16,6 +16,12 @@ pub enum HasherError {
     PoseidonSyscall(#[from] PoseidonSyscallError),
     #[error("Unknown Solana syscall error: {0}")]
     UnknownSolanaSyscall(u64),
+    #[error("Invalid domain separator length, expected at most {0} bytes, got {1}")]
+    InvalidDomainLength(usize, usize),
+    #[error("Borsh serialization of the hashed data failed")]
+    BorshSerialization,
+    #[error("Hashing to bn254 field size failed")]
+    HashToFieldSize,
 }
 
 // NOTE(vadorovsky): Unfortunately, we need to do it by hand. `num_derive::ToPrimitive`
@@ -30,6 +36,9 @@ impl From<HasherError> for u32 {
             #[cfg(target_os = "solana")]
             HasherError::PoseidonSyscall(e) => (u64::from(e)).try_into().unwrap_or(7003),
             HasherError::UnknownSolanaSyscall(e) => e.try_into().unwrap_or(7004),
+            HasherError::InvalidDomainLength(_, _) => 7005,
+            HasherError::BorshSerialization => 7006,
+            HasherError::HashToFieldSize => 7007,
         }
     }
 }
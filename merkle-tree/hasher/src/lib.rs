@@ -2,6 +2,7 @@ pub mod errors;
 pub mod keccak;
 pub mod poseidon;
 pub mod sha256;
+pub mod sponge;
 pub mod syscalls;
 pub mod zero_bytes;
 pub mod zero_indexed_leaf;
@@ -0,0 +1,221 @@
+//! Hashing of arbitrary-length byte data.
+//!
+//! [`Hasher::hashv`] accepts only a limited number of inputs, each of them
+//! being a field element (in case of Poseidon). [`Sponge`] absorbs byte
+//! data of any length and hashes it in a standardized way:
+//!
+//! 1. The domain separator (at most [`DOMAIN_BYTES`] bytes) is encoded as a
+//!    field element `[len, domain..., 0...]` and hashed to obtain the initial
+//!    state.
+//! 2. Data is split into chunks of [`CHUNK_BYTES`] bytes. Each chunk is
+//!    encoded as a field element `[0, chunk..., 0...]`, which is always
+//!    lower than the bn254 modulus.
+//! 3. Every [`RATE`] chunks, the state is updated with
+//!    `state = H(state, chunk_1, ..., chunk_RATE)`.
+//! 4. The remaining chunks and the total length of the data (as a big-endian
+//!    `u64` element) are absorbed in the final hash
+//!    `H(state, chunk_1, ..., chunk_k, length)`.
+//!
+//! The final hash takes at most 12 inputs, which is the limit of both the
+//! circom Poseidon parameters and the Poseidon syscall, so the result is the
+//! same on-chain and off-chain.
+
+use std::marker::PhantomData;
+
+use crate::{errors::HasherError, Hash, Hasher, HASH_BYTES};
+
+/// Number of data bytes encoded in one field element.
+pub const CHUNK_BYTES: usize = 31;
+/// Number of chunks absorbed in one hash invocation.
+pub const RATE: usize = 10;
+/// Maximum length of a domain separator.
+pub const DOMAIN_BYTES: usize = 31;
+
+/// Absorbs byte data incrementally and hashes it with `H`.
+///
+/// Absorbing the data in one call or in several calls with arbitrary splits
+/// results in the same hash.
+#[derive(Debug, Clone)]
+pub struct Sponge<H: Hasher> {
+    state: Hash,
+    /// Encoded chunks which were not hashed yet.
+    chunks: [[u8; HASH_BYTES]; RATE],
+    /// Number of complete chunks in `chunks`.
+    num_chunks: usize,
+    /// Number of bytes in the incomplete chunk `chunks[num_chunks]`.
+    partial_chunk_len: usize,
+    /// Total number of absorbed bytes.
+    len: u64,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> Sponge<H> {
+    pub fn new(domain: &[u8]) -> Result<Self, HasherError> {
+        if domain.len() > DOMAIN_BYTES {
+            return Err(HasherError::InvalidDomainLength(DOMAIN_BYTES, domain.len()));
+        }
+        let mut domain_element = [0u8; HASH_BYTES];
+        domain_element[0] = domain.len() as u8;
+        domain_element[1..1 + domain.len()].copy_from_slice(domain);
+
+        Ok(Self {
+            state: H::hash(&domain_element)?,
+            chunks: [[0u8; HASH_BYTES]; RATE],
+            num_chunks: 0,
+            partial_chunk_len: 0,
+            len: 0,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Absorbs the given `data`.
+    pub fn absorb(&mut self, mut data: &[u8]) -> Result<(), HasherError> {
+        self.len = self
+            .len
+            .checked_add(data.len() as u64)
+            .ok_or(HasherError::IntegerOverflow)?;
+
+        while !data.is_empty() {
+            // Hash the chunks only when there is more data to absorb, so the
+            // final hash always contains at least one chunk of non-empty
+            // data.
+            if self.num_chunks == RATE {
+                self.permute()?;
+            }
+
+            let offset = 1 + self.partial_chunk_len;
+            let len = data.len().min(CHUNK_BYTES - self.partial_chunk_len);
+            self.chunks[self.num_chunks][offset..offset + len].copy_from_slice(&data[..len]);
+            self.partial_chunk_len += len;
+            data = &data[len..];
+
+            if self.partial_chunk_len == CHUNK_BYTES {
+                self.num_chunks += 1;
+                self.partial_chunk_len = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hashes the remaining chunks together with the length of the data.
+    pub fn finalize(self) -> Result<Hash, HasherError> {
+        let mut len_element = [0u8; HASH_BYTES];
+        len_element[HASH_BYTES - 8..].copy_from_slice(&self.len.to_be_bytes());
+
+        let num_chunks = self.num_chunks + usize::from(self.partial_chunk_len > 0);
+        let mut inputs = [[0u8; HASH_BYTES].as_slice(); RATE + 2];
+        inputs[0] = self.state.as_slice();
+        for (input, chunk) in inputs[1..].iter_mut().zip(self.chunks[..num_chunks].iter()) {
+            *input = chunk.as_slice();
+        }
+        inputs[num_chunks + 1] = len_element.as_slice();
+
+        H::hashv(&inputs[..num_chunks + 2])
+    }
+
+    fn permute(&mut self) -> Result<(), HasherError> {
+        let mut inputs = [[0u8; HASH_BYTES].as_slice(); RATE + 1];
+        inputs[0] = self.state.as_slice();
+        for (input, chunk) in inputs[1..].iter_mut().zip(self.chunks.iter()) {
+            *input = chunk.as_slice();
+        }
+        self.state = H::hashv(&inputs)?;
+
+        self.chunks = [[0u8; HASH_BYTES]; RATE];
+        self.num_chunks = 0;
+        Ok(())
+    }
+}
+
+/// Hashes arbitrary-length `data` with the given `domain` separator.
+pub fn hash_bytes<H: Hasher>(domain: &[u8], data: &[u8]) -> Result<Hash, HasherError> {
+    let mut sponge = Sponge::<H>::new(domain)?;
+    sponge.absorb(data)?;
+    sponge.finalize()
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+    use crate::Poseidon;
+
+    /// Tests:
+    /// 1. the hash of empty data is the hash of the initial state and length
+    /// 2. splitting the data doesn't change the hash, for lengths around
+    ///    chunk and rate boundaries
+    /// 3. length encoding: trailing zeros change the hash
+    /// 4. domain separation: different domains result in different hashes
+    /// 5. domain longer than 31 bytes fails
+    #[test]
+    fn test_sponge() {
+        // 1. empty data
+        let domain_element = {
+            let mut element = [0u8; 32];
+            element[0] = 4;
+            element[1..5].copy_from_slice(b"test");
+            element
+        };
+        let state = Poseidon::hash(&domain_element).unwrap();
+        assert_eq!(
+            hash_bytes::<Poseidon>(b"test", &[]).unwrap(),
+            Poseidon::hashv(&[state.as_slice(), [0u8; 32].as_slice()]).unwrap()
+        );
+
+        // 2. splitting the data
+        let mut rng = thread_rng();
+        for len in [
+            1,
+            30,
+            31,
+            32,
+            CHUNK_BYTES * RATE - 1,
+            CHUNK_BYTES * RATE,
+            CHUNK_BYTES * RATE + 1,
+            CHUNK_BYTES * RATE * 2,
+            1000,
+        ] {
+            let data = (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+            let expected_hash = hash_bytes::<Poseidon>(b"test", &data).unwrap();
+
+            for _ in 0..10 {
+                let mut sponge = Sponge::<Poseidon>::new(b"test").unwrap();
+                let mut remaining = data.as_slice();
+                while !remaining.is_empty() {
+                    let (left, right) = remaining.split_at(rng.gen_range(0..=remaining.len()));
+                    sponge.absorb(left).unwrap();
+                    remaining = right;
+                }
+                assert_eq!(sponge.finalize().unwrap(), expected_hash);
+            }
+
+            // 3. length encoding
+            let mut padded_data = data.clone();
+            padded_data.push(0);
+            assert_ne!(
+                hash_bytes::<Poseidon>(b"test", &padded_data).unwrap(),
+                expected_hash
+            );
+
+            // 4. domain separation
+            assert_ne!(
+                hash_bytes::<Poseidon>(b"test2", &data).unwrap(),
+                expected_hash
+            );
+            assert_ne!(hash_bytes::<Poseidon>(b"", &data).unwrap(), expected_hash);
+        }
+        assert_ne!(
+            hash_bytes::<Poseidon>(b"", &[]).unwrap(),
+            hash_bytes::<Poseidon>(&[0], &[]).unwrap()
+        );
+
+        // 5. domain too long
+        assert!(matches!(
+            Sponge::<Poseidon>::new(&[1u8; 32]),
+            Err(HasherError::InvalidDomainLength(31, 32))
+        ));
+        assert!(Sponge::<Poseidon>::new(&[1u8; 31]).is_ok());
+    }
+}
@@ -1,20 +1,19 @@
 use std::{cmp::Ordering, fmt::Debug, marker::PhantomData};
 
-use crate::{errors::IndexedMerkleTreeError, HIGHEST_ADDRESS_PLUS_ONE};
+use crate::{errors::IndexedMerkleTreeError, value::IndexedValue};
 use light_concurrent_merkle_tree::{event::RawIndexedElement, light_hasher::Hasher};
-use light_utils::bigint::bigint_to_be_bytes_array;
 use num_bigint::BigUint;
-use num_traits::Zero;
 use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
 
 #[derive(Clone, Debug, Default)]
-pub struct IndexedElement<I>
+pub struct IndexedElement<I, V = BigUint>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     pub index: I,
-    pub value: BigUint,
+    pub value: V,
     pub next_index: I,
 }
 
@@ -32,47 +31,52 @@ where
     }
 }
 
-impl<I> PartialEq for IndexedElement<I>
+impl<I, V> PartialEq for IndexedElement<I, V>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
     }
 }
 
-impl<I> Eq for IndexedElement<I>
+impl<I, V> Eq for IndexedElement<I, V>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
 }
 
-impl<I> PartialOrd for IndexedElement<I>
+impl<I, V> PartialOrd for IndexedElement<I, V>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<I> Ord for IndexedElement<I>
+impl<I, V> Ord for IndexedElement<I, V>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn cmp(&self, other: &Self) -> Ordering {
         self.value.cmp(&other.value)
     }
 }
 
-impl<I> IndexedElement<I>
+impl<I, V> IndexedElement<I, V>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     pub fn index(&self) -> usize {
         self.index.into()
@@ -82,62 +86,112 @@ where
         self.next_index.into()
     }
 
-    pub fn hash<H>(&self, next_value: &BigUint) -> Result<[u8; 32], IndexedMerkleTreeError>
+    pub fn hash<H>(&self, next_value: &V) -> Result<[u8; 32], IndexedMerkleTreeError>
     where
         H: Hasher,
     {
         let hash = H::hashv(&[
-            bigint_to_be_bytes_array::<32>(&self.value)?.as_ref(),
+            self.value.to_bytes()?.as_ref(),
             self.next_index.to_be_bytes().as_ref(),
-            bigint_to_be_bytes_array::<32>(next_value)?.as_ref(),
+            next_value.to_bytes()?.as_ref(),
         ])?;
 
         Ok(hash)
     }
 
-    pub fn update_from_raw_element(&mut self, raw_element: &RawIndexedElement<I>) {
+    pub fn update_from_raw_element(
+        &mut self,
+        raw_element: &RawIndexedElement<I>,
+    ) -> Result<(), IndexedMerkleTreeError> {
         self.index = raw_element.index;
-        self.value = BigUint::from_bytes_be(&raw_element.value);
+        self.value = V::from_bytes(&raw_element.value)?;
         self.next_index = raw_element.next_index;
+        Ok(())
+    }
+
+    /// Checks whether the element was deleted. Deleted elements are kept in
+    /// the indexed array to preserve indices of other elements, with the
+    /// lowest value and `next_index` 0. Their leaves are zeroed.
+    pub fn is_deleted(&self) -> bool {
+        self.index != I::zero() && self.value == V::lowest()
     }
+
+    /// Checks whether the element, used as a low element, proves that the
+    /// range `[start, end)` doesn't contain any element.
+    ///
+    /// The range is empty if the value of the element is lower than `start`
+    /// and `next_value` is greater or equal to `end`. If the element is the
+    /// highest one (its `next_index` is 0), there is no upper bound.
+    pub fn check_range_non_inclusion(
+        &self,
+        next_value: &V,
+        start: &V,
+        end: &V,
+    ) -> Result<(), IndexedMerkleTreeError> {
+        if start >= end {
+            return Err(IndexedMerkleTreeError::InvalidRange);
+        }
+        if self.value >= *start {
+            return Err(IndexedMerkleTreeError::LowElementGreaterOrEqualToNewElement);
+        }
+        if self.next_index != I::zero() && next_value < end {
+            return Err(IndexedMerkleTreeError::RangeNotEmpty);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedElementBundle<I, V = BigUint>
+where
+    I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
+    usize: From<I>,
+    V: IndexedValue,
+{
+    pub new_low_element: IndexedElement<I, V>,
+    pub new_element: IndexedElement<I, V>,
+    pub new_element_next_value: V,
 }
 
 #[derive(Clone, Debug)]
-pub struct IndexedElementBundle<I>
+pub struct IndexedElementDeletionBundle<I, V = BigUint>
 where
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    pub new_low_element: IndexedElement<I>,
-    pub new_element: IndexedElement<I>,
-    pub new_element_next_value: BigUint,
+    pub new_low_element: IndexedElement<I, V>,
+    pub new_low_element_next_value: V,
+    pub deleted_element: IndexedElement<I, V>,
 }
 
 #[derive(Clone, Debug)]
-pub struct IndexedArray<H, I>
+pub struct IndexedArray<H, I, V = BigUint>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    pub elements: Vec<IndexedElement<I>>,
+    pub elements: Vec<IndexedElement<I, V>>,
     pub current_node_index: I,
     pub highest_element_index: I,
 
     _hasher: PhantomData<H>,
 }
 
-impl<H, I> Default for IndexedArray<H, I>
+impl<H, I, V> Default for IndexedArray<H, I, V>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn default() -> Self {
         Self {
             elements: vec![IndexedElement {
                 index: I::zero(),
-                value: BigUint::zero(),
+                value: V::lowest(),
                 next_index: I::zero(),
             }],
             current_node_index: I::zero(),
@@ -147,13 +201,14 @@ where
     }
 }
 
-impl<H, I> IndexedArray<H, I>
+impl<H, I, V> IndexedArray<H, I, V>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    pub fn get(&self, index: usize) -> Option<&IndexedElement<I>> {
+    pub fn get(&self, index: usize) -> Option<&IndexedElement<I, V>> {
         self.elements.get(index)
     }
 
@@ -165,7 +220,7 @@ where
         self.current_node_index == I::zero()
     }
 
-    pub fn iter(&self) -> IndexingArrayIter<H, I> {
+    pub fn iter(&self) -> IndexingArrayIter<H, I, V> {
         IndexingArrayIter {
             indexing_array: self,
             front: 0,
@@ -173,17 +228,14 @@ where
         }
     }
 
-    pub fn find_element(&self, value: &BigUint) -> Option<&IndexedElement<I>> {
+    pub fn find_element(&self, value: &V) -> Option<&IndexedElement<I, V>> {
         self.elements[..self.len() + 1]
             .iter()
             .find(|&node| node.value == *value)
     }
 
-    pub fn init(&mut self) -> Result<IndexedElementBundle<I>, IndexedMerkleTreeError> {
-        use num_traits::Num;
-        let init_value = BigUint::from_str_radix(HIGHEST_ADDRESS_PLUS_ONE, 10)
-            .map_err(|_| IndexedMerkleTreeError::IntegerOverflow)?;
-        self.append(&init_value)
+    pub fn init(&mut self) -> Result<IndexedElementBundle<I, V>, IndexedMerkleTreeError> {
+        self.append(&V::highest())
     }
 
     /// Returns the index of the low element for the given `value`, which is
@@ -195,7 +247,7 @@ where
     /// Low elements are used in non-membership proofs.
     pub fn find_low_element_index_for_nonexistent(
         &self,
-        value: &BigUint,
+        value: &V,
     ) -> Result<I, IndexedMerkleTreeError> {
         // Try to find element whose next element is higher than the provided
         // value.
@@ -228,8 +280,8 @@ where
     /// Low elements are used in non-membership proofs.
     pub fn find_low_element_for_nonexistent(
         &self,
-        value: &BigUint,
-    ) -> Result<(IndexedElement<I>, BigUint), IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<(IndexedElement<I, V>, V), IndexedMerkleTreeError> {
         let low_element_index = self.find_low_element_index_for_nonexistent(value)?;
         let low_element = self.elements[usize::from(low_element_index)].clone();
         Ok((
@@ -247,7 +299,7 @@ where
     /// Low elements are used in non-membership proofs.
     pub fn find_low_element_index_for_existent(
         &self,
-        value: &BigUint,
+        value: &V,
     ) -> Result<I, IndexedMerkleTreeError> {
         for (i, node) in self.elements[..self.len() + 1].iter().enumerate() {
             if self.elements[usize::from(node.next_index)].value == *value {
@@ -269,8 +321,8 @@ where
     /// Low elements are used in non-membership proofs.
     pub fn find_low_element_for_existent(
         &self,
-        value: &BigUint,
-    ) -> Result<IndexedElement<I>, IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<IndexedElement<I, V>, IndexedMerkleTreeError> {
         let low_element_index = self.find_low_element_index_for_existent(value)?;
         let low_element = self.elements[usize::from(low_element_index)].clone();
         Ok(low_element)
@@ -281,21 +333,21 @@ where
     /// * The value of the given element.
     /// * The `next_index` of the given element.
     /// * The value of the element pointed by `next_index`.
+    ///
+    /// Leaves of deleted elements are zeroed.
     pub fn hash_element(&self, index: I) -> Result<[u8; 32], IndexedMerkleTreeError> {
         let element = self
             .elements
             .get(usize::from(index))
             .ok_or(IndexedMerkleTreeError::IndexHigherThanMax)?;
+        if element.is_deleted() {
+            return Ok(H::zero_bytes()[0]);
+        }
         let next_element = self
             .elements
             .get(usize::from(element.next_index))
             .ok_or(IndexedMerkleTreeError::IndexHigherThanMax)?;
-        let hash = H::hashv(&[
-            bigint_to_be_bytes_array::<32>(&element.value)?.as_ref(),
-            element.next_index.to_be_bytes().as_ref(),
-            bigint_to_be_bytes_array::<32>(&next_element.value)?.as_ref(),
-        ])?;
-        Ok(hash)
+        element.hash::<H>(&next_element.value)
     }
 
     /// Returns an updated low element and a new element, created based on the
@@ -303,8 +355,8 @@ where
     pub fn new_element_with_low_element_index(
         &self,
         low_element_index: I,
-        value: &BigUint,
-    ) -> Result<IndexedElementBundle<I>, IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<IndexedElementBundle<I, V>, IndexedMerkleTreeError> {
         let mut new_low_element = self.elements[usize::from(low_element_index)].clone();
 
         let new_element_index = self
@@ -332,8 +384,8 @@ where
 
     pub fn new_element(
         &self,
-        value: &BigUint,
-    ) -> Result<IndexedElementBundle<I>, IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<IndexedElementBundle<I, V>, IndexedMerkleTreeError> {
         let low_element_index = self.find_low_element_index_for_nonexistent(value)?;
         let element = self.new_element_with_low_element_index(low_element_index, value)?;
 
@@ -344,8 +396,8 @@ where
     pub fn append_with_low_element_index(
         &mut self,
         low_element_index: I,
-        value: &BigUint,
-    ) -> Result<IndexedElementBundle<I>, IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<IndexedElementBundle<I, V>, IndexedMerkleTreeError> {
         // TOD0: add length check, and add field to with tree height here
 
         let old_low_element = &self.elements[usize::from(low_element_index)];
@@ -399,13 +451,81 @@ where
 
     pub fn append(
         &mut self,
-        value: &BigUint,
-    ) -> Result<IndexedElementBundle<I>, IndexedMerkleTreeError> {
+        value: &V,
+    ) -> Result<IndexedElementBundle<I, V>, IndexedMerkleTreeError> {
         let low_element_index = self.find_low_element_index_for_nonexistent(value)?;
         self.append_with_low_element_index(low_element_index, value)
     }
 
-    pub fn lowest(&self) -> Option<IndexedElement<I>> {
+    /// Returns the low element and its next value, which prove that the
+    /// range `[start, end)` doesn't contain any element of the array.
+    pub fn find_low_element_for_nonexistent_range(
+        &self,
+        start: &V,
+        end: &V,
+    ) -> Result<(IndexedElement<I, V>, V), IndexedMerkleTreeError> {
+        if start >= end {
+            return Err(IndexedMerkleTreeError::InvalidRange);
+        }
+        let (low_element, low_element_next_value) = self
+            .find_low_element_for_nonexistent(start)
+            .map_err(|e| match e {
+                IndexedMerkleTreeError::ElementAlreadyExists => {
+                    IndexedMerkleTreeError::RangeNotEmpty
+                }
+                e => e,
+            })?;
+        low_element.check_range_non_inclusion(&low_element_next_value, start, end)?;
+        Ok((low_element, low_element_next_value))
+    }
+
+    /// Deletes the element with the given `value` from the array, by
+    /// pointing its low element to the next element of the deleted one.
+    ///
+    /// The deleted element is replaced with a tombstone (see
+    /// [`IndexedElement::is_deleted`]), so indices of other elements don't
+    /// change.
+    pub fn delete(
+        &mut self,
+        value: &V,
+    ) -> Result<IndexedElementDeletionBundle<I, V>, IndexedMerkleTreeError> {
+        let deleted_element = self
+            .find_element(value)
+            .cloned()
+            .ok_or(IndexedMerkleTreeError::ElementDoesNotExist)?;
+        if deleted_element.index == I::zero() {
+            return Err(IndexedMerkleTreeError::LowestElementDeletion);
+        }
+        if *value == V::highest() {
+            return Err(IndexedMerkleTreeError::HighestElementDeletion);
+        }
+        let low_element_index = self.find_low_element_index_for_existent(value)?;
+
+        let mut new_low_element = self.elements[usize::from(low_element_index)].clone();
+        new_low_element.next_index = deleted_element.next_index;
+        let new_low_element_next_value = self.elements[deleted_element.next_index()].value.clone();
+
+        // If the deleted element was the highest one, its low element becomes
+        // the highest.
+        if deleted_element.index == self.highest_element_index {
+            self.highest_element_index = low_element_index;
+        }
+
+        self.elements[usize::from(low_element_index)] = new_low_element.clone();
+        self.elements[deleted_element.index()] = IndexedElement {
+            index: deleted_element.index,
+            value: V::lowest(),
+            next_index: I::zero(),
+        };
+
+        Ok(IndexedElementDeletionBundle {
+            new_low_element,
+            new_low_element_next_value,
+            deleted_element,
+        })
+    }
+
+    pub fn lowest(&self) -> Option<IndexedElement<I, V>> {
         if self.current_node_index < I::one() {
             None
         } else {
@@ -414,24 +534,26 @@ where
     }
 }
 
-pub struct IndexingArrayIter<'a, H, I>
+pub struct IndexingArrayIter<'a, H, I, V = BigUint>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    indexing_array: &'a IndexedArray<H, I>,
+    indexing_array: &'a IndexedArray<H, I, V>,
     front: usize,
     back: usize,
 }
 
-impl<'a, H, I> Iterator for IndexingArrayIter<'a, H, I>
+impl<'a, H, I, V> Iterator for IndexingArrayIter<'a, H, I, V>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    type Item = &'a IndexedElement<I>;
+    type Item = &'a IndexedElement<I, V>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.front <= self.back {
@@ -444,11 +566,12 @@ where
     }
 }
 
-impl<'a, H, I> DoubleEndedIterator for IndexingArrayIter<'a, H, I>
+impl<'a, H, I, V> DoubleEndedIterator for IndexingArrayIter<'a, H, I, V>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         if self.back >= self.front {
@@ -464,6 +587,7 @@ where
 #[cfg(test)]
 mod test {
     use light_concurrent_merkle_tree::light_hasher::Poseidon;
+    use light_utils::bigint::bigint_to_be_bytes_array;
     use num_bigint::{RandBigInt, ToBigUint};
     use rand::thread_rng;
 
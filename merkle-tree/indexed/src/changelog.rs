@@ -1,7 +1,8 @@
+use bytemuck::{Pod, Zeroable};
 use light_concurrent_merkle_tree::event::RawIndexedElement;
 
 /// NET_HEIGHT = HEIGHT -  CANOPY_DEPTH
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct IndexedChangelogEntry<I, const NET_HEIGHT: usize>
 where
     I: Clone,
@@ -14,3 +15,10 @@ where
     /// the same operation.
     pub changelog_index: usize,
 }
+
+// SAFETY: With `usize` indices, all fields (including the ones of
+// `RawIndexedElement`) are byte arrays and integers with sizes divisible by 8,
+// so there is no padding regardless of their order. Any bit pattern is a valid
+// value.
+unsafe impl<const NET_HEIGHT: usize> Zeroable for IndexedChangelogEntry<usize, NET_HEIGHT> {}
+unsafe impl<const NET_HEIGHT: usize> Pod for IndexedChangelogEntry<usize, NET_HEIGHT> {}
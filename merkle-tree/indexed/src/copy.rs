@@ -1,17 +1,18 @@
 use std::{fmt, marker::PhantomData, ops::Deref};
 
-use crate::{errors::IndexedMerkleTreeError, IndexedMerkleTree};
+use crate::{errors::IndexedMerkleTreeError, value::IndexedValue, IndexedMerkleTree};
 use light_bounded_vec::CyclicBoundedVecMetadata;
 use light_concurrent_merkle_tree::{
     copy::ConcurrentMerkleTreeCopy, errors::ConcurrentMerkleTreeError,
 };
 use light_hasher::Hasher;
 use light_utils::offset::copy::{read_cyclic_bounded_vec_at, read_value_at};
+use num_bigint::BigUint;
 use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
 
 #[derive(Debug)]
-pub struct IndexedMerkleTreeCopy<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>(
-    IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>,
+pub struct IndexedMerkleTreeCopy<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V = BigUint>(
+    IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>,
 )
 where
     H: Hasher,
@@ -24,10 +25,11 @@ where
         + ToBytes
         + TryFrom<usize>
         + Unsigned,
-    usize: From<I>;
+    usize: From<I>,
+    V: IndexedValue;
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>
-    IndexedMerkleTreeCopy<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V>
+    IndexedMerkleTreeCopy<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -40,6 +42,7 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     /// Casts a byte slice into wrapped `IndexedMerkleTree` structure reference,
     /// including dynamic fields.
@@ -55,7 +58,7 @@ where
         let indexed_changelog_metadata: CyclicBoundedVecMetadata =
             unsafe { read_value_at(bytes, &mut offset) };
 
-        let expected_size = IndexedMerkleTree::<H, I, HEIGHT, NET_HEIGHT>::size_in_account(
+        let expected_size = IndexedMerkleTree::<H, I, HEIGHT, NET_HEIGHT, V>::size_in_account(
             merkle_tree.height,
             merkle_tree.changelog.capacity(),
             merkle_tree.roots.capacity(),
@@ -75,12 +78,13 @@ where
             merkle_tree,
             indexed_changelog,
             _index: PhantomData,
+            _value: PhantomData,
         }))
     }
 }
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize> Deref
-    for IndexedMerkleTreeCopy<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> Deref
+    for IndexedMerkleTreeCopy<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -93,8 +97,9 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>;
+    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -33,6 +33,18 @@ pub enum IndexedMerkleTreeError {
     BoundedVec(#[from] BoundedVecError),
     #[error("Indexed array is full, cannot append more elements")]
     ArrayFull,
+    #[error("Invalid encoding of an indexed value")]
+    InvalidValueEncoding,
+    #[error("The start of the range is greater or equal to its end.")]
+    InvalidRange,
+    #[error("The range contains at least one element.")]
+    RangeNotEmpty,
+    #[error("The lowest element cannot be deleted.")]
+    LowestElementDeletion,
+    #[error("The low element does not point to the deleted element.")]
+    InvalidLowElement,
+    #[error("The highest element cannot be deleted.")]
+    HighestElementDeletion,
 }
 
 // NOTE(vadorovsky): Unfortunately, we need to do it by hand. `num_derive::ToPrimitive`
@@ -50,6 +62,12 @@ impl From<IndexedMerkleTreeError> for u32 {
             IndexedMerkleTreeError::ElementDoesNotExist => 11007,
             IndexedMerkleTreeError::ChangelogBufferSize(_, _) => 11008,
             IndexedMerkleTreeError::ArrayFull => 11009,
+            IndexedMerkleTreeError::InvalidValueEncoding => 11010,
+            IndexedMerkleTreeError::InvalidRange => 11011,
+            IndexedMerkleTreeError::RangeNotEmpty => 11012,
+            IndexedMerkleTreeError::LowestElementDeletion => 11013,
+            IndexedMerkleTreeError::InvalidLowElement => 11014,
+            IndexedMerkleTreeError::HighestElementDeletion => 11015,
             IndexedMerkleTreeError::Hasher(e) => e.into(),
             IndexedMerkleTreeError::ConcurrentMerkleTree(e) => e.into(),
             IndexedMerkleTreeError::Utils(e) => e.into(),
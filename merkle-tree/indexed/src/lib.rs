@@ -14,25 +14,25 @@ use light_concurrent_merkle_tree::{
     light_hasher::Hasher,
     ConcurrentMerkleTree,
 };
-use light_utils::bigint::bigint_to_be_bytes_array;
 use num_bigint::BigUint;
-use num_traits::{CheckedAdd, CheckedSub, Num, ToBytes, Unsigned};
+use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
 
 pub mod array;
 pub mod changelog;
 pub mod copy;
 pub mod errors;
 pub mod reference;
+pub mod value;
 pub mod zero_copy;
 
-use crate::errors::IndexedMerkleTreeError;
+use crate::{errors::IndexedMerkleTreeError, value::IndexedValue};
 
 pub const HIGHEST_ADDRESS_PLUS_ONE: &str =
     "452312848583266388373324160190187140051835877600158453279131187530910662655";
 
 #[derive(Debug)]
 #[repr(C)]
-pub struct IndexedMerkleTree<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>
+pub struct IndexedMerkleTree<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V = BigUint>
 where
     H: Hasher,
     I: CheckedAdd
@@ -45,16 +45,19 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     pub merkle_tree: ConcurrentMerkleTree<H, HEIGHT>,
     pub indexed_changelog: CyclicBoundedVec<IndexedChangelogEntry<I, NET_HEIGHT>>,
 
     _index: PhantomData<I>,
+    _value: PhantomData<V>,
 }
 
 pub type IndexedMerkleTree26<H, I> = IndexedMerkleTree<H, I, 26, 16>;
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize> IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V>
+    IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -67,6 +70,7 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     /// Size of the struct **without** dynamically sized fields (`BoundedVec`,
     /// `CyclicBoundedVec`).
@@ -113,6 +117,7 @@ where
             merkle_tree,
             indexed_changelog: CyclicBoundedVec::with_capacity(indexed_changelog_size),
             _index: PhantomData,
+            _value: PhantomData,
         })
     }
 
@@ -137,14 +142,14 @@ where
             proof: H::zero_bytes()[..NET_HEIGHT].try_into().unwrap(),
             changelog_index: 0,
         };
-        self.indexed_changelog.push(changelog_entry.clone());
+        self.indexed_changelog.push(changelog_entry);
         self.indexed_changelog.push(changelog_entry);
 
         Ok(())
     }
 
     /// Add the hightest element with a maximum value allowed by the prime
-    /// field.
+    /// field (or, for custom value types, [`IndexedValue::highest`]).
     ///
     /// Initializing an indexed Merkle tree not only with the lowest element
     /// (mandatory for the IMT algorithm to work), but also the highest element,
@@ -154,9 +159,9 @@ where
     /// However, it comes with a tradeoff - the space available in the tree
     /// becomes lower by 1.
     pub fn add_highest_element(&mut self) -> Result<(), IndexedMerkleTreeError> {
-        let init_value = BigUint::from_str_radix(HIGHEST_ADDRESS_PLUS_ONE, 10).unwrap();
+        let init_value = V::highest();
 
-        let mut indexed_array = IndexedArray::<H, I>::default();
+        let mut indexed_array = IndexedArray::<H, I, V>::default();
         let element_bundle = indexed_array.append(&init_value)?;
         let new_low_leaf = element_bundle
             .new_low_element
@@ -179,9 +184,9 @@ where
 
         // Emit changelog for low element.
         let low_element = RawIndexedElement {
-            value: bigint_to_be_bytes_array::<32>(&element_bundle.new_low_element.value)?,
+            value: element_bundle.new_low_element.value.to_bytes()?,
             next_index: element_bundle.new_low_element.next_index,
-            next_value: bigint_to_be_bytes_array::<32>(&element_bundle.new_element.value)?,
+            next_value: element_bundle.new_element.value.to_bytes()?,
             index: element_bundle.new_low_element.index,
         };
 
@@ -200,7 +205,7 @@ where
 
         // Emit changelog for new element.
         let new_element = RawIndexedElement {
-            value: bigint_to_be_bytes_array::<32>(&element_bundle.new_element.value)?,
+            value: element_bundle.new_element.value.to_bytes()?,
             next_index: element_bundle.new_element.next_index,
             next_value: [0_u8; 32],
             index: element_bundle.new_element.index,
@@ -248,9 +253,9 @@ where
         &mut self,
         indexed_changelog_index: usize,
         changelog_index: &mut usize,
-        new_element: &mut IndexedElement<I>,
-        low_element: &mut IndexedElement<I>,
-        low_element_next_value: &mut BigUint,
+        new_element: &mut IndexedElement<I, V>,
+        low_element: &mut IndexedElement<I, V>,
+        low_element_next_value: &mut V,
         low_leaf_proof: &mut BoundedVec<[u8; 32]>,
     ) -> Result<(), IndexedMerkleTreeError> {
         let next_indexed_changelog_indices: Vec<usize> = self
@@ -272,7 +277,29 @@ where
         for next_indexed_changelog_index in next_indexed_changelog_indices {
             let changelog_entry = &mut self.indexed_changelog[next_indexed_changelog_index];
 
-            let next_element_value = BigUint::from_bytes_be(&changelog_entry.element.next_value);
+            // If the low element was deleted, its range belongs to its own
+            // low element, whose changelog entry is pushed right before the
+            // deletion entry.
+            //
+            // Save it and break the loop.
+            if changelog_entry.element.index != I::zero()
+                && V::from_bytes(&changelog_entry.element.value)? == V::lowest()
+            {
+                let previous_indexed_changelog_index =
+                    (next_indexed_changelog_index + self.indexed_changelog.len() - 1)
+                        % self.indexed_changelog.len();
+                new_low_element = Some((
+                    previous_indexed_changelog_index,
+                    V::from_bytes(
+                        &self.indexed_changelog[previous_indexed_changelog_index]
+                            .element
+                            .value,
+                    )?,
+                ));
+                break;
+            }
+
+            let next_element_value = V::from_bytes(&changelog_entry.element.next_value)?;
             if next_element_value < new_element.value {
                 // If the next element is lower than the current element, it means
                 // that it should become the low element.
@@ -292,9 +319,9 @@ where
             new_element.next_index = changelog_entry.element.next_index;
 
             // Patch the element.
-            low_element.update_from_raw_element(&changelog_entry.element);
+            low_element.update_from_raw_element(&changelog_entry.element)?;
             // Patch the next value.
-            *low_element_next_value = BigUint::from_bytes_be(&changelog_entry.element.next_value);
+            *low_element_next_value = V::from_bytes(&changelog_entry.element.next_value)?;
             // Patch the proof.
             for i in 0..low_leaf_proof.len() {
                 low_leaf_proof[i] = changelog_entry.proof[i];
@@ -312,6 +339,9 @@ where
                 next_index: new_low_element_changelog_entry.element.next_index,
             };
 
+            *low_element_next_value =
+                V::from_bytes(&new_low_element_changelog_entry.element.next_value)?;
+
             for i in 0..low_leaf_proof.len() {
                 low_leaf_proof[i] = new_low_element_changelog_entry.proof[i];
             }
@@ -335,9 +365,9 @@ where
         &mut self,
         mut changelog_index: usize,
         indexed_changelog_index: usize,
-        new_element_value: BigUint,
-        mut low_element: IndexedElement<I>,
-        mut low_element_next_value: BigUint,
+        new_element_value: V,
+        mut low_element: IndexedElement<I, V>,
+        mut low_element_next_value: V,
         low_leaf_proof: &mut BoundedVec<[u8; 32]>,
     ) -> Result<IndexedMerkleTreeUpdate<I>, IndexedMerkleTreeError> {
         let mut new_element = IndexedElement {
@@ -398,9 +428,9 @@ where
 
         // Emit changelog entry for low element.
         let new_low_element = RawIndexedElement {
-            value: bigint_to_be_bytes_array::<32>(&new_low_element.value).unwrap(),
+            value: new_low_element.value.to_bytes()?,
             next_index: new_low_element.next_index,
-            next_value: bigint_to_be_bytes_array::<32>(&new_element.value)?,
+            next_value: new_element.value.to_bytes()?,
             index: new_low_element.index,
         };
         let low_element_changelog_entry = IndexedChangelogEntry {
@@ -425,9 +455,9 @@ where
 
         // Prepare raw new element to save in changelog.
         let raw_new_element = RawIndexedElement {
-            value: bigint_to_be_bytes_array::<32>(&new_element.value).unwrap(),
+            value: new_element.value.to_bytes()?,
             next_index: new_element.next_index,
-            next_value: bigint_to_be_bytes_array::<32>(&low_element_next_value)?,
+            next_value: low_element_next_value.to_bytes()?,
             index: new_element.index,
         };
 
@@ -448,10 +478,130 @@ where
 
         Ok(output)
     }
+
+    /// Deletes `element` from the tree:
+    ///
+    /// 1. Points `low_element` to the next element of `element`.
+    /// 2. Zeroes the leaf of `element`.
+    ///
+    /// Both changes are saved in the indexed changelog. The deleted element
+    /// is saved as a tombstone (see [`IndexedElement::is_deleted`]), so
+    /// concurrent updates using it as a low element get patched to its low
+    /// element.
+    ///
+    /// Merkle proofs are patched with the changelog, but, unlike in
+    /// [`update`](IndexedMerkleTree::update), elements are not patched with
+    /// the indexed changelog. If `low_element` or `element` changed after
+    /// `changelog_index`, their leaves can't be updated and the caller has
+    /// to retry with up to date elements.
+    pub fn delete(
+        &mut self,
+        changelog_index: usize,
+        low_element: IndexedElement<I, V>,
+        element: IndexedElement<I, V>,
+        element_next_value: V,
+        low_leaf_proof: &mut BoundedVec<[u8; 32]>,
+        leaf_proof: &mut BoundedVec<[u8; 32]>,
+    ) -> Result<(), IndexedMerkleTreeError> {
+        if element.index == I::zero() {
+            return Err(IndexedMerkleTreeError::LowestElementDeletion);
+        }
+        if element.value == V::highest() {
+            return Err(IndexedMerkleTreeError::HighestElementDeletion);
+        }
+        if low_element.next_index != element.index {
+            return Err(IndexedMerkleTreeError::InvalidLowElement);
+        }
+
+        // Point the low element to the next element of the deleted one.
+        let new_low_element = IndexedElement {
+            index: low_element.index,
+            value: low_element.value.clone(),
+            next_index: element.next_index,
+        };
+        let old_low_leaf = low_element.hash::<H>(&element.value)?;
+        let new_low_leaf = new_low_element.hash::<H>(&element_next_value)?;
+        let (new_changelog_index, _) = self.merkle_tree.update(
+            changelog_index,
+            &old_low_leaf,
+            &new_low_leaf,
+            low_element.index.into(),
+            low_leaf_proof,
+        )?;
+
+        // Emit changelog entry for low element.
+        let new_low_element = RawIndexedElement {
+            value: new_low_element.value.to_bytes()?,
+            next_index: new_low_element.next_index,
+            next_value: element_next_value.to_bytes()?,
+            index: new_low_element.index,
+        };
+        let low_element_changelog_entry = IndexedChangelogEntry {
+            element: new_low_element,
+            proof: low_leaf_proof.as_slice()[..NET_HEIGHT].try_into().unwrap(),
+            changelog_index: new_changelog_index,
+        };
+        self.indexed_changelog.push(low_element_changelog_entry);
+
+        // Zero the leaf of the deleted element. Its proof is patched with
+        // the low element update.
+        let old_leaf = element.hash::<H>(&element_next_value)?;
+        let (new_changelog_index, _) = self.merkle_tree.update(
+            changelog_index,
+            &old_leaf,
+            &H::zero_bytes()[0],
+            element.index.into(),
+            leaf_proof,
+        )?;
+
+        // Emit changelog entry for the deleted element.
+        let deleted_element = RawIndexedElement {
+            value: V::lowest().to_bytes()?,
+            next_index: I::zero(),
+            next_value: V::lowest().to_bytes()?,
+            index: element.index,
+        };
+        let deleted_element_changelog_entry = IndexedChangelogEntry {
+            element: deleted_element,
+            proof: leaf_proof.as_slice()[..NET_HEIGHT].try_into().unwrap(),
+            changelog_index: new_changelog_index,
+        };
+        self.indexed_changelog.push(deleted_element_changelog_entry);
+
+        Ok(())
+    }
+
+    /// Checks whether `low_element` proves that the range `[start, end)`
+    /// doesn't contain any element of the tree (see
+    /// [`IndexedElement::check_range_non_inclusion`]) and validates its
+    /// Merkle `proof` against the current root.
+    pub fn validate_range_non_inclusion(
+        &self,
+        start: &V,
+        end: &V,
+        low_element: &IndexedElement<I, V>,
+        low_element_next_value: &V,
+        proof: &mut BoundedVec<[u8; 32]>,
+    ) -> Result<(), IndexedMerkleTreeError> {
+        low_element.check_range_non_inclusion(low_element_next_value, start, end)?;
+
+        let expected_proof_len = self.merkle_tree.height - self.merkle_tree.canopy_depth;
+        if proof.len() != expected_proof_len {
+            return Err(IndexedMerkleTreeError::ConcurrentMerkleTree(
+                ConcurrentMerkleTreeError::InvalidProofLength(expected_proof_len, proof.len()),
+            ));
+        }
+        if self.merkle_tree.canopy_depth > 0 {
+            self.merkle_tree
+                .update_proof_from_canopy(low_element.index(), proof)?;
+        }
+        let leaf = low_element.hash::<H>(low_element_next_value)?;
+        self.validate_proof(&leaf, low_element.index(), proof)
+    }
 }
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize> Deref
-    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> Deref
+    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -464,6 +614,7 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     type Target = ConcurrentMerkleTree<H, HEIGHT>;
 
@@ -472,8 +623,8 @@ where
     }
 }
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize> DerefMut
-    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> DerefMut
+    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -486,14 +637,15 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.merkle_tree
     }
 }
 
-impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize> PartialEq
-    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>
+impl<H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> PartialEq
+    for IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -506,6 +658,7 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn eq(&self, other: &Self) -> bool {
         self.merkle_tree.eq(&other.merkle_tree)
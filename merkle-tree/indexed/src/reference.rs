@@ -3,15 +3,14 @@ use std::marker::PhantomData;
 use light_bounded_vec::{BoundedVec, BoundedVecError};
 use light_concurrent_merkle_tree::light_hasher::{errors::HasherError, Hasher};
 use light_merkle_tree_reference::{MerkleTree, ReferenceMerkleTreeError};
-use light_utils::bigint::bigint_to_be_bytes_array;
 use num_bigint::BigUint;
-use num_traits::{CheckedAdd, CheckedSub, Num, ToBytes, Unsigned};
+use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
 use thiserror::Error;
 
 use crate::{
     array::{IndexedArray, IndexedElement},
     errors::IndexedMerkleTreeError,
-    HIGHEST_ADDRESS_PLUS_ONE,
+    value::IndexedValue,
 };
 
 #[derive(Debug, Error)]
@@ -22,24 +21,29 @@ pub enum IndexedReferenceMerkleTreeError {
     Reference(#[from] ReferenceMerkleTreeError),
     #[error(transparent)]
     Hasher(#[from] HasherError),
+    #[error("Invalid Merkle proof")]
+    InvalidProof,
 }
 
 #[derive(Debug, Clone)]
 #[repr(C)]
-pub struct IndexedMerkleTree<H, I>
+pub struct IndexedMerkleTree<H, I, V = BigUint>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
+    V: IndexedValue,
 {
     pub merkle_tree: MerkleTree<H>,
     _index: PhantomData<I>,
+    _value: PhantomData<V>,
 }
 
-impl<H, I> IndexedMerkleTree<H, I>
+impl<H, I, V> IndexedMerkleTree<H, I, V>
 where
     H: Hasher,
     I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     pub fn new(
         height: usize,
@@ -56,6 +60,7 @@ where
         Ok(Self {
             merkle_tree,
             _index: PhantomData,
+            _value: PhantomData,
         })
     }
 
@@ -63,8 +68,8 @@ where
     /// on-chain indexed concurrent merkle tree.
     /// Inserts the ranges 0 - BN254 Field Size - 1 into the tree.
     pub fn init(&mut self) -> Result<(), IndexedReferenceMerkleTreeError> {
-        let mut indexed_array = IndexedArray::<H, I>::default();
-        let init_value = BigUint::from_str_radix(HIGHEST_ADDRESS_PLUS_ONE, 10).unwrap();
+        let mut indexed_array = IndexedArray::<H, I, V>::default();
+        let init_value = V::highest();
         let nullifier_bundle = indexed_array.append(&init_value)?;
         let new_low_leaf = nullifier_bundle
             .new_low_element
@@ -101,9 +106,9 @@ where
     // TODO: rename input values
     pub fn update(
         &mut self,
-        new_low_element: &IndexedElement<I>,
-        new_element: &IndexedElement<I>,
-        new_element_next_value: &BigUint,
+        new_low_element: &IndexedElement<I, V>,
+        new_element: &IndexedElement<I, V>,
+        new_element_next_value: &V,
     ) -> Result<(), IndexedReferenceMerkleTreeError> {
         // Update the low element.
         let new_low_leaf = new_low_element.hash::<H>(&new_element.value)?;
@@ -120,8 +125,8 @@ where
     // TODO: add append with new value, so that we don't need to compute the lowlevel values manually
     pub fn append(
         &mut self,
-        value: &BigUint,
-        indexed_array: &mut IndexedArray<H, I>,
+        value: &V,
+        indexed_array: &mut IndexedArray<H, I, V>,
     ) -> Result<(), IndexedReferenceMerkleTreeError> {
         let nullifier_bundle = indexed_array.append(value).unwrap();
         self.update(
@@ -133,10 +138,37 @@ where
         Ok(())
     }
 
+    /// Deletes the element with the given `value` from the indexed array
+    /// and updates the leaves of its low element and the deleted element.
+    pub fn delete(
+        &mut self,
+        value: &V,
+        indexed_array: &mut IndexedArray<H, I, V>,
+    ) -> Result<(), IndexedReferenceMerkleTreeError> {
+        let deletion_bundle = indexed_array.delete(value)?;
+
+        // Update the low element.
+        let new_low_leaf = deletion_bundle
+            .new_low_element
+            .hash::<H>(&deletion_bundle.new_low_element_next_value)?;
+        self.merkle_tree.update(
+            &new_low_leaf,
+            usize::from(deletion_bundle.new_low_element.index),
+        )?;
+
+        // Zero the deleted element.
+        self.merkle_tree.update(
+            &H::zero_bytes()[0],
+            usize::from(deletion_bundle.deleted_element.index),
+        )?;
+
+        Ok(())
+    }
+
     pub fn get_non_inclusion_proof(
         &self,
-        value: &BigUint,
-        indexed_array: &IndexedArray<H, I>,
+        value: &V,
+        indexed_array: &IndexedArray<H, I, V>,
     ) -> Result<NonInclusionProof, IndexedReferenceMerkleTreeError> {
         let (low_element, _next_value) = indexed_array.find_low_element_for_nonexistent(value)?;
         let merkle_proof = self
@@ -149,9 +181,9 @@ where
             .clone();
         Ok(NonInclusionProof {
             root: self.root(),
-            value: bigint_to_be_bytes_array::<32>(value).unwrap(),
-            leaf_lower_range_value: bigint_to_be_bytes_array::<32>(&low_element.value).unwrap(),
-            leaf_higher_range_value: bigint_to_be_bytes_array::<32>(&higher_range_value).unwrap(),
+            value: value.to_bytes()?,
+            leaf_lower_range_value: low_element.value.to_bytes()?,
+            leaf_higher_range_value: higher_range_value.to_bytes()?,
             leaf_index: low_element.index.into(),
             next_index: low_element.next_index(),
             merkle_proof,
@@ -162,18 +194,68 @@ where
         &self,
         proof: &NonInclusionProof,
     ) -> Result<(), IndexedReferenceMerkleTreeError> {
-        let array_element = IndexedElement::<usize> {
-            value: BigUint::from_bytes_be(&proof.value),
+        let array_element = IndexedElement::<usize, V> {
+            value: V::from_bytes(&proof.value)?,
             index: proof.leaf_index,
             next_index: proof.next_index,
         };
-        let leaf_hash =
-            array_element.hash::<H>(&BigUint::from_bytes_be(&proof.leaf_higher_range_value))?;
+        let leaf_hash = array_element.hash::<H>(&V::from_bytes(&proof.leaf_higher_range_value)?)?;
         self.merkle_tree
             .verify(&leaf_hash, &proof.merkle_proof, proof.leaf_index)
             .unwrap();
         Ok(())
     }
+
+    /// Returns a proof that the range `[start, end)` doesn't contain any
+    /// element.
+    pub fn get_range_non_inclusion_proof(
+        &self,
+        start: &V,
+        end: &V,
+        indexed_array: &IndexedArray<H, I, V>,
+    ) -> Result<RangeNonInclusionProof, IndexedReferenceMerkleTreeError> {
+        let (low_element, low_element_next_value) =
+            indexed_array.find_low_element_for_nonexistent_range(start, end)?;
+        let merkle_proof = self
+            .get_proof_of_leaf(low_element.index(), true)
+            .map_err(IndexedMerkleTreeError::from)?;
+        Ok(RangeNonInclusionProof {
+            root: self.root(),
+            start: start.to_bytes()?,
+            end: end.to_bytes()?,
+            leaf_lower_range_value: low_element.value.to_bytes()?,
+            leaf_higher_range_value: low_element_next_value.to_bytes()?,
+            leaf_index: low_element.index(),
+            next_index: low_element.next_index(),
+            merkle_proof,
+        })
+    }
+
+    pub fn verify_range_non_inclusion_proof(
+        &self,
+        proof: &RangeNonInclusionProof,
+    ) -> Result<(), IndexedReferenceMerkleTreeError> {
+        let low_element = IndexedElement::<usize, V> {
+            index: proof.leaf_index,
+            value: V::from_bytes(&proof.leaf_lower_range_value)?,
+            next_index: proof.next_index,
+        };
+        let low_element_next_value = V::from_bytes(&proof.leaf_higher_range_value)?;
+        low_element.check_range_non_inclusion(
+            &low_element_next_value,
+            &V::from_bytes(&proof.start)?,
+            &V::from_bytes(&proof.end)?,
+        )?;
+        let leaf_hash = low_element.hash::<H>(&low_element_next_value)?;
+        if proof.root != self.root()
+            || !self
+                .merkle_tree
+                .verify(&leaf_hash, &proof.merkle_proof, proof.leaf_index)?
+        {
+            return Err(IndexedReferenceMerkleTreeError::InvalidProof);
+        }
+        Ok(())
+    }
 }
 
 // TODO: check why next_index is usize while index is I
@@ -190,3 +272,19 @@ pub struct NonInclusionProof {
     pub next_index: usize,
     pub merkle_proof: BoundedVec<[u8; 32]>,
 }
+
+/// We prove that the range `[start, end)` doesn't contain any element by:
+/// 1. Showing that leaf_lower_range_value is lower than start and leaf_higher_range_value is greater or equal to end
+///    (or the low element is the highest one, with next_index 0)
+/// 2. Showing that the leaf_hash H(leaf_lower_range_value, leaf_next_index, leaf_higher_value) is included in the root (Merkle tree)
+#[derive(Debug)]
+pub struct RangeNonInclusionProof {
+    pub root: [u8; 32],
+    pub start: [u8; 32],
+    pub end: [u8; 32],
+    pub leaf_lower_range_value: [u8; 32],
+    pub leaf_higher_range_value: [u8; 32],
+    pub leaf_index: usize,
+    pub next_index: usize,
+    pub merkle_proof: BoundedVec<[u8; 32]>,
+}
@@ -0,0 +1,77 @@
+use std::fmt::Debug;
+
+use light_utils::bigint::bigint_to_be_bytes_array;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
+
+use crate::{errors::IndexedMerkleTreeError, HIGHEST_ADDRESS_PLUS_ONE};
+
+/// Value stored in an indexed array and an indexed Merkle tree.
+///
+/// Elements are ordered by the [`Ord`] implementation of the value, which
+/// makes it possible to build sorted sets and key-value maps (e.g. with
+/// `(namespace, key)` pairs or timestamped entries as values) with
+/// non-membership proofs.
+///
+/// Values are stored in leaf hashes and in the indexed changelog in their
+/// 32-byte encoding. The encoding has to be:
+///
+/// * Canonical - `from_bytes(to_bytes(value)) == value`.
+/// * Accepted by the hasher - e.g. lower than the BN254 field size for
+///   Poseidon.
+/// * All zeros for the [`lowest`](IndexedValue::lowest) value, since the
+///   first leaf of every indexed Merkle tree is
+///   [`zero_indexed_leaf`](light_hasher::Hasher::zero_indexed_leaf).
+///
+/// Non-inclusion circuits compare encoded values as field elements, proofs
+/// for values whose encoding doesn't preserve the order can be verified only
+/// off-chain or by a program.
+pub trait IndexedValue: Clone + Debug + Ord {
+    /// The lowest value, stored in the first element of an indexed array.
+    fn lowest() -> Self;
+
+    /// The highest value, stored in the element which initializes an
+    /// indexed Merkle tree.
+    fn highest() -> Self;
+
+    /// Encodes the value into 32 bytes.
+    fn to_bytes(&self) -> Result<[u8; 32], IndexedMerkleTreeError>;
+
+    /// Decodes the value from 32 bytes.
+    fn from_bytes(bytes: &[u8; 32]) -> Result<Self, IndexedMerkleTreeError>;
+}
+
+/// Field elements, used as addresses.
+impl IndexedValue for BigUint {
+    fn lowest() -> Self {
+        BigUint::zero()
+    }
+
+    fn highest() -> Self {
+        // PANICS: The constant is a valid decimal number.
+        BigUint::from_str_radix(HIGHEST_ADDRESS_PLUS_ONE, 10).unwrap()
+    }
+
+    fn to_bytes(&self) -> Result<[u8; 32], IndexedMerkleTreeError> {
+        Ok(bigint_to_be_bytes_array::<32>(self)?)
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> Result<Self, IndexedMerkleTreeError> {
+        Ok(BigUint::from_bytes_be(bytes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_biguint_encoding() {
+        assert_eq!(BigUint::lowest().to_bytes().unwrap(), [0u8; 32]);
+        for value in [BigUint::lowest(), BigUint::from(30_u32), BigUint::highest()] {
+            let bytes = value.to_bytes().unwrap();
+            assert_eq!(BigUint::from_bytes(&bytes).unwrap(), value);
+        }
+        assert!(BigUint::lowest() < BigUint::highest());
+    }
+}
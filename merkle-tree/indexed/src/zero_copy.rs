@@ -5,21 +5,31 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use light_bounded_vec::{CyclicBoundedVec, CyclicBoundedVecMetadata};
+use bytemuck::Pod;
+use light_bounded_vec::zero_copy::{CyclicBoundedVecZeroCopy, CyclicBoundedVecZeroCopyMut};
 use light_concurrent_merkle_tree::{
     errors::ConcurrentMerkleTreeError,
     zero_copy::{ConcurrentMerkleTreeZeroCopy, ConcurrentMerkleTreeZeroCopyMut},
     ConcurrentMerkleTree,
 };
 use light_hasher::Hasher;
-use light_utils::offset::zero_copy::{read_array_like_ptr_at, read_ptr_at, write_at};
+use num_bigint::BigUint;
 use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
 
-use crate::{errors::IndexedMerkleTreeError, IndexedMerkleTree};
+use crate::{
+    changelog::IndexedChangelogEntry, errors::IndexedMerkleTreeError, value::IndexedValue,
+    IndexedMerkleTree,
+};
 
 #[derive(Debug)]
-pub struct IndexedMerkleTreeZeroCopy<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize>
-where
+pub struct IndexedMerkleTreeZeroCopy<
+    'a,
+    H,
+    I,
+    const HEIGHT: usize,
+    const NET_HEIGHT: usize,
+    V = BigUint,
+> where
     H: Hasher,
     I: CheckedAdd
         + CheckedSub
@@ -31,15 +41,16 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    pub merkle_tree: mem::ManuallyDrop<IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>>,
+    pub merkle_tree: mem::ManuallyDrop<IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>>,
     // The purpose of this field is ensuring that the wrapper does not outlive
     // the buffer.
     _bytes: &'a [u8],
 }
 
-impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize>
-    IndexedMerkleTreeZeroCopy<'a, H, I, HEIGHT, NET_HEIGHT>
+impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V>
+    IndexedMerkleTreeZeroCopy<'a, H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -52,53 +63,35 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
+    IndexedChangelogEntry<I, NET_HEIGHT>: Pod,
 {
     /// Returns a zero-copy wrapper of `IndexedMerkleTree` created from the
     /// data in the provided `bytes` buffer.
     pub fn from_bytes_zero_copy(bytes: &'a [u8]) -> Result<Self, IndexedMerkleTreeError> {
-        let (merkle_tree, mut offset) =
+        let (merkle_tree, indexed_changelog_bytes) =
             ConcurrentMerkleTreeZeroCopy::struct_from_bytes_zero_copy(bytes)?;
+        // Don't drop the tree when creating the indexed changelog fails.
+        let merkle_tree = mem::ManuallyDrop::new(merkle_tree);
 
-        let indexed_changelog_metadata: *mut CyclicBoundedVecMetadata =
-            unsafe { read_ptr_at(bytes, &mut offset) };
-
-        let expected_size = IndexedMerkleTree::<H, I, HEIGHT, NET_HEIGHT>::size_in_account(
-            merkle_tree.height,
-            merkle_tree.changelog.capacity(),
-            merkle_tree.roots.capacity(),
-            merkle_tree.canopy_depth,
-            unsafe { (*indexed_changelog_metadata).capacity() },
-        );
-        if bytes.len() < expected_size {
-            return Err(IndexedMerkleTreeError::ConcurrentMerkleTree(
-                ConcurrentMerkleTreeError::BufferSize(expected_size, bytes.len()),
-            ));
-        }
-
-        let indexed_changelog = unsafe {
-            CyclicBoundedVec::from_raw_parts(
-                indexed_changelog_metadata,
-                read_array_like_ptr_at(
-                    bytes,
-                    &mut offset,
-                    (*indexed_changelog_metadata).capacity(),
-                ),
-            )
-        };
+        let (indexed_changelog, _) = CyclicBoundedVecZeroCopy::from_bytes(indexed_changelog_bytes)?;
 
+        // SAFETY: The vectors are backed by `bytes` and the wrapper doesn't
+        // outlive it, nor drops them.
         Ok(Self {
             merkle_tree: mem::ManuallyDrop::new(IndexedMerkleTree {
-                merkle_tree,
-                indexed_changelog,
+                merkle_tree: mem::ManuallyDrop::into_inner(merkle_tree),
+                indexed_changelog: unsafe { indexed_changelog.into_inner() },
                 _index: PhantomData,
+                _value: PhantomData,
             }),
             _bytes: bytes,
         })
     }
 }
 
-impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize> Deref
-    for IndexedMerkleTreeZeroCopy<'a, H, I, HEIGHT, NET_HEIGHT>
+impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> Deref
+    for IndexedMerkleTreeZeroCopy<'a, H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -111,8 +104,9 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>;
+    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>;
 
     fn deref(&self) -> &Self::Target {
         &self.merkle_tree
@@ -120,10 +114,14 @@ where
 }
 
 #[derive(Debug)]
-pub struct IndexedMerkleTreeZeroCopyMut<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize>(
-    IndexedMerkleTreeZeroCopy<'a, H, I, HEIGHT, NET_HEIGHT>,
-)
-where
+pub struct IndexedMerkleTreeZeroCopyMut<
+    'a,
+    H,
+    I,
+    const HEIGHT: usize,
+    const NET_HEIGHT: usize,
+    V = BigUint,
+> where
     H: Hasher,
     I: CheckedAdd
         + CheckedSub
@@ -134,10 +132,17 @@ where
         + ToBytes
         + TryFrom<usize>
         + Unsigned,
-    usize: From<I>;
+    usize: From<I>,
+    V: IndexedValue,
+{
+    merkle_tree: mem::ManuallyDrop<IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>>,
+    // The purpose of this field is ensuring that the wrapper does not outlive
+    // the buffer.
+    _bytes: PhantomData<&'a mut [u8]>,
+}
 
-impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize>
-    IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT>
+impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V>
+    IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -150,11 +155,29 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
+    IndexedChangelogEntry<I, NET_HEIGHT>: Pod,
 {
     pub fn from_bytes_zero_copy_mut(bytes: &'a mut [u8]) -> Result<Self, IndexedMerkleTreeError> {
-        Ok(Self(IndexedMerkleTreeZeroCopy::from_bytes_zero_copy(
-            bytes,
-        )?))
+        let (merkle_tree, indexed_changelog_bytes) =
+            ConcurrentMerkleTreeZeroCopyMut::struct_from_bytes_zero_copy_mut(bytes)?;
+        // Don't drop the tree when creating the indexed changelog fails.
+        let merkle_tree = mem::ManuallyDrop::new(merkle_tree);
+
+        let (indexed_changelog, _) =
+            CyclicBoundedVecZeroCopyMut::from_bytes(indexed_changelog_bytes)?;
+
+        // SAFETY: The vectors are backed by `bytes` and the wrapper doesn't
+        // outlive it, nor drops them.
+        Ok(Self {
+            merkle_tree: mem::ManuallyDrop::new(IndexedMerkleTree {
+                merkle_tree: mem::ManuallyDrop::into_inner(merkle_tree),
+                indexed_changelog: unsafe { indexed_changelog.into_inner() },
+                _index: PhantomData,
+                _value: PhantomData,
+            }),
+            _bytes: PhantomData,
+        })
     }
 
     pub fn from_bytes_zero_copy_init(
@@ -173,7 +196,7 @@ where
             roots_capacity,
         )?;
 
-        let expected_size = IndexedMerkleTree::<H, I, HEIGHT, NET_HEIGHT>::size_in_account(
+        let expected_size = IndexedMerkleTree::<H, I, HEIGHT, NET_HEIGHT, V>::size_in_account(
             height,
             changelog_capacity,
             roots_capacity,
@@ -186,26 +209,23 @@ where
             ));
         }
 
-        let mut offset = ConcurrentMerkleTree::<H, HEIGHT>::size_in_account(
+        let offset = ConcurrentMerkleTree::<H, HEIGHT>::size_in_account(
             height,
             changelog_capacity,
             roots_capacity,
             canopy_depth,
         );
-
-        let indexed_changelog_metadata = CyclicBoundedVecMetadata::new(indexed_changelog_capacity);
-        write_at::<CyclicBoundedVecMetadata>(
-            bytes,
-            &indexed_changelog_metadata.to_ne_bytes(),
-            &mut offset,
-        );
+        CyclicBoundedVecZeroCopyMut::<IndexedChangelogEntry<I, NET_HEIGHT>>::init(
+            &mut bytes[offset..],
+            indexed_changelog_capacity,
+        )?;
 
         Self::from_bytes_zero_copy_mut(bytes)
     }
 }
 
-impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize> Deref
-    for IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT>
+impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> Deref
+    for IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -218,16 +238,17 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
-    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>;
+    type Target = IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT, V>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0.merkle_tree
+        &self.merkle_tree
     }
 }
 
-impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize> DerefMut
-    for IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT>
+impl<'a, H, I, const HEIGHT: usize, const NET_HEIGHT: usize, V> DerefMut
+    for IndexedMerkleTreeZeroCopyMut<'a, H, I, HEIGHT, NET_HEIGHT, V>
 where
     H: Hasher,
     I: CheckedAdd
@@ -240,14 +261,16 @@ where
         + TryFrom<usize>
         + Unsigned,
     usize: From<I>,
+    V: IndexedValue,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0.merkle_tree
+        &mut self.merkle_tree
     }
 }
 
 #[cfg(test)]
 mod test {
+    use light_bounded_vec::{BoundedVecError, CyclicBoundedVecMetadata};
     use light_hasher::Poseidon;
     use light_utils::bigint::bigint_to_be_bytes_array;
     use num_bigint::RandBigInt;
@@ -340,4 +363,73 @@ mod test {
             OPERATIONS,
         >()
     }
+
+    /// Tests:
+    /// 1. buffer without space for the indexed changelog fails
+    /// 2. buffer with invalid indexed changelog metadata fails
+    #[test]
+    fn test_from_invalid_bytes() {
+        const HEIGHT: usize = 26;
+        const NET_HEIGHT: usize = 16;
+        const CHANGELOG_SIZE: usize = 16;
+        const ROOTS: usize = 16;
+        const CANOPY_DEPTH: usize = 4;
+        const INDEXED_CHANGELOG_SIZE: usize = 16;
+
+        let size = IndexedMerkleTree::<Poseidon, usize, HEIGHT, NET_HEIGHT>::size_in_account(
+            HEIGHT,
+            CHANGELOG_SIZE,
+            ROOTS,
+            CANOPY_DEPTH,
+            INDEXED_CHANGELOG_SIZE,
+        );
+        let mut bytes = vec![0u8; size];
+        IndexedMerkleTreeZeroCopyMut::<Poseidon, usize, HEIGHT, NET_HEIGHT>::from_bytes_zero_copy_init(
+            &mut bytes,
+            HEIGHT,
+            CANOPY_DEPTH,
+            CHANGELOG_SIZE,
+            ROOTS,
+            INDEXED_CHANGELOG_SIZE,
+        )
+        .unwrap()
+        .init()
+        .unwrap();
+
+        // 1. missing indexed changelog
+        assert!(matches!(
+            IndexedMerkleTreeZeroCopy::<Poseidon, usize, HEIGHT, NET_HEIGHT>::from_bytes_zero_copy(
+                &bytes[..size - 1]
+            ),
+            Err(IndexedMerkleTreeError::BoundedVec(
+                BoundedVecError::BufferSize(_, _)
+            ))
+        ));
+
+        // 2. invalid indexed changelog metadata
+        let indexed_changelog_metadata_offset =
+            ConcurrentMerkleTree::<Poseidon, HEIGHT>::size_in_account(
+                HEIGHT,
+                CHANGELOG_SIZE,
+                ROOTS,
+                CANOPY_DEPTH,
+            );
+        let invalid_metadata = CyclicBoundedVecMetadata::new_with_indices(
+            INDEXED_CHANGELOG_SIZE,
+            INDEXED_CHANGELOG_SIZE,
+            INDEXED_CHANGELOG_SIZE,
+            0,
+        );
+        bytes[indexed_changelog_metadata_offset
+            ..indexed_changelog_metadata_offset + mem::size_of::<CyclicBoundedVecMetadata>()]
+            .copy_from_slice(&invalid_metadata.to_ne_bytes());
+        assert!(matches!(
+            IndexedMerkleTreeZeroCopyMut::<Poseidon, usize, HEIGHT, NET_HEIGHT>::from_bytes_zero_copy_mut(
+                &mut bytes
+            ),
+            Err(IndexedMerkleTreeError::BoundedVec(
+                BoundedVecError::InvalidMetadata
+            ))
+        ));
+    }
 }
@@ -10,11 +10,13 @@ use light_hash_set::{HashSet, HashSetError};
 use light_indexed_merkle_tree::{
     array::{IndexedArray, IndexedElement},
     errors::IndexedMerkleTreeError,
-    reference, IndexedMerkleTree, HIGHEST_ADDRESS_PLUS_ONE,
+    reference,
+    value::IndexedValue,
+    IndexedMerkleTree, HIGHEST_ADDRESS_PLUS_ONE,
 };
 use light_utils::bigint::bigint_to_be_bytes_array;
 use num_bigint::{BigUint, RandBigInt, ToBigUint};
-use num_traits::{FromBytes, Num};
+use num_traits::Num;
 use rand::thread_rng;
 use thiserror::Error;
 
@@ -43,8 +45,7 @@ where
     H: Hasher,
 {
     for i in 0..NR_NULLIFIERS {
-        let nullifier = BigUint::from_be_bytes(nullifiers[i].as_slice());
-        queue.insert(&nullifier, merkle_tree.sequence_number())?;
+        queue.insert(&nullifiers[i], merkle_tree.sequence_number())?;
     }
     Ok(())
 }
@@ -440,11 +441,17 @@ where
     let nullifier2 = 10_u32.to_biguint().unwrap();
     onchain_queue
         .borrow_mut()
-        .insert(&nullifier1, onchain_tree.borrow().sequence_number())
+        .insert(
+            &bigint_to_be_bytes_array(&nullifier1).unwrap(),
+            onchain_tree.borrow().sequence_number(),
+        )
         .unwrap();
     onchain_queue
         .borrow_mut()
-        .insert(&nullifier2, onchain_tree.borrow().sequence_number())
+        .insert(
+            &bigint_to_be_bytes_array(&nullifier2).unwrap(),
+            onchain_tree.borrow().sequence_number(),
+        )
         .unwrap();
     let nullifier_bundle = local_indexed_array.append(&nullifier1).unwrap();
     local_merkle_tree
@@ -474,14 +481,17 @@ where
     let nullifier3 = 20_u32.to_biguint().unwrap();
     onchain_queue
         .borrow_mut()
-        .insert(&nullifier3, onchain_tree.borrow().sequence_number())
+        .insert(
+            &bigint_to_be_bytes_array(&nullifier3).unwrap(),
+            onchain_tree.borrow().sequence_number(),
+        )
         .unwrap();
     let changelog_index = onchain_tree.borrow().changelog_index();
     let indexed_changelog_index = onchain_tree.borrow().indexed_changelog_index();
     // Index of our new nullifier in the queue.
     let queue_index = onchain_queue
         .borrow()
-        .find_element_index(&nullifier3, None)
+        .find_element_index(&bigint_to_be_bytes_array(&nullifier3).unwrap(), None)
         .unwrap()
         .unwrap();
     // (Invalid) low nullifier.
@@ -511,14 +521,17 @@ where
     let nullifier3 = 50_u32.to_biguint().unwrap();
     onchain_queue
         .borrow_mut()
-        .insert(&nullifier3, onchain_tree.borrow().sequence_number())
+        .insert(
+            &bigint_to_be_bytes_array(&nullifier3).unwrap(),
+            onchain_tree.borrow().sequence_number(),
+        )
         .unwrap();
     let changelog_index = onchain_tree.borrow().changelog_index();
     let indexed_changelog_index = onchain_tree.borrow().indexed_changelog_index();
     // Index of our new nullifier in the queue.
     let queue_index = onchain_queue
         .borrow()
-        .find_element_index(&nullifier3, None)
+        .find_element_index(&bigint_to_be_bytes_array(&nullifier3).unwrap(), None)
         .unwrap()
         .unwrap();
     // (Invalid) low nullifier.
@@ -545,7 +558,10 @@ where
     let nullifier4 = 45_u32.to_biguint().unwrap();
     onchain_queue
         .borrow_mut()
-        .insert(&nullifier4, onchain_tree.borrow().sequence_number())
+        .insert(
+            &bigint_to_be_bytes_array(&nullifier4).unwrap(),
+            onchain_tree.borrow().sequence_number(),
+        )
         .unwrap();
     let changelog_index = onchain_tree.borrow().changelog_index();
     let indexed_changelog_index = onchain_tree.borrow().indexed_changelog_index();
@@ -1152,3 +1168,569 @@ fn perform_change_log_test<
         }
     }
 }
+
+/// Key of a sorted key-value map, used to test indexed Merkle trees with
+/// custom value types. Keys are ordered by namespace first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct NamespacedKey {
+    namespace: u32,
+    key: u64,
+}
+
+/// Encodes the key as `[0, namespace (4 bytes BE), 0..., key (8 bytes BE)]`.
+/// The first byte is zero, so the encoding is always lower than the field
+/// size, and the big-endian encoding preserves the order.
+impl IndexedValue for NamespacedKey {
+    fn lowest() -> Self {
+        Self {
+            namespace: 0,
+            key: 0,
+        }
+    }
+
+    fn highest() -> Self {
+        Self {
+            namespace: u32::MAX,
+            key: u64::MAX,
+        }
+    }
+
+    fn to_bytes(&self) -> Result<[u8; 32], IndexedMerkleTreeError> {
+        let mut bytes = [0u8; 32];
+        bytes[1..5].copy_from_slice(&self.namespace.to_be_bytes());
+        bytes[24..].copy_from_slice(&self.key.to_be_bytes());
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> Result<Self, IndexedMerkleTreeError> {
+        if bytes[0] != 0 || bytes[5..24].iter().any(|byte| *byte != 0) {
+            return Err(IndexedMerkleTreeError::InvalidValueEncoding);
+        }
+        Ok(Self {
+            namespace: u32::from_be_bytes(bytes[1..5].try_into().unwrap()),
+            key: u64::from_be_bytes(bytes[24..].try_into().unwrap()),
+        })
+    }
+}
+
+/// Tests an indexed Merkle tree with a custom value type:
+///
+/// 1. Inserts keys in random order into the indexed array, the reference
+///    tree and the concurrent tree (with outdated changelog indices, which
+///    requires patching from the indexed changelog).
+/// 2. Checks that roots match and that elements are sorted by namespace
+///    and key.
+/// 3. Checks non-inclusion proofs of absent keys.
+/// 4. Checks that inserting an existing key fails.
+/// 5. Checks that decoding invalid bytes fails.
+#[test]
+fn custom_value_type() {
+    const HEIGHT: usize = 8;
+    const CANOPY: usize = 0;
+    const NET_HEIGHT: usize = HEIGHT - CANOPY;
+
+    let mut indexed_array = IndexedArray::<Poseidon, usize, NamespacedKey>::default();
+    indexed_array.init().unwrap();
+    let mut reference_merkle_tree =
+        reference::IndexedMerkleTree::<Poseidon, usize, NamespacedKey>::new(HEIGHT, CANOPY)
+            .unwrap();
+    reference_merkle_tree.init().unwrap();
+    let mut merkle_tree =
+        IndexedMerkleTree::<Poseidon, usize, HEIGHT, NET_HEIGHT, NamespacedKey>::new(
+            HEIGHT,
+            MERKLE_TREE_CHANGELOG,
+            MERKLE_TREE_ROOTS,
+            CANOPY,
+            MERKLE_TREE_INDEXED_CHANGELOG,
+        )
+        .unwrap();
+    merkle_tree.init().unwrap();
+    merkle_tree.add_highest_element().unwrap();
+    assert_eq!(reference_merkle_tree.root(), merkle_tree.root());
+
+    // 1. Insert keys. The changelog indices are taken only once, every
+    //    subsequent update has to be patched.
+    let keys = [
+        NamespacedKey {
+            namespace: 2,
+            key: 1,
+        },
+        NamespacedKey {
+            namespace: 1,
+            key: u64::MAX,
+        },
+        NamespacedKey {
+            namespace: 2,
+            key: 0,
+        },
+        NamespacedKey {
+            namespace: 1,
+            key: 5,
+        },
+        NamespacedKey {
+            namespace: 3,
+            key: 7,
+        },
+    ];
+    let changelog_index = merkle_tree.changelog_index();
+    let indexed_changelog_index = merkle_tree.indexed_changelog_index();
+    let initial_array = indexed_array.clone();
+    let initial_reference_merkle_tree = reference_merkle_tree.clone();
+    for key in keys.iter() {
+        let (low_element, low_element_next_value) =
+            initial_array.find_low_element_for_nonexistent(key).unwrap();
+        let mut low_element_proof = initial_reference_merkle_tree
+            .get_proof_of_leaf(low_element.index, false)
+            .unwrap();
+        merkle_tree
+            .update(
+                changelog_index,
+                indexed_changelog_index,
+                *key,
+                low_element,
+                low_element_next_value,
+                &mut low_element_proof,
+            )
+            .unwrap();
+        reference_merkle_tree
+            .append(key, &mut indexed_array)
+            .unwrap();
+        assert_eq!(reference_merkle_tree.root(), merkle_tree.root());
+    }
+
+    // 2. Elements are linked in the order of the keys.
+    let mut sorted_keys = keys.to_vec();
+    sorted_keys.sort();
+    let mut linked_keys = Vec::new();
+    let mut element = indexed_array.get(0).unwrap();
+    while element.next_index() != 0 {
+        element = indexed_array.get(element.next_index()).unwrap();
+        linked_keys.push(element.value);
+    }
+    assert_eq!(linked_keys.pop(), Some(NamespacedKey::highest()));
+    assert_eq!(linked_keys, sorted_keys);
+
+    // 3. Non-inclusion proofs.
+    let absent_key = NamespacedKey {
+        namespace: 1,
+        key: 6,
+    };
+    let proof = reference_merkle_tree
+        .get_non_inclusion_proof(&absent_key, &indexed_array)
+        .unwrap();
+    assert_eq!(proof.value, absent_key.to_bytes().unwrap());
+    assert_eq!(
+        NamespacedKey::from_bytes(&proof.leaf_lower_range_value).unwrap(),
+        NamespacedKey {
+            namespace: 1,
+            key: 5,
+        }
+    );
+    assert_eq!(
+        NamespacedKey::from_bytes(&proof.leaf_higher_range_value).unwrap(),
+        NamespacedKey {
+            namespace: 1,
+            key: u64::MAX,
+        }
+    );
+    reference_merkle_tree
+        .verify_non_inclusion_proof(&proof)
+        .unwrap();
+
+    // 4. Inserting an existing key fails.
+    assert!(matches!(
+        indexed_array.find_low_element_for_nonexistent(&keys[0]),
+        Err(IndexedMerkleTreeError::ElementAlreadyExists)
+    ));
+
+    // 5. Invalid encodings.
+    let mut bytes = keys[0].to_bytes().unwrap();
+    bytes[10] = 1;
+    assert!(matches!(
+        NamespacedKey::from_bytes(&bytes),
+        Err(IndexedMerkleTreeError::InvalidValueEncoding)
+    ));
+}
+
+/// Tests deletions and range non-inclusion proofs:
+///
+/// 1. Inserts elements into the indexed array, the reference tree and the
+///    concurrent tree.
+/// 2. Checks range non-inclusion proofs of empty and non-empty ranges.
+/// 3. Deletes an element and checks that its leaf is zeroed, that both
+///    changes are saved in the indexed changelog and that the range it
+///    belonged to becomes empty.
+/// 4. Inserts an element with changelog indices from before the deletion,
+///    which requires patching the low element from the indexed changelog.
+/// 5. Inserts an element with changelog indices from before the deletion,
+///    with the deleted element as the low element, which gets patched to
+///    its low element.
+/// 6. Deletes the highest inserted element.
+/// 7. Checks that invalid deletions fail.
+fn deletion_and_range_non_inclusion<const CANOPY: usize, const NET_HEIGHT: usize>() {
+    let mut indexed_array = IndexedArray::<Poseidon, usize>::default();
+    indexed_array.init().unwrap();
+    let mut reference_merkle_tree =
+        reference::IndexedMerkleTree::<Poseidon, usize>::new(MERKLE_TREE_HEIGHT, CANOPY).unwrap();
+    reference_merkle_tree.init().unwrap();
+    let mut merkle_tree =
+        IndexedMerkleTree::<Poseidon, usize, MERKLE_TREE_HEIGHT, NET_HEIGHT>::new(
+            MERKLE_TREE_HEIGHT,
+            MERKLE_TREE_CHANGELOG,
+            MERKLE_TREE_ROOTS,
+            CANOPY,
+            MERKLE_TREE_INDEXED_CHANGELOG,
+        )
+        .unwrap();
+    merkle_tree.init().unwrap();
+    merkle_tree.add_highest_element().unwrap();
+
+    // 1. Insert elements.
+    for value in [30_u32, 10, 50, 20, 40] {
+        let value = BigUint::from(value);
+        let (low_element, low_element_next_value) = indexed_array
+            .find_low_element_for_nonexistent(&value)
+            .unwrap();
+        let mut low_element_proof = reference_merkle_tree
+            .get_proof_of_leaf(low_element.index, false)
+            .unwrap();
+        merkle_tree
+            .update(
+                merkle_tree.changelog_index(),
+                merkle_tree.indexed_changelog_index(),
+                value.clone(),
+                low_element,
+                low_element_next_value,
+                &mut low_element_proof,
+            )
+            .unwrap();
+        reference_merkle_tree
+            .append(&value, &mut indexed_array)
+            .unwrap();
+    }
+    assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
+
+    // 2. Range non-inclusion proofs.
+    let range_non_inclusion =
+        |merkle_tree: &IndexedMerkleTree<Poseidon, usize, MERKLE_TREE_HEIGHT, NET_HEIGHT>,
+         reference_merkle_tree: &reference::IndexedMerkleTree<Poseidon, usize>,
+         indexed_array: &IndexedArray<Poseidon, usize>,
+         start: u32,
+         end: u32|
+         -> Result<(), IndexedMerkleTreeError> {
+            let (start, end) = (BigUint::from(start), BigUint::from(end));
+            let (low_element, low_element_next_value) =
+                indexed_array.find_low_element_for_nonexistent_range(&start, &end)?;
+            let proof = reference_merkle_tree
+                .get_range_non_inclusion_proof(&start, &end, indexed_array)
+                .unwrap();
+            reference_merkle_tree
+                .verify_range_non_inclusion_proof(&proof)
+                .unwrap();
+            let mut low_element_proof = reference_merkle_tree
+                .get_proof_of_leaf(low_element.index, false)
+                .unwrap();
+            merkle_tree.validate_range_non_inclusion(
+                &start,
+                &end,
+                &low_element,
+                &low_element_next_value,
+                &mut low_element_proof,
+            )
+        };
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 21, 30).unwrap();
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 0, 10).unwrap_err();
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 1, 10).unwrap();
+    range_non_inclusion(
+        &merkle_tree,
+        &reference_merkle_tree,
+        &indexed_array,
+        51,
+        1000,
+    )
+    .unwrap();
+    assert!(matches!(
+        range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 21, 31),
+        Err(IndexedMerkleTreeError::RangeNotEmpty)
+    ));
+    assert!(matches!(
+        range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 20, 25),
+        Err(IndexedMerkleTreeError::RangeNotEmpty)
+    ));
+    assert!(matches!(
+        range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 25, 25),
+        Err(IndexedMerkleTreeError::InvalidRange)
+    ));
+    // A low element which doesn't prove the range fails the validation.
+    let (low_element, low_element_next_value) = indexed_array
+        .find_low_element_for_nonexistent(&BigUint::from(25_u32))
+        .unwrap();
+    let mut low_element_proof = reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    assert!(matches!(
+        merkle_tree.validate_range_non_inclusion(
+            &BigUint::from(25_u32),
+            &BigUint::from(35_u32),
+            &low_element,
+            &low_element_next_value,
+            &mut low_element_proof,
+        ),
+        Err(IndexedMerkleTreeError::RangeNotEmpty)
+    ));
+
+    // Changelog indices and the indexed array from before the deletion.
+    let changelog_index = merkle_tree.changelog_index();
+    let indexed_changelog_index = merkle_tree.indexed_changelog_index();
+    let stale_indexed_array = indexed_array.clone();
+    let stale_reference_merkle_tree = reference_merkle_tree.clone();
+
+    // 3. Delete 30.
+    let value = BigUint::from(30_u32);
+    let low_element = indexed_array.find_low_element_for_existent(&value).unwrap();
+    let element = indexed_array.find_element(&value).unwrap().clone();
+    let element_next_value = indexed_array
+        .get(element.next_index())
+        .unwrap()
+        .value
+        .clone();
+    let mut low_element_proof = reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    let mut element_proof = reference_merkle_tree
+        .get_proof_of_leaf(element.index, false)
+        .unwrap();
+    merkle_tree
+        .delete(
+            changelog_index,
+            low_element.clone(),
+            element.clone(),
+            element_next_value,
+            &mut low_element_proof,
+            &mut element_proof,
+        )
+        .unwrap();
+    reference_merkle_tree
+        .delete(&value, &mut indexed_array)
+        .unwrap();
+    assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
+    let deleted_element_entry =
+        &merkle_tree.indexed_changelog[merkle_tree.indexed_changelog_index()];
+    assert_eq!(deleted_element_entry.element.index, element.index);
+    assert_eq!(deleted_element_entry.element.value, [0u8; 32]);
+    assert_eq!(deleted_element_entry.element.next_index, 0);
+    assert_eq!(
+        deleted_element_entry.changelog_index,
+        merkle_tree.changelog_index()
+    );
+    let low_element_entry = &merkle_tree.indexed_changelog[(merkle_tree.indexed_changelog_index()
+        + MERKLE_TREE_INDEXED_CHANGELOG
+        - 1)
+        % MERKLE_TREE_INDEXED_CHANGELOG];
+    assert_eq!(low_element_entry.element.index, low_element.index);
+    assert_eq!(low_element_entry.element.next_index, element.next_index);
+    assert_eq!(
+        reference_merkle_tree.merkle_tree.leaf(element.index),
+        [0u8; 32]
+    );
+    assert!(indexed_array.get(element.index).unwrap().is_deleted());
+    assert!(indexed_array.find_element(&value).is_none());
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 21, 40).unwrap();
+
+    // 4. Insert 25 with changelog indices from before the deletion. The low
+    //    element (20) used to point to 30, it gets patched to point to 40.
+    let value = BigUint::from(25_u32);
+    let (low_element, low_element_next_value) = stale_indexed_array
+        .find_low_element_for_nonexistent(&value)
+        .unwrap();
+    assert_eq!(low_element_next_value, BigUint::from(30_u32));
+    let mut low_element_proof = stale_reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    merkle_tree
+        .update(
+            changelog_index,
+            indexed_changelog_index,
+            value.clone(),
+            low_element,
+            low_element_next_value,
+            &mut low_element_proof,
+        )
+        .unwrap();
+    reference_merkle_tree
+        .append(&value, &mut indexed_array)
+        .unwrap();
+    assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 26, 40).unwrap();
+
+    // 5. Insert 35 with changelog indices from before the deletion. The low
+    //    element (30) was deleted, it gets patched to its low element (20)
+    //    and then to 25, inserted in the previous step.
+    let value = BigUint::from(35_u32);
+    let (low_element, low_element_next_value) = stale_indexed_array
+        .find_low_element_for_nonexistent(&value)
+        .unwrap();
+    assert_eq!(low_element.value, BigUint::from(30_u32));
+    let mut low_element_proof = stale_reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    merkle_tree
+        .update(
+            changelog_index,
+            indexed_changelog_index,
+            value.clone(),
+            low_element,
+            low_element_next_value,
+            &mut low_element_proof,
+        )
+        .unwrap();
+    reference_merkle_tree
+        .append(&value, &mut indexed_array)
+        .unwrap();
+    assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 26, 35).unwrap();
+    range_non_inclusion(&merkle_tree, &reference_merkle_tree, &indexed_array, 36, 40).unwrap();
+
+    // 6. Delete the highest inserted element.
+    let value = BigUint::from(50_u32);
+    let low_element = indexed_array.find_low_element_for_existent(&value).unwrap();
+    let element = indexed_array.find_element(&value).unwrap().clone();
+    let element_next_value = indexed_array
+        .get(element.next_index())
+        .unwrap()
+        .value
+        .clone();
+    let mut low_element_proof = reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    let mut element_proof = reference_merkle_tree
+        .get_proof_of_leaf(element.index, false)
+        .unwrap();
+    merkle_tree
+        .delete(
+            merkle_tree.changelog_index(),
+            low_element,
+            element,
+            element_next_value,
+            &mut low_element_proof,
+            &mut element_proof,
+        )
+        .unwrap();
+    reference_merkle_tree
+        .delete(&value, &mut indexed_array)
+        .unwrap();
+    assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
+    range_non_inclusion(
+        &merkle_tree,
+        &reference_merkle_tree,
+        &indexed_array,
+        41,
+        1000,
+    )
+    .unwrap();
+
+    // 7. Invalid deletions.
+    assert!(matches!(
+        indexed_array.delete(&BigUint::from(30_u32)),
+        Err(IndexedMerkleTreeError::ElementDoesNotExist)
+    ));
+    assert!(matches!(
+        indexed_array.delete(&BigUint::from(0_u32)),
+        Err(IndexedMerkleTreeError::LowestElementDeletion)
+    ));
+    assert!(matches!(
+        indexed_array.delete(&BigUint::highest()),
+        Err(IndexedMerkleTreeError::HighestElementDeletion)
+    ));
+    // The highest element can't be deleted from the tree either.
+    let low_element = indexed_array
+        .find_low_element_for_existent(&BigUint::highest())
+        .unwrap();
+    let element = indexed_array
+        .find_element(&BigUint::highest())
+        .unwrap()
+        .clone();
+    let mut low_element_proof = reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    let mut element_proof = reference_merkle_tree
+        .get_proof_of_leaf(element.index, false)
+        .unwrap();
+    assert!(matches!(
+        merkle_tree.delete(
+            merkle_tree.changelog_index(),
+            low_element,
+            element,
+            BigUint::from(0_u32),
+            &mut low_element_proof,
+            &mut element_proof,
+        ),
+        Err(IndexedMerkleTreeError::HighestElementDeletion)
+    ));
+    // The low element doesn't point to the deleted element.
+    let low_element = indexed_array.get(0).unwrap().clone();
+    let element = indexed_array
+        .find_element(&BigUint::from(40_u32))
+        .unwrap()
+        .clone();
+    let element_next_value = indexed_array
+        .get(element.next_index())
+        .unwrap()
+        .value
+        .clone();
+    let mut low_element_proof = reference_merkle_tree
+        .get_proof_of_leaf(low_element.index, false)
+        .unwrap();
+    let mut element_proof = reference_merkle_tree
+        .get_proof_of_leaf(element.index, false)
+        .unwrap();
+    assert!(matches!(
+        merkle_tree.delete(
+            merkle_tree.changelog_index(),
+            low_element,
+            element.clone(),
+            element_next_value.clone(),
+            &mut low_element_proof,
+            &mut element_proof,
+        ),
+        Err(IndexedMerkleTreeError::InvalidLowElement)
+    ));
+    // An outdated low element (20, pointing to 30) can't be updated, since
+    // its leaf changed after the changelog index.
+    let stale_low_element = stale_indexed_array
+        .find_low_element_for_existent(&BigUint::from(30_u32))
+        .unwrap();
+    let stale_element = stale_indexed_array
+        .find_element(&BigUint::from(30_u32))
+        .unwrap()
+        .clone();
+    let mut low_element_proof = stale_reference_merkle_tree
+        .get_proof_of_leaf(stale_low_element.index, false)
+        .unwrap();
+    let mut element_proof = stale_reference_merkle_tree
+        .get_proof_of_leaf(stale_element.index, false)
+        .unwrap();
+    assert!(matches!(
+        merkle_tree.delete(
+            changelog_index,
+            stale_low_element,
+            stale_element,
+            BigUint::from(40_u32),
+            &mut low_element_proof,
+            &mut element_proof,
+        ),
+        Err(IndexedMerkleTreeError::ConcurrentMerkleTree(
+            ConcurrentMerkleTreeError::CannotUpdateLeaf
+        ))
+    ));
+}
+
+#[test]
+fn test_deletion_and_range_non_inclusion_canopy_0() {
+    deletion_and_range_non_inclusion::<0, MERKLE_TREE_HEIGHT>()
+}
+
+#[test]
+fn test_deletion_and_range_non_inclusion_canopy_2() {
+    deletion_and_range_non_inclusion::<2, { MERKLE_TREE_HEIGHT - 2 }>()
+}
@@ -0,0 +1,261 @@
+use light_bounded_vec::BoundedVec;
+use light_concurrent_merkle_tree::{
+    changelog::MAX_BATCH_SIZE, hash::compute_root, ConcurrentMerkleTree,
+};
+use light_hasher::Hasher;
+
+use crate::errors::IntegrityError;
+
+/// Checks all invariants of a concurrent Merkle tree:
+///
+/// * `next_index` bounds.
+/// * Changelog and root buffer, which are expected to contain one entry per
+///   sequence number and to agree on roots.
+/// * Filled subtrees, which are expected to form the Merkle proof of the next
+///   (empty) leaf and to contain the rightmost leaf.
+/// * Canopy, which is expected to be consistent with itself and the root.
+pub fn check_concurrent_merkle_tree<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+{
+    check_next_index(merkle_tree)?;
+    check_changelog_and_roots(merkle_tree)?;
+    check_filled_subtrees(merkle_tree)?;
+    check_canopy(merkle_tree)?;
+    Ok(())
+}
+
+/// Checks whether `next_index` doesn't exceed the capacity of the tree and
+/// the number of leaves which could have been appended (every append
+/// increments the sequence number once and appends at most
+/// [`MAX_BATCH_SIZE`] leaves).
+pub fn check_next_index<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+{
+    let capacity = 1 << merkle_tree.height;
+    let next_index = merkle_tree.next_index();
+    if next_index > capacity {
+        return Err(IntegrityError::NextIndexOutOfBounds(next_index, capacity));
+    }
+    let sequence_number = merkle_tree.sequence_number();
+    if next_index > sequence_number.saturating_mul(MAX_BATCH_SIZE) {
+        return Err(IntegrityError::NextIndexGreaterThanSequenceNumber(
+            next_index,
+            sequence_number,
+        ));
+    }
+    Ok(())
+}
+
+/// Checks whether the changelog and the root buffer contain one entry per
+/// sequence number (up to their capacity), whether the roots of changelog
+/// entries match the roots from the root buffer and whether changelog
+/// entries (including all leaves of batch appends) point to appended leaves.
+pub fn check_changelog_and_roots<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+{
+    let sequence_number = merkle_tree.sequence_number();
+    let expected_changelog_len = merkle_tree
+        .changelog
+        .capacity()
+        .min(sequence_number.saturating_add(1));
+    if merkle_tree.changelog.len() != expected_changelog_len {
+        return Err(IntegrityError::ChangelogLength(
+            expected_changelog_len,
+            merkle_tree.changelog.len(),
+        ));
+    }
+    let expected_roots_len = merkle_tree
+        .roots
+        .capacity()
+        .min(sequence_number.saturating_add(1));
+    if merkle_tree.roots.len() != expected_roots_len {
+        return Err(IntegrityError::RootsLength(
+            expected_roots_len,
+            merkle_tree.roots.len(),
+        ));
+    }
+
+    let roots_capacity = merkle_tree.roots.capacity();
+    for changes in 0..merkle_tree.changelog.len() {
+        let entry_sequence_number = sequence_number - changes;
+        let changelog_index = merkle_tree.changelog_index_of(entry_sequence_number)?;
+        let changelog_entry = &merkle_tree.changelog[changelog_index];
+
+        if changes < merkle_tree.roots.len() {
+            let root_index = (merkle_tree.root_index() + roots_capacity - changes) % roots_capacity;
+            let root = merkle_tree.roots[root_index];
+            if changelog_entry.root != root {
+                return Err(IntegrityError::ChangelogRoot(
+                    entry_sequence_number,
+                    root,
+                    changelog_entry.root,
+                ));
+            }
+        }
+        // The first changelog entry, created during initialization, doesn't
+        // point to any leaf.
+        let last_leaf_index =
+            changelog_entry.index() + changelog_entry.batch_size().unwrap_or(1) - 1;
+        if entry_sequence_number > 0 && last_leaf_index >= merkle_tree.next_index() {
+            return Err(IntegrityError::ChangelogLeafIndex(
+                entry_sequence_number,
+                changelog_entry.index(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the filled subtrees:
+///
+/// * Together with zero bytes, they are expected to form the Merkle proof of
+///   the next (empty) leaf, which produces the current root.
+/// * The rightmost leaf is expected to be the left sibling of the next leaf
+///   (if the next leaf is on the right side) or the newest leaf in the
+///   changelog with its index.
+pub fn check_filled_subtrees<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+{
+    if merkle_tree.filled_subtrees.len() != merkle_tree.height {
+        return Err(IntegrityError::FilledSubtreesLength(
+            merkle_tree.height,
+            merkle_tree.filled_subtrees.len(),
+        ));
+    }
+    let next_index = merkle_tree.next_index();
+    // Filled subtrees are not updated anymore once the tree is full.
+    if next_index >= 1 << merkle_tree.height {
+        return Ok(());
+    }
+
+    let mut proof = BoundedVec::with_capacity(merkle_tree.height);
+    for level in 0..merkle_tree.height {
+        // Filled subtrees are meaningful only on levels where the path of
+        // the next leaf is on the right side.
+        if (next_index >> level) & 1 == 1 {
+            proof.push(merkle_tree.filled_subtrees[level])?;
+        } else {
+            proof.push(H::zero_bytes()[level])?;
+        }
+    }
+    let computed_root = compute_root::<H>(&H::zero_bytes()[0], next_index, &proof)?;
+    let root = merkle_tree.root();
+    if computed_root != root {
+        return Err(IntegrityError::FilledSubtreesRoot(root, computed_root));
+    }
+
+    if next_index == 0 {
+        return Ok(());
+    }
+    let rightmost_index = next_index - 1;
+    let rightmost_leaf = if next_index % 2 == 1 {
+        Some(merkle_tree.filled_subtrees[0])
+    } else {
+        newest_changelog_leaf(merkle_tree, rightmost_index)?
+    };
+    if let Some(leaf) = rightmost_leaf {
+        if leaf != merkle_tree.rightmost_leaf() {
+            return Err(IntegrityError::RightmostLeaf(
+                leaf,
+                merkle_tree.rightmost_leaf(),
+                rightmost_index,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the newest value of the leaf with the given index stored in the
+/// changelog.
+///
+/// Entries of batch appends store only one leaf of the batch (the first leaf
+/// if it is a right child, otherwise the second one), other leaves of the
+/// batch are not known.
+pub(crate) fn newest_changelog_leaf<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+    leaf_index: usize,
+) -> Result<Option<[u8; 32]>, IntegrityError>
+where
+    H: Hasher,
+{
+    let sequence_number = merkle_tree.sequence_number();
+    // The first changelog entry, created during initialization, doesn't
+    // point to any leaf.
+    for changes in 0..merkle_tree.changelog.len().min(sequence_number) {
+        let changelog_index = merkle_tree.changelog_index_of(sequence_number - changes)?;
+        let changelog_entry = &merkle_tree.changelog[changelog_index];
+        let index = changelog_entry.index();
+        match changelog_entry.batch_size() {
+            Some(batch_size) if (index..index + batch_size).contains(&leaf_index) => {
+                let stored_leaf_index = if index % 2 == 1 { index } else { index + 1 };
+                return Ok((leaf_index == stored_leaf_index).then_some(changelog_entry.path[0]));
+            }
+            None if index == leaf_index => return Ok(Some(changelog_entry.path[0])),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Checks whether every pair of canopy nodes hashes to its parent (the
+/// topmost pair to the root) and whether nodes covering only empty leaves
+/// are zero bytes.
+pub fn check_canopy<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+{
+    let canopy_depth = merkle_tree.canopy_depth;
+    let expected_canopy_len = ConcurrentMerkleTree::<H, HEIGHT>::canopy_size(canopy_depth);
+    if merkle_tree.canopy.len() != expected_canopy_len {
+        return Err(IntegrityError::CanopyLength(
+            expected_canopy_len,
+            merkle_tree.canopy.len(),
+        ));
+    }
+
+    // Nodes are numbered like in a heap, the root has index 1 and `index - 2`
+    // maps to the canopy index.
+    for node_index in 1..(1 << canopy_depth) {
+        let node = if node_index == 1 {
+            merkle_tree.root()
+        } else {
+            merkle_tree.canopy[node_index - 2]
+        };
+        let left_child = merkle_tree.canopy[2 * node_index - 2];
+        let right_child = merkle_tree.canopy[2 * node_index - 1];
+        let computed_node = H::hashv(&[&left_child, &right_child])?;
+        if computed_node != node {
+            return Err(IntegrityError::CanopyNode(node_index, node, computed_node));
+        }
+    }
+
+    let next_index = merkle_tree.next_index();
+    for node_index in 2_usize..(1 << (canopy_depth + 1)) {
+        let depth = node_index.ilog2() as usize;
+        let level = merkle_tree.height - depth;
+        let first_leaf_index = (node_index - (1 << depth)) << level;
+        let node = merkle_tree.canopy[node_index - 2];
+        if first_leaf_index >= next_index && node != H::zero_bytes()[level] {
+            return Err(IntegrityError::CanopyNode(
+                node_index,
+                H::zero_bytes()[level],
+                node,
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use light_concurrent_merkle_tree::ConcurrentMerkleTree;
+use light_hasher::Hasher;
+use light_indexed_merkle_tree::{array::IndexedArray, IndexedMerkleTree};
+use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
+
+use crate::{
+    concurrent::newest_changelog_leaf,
+    errors::IntegrityError,
+    indexed::{check_indexed_array, indexed_array_leaves},
+};
+
+/// The first difference between a Merkle tree account and a snapshot of its
+/// leaves (e.g. from an indexer). Expected values come from the account,
+/// actual values from the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The number of leaves differs from `next_index`.
+    NextIndex { expected: usize, actual: usize },
+    /// The snapshot produces an older root of the tree, the snapshot is
+    /// behind the tree.
+    StaleSnapshot { sequence_number: usize },
+    /// A leaf differs from its newest value in the changelog.
+    Leaf {
+        leaf_index: usize,
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    /// A canopy node differs. `index` is the index of the node on its
+    /// `level` (leaves are on level 0).
+    Node {
+        level: usize,
+        index: usize,
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    /// Roots differ, but it's not possible to narrow down the divergence.
+    Root {
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NextIndex { expected, actual } => write!(
+                f,
+                "the tree has {} leaves, the snapshot has {} leaves",
+                expected, actual
+            ),
+            Self::StaleSnapshot { sequence_number } => write!(
+                f,
+                "the snapshot produces the root of sequence number {}",
+                sequence_number
+            ),
+            Self::Leaf {
+                leaf_index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "leaf {} differs, expected {:?}, got {:?}",
+                leaf_index, expected, actual
+            ),
+            Self::Node {
+                level,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "node {} on level {} differs, expected {:?}, got {:?}",
+                index, level, expected, actual
+            ),
+            Self::Root { expected, actual } => {
+                write!(f, "root differs, expected {:?}, got {:?}", expected, actual)
+            }
+        }
+    }
+}
+
+/// Recomputes the root of a concurrent Merkle tree from `leaves` and returns
+/// the first divergence from the tree, or `None` if the snapshot matches.
+///
+/// The divergence is narrowed down in the following order:
+///
+/// 1. Stale snapshot, if the recomputed root is one of the previous roots.
+/// 2. Number of leaves.
+/// 3. Leaves, compared with their newest values in the changelog.
+/// 4. Canopy nodes, descending to the leftmost divergent node.
+/// 5. Root.
+pub fn find_divergence<H, const HEIGHT: usize>(
+    merkle_tree: &ConcurrentMerkleTree<H, HEIGHT>,
+    leaves: &[[u8; 32]],
+) -> Result<Option<Divergence>, IntegrityError>
+where
+    H: Hasher,
+{
+    let layers = compute_layers::<H>(merkle_tree.height, leaves)?;
+    let node = |level: usize, index: usize| -> [u8; 32] {
+        layers[level]
+            .get(index)
+            .copied()
+            .unwrap_or(H::zero_bytes()[level])
+    };
+    let root = node(merkle_tree.height, 0);
+    let next_index = merkle_tree.next_index();
+
+    if root == merkle_tree.root() {
+        if leaves.len() != next_index {
+            return Ok(Some(Divergence::NextIndex {
+                expected: next_index,
+                actual: leaves.len(),
+            }));
+        }
+        return Ok(None);
+    }
+
+    let sequence_number = merkle_tree.sequence_number();
+    let roots_capacity = merkle_tree.roots.capacity();
+    for changes in 1..merkle_tree.roots.len() {
+        let root_index = (merkle_tree.root_index() + roots_capacity - changes) % roots_capacity;
+        if merkle_tree.roots[root_index] == root {
+            return Ok(Some(Divergence::StaleSnapshot {
+                sequence_number: sequence_number - changes,
+            }));
+        }
+    }
+
+    if leaves.len() != next_index {
+        return Ok(Some(Divergence::NextIndex {
+            expected: next_index,
+            actual: leaves.len(),
+        }));
+    }
+
+    for (leaf_index, leaf) in leaves.iter().enumerate() {
+        if let Some(expected) = newest_changelog_leaf(merkle_tree, leaf_index)? {
+            if expected != *leaf {
+                return Ok(Some(Divergence::Leaf {
+                    leaf_index,
+                    expected,
+                    actual: *leaf,
+                }));
+            }
+        }
+    }
+
+    // Descend from the root to the leftmost divergent canopy node. Nodes are
+    // numbered like in a heap, `node_index - 2` maps to the canopy index.
+    let mut divergence = None;
+    let mut node_index = 1;
+    for depth in 1..=merkle_tree.canopy_depth {
+        let level = merkle_tree.height - depth;
+        let children = [2 * node_index, 2 * node_index + 1];
+        let divergent_child = children.iter().find_map(|child_index| {
+            let index = child_index - (1 << depth);
+            let expected = merkle_tree.canopy[child_index - 2];
+            let actual = node(level, index);
+            (expected != actual).then_some((
+                *child_index,
+                Divergence::Node {
+                    level,
+                    index,
+                    expected,
+                    actual,
+                },
+            ))
+        });
+        match divergent_child {
+            Some((child_index, child_divergence)) => {
+                node_index = child_index;
+                divergence = Some(child_divergence);
+            }
+            None => break,
+        }
+    }
+
+    Ok(Some(divergence.unwrap_or(Divergence::Root {
+        expected: merkle_tree.root(),
+        actual: root,
+    })))
+}
+
+/// Checks the integrity of an indexed array, recomputes the root of an
+/// indexed Merkle tree from it and returns the first divergence from the
+/// tree, or `None` if the snapshot matches.
+pub fn find_indexed_divergence<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>(
+    merkle_tree: &IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>,
+    indexed_array: &IndexedArray<H, I>,
+) -> Result<Option<Divergence>, IntegrityError>
+where
+    H: Hasher,
+    I: CheckedAdd
+        + CheckedSub
+        + Copy
+        + Clone
+        + fmt::Debug
+        + PartialOrd
+        + ToBytes
+        + TryFrom<usize>
+        + Unsigned,
+    usize: From<I>,
+{
+    check_indexed_array(indexed_array)?;
+    let leaves = indexed_array_leaves(indexed_array)?;
+    find_divergence(&merkle_tree.merkle_tree, &leaves)
+}
+
+/// Computes all non-empty nodes of a tree with the given `leaves`, level by
+/// level.
+pub(crate) fn compute_layers<H>(
+    height: usize,
+    leaves: &[[u8; 32]],
+) -> Result<Vec<Vec<[u8; 32]>>, IntegrityError>
+where
+    H: Hasher,
+{
+    let mut layers = Vec::with_capacity(height + 1);
+    layers.push(leaves.to_vec());
+    for level in 0..height {
+        let layer = &layers[level];
+        let zero_node = H::zero_bytes()[level];
+        let mut parents = Vec::with_capacity(layer.len().div_ceil(2));
+        for pair in layer.chunks(2) {
+            let right = pair.get(1).unwrap_or(&zero_node);
+            parents.push(H::hashv(&[&pair[0], right])?);
+        }
+        layers.push(parents);
+    }
+    Ok(layers)
+}
@@ -0,0 +1,65 @@
+use light_bounded_vec::BoundedVecError;
+use light_concurrent_merkle_tree::errors::ConcurrentMerkleTreeError;
+use light_hasher::errors::HasherError;
+use light_indexed_merkle_tree::errors::IndexedMerkleTreeError;
+use thiserror::Error;
+
+use crate::divergence::Divergence;
+
+/// Violation of a Merkle tree invariant. Every variant describes the first
+/// inconsistency found by the check.
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("Next index {0} exceeds the capacity of the tree {1}")]
+    NextIndexOutOfBounds(usize, usize),
+    #[error("Next index {0} is greater than the number of leaves which can be appended in {1} operations")]
+    NextIndexGreaterThanSequenceNumber(usize, usize),
+    #[error("Invalid changelog length, expected {0}, got {1}")]
+    ChangelogLength(usize, usize),
+    #[error("Invalid root buffer length, expected {0}, got {1}")]
+    RootsLength(usize, usize),
+    #[error("Changelog entry of sequence number {0} doesn't match the root {1:?} from the root buffer, it has root {2:?}")]
+    ChangelogRoot(usize, [u8; 32], [u8; 32]),
+    #[error(
+        "Changelog entry of sequence number {0} points to the leaf {1}, which is not appended yet"
+    )]
+    ChangelogLeafIndex(usize, usize),
+    #[error("Invalid number of filled subtrees, expected {0}, got {1}")]
+    FilledSubtreesLength(usize, usize),
+    #[error("Filled subtrees produce root {1:?}, expected {0:?}")]
+    FilledSubtreesRoot([u8; 32], [u8; 32]),
+    #[error("Rightmost leaf {1:?} doesn't match the leaf {0:?} with index {2}")]
+    RightmostLeaf([u8; 32], [u8; 32], usize),
+    #[error("Invalid canopy length, expected {0}, got {1}")]
+    CanopyLength(usize, usize),
+    #[error("Canopy node with index {0} is invalid, expected {1:?}, got {2:?}")]
+    CanopyNode(usize, [u8; 32], [u8; 32]),
+    #[error("Indexed changelog is empty")]
+    IndexedChangelogEmpty,
+    #[error("Indexed changelog element with index {0} is not appended yet")]
+    IndexedChangelogElementIndex(usize),
+    #[error("Indexed changelog element with index {0} has a value greater or equal to the value of the next element")]
+    IndexedChangelogElementOrder(usize),
+    #[error(
+        "Indexed changelog entry points to the changelog index {0}, the changelog capacity is {1}"
+    )]
+    IndexedChangelogIndex(usize, usize),
+    #[error("Indexed array element at position {0} has index {1}")]
+    IndexedArrayIndex(usize, usize),
+    #[error("Indexed array element with index {0} points to a non-existing element {1}")]
+    IndexedArrayNextIndex(usize, usize),
+    #[error("Indexed array element with index {0} has a value greater or equal to the value of the next element {1}")]
+    IndexedArrayOrder(usize, usize),
+    #[error("{0} indexed array elements are not reachable from the lowest element")]
+    IndexedArrayUnreachable(usize),
+    #[error("Hasher error: {0}")]
+    Hasher(#[from] HasherError),
+    #[error("Bounded vector error: {0}")]
+    BoundedVec(#[from] BoundedVecError),
+    #[error("Concurrent Merkle tree error: {0}")]
+    ConcurrentMerkleTree(#[from] ConcurrentMerkleTreeError),
+    #[error("Indexed Merkle tree error: {0}")]
+    IndexedMerkleTree(#[from] IndexedMerkleTreeError),
+    #[error("Snapshot diverges from the tree: {0}")]
+    Divergence(Divergence),
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use light_hasher::Hasher;
+use light_indexed_merkle_tree::{array::IndexedArray, IndexedMerkleTree};
+use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
+
+use crate::{concurrent::check_concurrent_merkle_tree, errors::IntegrityError};
+
+/// Checks all invariants of an indexed Merkle tree: invariants of the
+/// underlying concurrent Merkle tree and of the indexed changelog, whose
+/// elements are expected to be appended and to point to greater values.
+pub fn check_indexed_merkle_tree<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>(
+    merkle_tree: &IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>,
+) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+    I: CheckedAdd
+        + CheckedSub
+        + Copy
+        + Clone
+        + fmt::Debug
+        + PartialOrd
+        + ToBytes
+        + TryFrom<usize>
+        + Unsigned,
+    usize: From<I>,
+{
+    check_concurrent_merkle_tree(&merkle_tree.merkle_tree)?;
+
+    if merkle_tree.indexed_changelog.is_empty() {
+        return Err(IntegrityError::IndexedChangelogEmpty);
+    }
+    let next_index = merkle_tree.merkle_tree.next_index();
+    let changelog_capacity = merkle_tree.merkle_tree.changelog.capacity();
+    for changelog_entry in merkle_tree.indexed_changelog.iter() {
+        let element = &changelog_entry.element;
+        let index = usize::from(element.index);
+        if index >= next_index {
+            return Err(IntegrityError::IndexedChangelogElementIndex(index));
+        }
+        // The highest element doesn't point to any element.
+        if usize::from(element.next_index) != 0 && element.value >= element.next_value {
+            return Err(IntegrityError::IndexedChangelogElementOrder(index));
+        }
+        if changelog_entry.changelog_index >= changelog_capacity {
+            return Err(IntegrityError::IndexedChangelogIndex(
+                changelog_entry.changelog_index,
+                changelog_capacity,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the integrity of an indexed array (e.g. kept by an indexer):
+///
+/// * Elements are stored under their indices.
+/// * The linked list starting from the lowest element (with index 0) visits
+///   all elements, except deleted ones, in ascending order of values and
+///   ends with the highest element, pointing to index 0.
+pub fn check_indexed_array<H, I>(indexed_array: &IndexedArray<H, I>) -> Result<(), IntegrityError>
+where
+    H: Hasher,
+    I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
+    usize: From<I>,
+{
+    for (position, element) in indexed_array.elements.iter().enumerate() {
+        if element.index() != position {
+            return Err(IntegrityError::IndexedArrayIndex(position, element.index()));
+        }
+    }
+    if indexed_array.elements.is_empty() {
+        return Ok(());
+    }
+
+    let mut visited = 1;
+    let mut element = &indexed_array.elements[0];
+    while element.next_index() != 0 {
+        let next_element = indexed_array.get(element.next_index()).ok_or(
+            IntegrityError::IndexedArrayNextIndex(element.index(), element.next_index()),
+        )?;
+        // Values are strictly increasing, therefore the list can't contain
+        // cycles.
+        if next_element.value <= element.value {
+            return Err(IntegrityError::IndexedArrayOrder(
+                element.index(),
+                element.next_index(),
+            ));
+        }
+        visited += 1;
+        element = next_element;
+    }
+    let elements = indexed_array
+        .elements
+        .iter()
+        .filter(|element| !element.is_deleted())
+        .count();
+    if visited != elements {
+        return Err(IntegrityError::IndexedArrayUnreachable(elements - visited));
+    }
+    Ok(())
+}
+
+/// Computes leaves of an indexed Merkle tree from an indexed array.
+pub fn indexed_array_leaves<H, I>(
+    indexed_array: &IndexedArray<H, I>,
+) -> Result<Vec<[u8; 32]>, IntegrityError>
+where
+    H: Hasher,
+    I: CheckedAdd + CheckedSub + Copy + Clone + PartialOrd + ToBytes + TryFrom<usize> + Unsigned,
+    usize: From<I>,
+{
+    indexed_array
+        .elements
+        .iter()
+        .map(|element| Ok(indexed_array.hash_element(element.index)?))
+        .collect()
+}
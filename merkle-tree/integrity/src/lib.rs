@@ -0,0 +1,23 @@
+//! Integrity checks of concurrent and indexed Merkle trees, meant to be used
+//! for debugging tree accounts off-chain.
+//!
+//! * [`check_concurrent_merkle_tree`] and [`check_indexed_merkle_tree`]
+//!   validate invariants of the tree data.
+//! * [`check_indexed_array`] validates an indexed array kept by an indexer.
+//! * [`find_divergence`] and [`find_indexed_divergence`] recompute the root
+//!   from a snapshot of leaves and report the first divergence from the tree.
+//! * [`repair_concurrent_merkle_tree`] and [`repair_indexed_merkle_tree`]
+//!   recompute the state derived from the leaves (filled subtrees, rightmost
+//!   leaf, canopy) from a matching snapshot.
+
+pub mod concurrent;
+pub mod divergence;
+pub mod errors;
+pub mod indexed;
+pub mod repair;
+
+pub use concurrent::check_concurrent_merkle_tree;
+pub use divergence::{find_divergence, find_indexed_divergence, Divergence};
+pub use errors::IntegrityError;
+pub use indexed::{check_indexed_array, check_indexed_merkle_tree};
+pub use repair::{repair_concurrent_merkle_tree, repair_indexed_merkle_tree, Repair};
@@ -0,0 +1,168 @@
+use std::fmt;
+
+use light_concurrent_merkle_tree::ConcurrentMerkleTree;
+use light_hasher::Hasher;
+use light_indexed_merkle_tree::{array::IndexedArray, IndexedMerkleTree};
+use num_traits::{CheckedAdd, CheckedSub, ToBytes, Unsigned};
+
+use crate::{
+    concurrent::check_concurrent_merkle_tree,
+    divergence::{compute_layers, find_divergence},
+    errors::IntegrityError,
+    indexed::{check_indexed_array, check_indexed_merkle_tree, indexed_array_leaves},
+};
+
+/// A field of a Merkle tree overwritten by a repair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    FilledSubtree {
+        level: usize,
+        previous: [u8; 32],
+        repaired: [u8; 32],
+    },
+    RightmostLeaf {
+        previous: [u8; 32],
+        repaired: [u8; 32],
+    },
+    /// `node_index` is the index of the node in the heap layout, the root
+    /// has index 1 and `node_index - 2` maps to the canopy index.
+    CanopyNode {
+        node_index: usize,
+        previous: [u8; 32],
+        repaired: [u8; 32],
+    },
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FilledSubtree {
+                level,
+                previous,
+                repaired,
+            } => write!(
+                f,
+                "filled subtree on level {}: {:?} -> {:?}",
+                level, previous, repaired
+            ),
+            Self::RightmostLeaf { previous, repaired } => {
+                write!(f, "rightmost leaf: {:?} -> {:?}", previous, repaired)
+            }
+            Self::CanopyNode {
+                node_index,
+                previous,
+                repaired,
+            } => write!(
+                f,
+                "canopy node {}: {:?} -> {:?}",
+                node_index, previous, repaired
+            ),
+        }
+    }
+}
+
+/// Repairs the state of a concurrent Merkle tree which is derived from its
+/// leaves: filled subtrees, the rightmost leaf and the canopy. They are
+/// recomputed from `leaves`, which have to match the tree (see
+/// [`find_divergence`]). The root buffer and the changelog are never
+/// modified, they can't be recomputed from the leaves.
+///
+/// Returns the repaired fields. Fails if the snapshot diverges from the tree
+/// or if the repaired tree still violates an invariant.
+pub fn repair_concurrent_merkle_tree<H, const HEIGHT: usize>(
+    merkle_tree: &mut ConcurrentMerkleTree<H, HEIGHT>,
+    leaves: &[[u8; 32]],
+) -> Result<Vec<Repair>, IntegrityError>
+where
+    H: Hasher,
+{
+    if let Some(divergence) = find_divergence(merkle_tree, leaves)? {
+        return Err(IntegrityError::Divergence(divergence));
+    }
+    let layers = compute_layers::<H>(merkle_tree.height, leaves)?;
+    let node = |level: usize, index: usize| -> [u8; 32] {
+        layers[level]
+            .get(index)
+            .copied()
+            .unwrap_or(H::zero_bytes()[level])
+    };
+    let mut repairs = Vec::new();
+
+    let next_index = merkle_tree.next_index();
+    // Filled subtrees and the rightmost leaf are not updated anymore once the
+    // tree is full.
+    if next_index < 1 << merkle_tree.height {
+        for level in 0..merkle_tree.height {
+            // Filled subtrees are meaningful only on levels where the path of
+            // the next leaf is on the right side, see
+            // `check_filled_subtrees`.
+            if (next_index >> level) & 1 == 0 {
+                continue;
+            }
+            let repaired = node(level, (next_index >> level) - 1);
+            let previous = merkle_tree.filled_subtrees[level];
+            if previous != repaired {
+                merkle_tree.filled_subtrees[level] = repaired;
+                repairs.push(Repair::FilledSubtree {
+                    level,
+                    previous,
+                    repaired,
+                });
+            }
+        }
+        if next_index > 0 {
+            let repaired = leaves[next_index - 1];
+            let previous = merkle_tree.rightmost_leaf();
+            if previous != repaired {
+                // SAFETY: `rightmost_leaf` points to the rightmost leaf of
+                // the tree, which is valid as long as the tree is.
+                unsafe { *merkle_tree.rightmost_leaf = repaired };
+                repairs.push(Repair::RightmostLeaf { previous, repaired });
+            }
+        }
+    }
+
+    for node_index in 2_usize..(1 << (merkle_tree.canopy_depth + 1)) {
+        let depth = node_index.ilog2() as usize;
+        let repaired = node(merkle_tree.height - depth, node_index - (1 << depth));
+        let previous = merkle_tree.canopy[node_index - 2];
+        if previous != repaired {
+            merkle_tree.canopy[node_index - 2] = repaired;
+            repairs.push(Repair::CanopyNode {
+                node_index,
+                previous,
+                repaired,
+            });
+        }
+    }
+
+    check_concurrent_merkle_tree(merkle_tree)?;
+    Ok(repairs)
+}
+
+/// Repairs the state of an indexed Merkle tree which is derived from its
+/// leaves, computed from `indexed_array`. See
+/// [`repair_concurrent_merkle_tree`].
+pub fn repair_indexed_merkle_tree<H, I, const HEIGHT: usize, const NET_HEIGHT: usize>(
+    merkle_tree: &mut IndexedMerkleTree<H, I, HEIGHT, NET_HEIGHT>,
+    indexed_array: &IndexedArray<H, I>,
+) -> Result<Vec<Repair>, IntegrityError>
+where
+    H: Hasher,
+    I: CheckedAdd
+        + CheckedSub
+        + Copy
+        + Clone
+        + fmt::Debug
+        + PartialOrd
+        + ToBytes
+        + TryFrom<usize>
+        + Unsigned,
+    usize: From<I>,
+{
+    check_indexed_array(indexed_array)?;
+    let leaves = indexed_array_leaves(indexed_array)?;
+    let repairs = repair_concurrent_merkle_tree(&mut merkle_tree.merkle_tree, &leaves)?;
+    check_indexed_merkle_tree(merkle_tree)?;
+    Ok(repairs)
+}
@@ -0,0 +1,525 @@
+use light_concurrent_merkle_tree::ConcurrentMerkleTree;
+use light_hasher::{Hasher, Keccak, Poseidon, Sha256};
+use light_indexed_merkle_tree::{array::IndexedArray, reference, IndexedMerkleTree};
+use light_merkle_tree_integrity::{
+    check_concurrent_merkle_tree, check_indexed_array, check_indexed_merkle_tree, find_divergence,
+    find_indexed_divergence, repair_concurrent_merkle_tree, repair_indexed_merkle_tree, Divergence,
+    IntegrityError, Repair,
+};
+use num_bigint::BigUint;
+use rand::{thread_rng, Rng};
+
+const HEIGHT: usize = 8;
+const CHANGELOG: usize = 16;
+const ROOTS: usize = 32;
+
+/// Creates a concurrent Merkle tree and a matching reference tree with
+/// appends, batch appends (leaf by leaf and by subtrees) and updates.
+fn setup<H>(
+    canopy_depth: usize,
+    num_leaves: usize,
+) -> (
+    ConcurrentMerkleTree<H, HEIGHT>,
+    light_merkle_tree_reference::MerkleTree<H>,
+)
+where
+    H: Hasher,
+{
+    let mut merkle_tree =
+        ConcurrentMerkleTree::<H, HEIGHT>::new(HEIGHT, CHANGELOG, ROOTS, canopy_depth).unwrap();
+    merkle_tree.init().unwrap();
+    let mut reference_tree =
+        light_merkle_tree_reference::MerkleTree::<H>::new(HEIGHT, canopy_depth);
+
+    let mut rng = thread_rng();
+    let mut leaves = Vec::with_capacity(num_leaves);
+    for _ in 0..num_leaves {
+        let mut leaf = [0u8; 32];
+        rng.fill(&mut leaf[1..]);
+        leaves.push(leaf);
+    }
+    let (single, batch) = leaves.split_at(num_leaves / 2);
+    for leaf in single.iter() {
+        merkle_tree.append(leaf).unwrap();
+        reference_tree.append(leaf).unwrap();
+    }
+    for (i, chunk) in batch.chunks(3).enumerate() {
+        let chunk_leaves = chunk.iter().collect::<Vec<_>>();
+        if i % 2 == 0 {
+            merkle_tree.append_batch(chunk_leaves.as_slice()).unwrap();
+        } else {
+            merkle_tree
+                .append_batch_subtrees(chunk_leaves.as_slice())
+                .unwrap();
+        }
+        for leaf in chunk.iter() {
+            reference_tree.append(leaf).unwrap();
+        }
+    }
+    for leaf_index in (0..num_leaves).step_by(5) {
+        let mut new_leaf = [0u8; 32];
+        rng.fill(&mut new_leaf[1..]);
+        let changelog_index = merkle_tree.changelog_index();
+        let mut proof = reference_tree.get_proof_of_leaf(leaf_index, false).unwrap();
+        merkle_tree
+            .update(
+                changelog_index,
+                &reference_tree.leaf(leaf_index),
+                &new_leaf,
+                leaf_index,
+                &mut proof,
+            )
+            .unwrap();
+        reference_tree.update(&new_leaf, leaf_index).unwrap();
+    }
+    (merkle_tree, reference_tree)
+}
+
+fn reference_leaves<H: Hasher>(
+    reference_tree: &light_merkle_tree_reference::MerkleTree<H>,
+) -> Vec<[u8; 32]> {
+    (0..reference_tree.rightmost_index)
+        .map(|leaf_index| reference_tree.leaf(leaf_index))
+        .collect()
+}
+
+/// Tests:
+/// 1. valid trees of different sizes pass all checks
+/// 2. snapshots of valid trees don't diverge
+fn valid_trees<H: Hasher>(canopy_depth: usize) {
+    for num_leaves in [0, 1, 2, 7, 8, 33, 1 << HEIGHT] {
+        let (merkle_tree, reference_tree) = setup::<H>(canopy_depth, num_leaves);
+        check_concurrent_merkle_tree(&merkle_tree).unwrap();
+        assert_eq!(
+            find_divergence(&merkle_tree, &reference_leaves(&reference_tree)).unwrap(),
+            None
+        );
+    }
+}
+
+#[test]
+fn test_valid_trees_keccak_canopy_0() {
+    valid_trees::<Keccak>(0)
+}
+
+#[test]
+fn test_valid_trees_poseidon_canopy_0() {
+    valid_trees::<Poseidon>(0)
+}
+
+#[test]
+fn test_valid_trees_poseidon_canopy_3() {
+    valid_trees::<Poseidon>(3)
+}
+
+#[test]
+fn test_valid_trees_sha256_canopy_7() {
+    valid_trees::<Sha256>(7)
+}
+
+/// Tests that corrupted tree data is detected:
+/// 1. next index out of bounds
+/// 2. next index greater than the number of leaves which can be appended
+/// 3. changelog root not matching the root buffer
+/// 4. changelog entry pointing to an empty leaf
+/// 5. filled subtrees not matching the root
+/// 6. rightmost leaf not matching filled subtrees
+/// 7. inconsistent canopy
+#[test]
+fn test_corrupted_trees() {
+    // 1. next index out of bounds
+    let (merkle_tree, _) = setup::<Poseidon>(2, 33);
+    unsafe { *merkle_tree.next_index = (1 << HEIGHT) + 1 };
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::NextIndexOutOfBounds(_, _))
+    ));
+
+    // 2. next index greater than the number of leaves which can be appended
+    let (merkle_tree, _) = setup::<Poseidon>(2, 33);
+    unsafe { *merkle_tree.sequence_number = 0 };
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::NextIndexGreaterThanSequenceNumber(33, 0))
+    ));
+
+    // 3. changelog root not matching the root buffer
+    let (mut merkle_tree, _) = setup::<Poseidon>(2, 33);
+    let changelog_index = merkle_tree.changelog_index_of(35).unwrap();
+    merkle_tree.changelog[changelog_index].root = [1u8; 32];
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::ChangelogRoot(35, _, root)) if root == [1u8; 32]
+    ));
+
+    // 4. changelog entry pointing to an empty leaf
+    let (mut merkle_tree, _) = setup::<Poseidon>(2, 33);
+    let changelog_index = merkle_tree.changelog_index();
+    merkle_tree.changelog[changelog_index].index = 40;
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::ChangelogLeafIndex(_, 40))
+    ));
+
+    // 5. filled subtrees not matching the root
+    let (mut merkle_tree, _) = setup::<Poseidon>(2, 33);
+    merkle_tree.filled_subtrees[5] = [1u8; 32];
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::FilledSubtreesRoot(_, _))
+    ));
+
+    // 6. rightmost leaf not matching filled subtrees
+    let (merkle_tree, _) = setup::<Poseidon>(2, 33);
+    unsafe { *merkle_tree.rightmost_leaf = [1u8; 32] };
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::RightmostLeaf(_, leaf, 32)) if leaf == [1u8; 32]
+    ));
+    let (merkle_tree, _) = setup::<Poseidon>(2, 34);
+    unsafe { *merkle_tree.rightmost_leaf = [1u8; 32] };
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::RightmostLeaf(_, leaf, 33)) if leaf == [1u8; 32]
+    ));
+
+    // 7. inconsistent canopy
+    let (mut merkle_tree, _) = setup::<Poseidon>(2, 33);
+    merkle_tree.canopy[3] = [1u8; 32];
+    assert!(matches!(
+        check_concurrent_merkle_tree(&merkle_tree),
+        Err(IntegrityError::CanopyNode(2, _, _))
+    ));
+}
+
+/// Tests that the first divergence of a snapshot is reported:
+/// 1. stale snapshot
+/// 2. different number of leaves
+/// 3. leaf differing from the changelog
+/// 4. canopy node
+/// 5. root, when the divergence can't be narrowed down
+#[test]
+fn test_find_divergence() {
+    let (mut merkle_tree, mut reference_tree) = setup::<Poseidon>(4, 100);
+
+    // 1. stale snapshot
+    let stale_leaves = reference_leaves(&reference_tree);
+    let sequence_number = merkle_tree.sequence_number();
+    let changelog_index = merkle_tree.changelog_index();
+    let mut proof = reference_tree.get_proof_of_leaf(50, false).unwrap();
+    merkle_tree
+        .update(
+            changelog_index,
+            &reference_tree.leaf(50),
+            &[1u8; 32],
+            50,
+            &mut proof,
+        )
+        .unwrap();
+    reference_tree.update(&[1u8; 32], 50).unwrap();
+    assert_eq!(
+        find_divergence(&merkle_tree, &stale_leaves).unwrap(),
+        Some(Divergence::StaleSnapshot { sequence_number })
+    );
+
+    // 2. different number of leaves
+    let leaves = reference_leaves(&reference_tree);
+    let mut more_leaves = leaves.clone();
+    more_leaves.push([2u8; 32]);
+    assert_eq!(
+        find_divergence(&merkle_tree, &more_leaves).unwrap(),
+        Some(Divergence::NextIndex {
+            expected: 100,
+            actual: 101
+        })
+    );
+    // Zero leaves don't change the root.
+    let mut more_leaves = leaves.clone();
+    more_leaves.push([0u8; 32]);
+    assert_eq!(
+        find_divergence(&merkle_tree, &more_leaves).unwrap(),
+        Some(Divergence::NextIndex {
+            expected: 100,
+            actual: 101
+        })
+    );
+
+    // 3. leaf differing from the changelog
+    let mut diverging_leaves = leaves.clone();
+    diverging_leaves[50] = [3u8; 32];
+    assert_eq!(
+        find_divergence(&merkle_tree, &diverging_leaves).unwrap(),
+        Some(Divergence::Leaf {
+            leaf_index: 50,
+            expected: [1u8; 32],
+            actual: [3u8; 32],
+        })
+    );
+
+    // 4. canopy node
+    // Leaf 17 is not in the changelog anymore, its divergence is narrowed
+    // down to the canopy node on level `HEIGHT - 4` covering it.
+    let mut diverging_leaves = leaves.clone();
+    diverging_leaves[17] = [3u8; 32];
+    match find_divergence(&merkle_tree, &diverging_leaves).unwrap() {
+        Some(Divergence::Node {
+            level,
+            index,
+            expected,
+            actual,
+        }) => {
+            assert_eq!(level, HEIGHT - 4);
+            assert_eq!(index, 17 >> level);
+            assert_ne!(expected, actual);
+        }
+        divergence => panic!("unexpected divergence: {:?}", divergence),
+    }
+
+    // 5. root, when the divergence can't be narrowed down
+    let (merkle_tree, reference_tree) = setup::<Poseidon>(0, 100);
+    let mut diverging_leaves = reference_leaves(&reference_tree);
+    diverging_leaves[17] = [3u8; 32];
+    assert!(matches!(
+        find_divergence(&merkle_tree, &diverging_leaves).unwrap(),
+        Some(Divergence::Root { .. })
+    ));
+}
+
+/// Tests:
+/// 1. corrupted filled subtrees, rightmost leaf and canopy are repaired
+/// 2. valid trees are not modified
+/// 3. trees are not repaired with a diverging snapshot
+/// 4. corrupted changelog can't be repaired
+#[test]
+fn test_repair() {
+    let (mut merkle_tree, reference_tree) = setup::<Poseidon>(4, 100);
+    let leaves = reference_leaves(&reference_tree);
+    let root = merkle_tree.root();
+    let filled_subtree = merkle_tree.filled_subtrees[2];
+    let rightmost_leaf = merkle_tree.rightmost_leaf();
+    let canopy_node = merkle_tree.canopy[5];
+
+    // 1. corrupted filled subtrees, rightmost leaf and canopy are repaired
+    merkle_tree.filled_subtrees[2] = [1u8; 32];
+    unsafe { *merkle_tree.rightmost_leaf = [2u8; 32] };
+    merkle_tree.canopy[5] = [3u8; 32];
+    assert!(check_concurrent_merkle_tree(&merkle_tree).is_err());
+    assert_eq!(
+        repair_concurrent_merkle_tree(&mut merkle_tree, &leaves).unwrap(),
+        vec![
+            Repair::FilledSubtree {
+                level: 2,
+                previous: [1u8; 32],
+                repaired: filled_subtree,
+            },
+            Repair::RightmostLeaf {
+                previous: [2u8; 32],
+                repaired: rightmost_leaf,
+            },
+            Repair::CanopyNode {
+                node_index: 7,
+                previous: [3u8; 32],
+                repaired: canopy_node,
+            },
+        ]
+    );
+    check_concurrent_merkle_tree(&merkle_tree).unwrap();
+    assert_eq!(merkle_tree.root(), root);
+
+    // 2. valid trees are not modified
+    assert_eq!(
+        repair_concurrent_merkle_tree(&mut merkle_tree, &leaves).unwrap(),
+        vec![]
+    );
+
+    // 3. trees are not repaired with a diverging snapshot
+    merkle_tree.canopy[5] = [3u8; 32];
+    let mut diverging_leaves = leaves.clone();
+    diverging_leaves[99] = [4u8; 32];
+    assert!(matches!(
+        repair_concurrent_merkle_tree(&mut merkle_tree, &diverging_leaves),
+        Err(IntegrityError::Divergence(_))
+    ));
+    assert_eq!(merkle_tree.canopy[5], [3u8; 32]);
+
+    // 4. corrupted changelog can't be repaired
+    let changelog_index = merkle_tree.changelog_index();
+    merkle_tree.changelog[changelog_index].index = 200;
+    assert!(matches!(
+        repair_concurrent_merkle_tree(&mut merkle_tree, &leaves),
+        Err(IntegrityError::ChangelogLeafIndex(_, 200))
+    ));
+}
+
+const INDEXED_HEIGHT: usize = 4;
+const INDEXED_CANOPY: usize = 0;
+const NET_HEIGHT: usize = INDEXED_HEIGHT - INDEXED_CANOPY;
+
+/// Tests:
+/// 1. valid indexed trees and indexed arrays pass all checks and don't
+///    diverge
+/// 2. trees and arrays with deleted elements pass all checks and don't
+///    diverge
+/// 3. derived state of an indexed tree is repaired from an indexed array
+/// 4. corrupted indexed changelog is detected
+/// 5. corrupted indexed arrays are detected
+#[test]
+fn test_indexed_merkle_tree() {
+    let mut merkle_tree = IndexedMerkleTree::<Poseidon, usize, INDEXED_HEIGHT, NET_HEIGHT>::new(
+        INDEXED_HEIGHT,
+        CHANGELOG,
+        ROOTS,
+        INDEXED_CANOPY,
+        CHANGELOG,
+    )
+    .unwrap();
+    merkle_tree.init().unwrap();
+    let mut indexed_array = IndexedArray::<Poseidon, usize>::default();
+    let mut reference_tree =
+        reference::IndexedMerkleTree::<Poseidon, usize>::new(INDEXED_HEIGHT, INDEXED_CANOPY)
+            .unwrap();
+
+    for value in [30_u32, 10, 20, 50, 40] {
+        let value = BigUint::from(value);
+        let (low_element, low_element_next_value) = indexed_array
+            .find_low_element_for_nonexistent(&value)
+            .unwrap();
+        let bundle = indexed_array
+            .new_element_with_low_element_index(low_element.index, &value)
+            .unwrap();
+        let mut low_element_proof = reference_tree
+            .get_proof_of_leaf(low_element.index(), false)
+            .unwrap();
+        merkle_tree
+            .update(
+                merkle_tree.changelog_index(),
+                merkle_tree.indexed_changelog_index(),
+                value.clone(),
+                low_element,
+                low_element_next_value,
+                &mut low_element_proof,
+            )
+            .unwrap();
+        reference_tree
+            .update(
+                &bundle.new_low_element,
+                &bundle.new_element,
+                &bundle.new_element_next_value,
+            )
+            .unwrap();
+        indexed_array
+            .append_with_low_element_index(bundle.new_low_element.index, &value)
+            .unwrap();
+    }
+
+    // 1. valid indexed trees and indexed arrays pass all checks and don't
+    //    diverge
+    check_indexed_merkle_tree(&merkle_tree).unwrap();
+    check_indexed_array(&indexed_array).unwrap();
+    assert_eq!(
+        find_indexed_divergence(&merkle_tree, &indexed_array).unwrap(),
+        None
+    );
+
+    // 2. trees and arrays with deleted elements pass all checks and don't
+    //    diverge
+    let value = BigUint::from(40_u32);
+    let low_element = indexed_array.find_low_element_for_existent(&value).unwrap();
+    let element = indexed_array.find_element(&value).unwrap().clone();
+    let element_next_value = indexed_array
+        .get(element.next_index())
+        .unwrap()
+        .value
+        .clone();
+    let mut low_element_proof = reference_tree
+        .get_proof_of_leaf(low_element.index(), false)
+        .unwrap();
+    let mut element_proof = reference_tree
+        .get_proof_of_leaf(element.index(), false)
+        .unwrap();
+    merkle_tree
+        .delete(
+            merkle_tree.changelog_index(),
+            low_element,
+            element,
+            element_next_value,
+            &mut low_element_proof,
+            &mut element_proof,
+        )
+        .unwrap();
+    reference_tree.delete(&value, &mut indexed_array).unwrap();
+    assert_eq!(merkle_tree.root(), reference_tree.root());
+    check_indexed_merkle_tree(&merkle_tree).unwrap();
+    check_indexed_array(&indexed_array).unwrap();
+    assert_eq!(
+        find_indexed_divergence(&merkle_tree, &indexed_array).unwrap(),
+        None
+    );
+
+    // 3. derived state of an indexed tree is repaired from an indexed array
+    let rightmost_leaf = merkle_tree.merkle_tree.rightmost_leaf();
+    unsafe { *merkle_tree.merkle_tree.rightmost_leaf = [1u8; 32] };
+    assert_eq!(
+        repair_indexed_merkle_tree(&mut merkle_tree, &indexed_array).unwrap(),
+        vec![Repair::RightmostLeaf {
+            previous: [1u8; 32],
+            repaired: rightmost_leaf,
+        }]
+    );
+    check_indexed_merkle_tree(&merkle_tree).unwrap();
+
+    // 4. corrupted indexed changelog is detected, the newest entry is the
+    //    deleted element, the entry before is its low element
+    let indexed_changelog_capacity = merkle_tree.indexed_changelog.capacity();
+    let indexed_changelog_index =
+        (merkle_tree.indexed_changelog_index() + indexed_changelog_capacity - 1)
+            % indexed_changelog_capacity;
+    let next_value = merkle_tree.indexed_changelog[indexed_changelog_index]
+        .element
+        .next_value;
+    merkle_tree.indexed_changelog[indexed_changelog_index]
+        .element
+        .value = next_value;
+    assert!(matches!(
+        check_indexed_merkle_tree(&merkle_tree),
+        Err(IntegrityError::IndexedChangelogElementOrder(_))
+    ));
+
+    // 5. corrupted indexed arrays are detected
+    let mut corrupted_array = indexed_array.clone();
+    corrupted_array.elements[2].index = 3;
+    assert!(matches!(
+        check_indexed_array(&corrupted_array),
+        Err(IntegrityError::IndexedArrayIndex(2, 3))
+    ));
+    let mut corrupted_array = indexed_array.clone();
+    corrupted_array.elements[2].next_index = 10;
+    assert!(matches!(
+        check_indexed_array(&corrupted_array),
+        Err(IntegrityError::IndexedArrayNextIndex(2, 10))
+    ));
+    let mut corrupted_array = indexed_array.clone();
+    corrupted_array.elements[2].value = BigUint::from(35_u32);
+    assert!(matches!(
+        check_indexed_array(&corrupted_array),
+        Err(IntegrityError::IndexedArrayOrder(_, _))
+    ));
+    let mut corrupted_array = indexed_array.clone();
+    // Skip the element with value 20.
+    let element_10 = corrupted_array
+        .elements
+        .iter()
+        .position(|element| element.value == BigUint::from(10_u32))
+        .unwrap();
+    let element_30 = corrupted_array
+        .elements
+        .iter()
+        .position(|element| element.value == BigUint::from(30_u32))
+        .unwrap();
+    corrupted_array.elements[element_10].next_index = element_30;
+    assert!(matches!(
+        check_indexed_array(&corrupted_array),
+        Err(IntegrityError::IndexedArrayUnreachable(1))
+    ));
+    assert!(find_indexed_divergence(&merkle_tree, &corrupted_array).is_err());
+}
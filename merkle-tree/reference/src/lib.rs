@@ -1,9 +1,11 @@
-use std::marker::PhantomData;
+use std::{io, marker::PhantomData};
 
 use light_bounded_vec::{BoundedVec, BoundedVecError};
 use light_hasher::{errors::HasherError, Hasher};
 use thiserror::Error;
 
+pub mod node_store;
+pub mod sparse;
 pub mod store;
 
 #[derive(Debug, Error)]
@@ -14,6 +16,34 @@ pub enum ReferenceMerkleTreeError {
     Hasher(#[from] HasherError),
     #[error("Invalid proof length provided: {0} required {1}")]
     InvalidProofLength(usize, usize),
+    #[error("Bounded vector error: {0}")]
+    BoundedVec(#[from] BoundedVecError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid tree height {0}")]
+    InvalidHeight(usize),
+    #[error("Tree height {0} in the store doesn't match the requested height {1}")]
+    HeightMismatch(usize, usize),
+    #[error("The store already contains a tree")]
+    StoreNotEmpty,
+    #[error("The store doesn't contain a tree")]
+    StoreEmpty,
+    #[error("The store is corrupted")]
+    CorruptedStore,
+    #[error("The tree is full")]
+    TreeFull,
+    #[error("Event is not supported by this tree")]
+    UnsupportedEvent,
+    #[error("Event with sequence number {0} doesn't match the tree")]
+    InvalidEvent(usize),
+    #[error("Expected sequence number {0}, got {1}")]
+    SequenceNumberGap(usize, usize),
+    #[error("Snapshot at sequence number {0} is not available")]
+    SnapshotUnavailable(usize),
+    #[error("Storage error: {0}")]
+    Storage(Box<redb::Error>),
+    #[error("The store has no open write transaction")]
+    NoTransaction,
 }
 
 #[derive(Debug, Clone)]
@@ -115,6 +145,24 @@ where
         Ok(())
     }
 
+    /// Appends the leaves as a single operation, with one root and one
+    /// sequence number, like `ConcurrentMerkleTree::append_batch_subtrees`.
+    pub fn append_batch_subtrees(&mut self, leaves: &[&[u8; 32]]) -> Result<(), HasherError> {
+        let num_roots = self.roots.len();
+        for leaf in leaves {
+            self.layers[0].push(**leaf);
+            let i = self.rightmost_index;
+            self.rightmost_index += 1;
+            self.update_upper_layers(i)?;
+        }
+        let root = self.root();
+        self.roots.truncate(num_roots);
+        self.roots.push(root);
+
+        self.sequence_number += 1;
+        Ok(())
+    }
+
     pub fn update(
         &mut self,
         leaf: &[u8; 32],
@@ -130,6 +178,69 @@ where
         Ok(())
     }
 
+    /// Reverts the last operation, which was an append.
+    pub fn revert_append(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        let leaf_index = self
+            .rightmost_index
+            .checked_sub(1)
+            .ok_or(ReferenceMerkleTreeError::LeafDoesNotExist(0))?;
+        self.revert_update(&H::zero_bytes()[0], leaf_index)?;
+
+        // Nodes of the removed leaf are zero now, drop them so that the next
+        // append pushes them again.
+        self.rightmost_index = leaf_index;
+        for (level, layer) in self.layers.iter_mut().enumerate() {
+            layer.truncate(self.rightmost_index.div_ceil(1 << level));
+        }
+        Ok(())
+    }
+
+    /// Reverts the last operation, which was a batch append of `num_leaves`
+    /// leaves with [`append_batch_subtrees`](MerkleTree::append_batch_subtrees).
+    pub fn revert_append_batch_subtrees(
+        &mut self,
+        num_leaves: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        let first_leaf_index = self
+            .rightmost_index
+            .checked_sub(num_leaves)
+            .ok_or(ReferenceMerkleTreeError::LeafDoesNotExist(0))?;
+        let num_roots = self.roots.len();
+        for leaf_index in (first_leaf_index..self.rightmost_index).rev() {
+            self.layers[0][leaf_index] = H::zero_bytes()[0];
+            self.update_upper_layers(leaf_index)?;
+        }
+
+        self.rightmost_index = first_leaf_index;
+        for (level, layer) in self.layers.iter_mut().enumerate() {
+            layer.truncate(self.rightmost_index.div_ceil(1 << level));
+        }
+        // Drop the recomputed roots and the root of the reverted batch.
+        self.roots.truncate(num_roots - 1);
+        self.sequence_number -= 1;
+        Ok(())
+    }
+
+    /// Reverts the last operation, which was an update of the leaf at
+    /// `leaf_index` from `previous_leaf`.
+    pub fn revert_update(
+        &mut self,
+        previous_leaf: &[u8; 32],
+        leaf_index: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        *self.layers[0]
+            .get_mut(leaf_index)
+            .ok_or(ReferenceMerkleTreeError::LeafDoesNotExist(leaf_index))? = *previous_leaf;
+
+        self.update_upper_layers(leaf_index)?;
+
+        // Drop the root we just recomputed and the root of the reverted
+        // operation, the previous root is the current one again.
+        self.roots.truncate(self.roots.len() - 2);
+        self.sequence_number -= 1;
+        Ok(())
+    }
+
     pub fn root(&self) -> [u8; 32] {
         // PANICS: We always initialize the Merkle tree with a
         // root (from zero bytes), so the following should never
@@ -0,0 +1,486 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use redb::{Database, Durability, ReadableTable, TableDefinition, WriteTransaction};
+
+use crate::ReferenceMerkleTreeError;
+
+/// Metadata of a [`SparseMerkleTree`](crate::sparse::SparseMerkleTree)
+/// persisted next to its nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SparseMerkleTreeMetadata {
+    pub height: u64,
+    pub next_index: u64,
+    pub sequence_number: u64,
+    /// Oldest sequence number the tree can be restored to.
+    pub oldest_sequence_number: u64,
+}
+
+impl SparseMerkleTreeMetadata {
+    pub const LEN: usize = 32;
+
+    fn to_bytes(self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.height.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.next_index.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.sequence_number.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.oldest_sequence_number.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        Self {
+            height: u64_from_le_bytes(&bytes[0..8]),
+            next_index: u64_from_le_bytes(&bytes[8..16]),
+            sequence_number: u64_from_le_bytes(&bytes[16..24]),
+            oldest_sequence_number: u64_from_le_bytes(&bytes[24..32]),
+        }
+    }
+}
+
+/// Entry of the undo journal. Records the state of the tree overwritten by
+/// the operation with `sequence_number`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalEntry {
+    /// Start of an operation, recorded for every operation, also for ones
+    /// which don't change any node.
+    Operation {
+        sequence_number: u64,
+        /// Next index of the tree before the operation.
+        next_index: u64,
+    },
+    /// Previous value of a node overwritten by the operation.
+    Node {
+        sequence_number: u64,
+        /// Index of the node, the root has index 1, children of node `i`
+        /// have indices `2i` and `2i + 1`.
+        node_index: u64,
+        /// Previous value of the node, `None` if the node was a zero node.
+        node: Option<[u8; 32]>,
+    },
+}
+
+impl JournalEntry {
+    /// Sequence number of the operation which created the entry.
+    pub fn sequence_number(&self) -> u64 {
+        match self {
+            Self::Operation {
+                sequence_number, ..
+            } => *sequence_number,
+            Self::Node {
+                sequence_number, ..
+            } => *sequence_number,
+        }
+    }
+
+    /// Key of the entry in [`FileNodeStore`]. Node indices start at 1, index
+    /// 0 is used for operation entries so that they precede the node entries
+    /// of their operation.
+    fn key(&self) -> (u64, u64) {
+        match self {
+            Self::Operation {
+                sequence_number, ..
+            } => (*sequence_number, 0),
+            Self::Node {
+                sequence_number,
+                node_index,
+                ..
+            } => (*sequence_number, *node_index),
+        }
+    }
+
+    fn value(&self) -> [u8; 32] {
+        match self {
+            Self::Operation { next_index, .. } => {
+                let mut bytes = [0u8; 32];
+                bytes[0..8].copy_from_slice(&next_index.to_le_bytes());
+                bytes
+            }
+            Self::Node { node, .. } => node.unwrap_or([0u8; 32]),
+        }
+    }
+
+    fn from_key_value((sequence_number, node_index): (u64, u64), value: [u8; 32]) -> Self {
+        if node_index == 0 {
+            Self::Operation {
+                sequence_number,
+                next_index: u64_from_le_bytes(&value[0..8]),
+            }
+        } else {
+            Self::Node {
+                sequence_number,
+                node_index,
+                node: node_or_none(value),
+            }
+        }
+    }
+}
+
+/// Storage of the non-zero nodes, the metadata and the undo journal of a
+/// [`SparseMerkleTree`](crate::sparse::SparseMerkleTree).
+///
+/// Nodes are addressed by their index in a binary heap layout, the root has
+/// index 1 and children of node `i` have indices `2i` and `2i + 1`. This is
+/// the same layout as `PathNode::index` in changelog events.
+///
+/// Writes of a tree operation are made visible to readers immediately and
+/// are persisted together by [`NodeStore::commit`].
+pub trait NodeStore {
+    fn get_node(&self, node_index: u64) -> Result<Option<[u8; 32]>, ReferenceMerkleTreeError>;
+    fn put_node(
+        &mut self,
+        node_index: u64,
+        node: &[u8; 32],
+    ) -> Result<(), ReferenceMerkleTreeError>;
+    fn remove_node(&mut self, node_index: u64) -> Result<(), ReferenceMerkleTreeError>;
+
+    fn get_metadata(&self) -> Result<Option<SparseMerkleTreeMetadata>, ReferenceMerkleTreeError>;
+    fn put_metadata(
+        &mut self,
+        metadata: &SparseMerkleTreeMetadata,
+    ) -> Result<(), ReferenceMerkleTreeError>;
+
+    /// Appends an entry to the journal. Entries are appended in order of
+    /// sequence numbers, every operation appends its
+    /// [`JournalEntry::Operation`] before its node entries.
+    fn append_journal_entry(
+        &mut self,
+        entry: &JournalEntry,
+    ) -> Result<(), ReferenceMerkleTreeError>;
+    /// Returns journal entries with a sequence number greater than
+    /// `sequence_number`, ordered by sequence number, the
+    /// [`JournalEntry::Operation`] of an operation comes first.
+    fn journal_entries_after(
+        &self,
+        sequence_number: u64,
+    ) -> Result<Vec<JournalEntry>, ReferenceMerkleTreeError>;
+    /// Removes journal entries with a sequence number greater than
+    /// `sequence_number`.
+    fn truncate_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError>;
+    /// Removes journal entries with a sequence number lower than or equal to
+    /// `sequence_number`.
+    fn prune_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError>;
+
+    /// Atomically persists all writes since the last commit. Called by the
+    /// tree at the end of every operation.
+    fn commit(&mut self) -> Result<(), ReferenceMerkleTreeError>;
+    /// Makes all committed writes durable.
+    fn flush(&mut self) -> Result<(), ReferenceMerkleTreeError>;
+}
+
+/// In-memory node store.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryNodeStore {
+    nodes: HashMap<u64, [u8; 32]>,
+    metadata: Option<SparseMerkleTreeMetadata>,
+    journal: Vec<JournalEntry>,
+}
+
+impl MemoryNodeStore {
+    /// Number of stored (non-zero) nodes.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl NodeStore for MemoryNodeStore {
+    fn get_node(&self, node_index: u64) -> Result<Option<[u8; 32]>, ReferenceMerkleTreeError> {
+        Ok(self.nodes.get(&node_index).copied())
+    }
+
+    fn put_node(
+        &mut self,
+        node_index: u64,
+        node: &[u8; 32],
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.nodes.insert(node_index, *node);
+        Ok(())
+    }
+
+    fn remove_node(&mut self, node_index: u64) -> Result<(), ReferenceMerkleTreeError> {
+        self.nodes.remove(&node_index);
+        Ok(())
+    }
+
+    fn get_metadata(&self) -> Result<Option<SparseMerkleTreeMetadata>, ReferenceMerkleTreeError> {
+        Ok(self.metadata)
+    }
+
+    fn put_metadata(
+        &mut self,
+        metadata: &SparseMerkleTreeMetadata,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.metadata = Some(*metadata);
+        Ok(())
+    }
+
+    fn append_journal_entry(
+        &mut self,
+        entry: &JournalEntry,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.journal.push(*entry);
+        Ok(())
+    }
+
+    fn journal_entries_after(
+        &self,
+        sequence_number: u64,
+    ) -> Result<Vec<JournalEntry>, ReferenceMerkleTreeError> {
+        Ok(self
+            .journal
+            .iter()
+            .filter(|entry| entry.sequence_number() > sequence_number)
+            .copied()
+            .collect())
+    }
+
+    fn truncate_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError> {
+        self.journal
+            .retain(|entry| entry.sequence_number() <= sequence_number);
+        Ok(())
+    }
+
+    fn prune_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError> {
+        self.journal
+            .retain(|entry| entry.sequence_number() > sequence_number);
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        Ok(())
+    }
+}
+
+const NODES_TABLE: TableDefinition<u64, [u8; 32]> = TableDefinition::new("nodes");
+/// Journal entries keyed by `(sequence_number, node_index)`, see
+/// [`JournalEntry::key`].
+const JOURNAL_TABLE: TableDefinition<(u64, u64), [u8; 32]> = TableDefinition::new("journal");
+const METADATA_TABLE: TableDefinition<&str, [u8; SparseMerkleTreeMetadata::LEN]> =
+    TableDefinition::new("metadata");
+const METADATA_KEY: &str = "tree";
+
+/// On-disk node store, backed by an embedded [`redb`] key-value database in
+/// the file `nodes.redb` of a directory. Only non-zero nodes are stored.
+///
+/// All writes between two commits happen in a single write transaction, a
+/// tree operation is either persisted completely or not at all. Commits are
+/// made durable with [`NodeStore::flush`], after a crash the store is opened
+/// at a previous commit.
+pub struct FileNodeStore {
+    path: PathBuf,
+    /// Write transaction of the current operation, replaced on commit.
+    /// Declared before `database`, the transaction has to be dropped first.
+    transaction: Option<WriteTransaction>,
+    database: Database,
+}
+
+impl fmt::Debug for FileNodeStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileNodeStore")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl FileNodeStore {
+    const DATABASE_FILE: &'static str = "nodes.redb";
+
+    /// Opens the store in the directory `path`, creates the directory if it
+    /// doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ReferenceMerkleTreeError> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+        let database = Database::create(path.join(Self::DATABASE_FILE)).map_err(storage_error)?;
+        let transaction = Self::begin_write(&database)?;
+        Ok(Self {
+            path,
+            transaction: Some(transaction),
+            database,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn begin_write(database: &Database) -> Result<WriteTransaction, ReferenceMerkleTreeError> {
+        let mut transaction = database.begin_write().map_err(storage_error)?;
+        transaction.set_durability(Durability::Eventual);
+        Ok(transaction)
+    }
+
+    fn transaction(&self) -> Result<&WriteTransaction, ReferenceMerkleTreeError> {
+        self.transaction
+            .as_ref()
+            .ok_or(ReferenceMerkleTreeError::NoTransaction)
+    }
+
+    fn commit_with_durability(
+        &mut self,
+        durability: Durability,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        // Only one write transaction can be open at a time, the next one is
+        // started after the commit.
+        let mut transaction = self
+            .transaction
+            .take()
+            .ok_or(ReferenceMerkleTreeError::NoTransaction)?;
+        transaction.set_durability(durability);
+        let result = transaction.commit().map_err(storage_error);
+        self.transaction = Some(Self::begin_write(&self.database)?);
+        result
+    }
+}
+
+impl NodeStore for FileNodeStore {
+    fn get_node(&self, node_index: u64) -> Result<Option<[u8; 32]>, ReferenceMerkleTreeError> {
+        let table = self
+            .transaction()?
+            .open_table(NODES_TABLE)
+            .map_err(storage_error)?;
+        let node = table
+            .get(node_index)
+            .map_err(storage_error)?
+            .map(|node| node.value());
+        Ok(node)
+    }
+
+    fn put_node(
+        &mut self,
+        node_index: u64,
+        node: &[u8; 32],
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(NODES_TABLE)
+            .map_err(storage_error)?;
+        table.insert(node_index, node).map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn remove_node(&mut self, node_index: u64) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(NODES_TABLE)
+            .map_err(storage_error)?;
+        table.remove(node_index).map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn get_metadata(&self) -> Result<Option<SparseMerkleTreeMetadata>, ReferenceMerkleTreeError> {
+        let table = self
+            .transaction()?
+            .open_table(METADATA_TABLE)
+            .map_err(storage_error)?;
+        let metadata = table
+            .get(METADATA_KEY)
+            .map_err(storage_error)?
+            .map(|metadata| SparseMerkleTreeMetadata::from_bytes(&metadata.value()));
+        Ok(metadata)
+    }
+
+    fn put_metadata(
+        &mut self,
+        metadata: &SparseMerkleTreeMetadata,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(METADATA_TABLE)
+            .map_err(storage_error)?;
+        table
+            .insert(METADATA_KEY, metadata.to_bytes())
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn append_journal_entry(
+        &mut self,
+        entry: &JournalEntry,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(JOURNAL_TABLE)
+            .map_err(storage_error)?;
+        // An operation overwrites a node at most once, if it does more often
+        // the first entry holds the value before the operation.
+        if table.get(entry.key()).map_err(storage_error)?.is_none() {
+            table
+                .insert(entry.key(), entry.value())
+                .map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    fn journal_entries_after(
+        &self,
+        sequence_number: u64,
+    ) -> Result<Vec<JournalEntry>, ReferenceMerkleTreeError> {
+        let table = self
+            .transaction()?
+            .open_table(JOURNAL_TABLE)
+            .map_err(storage_error)?;
+        let mut entries = Vec::new();
+        for entry in table
+            .range((sequence_number.saturating_add(1), 0)..)
+            .map_err(storage_error)?
+        {
+            let (key, value) = entry.map_err(storage_error)?;
+            entries.push(JournalEntry::from_key_value(key.value(), value.value()));
+        }
+        Ok(entries)
+    }
+
+    fn truncate_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(JOURNAL_TABLE)
+            .map_err(storage_error)?;
+        table
+            .retain_in((sequence_number.saturating_add(1), 0).., |_, _| false)
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn prune_journal(&mut self, sequence_number: u64) -> Result<(), ReferenceMerkleTreeError> {
+        let mut table = self
+            .transaction()?
+            .open_table(JOURNAL_TABLE)
+            .map_err(storage_error)?;
+        table
+            .retain_in(..=(sequence_number, u64::MAX), |_, _| false)
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        self.commit_with_durability(Durability::Eventual)
+    }
+
+    fn flush(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        self.commit_with_durability(Durability::Immediate)
+    }
+}
+
+fn storage_error<E: Into<redb::Error>>(error: E) -> ReferenceMerkleTreeError {
+    ReferenceMerkleTreeError::Storage(Box::new(error.into()))
+}
+
+fn u64_from_le_bytes(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Zero bytes mark absent nodes in on-disk records.
+fn node_or_none(node: [u8; 32]) -> Option<[u8; 32]> {
+    if node == [0u8; 32] {
+        None
+    } else {
+        Some(node)
+    }
+}
@@ -0,0 +1,609 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use light_bounded_vec::BoundedVec;
+use light_concurrent_merkle_tree::event::{
+    ChangelogEvent, MerkleTreeEvent, NullifierEvent, PathNode,
+};
+use light_hasher::{zero_bytes::MAX_HEIGHT, Hasher};
+
+use crate::{
+    node_store::{JournalEntry, NodeStore, SparseMerkleTreeMetadata},
+    ReferenceMerkleTreeError,
+};
+
+/// Sparse reference Merkle tree. Only non-zero nodes are kept in the
+/// [`NodeStore`], all other nodes are taken from `Hasher::zero_bytes`.
+///
+/// Every operation records the next index of the tree and the previous values
+/// of the nodes it overwrites in an undo journal, which makes it possible to
+/// take snapshots of the tree at previous sequence numbers and to roll the
+/// tree back. The journal can be pruned with
+/// [`SparseMerkleTree::prune_history`].
+///
+/// Operations are atomic, the writes of an operation are committed to the
+/// store together and reverted if the operation fails.
+///
+/// Sequence numbers follow the on-chain concurrent Merkle tree, the sequence
+/// number of an operation is the sequence number of the tree after the
+/// operation.
+#[derive(Debug)]
+pub struct SparseMerkleTree<H, S>
+where
+    H: Hasher,
+    S: NodeStore,
+{
+    pub height: usize,
+    pub capacity: usize,
+    pub next_index: usize,
+    pub sequence_number: usize,
+    /// Oldest sequence number the tree can be restored to.
+    pub oldest_sequence_number: usize,
+    store: S,
+
+    _hasher: PhantomData<H>,
+}
+
+impl<H, S> SparseMerkleTree<H, S>
+where
+    H: Hasher,
+    S: NodeStore,
+{
+    /// Creates an empty tree in `store`. Fails if `store` already contains a
+    /// tree.
+    pub fn new(store: S, height: usize) -> Result<Self, ReferenceMerkleTreeError> {
+        if height == 0 || height > MAX_HEIGHT {
+            return Err(ReferenceMerkleTreeError::InvalidHeight(height));
+        }
+        if store.get_metadata()?.is_some() {
+            return Err(ReferenceMerkleTreeError::StoreNotEmpty);
+        }
+        let mut tree = Self {
+            height,
+            capacity: 1 << height,
+            next_index: 0,
+            sequence_number: 0,
+            oldest_sequence_number: 0,
+            store,
+            _hasher: PhantomData,
+        };
+        tree.put_metadata()?;
+        tree.store.commit()?;
+        Ok(tree)
+    }
+
+    /// Opens the tree persisted in `store`.
+    pub fn open(store: S) -> Result<Self, ReferenceMerkleTreeError> {
+        let metadata = store
+            .get_metadata()?
+            .ok_or(ReferenceMerkleTreeError::StoreEmpty)?;
+        let height = metadata.height as usize;
+        if height == 0 || height > MAX_HEIGHT {
+            return Err(ReferenceMerkleTreeError::CorruptedStore);
+        }
+        Ok(Self {
+            height,
+            capacity: 1 << height,
+            next_index: metadata.next_index as usize,
+            sequence_number: metadata.sequence_number as usize,
+            oldest_sequence_number: metadata.oldest_sequence_number as usize,
+            store,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Opens the tree persisted in `store` or creates an empty one.
+    pub fn open_or_new(store: S, height: usize) -> Result<Self, ReferenceMerkleTreeError> {
+        match store.get_metadata()? {
+            Some(metadata) if metadata.height as usize != height => Err(
+                ReferenceMerkleTreeError::HeightMismatch(metadata.height as usize, height),
+            ),
+            Some(_) => Self::open(store),
+            None => Self::new(store, height),
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Persists all pending writes of the store.
+    pub fn flush(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        self.store.flush()
+    }
+
+    pub fn root(&self) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        self.get_node(self.height, 0)
+    }
+
+    pub fn leaf(&self, leaf_index: usize) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        self.get_node(0, leaf_index)
+    }
+
+    /// Returns the node at `index` of `level`, the leaf level is 0.
+    pub fn get_node(
+        &self,
+        level: usize,
+        index: usize,
+    ) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        Ok(self
+            .store
+            .get_node(node_index(self.height, level, index))?
+            .unwrap_or(H::zero_bytes()[level]))
+    }
+
+    pub fn append(&mut self, leaf: &[u8; 32]) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| tree.append_leaf(leaf))
+    }
+
+    /// Appends the leaves in a single atomic operation, every leaf gets its
+    /// own sequence number.
+    pub fn append_batch(&mut self, leaves: &[&[u8; 32]]) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| {
+            for leaf in leaves {
+                tree.append_leaf(leaf)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Appends the leaves in a single atomic operation with a single
+    /// sequence number, like `ConcurrentMerkleTree::append_batch_subtrees`.
+    pub fn append_batch_subtrees(
+        &mut self,
+        leaves: &[&[u8; 32]],
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| {
+            if tree.next_index + leaves.len() > tree.capacity {
+                return Err(ReferenceMerkleTreeError::TreeFull);
+            }
+            let sequence_number = tree.sequence_number + 1;
+            for leaf in leaves {
+                tree.set_leaf(leaf, tree.next_index, sequence_number)?;
+                tree.next_index += 1;
+            }
+            tree.sequence_number = sequence_number;
+            Ok(())
+        })
+    }
+
+    pub fn update(
+        &mut self,
+        leaf: &[u8; 32],
+        leaf_index: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| {
+            if leaf_index >= tree.next_index {
+                return Err(ReferenceMerkleTreeError::LeafDoesNotExist(leaf_index));
+            }
+            let sequence_number = tree.sequence_number + 1;
+            tree.set_leaf(leaf, leaf_index, sequence_number)?;
+            tree.sequence_number = sequence_number;
+            Ok(())
+        })
+    }
+
+    fn append_leaf(&mut self, leaf: &[u8; 32]) -> Result<(), ReferenceMerkleTreeError> {
+        if self.next_index >= self.capacity {
+            return Err(ReferenceMerkleTreeError::TreeFull);
+        }
+        let sequence_number = self.sequence_number + 1;
+        self.set_leaf(leaf, self.next_index, sequence_number)?;
+        self.next_index += 1;
+        self.sequence_number = sequence_number;
+        Ok(())
+    }
+
+    pub fn get_proof_of_leaf(
+        &self,
+        leaf_index: usize,
+    ) -> Result<BoundedVec<[u8; 32]>, ReferenceMerkleTreeError> {
+        get_proof_of_leaf(self.height, leaf_index, |level, index| {
+            self.get_node(level, index)
+        })
+    }
+
+    pub fn verify(
+        &self,
+        leaf: &[u8; 32],
+        proof: &BoundedVec<[u8; 32]>,
+        leaf_index: usize,
+    ) -> Result<bool, ReferenceMerkleTreeError> {
+        if leaf_index >= self.capacity {
+            return Err(ReferenceMerkleTreeError::LeafDoesNotExist(leaf_index));
+        }
+        if proof.len() != self.height {
+            return Err(ReferenceMerkleTreeError::InvalidProofLength(
+                proof.len(),
+                self.height,
+            ));
+        }
+        let mut computed_hash = *leaf;
+        let mut current_index = leaf_index;
+        for sibling_hash in proof.iter() {
+            computed_hash = if current_index % 2 == 0 {
+                H::hashv(&[&computed_hash, sibling_hash])?
+            } else {
+                H::hashv(&[sibling_hash, &computed_hash])?
+            };
+            current_index /= 2;
+        }
+        Ok(computed_hash == self.root()?)
+    }
+
+    /// Applies any Merkle tree event emitted for a state Merkle tree.
+    /// Events which have already been applied are skipped, which makes it
+    /// possible to rebuild the tree by replaying overlapping event streams.
+    pub fn apply_event(&mut self, event: &MerkleTreeEvent) -> Result<(), ReferenceMerkleTreeError> {
+        match event {
+            MerkleTreeEvent::V1(event) => self.apply_changelog_event(event),
+            MerkleTreeEvent::V2(event) => self.apply_nullifier_event(event),
+            MerkleTreeEvent::V3(_) => Err(ReferenceMerkleTreeError::UnsupportedEvent),
+        }
+    }
+
+    /// Applies a changelog event (appends). Leaves are applied in order,
+    /// then the paths of all changelog entries are compared with the stored
+    /// nodes. Batch appends emit the paths of preceding leaves with the nodes
+    /// of the final state of the batch, their roots are intermediate hashes,
+    /// so only the root of the last path is compared. An event which doesn't
+    /// match the tree is not applied.
+    pub fn apply_changelog_event(
+        &mut self,
+        event: &ChangelogEvent,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| {
+            let mut applied = false;
+            for (i, path) in event.paths.iter().enumerate() {
+                let sequence_number = event.seq as usize + i;
+                let leaf_index = tree.path_leaf_index(path, sequence_number)?;
+                if !tree.check_sequence_number(sequence_number)? {
+                    continue;
+                }
+                tree.set_leaf(&path[0].node, leaf_index, sequence_number)?;
+                tree.next_index = tree.next_index.max(leaf_index + 1);
+                tree.sequence_number = sequence_number;
+                applied = true;
+            }
+            if !applied {
+                return Ok(());
+            }
+            for (i, path) in event.paths.iter().enumerate() {
+                let sequence_number = event.seq as usize + i;
+                let leaf_index = tree.path_leaf_index(path, sequence_number)?;
+                let levels = if i == event.paths.len() - 1 {
+                    tree.height + 1
+                } else {
+                    tree.height
+                };
+                for (level, path_node) in path.iter().enumerate().take(levels) {
+                    if tree.get_node(level, leaf_index >> level)? != path_node.node {
+                        return Err(ReferenceMerkleTreeError::InvalidEvent(sequence_number));
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Applies a nullifier event, nullified leaves are replaced with zero
+    /// bytes.
+    pub fn apply_nullifier_event(
+        &mut self,
+        event: &NullifierEvent,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.operation(|tree| {
+            for (i, leaf_index) in event.nullified_leaves_indices.iter().enumerate() {
+                let sequence_number = event.seq as usize + i;
+                if !tree.check_sequence_number(sequence_number)? {
+                    continue;
+                }
+                let leaf_index = *leaf_index as usize;
+                if leaf_index >= tree.next_index {
+                    return Err(ReferenceMerkleTreeError::LeafDoesNotExist(leaf_index));
+                }
+                tree.set_leaf(&H::zero_bytes()[0], leaf_index, sequence_number)?;
+                tree.sequence_number = sequence_number;
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns a read-only view of the tree at `sequence_number`.
+    pub fn snapshot(
+        &self,
+        sequence_number: usize,
+    ) -> Result<SparseMerkleTreeSnapshot<'_, H, S>, ReferenceMerkleTreeError> {
+        self.check_snapshot_sequence_number(sequence_number)?;
+        let entries = self.store.journal_entries_after(sequence_number as u64)?;
+        let mut next_index = self.next_index;
+        let mut nodes = HashMap::new();
+        // The earliest entries hold the state at `sequence_number`.
+        for entry in entries.iter().rev() {
+            match *entry {
+                JournalEntry::Operation {
+                    next_index: previous_next_index,
+                    ..
+                } => next_index = previous_next_index as usize,
+                JournalEntry::Node {
+                    node_index, node, ..
+                } => {
+                    nodes.insert(node_index, node);
+                }
+            }
+        }
+        Ok(SparseMerkleTreeSnapshot {
+            tree: self,
+            sequence_number,
+            next_index,
+            nodes,
+        })
+    }
+
+    /// Rolls the tree back to `sequence_number`.
+    pub fn rollback(&mut self, sequence_number: usize) -> Result<(), ReferenceMerkleTreeError> {
+        self.check_snapshot_sequence_number(sequence_number)?;
+        self.revert(sequence_number)?;
+        self.put_metadata()?;
+        self.store.commit()
+    }
+
+    /// Discards the history up to `sequence_number`, snapshots of earlier
+    /// sequence numbers are no longer possible.
+    pub fn prune_history(
+        &mut self,
+        sequence_number: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        self.check_snapshot_sequence_number(sequence_number)?;
+        self.store.prune_journal(sequence_number as u64)?;
+        self.oldest_sequence_number = sequence_number;
+        self.put_metadata()?;
+        self.store.commit()
+    }
+
+    /// Runs an operation and commits its writes. If the operation fails, its
+    /// writes are reverted with the journal.
+    fn operation<F>(&mut self, f: F) -> Result<(), ReferenceMerkleTreeError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), ReferenceMerkleTreeError>,
+    {
+        let sequence_number = self.sequence_number;
+        match f(self) {
+            Ok(()) => {
+                self.put_metadata()?;
+                self.store.commit()
+            }
+            Err(e) => {
+                self.revert(sequence_number)?;
+                self.put_metadata()?;
+                self.store.commit()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Reverts all operations after `sequence_number` and removes them from
+    /// the journal.
+    fn revert(&mut self, sequence_number: usize) -> Result<(), ReferenceMerkleTreeError> {
+        let entries = self.store.journal_entries_after(sequence_number as u64)?;
+        for entry in entries.iter().rev() {
+            match *entry {
+                JournalEntry::Operation { next_index, .. } => {
+                    self.next_index = next_index as usize;
+                }
+                JournalEntry::Node {
+                    node_index,
+                    node: Some(node),
+                    ..
+                } => self.store.put_node(node_index, &node)?,
+                JournalEntry::Node {
+                    node_index,
+                    node: None,
+                    ..
+                } => self.store.remove_node(node_index)?,
+            }
+        }
+        self.store.truncate_journal(sequence_number as u64)?;
+        self.sequence_number = sequence_number;
+        Ok(())
+    }
+
+    /// Checks the node indices of a changelog path and returns the index of
+    /// its leaf.
+    fn path_leaf_index(
+        &self,
+        path: &[PathNode],
+        sequence_number: usize,
+    ) -> Result<usize, ReferenceMerkleTreeError> {
+        let leaf = path
+            .first()
+            .ok_or(ReferenceMerkleTreeError::InvalidEvent(sequence_number))?;
+        if path.len() != self.height + 1
+            || (leaf.index as usize) < self.capacity
+            || leaf.index as usize >= self.capacity * 2
+        {
+            return Err(ReferenceMerkleTreeError::InvalidEvent(sequence_number));
+        }
+        let leaf_index = leaf.index as usize - self.capacity;
+        for (level, path_node) in path.iter().enumerate() {
+            if node_index(self.height, level, leaf_index >> level) != path_node.index as u64 {
+                return Err(ReferenceMerkleTreeError::InvalidEvent(sequence_number));
+            }
+        }
+        Ok(leaf_index)
+    }
+
+    /// Returns whether the operation with `sequence_number` has to be
+    /// applied, fails if operations are missing.
+    fn check_sequence_number(
+        &self,
+        sequence_number: usize,
+    ) -> Result<bool, ReferenceMerkleTreeError> {
+        if sequence_number <= self.sequence_number {
+            Ok(false)
+        } else if sequence_number == self.sequence_number + 1 {
+            Ok(true)
+        } else {
+            Err(ReferenceMerkleTreeError::SequenceNumberGap(
+                self.sequence_number + 1,
+                sequence_number,
+            ))
+        }
+    }
+
+    fn check_snapshot_sequence_number(
+        &self,
+        sequence_number: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        if sequence_number < self.oldest_sequence_number || sequence_number > self.sequence_number {
+            return Err(ReferenceMerkleTreeError::SnapshotUnavailable(
+                sequence_number,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sets the leaf and recomputes its path to the root. Every call is the
+    /// operation with `sequence_number`, it journals the next index of the
+    /// tree before the operation.
+    fn set_leaf(
+        &mut self,
+        leaf: &[u8; 32],
+        leaf_index: usize,
+        sequence_number: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        if leaf_index >= self.capacity {
+            return Err(ReferenceMerkleTreeError::LeafDoesNotExist(leaf_index));
+        }
+        self.store.append_journal_entry(&JournalEntry::Operation {
+            sequence_number: sequence_number as u64,
+            next_index: self.next_index as u64,
+        })?;
+        let mut node = *leaf;
+        let mut index = leaf_index;
+        for level in 0..=self.height {
+            if level > 0 {
+                let (left, right) = if index % 2 == 0 {
+                    (node, self.get_node(level - 1, index + 1)?)
+                } else {
+                    (self.get_node(level - 1, index - 1)?, node)
+                };
+                node = H::hashv(&[&left, &right])?;
+                index /= 2;
+            }
+            self.set_node(level, index, &node, sequence_number)?;
+        }
+        Ok(())
+    }
+
+    fn set_node(
+        &mut self,
+        level: usize,
+        index: usize,
+        node: &[u8; 32],
+        sequence_number: usize,
+    ) -> Result<(), ReferenceMerkleTreeError> {
+        let node_index = node_index(self.height, level, index);
+        let previous_node = self.store.get_node(node_index)?;
+        let node = if *node == H::zero_bytes()[level] {
+            None
+        } else {
+            Some(*node)
+        };
+        if previous_node == node {
+            return Ok(());
+        }
+        self.store.append_journal_entry(&JournalEntry::Node {
+            sequence_number: sequence_number as u64,
+            node_index,
+            node: previous_node,
+        })?;
+        match node {
+            Some(node) => self.store.put_node(node_index, &node),
+            None => self.store.remove_node(node_index),
+        }
+    }
+
+    fn put_metadata(&mut self) -> Result<(), ReferenceMerkleTreeError> {
+        self.store.put_metadata(&SparseMerkleTreeMetadata {
+            height: self.height as u64,
+            next_index: self.next_index as u64,
+            sequence_number: self.sequence_number as u64,
+            oldest_sequence_number: self.oldest_sequence_number as u64,
+        })
+    }
+}
+
+/// Read-only view of a [`SparseMerkleTree`] at a previous sequence number.
+#[derive(Debug)]
+pub struct SparseMerkleTreeSnapshot<'a, H, S>
+where
+    H: Hasher,
+    S: NodeStore,
+{
+    tree: &'a SparseMerkleTree<H, S>,
+    pub sequence_number: usize,
+    pub next_index: usize,
+    /// Nodes which changed after `sequence_number`, with their values at
+    /// `sequence_number`.
+    nodes: HashMap<u64, Option<[u8; 32]>>,
+}
+
+impl<'a, H, S> SparseMerkleTreeSnapshot<'a, H, S>
+where
+    H: Hasher,
+    S: NodeStore,
+{
+    pub fn root(&self) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        self.get_node(self.tree.height, 0)
+    }
+
+    pub fn leaf(&self, leaf_index: usize) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        self.get_node(0, leaf_index)
+    }
+
+    pub fn get_node(
+        &self,
+        level: usize,
+        index: usize,
+    ) -> Result<[u8; 32], ReferenceMerkleTreeError> {
+        match self.nodes.get(&node_index(self.tree.height, level, index)) {
+            Some(node) => Ok(node.unwrap_or(H::zero_bytes()[level])),
+            None => self.tree.get_node(level, index),
+        }
+    }
+
+    pub fn get_proof_of_leaf(
+        &self,
+        leaf_index: usize,
+    ) -> Result<BoundedVec<[u8; 32]>, ReferenceMerkleTreeError> {
+        get_proof_of_leaf(self.tree.height, leaf_index, |level, index| {
+            self.get_node(level, index)
+        })
+    }
+}
+
+/// Index of a node in the binary heap layout, the root has index 1 and
+/// children of node `i` have indices `2i` and `2i + 1`.
+pub fn node_index(height: usize, level: usize, index: usize) -> u64 {
+    (1u64 << (height - level)) + index as u64
+}
+
+fn get_proof_of_leaf<F>(
+    height: usize,
+    mut index: usize,
+    get_node: F,
+) -> Result<BoundedVec<[u8; 32]>, ReferenceMerkleTreeError>
+where
+    F: Fn(usize, usize) -> Result<[u8; 32], ReferenceMerkleTreeError>,
+{
+    if index >= 1 << height {
+        return Err(ReferenceMerkleTreeError::LeafDoesNotExist(index));
+    }
+    let mut proof = BoundedVec::with_capacity(height);
+    for level in 0..height {
+        let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
+        proof.push(get_node(level, sibling_index)?)?;
+        index /= 2;
+    }
+    Ok(proof)
+}
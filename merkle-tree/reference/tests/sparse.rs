@@ -0,0 +1,437 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use light_concurrent_merkle_tree::{
+    event::{MerkleTreeEvent, NullifierEvent},
+    ConcurrentMerkleTree,
+};
+use light_hasher::{Hasher, Keccak, Poseidon};
+use light_merkle_tree_reference::{
+    node_store::{FileNodeStore, JournalEntry, MemoryNodeStore, NodeStore},
+    sparse::SparseMerkleTree,
+    MerkleTree, ReferenceMerkleTreeError,
+};
+
+const HEIGHT: usize = 8;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!(
+        "light-sparse-merkle-tree-{}-{}-{}",
+        name,
+        std::process::id(),
+        nanos
+    ))
+}
+
+fn leaf(i: usize) -> [u8; 32] {
+    let mut leaf = [0u8; 32];
+    leaf[24..].copy_from_slice(&(i as u64 + 1).to_be_bytes());
+    leaf
+}
+
+/// Tests:
+/// 1. roots, leaves and proofs equal the non-sparse reference tree after
+///    appends and updates
+/// 2. only non-zero nodes are stored
+fn sparse_equivalence<H, S>(store: S)
+where
+    H: Hasher,
+    S: NodeStore,
+{
+    let mut sparse_tree = SparseMerkleTree::<H, S>::new(store, HEIGHT).unwrap();
+    let mut reference_tree = MerkleTree::<H>::new(HEIGHT, 0);
+    assert_eq!(sparse_tree.root().unwrap(), reference_tree.root());
+
+    for i in 0..20 {
+        sparse_tree.append(&leaf(i)).unwrap();
+        reference_tree.append(&leaf(i)).unwrap();
+        assert_eq!(sparse_tree.root().unwrap(), reference_tree.root());
+    }
+    for i in (0..20).step_by(3) {
+        sparse_tree.update(&leaf(100 + i), i).unwrap();
+        reference_tree.update(&leaf(100 + i), i).unwrap();
+        assert_eq!(sparse_tree.root().unwrap(), reference_tree.root());
+    }
+    // Nullify a leaf.
+    sparse_tree.update(&[0u8; 32], 1).unwrap();
+    reference_tree.update(&[0u8; 32], 1).unwrap();
+    assert_eq!(sparse_tree.root().unwrap(), reference_tree.root());
+    assert_eq!(sparse_tree.sequence_number, reference_tree.sequence_number);
+    assert_eq!(sparse_tree.next_index, reference_tree.rightmost_index);
+
+    for i in 0..32 {
+        assert_eq!(sparse_tree.leaf(i).unwrap(), reference_tree.leaf(i));
+        let proof = sparse_tree.get_proof_of_leaf(i).unwrap();
+        assert_eq!(
+            proof.as_slice(),
+            reference_tree
+                .get_proof_of_leaf(i, true)
+                .unwrap()
+                .as_slice()
+        );
+        assert!(sparse_tree
+            .verify(&sparse_tree.leaf(i).unwrap(), &proof, i)
+            .unwrap());
+    }
+    assert!(matches!(
+        sparse_tree.update(&leaf(0), 20),
+        Err(ReferenceMerkleTreeError::LeafDoesNotExist(20))
+    ));
+}
+
+#[test]
+fn test_sparse_equivalence_memory_keccak() {
+    let store = MemoryNodeStore::default();
+    sparse_equivalence::<Keccak, _>(store);
+}
+
+#[test]
+fn test_sparse_equivalence_memory_poseidon() {
+    sparse_equivalence::<Poseidon, _>(MemoryNodeStore::default());
+}
+
+#[test]
+fn test_sparse_equivalence_file_keccak() {
+    let dir = temp_dir("equivalence");
+    sparse_equivalence::<Keccak, _>(FileNodeStore::open(&dir).unwrap());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_sparse_only_stores_non_zero_nodes() {
+    let mut tree =
+        SparseMerkleTree::<Keccak, MemoryNodeStore>::new(MemoryNodeStore::default(), 26).unwrap();
+    tree.append(&leaf(0)).unwrap();
+    // One leaf and its path up to the root.
+    assert_eq!(tree.store().num_nodes(), 27);
+    tree.update(&[0u8; 32], 0).unwrap();
+    assert_eq!(tree.store().num_nodes(), 0);
+    assert_eq!(tree.root().unwrap(), Keccak::zero_bytes()[26]);
+}
+
+/// Tests:
+/// 1. the tree is restored after reopening the store
+/// 2. opening with a different height fails
+/// 3. writes which are not committed are lost after reopening the store
+#[test]
+fn test_sparse_persistence() {
+    let dir = temp_dir("persistence");
+    let root = {
+        let store = FileNodeStore::open(&dir).unwrap();
+        let mut tree = SparseMerkleTree::<Keccak, _>::new(store, HEIGHT).unwrap();
+        for i in 0..10 {
+            tree.append(&leaf(i)).unwrap();
+        }
+        tree.flush().unwrap();
+        tree.root().unwrap()
+    };
+    let store = FileNodeStore::open(&dir).unwrap();
+    assert!(matches!(
+        SparseMerkleTree::<Keccak, _>::new(store, HEIGHT),
+        Err(ReferenceMerkleTreeError::StoreNotEmpty)
+    ));
+    let store = FileNodeStore::open(&dir).unwrap();
+    assert!(matches!(
+        SparseMerkleTree::<Keccak, _>::open_or_new(store, HEIGHT + 1),
+        Err(ReferenceMerkleTreeError::HeightMismatch(HEIGHT, _))
+    ));
+    let store = FileNodeStore::open(&dir).unwrap();
+    let mut tree = SparseMerkleTree::<Keccak, _>::open_or_new(store, HEIGHT).unwrap();
+    assert_eq!(tree.root().unwrap(), root);
+    assert_eq!(tree.next_index, 10);
+    assert_eq!(tree.sequence_number, 10);
+    // The history survives reopening too.
+    tree.append(&leaf(10)).unwrap();
+    assert_eq!(tree.snapshot(10).unwrap().root().unwrap(), root);
+    let root = tree.root().unwrap();
+    drop(tree);
+
+    // 3. writes which are not committed are lost after reopening the store
+    {
+        let mut store = FileNodeStore::open(&dir).unwrap();
+        store.put_node(1, &[1u8; 32]).unwrap();
+        store
+            .append_journal_entry(&JournalEntry::Operation {
+                sequence_number: 12,
+                next_index: 11,
+            })
+            .unwrap();
+        assert_eq!(store.get_node(1).unwrap(), Some([1u8; 32]));
+    }
+    let store = FileNodeStore::open(&dir).unwrap();
+    let tree = SparseMerkleTree::<Keccak, _>::open(store).unwrap();
+    assert_eq!(tree.root().unwrap(), root);
+    assert!(tree.store().journal_entries_after(11).unwrap().is_empty());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Tests:
+/// 1. snapshots return roots, leaves and proofs of previous sequence numbers
+/// 2. rollback restores the tree at a previous sequence number
+/// 3. pruned history isn't available
+/// 4. rollback of an append which doesn't change any node restores the next
+///    index
+fn sparse_snapshots<S: NodeStore>(store: S) {
+    let mut sparse_tree = SparseMerkleTree::<Keccak, S>::new(store, HEIGHT).unwrap();
+    let mut reference_tree = MerkleTree::<Keccak>::new(HEIGHT, 0);
+    let mut reference_trees = vec![reference_tree.clone()];
+    for i in 0..10 {
+        sparse_tree.append(&leaf(i)).unwrap();
+        reference_tree.append(&leaf(i)).unwrap();
+        reference_trees.push(reference_tree.clone());
+    }
+    for i in 0..5 {
+        sparse_tree.update(&leaf(50 + i), i * 2).unwrap();
+        reference_tree.update(&leaf(50 + i), i * 2).unwrap();
+        reference_trees.push(reference_tree.clone());
+    }
+
+    // 1. snapshots return roots, leaves and proofs of previous sequence numbers
+    for (sequence_number, reference_tree) in reference_trees.iter().enumerate() {
+        let snapshot = sparse_tree.snapshot(sequence_number).unwrap();
+        assert_eq!(snapshot.root().unwrap(), reference_tree.root());
+        assert_eq!(snapshot.next_index, reference_tree.rightmost_index);
+        for i in 0..12 {
+            assert_eq!(snapshot.leaf(i).unwrap(), reference_tree.leaf(i));
+            assert_eq!(
+                snapshot.get_proof_of_leaf(i).unwrap().to_vec(),
+                reference_tree.get_proof_of_leaf(i, true).unwrap().to_vec()
+            );
+        }
+    }
+    assert!(matches!(
+        sparse_tree.snapshot(16),
+        Err(ReferenceMerkleTreeError::SnapshotUnavailable(16))
+    ));
+
+    // 2. rollback restores the tree at a previous sequence number
+    sparse_tree.rollback(12).unwrap();
+    assert_eq!(sparse_tree.root().unwrap(), reference_trees[12].root());
+    assert_eq!(sparse_tree.sequence_number, 12);
+    assert_eq!(sparse_tree.next_index, 10);
+    sparse_tree.rollback(7).unwrap();
+    assert_eq!(sparse_tree.root().unwrap(), reference_trees[7].root());
+    assert_eq!(sparse_tree.next_index, 7);
+    // Continue from the rolled back state.
+    let mut reference_tree = reference_trees[7].clone();
+    sparse_tree.append(&leaf(200)).unwrap();
+    reference_tree.append(&leaf(200)).unwrap();
+    assert_eq!(sparse_tree.root().unwrap(), reference_tree.root());
+
+    // 3. pruned history isn't available
+    sparse_tree.prune_history(5).unwrap();
+    assert!(matches!(
+        sparse_tree.snapshot(4),
+        Err(ReferenceMerkleTreeError::SnapshotUnavailable(4))
+    ));
+    assert_eq!(
+        sparse_tree.snapshot(5).unwrap().root().unwrap(),
+        reference_trees[5].root()
+    );
+
+    // 4. rollback of an append which doesn't change any node restores the
+    //    next index
+    let root = sparse_tree.root().unwrap();
+    let sequence_number = sparse_tree.sequence_number;
+    sparse_tree.append(&[0u8; 32]).unwrap();
+    assert_eq!(sparse_tree.root().unwrap(), root);
+    assert_eq!(sparse_tree.next_index, 9);
+    assert_eq!(sparse_tree.snapshot(sequence_number).unwrap().next_index, 8);
+    sparse_tree.rollback(sequence_number).unwrap();
+    assert_eq!(sparse_tree.next_index, 8);
+    assert_eq!(sparse_tree.root().unwrap(), root);
+}
+
+#[test]
+fn test_sparse_snapshots_memory() {
+    sparse_snapshots(MemoryNodeStore::default());
+}
+
+#[test]
+fn test_sparse_snapshots_file() {
+    let dir = temp_dir("snapshots");
+    sparse_snapshots(FileNodeStore::open(&dir).unwrap());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Tests:
+/// 1. the tree is rebuilt from changelog and nullifier events
+/// 2. replayed events are skipped
+/// 3. events with a sequence number gap fail
+/// 4. events which don't match the tree fail and are not applied
+/// 5. batch append events with a mismatching path of a preceding leaf fail
+#[test]
+fn test_sparse_rebuild_from_events() {
+    let mut concurrent_tree =
+        ConcurrentMerkleTree::<Poseidon, HEIGHT>::new(HEIGHT, 64, 64, 0).unwrap();
+    concurrent_tree.init().unwrap();
+    let mut events = Vec::new();
+
+    for i in 0..10 {
+        let (changelog_index, sequence_number) = concurrent_tree.append(&leaf(i)).unwrap();
+        events.push(
+            concurrent_tree
+                .get_changelog_event([0u8; 32], changelog_index, sequence_number, 1)
+                .unwrap(),
+        );
+    }
+    let leaves = [leaf(20), leaf(21), leaf(22)];
+    let leaves = leaves.iter().collect::<Vec<_>>();
+    let (changelog_index, sequence_number) =
+        concurrent_tree.append_batch(leaves.as_slice()).unwrap();
+    events.push(
+        concurrent_tree
+            .get_changelog_event([0u8; 32], changelog_index, sequence_number, 3)
+            .unwrap(),
+    );
+
+    // Nullify leaves 2 and 5 in the concurrent tree, proofs are taken from a
+    // sparse tree mirroring it.
+    let mut mirror =
+        SparseMerkleTree::<Poseidon, _>::new(MemoryNodeStore::default(), HEIGHT).unwrap();
+    for event in events.iter() {
+        mirror.apply_event(event).unwrap();
+    }
+    let first_sequence_number = concurrent_tree.sequence_number() + 1;
+    for leaf_index in [2, 5] {
+        let mut proof = mirror.get_proof_of_leaf(leaf_index).unwrap();
+        concurrent_tree
+            .update(
+                concurrent_tree.changelog_index(),
+                &mirror.leaf(leaf_index).unwrap(),
+                &[0u8; 32],
+                leaf_index,
+                &mut proof,
+            )
+            .unwrap();
+        mirror.update(&[0u8; 32], leaf_index).unwrap();
+    }
+    events.push(MerkleTreeEvent::V2(NullifierEvent {
+        id: [0u8; 32],
+        nullified_leaves_indices: vec![2, 5],
+        seq: first_sequence_number as u64,
+    }));
+
+    // 1. the tree is rebuilt from changelog and nullifier events
+    let dir = temp_dir("events");
+    let mut tree =
+        SparseMerkleTree::<Poseidon, _>::new(FileNodeStore::open(&dir).unwrap(), HEIGHT).unwrap();
+    for event in events.iter() {
+        tree.apply_event(event).unwrap();
+    }
+    assert_eq!(tree.root().unwrap(), concurrent_tree.root());
+    assert_eq!(tree.next_index, concurrent_tree.next_index());
+    assert_eq!(tree.sequence_number, concurrent_tree.sequence_number());
+
+    // 2. replayed events are skipped
+    for event in events.iter() {
+        tree.apply_event(event).unwrap();
+    }
+    assert_eq!(tree.root().unwrap(), concurrent_tree.root());
+
+    // 3. events with a sequence number gap fail
+    let mut gap_tree =
+        SparseMerkleTree::<Poseidon, _>::new(MemoryNodeStore::default(), HEIGHT).unwrap();
+    assert!(matches!(
+        gap_tree.apply_event(&events[1]),
+        Err(ReferenceMerkleTreeError::SequenceNumberGap(1, 2))
+    ));
+
+    // 4. events which don't match the tree fail and are not applied
+    let mut invalid_event = match concurrent_tree
+        .get_changelog_event([0u8; 32], 1, 1, 1)
+        .unwrap()
+    {
+        MerkleTreeEvent::V1(event) => event,
+        _ => unreachable!(),
+    };
+    invalid_event.paths[0][HEIGHT].node = [1u8; 32];
+    let mut invalid_tree =
+        SparseMerkleTree::<Poseidon, _>::new(MemoryNodeStore::default(), HEIGHT).unwrap();
+    assert!(matches!(
+        invalid_tree.apply_changelog_event(&invalid_event),
+        Err(ReferenceMerkleTreeError::InvalidEvent(1))
+    ));
+    assert_eq!(invalid_tree.sequence_number, 0);
+    assert_eq!(invalid_tree.root().unwrap(), Poseidon::zero_bytes()[HEIGHT]);
+
+    // 5. batch append events with a mismatching path of a preceding leaf fail
+    let mut invalid_event = match concurrent_tree
+        .get_changelog_event([0u8; 32], changelog_index, sequence_number, 3)
+        .unwrap()
+    {
+        MerkleTreeEvent::V1(event) => event,
+        _ => unreachable!(),
+    };
+    assert_eq!(invalid_event.paths.len(), 3);
+    invalid_event.paths[0][1].node = [1u8; 32];
+    let mut invalid_tree =
+        SparseMerkleTree::<Poseidon, _>::new(MemoryNodeStore::default(), HEIGHT).unwrap();
+    for event in events[..10].iter() {
+        invalid_tree.apply_event(event).unwrap();
+    }
+    let root = invalid_tree.root().unwrap();
+    assert!(matches!(
+        invalid_tree.apply_changelog_event(&invalid_event),
+        Err(ReferenceMerkleTreeError::InvalidEvent(11))
+    ));
+    assert_eq!(invalid_tree.sequence_number, 10);
+    assert_eq!(invalid_tree.next_index, 10);
+    assert_eq!(invalid_tree.root().unwrap(), root);
+    invalid_tree.apply_event(&events[10]).unwrap();
+    assert_eq!(invalid_tree.next_index, 13);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Tests:
+/// 1. a batch append matches the concurrent tree, with a single sequence
+///    number
+/// 2. rollback of a batch append restores the tree before it
+/// 3. a batch append which doesn't fit into the tree fails and is not applied
+#[test]
+fn test_sparse_append_batch_subtrees() {
+    let mut concurrent_tree =
+        ConcurrentMerkleTree::<Poseidon, HEIGHT>::new(HEIGHT, 64, 64, 0).unwrap();
+    concurrent_tree.init().unwrap();
+    let mut tree =
+        SparseMerkleTree::<Poseidon, _>::new(MemoryNodeStore::default(), HEIGHT).unwrap();
+    concurrent_tree.append(&leaf(0)).unwrap();
+    tree.append(&leaf(0)).unwrap();
+    let root = tree.root().unwrap();
+
+    // 1. a batch append matches the concurrent tree, with a single sequence
+    //    number
+    let leaves = (1..12).map(leaf).collect::<Vec<_>>();
+    let leaves = leaves.iter().collect::<Vec<_>>();
+    concurrent_tree
+        .append_batch_subtrees(leaves.as_slice())
+        .unwrap();
+    tree.append_batch_subtrees(leaves.as_slice()).unwrap();
+    assert_eq!(tree.root().unwrap(), concurrent_tree.root());
+    assert_eq!(tree.next_index, concurrent_tree.next_index());
+    assert_eq!(tree.sequence_number, concurrent_tree.sequence_number());
+    assert_eq!(tree.sequence_number, 2);
+
+    // 2. rollback of a batch append restores the tree before it
+    tree.rollback(1).unwrap();
+    assert_eq!(tree.root().unwrap(), root);
+    assert_eq!(tree.next_index, 1);
+    assert_eq!(tree.sequence_number, 1);
+
+    // 3. a batch append which doesn't fit into the tree fails and is not
+    //    applied
+    let leaves = (1..(1 << HEIGHT) + 1).map(leaf).collect::<Vec<_>>();
+    let leaves = leaves.iter().collect::<Vec<_>>();
+    assert!(matches!(
+        tree.append_batch_subtrees(leaves.as_slice()),
+        Err(ReferenceMerkleTreeError::TreeFull)
+    ));
+    assert_eq!(tree.root().unwrap(), root);
+    assert_eq!(tree.next_index, 1);
+}
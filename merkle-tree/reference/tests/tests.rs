@@ -730,3 +730,94 @@ fn test_sequence_number() {
     merkle_tree.update(&leaf2, 0).unwrap();
     assert_eq!(merkle_tree.sequence_number, 2);
 }
+
+fn assert_trees_eq<H: Hasher>(merkle_tree: &MerkleTree<H>, expected: &MerkleTree<H>) {
+    assert_eq!(merkle_tree.layers, expected.layers);
+    assert_eq!(merkle_tree.roots, expected.roots);
+    assert_eq!(merkle_tree.rightmost_index, expected.rightmost_index);
+    assert_eq!(merkle_tree.sequence_number, expected.sequence_number);
+}
+
+/// Tests:
+/// 1. reverting appends restores the tree before them
+/// 2. reverting an update restores the previous leaf
+/// 3. appends after a revert match appends on a tree without reverts
+/// 4. reverting an append on an empty tree fails
+#[test]
+fn test_revert() {
+    let leaves = (0..7u8)
+        .map(|i| Poseidon::hash(&[i; 32]).unwrap())
+        .collect::<Vec<_>>();
+    let mut merkle_tree = MerkleTree::<Poseidon>::new(4, 0);
+    for leaf in leaves[..3].iter() {
+        merkle_tree.append(leaf).unwrap();
+    }
+    let snapshot = merkle_tree.clone();
+
+    // 1. reverting appends restores the tree before them
+    for leaf in leaves[3..].iter() {
+        merkle_tree.append(leaf).unwrap();
+    }
+    for _ in leaves[3..].iter() {
+        merkle_tree.revert_append().unwrap();
+    }
+    assert_trees_eq(&merkle_tree, &snapshot);
+
+    // 2. reverting an update restores the previous leaf
+    merkle_tree.update(&[0u8; 32], 1).unwrap();
+    merkle_tree.revert_update(&leaves[1], 1).unwrap();
+    assert_trees_eq(&merkle_tree, &snapshot);
+
+    // 3. appends after a revert match appends on a tree without reverts
+    merkle_tree.append(&leaves[6]).unwrap();
+    merkle_tree.revert_append().unwrap();
+    merkle_tree.append(&leaves[3]).unwrap();
+    let mut expected = snapshot.clone();
+    expected.append(&leaves[3]).unwrap();
+    assert_trees_eq(&merkle_tree, &expected);
+
+    // 4. reverting an append on an empty tree fails
+    let mut merkle_tree = MerkleTree::<Poseidon>::new(4, 0);
+    assert!(merkle_tree.revert_append().is_err());
+}
+
+/// Tests:
+/// 1. a batch append pushes one root and increments the sequence number once
+/// 2. the batch results in the same layers as single appends
+/// 3. reverting a batch append restores the tree before it
+/// 4. reverting more leaves than the tree contains fails
+#[test]
+fn test_append_batch_subtrees() {
+    let leaves = (0..7u8)
+        .map(|i| Poseidon::hash(&[i; 32]).unwrap())
+        .collect::<Vec<_>>();
+    let mut merkle_tree = MerkleTree::<Poseidon>::new(4, 0);
+    merkle_tree.append(&leaves[0]).unwrap();
+    let snapshot = merkle_tree.clone();
+
+    // 1. a batch append pushes one root and increments the sequence number
+    //    once
+    merkle_tree
+        .append_batch_subtrees(&leaves[1..].iter().collect::<Vec<_>>())
+        .unwrap();
+    assert_eq!(merkle_tree.roots.len(), snapshot.roots.len() + 1);
+    assert_eq!(merkle_tree.sequence_number, snapshot.sequence_number + 1);
+
+    // 2. the batch results in the same layers as single appends
+    let mut expected = snapshot.clone();
+    for leaf in leaves[1..].iter() {
+        expected.append(leaf).unwrap();
+    }
+    assert_eq!(merkle_tree.layers, expected.layers);
+    assert_eq!(merkle_tree.root(), expected.root());
+    assert_eq!(merkle_tree.rightmost_index, expected.rightmost_index);
+
+    // 3. reverting a batch append restores the tree before it
+    merkle_tree
+        .revert_append_batch_subtrees(leaves.len() - 1)
+        .unwrap();
+    assert_trees_eq(&merkle_tree, &snapshot);
+
+    // 4. reverting more leaves than the tree contains fails
+    assert!(merkle_tree.revert_append_batch_subtrees(2).is_err());
+}
@@ -52,4 +52,12 @@ pub enum AccountCompressionErrorCode {
     UnsupportedCloseThreshold,
     #[msg("InvalidAccountBalance")]
     InvalidAccountBalance,
+    #[msg("Queue capacity has to be larger than the current one")]
+    InvalidQueueCapacity,
+    #[msg("Event data exceeds the max instruction data of a cpi.")]
+    EventTooLarge,
+    #[msg("Queue is not being migrated to the provided queue")]
+    QueueNotMigrating,
+    #[msg("Queue has already been rolled over or migrated")]
+    QueueAlreadyRolledOver,
 }
@@ -63,7 +63,7 @@ pub fn process_append_leaves_to_merkle_trees<'a, 'b, 'c: 'info, 'info>(
 /// 4. check Merkle tree account discriminator (AccountLoader)
 /// 5. check signer elibility to write into Merkle tree account
 ///    (check_signer_is_registered_or_authority)
-/// 6. append batch to Merkle tree
+/// 6. append batch to Merkle tree (by aligned subtrees)
 /// 7. transfer rollover fee
 /// 8. get next Merkle tree account
 fn batch_append_leaves<'a, 'c: 'info, 'info>(
@@ -108,8 +108,9 @@ fn batch_append_leaves<'a, 'c: 'info, 'info>(
             let mut merkle_tree = merkle_tree_acc_info.try_borrow_mut_data()?;
             let mut merkle_tree = state_merkle_tree_from_bytes_zero_copy_mut(&mut merkle_tree)?;
 
+            light_heap::bench_sbf_start!("acp_append_batch");
             merkle_tree
-                .append_batch(
+                .append_batch_subtrees(
                     leaves[start..end]
                         .iter()
                         .map(|x| &x.1)
@@ -117,6 +118,7 @@ fn batch_append_leaves<'a, 'c: 'info, 'info>(
                         .as_slice(),
                 )
                 .map_err(ProgramError::from)?;
+            light_heap::bench_sbf_end!("acp_append_batch");
 
             rollover_fee
         };
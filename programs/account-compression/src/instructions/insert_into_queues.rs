@@ -11,7 +11,6 @@ use crate::{
     QueueType, RegisteredProgram,
 };
 use anchor_lang::{prelude::*, solana_program::pubkey::Pubkey, ZeroCopy};
-use num_bigint::BigUint;
 
 #[derive(Accounts)]
 pub struct InsertIntoQueues<'info> {
@@ -102,9 +101,8 @@ pub fn process_insert_into_queues<'a, 'b, 'c: 'info, 'info, MerkleTreeAccount: O
             light_heap::bench_sbf_end!("acp_prep_insertion");
             light_heap::bench_sbf_start!("acp_insert_nf_into_queue");
             for element in queue_bundle.elements.iter() {
-                let element = BigUint::from_bytes_be(element.as_slice());
                 queue
-                    .insert(&element, sequence_number)
+                    .insert(element, sequence_number)
                     .map_err(ProgramError::from)?;
             }
             light_heap::bench_sbf_end!("acp_insert_nf_into_queue");
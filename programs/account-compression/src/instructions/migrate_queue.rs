@@ -0,0 +1,352 @@
+use crate::{
+    address_merkle_tree_from_bytes_zero_copy,
+    errors::AccountCompressionErrorCode,
+    state::queue::{
+        queue_from_bytes_zero_copy_init, queue_from_bytes_zero_copy_mut, QueueAccount,
+        QueueMigration,
+    },
+    state_merkle_tree_from_bytes_zero_copy,
+    utils::{
+        check_account::check_account_balance_is_rent_exempt,
+        check_signer_is_registered_or_authority::{
+            check_signer_is_registered_or_authority, GroupAccounts,
+        },
+        constants::QUEUE_MIGRATION_SEED,
+        transfer_lamports::transfer_lamports,
+    },
+    AddressMerkleTreeAccount, MerkleTreeMetadata, QueueType, RegisteredProgram,
+    StateMerkleTreeAccount,
+};
+use anchor_lang::{prelude::*, solana_program::pubkey::Pubkey, AccountsClose, Discriminator};
+use std::{
+    cell::{Ref, RefMut},
+    cmp, mem,
+};
+
+#[derive(Accounts)]
+pub struct MigrateQueue<'info> {
+    #[account(mut)]
+    /// Pays the rent of the queue migration account, which is refunded once
+    /// the migration is finished.
+    pub fee_payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    pub registered_program_pda: Option<Account<'info, RegisteredProgram>>,
+    /// CHECK: state or address Merkle tree, depending on the queue type.
+    /// Owner, discriminator and association with `old_queue` are checked in
+    /// the instruction.
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+    #[account(mut)]
+    pub old_queue: AccountLoader<'info, QueueAccount>,
+    #[account(zero)]
+    pub new_queue: AccountLoader<'info, QueueAccount>,
+    #[account(
+        init,
+        payer = fee_payer,
+        seeds = [QUEUE_MIGRATION_SEED, old_queue.key().to_bytes().as_slice()],
+        bump,
+        space = QueueMigration::LEN,
+    )]
+    pub queue_migration: Account<'info, QueueMigration>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> GroupAccounts<'info> for MigrateQueue<'info> {
+    fn get_authority(&self) -> &Signer<'info> {
+        &self.authority
+    }
+    fn get_registered_program_pda(&self) -> &Option<Account<'info, RegisteredProgram>> {
+        &self.registered_program_pda
+    }
+}
+
+#[derive(Accounts)]
+pub struct MigrateQueueElements<'info> {
+    #[account(mut)]
+    /// Receives the rent of the old queue and of the queue migration account
+    /// once the migration is finished.
+    pub fee_payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    pub registered_program_pda: Option<Account<'info, RegisteredProgram>>,
+    /// CHECK: state or address Merkle tree, depending on the queue type.
+    /// Owner, discriminator and association with `new_queue` are checked in
+    /// the instruction.
+    pub merkle_tree: AccountInfo<'info>,
+    #[account(mut)]
+    pub old_queue: AccountLoader<'info, QueueAccount>,
+    #[account(mut)]
+    pub new_queue: AccountLoader<'info, QueueAccount>,
+    #[account(
+        mut,
+        seeds = [QUEUE_MIGRATION_SEED, old_queue.key().to_bytes().as_slice()],
+        bump = queue_migration.bump,
+    )]
+    pub queue_migration: Account<'info, QueueMigration>,
+}
+
+impl<'info> GroupAccounts<'info> for MigrateQueueElements<'info> {
+    fn get_authority(&self) -> &Signer<'info> {
+        &self.authority
+    }
+    fn get_registered_program_pda(&self) -> &Option<Account<'info, RegisteredProgram>> {
+        &self.registered_program_pda
+    }
+}
+
+/// Starts the migration of a queue into a new queue with a larger capacity.
+/// The elements are moved by `migrate_queue_elements`, which has to be
+/// invoked until all buckets of the old queue are migrated.
+///
+/// Checks:
+/// 1. Signer is the queue owner or a registered program
+/// 2. Merkle tree and old queue are associated
+/// 3. Old queue is neither rolled over nor being migrated
+/// 4. New capacity is larger than the old one
+/// 5. New queue account is rent exempt
+/// Actions:
+/// 1. associate the Merkle tree with the new queue
+/// 2. initialize the new queue with the metadata of the old queue, without
+///    associating it with the Merkle tree, so that no element can be
+///    inserted before all elements of the old queue are migrated
+/// 3. detach the old queue from the Merkle tree, so that no element can be
+///    inserted into or nullified from it
+/// 4. initialize the queue migration account, which tracks the migrated
+///    buckets of the old queue
+pub fn process_migrate_queue<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, MigrateQueue<'info>>,
+    capacity: u16,
+) -> Result<()> {
+    let merkle_tree_key = ctx.accounts.merkle_tree.key();
+    let old_queue_key = ctx.accounts.old_queue.key();
+    let new_queue_key = ctx.accounts.new_queue.key();
+
+    let queue_metadata = {
+        let mut old_queue = ctx.accounts.old_queue.load_mut()?;
+        check_signer_is_registered_or_authority::<MigrateQueue, QueueAccount>(&ctx, &old_queue)?;
+        if old_queue.metadata.associated_merkle_tree != merkle_tree_key {
+            return err!(AccountCompressionErrorCode::MerkleTreeAndQueueNotAssociated);
+        }
+        let queue_metadata = old_queue.metadata;
+        old_queue.metadata.migrate(new_queue_key)?;
+        queue_metadata
+    };
+
+    let sequence_threshold = {
+        let old_queue = ctx.accounts.old_queue.to_account_info();
+        let mut old_queue = old_queue.try_borrow_mut_data()?;
+        let old_queue = unsafe { queue_from_bytes_zero_copy_mut(&mut old_queue)? };
+        if capacity as usize <= old_queue.hash_set.capacity {
+            msg!(
+                "New capacity {} has to be larger than the old capacity {}",
+                capacity,
+                old_queue.hash_set.capacity
+            );
+            return err!(AccountCompressionErrorCode::InvalidQueueCapacity);
+        }
+        old_queue.hash_set.sequence_threshold
+    };
+
+    let (queue_type, discriminator) = queue_type_and_discriminator(queue_metadata.queue_type)?;
+    {
+        let mut merkle_tree = merkle_tree_metadata_mut(&ctx.accounts.merkle_tree, discriminator)?;
+        merkle_tree.migrate_queue(old_queue_key, new_queue_key)?;
+    }
+
+    check_account_balance_is_rent_exempt(
+        &ctx.accounts.new_queue.to_account_info(),
+        QueueAccount::size(capacity as usize)?,
+    )?;
+
+    {
+        let mut new_queue = ctx.accounts.new_queue.load_init()?;
+        // The rollover fee is kept, the additional rent of the larger queue
+        // is not reflected in it.
+        new_queue.init(
+            queue_metadata.access_metadata,
+            queue_metadata.rollover_metadata,
+            Pubkey::default(),
+            queue_type,
+        );
+    }
+    ctx.accounts.queue_migration.set_inner(QueueMigration {
+        cursor: 0,
+        bump: ctx.bumps.queue_migration,
+    });
+
+    let new_queue = ctx.accounts.new_queue.to_account_info();
+    let mut new_queue = new_queue.try_borrow_mut_data()?;
+    unsafe {
+        queue_from_bytes_zero_copy_init(&mut new_queue, capacity as usize, sequence_threshold)?
+    };
+
+    Ok(())
+}
+
+/// Migrates the elements of up to `num_buckets` buckets of the old queue,
+/// starting from the cursor of the queue migration account, into the new
+/// queue. Once all buckets are migrated, the old queue and the queue
+/// migration account are closed.
+///
+/// Checks:
+/// 1. Signer is the queue owner or a registered program
+/// 2. Old queue is being migrated to the new queue
+/// 3. Merkle tree is associated with the new queue
+/// Actions:
+/// 1. copy valid elements, preserving their sequence numbers, expired
+///    elements are dropped
+/// 2. advance the cursor of the queue migration account
+/// 3. once the cursor reaches the capacity of the old queue, associate the
+///    new queue with the Merkle tree, transfer the rollover fees collected
+///    by the old queue to the new queue and close the old queue and the
+///    queue migration account, their rent is transferred to the fee payer
+pub fn process_migrate_queue_elements<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, MigrateQueueElements<'info>>,
+    num_buckets: u16,
+) -> Result<()> {
+    let merkle_tree_key = ctx.accounts.merkle_tree.key();
+    let new_queue_key = ctx.accounts.new_queue.key();
+
+    let queue_metadata = {
+        let old_queue = ctx.accounts.old_queue.load()?;
+        check_signer_is_registered_or_authority::<MigrateQueueElements, QueueAccount>(
+            &ctx, &old_queue,
+        )?;
+        if !old_queue.metadata.is_migrating_to(&new_queue_key) {
+            return err!(AccountCompressionErrorCode::QueueNotMigrating);
+        }
+        old_queue.metadata
+    };
+    let cursor = ctx.accounts.queue_migration.cursor as usize;
+
+    let (queue_type, discriminator) = queue_type_and_discriminator(queue_metadata.queue_type)?;
+    {
+        let merkle_tree = merkle_tree_metadata(&ctx.accounts.merkle_tree, discriminator)?;
+        if merkle_tree.associated_queue != new_queue_key {
+            return err!(AccountCompressionErrorCode::MerkleTreeAndQueueNotAssociated);
+        }
+    }
+    let sequence_number = merkle_tree_sequence_number(&ctx.accounts.merkle_tree, queue_type)?;
+
+    let (migrated, next_cursor, old_capacity) = {
+        let old_queue = ctx.accounts.old_queue.to_account_info();
+        let mut old_queue = old_queue.try_borrow_mut_data()?;
+        let old_queue = unsafe { queue_from_bytes_zero_copy_mut(&mut old_queue)? };
+
+        let new_queue = ctx.accounts.new_queue.to_account_info();
+        let mut new_queue = new_queue.try_borrow_mut_data()?;
+        let mut new_queue = unsafe { queue_from_bytes_zero_copy_mut(&mut new_queue)? };
+
+        let next_cursor = cmp::min(cursor + num_buckets as usize, old_queue.hash_set.capacity);
+        light_heap::bench_sbf_start!("acp_migrate_queue");
+        let migrated = old_queue
+            .migrate(&mut new_queue, cursor..next_cursor, sequence_number)
+            .map_err(ProgramError::from)?;
+        light_heap::bench_sbf_end!("acp_migrate_queue");
+        (migrated, next_cursor, old_queue.hash_set.capacity)
+    };
+    msg!(
+        "Migrated {} elements, buckets {}/{}",
+        migrated,
+        next_cursor,
+        old_capacity
+    );
+
+    ctx.accounts.queue_migration.cursor = next_cursor as u64;
+    if next_cursor < old_capacity {
+        return Ok(());
+    }
+
+    ctx.accounts
+        .new_queue
+        .load_mut()?
+        .metadata
+        .associated_merkle_tree = merkle_tree_key;
+
+    let old_queue = ctx.accounts.old_queue.to_account_info();
+    let rent = Rent::get()?.minimum_balance(old_queue.data_len());
+    let rollover_fees = old_queue.lamports().saturating_sub(rent);
+    transfer_lamports(
+        &old_queue,
+        &ctx.accounts.new_queue.to_account_info(),
+        rollover_fees,
+    )?;
+    ctx.accounts
+        .old_queue
+        .close(ctx.accounts.fee_payer.to_account_info())?;
+    ctx.accounts
+        .queue_migration
+        .close(ctx.accounts.fee_payer.to_account_info())?;
+
+    Ok(())
+}
+
+fn queue_type_and_discriminator(queue_type: u64) -> Result<(QueueType, [u8; 8])> {
+    match queue_type {
+        t if t == QueueType::NullifierQueue as u64 => Ok((
+            QueueType::NullifierQueue,
+            StateMerkleTreeAccount::DISCRIMINATOR,
+        )),
+        t if t == QueueType::AddressQueue as u64 => Ok((
+            QueueType::AddressQueue,
+            AddressMerkleTreeAccount::DISCRIMINATOR,
+        )),
+        _ => err!(AccountCompressionErrorCode::InvalidQueueType),
+    }
+}
+
+/// Returns the sequence number of a state Merkle tree for nullifier queues
+/// and of an address Merkle tree for address queues.
+fn merkle_tree_sequence_number(merkle_tree: &AccountInfo, queue_type: QueueType) -> Result<usize> {
+    let merkle_tree = merkle_tree.try_borrow_data()?;
+    let sequence_number = match queue_type {
+        QueueType::NullifierQueue => {
+            state_merkle_tree_from_bytes_zero_copy(&merkle_tree)?.sequence_number()
+        }
+        QueueType::AddressQueue => {
+            address_merkle_tree_from_bytes_zero_copy(&merkle_tree)?.sequence_number()
+        }
+    };
+    Ok(sequence_number)
+}
+
+/// Checks the owner and the discriminator of the given Merkle tree account
+/// data. State and address Merkle tree accounts share the same metadata
+/// layout.
+fn check_merkle_tree_account(
+    merkle_tree: &AccountInfo,
+    data: &[u8],
+    discriminator: [u8; 8],
+) -> Result<()> {
+    if merkle_tree.owner != &crate::ID {
+        return err!(ErrorCode::AccountOwnedByWrongProgram);
+    }
+    if data.len() < 8 + mem::size_of::<MerkleTreeMetadata>() {
+        return err!(ErrorCode::AccountDidNotDeserialize);
+    }
+    if data[..8] != discriminator {
+        return err!(ErrorCode::AccountDiscriminatorMismatch);
+    }
+    Ok(())
+}
+
+fn merkle_tree_metadata<'a, 'info>(
+    merkle_tree: &'a AccountInfo<'info>,
+    discriminator: [u8; 8],
+) -> Result<Ref<'a, MerkleTreeMetadata>> {
+    let data = merkle_tree.try_borrow_data()?;
+    check_merkle_tree_account(merkle_tree, &data, discriminator)?;
+    Ok(Ref::map(data, |data| {
+        bytemuck::from_bytes(&data[8..8 + mem::size_of::<MerkleTreeMetadata>()])
+    }))
+}
+
+fn merkle_tree_metadata_mut<'a, 'info>(
+    merkle_tree: &'a AccountInfo<'info>,
+    discriminator: [u8; 8],
+) -> Result<RefMut<'a, MerkleTreeMetadata>> {
+    let data = merkle_tree.try_borrow_mut_data()?;
+    check_merkle_tree_account(merkle_tree, &data, discriminator)?;
+    Ok(RefMut::map(data, |data| {
+        bytemuck::from_bytes_mut(&mut data[8..8 + mem::size_of::<MerkleTreeMetadata>()])
+    }))
+}
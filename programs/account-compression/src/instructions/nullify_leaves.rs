@@ -1,5 +1,5 @@
 use crate::{
-    emit_indexer_event,
+    emit_merkle_tree_event,
     errors::AccountCompressionErrorCode,
     state::{
         queue::{queue_from_bytes_zero_copy_mut, QueueAccount},
@@ -146,7 +146,7 @@ fn insert_nullifier<'a, 'c: 'info, 'info>(
         seq,
     };
     let nullify_event = MerkleTreeEvent::V2(nullify_event);
-    emit_indexer_event(nullify_event.try_to_vec()?, &ctx.accounts.log_wrapper)?;
+    emit_merkle_tree_event(&nullify_event, &ctx.accounts.log_wrapper)?;
     Ok(())
 }
 
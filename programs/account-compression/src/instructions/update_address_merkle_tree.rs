@@ -4,7 +4,7 @@ use light_indexed_merkle_tree::array::IndexedElement;
 use num_bigint::BigUint;
 
 use crate::{
-    address_merkle_tree_from_bytes_zero_copy_mut, emit_indexer_event,
+    address_merkle_tree_from_bytes_zero_copy_mut, emit_merkle_tree_event,
     errors::AccountCompressionErrorCode,
     from_vec,
     state::{queue_from_bytes_zero_copy_mut, QueueAccount},
@@ -128,8 +128,5 @@ pub fn process_update_address_merkle_tree<'info>(
         // thus the first seq number is final seq - 1.
         seq: merkle_tree.sequence_number() as u64 - 1,
     });
-    emit_indexer_event(
-        address_event.try_to_vec()?,
-        &ctx.accounts.log_wrapper.to_account_info(),
-    )
+    emit_merkle_tree_event(&address_event, &ctx.accounts.log_wrapper.to_account_info())
 }
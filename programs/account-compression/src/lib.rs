@@ -187,4 +187,22 @@ pub mod account_compression {
     ) -> Result<()> {
         process_rollover_state_merkle_tree_nullifier_queue_pair(ctx)
     }
+
+    /// Starts moving the elements of a queue into a new queue with a larger
+    /// capacity.
+    pub fn migrate_queue<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateQueue<'info>>,
+        capacity: u16,
+    ) -> Result<()> {
+        process_migrate_queue(ctx, capacity)
+    }
+
+    /// Moves the elements of the next `num_buckets` buckets of a queue which
+    /// is being migrated, closes the old queue once all are moved.
+    pub fn migrate_queue_elements<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateQueueElements<'info>>,
+        num_buckets: u16,
+    ) -> Result<()> {
+        process_migrate_queue_elements(ctx, num_buckets)
+    }
 }
@@ -10,6 +10,7 @@ use crate::{
     instruction::{
         InitializeAddressMerkleTreeAndQueue, InitializeStateMerkleTreeAndNullifierQueue,
     },
+    utils::constants::QUEUE_MIGRATION_SEED,
     AddressMerkleTreeConfig, AddressQueueConfig, NullifierQueueConfig, StateMerkleTreeConfig,
 };
 
@@ -108,3 +109,66 @@ pub fn create_initialize_address_merkle_tree_and_queue_instruction(
         data: instruction_data.data(),
     }
 }
+
+/// Returns the address of the account which tracks the migration of the
+/// given queue.
+pub fn get_queue_migration_pda(old_queue_pubkey: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[QUEUE_MIGRATION_SEED, old_queue_pubkey.to_bytes().as_slice()],
+        &crate::ID,
+    )
+    .0
+}
+
+pub fn create_migrate_queue_instruction(
+    fee_payer: Pubkey,
+    authority: Pubkey,
+    registered_program_pda: Option<Pubkey>,
+    merkle_tree_pubkey: Pubkey,
+    old_queue_pubkey: Pubkey,
+    new_queue_pubkey: Pubkey,
+    capacity: u16,
+) -> Instruction {
+    let instruction_data = crate::instruction::MigrateQueue { capacity };
+    let accounts = crate::accounts::MigrateQueue {
+        fee_payer,
+        authority,
+        registered_program_pda,
+        merkle_tree: merkle_tree_pubkey,
+        old_queue: old_queue_pubkey,
+        new_queue: new_queue_pubkey,
+        queue_migration: get_queue_migration_pda(&old_queue_pubkey),
+        system_program: system_program::ID,
+    };
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(Some(true)),
+        data: instruction_data.data(),
+    }
+}
+
+pub fn create_migrate_queue_elements_instruction(
+    fee_payer: Pubkey,
+    authority: Pubkey,
+    registered_program_pda: Option<Pubkey>,
+    merkle_tree_pubkey: Pubkey,
+    old_queue_pubkey: Pubkey,
+    new_queue_pubkey: Pubkey,
+    num_buckets: u16,
+) -> Instruction {
+    let instruction_data = crate::instruction::MigrateQueueElements { num_buckets };
+    let accounts = crate::accounts::MigrateQueueElements {
+        fee_payer,
+        authority,
+        registered_program_pda,
+        merkle_tree: merkle_tree_pubkey,
+        old_queue: old_queue_pubkey,
+        new_queue: new_queue_pubkey,
+        queue_migration: get_queue_migration_pda(&old_queue_pubkey),
+    };
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(Some(true)),
+        data: instruction_data.data(),
+    }
+}
@@ -2,6 +2,10 @@ use anchor_lang::{
     prelude::*,
     solana_program::{instruction::Instruction, program::invoke},
 };
+use light_concurrent_merkle_tree::event::MerkleTreeEvent;
+use light_utils::event::{
+    EventHeader, MAX_EVENT_DATA_LEN, MERKLE_TREE_EVENT_DISCRIMINATOR, MERKLE_TREE_EVENT_VERSION,
+};
 
 use crate::{errors::AccountCompressionErrorCode, utils::constants::NOOP_PUBKEY};
 
@@ -18,3 +22,23 @@ pub fn emit_indexer_event(data: Vec<u8>, noop_program: &AccountInfo) -> Result<(
     invoke(&instruction, &[noop_program.to_account_info()])?;
     Ok(())
 }
+
+/// Emits a Merkle tree event prefixed with the event envelope
+/// (discriminator and version). The envelope is part of the event data, the
+/// serialized event is limited to MAX_EVENT_PAYLOAD_LEN bytes.
+#[inline(never)]
+pub fn emit_merkle_tree_event(event: &MerkleTreeEvent, noop_program: &AccountInfo) -> Result<()> {
+    let mut data = Vec::new();
+    EventHeader::new(MERKLE_TREE_EVENT_DISCRIMINATOR, MERKLE_TREE_EVENT_VERSION)
+        .write(&mut data)?;
+    event.serialize(&mut data)?;
+    if data.len() > MAX_EVENT_DATA_LEN {
+        msg!(
+            "Event data of {} bytes exceeds the limit of {} bytes.",
+            data.len(),
+            MAX_EVENT_DATA_LEN
+        );
+        return err!(AccountCompressionErrorCode::EventTooLarge);
+    }
+    emit_indexer_event(data, noop_program)
+}
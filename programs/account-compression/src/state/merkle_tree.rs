@@ -37,6 +37,16 @@ impl MerkleTreeMetadata {
         self.rollover_metadata.rollover()?;
         self.next_merkle_tree = next_merkle_tree;
 
+        Ok(())
+    }
+    /// Associates the Merkle tree with `new_queue`, which the elements of
+    /// `old_queue` were migrated to.
+    pub fn migrate_queue(&mut self, old_queue: Pubkey, new_queue: Pubkey) -> Result<()> {
+        if self.associated_queue != old_queue {
+            return err!(AccountCompressionErrorCode::MerkleTreeAndQueueNotAssociated);
+        }
+        self.associated_queue = new_queue;
+
         Ok(())
     }
 }
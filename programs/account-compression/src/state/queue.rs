@@ -64,6 +64,42 @@ impl QueueMetadata {
 
         Ok(())
     }
+    /// Detaches the queue from its Merkle tree, so that its elements can be
+    /// migrated to `next_queue`. Elements can't be inserted into a detached
+    /// queue anymore.
+    ///
+    /// Queues which are already detached, rolled over or being migrated
+    /// can't be migrated.
+    pub fn migrate(&mut self, next_queue: Pubkey) -> Result<()> {
+        if self.associated_merkle_tree == Pubkey::default() {
+            return err!(AccountCompressionErrorCode::MerkleTreeAndQueueNotAssociated);
+        }
+        if self.next_queue != Pubkey::default() {
+            return err!(AccountCompressionErrorCode::QueueAlreadyRolledOver);
+        }
+        self.associated_merkle_tree = Pubkey::default();
+        self.next_queue = next_queue;
+
+        Ok(())
+    }
+
+    /// Returns whether the elements of the queue are being migrated to
+    /// `next_queue`.
+    pub fn is_migrating_to(&self, next_queue: &Pubkey) -> bool {
+        self.associated_merkle_tree == Pubkey::default() && self.next_queue == *next_queue
+    }
+}
+
+/// Progress of the migration of a queue, stored in a PDA derived from the
+/// old queue with [`QUEUE_MIGRATION_SEED`](crate::utils::constants::QUEUE_MIGRATION_SEED).
+/// It's closed together with the old queue.
+#[account]
+#[aligned_sized(anchor)]
+#[derive(Debug, PartialEq)]
+pub struct QueueMigration {
+    /// Index of the next bucket of the old queue to be migrated.
+    pub cursor: u64,
+    pub bump: u8,
 }
 
 #[account(zero_copy)]
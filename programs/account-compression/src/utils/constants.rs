@@ -7,6 +7,9 @@ pub const CPI_AUTHORITY_PDA_SEED: &[u8] = b"cpi_authority";
 #[constant]
 pub const GROUP_AUTHORITY_SEED: &[u8] = b"group_authority";
 
+#[constant]
+pub const QUEUE_MIGRATION_SEED: &[u8] = b"queue_migration";
+
 #[constant]
 pub const STATE_MERKLE_TREE_HEIGHT: u64 = 26;
 #[constant]
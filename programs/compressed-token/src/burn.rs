@@ -184,7 +184,13 @@ pub mod sdk {
             mint: inputs.mint,
             burn_amount: inputs.burn_amount,
         };
-        let remaining_accounts = to_account_metas(remaining_accounts);
+        let mut remaining_accounts = to_account_metas(remaining_accounts);
+        remaining_accounts.extend(
+            light_system_program::sdk::invoke::get_verifying_key_account_meta(
+                inputs.input_token_data.len(),
+                0,
+            ),
+        );
         let mut serialized_ix_data = Vec::new();
         CompressedTokenInstructionDataBurn::serialize(&inputs_struct, &mut serialized_ix_data)
             .map_err(|_| TransferSdkError::SerializationError)?;
@@ -260,7 +260,13 @@ pub mod sdk {
             delegate_merkle_tree_index: *delegated_merkle_tree_index as u8,
             change_account_merkle_tree_index: *change_account_merkle_tree_index as u8,
         };
-        let remaining_accounts = to_account_metas(remaining_accounts);
+        let mut remaining_accounts = to_account_metas(remaining_accounts);
+        remaining_accounts.extend(
+            light_system_program::sdk::invoke::get_verifying_key_account_meta(
+                inputs.input_token_data.len(),
+                0,
+            ),
+        );
         let mut serialized_ix_data = Vec::new();
         CompressedTokenInstructionDataApprove::serialize(&inputs_struct, &mut serialized_ix_data)
             .map_err(|_| TransferSdkError::SerializationError)?;
@@ -332,7 +338,13 @@ pub mod sdk {
             cpi_context: None,
             output_account_merkle_tree_index: *output_account_merkle_tree_index as u8,
         };
-        let remaining_accounts = to_account_metas(remaining_accounts);
+        let mut remaining_accounts = to_account_metas(remaining_accounts);
+        remaining_accounts.extend(
+            light_system_program::sdk::invoke::get_verifying_key_account_meta(
+                inputs.input_token_data.len(),
+                0,
+            ),
+        );
         let mut serialized_ix_data = Vec::new();
         CompressedTokenInstructionDataRevoke::serialize(&inputs_struct, &mut serialized_ix_data)
             .map_err(|_| TransferSdkError::SerializationError)?;
@@ -223,7 +223,13 @@ pub mod sdk {
             outputs_merkle_tree_index: *outputs_merkle_tree_index as u8,
             owner: inputs.input_token_data[0].owner,
         };
-        let remaining_accounts = to_account_metas(remaining_accounts);
+        let mut remaining_accounts = to_account_metas(remaining_accounts);
+        remaining_accounts.extend(
+            light_system_program::sdk::invoke::get_verifying_key_account_meta(
+                inputs.input_token_data.len(),
+                0,
+            ),
+        );
         let mut serialized_ix_data = Vec::new();
         CompressedTokenInstructionDataFreeze::serialize(&inputs_struct, &mut serialized_ix_data)
             .unwrap();
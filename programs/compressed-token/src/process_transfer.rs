@@ -504,6 +504,7 @@ pub fn get_input_compressed_accounts_with_merkle_context_and_check_signer<const
                 compressed_account,
                 merkle_context: input_token_data.merkle_context,
                 root_index: input_token_data.root_index,
+                close_address: false,
             },
         );
     }
@@ -603,7 +604,15 @@ pub mod transfer_sdk {
                 .output_compressed_accounts
                 .sort_by_key(|data| data.merkle_tree_index);
         }
-        let remaining_accounts = to_account_metas(remaining_accounts);
+        let mut remaining_accounts = to_account_metas(remaining_accounts);
+        if proof.is_some() {
+            remaining_accounts.extend(
+                light_system_program::sdk::invoke::get_verifying_key_account_meta(
+                    input_token_data.len(),
+                    0,
+                ),
+            );
+        }
         let mut inputs = Vec::new();
         CompressedTokenInstructionDataTransfer::serialize(&inputs_struct, &mut inputs)
             .map_err(|_| TransferSdkError::SerializationError)?;
@@ -7,6 +7,8 @@ use anchor_lang::prelude::*;
 
 pub mod forester;
 pub use forester::*;
+pub mod verifying_key;
+pub use verifying_key::*;
 #[cfg(not(target_os = "solana"))]
 pub mod sdk;
 
@@ -16,6 +18,12 @@ declare_id!("7Z9Yuy3HkBCc2Wf3xzMGnz6qpV4n7ciwcoEMGKqhAnj1");
 pub enum RegistryError {
     #[msg("InvalidForester")]
     InvalidForester,
+    #[msg("InvalidCircuitShape")]
+    InvalidCircuitShape,
+    #[msg("InvalidVerifyingKey")]
+    InvalidVerifyingKey,
+    #[msg("InvalidVerifyingKeyChunk")]
+    InvalidVerifyingKeyChunk,
 }
 
 #[constant]
@@ -249,6 +257,40 @@ pub mod light_registry {
         Ok(())
     }
 
+    /// Creates the verifying key account of `shape`. The key is uploaded with
+    /// `write_verifying_key` and `activate_verifying_key`.
+    pub fn initialize_verifying_key(
+        ctx: Context<InitializeVerifyingKey>,
+        shape: CircuitShape,
+    ) -> Result<()> {
+        shape.check()?;
+        let key_size = VerifyingKeyData::serialized_size(shape.num_public_inputs());
+        ctx.accounts.verifying_key_account.shape = shape;
+        ctx.accounts.verifying_key_account.key = None;
+        ctx.accounts.verifying_key_account.pending_key = vec![0; key_size];
+        Ok(())
+    }
+
+    /// Writes a chunk of the serialized `VerifyingKeyData` to the pending key.
+    pub fn write_verifying_key(
+        ctx: Context<UpdateVerifyingKey>,
+        offset: u32,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        write_pending_verifying_key(&mut ctx.accounts.verifying_key_account, offset, &data)
+    }
+
+    /// Replaces the key used by the system program with the pending key.
+    pub fn activate_verifying_key(ctx: Context<UpdateVerifyingKey>) -> Result<()> {
+        let key = ctx.accounts.verifying_key_account.pending_verifying_key()?;
+        msg!(
+            "activated verifying key: {:?}",
+            ctx.accounts.verifying_key_account.shape
+        );
+        ctx.accounts.verifying_key_account.key = Some(key);
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn initialize_address_merkle_tree(
         ctx: Context<InitializeAddressMerkleTreeAndQueue>,
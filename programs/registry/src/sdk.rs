@@ -1,5 +1,5 @@
 #![cfg(not(target_os = "solana"))]
-use crate::get_forester_epoch_pda_address;
+use crate::{get_forester_epoch_pda_address, get_verifying_key_pda_address, CircuitShape};
 use account_compression::{
     self, utils::constants::GROUP_AUTHORITY_SEED, AddressMerkleTreeConfig, AddressQueueConfig,
     NullifierQueueConfig, StateMerkleTreeConfig, ID,
@@ -164,6 +164,79 @@ pub fn create_update_forester_instruction(
     }
 }
 
+/// Size of the key chunks written by [`create_write_verifying_key_instructions`],
+/// small enough to fit into a transaction with the other accounts.
+pub const VERIFYING_KEY_CHUNK_SIZE: usize = 800;
+
+pub fn create_initialize_verifying_key_instruction(
+    governance_authority: &Pubkey,
+    shape: CircuitShape,
+) -> Instruction {
+    let (verifying_key_account, _) = get_verifying_key_pda_address(&shape);
+    let instruction_data = crate::instruction::InitializeVerifyingKey { shape };
+    let (authority_pda, _) = get_governance_authority_pda();
+    let accounts = crate::accounts::InitializeVerifyingKey {
+        authority: *governance_authority,
+        authority_pda,
+        verifying_key_account,
+        system_program: solana_sdk::system_program::id(),
+    };
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(Some(true)),
+        data: instruction_data.data(),
+    }
+}
+
+/// Returns instructions writing `payload`, the serialized `VerifyingKeyData`
+/// of `shape`, to the pending key in chunks.
+pub fn create_write_verifying_key_instructions(
+    governance_authority: &Pubkey,
+    shape: CircuitShape,
+    payload: &[u8],
+) -> Vec<Instruction> {
+    let accounts = update_verifying_key_accounts(governance_authority, &shape);
+    payload
+        .chunks(VERIFYING_KEY_CHUNK_SIZE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let instruction_data = crate::instruction::WriteVerifyingKey {
+                offset: (i * VERIFYING_KEY_CHUNK_SIZE) as u32,
+                data: chunk.to_vec(),
+            };
+            Instruction {
+                program_id: crate::ID,
+                accounts: accounts.to_account_metas(Some(true)),
+                data: instruction_data.data(),
+            }
+        })
+        .collect()
+}
+
+pub fn create_activate_verifying_key_instruction(
+    governance_authority: &Pubkey,
+    shape: CircuitShape,
+) -> Instruction {
+    let instruction_data = crate::instruction::ActivateVerifyingKey {};
+    let accounts = update_verifying_key_accounts(governance_authority, &shape);
+    Instruction {
+        program_id: crate::ID,
+        accounts: accounts.to_account_metas(Some(true)),
+        data: instruction_data.data(),
+    }
+}
+
+fn update_verifying_key_accounts(
+    governance_authority: &Pubkey,
+    shape: &CircuitShape,
+) -> crate::accounts::UpdateVerifyingKey {
+    crate::accounts::UpdateVerifyingKey {
+        authority: *governance_authority,
+        authority_pda: get_governance_authority_pda().0,
+        verifying_key_account: get_verifying_key_pda_address(shape).0,
+    }
+}
+
 pub struct CreateNullifyInstructionInputs {
     pub authority: Pubkey,
     pub nullifier_queue: Pubkey,
@@ -0,0 +1,207 @@
+use anchor_lang::{prelude::*, Discriminator};
+use groth16_solana::groth16::Groth16Verifyingkey;
+
+use crate::{LightGovernanceAuthority, RegistryError};
+
+pub const VERIFYING_KEY_SEED: &[u8] = b"verifying_key";
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitType {
+    Inclusion,
+    NonInclusion,
+    Combined,
+}
+
+/// Circuit of a verifying key. Each shape has its own verifying key account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitShape {
+    pub circuit_type: CircuitType,
+    pub height: u8,
+    pub num_leaves: u8,
+    pub num_addresses: u8,
+}
+
+impl CircuitShape {
+    /// Selects the circuit type the same way as the system program, the
+    /// combined circuit is used when both leaves and addresses are proven.
+    pub fn new(height: u8, num_leaves: u8, num_addresses: u8) -> Result<Self> {
+        let circuit_type = match (num_leaves, num_addresses) {
+            (0, 0) => return err!(RegistryError::InvalidCircuitShape),
+            (_, 0) => CircuitType::Inclusion,
+            (0, _) => CircuitType::NonInclusion,
+            (_, _) => CircuitType::Combined,
+        };
+        Ok(Self {
+            circuit_type,
+            height,
+            num_leaves,
+            num_addresses,
+        })
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if Self::new(self.height, self.num_leaves, self.num_addresses)? != *self {
+            return err!(RegistryError::InvalidCircuitShape);
+        }
+        Ok(())
+    }
+
+    /// Roots and values of all proofs.
+    pub fn num_public_inputs(&self) -> usize {
+        2 * (self.num_leaves as usize + self.num_addresses as usize)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 4] {
+        [
+            self.circuit_type as u8,
+            self.height,
+            self.num_leaves,
+            self.num_addresses,
+        ]
+    }
+}
+
+/// Groth16 verifying key in the layout of [`Groth16Verifyingkey`]. Its Borsh
+/// serialization is the payload emitted by `xtask generate-vkey-rs`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingKeyData {
+    pub vk_alpha_g1: [u8; 64],
+    pub vk_beta_g2: [u8; 128],
+    pub vk_gamme_g2: [u8; 128],
+    pub vk_delta_g2: [u8; 128],
+    pub vk_ic: Vec<[u8; 64]>,
+}
+
+impl From<&Groth16Verifyingkey<'_>> for VerifyingKeyData {
+    fn from(verifying_key: &Groth16Verifyingkey<'_>) -> Self {
+        Self {
+            vk_alpha_g1: verifying_key.vk_alpha_g1,
+            vk_beta_g2: verifying_key.vk_beta_g2,
+            vk_gamme_g2: verifying_key.vk_gamme_g2,
+            vk_delta_g2: verifying_key.vk_delta_g2,
+            vk_ic: verifying_key.vk_ic.to_vec(),
+        }
+    }
+}
+
+impl VerifyingKeyData {
+    pub fn serialized_size(num_public_inputs: usize) -> usize {
+        64 + 3 * 128 + 4 + (num_public_inputs + 1) * 64
+    }
+
+    pub fn as_groth16_verifyingkey(&self) -> Groth16Verifyingkey<'_> {
+        Groth16Verifyingkey {
+            nr_pubinputs: self.vk_ic.len().saturating_sub(1),
+            vk_alpha_g1: self.vk_alpha_g1,
+            vk_beta_g2: self.vk_beta_g2,
+            vk_gamme_g2: self.vk_gamme_g2,
+            vk_delta_g2: self.vk_delta_g2,
+            vk_ic: self.vk_ic.as_slice(),
+        }
+    }
+}
+
+/// Verifying key of one circuit shape, used by the system program instead of
+/// the keys compiled into `light-verifier`.
+///
+/// Keys don't fit into a single transaction, so they are uploaded in chunks
+/// to `pending_key` and replace `key` at once on activation. Proofs are
+/// verified with the previous key until then.
+#[account]
+#[derive(Debug, PartialEq)]
+pub struct VerifyingKeyAccount {
+    pub shape: CircuitShape,
+    /// `None` until the first key is activated.
+    pub key: Option<VerifyingKeyData>,
+    /// Serialized `VerifyingKeyData` being uploaded.
+    pub pending_key: Vec<u8>,
+}
+
+impl VerifyingKeyAccount {
+    pub fn size(shape: &CircuitShape) -> usize {
+        let key_size = VerifyingKeyData::serialized_size(shape.num_public_inputs());
+        // discriminator + shape + key + pending key
+        8 + 4 + 1 + key_size + 4 + key_size
+    }
+
+    /// Deserializes the shape of the serialized account, `None` if the data
+    /// isn't a verifying key account.
+    pub fn deserialize_shape(data: &[u8]) -> Option<CircuitShape> {
+        if data.len() < 8 || data[..8] != Self::DISCRIMINATOR {
+            return None;
+        }
+        CircuitShape::deserialize(&mut &data[8..]).ok()
+    }
+
+    /// Deserializes the shape and the active key of the serialized account,
+    /// without the pending key.
+    pub fn deserialize_active_key(data: &[u8]) -> Result<(CircuitShape, Option<VerifyingKeyData>)> {
+        if data.len() < 8 || data[..8] != Self::DISCRIMINATOR {
+            return err!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+        }
+        let mut data = &data[8..];
+        let shape = CircuitShape::deserialize(&mut data)?;
+        let key = Option::<VerifyingKeyData>::deserialize(&mut data)?;
+        Ok((shape, key))
+    }
+
+    /// Deserializes the pending key and checks that it matches the shape.
+    pub fn pending_verifying_key(&self) -> Result<VerifyingKeyData> {
+        let key = VerifyingKeyData::deserialize(&mut self.pending_key.as_slice())
+            .map_err(|_| RegistryError::InvalidVerifyingKey)?;
+        if key.vk_ic.len() != self.shape.num_public_inputs() + 1 {
+            return err!(RegistryError::InvalidVerifyingKey);
+        }
+        Ok(key)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(shape: CircuitShape)]
+pub struct InitializeVerifyingKey<'info> {
+    #[account(mut, address = authority_pda.authority)]
+    pub authority: Signer<'info>,
+    pub authority_pda: Account<'info, LightGovernanceAuthority>,
+    #[account(
+        init,
+        seeds = [VERIFYING_KEY_SEED, shape.to_bytes().as_slice()],
+        bump,
+        space = VerifyingKeyAccount::size(&shape),
+        payer = authority
+    )]
+    pub verifying_key_account: Account<'info, VerifyingKeyAccount>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVerifyingKey<'info> {
+    #[account(address = authority_pda.authority)]
+    pub authority: Signer<'info>,
+    pub authority_pda: Account<'info, LightGovernanceAuthority>,
+    #[account(mut)]
+    pub verifying_key_account: Account<'info, VerifyingKeyAccount>,
+}
+
+pub fn write_pending_verifying_key(
+    verifying_key_account: &mut VerifyingKeyAccount,
+    offset: u32,
+    data: &[u8],
+) -> Result<()> {
+    let start = offset as usize;
+    let end = start
+        .checked_add(data.len())
+        .ok_or(RegistryError::InvalidVerifyingKeyChunk)?;
+    verifying_key_account
+        .pending_key
+        .get_mut(start..end)
+        .ok_or(RegistryError::InvalidVerifyingKeyChunk)?
+        .copy_from_slice(data);
+    Ok(())
+}
+
+pub fn get_verifying_key_pda_address(shape: &CircuitShape) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VERIFYING_KEY_SEED, shape.to_bytes().as_slice()],
+        &crate::ID,
+    )
+}
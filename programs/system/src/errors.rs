@@ -68,4 +68,18 @@ pub enum SystemProgramError {
     InputMerkleTreeIndicesNotInOrder,
     #[msg("Output merkle tree indices are not in ascending order.")]
     OutputMerkleTreeIndicesNotInOrder,
+    #[msg("Address of an input compressed account can only be assigned to an output of the same owner.")]
+    AddressOwnerMismatch,
+    #[msg("Address is assigned to more than one output compressed account.")]
+    AddressAssignedToMultipleOutputs,
+    #[msg("CpiContextSlotMismatch")]
+    CpiContextSlotMismatch,
+    #[msg("The verifying key account of the proof is missing.")]
+    VerifyingKeyAccountMissing,
+    #[msg("VerifyingKeyNotActivated")]
+    VerifyingKeyNotActivated,
+    #[msg("Only the address of a compressed account with an address can be closed.")]
+    CloseAddressWithoutAddress,
+    #[msg("Event data exceeds the max instruction data of a cpi.")]
+    EventTooLarge,
 }
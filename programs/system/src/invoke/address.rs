@@ -3,19 +3,20 @@ use anchor_lang::{prelude::*, Bumps};
 
 use crate::{
     constants::CPI_AUTHORITY_PDA_BUMP,
+    errors::SystemProgramError,
     invoke_cpi::verify_signer::check_program_owner_address_merkle_tree,
     sdk::{
         accounts::{InvokeAccounts, SignerAccounts},
         address::derive_address,
     },
-    NewAddressParamsPacked,
+    NewAddressParamsPacked, OutputCompressedAccountWithPackedContext,
 };
 
 pub fn derive_new_addresses(
     new_address_params: &[NewAddressParamsPacked],
     num_input_compressed_accounts: usize,
     remaining_accounts: &[AccountInfo],
-    compressed_account_addresses: &mut [Option<[u8; 32]>],
+    compressed_account_addresses: &mut [Option<([u8; 32], Option<Pubkey>)>],
     new_addresses: &mut [[u8; 32]],
 ) -> Result<()> {
     new_address_params
@@ -30,12 +31,78 @@ pub fn derive_new_addresses(
             .map_err(ProgramError::from)?;
             // We are inserting addresses into two vectors to avoid unwrapping
             // the option in following functions.
-            compressed_account_addresses[i + num_input_compressed_accounts] = Some(address);
+            // New addresses are not bound to an owner yet.
+            compressed_account_addresses[i + num_input_compressed_accounts] = Some((address, None));
             new_addresses[i] = address;
             Ok(())
         })
 }
 
+/// Checks address continuity of an output compressed account address and
+/// consumes the address so that it cannot be assigned to another output.
+///
+/// Checks:
+/// 1. The address is a new address or the address of an input compressed
+///    account.
+/// 2. The address of an input compressed account can only be assigned to an
+///    output compressed account with the same owner. New addresses can be
+///    assigned to any owner.
+/// 3. An address is assigned to at most one output compressed account.
+///
+/// Addresses of input compressed accounts with close_address are not available
+/// to output compressed accounts, assigning them fails with InvalidAddress.
+pub fn check_and_consume_output_address(
+    address: &[u8; 32],
+    owner: &Pubkey,
+    compressed_account_addresses: &mut [Option<([u8; 32], Option<Pubkey>)>],
+    previous_output_compressed_accounts: &[OutputCompressedAccountWithPackedContext],
+) -> Result<()> {
+    let position = compressed_account_addresses
+        .iter()
+        .position(|x| matches!(x, Some((x_address, _)) if x_address == address));
+    match position {
+        Some(position) => {
+            // Check 2.
+            if let Some((_, Some(input_owner))) = compressed_account_addresses[position] {
+                if input_owner != *owner {
+                    msg!(
+                        "Address {:?} is owned by {:?} and cannot be assigned to an output owned by {:?}.",
+                        address,
+                        input_owner,
+                        owner
+                    );
+                    return err!(SystemProgramError::AddressOwnerMismatch);
+                }
+            }
+            compressed_account_addresses[position] = None;
+            Ok(())
+        }
+        None => {
+            // Check 3.
+            if previous_output_compressed_accounts
+                .iter()
+                .any(|x| x.compressed_account.address.as_ref() == Some(address))
+            {
+                msg!(
+                    "Address {:?} is assigned to more than one output compressed account.",
+                    address
+                );
+                return err!(SystemProgramError::AddressAssignedToMultipleOutputs);
+            }
+            // Check 1.
+            msg!(
+                "Address {:?}, is no new address and does not exist in input compressed accounts.",
+                address
+            );
+            msg!(
+                "Remaining compressed_account_addresses: {:?}",
+                compressed_account_addresses
+            );
+            err!(SystemProgramError::InvalidAddress)
+        }
+    }
+}
+
 pub fn insert_addresses_into_address_merkle_tree_queue<
     'a,
     'b,
@@ -112,3 +179,78 @@ pub fn insert_addresses_cpi<'a, 'b>(
 
     account_compression::cpi::insert_addresses(cpi_ctx, addresses)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sdk::compressed_account::CompressedAccount;
+
+    fn output_with_address(
+        address: [u8; 32],
+        owner: Pubkey,
+    ) -> OutputCompressedAccountWithPackedContext {
+        OutputCompressedAccountWithPackedContext {
+            compressed_account: CompressedAccount {
+                owner,
+                address: Some(address),
+                ..CompressedAccount::default()
+            },
+            merkle_tree_index: 0,
+        }
+    }
+
+    /// Tests:
+    /// 1. functional: input address is assigned to an output of the same owner
+    /// 2. functional: new address is assigned to an output of any owner
+    /// 3. failing: input address is assigned to an output of a different owner
+    /// 4. failing: address is assigned to two outputs
+    /// 5. failing: address neither new nor input address
+    #[test]
+    fn test_check_and_consume_output_address() {
+        let owner = Pubkey::new_unique();
+        let other_owner = Pubkey::new_unique();
+        let input_address = [1u8; 32];
+        let new_address = [2u8; 32];
+        let mut addresses = vec![
+            Some((input_address, Some(owner))),
+            Some((new_address, None)),
+        ];
+
+        // 1. functional: input address is assigned to an output of the same owner
+        assert_eq!(
+            check_and_consume_output_address(&input_address, &owner, &mut addresses, &[]),
+            Ok(())
+        );
+        assert_eq!(addresses, vec![None, Some((new_address, None))]);
+        // 2. functional: new address is assigned to an output of any owner
+        let outputs = vec![output_with_address(input_address, owner)];
+        assert_eq!(
+            check_and_consume_output_address(&new_address, &other_owner, &mut addresses, &outputs),
+            Ok(())
+        );
+        assert_eq!(addresses, vec![None, None]);
+
+        // 3. failing: input address is assigned to an output of a different owner
+        let mut addresses = vec![Some((input_address, Some(owner)))];
+        assert_eq!(
+            check_and_consume_output_address(&input_address, &other_owner, &mut addresses, &[]),
+            Err(SystemProgramError::AddressOwnerMismatch.into())
+        );
+        assert_eq!(addresses, vec![Some((input_address, Some(owner)))]);
+
+        // 4. failing: address is assigned to two outputs
+        check_and_consume_output_address(&input_address, &owner, &mut addresses, &[]).unwrap();
+        let outputs = vec![output_with_address(input_address, owner)];
+        assert_eq!(
+            check_and_consume_output_address(&input_address, &owner, &mut addresses, &outputs),
+            Err(SystemProgramError::AddressAssignedToMultipleOutputs.into())
+        );
+
+        // 5. failing: address neither new nor input address
+        let mut addresses = vec![Some((input_address, Some(owner)))];
+        assert_eq!(
+            check_and_consume_output_address(&[3u8; 32], &owner, &mut addresses, &[]),
+            Err(SystemProgramError::InvalidAddress.into())
+        );
+    }
+}
@@ -12,6 +12,7 @@ use light_utils::hash_to_bn254_field_size_be;
 use crate::{
     constants::CPI_AUTHORITY_PDA_BUMP,
     errors::SystemProgramError,
+    invoke::address::check_and_consume_output_address,
     invoke_cpi::verify_signer::check_program_owner_state_merkle_tree,
     sdk::{
         accounts::{InvokeAccounts, SignerAccounts},
@@ -33,7 +34,7 @@ pub fn insert_output_compressed_accounts_into_state_merkle_tree<
     ctx: &'a Context<'a, 'b, 'c, 'info, A>,
     output_compressed_account_indices: &'a mut [u32],
     output_compressed_account_hashes: &'a mut [[u8; 32]],
-    compressed_account_addresses: &'a mut Vec<Option<[u8; 32]>>,
+    compressed_account_addresses: &'a mut [Option<([u8; 32], Option<Pubkey>)>],
     invoking_program: &Option<Pubkey>,
     hashed_pubkeys: &'a mut Vec<(Pubkey, [u8; 32])>,
     sequence_numbers: &'a mut Vec<MerkleTreeSequenceNumber>,
@@ -97,14 +98,18 @@ pub fn insert_output_compressed_accounts_into_state_merkle_tree<
 ///    eligibility.
 /// 2. Checks ordering of Merkle tree indices.
 /// 3. Checks that addresses in output compressed accounts have been created or
-///    exist in input compressed accounts. An address may not be used in an
-///    output compressed accounts. This will close the account.
+///    exist in input compressed accounts, that input compressed account
+///    addresses keep their owner and that every address is used in at most
+///    one output compressed account. Addresses of input compressed accounts
+///    with close_address are not available to outputs. Input addresses which
+///    are assigned to an output are consumed from compressed_account_addresses,
+///    the remaining input addresses are reported as closed in the event.
 #[allow(clippy::too_many_arguments)]
 pub fn create_cpi_accounts_and_instruction_data<'a>(
     output_compressed_accounts: &[OutputCompressedAccountWithPackedContext],
     output_compressed_account_indices: &mut [u32],
     output_compressed_account_hashes: &mut [[u8; 32]],
-    compressed_account_addresses: &mut Vec<Option<[u8; 32]>>,
+    compressed_account_addresses: &mut [Option<([u8; 32], Option<Pubkey>)>],
     invoking_program: &Option<Pubkey>,
     hashed_pubkeys: &mut Vec<(Pubkey, [u8; 32])>,
     sequence_numbers: &mut Vec<MerkleTreeSequenceNumber>,
@@ -174,20 +179,12 @@ pub fn create_cpi_accounts_and_instruction_data<'a>(
 
         // Check 3.
         if let Some(address) = account.compressed_account.address {
-            if let Some(position) = compressed_account_addresses
-                .iter()
-                .filter(|x| x.is_some())
-                .position(|&x| x.unwrap() == address)
-            {
-                compressed_account_addresses.remove(position);
-            } else {
-                msg!("Address {:?}, is no new address and does not exist in input compressed accounts.", address);
-                msg!(
-                    "Remaining compressed_account_addresses: {:?}",
-                    compressed_account_addresses
-                );
-                return Err(SystemProgramError::InvalidAddress.into());
-            }
+            check_and_consume_output_address(
+                &address,
+                &account.compressed_account.owner,
+                compressed_account_addresses,
+                &output_compressed_accounts[..j],
+            )?;
         }
 
         output_compressed_account_indices[j] = mt_next_index + num_leaves_in_tree;
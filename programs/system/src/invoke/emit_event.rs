@@ -4,11 +4,16 @@ use anchor_lang::{
     solana_program::{instruction::Instruction, program::invoke},
     Bumps,
 };
+use light_utils::event::{
+    EventHeader, MAX_EVENT_DATA_LEN, PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR,
+    PUBLIC_TRANSACTION_EVENT_VERSION,
+};
 
 use crate::{
     errors::SystemProgramError,
     sdk::{
         accounts::InvokeAccounts,
+        compressed_account::PackedCompressedAccountWithMerkleContext,
         event::{MerkleTreeSequenceNumber, PublicTransactionEvent},
     },
     InstructionDataInvoke,
@@ -21,7 +26,12 @@ pub fn emit_state_transition_event<'a, 'b, 'c: 'info, 'info, A: InvokeAccounts<'
     output_compressed_account_hashes: Vec<[u8; 32]>,
     output_leaf_indices: Vec<u32>,
     sequence_numbers: Vec<MerkleTreeSequenceNumber>,
+    compressed_account_addresses: &[Option<([u8; 32], Option<Pubkey>)>],
 ) -> Result<()> {
+    let closed_addresses = get_closed_addresses(
+        &inputs.input_compressed_accounts_with_merkle_context,
+        compressed_account_addresses,
+    );
     // Note: message is unimplemented
     let event = PublicTransactionEvent {
         input_compressed_account_hashes,
@@ -34,6 +44,7 @@ pub fn emit_state_transition_event<'a, 'b, 'c: 'info, 'info, A: InvokeAccounts<'
         compress_or_decompress_lamports: inputs.compress_or_decompress_lamports,
         message: None,
         is_compress: inputs.is_compress,
+        closed_addresses,
     };
 
     if ctx.accounts.get_noop_program().key() != Pubkey::new_from_array(NOOP_PUBKEY)
@@ -42,11 +53,26 @@ pub fn emit_state_transition_event<'a, 'b, 'c: 'info, 'info, A: InvokeAccounts<'
         return err!(SystemProgramError::InvalidNoopPubkey);
     }
 
-    // 10240 = 10 * 1024 the max instruction data of a cpi.
-    let data_capacity = 10240;
+    // The event envelope takes EVENT_HEADER_LEN bytes of the event data, the
+    // serialized event is limited to MAX_EVENT_PAYLOAD_LEN bytes.
+    let data_capacity = MAX_EVENT_DATA_LEN;
     let mut data = Vec::with_capacity(data_capacity);
+    EventHeader::new(
+        PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR,
+        PUBLIC_TRANSACTION_EVENT_VERSION,
+    )
+    .write(&mut data)?;
     event.man_serialize(&mut data)?;
 
+    if data.len() > MAX_EVENT_DATA_LEN {
+        msg!(
+            "Event data of {} bytes exceeds the limit of {} bytes.",
+            data.len(),
+            MAX_EVENT_DATA_LEN
+        );
+        return err!(SystemProgramError::EventTooLarge);
+    }
+
     if data_capacity != data.capacity() {
         msg!(
             "Event serialization exceeded capacity. Used {}, allocated {}.",
@@ -66,3 +92,81 @@ pub fn emit_state_transition_event<'a, 'b, 'c: 'info, 'info, A: InvokeAccounts<'
     )?;
     Ok(())
 }
+
+/// Returns the addresses of input compressed accounts which are closed by this
+/// transaction. An input address is closed if
+/// 1. the input has close_address set, or
+/// 2. the address is not assigned to an output compressed account.
+///
+/// compressed_account_addresses are the addresses left after output addresses
+/// have been consumed in create_cpi_accounts_and_instruction_data. Addresses
+/// of inputs with close_address are never stored in it, addresses of other
+/// inputs are removed once assigned to an output.
+///
+/// Closed addresses remain in the address Merkle tree, re-creating a closed
+/// address is not supported.
+pub fn get_closed_addresses(
+    input_compressed_accounts_with_merkle_context: &[PackedCompressedAccountWithMerkleContext],
+    compressed_account_addresses: &[Option<([u8; 32], Option<Pubkey>)>],
+) -> Vec<[u8; 32]> {
+    input_compressed_accounts_with_merkle_context
+        .iter()
+        .zip(compressed_account_addresses.iter())
+        .filter(|(x, unassigned_address)| x.close_address || unassigned_address.is_some())
+        .filter_map(|(x, _)| x.compressed_account.address)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sdk::compressed_account::CompressedAccount;
+
+    /// Tests:
+    /// 1. addresses of inputs with close_address are closed
+    /// 2. addresses of inputs without close_address which are assigned to an
+    ///    output are not closed
+    /// 3. addresses of inputs without close_address which are not assigned to
+    ///    an output are closed
+    /// 4. inputs without address are never closed
+    #[test]
+    fn test_get_closed_addresses() {
+        let owner = Pubkey::new_unique();
+        let input = |address: Option<[u8; 32]>, close_address: bool| {
+            PackedCompressedAccountWithMerkleContext {
+                compressed_account: CompressedAccount {
+                    owner,
+                    address,
+                    ..CompressedAccount::default()
+                },
+                close_address,
+                ..PackedCompressedAccountWithMerkleContext::default()
+            }
+        };
+        let inputs = vec![
+            input(Some([1u8; 32]), true),
+            input(Some([2u8; 32]), false),
+            input(None, false),
+            input(Some([3u8; 32]), true),
+            input(Some([4u8; 32]), false),
+        ];
+        // The address of input 1 is assigned to an output, the address of
+        // input 4 is not. The last entry is a new address.
+        let compressed_account_addresses = vec![
+            None,
+            None,
+            None,
+            None,
+            Some(([4u8; 32], Some(owner))),
+            Some(([5u8; 32], None)),
+        ];
+        assert_eq!(
+            get_closed_addresses(&inputs, &compressed_account_addresses),
+            vec![[1u8; 32], [3u8; 32], [4u8; 32]]
+        );
+        assert_eq!(
+            get_closed_addresses(&[], &[Some(([5u8; 32], None))]),
+            Vec::<[u8; 32]>::new()
+        );
+    }
+}
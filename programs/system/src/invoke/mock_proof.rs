@@ -0,0 +1,31 @@
+//! Verification of mock proofs, which are generated without proving by
+//! `light_prover_client::prover::MockProver`.
+//!
+//! Compiled only into the test build of the program
+//! (`scripts/build-mock-proofs-program.sh`), which is built with
+//! `--cfg mock_proofs` and deployed as `light_system_program_mock_proofs`.
+
+use light_hasher::{Hasher, Keccak};
+use light_verifier::{CompressedProof, VerifierError};
+
+/// Value of `b` which identifies mock proofs.
+pub const MOCK_PROOF_MARKER: [u8; 64] =
+    *b"light-protocol-mock-proof-marker-light-protocol-mock-proof-marke";
+
+/// Checks that `a` is the Keccak hash of the public inputs and `c` is the
+/// hash of `a`.
+pub fn verify_mock_proof(
+    public_inputs: &[[u8; 32]],
+    proof: &CompressedProof,
+) -> Result<(), VerifierError> {
+    let inputs = public_inputs
+        .iter()
+        .map(|input| input.as_slice())
+        .collect::<Vec<_>>();
+    let a = Keccak::hashv(&inputs).map_err(|_| VerifierError::ProofVerificationFailed)?;
+    let c = Keccak::hash(&a).map_err(|_| VerifierError::ProofVerificationFailed)?;
+    if proof.a != a || proof.b != MOCK_PROOF_MARKER || proof.c != c {
+        return Err(VerifierError::ProofVerificationFailed);
+    }
+    Ok(())
+}
@@ -3,6 +3,8 @@ pub use instruction::*;
 pub mod address;
 pub mod append_state;
 pub mod emit_event;
+#[cfg(mock_proofs)]
+pub mod mock_proof;
 pub mod nullify_state;
 pub mod processor;
 pub mod sol_compression;
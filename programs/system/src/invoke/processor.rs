@@ -91,7 +91,10 @@ pub fn process<
     let num_output_compressed_accounts = inputs.output_compressed_accounts.len();
     let mut input_compressed_account_hashes = vec![[0u8; 32]; num_input_compressed_accounts];
 
-    let mut compressed_account_addresses: Vec<Option<[u8; 32]>> =
+    // Addresses of input compressed accounts and new addresses. Input
+    // compressed account addresses are stored with the owner of the input
+    // compressed account, new addresses have no owner.
+    let mut compressed_account_addresses: Vec<Option<([u8; 32], Option<Pubkey>)>> =
         vec![None; num_input_compressed_accounts + num_new_addresses];
     let mut output_leaf_indices = vec![0u32; num_output_compressed_accounts];
     let mut output_compressed_account_hashes = vec![[0u8; 32]; num_output_compressed_accounts];
@@ -185,6 +188,7 @@ pub fn process<
             c: proof.c,
         };
         match verify_state_proof(
+            ctx.remaining_accounts,
             &input_compressed_account_roots,
             &input_compressed_account_hashes,
             &new_address_roots,
@@ -277,6 +281,7 @@ pub fn process<
         output_compressed_account_hashes,
         output_leaf_indices,
         sequence_numbers,
+        &compressed_account_addresses,
     )?;
     bench_sbf_end!("emit_state_transition_event");
 
@@ -138,6 +138,7 @@ mod test {
                     leaf_index: 0,
                 },
                 root_index: 1,
+                close_address: false,
             });
         }
         let mut outputs = Vec::new();
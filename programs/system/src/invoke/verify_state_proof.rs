@@ -1,4 +1,5 @@
 use crate::{
+    errors::SystemProgramError,
     sdk::{accounts::InvokeAccounts, compressed_account::PackedCompressedAccountWithMerkleContext},
     NewAddressParamsPacked,
 };
@@ -11,10 +12,15 @@ use light_concurrent_merkle_tree::zero_copy::ConcurrentMerkleTreeZeroCopy;
 use light_hasher::Poseidon;
 use light_indexed_merkle_tree::zero_copy::IndexedMerkleTreeZeroCopy;
 use light_macros::heap_neutral;
+#[cfg(feature = "compiled-verifying-keys")]
+use light_registry::get_verifying_key_pda_address;
+use light_registry::{CircuitShape, VerifyingKeyAccount};
 use light_utils::hash_to_bn254_field_size_be;
+use light_verifier::{client::TREE_HEIGHT, verify_zkp, CompressedProof, VerifierError};
+#[cfg(feature = "compiled-verifying-keys")]
 use light_verifier::{
     verify_create_addresses_and_merkle_proof_zkp, verify_create_addresses_zkp,
-    verify_merkle_proof_zkp, CompressedProof,
+    verify_merkle_proof_zkp,
 };
 use std::mem;
 
@@ -85,6 +91,10 @@ pub fn fetch_roots_address_merkle_tree<
 /// Hashes the input compressed accounts and stores the results in the leaves array.
 /// Merkle tree pubkeys are hashed and stored in the hashed_pubkeys array.
 /// Merkle tree pubkeys should be ordered for efficiency.
+/// Addresses of input compressed accounts are stored together with the owner
+/// of the input compressed account to check address continuity of outputs.
+/// Addresses of inputs with close_address are not stored, they cannot be
+/// assigned to outputs and are always reported as closed.
 #[inline(never)]
 #[heap_neutral]
 #[allow(unused_mut)]
@@ -92,7 +102,7 @@ pub fn hash_input_compressed_accounts<'a, 'b, 'c: 'info, 'info>(
     remaining_accounts: &'a [AccountInfo<'info>],
     input_compressed_accounts_with_merkle_context: &'a [PackedCompressedAccountWithMerkleContext],
     leaves: &'a mut [[u8; 32]],
-    addresses: &'a mut [Option<[u8; 32]>],
+    addresses: &'a mut [Option<([u8; 32], Option<Pubkey>)>],
     hashed_pubkeys: &'a mut Vec<(Pubkey, [u8; 32])>,
 ) -> Result<()> {
     let mut owner_pubkey = input_compressed_accounts_with_merkle_context[0]
@@ -111,12 +121,27 @@ pub fn hash_input_compressed_accounts<'a, 'b, 'c: 'info, 'info>(
         .enumerate()
     {
         // For heap neutrality we cannot allocate new heap memory in this function.
-        match &input_compressed_account_with_context
-            .compressed_account
-            .address
-        {
-            Some(address) => addresses[j] = Some(*address),
-            None => {}
+        match (
+            &input_compressed_account_with_context
+                .compressed_account
+                .address,
+            input_compressed_account_with_context.close_address,
+        ) {
+            (Some(address), false) => {
+                addresses[j] = Some((
+                    *address,
+                    Some(
+                        input_compressed_account_with_context
+                            .compressed_account
+                            .owner,
+                    ),
+                ))
+            }
+            // Closed addresses are not available to output compressed
+            // accounts.
+            (Some(_), true) => {}
+            (None, true) => return err!(SystemProgramError::CloseAddressWithoutAddress),
+            (None, false) => {}
         };
 
         #[allow(clippy::comparison_chain)]
@@ -193,8 +218,91 @@ pub fn hash_input_compressed_accounts<'a, 'b, 'c: 'info, 'info>(
     Ok(())
 }
 
+/// Verifies the proof with the active key of its circuit shape from the
+/// verifying key registry of the registry program. The verifying key account
+/// of the shape is a PDA of the registry program and has to be passed as one
+/// of the remaining accounts.
+///
+/// The account is identified by its owner and its shape instead of deriving
+/// its address, verifying key accounts are only created by the registry
+/// program at the address derived from their shape.
+///
+/// With the `compiled-verifying-keys` feature, the keys compiled into
+/// `light-verifier` are used as long as the verifying key account of the
+/// shape doesn't exist.
+///
+/// The test build of the program (`--cfg mock_proofs`) additionally accepts
+/// mock proofs.
 #[heap_neutral]
 pub fn verify_state_proof(
+    remaining_accounts: &[AccountInfo],
+    roots: &[[u8; 32]],
+    leaves: &[[u8; 32]],
+    address_roots: &[[u8; 32]],
+    addresses: &[[u8; 32]],
+    compressed_proof: &CompressedProof,
+) -> anchor_lang::Result<()> {
+    let shape = CircuitShape::new(
+        TREE_HEIGHT as u8,
+        u8::try_from(leaves.len())
+            .map_err(|_| ProgramError::from(VerifierError::InvalidPublicInputsLength))?,
+        u8::try_from(addresses.len())
+            .map_err(|_| ProgramError::from(VerifierError::InvalidPublicInputsLength))?,
+    )?;
+    #[cfg(mock_proofs)]
+    if compressed_proof.b == super::mock_proof::MOCK_PROOF_MARKER {
+        return super::mock_proof::verify_mock_proof(
+            &[roots, leaves, address_roots, addresses].concat(),
+            compressed_proof,
+        )
+        .map_err(|e| ProgramError::from(e).into());
+    }
+    let verifying_key_account = match remaining_accounts.iter().find(|account_info| {
+        *account_info.owner == light_registry::ID
+            && account_info
+                .try_borrow_data()
+                .ok()
+                .and_then(|data| VerifyingKeyAccount::deserialize_shape(&data))
+                == Some(shape)
+    }) {
+        Some(account_info) => account_info,
+        None => {
+            // The compiled keys can only be used as long as the verifying
+            // key account of the shape doesn't exist, which requires its
+            // address.
+            #[cfg(feature = "compiled-verifying-keys")]
+            {
+                let verifying_key_pda = get_verifying_key_pda_address(&shape).0;
+                if remaining_accounts.iter().any(|account_info| {
+                    *account_info.key == verifying_key_pda && account_info.data_is_empty()
+                }) {
+                    return verify_state_proof_with_compiled_keys(
+                        roots,
+                        leaves,
+                        address_roots,
+                        addresses,
+                        compressed_proof,
+                    );
+                }
+            }
+            msg!("Verifying key account of {:?} is missing.", shape);
+            return err!(SystemProgramError::VerifyingKeyAccountMissing);
+        }
+    };
+    let (_, verifying_key) =
+        VerifyingKeyAccount::deserialize_active_key(&verifying_key_account.try_borrow_data()?)?;
+    let verifying_key = verifying_key.ok_or(SystemProgramError::VerifyingKeyNotActivated)?;
+    verify_zkp(
+        &[roots, leaves, address_roots, addresses].concat(),
+        compressed_proof,
+        &verifying_key.as_groth16_verifyingkey(),
+    )
+    .map_err(ProgramError::from)?;
+    Ok(())
+}
+
+#[cfg(feature = "compiled-verifying-keys")]
+fn verify_state_proof_with_compiled_keys(
     roots: &[[u8; 32]],
     leaves: &[[u8; 32]],
     address_roots: &[[u8; 32]],
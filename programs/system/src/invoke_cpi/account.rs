@@ -9,6 +9,10 @@ use crate::InstructionDataInvokeCpi;
 /// and executed as a single transaction.
 /// This enables to use input compressed accounts that are owned by multiple programs,
 /// with one zero-knowledge proof.
+/// A cpi context session is opened with the associated Merkle tree, subsequent
+/// invocations of the session are bound by fee payer and slot and can use any
+/// state Merkle tree. Multiple cpi context accounts can be associated with the
+/// same Merkle tree.
 #[aligned_sized(anchor)]
 #[derive(Debug, PartialEq, Default)]
 #[account]
@@ -16,11 +20,16 @@ pub struct CpiContextAccount {
     pub fee_payer: Pubkey,
     pub associated_merkle_tree: Pubkey,
     pub context: Vec<InstructionDataInvokeCpi>,
+    /// Slot in which the current cpi context session has been opened.
+    /// Appended after the context to keep the layout of existing accounts,
+    /// the slot is set whenever a session is opened.
+    pub slot: u64,
 }
 
 impl CpiContextAccount {
     pub fn init(&mut self, associated_merkle_tree: Pubkey) {
         self.associated_merkle_tree = associated_merkle_tree;
         self.context = Vec::new();
+        self.slot = 0;
     }
 }
@@ -24,10 +24,20 @@ use anchor_lang::prelude::*;
 ///    compressed account, reads cpi context and combines the instruction inputs
 ///    with verified inputs from the cpi context. The proof is verified and
 ///    other state transition is executed with the combined inputs.
+///
+/// Sessions:
+/// - a cpi context session is opened by the first invocation
+///   (first_set_context). The first invocation must use the associated Merkle
+///   tree of the cpi context account in at least one input or output compressed
+///   account.
+/// - subsequent invocations and the executing invocation are bound to the
+///   session by fee payer and slot. These invocations can use any state Merkle
+///   trees, hence a session can span multiple state Merkle trees.
 pub fn process_cpi_context<'info>(
     mut inputs: InstructionDataInvokeCpi,
     cpi_context_account: &mut Option<Account<'info, CpiContextAccount>>,
     fee_payer: Pubkey,
+    slot: u64,
     remaining_accounts: &[AccountInfo<'info>],
 ) -> Result<Option<InstructionDataInvokeCpi>> {
     let cpi_context = &inputs.cpi_context;
@@ -41,29 +51,22 @@ pub fn process_cpi_context<'info>(
             Some(cpi_context_account) => cpi_context_account,
             None => return err!(SystemProgramError::CpiContextAccountUndefined),
         };
-        let index = if !inputs
+        if inputs
             .input_compressed_accounts_with_merkle_context
             .is_empty()
+            && inputs.output_compressed_accounts.is_empty()
         {
-            inputs.input_compressed_accounts_with_merkle_context[0]
-                .merkle_context
-                .merkle_tree_pubkey_index
-        } else if !inputs.output_compressed_accounts.is_empty() {
-            inputs.output_compressed_accounts[0].merkle_tree_index
-        } else {
             return err!(SystemProgramError::NoInputs);
-        };
-        let first_merkle_tree_pubkey = remaining_accounts[index as usize].key();
-        if first_merkle_tree_pubkey != cpi_context_account.associated_merkle_tree {
-            msg!(
-                "first_merkle_tree_pubkey {:?} != associated_merkle_tree {:?}",
-                first_merkle_tree_pubkey,
-                cpi_context_account.associated_merkle_tree
-            );
-            return err!(SystemProgramError::CpiContextAssociatedMerkleTreeMismatch);
+        }
+        if cpi_context.set_context && cpi_context.first_set_context {
+            check_associated_merkle_tree(
+                &inputs,
+                &cpi_context_account.associated_merkle_tree,
+                remaining_accounts,
+            )?;
         }
         if cpi_context.set_context {
-            set_cpi_context(fee_payer, cpi_context_account, inputs)?;
+            set_cpi_context(fee_payer, slot, cpi_context_account, inputs)?;
             return Ok(None);
         } else {
             if cpi_context_account.context.is_empty() {
@@ -76,18 +79,54 @@ pub fn process_cpi_context<'info>(
                 msg!("fee payer : {:?}", fee_payer);
                 msg!("cpi context  : {:?}", cpi_context);
                 return err!(SystemProgramError::CpiContextFeePayerMismatch);
+            } else if cpi_context_account.slot != slot {
+                msg!("{} != {}", slot, cpi_context_account.slot);
+                return err!(SystemProgramError::CpiContextSlotMismatch);
             }
             inputs.combine(&cpi_context_account.context);
             // Reset cpi context account
             cpi_context_account.context = Vec::new();
             cpi_context_account.fee_payer = Pubkey::default();
+            cpi_context_account.slot = 0;
         }
     }
     Ok(Some(inputs))
 }
 
+/// Checks that at least one input or output compressed account of the
+/// invocation uses the associated Merkle tree of the cpi context account.
+fn check_associated_merkle_tree(
+    inputs: &InstructionDataInvokeCpi,
+    associated_merkle_tree: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<()> {
+    let is_associated_merkle_tree = |index: u8| {
+        remaining_accounts
+            .get(index as usize)
+            .map(|account_info| account_info.key() == *associated_merkle_tree)
+            .unwrap_or(false)
+    };
+    let uses_associated_merkle_tree = inputs
+        .input_compressed_accounts_with_merkle_context
+        .iter()
+        .any(|x| is_associated_merkle_tree(x.merkle_context.merkle_tree_pubkey_index))
+        || inputs
+            .output_compressed_accounts
+            .iter()
+            .any(|x| is_associated_merkle_tree(x.merkle_tree_index));
+    if !uses_associated_merkle_tree {
+        msg!(
+            "No input or output compressed account uses associated_merkle_tree {:?}",
+            associated_merkle_tree
+        );
+        return err!(SystemProgramError::CpiContextAssociatedMerkleTreeMismatch);
+    }
+    Ok(())
+}
+
 pub fn set_cpi_context(
     fee_payer: Pubkey,
+    slot: u64,
     cpi_context_account: &mut CpiContextAccount,
     mut inputs: InstructionDataInvokeCpi,
 ) -> Result<()> {
@@ -97,19 +136,26 @@ pub fn set_cpi_context(
     //   transaction
     // - leaf over data: There cannot be any leftover data in the
     //   account since if the transaction fails the account doesn't change.
+    // - data of a session which has not been executed: subsequent invocations
+    //   must be in the slot in which the session was opened.
 
     // Expected usage:
     // 1. The first invocation is marked with
     // No need to store the proof (except in first invokation),
     // cpi context, compress_or_decompress_lamports,
     // relay_fee
-    // 2. Subsequent invocations check the proof and fee payer
+    // 2. Subsequent invocations check the proof, fee payer and slot
     if inputs.cpi_context.unwrap().first_set_context {
         clean_input_data(&mut inputs);
         cpi_context_account.context = vec![inputs];
         cpi_context_account.fee_payer = fee_payer;
+        cpi_context_account.slot = slot;
     } else if fee_payer == cpi_context_account.fee_payer && !cpi_context_account.context.is_empty()
     {
+        if slot != cpi_context_account.slot {
+            msg!("{} != {}", slot, cpi_context_account.slot);
+            return err!(SystemProgramError::CpiContextSlotMismatch);
+        }
         clean_input_data(&mut inputs);
         cpi_context_account.context.push(inputs);
     } else {
@@ -132,6 +178,7 @@ fn clean_input_data(inputs: &mut InstructionDataInvokeCpi) {
 /// 2. Functional: Set cpi context subsequent invocation
 /// 3. Failing: Set cpi context fee payer mismatch
 /// 4. Failing: Set cpi context without first context
+/// 5. Failing: Set cpi context slot mismatch
 ///
 /// process cpi context:
 /// 1. CpiContextMissing
@@ -141,13 +188,15 @@ fn clean_input_data(inputs: &mut InstructionDataInvokeCpi) {
 /// 5. CpiContextEmpty
 /// 6. CpiContextFeePayerMismatch
 ///
+/// 7. CpiContextSlotMismatch
+///
 /// Functional process cpi context:
 /// 1. Set context
 /// 2. Combine (with malicious input in cpi context account)
+/// 3. Combine invocations using different state Merkle trees
+/// 4. Concurrent sessions in cpi context accounts of the same Merkle tree
 #[cfg(test)]
 mod tests {
-    use std::cell::RefCell;
-
     use crate::{
         sdk::{
             compressed_account::{
@@ -166,9 +215,44 @@ mod tests {
             fee_payer: Pubkey::new_unique(),
             associated_merkle_tree: Pubkey::new_unique(),
             context: vec![],
+            slot: 0,
         }
     }
 
+    /// Creates an account info which owns its key, lamports and data.
+    fn create_test_account_info(key: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            false,
+            Box::leak(Box::new(0)),
+            Box::leak(data.into_boxed_slice()),
+            &crate::ID,
+            false,
+            0,
+        )
+    }
+
+    fn create_test_cpi_context(
+        associated_merkle_tree: Pubkey,
+        context: Vec<InstructionDataInvokeCpi>,
+    ) -> Account<'static, CpiContextAccount> {
+        let cpi_context_content = CpiContextAccount {
+            fee_payer: Pubkey::default(),
+            associated_merkle_tree,
+            context,
+            slot: 0,
+        };
+        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
+        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
+        let account_info = create_test_account_info(Pubkey::new_unique(), data);
+        Account::try_from(Box::leak(Box::new(account_info))).unwrap()
+    }
+
+    fn create_test_merkle_tree_account_info(merkle_tree_pubkey: Pubkey) -> AccountInfo<'static> {
+        create_test_account_info(merkle_tree_pubkey, vec![172, 43, 172, 186, 29, 73, 219, 84])
+    }
+
     fn create_test_instruction_data(
         first_set_context: bool,
         set_context: bool,
@@ -196,6 +280,7 @@ mod tests {
                         leaf_index: 0,
                     },
                     root_index: iter.into(),
+                    close_address: false,
                 },
             ],
             output_compressed_accounts: vec![OutputCompressedAccountWithPackedContext {
@@ -225,7 +310,7 @@ mod tests {
         let mut cpi_context_account = create_test_cpi_context_account();
         let mut inputs = create_test_instruction_data(true, true, 1);
 
-        let result = set_cpi_context(fee_payer, &mut cpi_context_account, inputs.clone());
+        let result = set_cpi_context(fee_payer, 0, &mut cpi_context_account, inputs.clone());
         assert!(result.is_ok());
         assert_eq!(cpi_context_account.fee_payer, fee_payer);
         assert_eq!(cpi_context_account.context.len(), 1);
@@ -239,11 +324,12 @@ mod tests {
         let fee_payer = Pubkey::new_unique();
         let mut cpi_context_account = create_test_cpi_context_account();
         let inputs_first = create_test_instruction_data(true, true, 1);
-        set_cpi_context(fee_payer, &mut cpi_context_account, inputs_first.clone()).unwrap();
+        set_cpi_context(fee_payer, 0, &mut cpi_context_account, inputs_first.clone()).unwrap();
 
         let mut inputs_subsequent = create_test_instruction_data(false, true, 2);
         let result = set_cpi_context(
             fee_payer,
+            0,
             &mut cpi_context_account,
             inputs_subsequent.clone(),
         );
@@ -258,12 +344,13 @@ mod tests {
         let fee_payer = Pubkey::new_unique();
         let mut cpi_context_account = create_test_cpi_context_account();
         let inputs_first = create_test_instruction_data(true, true, 1);
-        set_cpi_context(fee_payer, &mut cpi_context_account, inputs_first.clone()).unwrap();
+        set_cpi_context(fee_payer, 0, &mut cpi_context_account, inputs_first.clone()).unwrap();
 
         let different_fee_payer = Pubkey::new_unique();
         let inputs_subsequent = create_test_instruction_data(false, true, 2);
         let result = set_cpi_context(
             different_fee_payer,
+            0,
             &mut cpi_context_account,
             inputs_subsequent,
         );
@@ -275,13 +362,30 @@ mod tests {
         let fee_payer = Pubkey::new_unique();
         let mut cpi_context_account = create_test_cpi_context_account();
         let inputs_first = create_test_instruction_data(false, true, 1);
-        let result = set_cpi_context(fee_payer, &mut cpi_context_account, inputs_first.clone());
+        let result = set_cpi_context(fee_payer, 0, &mut cpi_context_account, inputs_first.clone());
         assert_eq!(
             result,
             Err(SystemProgramError::CpiContextFeePayerMismatch.into())
         );
     }
 
+    #[test]
+    fn test_set_cpi_context_slot_mismatch() {
+        let fee_payer = Pubkey::new_unique();
+        let mut cpi_context_account = create_test_cpi_context_account();
+        let inputs_first = create_test_instruction_data(true, true, 1);
+        set_cpi_context(fee_payer, 1, &mut cpi_context_account, inputs_first).unwrap();
+        assert_eq!(cpi_context_account.slot, 1);
+
+        let inputs_subsequent = create_test_instruction_data(false, true, 2);
+        let result = set_cpi_context(fee_payer, 2, &mut cpi_context_account, inputs_subsequent);
+        assert_eq!(
+            result,
+            Err(SystemProgramError::CpiContextSlotMismatch.into())
+        );
+        assert_eq!(cpi_context_account.context.len(), 1);
+    }
+
     /// Check: process cpi 1
     #[test]
     fn test_process_cpi_context_both_none() {
@@ -289,7 +393,8 @@ mod tests {
         let inputs = create_test_instruction_data(false, true, 1);
         let mut cpi_context_account: Option<Account<CpiContextAccount>> = None;
 
-        let result = process_cpi_context(inputs.clone(), &mut cpi_context_account, fee_payer, &[]);
+        let result =
+            process_cpi_context(inputs.clone(), &mut cpi_context_account, fee_payer, 0, &[]);
         assert_eq!(
             result,
             Err(SystemProgramError::CpiContextAccountUndefined.into())
@@ -303,7 +408,7 @@ mod tests {
         let inputs = create_test_instruction_data(false, true, 1);
         let mut cpi_context_account: Option<Account<CpiContextAccount>> = None;
 
-        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, &[]);
+        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, 0, &[]);
         assert_eq!(
             result,
             Err(SystemProgramError::CpiContextAccountUndefined.into())
@@ -318,26 +423,8 @@ mod tests {
             cpi_context: None,
             ..create_test_instruction_data(false, true, 1)
         };
-        let mut lamports = 0;
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: Pubkey::new_unique(),
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, &[]);
+        let mut cpi_context_account = Some(create_test_cpi_context(Pubkey::new_unique(), vec![]));
+        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, 0, &[]);
         assert_eq!(result, Err(SystemProgramError::CpiContextMissing.into()));
     }
 
@@ -349,26 +436,8 @@ mod tests {
         inputs.input_compressed_accounts_with_merkle_context = vec![];
         inputs.output_compressed_accounts = vec![];
 
-        let mut lamports = 0;
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: Pubkey::new_unique(),
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, &[]);
+        let mut cpi_context_account = Some(create_test_cpi_context(Pubkey::new_unique(), vec![]));
+        let result = process_cpi_context(inputs, &mut cpi_context_account, fee_payer, 0, &[]);
         assert_eq!(result, Err(SystemProgramError::NoInputs.into()));
     }
 
@@ -377,44 +446,15 @@ mod tests {
     fn test_process_cpi_context_associated_tree_mismatch() {
         let fee_payer = Pubkey::new_unique();
         let inputs = create_test_instruction_data(true, true, 1);
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let invalid_merkle_tree_pubkey = Pubkey::new_unique();
-        let merkle_tree_account_info = AccountInfo {
-            key: &invalid_merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(Pubkey::new_unique());
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs,
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert_eq!(
@@ -428,43 +468,15 @@ mod tests {
     fn test_process_cpi_context_no_set_context() {
         let fee_payer = Pubkey::new_unique();
         let inputs = create_test_instruction_data(false, false, 1);
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let merkle_tree_account_info = AccountInfo {
-            key: &merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs.clone(),
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert_eq!(result, Err(SystemProgramError::CpiContextEmpty.into()));
@@ -475,43 +487,15 @@ mod tests {
     fn test_process_cpi_context_empty_context_error() {
         let fee_payer = Pubkey::default();
         let inputs = create_test_instruction_data(false, true, 1);
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let merkle_tree_account_info = AccountInfo {
-            key: &merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs,
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert_eq!(
@@ -525,43 +509,15 @@ mod tests {
     fn test_process_cpi_context_fee_payer_mismatch_error() {
         let fee_payer = Pubkey::new_unique();
         let inputs = create_test_instruction_data(true, true, 1);
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let merkle_tree_account_info = AccountInfo {
-            key: &merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs.clone(),
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert!(result.is_ok());
@@ -571,6 +527,7 @@ mod tests {
             inputs,
             &mut cpi_context_account,
             invalid_fee_payer,
+            0,
             remaining_accounts,
         );
         assert_eq!(
@@ -583,43 +540,15 @@ mod tests {
     fn test_process_cpi_context_set_context() {
         let fee_payer = Pubkey::new_unique();
         let mut inputs = create_test_instruction_data(true, true, 1);
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let merkle_tree_account_info = AccountInfo {
-            key: &merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs.clone(),
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert!(result.is_ok());
@@ -636,43 +565,18 @@ mod tests {
         let mut inputs = create_test_instruction_data(true, true, 1);
         let malicious_inputs = create_test_instruction_data(true, true, 100);
 
-        let mut lamports = 0;
         let merkle_tree_pubkey = Pubkey::new_unique();
-        let cpi_context_content = CpiContextAccount {
-            fee_payer: Pubkey::default(),
-            associated_merkle_tree: merkle_tree_pubkey,
-            context: vec![malicious_inputs],
-        };
-        let mut data = vec![22, 20, 149, 218, 74, 204, 128, 166];
-        data.extend_from_slice(&cpi_context_content.try_to_vec().unwrap());
-        let account_info = AccountInfo {
-            key: &Pubkey::new_unique(),
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
-        let mut cpi_context_account = Some(Account::try_from(account_info.as_ref()).unwrap());
-        let mut mt_lamports = 0;
-        let mut data = vec![172, 43, 172, 186, 29, 73, 219, 84];
-        let merkle_tree_account_info = AccountInfo {
-            key: &merkle_tree_pubkey,
-            is_signer: false,
-            is_writable: false,
-            lamports: RefCell::new(&mut mt_lamports).into(),
-            data: RefCell::new(data.as_mut_slice()).into(),
-            owner: &crate::ID,
-            rent_epoch: 0,
-            executable: false,
-        };
+        let mut cpi_context_account = Some(create_test_cpi_context(
+            merkle_tree_pubkey,
+            vec![malicious_inputs],
+        ));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
         let remaining_accounts = &[merkle_tree_account_info];
         let result = process_cpi_context(
             inputs.clone(),
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert!(result.is_ok());
@@ -688,6 +592,7 @@ mod tests {
                 inputs.clone(),
                 &mut cpi_context_account,
                 fee_payer,
+                0,
                 remaining_accounts,
             );
             assert!(result.is_ok());
@@ -709,6 +614,7 @@ mod tests {
             inputs.clone(),
             &mut cpi_context_account,
             fee_payer,
+            0,
             remaining_accounts,
         );
         assert!(result.is_ok());
@@ -741,4 +647,191 @@ mod tests {
         );
         assert_eq!(cpi_context_account.as_ref().unwrap().context.len(), 0);
     }
+
+    /// Check: process cpi 7
+    #[test]
+    fn test_process_cpi_context_slot_mismatch_error() {
+        let fee_payer = Pubkey::new_unique();
+        let inputs = create_test_instruction_data(true, true, 1);
+        let merkle_tree_pubkey = Pubkey::new_unique();
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
+        let remaining_accounts = &[merkle_tree_account_info];
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            1,
+            remaining_accounts,
+        );
+        assert!(result.is_ok());
+        // Executing the session in a later slot fails.
+        let inputs = create_test_instruction_data(false, false, 1);
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            2,
+            remaining_accounts,
+        );
+        assert_eq!(
+            result,
+            Err(SystemProgramError::CpiContextSlotMismatch.into())
+        );
+        assert_eq!(cpi_context_account.as_ref().unwrap().context.len(), 1);
+    }
+
+    /// A cpi context session is opened with the associated Merkle tree as
+    /// output Merkle tree, subsequent invocations use a different state Merkle
+    /// tree only.
+    #[test]
+    fn test_process_cpi_context_multiple_merkle_trees() {
+        let fee_payer = Pubkey::new_unique();
+        let slot = 10;
+        let merkle_tree_pubkey = Pubkey::new_unique();
+        let other_merkle_tree_pubkey = Pubkey::new_unique();
+        let mut cpi_context_account = Some(create_test_cpi_context(merkle_tree_pubkey, vec![]));
+        let merkle_tree_account_info = create_test_merkle_tree_account_info(merkle_tree_pubkey);
+        let other_merkle_tree_account_info =
+            create_test_merkle_tree_account_info(other_merkle_tree_pubkey);
+        let remaining_accounts = &[merkle_tree_account_info, other_merkle_tree_account_info];
+        let with_merkle_tree_indices =
+            |mut inputs: InstructionDataInvokeCpi, input_index: u8, output_index: u8| {
+                inputs.input_compressed_accounts_with_merkle_context[0]
+                    .merkle_context
+                    .merkle_tree_pubkey_index = input_index;
+                inputs.output_compressed_accounts[0].merkle_tree_index = output_index;
+                inputs
+            };
+
+        // Opening a session without the associated Merkle tree fails.
+        let inputs = with_merkle_tree_indices(create_test_instruction_data(true, true, 1), 1, 1);
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            slot,
+            remaining_accounts,
+        );
+        assert_eq!(
+            result,
+            Err(SystemProgramError::CpiContextAssociatedMerkleTreeMismatch.into())
+        );
+        // Open session, the associated Merkle tree is not the first Merkle tree.
+        let inputs = with_merkle_tree_indices(create_test_instruction_data(true, true, 1), 1, 0);
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            slot,
+            remaining_accounts,
+        );
+        assert_eq!(result, Ok(None));
+        // Subsequent invocation using the other Merkle tree only.
+        let inputs = with_merkle_tree_indices(create_test_instruction_data(false, true, 2), 1, 1);
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            slot,
+            remaining_accounts,
+        );
+        assert_eq!(result, Ok(None));
+        assert_eq!(cpi_context_account.as_ref().unwrap().context.len(), 2);
+        // Executing invocation using the other Merkle tree only.
+        let inputs = with_merkle_tree_indices(create_test_instruction_data(false, false, 3), 1, 1);
+        let result = process_cpi_context(
+            inputs,
+            &mut cpi_context_account,
+            fee_payer,
+            slot,
+            remaining_accounts,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            result.input_compressed_accounts_with_merkle_context.len(),
+            3
+        );
+        assert_eq!(result.output_compressed_accounts.len(), 3);
+        assert_eq!(result.output_compressed_accounts[1].merkle_tree_index, 0);
+        assert_eq!(cpi_context_account.as_ref().unwrap().context.len(), 0);
+        assert_eq!(cpi_context_account.as_ref().unwrap().slot, 0);
+    }
+
+    /// Two cpi context accounts associated with the same Merkle tree hold
+    /// independent sessions of different fee payers in the same slot.
+    #[test]
+    fn test_process_cpi_context_multiple_accounts_per_merkle_tree() {
+        let slot = 10;
+        let merkle_tree_pubkey = Pubkey::new_unique();
+        let remaining_accounts = &[create_test_merkle_tree_account_info(merkle_tree_pubkey)];
+        let mut cpi_context_accounts = [
+            Some(create_test_cpi_context(merkle_tree_pubkey, vec![])),
+            Some(create_test_cpi_context(merkle_tree_pubkey, vec![])),
+        ];
+        let fee_payers = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        for (i, (cpi_context_account, fee_payer)) in cpi_context_accounts
+            .iter_mut()
+            .zip(fee_payers.iter())
+            .enumerate()
+        {
+            let inputs = create_test_instruction_data(true, true, i as u8);
+            let result = process_cpi_context(
+                inputs,
+                cpi_context_account,
+                *fee_payer,
+                slot,
+                remaining_accounts,
+            );
+            assert_eq!(result, Ok(None));
+        }
+        for (i, (cpi_context_account, fee_payer)) in cpi_context_accounts
+            .iter_mut()
+            .zip(fee_payers.iter())
+            .enumerate()
+        {
+            let cpi_context_account_data = cpi_context_account.as_ref().unwrap();
+            assert_eq!(cpi_context_account_data.fee_payer, *fee_payer);
+            assert_eq!(cpi_context_account_data.context.len(), 1);
+            assert_eq!(
+                cpi_context_account_data.context[0].output_compressed_accounts[0]
+                    .compressed_account
+                    .lamports,
+                i as u64
+            );
+            // The session of one account can't be executed by the fee payer
+            // of the other account.
+            let inputs = create_test_instruction_data(false, false, 2);
+            let result = process_cpi_context(
+                inputs.clone(),
+                cpi_context_account,
+                fee_payers[1 - i],
+                slot,
+                remaining_accounts,
+            );
+            assert_eq!(
+                result,
+                Err(SystemProgramError::CpiContextFeePayerMismatch.into())
+            );
+            let result = process_cpi_context(
+                inputs,
+                cpi_context_account,
+                *fee_payer,
+                slot,
+                remaining_accounts,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(result.output_compressed_accounts.len(), 2);
+            assert_eq!(
+                result.output_compressed_accounts[1]
+                    .compressed_account
+                    .lamports,
+                i as u64
+            );
+            assert_eq!(cpi_context_account.as_ref().unwrap().context.len(), 0);
+        }
+    }
 }
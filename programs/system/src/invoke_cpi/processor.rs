@@ -37,6 +37,7 @@ pub fn process_invoke_cpi<'a, 'b, 'c: 'info + 'b, 'info>(
         inputs,
         &mut ctx.accounts.cpi_context_account,
         ctx.accounts.fee_payer.key(),
+        Clock::get()?.slot,
         ctx.remaining_accounts,
     ) {
         Ok(Some(inputs)) => inputs,
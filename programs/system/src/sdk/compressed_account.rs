@@ -10,6 +10,9 @@ pub struct PackedCompressedAccountWithMerkleContext {
     pub merkle_context: PackedMerkleContext,
     /// Index of root used in inclusion validity proof.
     pub root_index: u16,
+    /// Closes the address of the compressed account. A closed address cannot
+    /// be assigned to an output compressed account.
+    pub close_address: bool,
 }
 
 #[derive(Debug, PartialEq, Default, Clone, AnchorSerialize, AnchorDeserialize)]
@@ -24,6 +24,10 @@ pub struct PublicTransactionEvent {
     pub compress_or_decompress_lamports: Option<u64>,
     pub pubkey_array: Vec<Pubkey>,
     pub message: Option<Vec<u8>>,
+    /// Addresses of input compressed accounts which have been closed, either
+    /// with close_address or by not assigning the address to an output
+    /// compressed account. Closed addresses cannot be used again.
+    pub closed_addresses: Vec<[u8; 32]>,
 }
 
 impl PublicTransactionEvent {
@@ -90,6 +94,11 @@ impl PublicTransactionEvent {
             None => writer.write_all(&[0]),
         }?;
 
+        writer.write_all(&(self.closed_addresses.len() as u32).to_le_bytes())?;
+        for address in self.closed_addresses.iter() {
+            writer.write_all(address)?;
+        }
+
         Ok(())
     }
 }
@@ -137,6 +146,7 @@ pub mod test {
             compress_or_decompress_lamports: Some(5000),
             pubkey_array: vec![Keypair::new().pubkey(), Keypair::new().pubkey()],
             message: Some(vec![8, 9, 10]),
+            closed_addresses: vec![[11u8; 32], [12u8; 32]],
         };
 
         // Serialize using Borsh
@@ -200,6 +210,7 @@ pub mod test {
                 compress_or_decompress_lamports: if rng.gen() { Some(rng.gen()) } else { None },
                 pubkey_array: pubkeys,
                 message,
+                closed_addresses: (0..rng.gen_range(0..10)).map(|_| rng.gen()).collect(),
             };
 
             let borsh_serialized = event.try_to_vec().unwrap();
@@ -1,7 +1,12 @@
 #![cfg(not(target_os = "solana"))]
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
 
 use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use light_registry::{get_verifying_key_pda_address, CircuitShape};
+use light_verifier::client::TREE_HEIGHT;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
@@ -21,6 +26,32 @@ pub fn get_sol_pool_pda() -> Pubkey {
     Pubkey::find_program_address(&[SOL_POOL_PDA_SEED], &crate::ID).0
 }
 
+/// Addresses of verifying key accounts by circuit shape, so that they are
+/// derived once per shape rather than for every instruction.
+static VERIFYING_KEY_PDAS: OnceLock<Mutex<HashMap<[u8; 4], Pubkey>>> = OnceLock::new();
+
+/// Returns the verifying key account, which instructions with a proof of
+/// `num_leaves` input compressed accounts and `num_addresses` new addresses
+/// have to pass as a remaining account. `None` if nothing is proven.
+pub fn get_verifying_key_account_meta(
+    num_leaves: usize,
+    num_addresses: usize,
+) -> Option<AccountMeta> {
+    let shape = CircuitShape::new(
+        TREE_HEIGHT as u8,
+        u8::try_from(num_leaves).ok()?,
+        u8::try_from(num_addresses).ok()?,
+    )
+    .ok()?;
+    let verifying_key_pda = *VERIFYING_KEY_PDAS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(shape.to_bytes())
+        .or_insert_with(|| get_verifying_key_pda_address(&shape).0);
+    Some(AccountMeta::new_readonly(verifying_key_pda, false))
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn create_invoke_instruction(
     fee_payer: &Pubkey,
@@ -123,6 +154,7 @@ pub fn create_invoke_instruction_data_and_remaining_accounts(
                 leaf_index: context.leaf_index,
             },
             root_index: input_root_indices[i],
+            close_address: false,
         });
     }
 
@@ -194,10 +226,16 @@ pub fn create_invoke_instruction_data_and_remaining_accounts(
         .collect::<Vec<(AccountMeta, usize)>>();
     // hash maps are not sorted so we need to sort manually and collect into a vector again
     remaining_accounts.sort_by(|a, b| a.1.cmp(&b.1));
-    let remaining_accounts = remaining_accounts
+    let mut remaining_accounts = remaining_accounts
         .iter()
         .map(|(k, _)| k.clone())
         .collect::<Vec<AccountMeta>>();
+    if proof.is_some() {
+        remaining_accounts.extend(get_verifying_key_account_meta(
+            merkle_context.len(),
+            new_address_params.len(),
+        ));
+    }
 
     let inputs_struct = InstructionDataInvoke {
         relay_fee: None,
@@ -401,5 +439,9 @@ mod test {
                 .merkle_tree_index as usize],
             AccountMeta::new(merkle_tree_pubkey_1, false)
         );
+        assert_eq!(
+            instruction.accounts.last(),
+            get_verifying_key_account_meta(2, 0).as_ref()
+        );
     }
 }
@@ -0,0 +1,15 @@
+#!/bin/bash
+# Builds the test build of the system program, which additionally accepts
+# proofs of `light_prover_client::prover::MockProver`, and deploys it as
+# target/deploy/light_system_program_mock_proofs.so. The build uses its own
+# target directory and is never used outside of program tests.
+set -e
+cd "$(git rev-parse --show-toplevel)"
+
+OUT_DIR="target/mock-proofs"
+
+RUSTFLAGS="--cfg mock_proofs" CARGO_TARGET_DIR="$OUT_DIR/build" cargo build-sbf \
+    --manifest-path programs/system/Cargo.toml \
+    --sbf-out-dir "$OUT_DIR/deploy"
+mkdir -p target/deploy
+cp "$OUT_DIR/deploy/light_system_program.so" target/deploy/light_system_program_mock_proofs.so
@@ -0,0 +1,312 @@
+use anchor_lang::{error::ErrorCode, prelude::*};
+use light_hasher::Poseidon;
+pub use light_hasher::{errors::HasherError, sponge::Sponge, DataHasher, Hasher};
+use light_system_program::{
+    sdk::{
+        address::derive_address,
+        compressed_account::{CompressedAccount, CompressedAccountData},
+    },
+    NewAddressParamsPacked, OutputCompressedAccountWithPackedContext,
+};
+use light_utils::hash_to_bn254_field_size_be;
+
+/// Discriminator of a compressed account type. Stored in
+/// `CompressedAccountData::discriminator`.
+pub trait LightDiscriminator {
+    const LIGHT_DISCRIMINATOR: [u8; 8];
+}
+
+/// Program data stored in a compressed account.
+///
+/// Implemented with `#[derive(LightAccount)]`, which generates the
+/// discriminator and the data hash.
+pub trait LightAccount:
+    LightDiscriminator + DataHasher + AnchorSerialize + AnchorDeserialize + Sized
+{
+    /// Serializes the account into compressed account data with a Poseidon
+    /// data hash.
+    fn to_compressed_account_data(&self) -> Result<CompressedAccountData> {
+        Ok(CompressedAccountData {
+            discriminator: Self::LIGHT_DISCRIMINATOR,
+            data: self.try_to_vec()?,
+            data_hash: self.hash::<Poseidon>().map_err(ProgramError::from)?,
+        })
+    }
+
+    /// Deserializes the account from compressed account data, checks the
+    /// discriminator.
+    fn try_from_compressed_account_data(data: &CompressedAccountData) -> Result<Self> {
+        if data.discriminator != Self::LIGHT_DISCRIMINATOR {
+            return err!(ErrorCode::AccountDiscriminatorMismatch);
+        }
+        Ok(Self::deserialize(&mut data.data.as_slice())?)
+    }
+
+    /// Derives the address seed of an account of this type from the owning
+    /// program id and custom seeds, the seed is unique per program, account
+    /// type and custom seeds. Every custom seed is prefixed with its length so
+    /// that different splits of the same bytes derive different seeds.
+    fn address_seed(program_id: &Pubkey, seeds: &[&[u8]]) -> Result<[u8; 32]> {
+        let mut bytes = program_id.to_bytes().to_vec();
+        bytes.extend_from_slice(&Self::LIGHT_DISCRIMINATOR);
+        for seed in seeds.iter() {
+            bytes.extend_from_slice(&(seed.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(seed);
+        }
+        Ok(hash_to_bn254_field_size_be(bytes.as_slice())
+            .ok_or(ProgramError::InvalidSeeds)?
+            .0)
+    }
+
+    /// Derives the address of an account in the given address Merkle tree.
+    fn derive_address(
+        address_merkle_tree_pubkey: &Pubkey,
+        address_seed: &[u8; 32],
+    ) -> Result<[u8; 32]> {
+        derive_address(address_merkle_tree_pubkey, address_seed)
+    }
+
+    /// Creates an output compressed account which carries the account data.
+    fn to_output_compressed_account(
+        &self,
+        owner: &Pubkey,
+        address: Option<[u8; 32]>,
+        merkle_tree_index: u8,
+    ) -> Result<OutputCompressedAccountWithPackedContext> {
+        Ok(OutputCompressedAccountWithPackedContext {
+            compressed_account: CompressedAccount {
+                owner: *owner,
+                lamports: 0,
+                address,
+                data: Some(self.to_compressed_account_data()?),
+            },
+            merkle_tree_index,
+        })
+    }
+
+    /// Creates an output compressed account with the new address of
+    /// `new_address_params`. The seed of `new_address_params` has to be the
+    /// address seed of this account type derived from `owner` and `seeds`.
+    /// The address Merkle tree is looked up in `remaining_accounts`.
+    fn new_compressed_account(
+        &self,
+        owner: &Pubkey,
+        seeds: &[&[u8]],
+        new_address_params: &NewAddressParamsPacked,
+        remaining_accounts: &[AccountInfo],
+        merkle_tree_index: u8,
+    ) -> Result<OutputCompressedAccountWithPackedContext> {
+        if new_address_params.seed != Self::address_seed(owner, seeds)? {
+            msg!("The seed of the new address params is no address seed of the account.");
+            return err!(ErrorCode::ConstraintSeeds);
+        }
+        let address_merkle_tree = remaining_accounts
+            .get(new_address_params.address_merkle_tree_account_index as usize)
+            .ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        let address = Self::derive_address(&address_merkle_tree.key(), &new_address_params.seed)?;
+        self.to_output_compressed_account(owner, Some(address), merkle_tree_index)
+    }
+}
+
+/// Hashes a field of a compressed account to a bn254 field element. Used by
+/// `#[derive(LightAccount)]`.
+pub fn hash_field<T: AnchorSerialize>(field: &T) -> std::result::Result<[u8; 32], HasherError> {
+    let bytes = field
+        .try_to_vec()
+        .map_err(|_| HasherError::BorshSerialization)?;
+    Ok(hash_to_bn254_field_size_be(bytes.as_slice())
+        .ok_or(HasherError::HashToFieldSize)?
+        .0)
+}
+
+/// Absorbs the borsh serialization of a field into the sponge. Used by
+/// `#[derive(SpongeHasher)]`.
+pub fn absorb_field<H: Hasher, T: AnchorSerialize>(
+    sponge: &mut Sponge<H>,
+    field: &T,
+) -> std::result::Result<(), HasherError> {
+    let bytes = field
+        .try_to_vec()
+        .map_err(|_| HasherError::BorshSerialization)?;
+    sponge.absorb(bytes.as_slice())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{LightAccount, SpongeHasher};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, AnchorSerialize, AnchorDeserialize, LightAccount)]
+    struct MyCompressedAccount {
+        owner: Pubkey,
+        amount: u64,
+        data: Option<Vec<u8>>,
+    }
+
+    /// Tests:
+    /// 1. compressed account data round trip
+    /// 2. data hash is the hash of the hashed fields
+    /// 3. discriminator mismatch fails
+    /// 4. address derivation
+    /// 5. seeds are length prefixed
+    /// 6. new compressed account with a valid and an invalid address seed
+    #[test]
+    fn test_light_account() {
+        let account = MyCompressedAccount {
+            owner: Pubkey::new_unique(),
+            amount: 1,
+            data: Some(vec![1, 2, 3]),
+        };
+        // 1. compressed account data round trip
+        let mut data = account.to_compressed_account_data().unwrap();
+        assert_eq!(data.discriminator, MyCompressedAccount::LIGHT_DISCRIMINATOR);
+        assert_eq!(
+            MyCompressedAccount::try_from_compressed_account_data(&data).unwrap(),
+            account
+        );
+        // 2. data hash is the hash of the hashed fields
+        let expected_hash = Poseidon::hashv(&[
+            hash_field(&account.owner).unwrap().as_slice(),
+            hash_field(&account.amount).unwrap().as_slice(),
+            hash_field(&account.data).unwrap().as_slice(),
+        ])
+        .unwrap();
+        assert_eq!(data.data_hash, expected_hash);
+        // 3. discriminator mismatch fails
+        data.discriminator = [0u8; 8];
+        assert!(MyCompressedAccount::try_from_compressed_account_data(&data).is_err());
+        // 4. address derivation
+        let program_id = Pubkey::new_unique();
+        let address_merkle_tree_pubkey = Pubkey::new_unique();
+        let seed = MyCompressedAccount::address_seed(&program_id, &[b"seed"]).unwrap();
+        assert_ne!(
+            seed,
+            MyCompressedAccount::address_seed(&program_id, &[b"seed2"]).unwrap()
+        );
+        assert_ne!(
+            seed,
+            MyCompressedAccount::address_seed(&Pubkey::new_unique(), &[b"seed"]).unwrap()
+        );
+        let address =
+            MyCompressedAccount::derive_address(&address_merkle_tree_pubkey, &seed).unwrap();
+        assert_eq!(
+            address,
+            derive_address(&address_merkle_tree_pubkey, &seed).unwrap()
+        );
+        let output = account
+            .to_output_compressed_account(&program_id, Some(address), 1)
+            .unwrap();
+        assert_eq!(output.compressed_account.address, Some(address));
+        assert_eq!(output.compressed_account.owner, program_id);
+        assert_eq!(output.merkle_tree_index, 1);
+        // 5. seeds are length prefixed
+        assert_ne!(
+            MyCompressedAccount::address_seed(&program_id, &[b"ab", b"c"]).unwrap(),
+            MyCompressedAccount::address_seed(&program_id, &[b"a", b"bc"]).unwrap()
+        );
+        assert_ne!(
+            MyCompressedAccount::address_seed(&program_id, &[b"abc"]).unwrap(),
+            MyCompressedAccount::address_seed(&program_id, &[b"abc", b""]).unwrap()
+        );
+        // 6. new compressed account with a valid and an invalid address seed
+        let mut lamports = 0;
+        let mut account_data = vec![];
+        let remaining_accounts = [AccountInfo::new(
+            &address_merkle_tree_pubkey,
+            false,
+            false,
+            &mut lamports,
+            &mut account_data,
+            &program_id,
+            false,
+            0,
+        )];
+        let mut new_address_params = NewAddressParamsPacked {
+            seed,
+            address_queue_account_index: 1,
+            address_merkle_tree_account_index: 0,
+            address_merkle_tree_root_index: 0,
+        };
+        let output = account
+            .new_compressed_account(
+                &program_id,
+                &[b"seed"],
+                &new_address_params,
+                &remaining_accounts,
+                0,
+            )
+            .unwrap();
+        assert_eq!(output.compressed_account.address, Some(address));
+        new_address_params.seed = [1u8; 32];
+        assert_eq!(
+            account.new_compressed_account(
+                &program_id,
+                &[b"seed"],
+                &new_address_params,
+                &remaining_accounts,
+                0,
+            ),
+            Err(ErrorCode::ConstraintSeeds.into())
+        );
+    }
+
+    #[derive(AnchorSerialize, AnchorDeserialize, SpongeHasher)]
+    struct MyData {
+        owner: Pubkey,
+        data: Vec<u8>,
+        a: u8,
+        b: u8,
+        c: u8,
+        d: u8,
+        e: u8,
+        f: u8,
+        g: u8,
+        h: u8,
+        i: u8,
+        j: u8,
+        k: u8,
+        l: u8,
+        m: u8,
+    }
+
+    /// Tests:
+    /// 1. data hash is the sponge hash of the serialized fields
+    /// 2. data longer than a single Poseidon invocation can take is hashed
+    #[test]
+    fn test_sponge_hasher() {
+        let mut my_data = MyData {
+            owner: Pubkey::new_unique(),
+            data: vec![1, 2, 3],
+            a: 1,
+            b: 2,
+            c: 3,
+            d: 4,
+            e: 5,
+            f: 6,
+            g: 7,
+            h: 8,
+            i: 9,
+            j: 10,
+            k: 11,
+            l: 12,
+            m: 13,
+        };
+        // 1. data hash is the sponge hash of the serialized fields
+        let hash = my_data.hash::<Poseidon>().unwrap();
+        let mut domain = [0u8; 8];
+        domain.copy_from_slice(
+            &anchor_lang::solana_program::hash::hash(b"sponge_hasher:MyData").to_bytes()[..8],
+        );
+        let expected_hash =
+            light_hasher::sponge::hash_bytes::<Poseidon>(&domain, &my_data.try_to_vec().unwrap())
+                .unwrap();
+        assert_eq!(hash, expected_hash);
+        // 2. long data
+        my_data.data = vec![1u8; 1000];
+        let long_data_hash = my_data.hash::<Poseidon>().unwrap();
+        assert_ne!(long_data_hash, hash);
+        my_data.data[999] = 2;
+        assert_ne!(my_data.hash::<Poseidon>().unwrap(), long_data_hash);
+    }
+}
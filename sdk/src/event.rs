@@ -0,0 +1,225 @@
+use anchor_lang::{prelude::borsh, solana_program::pubkey::Pubkey, AnchorDeserialize};
+use light_concurrent_merkle_tree::event::MerkleTreeEvent;
+use light_system_program::{
+    sdk::event::{MerkleTreeSequenceNumber, PublicTransactionEvent},
+    OutputCompressedAccountWithPackedContext,
+};
+pub use light_utils::event::*;
+
+/// Version of events emitted before the event envelope was introduced. Legacy
+/// events are the raw borsh serialized payload without discriminator and
+/// version.
+pub const LEGACY_EVENT_VERSION: u8 = 0;
+
+/// Decoder for events emitted through the noop program.
+///
+/// Implementors decode every version of their event into the latest layout.
+pub trait DecodeEvent: Sized {
+    const DISCRIMINATOR: [u8; EVENT_DISCRIMINATOR_LEN];
+
+    /// Decodes the payload of the given event version.
+    fn decode_version(version: u8, payload: &[u8]) -> Result<Self, EventError>;
+
+    /// Decodes event data with an event envelope. Falls back to the legacy
+    /// layout if the data does not start with the event discriminator.
+    fn decode(data: &[u8]) -> Result<Self, EventError> {
+        match EventHeader::split_with_discriminator(data, &Self::DISCRIMINATOR) {
+            Ok((version, payload)) => Self::decode_version(version, payload),
+            Err(EventError::UnknownDiscriminator(_)) | Err(EventError::InvalidEventHeader) => {
+                Self::decode_version(LEGACY_EVENT_VERSION, data)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Layout of [`PublicTransactionEvent`] before closed addresses were added.
+#[derive(Debug, Clone, AnchorDeserialize)]
+struct PublicTransactionEventV0 {
+    input_compressed_account_hashes: Vec<[u8; 32]>,
+    output_compressed_account_hashes: Vec<[u8; 32]>,
+    output_compressed_accounts: Vec<OutputCompressedAccountWithPackedContext>,
+    output_leaf_indices: Vec<u32>,
+    sequence_numbers: Vec<MerkleTreeSequenceNumber>,
+    relay_fee: Option<u64>,
+    is_compress: bool,
+    compress_or_decompress_lamports: Option<u64>,
+    pubkey_array: Vec<Pubkey>,
+    message: Option<Vec<u8>>,
+}
+
+impl From<PublicTransactionEventV0> for PublicTransactionEvent {
+    fn from(event: PublicTransactionEventV0) -> Self {
+        PublicTransactionEvent {
+            input_compressed_account_hashes: event.input_compressed_account_hashes,
+            output_compressed_account_hashes: event.output_compressed_account_hashes,
+            output_compressed_accounts: event.output_compressed_accounts,
+            output_leaf_indices: event.output_leaf_indices,
+            sequence_numbers: event.sequence_numbers,
+            relay_fee: event.relay_fee,
+            is_compress: event.is_compress,
+            compress_or_decompress_lamports: event.compress_or_decompress_lamports,
+            pubkey_array: event.pubkey_array,
+            message: event.message,
+            closed_addresses: Vec::new(),
+        }
+    }
+}
+
+impl DecodeEvent for PublicTransactionEvent {
+    const DISCRIMINATOR: [u8; EVENT_DISCRIMINATOR_LEN] = PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR;
+
+    fn decode_version(version: u8, payload: &[u8]) -> Result<Self, EventError> {
+        match version {
+            LEGACY_EVENT_VERSION => Ok(PublicTransactionEventV0::try_from_slice(payload)?.into()),
+            1 => Ok(PublicTransactionEvent::try_from_slice(payload)?),
+            _ => Err(EventError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl DecodeEvent for MerkleTreeEvent {
+    const DISCRIMINATOR: [u8; EVENT_DISCRIMINATOR_LEN] = MERKLE_TREE_EVENT_DISCRIMINATOR;
+
+    fn decode_version(version: u8, payload: &[u8]) -> Result<Self, EventError> {
+        match version {
+            LEGACY_EVENT_VERSION | 1 => Ok(MerkleTreeEvent::try_from_slice(payload)?),
+            _ => Err(EventError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Any event emitted by the Light Protocol programs.
+#[derive(Debug)]
+pub enum LightEvent {
+    PublicTransaction(PublicTransactionEvent),
+    MerkleTree(MerkleTreeEvent),
+}
+
+/// Decodes enveloped event data by dispatching on the event discriminator.
+/// Legacy events without envelope are not self-describing and have to be
+/// decoded with [`DecodeEvent::decode`] of the expected event type.
+pub fn decode_event(data: &[u8]) -> Result<LightEvent, EventError> {
+    let (header, payload) = EventHeader::split(data)?;
+    match header.discriminator {
+        PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR => Ok(LightEvent::PublicTransaction(
+            PublicTransactionEvent::decode_version(header.version, payload)?,
+        )),
+        MERKLE_TREE_EVENT_DISCRIMINATOR => Ok(LightEvent::MerkleTree(
+            MerkleTreeEvent::decode_version(header.version, payload)?,
+        )),
+        discriminator => Err(EventError::UnknownDiscriminator(discriminator)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::AnchorSerialize;
+    use light_concurrent_merkle_tree::event::NullifierEvent;
+    use light_system_program::sdk::compressed_account::CompressedAccount;
+
+    use super::*;
+
+    fn public_transaction_event() -> PublicTransactionEvent {
+        PublicTransactionEvent {
+            input_compressed_account_hashes: vec![[1u8; 32]],
+            output_compressed_account_hashes: vec![[2u8; 32]],
+            output_compressed_accounts: vec![OutputCompressedAccountWithPackedContext {
+                compressed_account: CompressedAccount {
+                    owner: Pubkey::new_unique(),
+                    lamports: 100,
+                    address: Some([3u8; 32]),
+                    data: None,
+                },
+                merkle_tree_index: 1,
+            }],
+            output_leaf_indices: vec![4],
+            sequence_numbers: vec![MerkleTreeSequenceNumber {
+                pubkey: Pubkey::new_unique(),
+                seq: 5,
+            }],
+            relay_fee: None,
+            is_compress: true,
+            compress_or_decompress_lamports: Some(100),
+            pubkey_array: vec![Pubkey::new_unique()],
+            message: None,
+            closed_addresses: vec![[6u8; 32]],
+        }
+    }
+
+    /// Tests:
+    /// 1. enveloped public transaction event round trip
+    /// 2. enveloped public transaction event via decode_event
+    /// 3. unsupported version fails
+    #[test]
+    fn test_decode_public_transaction_event() {
+        let event = public_transaction_event();
+        let mut data = Vec::new();
+        EventHeader::new(
+            PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR,
+            PUBLIC_TRANSACTION_EVENT_VERSION,
+        )
+        .write(&mut data)
+        .unwrap();
+        event.man_serialize(&mut data).unwrap();
+        // 1. enveloped public transaction event round trip
+        assert_eq!(PublicTransactionEvent::decode(&data).unwrap(), event);
+        // 2. enveloped public transaction event via decode_event
+        match decode_event(&data).unwrap() {
+            LightEvent::PublicTransaction(decoded) => assert_eq!(decoded, event),
+            LightEvent::MerkleTree(_) => panic!("Expected public transaction event"),
+        }
+        // 3. unsupported version fails
+        data[EVENT_DISCRIMINATOR_LEN] = PUBLIC_TRANSACTION_EVENT_VERSION + 1;
+        assert!(matches!(
+            PublicTransactionEvent::decode(&data),
+            Err(EventError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_legacy_public_transaction_event() {
+        let mut event = public_transaction_event();
+        event.closed_addresses = Vec::new();
+        let mut data = event.try_to_vec().unwrap();
+        // Legacy events end after the message, remove the closed addresses length.
+        data.truncate(data.len() - 4);
+        assert_eq!(PublicTransactionEvent::decode(&data).unwrap(), event);
+        assert!(matches!(
+            decode_event(&data),
+            Err(EventError::UnknownDiscriminator(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_merkle_tree_event() {
+        let event = MerkleTreeEvent::V2(NullifierEvent {
+            id: [1u8; 32],
+            nullified_leaves_indices: vec![1, 2, 3],
+            seq: 4,
+        });
+        let legacy_data = event.try_to_vec().unwrap();
+        let mut data = Vec::new();
+        EventHeader::new(MERKLE_TREE_EVENT_DISCRIMINATOR, MERKLE_TREE_EVENT_VERSION)
+            .write(&mut data)
+            .unwrap();
+        data.extend_from_slice(&legacy_data);
+
+        for data in [data.as_slice(), legacy_data.as_slice()] {
+            match MerkleTreeEvent::decode(data).unwrap() {
+                MerkleTreeEvent::V2(decoded) => {
+                    assert_eq!(decoded.id, [1u8; 32]);
+                    assert_eq!(decoded.nullified_leaves_indices, vec![1, 2, 3]);
+                    assert_eq!(decoded.seq, 4);
+                }
+                _ => panic!("Expected nullifier event"),
+            }
+        }
+        assert!(matches!(
+            decode_event(&data).unwrap(),
+            LightEvent::MerkleTree(MerkleTreeEvent::V2(_))
+        ));
+        // A public transaction event decoder doesn't accept a Merkle tree event.
+        assert!(PublicTransactionEvent::decode(&data).is_err());
+    }
+}
@@ -1,4 +1,10 @@
+// Allows `#[derive(LightAccount)]`, which refers to `::light_sdk`, within
+// this crate.
+extern crate self as light_sdk;
+
 pub use light_macros::*;
+pub mod compressed_account;
+pub mod event;
 pub mod traits;
 pub mod utils;
 pub mod verify;
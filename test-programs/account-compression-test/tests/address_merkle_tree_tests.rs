@@ -27,8 +27,10 @@ use light_test_utils::{
     },
     get_hash_set,
     indexer::{AddressMerkleTreeAccounts, AddressMerkleTreeBundle},
+    queue_migration::{migrate_all_queue_elements, migrate_queue},
     test_env::create_address_merkle_tree_and_queue_account,
     test_forester::{empty_address_queue_test, insert_addresses},
+    AccountZeroCopy,
 };
 use light_test_utils::{
     airdrop_lamports,
@@ -81,8 +83,8 @@ async fn address_queue_and_tree_functional(
             .await
     };
 
-    assert!(address_queue.contains(&address1, None).unwrap());
-    assert!(address_queue.contains(&address2, None).unwrap());
+    assert!(address_queue.contains(&addresses[0], None).unwrap());
+    assert!(address_queue.contains(&addresses[1], None).unwrap());
 
     // CHECK: 3 inserts two addresses into the address Merkle tree
     empty_address_queue_test(&payer, &mut context, &mut address_merkle_tree_bundle, true)
@@ -104,7 +106,7 @@ async fn address_queue_and_tree_functional(
             .await
     };
     address_queue
-        .find_element(&address3, None)
+        .find_element(&bigint_to_be_bytes_array(&address3).unwrap(), None)
         .unwrap()
         .unwrap();
     // CHECK: 4 insert third address which is inbetween the first two addresses
@@ -155,6 +157,134 @@ async fn test_address_queue_and_tree_functional_custom() {
     }
 }
 
+/// Tests:
+/// 1. Functional: Insert addresses and insert one of them into the address
+///    Merkle tree, which marks it in the queue
+/// 2. Functional: Migrate the address queue, the marked address keeps its
+///    sequence number of the address Merkle tree
+/// 3. Functional: Insert the remaining address from the new queue into the
+///    address Merkle tree
+#[tokio::test]
+async fn test_migrate_address_queue() {
+    let (mut context, payer, mut address_merkle_tree_bundle) = test_setup_with_address_merkle_tree(
+        &AddressMerkleTreeConfig::default(),
+        &AddressQueueConfig::default(),
+    )
+    .await;
+    let address_queue_pubkey = address_merkle_tree_bundle.accounts.queue;
+    let address_merkle_tree_pubkey = address_merkle_tree_bundle.accounts.merkle_tree;
+
+    // 1. Functional: insert addresses, insert the first one into the tree
+    let address1 = bigint_to_be_bytes_array(&30_u32.to_biguint().unwrap()).unwrap();
+    insert_addresses(
+        &mut context,
+        address_queue_pubkey,
+        address_merkle_tree_pubkey,
+        vec![address1],
+    )
+    .await
+    .unwrap();
+    empty_address_queue_test(&payer, &mut context, &mut address_merkle_tree_bundle, true)
+        .await
+        .unwrap();
+    let address2 = bigint_to_be_bytes_array(&10_u32.to_biguint().unwrap()).unwrap();
+    insert_addresses(
+        &mut context,
+        address_queue_pubkey,
+        address_merkle_tree_pubkey,
+        vec![address2],
+    )
+    .await
+    .unwrap();
+    let old_address_queue = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(&mut context, address_queue_pubkey)
+            .await
+    };
+    let marked_address = old_address_queue
+        .find_element(&address1, None)
+        .unwrap()
+        .unwrap()
+        .0;
+    assert!(marked_address.is_marked());
+
+    // 2. Functional: migrate the address queue
+    let new_address_queue_keypair = Keypair::new();
+    let new_address_queue_pubkey = new_address_queue_keypair.pubkey();
+    migrate_queue(
+        &mut context,
+        &payer,
+        &address_merkle_tree_pubkey,
+        &address_queue_pubkey,
+        &new_address_queue_keypair,
+        7901,
+    )
+    .await
+    .unwrap();
+    migrate_all_queue_elements(
+        &mut context,
+        &payer,
+        &address_merkle_tree_pubkey,
+        &address_queue_pubkey,
+        &new_address_queue_pubkey,
+        2000,
+    )
+    .await
+    .unwrap();
+    assert!(context
+        .get_account(address_queue_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    let address_merkle_tree =
+        AccountZeroCopy::<AddressMerkleTreeAccount>::new(&mut context, address_merkle_tree_pubkey)
+            .await;
+    assert_eq!(
+        address_merkle_tree.deserialized().metadata.associated_queue,
+        new_address_queue_pubkey
+    );
+    let new_address_queue = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(
+            &mut context,
+            new_address_queue_pubkey,
+        )
+        .await
+    };
+    assert_eq!(
+        new_address_queue
+            .find_element(&address1, None)
+            .unwrap()
+            .unwrap()
+            .0
+            .sequence_number,
+        marked_address.sequence_number
+    );
+    assert!(!new_address_queue
+        .find_element(&address2, None)
+        .unwrap()
+        .unwrap()
+        .0
+        .is_marked());
+
+    // 3. Functional: insert the remaining address into the tree
+    address_merkle_tree_bundle.accounts.queue = new_address_queue_pubkey;
+    empty_address_queue_test(&payer, &mut context, &mut address_merkle_tree_bundle, true)
+        .await
+        .unwrap();
+    let new_address_queue = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(
+            &mut context,
+            new_address_queue_pubkey,
+        )
+        .await
+    };
+    assert!(new_address_queue
+        .find_element(&address2, None)
+        .unwrap()
+        .unwrap()
+        .0
+        .is_marked());
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn initialize_address_merkle_tree_and_queue<R: RpcConnection>(
     context: &mut R,
@@ -1050,7 +1180,7 @@ async fn update_address_merkle_tree_wrap_around(
             .await
     };
     let value_index = address_queue
-        .find_element_index(&address1, None)
+        .find_element_index(&addresses[0], None)
         .unwrap()
         .unwrap();
 
@@ -5,8 +5,11 @@ use account_compression::{
     self,
     errors::AccountCompressionErrorCode,
     queue_from_bytes_copy,
-    sdk::{create_initialize_merkle_tree_instruction, create_insert_leaves_instruction},
-    state::{queue_from_bytes_zero_copy_mut, QueueAccount},
+    sdk::{
+        create_initialize_merkle_tree_instruction, create_insert_leaves_instruction,
+        get_queue_migration_pda,
+    },
+    state::{queue_from_bytes_zero_copy_mut, QueueAccount, QueueMigration},
     utils::constants::{STATE_MERKLE_TREE_CANOPY_DEPTH, STATE_MERKLE_TREE_HEIGHT},
     AddressMerkleTreeConfig, AddressQueueConfig, NullifierQueueConfig, QueueType,
     StateMerkleTreeAccount, StateMerkleTreeConfig, ID, SAFETY_MARGIN,
@@ -23,6 +26,7 @@ use light_test_utils::{
     airdrop_lamports,
     assert_merkle_tree::assert_merkle_tree_initialized,
     create_account_instruction, get_concurrent_merkle_tree, get_hash_set,
+    queue_migration::{migrate_all_queue_elements, migrate_queue, migrate_queue_elements},
     state_tree_rollover::{
         assert_rolled_over_pair, perform_state_merkle_tree_roll_over,
         set_state_merkle_tree_next_index,
@@ -40,12 +44,14 @@ use light_test_utils::{
     rpc::test_rpc::ProgramTestRpcConnection, state_tree_rollover::StateMerkleTreeRolloverMode,
 };
 use light_utils::bigint::bigint_to_be_bytes_array;
-use num_bigint::{BigUint, ToBigUint};
+use num_bigint::ToBigUint;
+use rand::{thread_rng, Rng};
 use solana_program_test::ProgramTest;
 use solana_sdk::{
     account::AccountSharedData,
     instruction::{AccountMeta, Instruction},
     signature::{Keypair, Signature, Signer},
+    system_instruction::SystemError,
     transaction::Transaction,
 };
 use solana_sdk::{account::WritableAccount, pubkey::Pubkey};
@@ -220,6 +226,382 @@ async fn test_init_and_insert_into_nullifier_queue_custom() {
     }
 }
 
+/// Tests:
+/// 1. Failing: Migrate queue into a queue with a smaller capacity
+/// 2. Failing: Migrate queue with invalid authority
+/// 3. Functional: Start migrating queue into a queue with a larger capacity
+/// 4. Failing: Migrate the queue again
+/// 5. Failing: Insert into the new queue before all elements are migrated
+/// 6. Failing: Migrate elements into a queue which is not the next queue
+/// 7. Functional: Migrate elements in chunks, the old queue and the queue
+///    migration account are closed
+/// 8. Functional: Insert into the new queue
+#[tokio::test]
+async fn test_migrate_nullifier_queue() {
+    let merkle_tree_config = StateMerkleTreeConfig::default();
+    let queue_config = NullifierQueueConfig::default();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("account_compression", ID, None);
+    program_test.add_program(
+        "spl_noop",
+        Pubkey::new_from_array(account_compression::utils::constants::NOOP_PUBKEY),
+        None,
+    );
+    let merkle_tree_keypair = Keypair::new();
+    let merkle_tree_pubkey = merkle_tree_keypair.pubkey();
+    let nullifier_queue_keypair = Keypair::new();
+    let nullifier_queue_pubkey = nullifier_queue_keypair.pubkey();
+    program_test.set_compute_max_units(1_400_000u64);
+    let context = program_test.start_with_context().await;
+    let mut context = ProgramTestRpcConnection { context };
+    let payer = context.get_payer().insecure_clone();
+    functional_1_initialize_state_merkle_tree_and_nullifier_queue(
+        &mut context,
+        &payer.pubkey(),
+        &merkle_tree_keypair,
+        &nullifier_queue_keypair,
+        &merkle_tree_config,
+        &queue_config,
+    )
+    .await;
+    functional_2_test_insert_into_nullifier_queues(
+        &mut context,
+        &nullifier_queue_pubkey,
+        &merkle_tree_pubkey,
+    )
+    .await;
+    let old_metrics = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(&mut context, nullifier_queue_pubkey)
+            .await
+    }
+    .metrics(0);
+
+    // 1. Failing: smaller capacity
+    let result = migrate_queue(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &Keypair::new(),
+        queue_config.capacity - 1,
+    )
+    .await;
+    assert_rpc_error(
+        result,
+        1,
+        AccountCompressionErrorCode::InvalidQueueCapacity.into(),
+    )
+    .unwrap();
+
+    // 2. Failing: invalid authority
+    let invalid_authority = Keypair::new();
+    airdrop_lamports(&mut context, &invalid_authority.pubkey(), 10_000_000_000)
+        .await
+        .unwrap();
+    let result = migrate_queue(
+        &mut context,
+        &invalid_authority,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &Keypair::new(),
+        7901,
+    )
+    .await;
+    assert_rpc_error(
+        result,
+        1,
+        AccountCompressionErrorCode::InvalidAuthority.into(),
+    )
+    .unwrap();
+
+    // 3. Functional: start migration
+    let new_nullifier_queue_keypair = Keypair::new();
+    let new_nullifier_queue_pubkey = new_nullifier_queue_keypair.pubkey();
+    migrate_queue(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &new_nullifier_queue_keypair,
+        7901,
+    )
+    .await
+    .unwrap();
+    let merkle_tree =
+        AccountZeroCopy::<StateMerkleTreeAccount>::new(&mut context, merkle_tree_pubkey).await;
+    assert_eq!(
+        merkle_tree.deserialized().metadata.associated_queue,
+        new_nullifier_queue_pubkey
+    );
+    let old_queue =
+        AccountZeroCopy::<QueueAccount>::new(&mut context, nullifier_queue_pubkey).await;
+    assert_eq!(
+        old_queue.deserialized().metadata.associated_merkle_tree,
+        Pubkey::default()
+    );
+    assert_eq!(
+        old_queue.deserialized().metadata.next_queue,
+        new_nullifier_queue_pubkey
+    );
+    let new_queue =
+        AccountZeroCopy::<QueueAccount>::new(&mut context, new_nullifier_queue_pubkey).await;
+    assert_eq!(
+        new_queue.deserialized().metadata.associated_merkle_tree,
+        Pubkey::default()
+    );
+    assert_eq!(
+        new_queue.deserialized().metadata.queue_type,
+        QueueType::NullifierQueue as u64
+    );
+    let queue_migration_pubkey = get_queue_migration_pda(&nullifier_queue_pubkey);
+    let queue_migration = context
+        .get_anchor_account::<QueueMigration>(&queue_migration_pubkey)
+        .await;
+    assert_eq!(queue_migration.cursor, 0);
+
+    // 4. Failing: the queue migration account of the old queue exists
+    // already
+    let result = migrate_queue(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &Keypair::new(),
+        7901,
+    )
+    .await;
+    assert_rpc_error(result, 1, SystemError::AccountAlreadyInUse as u32).unwrap();
+
+    // 5. Failing: insert into the new queue during the migration
+    let result = insert_into_single_nullifier_queue(
+        &vec![[3u8; 32]],
+        &payer,
+        &payer,
+        &new_nullifier_queue_pubkey,
+        &merkle_tree_pubkey,
+        &mut context,
+    )
+    .await;
+    assert_rpc_error(
+        result,
+        0,
+        AccountCompressionErrorCode::MerkleTreeAndQueueNotAssociated.into(),
+    )
+    .unwrap();
+
+    // 6. Failing: migrate elements into a queue which is not the next queue
+    let result = migrate_queue_elements(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &nullifier_queue_pubkey,
+        1000,
+    )
+    .await;
+    assert_rpc_error(
+        result,
+        0,
+        AccountCompressionErrorCode::QueueNotMigrating.into(),
+    )
+    .unwrap();
+
+    // 7. Functional: migrate elements in chunks
+    let queue_migration_rent = context
+        .get_account(queue_migration_pubkey)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+    let old_queue_account = context
+        .get_account(nullifier_queue_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let old_queue_rent = context
+        .get_minimum_balance_for_rent_exemption(old_queue_account.data.len())
+        .await
+        .unwrap();
+    let new_queue_lamports = context
+        .get_account(new_nullifier_queue_pubkey)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+    let payer_balance = context.get_balance(&payer.pubkey()).await.unwrap();
+    let num_transactions = migrate_all_queue_elements(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &new_nullifier_queue_pubkey,
+        1000,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        num_transactions,
+        (queue_config.capacity as usize).div_ceil(1000)
+    );
+    assert!(context
+        .get_account(nullifier_queue_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(context
+        .get_account(queue_migration_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+    // The payer receives the rent of the old queue and of the queue
+    // migration account and pays for the transactions, the rollover fees are
+    // kept in the new queue.
+    assert_eq!(
+        context.get_balance(&payer.pubkey()).await.unwrap(),
+        payer_balance + old_queue_rent + queue_migration_rent - num_transactions as u64 * 5000
+    );
+    assert_eq!(
+        context
+            .get_account(new_nullifier_queue_pubkey)
+            .await
+            .unwrap()
+            .unwrap()
+            .lamports,
+        new_queue_lamports + old_queue_account.lamports - old_queue_rent
+    );
+    let new_queue =
+        AccountZeroCopy::<QueueAccount>::new(&mut context, new_nullifier_queue_pubkey).await;
+    assert_eq!(
+        new_queue.deserialized().metadata.associated_merkle_tree,
+        merkle_tree_pubkey
+    );
+    let new_hash_set = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(
+            &mut context,
+            new_nullifier_queue_pubkey,
+        )
+        .await
+    };
+    assert_eq!(new_hash_set.capacity, 7901);
+    let new_metrics = new_hash_set.metrics(0);
+    assert_eq!(new_metrics.occupied, old_metrics.occupied);
+    assert_eq!(new_metrics.marked, old_metrics.marked);
+    assert_element_inserted_in_nullifier_queue(
+        &mut context,
+        &new_nullifier_queue_pubkey,
+        [1u8; 32],
+    )
+    .await;
+    assert_element_inserted_in_nullifier_queue(
+        &mut context,
+        &new_nullifier_queue_pubkey,
+        [2u8; 32],
+    )
+    .await;
+
+    // 8. Functional: insert into the new queue
+    insert_into_single_nullifier_queue(
+        &vec![[3u8; 32]],
+        &payer,
+        &payer,
+        &new_nullifier_queue_pubkey,
+        &merkle_tree_pubkey,
+        &mut context,
+    )
+    .await
+    .unwrap();
+    assert_element_inserted_in_nullifier_queue(
+        &mut context,
+        &new_nullifier_queue_pubkey,
+        [3u8; 32],
+    )
+    .await;
+}
+
+/// Tests:
+/// 1. Functional: Migrate a nullifier queue which is full except for one
+///    element, every migration transaction stays within the compute budget
+/// 2. Functional: All elements are in the new queue
+#[tokio::test]
+async fn test_migrate_near_full_nullifier_queue() {
+    let merkle_tree_config = StateMerkleTreeConfig::default();
+    let queue_config = NullifierQueueConfig::default();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("account_compression", ID, None);
+    program_test.add_program(
+        "spl_noop",
+        Pubkey::new_from_array(account_compression::utils::constants::NOOP_PUBKEY),
+        None,
+    );
+    let merkle_tree_keypair = Keypair::new();
+    let merkle_tree_pubkey = merkle_tree_keypair.pubkey();
+    let nullifier_queue_keypair = Keypair::new();
+    let nullifier_queue_pubkey = nullifier_queue_keypair.pubkey();
+    program_test.set_compute_max_units(1_400_000u64);
+    let context = program_test.start_with_context().await;
+    let mut context = ProgramTestRpcConnection { context };
+    let payer = context.get_payer().insecure_clone();
+    functional_1_initialize_state_merkle_tree_and_nullifier_queue(
+        &mut context,
+        &payer.pubkey(),
+        &merkle_tree_keypair,
+        &nullifier_queue_keypair,
+        &merkle_tree_config,
+        &queue_config,
+    )
+    .await;
+    let lamports = context
+        .get_account(nullifier_queue_pubkey)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+    set_nullifier_queue_to_full(&mut context, &nullifier_queue_pubkey, 1, lamports).await;
+    let num_elements = queue_config.capacity as usize - 1;
+
+    // 1. Functional: migrate the near full queue
+    let new_nullifier_queue_keypair = Keypair::new();
+    let new_nullifier_queue_pubkey = new_nullifier_queue_keypair.pubkey();
+    migrate_queue(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &new_nullifier_queue_keypair,
+        13711,
+    )
+    .await
+    .unwrap();
+    migrate_all_queue_elements(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &new_nullifier_queue_pubkey,
+        500,
+    )
+    .await
+    .unwrap();
+
+    // 2. Functional: all elements are migrated
+    let new_hash_set = unsafe {
+        get_hash_set::<QueueAccount, ProgramTestRpcConnection>(
+            &mut context,
+            new_nullifier_queue_pubkey,
+        )
+        .await
+    };
+    assert_eq!(new_hash_set.metrics(0).occupied, num_elements);
+    for i in 0..num_elements {
+        assert!(new_hash_set
+            .contains(
+                &bigint_to_be_bytes_array(&i.to_biguint().unwrap()).unwrap(),
+                None
+            )
+            .unwrap());
+    }
+}
+
 /// Tests:
 /// (Since nullifier queue and address queue use the same code, we only need to test one)
 /// Show that we cannot insert into a full queue.
@@ -610,6 +992,7 @@ async fn test_failing_queue_default() {
 /// 3. Should fail: merkle tree and queue not associated (invalid queue)
 /// 4. Should succeed: rollover state merkle tree
 /// 5. Should fail: merkle tree already rolled over
+/// 6. Should fail: migrate the rolled over queue
 async fn test_init_and_rollover_state_merkle_tree(
     merkle_tree_config: &StateMerkleTreeConfig,
     queue_config: &NullifierQueueConfig,
@@ -831,6 +1214,22 @@ async fn test_init_and_rollover_state_merkle_tree(
         AccountCompressionErrorCode::MerkleTreeAlreadyRolledOver.into(),
     )
     .unwrap();
+
+    let result = migrate_queue(
+        &mut context,
+        &payer,
+        &merkle_tree_pubkey,
+        &nullifier_queue_pubkey,
+        &Keypair::new(),
+        queue_config.capacity,
+    )
+    .await;
+    assert_rpc_error(
+        result,
+        1,
+        AccountCompressionErrorCode::QueueAlreadyRolledOver.into(),
+    )
+    .unwrap();
 }
 
 #[tokio::test]
@@ -999,6 +1398,186 @@ async fn test_append_functional_and_failing_default() {
     .await
 }
 
+/// Tests:
+/// 1. Functional: append batches of different sizes and measure the compute
+///    units consumed by the transactions (`cargo xtask bench` reports the
+///    compute units of every batch)
+/// 2. Compute units per leaf of the largest batch are less than half of the
+///    compute units of a single leaf, since batches are appended by aligned
+///    subtrees
+#[tokio::test]
+async fn test_append_batch_compute_units() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("account_compression", ID, None);
+    program_test.add_program(
+        "spl_noop",
+        Pubkey::new_from_array(account_compression::utils::constants::NOOP_PUBKEY),
+        None,
+    );
+
+    program_test.set_compute_max_units(1_400_000u64);
+    let context = program_test.start_with_context().await;
+    let mut context = ProgramTestRpcConnection { context };
+    let payer = context.get_payer().insecure_clone();
+    let merkle_tree_keypair = Keypair::new();
+    let queue_keypair = Keypair::new();
+    let merkle_tree_pubkey = functional_1_initialize_state_merkle_tree_and_nullifier_queue(
+        &mut context,
+        &payer.pubkey(),
+        &merkle_tree_keypair,
+        &queue_keypair,
+        &StateMerkleTreeConfig::default(),
+        &NullifierQueueConfig::default(),
+    )
+    .await;
+    let mut reference_merkle_tree = MerkleTree::<Poseidon>::new(
+        STATE_MERKLE_TREE_HEIGHT as usize,
+        STATE_MERKLE_TREE_CANOPY_DEPTH as usize,
+    );
+
+    // CHECK 1
+    let mut rng = thread_rng();
+    let mut compute_units = Vec::new();
+    for batch_size in [1, 2, 4, 7, 8, 16, 30] {
+        let leaves = (0..batch_size)
+            .map(|_| {
+                let mut leaf = [0u8; 32];
+                rng.fill(&mut leaf[1..]);
+                (0, leaf)
+            })
+            .collect::<Vec<(u8, [u8; 32])>>();
+        let instruction = create_insert_leaves_instruction(
+            leaves.clone(),
+            payer.pubkey(),
+            payer.pubkey(),
+            vec![merkle_tree_pubkey],
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            context.context.get_new_latest_blockhash().await.unwrap(),
+        );
+        let simulation = context
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        simulation.result.unwrap().unwrap();
+        let units_consumed = simulation.simulation_details.unwrap().units_consumed;
+        compute_units.push((batch_size as u64, units_consumed));
+
+        functional_3_append_leaves_to_merkle_tree(
+            &mut context,
+            &mut [&mut reference_merkle_tree],
+            &vec![merkle_tree_pubkey],
+            &leaves,
+        )
+        .await;
+    }
+
+    // CHECK 2
+    let (_, single_leaf_units) = compute_units[0];
+    let (batch_size, batch_units) = compute_units[compute_units.len() - 1];
+    assert!(batch_units / batch_size < single_leaf_units / 2);
+}
+
+/// Tests:
+/// 1. Functional: fill nullifier queues up to different load factors and
+///    measure the compute units consumed by inserting a batch of nullifiers
+/// 2. Compute units of an insertion into a half full queue are less than
+///    twice the compute units of an insertion into an empty queue, since
+///    probing doesn't allocate big integers
+///
+/// The compute units of the insertion are reported by
+/// `cargo xtask bench --t test_insert_into_nullifier_queue_compute_units`,
+/// the probing itself is compared with the big integer implementation by
+/// `cargo xtask hash-set bench-probing`.
+#[tokio::test]
+async fn test_insert_into_nullifier_queue_compute_units() {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("account_compression", ID, None);
+    program_test.add_program(
+        "spl_noop",
+        Pubkey::new_from_array(account_compression::utils::constants::NOOP_PUBKEY),
+        None,
+    );
+
+    program_test.set_compute_max_units(1_400_000u64);
+    let context = program_test.start_with_context().await;
+    let mut context = ProgramTestRpcConnection { context };
+    let payer = context.get_payer().insecure_clone();
+
+    // CHECK 1
+    let mut rng = thread_rng();
+    let mut compute_units = Vec::new();
+    for load_factor in [0, 25, 50] {
+        let merkle_tree_keypair = Keypair::new();
+        let queue_keypair = Keypair::new();
+        let merkle_tree_pubkey = functional_1_initialize_state_merkle_tree_and_nullifier_queue(
+            &mut context,
+            &payer.pubkey(),
+            &merkle_tree_keypair,
+            &queue_keypair,
+            &StateMerkleTreeConfig::default(),
+            &NullifierQueueConfig::default(),
+        )
+        .await;
+        let queue_pubkey = queue_keypair.pubkey();
+        if load_factor > 0 {
+            let capacity = NullifierQueueConfig::default().capacity as usize;
+            let lamports = context
+                .get_account(queue_pubkey)
+                .await
+                .unwrap()
+                .unwrap()
+                .lamports;
+            set_nullifier_queue_to_full(
+                &mut context,
+                &queue_pubkey,
+                capacity - capacity * load_factor / 100,
+                lamports,
+            )
+            .await;
+        }
+
+        let nullifiers = (0..10)
+            .map(|_| {
+                let mut nullifier = [0u8; 32];
+                rng.fill(&mut nullifier[1..]);
+                nullifier
+            })
+            .collect::<Vec<[u8; 32]>>();
+        let instruction = create_insert_into_single_nullifier_queue_instruction(
+            &nullifiers,
+            &payer.pubkey(),
+            &payer.pubkey(),
+            &queue_pubkey,
+            &merkle_tree_pubkey,
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            context.context.get_new_latest_blockhash().await.unwrap(),
+        );
+        let simulation = context
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        simulation.result.unwrap().unwrap();
+        compute_units.push(simulation.simulation_details.unwrap().units_consumed);
+    }
+
+    // CHECK 2
+    let empty_queue_units = compute_units[0];
+    let half_full_queue_units = compute_units[compute_units.len() - 1];
+    assert!(half_full_queue_units < empty_queue_units * 2);
+}
+
 /// Tests:
 /// 1. Functional: nullify leaf
 /// 2. Failing: nullify leaf with invalid leaf index
@@ -1089,7 +1668,7 @@ async fn test_nullify_leaves(
         let mut data = account.data.clone();
         let nullifier_queue = &mut unsafe { queue_from_bytes_copy(&mut data).unwrap() };
         let (_, index) = nullifier_queue
-            .find_element(&BigUint::from_bytes_be(&elements[0].1), None)
+            .find_element(&elements[0].1, None)
             .unwrap()
             .unwrap();
         index
@@ -1127,7 +1706,7 @@ async fn test_nullify_leaves(
         let mut data = account.data.clone();
         let nullifier_queue = &mut unsafe { queue_from_bytes_copy(&mut data).unwrap() };
         let (_, index) = nullifier_queue
-            .find_element(&BigUint::from_bytes_be(&elements[1].1), None)
+            .find_element(&elements[1].1, None)
             .unwrap()
             .unwrap();
         index as u16
@@ -1253,12 +1832,10 @@ async fn functional_2_test_insert_into_nullifier_queues<R: RpcConnection>(
     .await
     .unwrap();
     let array = unsafe { get_hash_set::<QueueAccount, R>(rpc, *nullifier_queue_pubkey).await };
-    let element_0 = BigUint::from_bytes_be(&elements[0]);
-    let (array_element_0, _) = array.find_element(&element_0, None).unwrap().unwrap();
+    let (array_element_0, _) = array.find_element(&elements[0], None).unwrap().unwrap();
     assert_eq!(array_element_0.value_bytes(), [1u8; 32]);
     assert_eq!(array_element_0.sequence_number(), None);
-    let element_1 = BigUint::from_bytes_be(&elements[1]);
-    let (array_element_1, _) = array.find_element(&element_1, None).unwrap().unwrap();
+    let (array_element_1, _) = array.find_element(&elements[1], None).unwrap().unwrap();
     assert_eq!(array_element_1.value_bytes(), [2u8; 32]);
     assert_eq!(array_element_1.sequence_number(), None);
 }
@@ -1335,7 +1912,7 @@ async fn functional_5_test_insert_into_nullifier_queue<R: RpcConnection>(
     .unwrap();
     let array = unsafe { get_hash_set::<QueueAccount, R>(rpc, *nullifier_queue_pubkey).await };
 
-    let (array_element, _) = array.find_element(&element, None).unwrap().unwrap();
+    let (array_element, _) = array.find_element(&elements[0], None).unwrap().unwrap();
     assert_eq!(array_element.value_biguint(), element);
     assert_eq!(array_element.sequence_number(), None);
 }
@@ -1348,12 +1925,36 @@ async fn insert_into_single_nullifier_queue<R: RpcConnection>(
     merkle_tree_pubkey: &Pubkey,
     context: &mut R,
 ) -> Result<Signature, RpcError> {
+    let instruction = create_insert_into_single_nullifier_queue_instruction(
+        elements,
+        &fee_payer.pubkey(),
+        &payer.pubkey(),
+        nullifier_queue_pubkey,
+        merkle_tree_pubkey,
+    );
+    let latest_blockhash = context.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&fee_payer.pubkey()),
+        &vec![fee_payer, payer],
+        latest_blockhash,
+    );
+    context.process_transaction(transaction.clone()).await
+}
+
+fn create_insert_into_single_nullifier_queue_instruction(
+    elements: &[[u8; 32]],
+    fee_payer: &Pubkey,
+    authority: &Pubkey,
+    nullifier_queue_pubkey: &Pubkey,
+    merkle_tree_pubkey: &Pubkey,
+) -> Instruction {
     let instruction_data = account_compression::instruction::InsertIntoNullifierQueues {
         nullifiers: elements.to_vec(),
     };
     let accounts = account_compression::accounts::InsertIntoQueues {
-        fee_payer: fee_payer.pubkey(),
-        authority: payer.pubkey(),
+        fee_payer: *fee_payer,
+        authority: *authority,
         registered_program_pda: None,
         system_program: system_program::ID,
     };
@@ -1370,19 +1971,11 @@ async fn insert_into_single_nullifier_queue<R: RpcConnection>(
         .flat_map(|x| x.to_vec())
         .collect::<Vec<AccountMeta>>(),
     );
-    let instruction = Instruction {
+    Instruction {
         program_id: ID,
         accounts: [accounts.to_account_metas(Some(true)), remaining_accounts].concat(),
         data: instruction_data.data(),
-    };
-    let latest_blockhash = context.get_latest_blockhash().await.unwrap();
-    let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&fee_payer.pubkey()),
-        &vec![fee_payer, payer],
-        latest_blockhash,
-    );
-    context.process_transaction(transaction.clone()).await
+    }
 }
 
 async fn insert_into_nullifier_queues<R: RpcConnection>(
@@ -1873,19 +2466,34 @@ pub async fn functional_3_append_leaves_to_merkle_tree<R: RpcConnection>(
         let leaves: Vec<&[u8; 32]> = leaves.iter().collect();
 
         let reference_merkle_tree = &mut reference_merkle_trees[*mt_index];
-        reference_merkle_tree.append_batch(&leaves).unwrap();
+        reference_merkle_tree
+            .append_batch_subtrees(&leaves)
+            .unwrap();
 
         assert_eq!(merkle_tree.root(), reference_merkle_tree.root());
         assert_eq!(lamports + roll_over_fee, post_account_mt.lamports);
 
+        // The batch is stored in a single changelog entry.
         let changelog_entry = merkle_tree
             .changelog
             .get(merkle_tree.changelog_index())
             .unwrap();
-        let path = reference_merkle_tree
-            .get_path_of_leaf(merkle_tree.current_index(), true)
-            .unwrap();
-        assert_eq!(changelog_entry.path.as_slice(), path.as_slice());
+        assert_eq!(changelog_entry.index(), *next_index);
+        assert_eq!(changelog_entry.batch_size(), Some(num_leaves));
+        assert_eq!(changelog_entry.root, merkle_tree.root());
+        for level in 0..merkle_tree.height {
+            let first_node_index = next_index >> level;
+            let node_index = if first_node_index % 2 == 1 {
+                first_node_index
+            } else {
+                first_node_index + 1
+            };
+            let node = reference_merkle_tree.layers[level]
+                .get(node_index)
+                .copied()
+                .unwrap_or(Poseidon::zero_bytes()[level]);
+            assert_eq!(changelog_entry.path[level], node);
+        }
     }
 }
 
@@ -2028,7 +2636,10 @@ pub async fn set_nullifier_queue_to_full<R: RpcConnection>(
         let arbitrary_sequence_number = 0;
         for i in 0..capacity {
             hash_set
-                .insert(&i.to_biguint().unwrap(), arbitrary_sequence_number)
+                .insert(
+                    &bigint_to_be_bytes_array(&i.to_biguint().unwrap()).unwrap(),
+                    arbitrary_sequence_number,
+                )
                 .unwrap();
         }
     }
@@ -2046,7 +2657,10 @@ pub async fn set_nullifier_queue_to_full<R: RpcConnection>(
     let nullifier_queue = &mut unsafe { queue_from_bytes_zero_copy_mut(&mut data).unwrap() };
     for i in 0..capacity {
         assert!(nullifier_queue
-            .contains(&i.to_biguint().unwrap(), None)
+            .contains(
+                &bigint_to_be_bytes_array(&i.to_biguint().unwrap()).unwrap(),
+                None
+            )
             .unwrap());
     }
 }
@@ -2132,8 +2746,7 @@ pub async fn assert_element_inserted_in_nullifier_queue(
     let array = unsafe {
         get_hash_set::<QueueAccount, ProgramTestRpcConnection>(rpc, *nullifier_queue_pubkey).await
     };
-    let nullifier_bn = BigUint::from_bytes_be(&nullifier);
-    let (array_element, _) = array.find_element(&nullifier_bn, None).unwrap().unwrap();
+    let (array_element, _) = array.find_element(&nullifier, None).unwrap().unwrap();
     assert_eq!(array_element.value_bytes(), nullifier);
     assert_eq!(array_element.sequence_number(), None);
 }
@@ -1055,7 +1055,8 @@ pub async fn failing_compress_decompress<R: RpcConnection>(
                 None,
                 rpc,
             )
-            .await;
+            .await
+            .unwrap();
         (proof_rpc_result.root_indices, Some(proof_rpc_result.proof))
     } else {
         (Vec::new(), None)
@@ -1173,7 +1174,8 @@ async fn test_invalid_inputs() {
             None,
             &mut rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let change_out_compressed_account_0 = TokenTransferOutputData {
         amount: input_compressed_account_token_data.amount - 1000,
         owner: recipient_keypair.pubkey(),
@@ -1,21 +1,23 @@
 #![cfg(feature = "test-sbf")]
 
-use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
 use light_registry::{
     get_forester_epoch_pda_address,
     sdk::{
-        create_nullify_instruction, create_update_address_merkle_tree_instruction,
+        create_initialize_verifying_key_instruction, create_nullify_instruction,
+        create_update_address_merkle_tree_instruction, create_write_verifying_key_instructions,
         get_governance_authority_pda, CreateNullifyInstructionInputs,
         UpdateAddressMerkleTreeInstructionInputs,
     },
-    ForesterEpoch, LightGovernanceAuthority, RegistryError,
+    CircuitShape, CircuitType, ForesterEpoch, LightGovernanceAuthority, RegistryError,
+    VerifyingKeyData,
 };
 use light_test_utils::rpc::solana_rpc::SolanaRpcUrl;
 use light_test_utils::{
     registry::{
         create_rollover_address_merkle_tree_instructions,
         create_rollover_state_merkle_tree_instructions, register_test_forester,
-        update_test_forester,
+        register_test_verifying_key, update_test_forester,
     },
     rpc::{errors::assert_rpc_error, rpc_connection::RpcConnection, SolanaRpcConnection},
     test_env::{
@@ -76,6 +78,83 @@ async fn test_register_and_update_forester_pda() {
         .unwrap();
 }
 
+/// Test:
+/// 1. SUCCESS: Register a verifying key
+/// 2. SUCCESS: Rotate the verifying key
+/// 3. FAIL: Initialize a verifying key account with invalid authority
+/// 4. FAIL: Write a key of another shape
+/// 5. FAIL: Initialize a verifying key account with invalid shape
+#[tokio::test]
+async fn test_register_and_rotate_verifying_key() {
+    let (mut rpc, env) = setup_test_programs_with_accounts(None).await;
+    let shape = CircuitShape::new(26, 1, 0).unwrap();
+    let verifying_key =
+        VerifyingKeyData::from(&light_verifier::verifying_keys::inclusion_26_1::VERIFYINGKEY);
+
+    // 1. SUCCESS: Register a verifying key
+    register_test_verifying_key(&mut rpc, &env.governance_authority, shape, &verifying_key)
+        .await
+        .unwrap();
+
+    // 2. SUCCESS: Rotate the verifying key
+    let mut rotated_verifying_key = verifying_key.clone();
+    rotated_verifying_key.vk_alpha_g1 = [1u8; 64];
+    register_test_verifying_key(
+        &mut rpc,
+        &env.governance_authority,
+        shape,
+        &rotated_verifying_key,
+    )
+    .await
+    .unwrap();
+
+    let payer = rpc.get_payer().insecure_clone();
+    // 3. FAIL: Initialize a verifying key account with invalid authority
+    {
+        // The test environment registers all compiled keys, a shape without
+        // a key isn't initialized yet.
+        let ix = create_initialize_verifying_key_instruction(
+            &payer.pubkey(),
+            CircuitShape::new(26, 5, 0).unwrap(),
+        );
+        let result = rpc
+            .create_and_send_transaction(&[ix], &payer.pubkey(), &[&payer])
+            .await;
+        let expected_error_code = anchor_lang::error::ErrorCode::ConstraintAddress as u32;
+        assert_rpc_error(result, 0, expected_error_code).unwrap();
+    }
+    // 4. FAIL: Write a key of another shape
+    {
+        let payload =
+            VerifyingKeyData::from(&light_verifier::verifying_keys::inclusion_26_2::VERIFYINGKEY)
+                .try_to_vec()
+                .unwrap();
+        let authority = &env.governance_authority;
+        let ixs = create_write_verifying_key_instructions(&authority.pubkey(), shape, &payload);
+        let result = rpc
+            .create_and_send_transaction(&ixs, &authority.pubkey(), &[authority])
+            .await;
+        let expected_error_code = RegistryError::InvalidVerifyingKeyChunk as u32 + 6000;
+        assert_rpc_error(result, 0, expected_error_code).unwrap();
+    }
+    // 5. FAIL: Initialize a verifying key account with invalid shape
+    {
+        let authority = &env.governance_authority;
+        let shape = CircuitShape {
+            circuit_type: CircuitType::Combined,
+            height: 26,
+            num_leaves: 1,
+            num_addresses: 0,
+        };
+        let ix = create_initialize_verifying_key_instruction(&authority.pubkey(), shape);
+        let result = rpc
+            .create_and_send_transaction(&[ix], &authority.pubkey(), &[authority])
+            .await;
+        let expected_error_code = RegistryError::InvalidCircuitShape as u32 + 6000;
+        assert_rpc_error(result, 0, expected_error_code).unwrap();
+    }
+}
+
 /// Test:
 /// 1. FAIL: Register a forester with invalid authority
 /// 2. FAIL: Update forester authority with invalid authority
@@ -14,6 +14,7 @@ use light_system_program::{
     sdk::{
         address::pack_new_address_params,
         compressed_account::PackedCompressedAccountWithMerkleContext,
+        invoke::get_verifying_key_account_meta,
     },
     NewAddressParams,
 };
@@ -71,7 +72,8 @@ pub fn create_pda_instruction(input_params: CreateCompressedPdaInstructionInputs
         cpi_signer,
         system_program: solana_sdk::system_program::id(),
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    remaining_accounts.extend(get_verifying_key_account_meta(0, 1));
 
     Instruction {
         program_id: crate::ID,
@@ -101,6 +103,8 @@ pub fn create_invalidate_not_owned_account_instruction(
     let (cpi_signer, bump) =
         Pubkey::find_program_address(&[b"cpi_signer".as_slice()], &crate::id());
     let cpi_context = input_params.cpi_context;
+    // The token account of the transfer is proven with the compressed account.
+    let num_leaves = 1 + usize::from(input_params.token_transfer_data.is_some());
 
     let mut remaining_accounts = HashMap::new();
     remaining_accounts.insert(*input_params.input_merkle_tree_pubkey, 0);
@@ -140,7 +144,8 @@ pub fn create_invalidate_not_owned_account_instruction(
         compressed_token_program: light_compressed_token::ID,
         invalid_fee_payer: *input_params.invalid_fee_payer,
     };
-    let remaining_accounts = to_account_metas(remaining_accounts);
+    let mut remaining_accounts = to_account_metas(remaining_accounts);
+    remaining_accounts.extend(get_verifying_key_account_meta(num_leaves, 0));
 
     Instruction {
         program_id: crate::ID,
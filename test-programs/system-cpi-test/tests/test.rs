@@ -472,7 +472,9 @@ pub async fn perform_create_pda_with_event<R: RpcConnection>(
         .create_and_send_transaction_with_event(&[instruction], &payer_pubkey, &[payer], None)
         .await?
         .unwrap();
-    test_indexer.add_compressed_accounts_with_token_data(&event.0);
+    test_indexer
+        .add_compressed_accounts_with_token_data(rpc, &event.0)
+        .await;
     Ok(())
 }
 
@@ -497,7 +499,8 @@ async fn perform_create_pda<R: RpcConnection>(
             Some(vec![env.address_merkle_tree_pubkey]),
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
 
     let new_address_params = NewAddressParams {
         seed,
@@ -609,7 +612,8 @@ pub async fn perform_with_input_accounts<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
 
     let token_transfer_data = match token_account {
         Some(token_account) => Some(TokenTransferData {
@@ -649,6 +653,7 @@ pub async fn perform_with_input_accounts<R: RpcConnection>(
                 nullifier_queue_pubkey_index: 1,
             },
             root_index: rpc_result.root_indices[0],
+            close_address: false,
         },
         token_transfer_data,
         invalid_fee_payer: &invalid_fee_payer.pubkey(),
@@ -0,0 +1,224 @@
+#![cfg(feature = "test-sbf")]
+use account_compression::StateMerkleTreeAccount;
+use light_hasher::Poseidon;
+use light_prover_client::prover::MockProver;
+use light_test_utils::{
+    get_concurrent_merkle_tree,
+    indexer::{AddressMerkleTreeBundle, Indexer, IndexerError, TestIndexer},
+    rpc::{rpc_connection::RpcConnection, test_rpc::ProgramTestRpcConnection},
+    system_program::compress_sol_test,
+    test_env::{setup_mock_proofs_test_programs_with_accounts, EnvAccounts},
+};
+use solana_sdk::{account::AccountSharedData, bs58, pubkey::Pubkey, signer::Signer};
+
+/// Simulates forks by restoring the accounts of the state Merkle tree and
+/// nullifier queue from before the transactions of the dropped slots.
+/// Changes are recorded at the slots the indexer reads from the rpc, the
+/// program test bank advances them with `warp_to_slot`.
+/// Tests:
+/// 1. events of a dropped slot are rolled back, the indexer matches the
+///    restored tree
+/// 2. the transaction can be applied again on the new fork
+/// 3. finalized slots can't be rolled back
+/// 4. nullifications and address inserts are rolled back
+#[tokio::test]
+async fn test_indexer_rollback() {
+    let (mut rpc, env) = setup_mock_proofs_test_programs_with_accounts(None).await;
+    let payer = rpc.get_payer().insecure_clone();
+    let mut test_indexer: TestIndexer<ProgramTestRpcConnection> =
+        TestIndexer::init_from_env_with_prover(&payer, &env, MockProver);
+
+    rpc.warp_to_slot(10).unwrap();
+    compress(&mut rpc, &mut test_indexer, &env, 1_000_000).await;
+    test_indexer.finalize(10);
+    assert_eq!(test_indexer.pending_changes(), 0);
+    let fork_accounts = [env.merkle_tree_pubkey, env.nullifier_queue_pubkey];
+    let mut snapshot = Vec::new();
+    for pubkey in fork_accounts {
+        let account = rpc.get_account(pubkey).await.unwrap().unwrap();
+        snapshot.push((pubkey, AccountSharedData::from(account)));
+    }
+    let pre_fork_indexer = test_indexer.clone();
+
+    // Spend the account and compress more in slots which are dropped.
+    rpc.warp_to_slot(20).unwrap();
+    let input_accounts = test_indexer.get_compressed_accounts_by_owner(&payer.pubkey());
+    compress_sol_test(
+        &mut rpc,
+        &mut test_indexer,
+        &payer,
+        &input_accounts,
+        false,
+        2_000_000,
+        &env.merkle_tree_pubkey,
+        None,
+    )
+    .await
+    .unwrap();
+    rpc.warp_to_slot(21).unwrap();
+    compress(&mut rpc, &mut test_indexer, &env, 3_000_000).await;
+    assert_eq!(test_indexer.pending_changes(), 2);
+
+    // 1. events of a dropped slot are rolled back, the indexer matches the
+    //    restored tree
+    for (pubkey, account) in snapshot.iter() {
+        rpc.set_account(pubkey, account);
+    }
+    test_indexer.rollback(10).unwrap();
+    assert_eq!(test_indexer.slot, 10);
+    assert_eq!(test_indexer.pending_changes(), 0);
+    assert_indexers_eq(&test_indexer, &pre_fork_indexer);
+    assert_state_tree_matches_onchain(&mut rpc, &test_indexer, &env).await;
+
+    // 2. the transaction can be applied again on the new fork
+    rpc.warp_to_slot(30).unwrap();
+    compress(&mut rpc, &mut test_indexer, &env, 4_000_000).await;
+    assert_state_tree_matches_onchain(&mut rpc, &test_indexer, &env).await;
+    let mut lamports = test_indexer
+        .get_compressed_accounts_by_owner(&payer.pubkey())
+        .iter()
+        .map(|account| account.compressed_account.lamports)
+        .collect::<Vec<_>>();
+    lamports.sort();
+    assert_eq!(lamports, vec![1_000_000, 4_000_000]);
+
+    // 3. finalized slots can't be rolled back
+    let finalized_slot = test_indexer.finalize_with_rpc(&mut rpc).await.unwrap();
+    assert_eq!(finalized_slot, rpc.get_slot().await.unwrap());
+    test_indexer.finalize(30);
+    assert_eq!(test_indexer.pending_changes(), 0);
+    assert!(matches!(
+        test_indexer.rollback(20),
+        Err(IndexerError::SlotFinalized(20))
+    ));
+
+    // 4. nullifications and address inserts are rolled back
+    let pre_fork_indexer = test_indexer.clone();
+    rpc.warp_to_slot(40).unwrap();
+    test_indexer.update_slot(&mut rpc).await.unwrap();
+    let account_hash = test_indexer.compressed_accounts[0].hash().unwrap();
+    test_indexer.account_nullified(
+        env.merkle_tree_pubkey,
+        &bs58::encode(account_hash).into_string(),
+    );
+    let mut address = Pubkey::new_unique().to_bytes();
+    address[0] = 0;
+    let address_proofs = test_indexer
+        .get_multiple_new_address_proofs(env.address_merkle_tree_pubkey.to_bytes(), vec![address])
+        .await
+        .unwrap();
+    test_indexer.address_tree_updated(
+        env.address_merkle_tree_pubkey.to_bytes(),
+        &address_proofs[0],
+    );
+    assert_eq!(test_indexer.pending_changes(), 2);
+    assert_ne!(
+        test_indexer.state_merkle_trees[0].merkle_tree.root(),
+        pre_fork_indexer.state_merkle_trees[0].merkle_tree.root()
+    );
+    test_indexer.rollback(30).unwrap();
+    assert_indexers_eq(&test_indexer, &pre_fork_indexer);
+    assert_state_tree_matches_onchain(&mut rpc, &test_indexer, &env).await;
+}
+
+async fn compress(
+    rpc: &mut ProgramTestRpcConnection,
+    test_indexer: &mut TestIndexer<ProgramTestRpcConnection>,
+    env: &EnvAccounts,
+    compress_amount: u64,
+) {
+    let payer = rpc.get_payer().insecure_clone();
+    compress_sol_test(
+        rpc,
+        test_indexer,
+        &payer,
+        &[],
+        false,
+        compress_amount,
+        &env.merkle_tree_pubkey,
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+async fn assert_state_tree_matches_onchain(
+    rpc: &mut ProgramTestRpcConnection,
+    test_indexer: &TestIndexer<ProgramTestRpcConnection>,
+    env: &EnvAccounts,
+) {
+    let onchain_merkle_tree = get_concurrent_merkle_tree::<
+        StateMerkleTreeAccount,
+        ProgramTestRpcConnection,
+        Poseidon,
+        26,
+    >(rpc, env.merkle_tree_pubkey)
+    .await;
+    let merkle_tree = &test_indexer.state_merkle_trees[0].merkle_tree;
+    assert_eq!(onchain_merkle_tree.root(), merkle_tree.root());
+    assert_eq!(
+        onchain_merkle_tree.next_index(),
+        merkle_tree.rightmost_index
+    );
+}
+
+fn assert_indexers_eq(
+    test_indexer: &TestIndexer<ProgramTestRpcConnection>,
+    expected: &TestIndexer<ProgramTestRpcConnection>,
+) {
+    assert_eq!(
+        test_indexer.compressed_accounts,
+        expected.compressed_accounts
+    );
+    assert_eq!(
+        test_indexer.nullified_compressed_accounts,
+        expected.nullified_compressed_accounts
+    );
+    assert_eq!(test_indexer.events.len(), expected.events.len());
+    assert_eq!(test_indexer.closed_addresses, expected.closed_addresses);
+    for (bundle, expected_bundle) in test_indexer
+        .state_merkle_trees
+        .iter()
+        .zip(expected.state_merkle_trees.iter())
+    {
+        assert_eq!(
+            bundle.merkle_tree.layers,
+            expected_bundle.merkle_tree.layers
+        );
+        assert_eq!(bundle.merkle_tree.roots, expected_bundle.merkle_tree.roots);
+        assert_eq!(
+            bundle.merkle_tree.sequence_number,
+            expected_bundle.merkle_tree.sequence_number
+        );
+    }
+    for (bundle, expected_bundle) in test_indexer
+        .address_merkle_trees
+        .iter()
+        .zip(expected.address_merkle_trees.iter())
+    {
+        assert_eq!(
+            bundle.merkle_tree.root(),
+            expected_bundle.merkle_tree.root()
+        );
+        assert_eq!(indexed_elements(bundle), indexed_elements(expected_bundle));
+        assert_eq!(
+            bundle.indexed_array.highest_element_index,
+            expected_bundle.indexed_array.highest_element_index
+        );
+    }
+}
+
+fn indexed_elements(bundle: &AddressMerkleTreeBundle) -> Vec<(usize, Vec<u8>, usize)> {
+    bundle
+        .indexed_array
+        .elements
+        .iter()
+        .map(|element| {
+            (
+                element.index,
+                element.value.to_bytes_be(),
+                element.next_index,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,312 @@
+#![cfg(feature = "test-sbf")]
+use light_prover_client::prover::MockProver;
+use light_test_utils::{
+    indexer::{Indexer, PhotonServer, TestIndexer},
+    rpc::{rpc_connection::RpcConnection, test_rpc::ProgramTestRpcConnection},
+    system_program::compress_sol_test,
+    test_env::setup_test_programs_with_accounts,
+};
+use photon_api::{
+    apis::{configuration::Configuration, default_api},
+    models,
+};
+use serde_json::json;
+use solana_sdk::{bs58, signature::Signature, signer::Signer};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Tests the Photon API server against the indexer it serves:
+/// 1. compressed accounts and balance of an owner
+/// 2. pagination of the compressed accounts of an owner
+/// 3. account proofs include the canopy and match the indexer
+/// 4. new address proofs of an address which doesn't exist, in the default
+///    tree and in a given tree
+/// 5. validity proof of an account and a new address in a given tree
+/// 6. signatures and compression info of the reported transactions
+/// 7. unknown methods and invalid params return JSON-RPC errors
+#[tokio::test]
+async fn test_photon_server() {
+    let (mut rpc, env) = setup_test_programs_with_accounts(None).await;
+    let payer = rpc.get_payer().insecure_clone();
+    let test_indexer: TestIndexer<ProgramTestRpcConnection> =
+        TestIndexer::init_from_env_with_prover(&payer, &env, MockProver);
+    let test_indexer = Arc::new(Mutex::new(test_indexer));
+    let server = PhotonServer::new(test_indexer.clone());
+    let mut signatures = Vec::new();
+    for compress_amount in [1_000_000, 2_000_000] {
+        compress_sol_test(
+            &mut rpc,
+            &mut *test_indexer.lock().await,
+            &payer,
+            &[],
+            false,
+            compress_amount,
+            &env.merkle_tree_pubkey,
+            None,
+        )
+        .await
+        .unwrap();
+        // The test helpers don't return the signatures, unique ones are
+        // reported instead.
+        let signature = Signature::new_unique();
+        let slot = rpc.get_slot().await.unwrap();
+        let event = test_indexer.lock().await.events.last().unwrap().clone();
+        server.add_transaction(signature, slot, &event).await;
+        signatures.push(signature);
+    }
+    let handle = server.serve(([127, 0, 0, 1], 0).into()).unwrap();
+    let configuration = Configuration {
+        base_path: handle.url().to_string(),
+        ..Default::default()
+    };
+    let owner = payer.pubkey().to_string();
+
+    // 1. compressed accounts and balance of an owner
+    let request = models::GetCompressedAccountsByOwnerPostRequest {
+        params: Box::new(models::GetCompressedAccountsByOwnerPostRequestParams::new(
+            owner.clone(),
+        )),
+        ..Default::default()
+    };
+    let accounts = default_api::get_compressed_accounts_by_owner_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value
+        .items;
+    assert_eq!(accounts.len(), 2);
+    let mut lamports = accounts
+        .iter()
+        .map(|account| account.lamports)
+        .collect::<Vec<_>>();
+    lamports.sort();
+    assert_eq!(lamports, vec![1_000_000, 2_000_000]);
+    for account in accounts.iter() {
+        assert_eq!(account.owner, owner);
+        assert_eq!(account.tree, env.merkle_tree_pubkey.to_string());
+    }
+    let request = models::GetCompressedBalanceByOwnerPostRequest {
+        params: Box::new(models::GetCompressedBalanceByOwnerPostRequestParams::new(
+            owner.clone(),
+        )),
+        ..Default::default()
+    };
+    let balance = default_api::get_compressed_balance_by_owner_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value;
+    assert_eq!(balance, 3_000_000);
+
+    // 2. pagination of the compressed accounts of an owner
+    let mut params = models::GetCompressedAccountsByOwnerPostRequestParams::new(owner.clone());
+    params.limit = Some(Some(1));
+    let request = models::GetCompressedAccountsByOwnerPostRequest {
+        params: Box::new(params.clone()),
+        ..Default::default()
+    };
+    let page = default_api::get_compressed_accounts_by_owner_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value;
+    assert_eq!(page.items, accounts[..1]);
+    assert_eq!(page.cursor, Some(accounts[0].hash.clone()));
+    params.cursor = Some(page.cursor);
+    let request = models::GetCompressedAccountsByOwnerPostRequest {
+        params: Box::new(params),
+        ..Default::default()
+    };
+    let page = default_api::get_compressed_accounts_by_owner_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value;
+    assert_eq!(page.items, accounts[1..]);
+    assert_eq!(page.cursor, None);
+
+    // 3. account proofs include the canopy and match the indexer
+    let hashes = accounts
+        .iter()
+        .map(|account| account.hash.clone())
+        .collect::<Vec<_>>();
+    let request = models::GetMultipleCompressedAccountProofsPostRequest {
+        params: hashes.clone(),
+        ..Default::default()
+    };
+    let proofs = default_api::get_multiple_compressed_account_proofs_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value;
+    let expected_proofs = test_indexer
+        .lock()
+        .await
+        .get_multiple_compressed_account_proofs(hashes.clone())
+        .await
+        .unwrap();
+    assert_eq!(proofs.len(), expected_proofs.len());
+    for (proof, expected_proof) in proofs.iter().zip(expected_proofs.iter()) {
+        assert_eq!(proof.hash, expected_proof.hash);
+        assert_eq!(proof.leaf_index, expected_proof.leaf_index);
+        assert_eq!(proof.merkle_tree, expected_proof.merkle_tree);
+        assert_eq!(proof.root_seq, expected_proof.root_seq);
+        assert_eq!(proof.proof.len(), 26);
+        let expected_nodes = expected_proof
+            .proof
+            .iter()
+            .map(|node| bs58::encode(node).into_string())
+            .collect::<Vec<_>>();
+        assert_eq!(proof.proof[..expected_nodes.len()], expected_nodes);
+    }
+
+    // 4. new address proofs of an address which doesn't exist, in the default
+    //    tree and in a given tree
+    let address = bs58::encode([1u8; 32]).into_string();
+    let request = models::GetMultipleNewAddressProofsPostRequest {
+        params: vec![address.clone()],
+        ..Default::default()
+    };
+    let address_proofs = default_api::get_multiple_new_address_proofs_post(&configuration, request)
+        .await
+        .unwrap()
+        .result
+        .unwrap()
+        .value;
+    assert_eq!(address_proofs.len(), 1);
+    assert_eq!(address_proofs[0].address, address);
+    assert_eq!(
+        address_proofs[0].merkle_tree,
+        env.address_merkle_tree_pubkey.to_string()
+    );
+    assert_eq!(address_proofs[0].low_element_leaf_index, 0);
+    let address_merkle_tree = env.address_merkle_tree_pubkey.to_string();
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "method": "getMultipleNewAddressProofs",
+        "params": [{ "address": address, "tree": address_merkle_tree }],
+    });
+    let response = server.handle_request(request.to_string().as_bytes()).await;
+    assert_eq!(
+        response["result"]["value"][0]["merkleTree"],
+        address_merkle_tree
+    );
+    assert_eq!(
+        response["result"]["value"][0]["root"],
+        address_proofs[0].root
+    );
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "method": "getMultipleNewAddressProofs",
+        "params": [{ "address": address, "tree": env.merkle_tree_pubkey.to_string() }],
+    });
+    let response = server.handle_request(request.to_string().as_bytes()).await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    // 5. validity proof of an account and a new address in a given tree
+    // `photon-api` models the proof elements as binary strings, so the
+    // response is read as JSON.
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "1",
+        "method": "getValidityProof",
+        "params": {
+            "hashes": [hashes[0]],
+            "newAddresses": [{ "address": address, "tree": address_merkle_tree }],
+        },
+    });
+    let response = server.handle_request(request.to_string().as_bytes()).await;
+    let validity_proof = &response["result"];
+    let proof_len = |element: &str| {
+        validity_proof["compressedProof"][element]
+            .as_array()
+            .unwrap()
+            .len()
+    };
+    assert_eq!(proof_len("a"), 32);
+    assert_eq!(proof_len("b"), 64);
+    assert_eq!(proof_len("c"), 32);
+    assert_eq!(validity_proof["leaves"], json!([hashes[0], address]));
+    assert_eq!(
+        validity_proof["merkleTrees"],
+        json!([env.merkle_tree_pubkey.to_string(), address_merkle_tree])
+    );
+    assert_eq!(
+        validity_proof["roots"],
+        json!([proofs[0].root, address_proofs[0].root])
+    );
+
+    // 6. signatures and compression info of the reported transactions
+    let request = models::GetCompressionSignaturesForOwnerPostRequest {
+        params: Box::new(
+            models::GetCompressionSignaturesForOwnerPostRequestParams::new(owner.clone()),
+        ),
+        ..Default::default()
+    };
+    let signature_infos =
+        default_api::get_compression_signatures_for_owner_post(&configuration, request)
+            .await
+            .unwrap()
+            .result
+            .unwrap()
+            .value
+            .items;
+    // The newest signature is returned first.
+    assert_eq!(
+        signature_infos
+            .iter()
+            .map(|info| info.signature.clone())
+            .collect::<Vec<_>>(),
+        signatures
+            .iter()
+            .rev()
+            .map(|signature| signature.to_string())
+            .collect::<Vec<_>>()
+    );
+    let request = models::GetTransactionWithCompressionInfoPostRequest {
+        params: Box::new(
+            models::GetTransactionWithCompressionInfoPostRequestParams::new(
+                signatures[1].to_string(),
+            ),
+        ),
+        ..Default::default()
+    };
+    let compression_info =
+        default_api::get_transaction_with_compression_info_post(&configuration, request)
+            .await
+            .unwrap()
+            .result
+            .unwrap()
+            .compression_info
+            .unwrap();
+    assert!(compression_info.closed_accounts.is_empty());
+    assert_eq!(compression_info.opened_accounts.len(), 1);
+    assert_eq!(
+        compression_info.opened_accounts[0].account.lamports,
+        2_000_000
+    );
+
+    // 7. unknown methods and invalid params return JSON-RPC errors
+    let response = server
+        .handle_request(br#"{"jsonrpc":"2.0","id":"1","method":"getFoo","params":{}}"#)
+        .await;
+    assert_eq!(response["error"]["code"], -32601);
+    assert_eq!(response["id"], "1");
+    let response = server
+        .handle_request(
+            br#"{"jsonrpc":"2.0","id":"1","method":"getCompressedAccountsByOwner","params":{"owner":"invalid"}}"#,
+        )
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+    let response = server.handle_request(b"not json").await;
+    assert_eq!(response["error"]["code"], -32700);
+
+    handle.shutdown().await.unwrap();
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "test-sbf")]
+use account_compression::StateMerkleTreeAccount;
+use light_hasher::Poseidon;
+use light_prover_client::prover::MockProver;
+use light_system_program::sdk::invoke::get_sol_pool_pda;
+use light_test_utils::{
+    get_concurrent_merkle_tree,
+    indexer::{Indexer, TestIndexer},
+    rpc::{rpc_connection::RpcConnection, test_rpc::ProgramTestRpcConnection},
+    system_program::{compress_sol_test, decompress_sol_test},
+    test_env::{
+        setup_mock_proofs_test_programs_with_accounts, setup_test_programs_with_snapshot,
+        PAYER_KEYPAIR,
+    },
+};
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Tests:
+/// 1. a snapshot restored into a fresh environment matches the indexer and
+///    the accounts it was written from
+/// 2. accounts of the restored trees can be spent and decompressed
+#[tokio::test]
+async fn test_indexer_snapshot() {
+    let (mut rpc, env) = setup_mock_proofs_test_programs_with_accounts(None).await;
+    // The payer of the environment is the same in every test context.
+    let payer = Keypair::from_bytes(&PAYER_KEYPAIR).unwrap();
+    let mut test_indexer: TestIndexer<ProgramTestRpcConnection> =
+        TestIndexer::init_from_env_with_prover(&payer, &env, MockProver);
+    for compress_amount in [1_000_000, 2_000_000] {
+        compress_sol_test(
+            &mut rpc,
+            &mut test_indexer,
+            &payer,
+            &[],
+            false,
+            compress_amount,
+            &env.merkle_tree_pubkey,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    let path = std::env::temp_dir().join("light_test_indexer_snapshot.bin");
+    test_indexer
+        .write_snapshot(&mut rpc, &[get_sol_pool_pda()], &path)
+        .await
+        .unwrap();
+
+    // 1. a snapshot restored into a fresh environment matches the indexer and
+    //    the accounts it was written from
+    let (mut restored_rpc, env, mut restored_indexer) =
+        setup_test_programs_with_snapshot(None, &path, MockProver).await;
+    assert_eq!(
+        restored_indexer.compressed_accounts,
+        test_indexer.compressed_accounts
+    );
+    assert_eq!(restored_indexer.events, test_indexer.events);
+    for (restored_bundle, bundle) in restored_indexer
+        .state_merkle_trees
+        .iter()
+        .zip(test_indexer.state_merkle_trees.iter())
+    {
+        assert_eq!(restored_bundle.accounts, bundle.accounts);
+        assert_eq!(
+            restored_bundle.merkle_tree.root(),
+            bundle.merkle_tree.root()
+        );
+        assert_eq!(
+            restored_bundle.merkle_tree.sequence_number,
+            bundle.merkle_tree.sequence_number
+        );
+    }
+    for (restored_bundle, bundle) in restored_indexer
+        .address_merkle_trees
+        .iter()
+        .zip(test_indexer.address_merkle_trees.iter())
+    {
+        assert_eq!(
+            restored_bundle.merkle_tree.root(),
+            bundle.merkle_tree.root()
+        );
+        assert_eq!(
+            restored_bundle.indexed_array.elements.len(),
+            bundle.indexed_array.elements.len()
+        );
+    }
+    let onchain_merkle_tree = get_concurrent_merkle_tree::<
+        StateMerkleTreeAccount,
+        ProgramTestRpcConnection,
+        Poseidon,
+        26,
+    >(&mut restored_rpc, env.merkle_tree_pubkey)
+    .await;
+    assert_eq!(
+        onchain_merkle_tree.root(),
+        restored_indexer.state_merkle_trees[0].merkle_tree.root()
+    );
+    assert_eq!(
+        restored_rpc.get_balance(&get_sol_pool_pda()).await.unwrap(),
+        rpc.get_balance(&get_sol_pool_pda()).await.unwrap()
+    );
+
+    // 2. accounts of the restored trees can be spent and decompressed
+    let input_accounts = restored_indexer.get_compressed_accounts_by_owner(&payer.pubkey());
+    decompress_sol_test(
+        &mut restored_rpc,
+        &mut restored_indexer,
+        &payer,
+        &input_accounts,
+        &Keypair::new().pubkey(),
+        2_500_000,
+        &env.merkle_tree_pubkey,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(restored_indexer
+        .get_compressed_accounts_by_owner(&payer.pubkey())
+        .is_empty());
+    // The change is owned by the payer of the context.
+    let accounts =
+        restored_indexer.get_compressed_accounts_by_owner(&restored_rpc.get_payer().pubkey());
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].compressed_account.lamports, 500_000);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -2,6 +2,7 @@
 use account_compression::errors::AccountCompressionErrorCode;
 use anchor_lang::error::ErrorCode;
 use light_hasher::Poseidon;
+use light_registry::get_verifying_key_pda_address;
 use light_system_program::{
     errors::SystemProgramError,
     sdk::{
@@ -21,6 +22,7 @@ use light_test_utils::{
     assert_compressed_tx::assert_created_compressed_accounts,
     assert_custom_error_or_program_error,
     indexer::TestIndexer,
+    registry::compiled_verifying_key_shapes,
     system_program::{
         compress_sol_test, create_addresses_test, decompress_sol_test, transfer_compressed_sol_test,
     },
@@ -67,6 +69,7 @@ use tokio::fs::write as async_write;
 /// 5. invalid Merkle tree account (AccountDiscriminatorMismatch)
 /// 6.1 invalid queue account (InvalidQueueType)
 /// 6.2 invalid queue account (AccountDiscriminatorMismatch)
+/// 7. missing verifying key account (VerifyingKeyAccountMissing)
 #[tokio::test]
 async fn invoke_failing_test() {
     let (mut context, env) = setup_test_programs_with_accounts(None).await;
@@ -217,7 +220,8 @@ pub async fn failing_transaction_inputs(
                     proof_input_address_merkle_tree_pubkeys,
                     context,
                 )
-                .await;
+                .await
+                .unwrap();
             for (i, root_index) in proof_rpc_res.address_root_indices.iter().enumerate() {
                 new_address_params[i].address_merkle_tree_root_index = *root_index;
             }
@@ -514,6 +518,20 @@ pub async fn failing_transaction_inputs_inner(
         .await
         .unwrap();
     }
+    // missing verifying key account
+    {
+        let mut remaining_accounts = remaining_accounts.clone();
+        remaining_accounts.pop();
+        create_instruction_and_failing_transaction(
+            context,
+            payer,
+            inputs_struct.clone(),
+            remaining_accounts,
+            SystemProgramError::VerifyingKeyAccountMissing.into(),
+        )
+        .await
+        .unwrap();
+    }
     Ok(())
 }
 
@@ -904,7 +922,10 @@ async fn invoke_test() {
         .await
         .unwrap()
         .unwrap();
-    let (created_compressed_accounts, _) = test_indexer.add_event_and_compressed_accounts(&event.0);
+    let (created_compressed_accounts, _) = test_indexer
+        .add_event_and_compressed_accounts(&mut context, &event.0)
+        .await
+        .unwrap();
     assert_created_compressed_accounts(
         output_compressed_accounts.as_slice(),
         output_merkle_tree_pubkeys.as_slice(),
@@ -997,7 +1018,8 @@ async fn invoke_test() {
             None,
             &mut context,
         )
-        .await;
+        .await
+        .unwrap();
     let input_compressed_accounts = vec![compressed_account_with_context.compressed_account];
     let instruction = create_invoke_instruction(
         &payer_pubkey,
@@ -1036,7 +1058,10 @@ async fn invoke_test() {
         .await
         .unwrap()
         .unwrap();
-    test_indexer.add_event_and_compressed_accounts(&event.0);
+    test_indexer
+        .add_event_and_compressed_accounts(&mut context, &event.0)
+        .await
+        .unwrap();
 
     println!("Double spend -------------------------");
     let output_compressed_accounts = vec![CompressedAccount {
@@ -1398,7 +1423,8 @@ async fn test_with_compression() {
             None,
             &mut context,
         )
-        .await;
+        .await
+        .unwrap();
     let input_compressed_accounts =
         vec![compressed_account_with_context.clone().compressed_account];
     let recipient_pubkey = Keypair::new().pubkey();
@@ -1484,6 +1510,16 @@ async fn regenerate_accounts() {
             env.registered_forester_epoch_pda,
         ),
     ];
+    let mut pubkeys = pubkeys
+        .into_iter()
+        .map(|(name, pubkey)| (name.to_string(), pubkey))
+        .collect::<Vec<_>>();
+    for (id, shape) in compiled_verifying_key_shapes() {
+        pubkeys.push((
+            format!("verifying_key_{}", id),
+            get_verifying_key_pda_address(&shape).0,
+        ));
+    }
 
     for (name, pubkey) in pubkeys {
         // Fetch account data. Adjust this part to match how you retrieve and structure your account data.
@@ -11,8 +11,6 @@ use light_system_program::sdk::{
     invoke::get_sol_pool_pda,
 };
 use log::debug;
-use num_bigint::BigUint;
-use num_traits::FromBytes;
 use solana_sdk::account::ReadableAccount;
 use solana_sdk::pubkey::Pubkey;
 
@@ -127,9 +125,7 @@ pub async fn assert_nullifiers_exist_in_hash_sets<R: RpcConnection>(
         let nullifier_queue = unsafe {
             get_hash_set::<QueueAccount, R>(rpc, snapshots[i].accounts.nullifier_queue).await
         };
-        assert!(nullifier_queue
-            .contains(&BigUint::from_be_bytes(hash.as_slice()), None)
-            .unwrap());
+        assert!(nullifier_queue.contains(hash, None).unwrap());
     }
 }
 
@@ -140,9 +136,7 @@ pub async fn assert_addresses_exist_in_hash_sets<R: RpcConnection>(
 ) {
     for (address, pubkey) in created_addresses.iter().zip(address_queue_pubkeys) {
         let address_queue = unsafe { get_hash_set::<QueueAccount, R>(rpc, *pubkey).await };
-        assert!(address_queue
-            .contains(&BigUint::from_be_bytes(address), None)
-            .unwrap());
+        assert!(address_queue.contains(address, None).unwrap());
     }
 }
 
@@ -209,8 +203,8 @@ pub fn assert_public_transaction_event(
     let mut updated_sequence_numbers = event.sequence_numbers.clone();
     for account in event.output_compressed_accounts.iter() {
         let merkle_tree_pubkey = event.pubkey_array[account.merkle_tree_index as usize];
-        let index = &mut updated_sequence_numbers
-            .iter_mut()
+        let index = updated_sequence_numbers
+            .iter()
             .find(|x| x.pubkey == merkle_tree_pubkey);
         if index.is_none() {
             debug!("reference sequence numbers: {:?}", sequence_numbers);
@@ -219,10 +213,13 @@ pub fn assert_public_transaction_event(
                 "merkle tree pubkey not found in sequence numbers : {:?}",
                 merkle_tree_pubkey
             );
-        } else {
-            index.as_mut().unwrap().seq += 1;
         }
     }
+    // Outputs of a tree are appended in one batch, which increments the
+    // sequence number once.
+    for sequence_number in updated_sequence_numbers.iter_mut() {
+        sequence_number.seq += 1;
+    }
     for sequence_number in updated_sequence_numbers.iter() {
         sequence_numbers.iter().any(|x| x == sequence_number);
     }
@@ -643,7 +643,7 @@ where
         from: &Keypair,
         to: &Pubkey,
     ) -> Result<Signature, RpcError> {
-        let input_compressed_accounts = self.get_compressed_sol_accounts(&from.pubkey());
+        let input_compressed_accounts = self.get_compressed_sol_accounts(&from.pubkey()).await;
         let output_merkle_tree = self.indexer.get_state_merkle_trees()[0]
             .accounts
             .merkle_tree;
@@ -667,7 +667,7 @@ where
     }
 
     pub async fn transfer_sol(&mut self, user_index: usize) {
-        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index);
+        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index).await;
 
         if !input_compressed_accounts.is_empty() {
             println!("\n --------------------------------------------------\n\t\t Transfer Sol\n --------------------------------------------------");
@@ -718,7 +718,7 @@ where
     }
 
     pub async fn decompress_sol(&mut self, user_index: usize) {
-        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index);
+        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index).await;
 
         if !input_compressed_accounts.is_empty() {
             println!("\n --------------------------------------------------\n\t\t Decompress Sol\n --------------------------------------------------");
@@ -760,7 +760,7 @@ where
     }
 
     pub async fn compress_sol_deterministic(&mut self, from: &Keypair, amount: u64) {
-        let input_compressed_accounts = self.get_compressed_sol_accounts(&from.pubkey());
+        let input_compressed_accounts = self.get_compressed_sol_accounts(&from.pubkey()).await;
         let output_merkle_tree = self.indexer.get_state_merkle_trees()[0]
             .accounts
             .merkle_tree;
@@ -794,7 +794,7 @@ where
         // Limit max compress amount to 1 sol so that context.payer doesn't get depleted by airdrops.
         let max_amount = std::cmp::min(balance, 1_000_000_000);
         let amount = Self::safe_gen_range(&mut self.rng, 1000..max_amount, max_amount / 2);
-        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index);
+        let input_compressed_accounts = self.get_random_compressed_sol_accounts(user_index).await;
         let create_output_compressed_accounts_for_input_accounts = false;
         // TODO: debug Merkle trees in wrong order
         // if input_compressed_accounts.is_empty() {
@@ -1177,23 +1177,28 @@ where
         Ok(())
     }
 
-    pub fn get_random_compressed_sol_accounts(
+    pub async fn get_random_compressed_sol_accounts(
         &mut self,
         user_index: usize,
     ) -> Vec<CompressedAccountWithMerkleContext> {
         let input_compressed_accounts = self
             .indexer
-            .get_compressed_accounts_by_owner(&self.users[user_index].keypair.pubkey());
+            .get_compressed_accounts_by_owner(&self.users[user_index].keypair.pubkey())
+            .await
+            .unwrap();
         let range = std::cmp::min(input_compressed_accounts.len(), 4);
         let number_of_compressed_accounts = Self::safe_gen_range(&mut self.rng, 0..=range, 0);
         input_compressed_accounts[0..number_of_compressed_accounts].to_vec()
     }
 
-    pub fn get_compressed_sol_accounts(
+    pub async fn get_compressed_sol_accounts(
         &self,
         pubkey: &Pubkey,
     ) -> Vec<CompressedAccountWithMerkleContext> {
-        self.indexer.get_compressed_accounts_by_owner(pubkey)
+        self.indexer
+            .get_compressed_accounts_by_owner(pubkey)
+            .await
+            .unwrap()
     }
 
     pub fn get_merkle_tree_pubkeys(&mut self, num: u64) -> Vec<Pubkey> {
@@ -1244,7 +1249,11 @@ where
         &mut self,
         user: &Pubkey,
     ) -> (Pubkey, Vec<TokenDataWithContext>) {
-        let user_token_accounts = &mut self.indexer.get_compressed_token_accounts_by_owner(user);
+        let user_token_accounts = &mut self
+            .indexer
+            .get_compressed_token_accounts_by_owner(user)
+            .await
+            .unwrap();
         // clean up dust so that we don't run into issues that account balances are too low
         user_token_accounts.retain(|t| t.token_data.amount > 1000);
         let token_accounts_with_mint;
@@ -1275,6 +1284,8 @@ where
             token_accounts_with_mint = self
                 .indexer
                 .get_compressed_token_accounts_by_owner(user)
+                .await
+                .unwrap()
                 .iter()
                 .filter(|token_account| token_account.token_data.mint == mint)
                 .cloned()
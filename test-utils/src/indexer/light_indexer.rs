@@ -0,0 +1,623 @@
+//! Implementation of the [`Indexer`] trait on top of a [`LightIndexer`].
+//!
+//! [`LightTestIndexer`] serves accounts and proofs from the `LightIndexer`
+//! and keeps Merkle tree bundles and token accounts in the layout of
+//! [`TestIndexer`](crate::indexer::TestIndexer), so that it can be used in
+//! its place. Proofs are returned without the nodes of the canopy, like the
+//! proofs of `TestIndexer`.
+//!
+//! The bundles are copies of the trees of the `LightIndexer`, which are
+//! refreshed by [`LightTestIndexer::sync`] after every change made through
+//! the `Indexer` trait. Bundles pushed through the mutable accessors are
+//! added to the `LightIndexer` on the next sync, changes to the trees of
+//! existing bundles are overwritten by it.
+
+use std::sync::Arc;
+
+use account_compression::{AddressMerkleTreeAccount, StateMerkleTreeAccount};
+use anchor_lang::prelude::Pubkey;
+use light_hasher::Poseidon;
+use light_indexer::{
+    store::{AccountRecord, IndexerStore, MemoryStore, TreeInfo, TreeType},
+    LightIndexer, LightIndexerError,
+};
+use light_merkle_tree_reference::MerkleTree;
+use light_prover_client::{
+    inclusion::merkle_inclusion_proof_inputs::InclusionMerkleProofInputs,
+    non_inclusion::merkle_non_inclusion_proof_inputs::NonInclusionMerkleProofInputs,
+    prover::{ProofRequest, ProofService, ProverBackend},
+};
+use light_system_program::{
+    invoke::processor::CompressedProof,
+    sdk::{compressed_account::CompressedAccountWithMerkleContext, event::PublicTransactionEvent},
+};
+use light_utils::bigint::bigint_to_be_bytes_array;
+use log::warn;
+use num_bigint::{BigInt, BigUint};
+use num_traits::ops::bytes::FromBytes;
+use solana_sdk::{bs58, signature::Keypair, signer::Signer};
+
+use crate::{
+    get_concurrent_merkle_tree, get_indexed_merkle_tree,
+    indexer::{
+        test_indexer::ProofRpcResult, AddressMerkleTreeAccounts, AddressMerkleTreeBundle, Indexer,
+        IndexerError, MerkleProof, NewAddressProofWithContext, StateMerkleTreeAccounts,
+        StateMerkleTreeBundle, TokenDataWithContext,
+    },
+    rpc::rpc_connection::RpcConnection,
+    transaction_params::FeeConfig,
+};
+
+impl From<StateMerkleTreeAccounts> for TreeInfo {
+    fn from(accounts: StateMerkleTreeAccounts) -> Self {
+        TreeInfo::state(
+            accounts.merkle_tree,
+            accounts.nullifier_queue,
+            accounts.cpi_context,
+        )
+    }
+}
+
+impl From<AddressMerkleTreeAccounts> for TreeInfo {
+    fn from(accounts: AddressMerkleTreeAccounts) -> Self {
+        TreeInfo::address(accounts.merkle_tree, accounts.queue)
+    }
+}
+
+impl From<LightIndexerError> for IndexerError {
+    fn from(error: LightIndexerError) -> Self {
+        IndexerError::Custom(error.to_string())
+    }
+}
+
+/// Splits accounts into compressed accounts and token accounts.
+fn split_token_accounts(
+    accounts: Vec<AccountRecord>,
+) -> (
+    Vec<CompressedAccountWithMerkleContext>,
+    Vec<TokenDataWithContext>,
+) {
+    let mut compressed_accounts = Vec::new();
+    let mut token_accounts = Vec::new();
+    for account in accounts {
+        match account.token_data {
+            Some(token_data) => token_accounts.push(TokenDataWithContext {
+                token_data,
+                compressed_account: account.account,
+            }),
+            None => compressed_accounts.push(account.account),
+        }
+    }
+    (compressed_accounts, token_accounts)
+}
+
+fn decode_hash(hash: &str) -> Result<[u8; 32], IndexerError> {
+    bs58::decode(hash)
+        .into_vec()
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .ok_or_else(|| IndexerError::Custom(format!("invalid hash {}", hash)))
+}
+
+/// [`Indexer`] backed by a [`LightIndexer`].
+#[derive(Debug)]
+pub struct LightTestIndexer<S: IndexerStore = MemoryStore> {
+    pub indexer: LightIndexer<S>,
+    pub state_merkle_trees: Vec<StateMerkleTreeBundle>,
+    pub address_merkle_trees: Vec<AddressMerkleTreeBundle>,
+    /// Token accounts which aren't nullified.
+    pub token_compressed_accounts: Vec<TokenDataWithContext>,
+    pub payer: Keypair,
+    pub group_pda: Pubkey,
+    /// Proof service shared by clones of the indexer.
+    pub prover: Arc<ProofService<ProverBackend>>,
+}
+
+impl<S: IndexerStore> Clone for LightTestIndexer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            indexer: self.indexer.clone(),
+            state_merkle_trees: self.state_merkle_trees.clone(),
+            address_merkle_trees: self.address_merkle_trees.clone(),
+            token_compressed_accounts: self.token_compressed_accounts.clone(),
+            payer: self.payer.insecure_clone(),
+            group_pda: self.group_pda,
+            prover: self.prover.clone(),
+        }
+    }
+}
+
+impl<S: IndexerStore> LightTestIndexer<S> {
+    /// Creates bundles of the trees of `indexer`, proofs are generated with
+    /// `prover`.
+    pub fn new<P: Into<ProverBackend>>(
+        indexer: LightIndexer<S>,
+        payer: &Keypair,
+        group_pda: Pubkey,
+        prover: P,
+    ) -> Result<Self, IndexerError> {
+        let mut light_test_indexer = Self {
+            indexer,
+            state_merkle_trees: Vec::new(),
+            address_merkle_trees: Vec::new(),
+            token_compressed_accounts: Vec::new(),
+            payer: payer.insecure_clone(),
+            group_pda,
+            prover: Arc::new(ProofService::new(prover.into(), Default::default())),
+        };
+        light_test_indexer.sync()?;
+        Ok(light_test_indexer)
+    }
+
+    /// Adds the trees of new bundles to the indexer and copies the leaves
+    /// and addresses of trees whose root differs from the indexer into their
+    /// bundles. Has to be called after blocks have been indexed by another
+    /// clone of the indexer.
+    pub fn sync(&mut self) -> Result<(), IndexerError> {
+        for accounts in self.state_merkle_trees.iter().map(|x| x.accounts) {
+            if self.indexer.tree(&accounts.merkle_tree).is_none() {
+                self.indexer.add_tree(accounts.into())?;
+            }
+        }
+        for accounts in self.address_merkle_trees.iter().map(|x| x.accounts) {
+            if self.indexer.tree(&accounts.merkle_tree).is_none() {
+                self.indexer.add_tree(accounts.into())?;
+            }
+        }
+        for tree in self.indexer.trees() {
+            match tree.tree_type {
+                TreeType::State => self.sync_state_tree(&tree)?,
+                TreeType::Address => self.sync_address_tree(&tree)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_state_tree(&mut self, tree: &TreeInfo) -> Result<(), IndexerError> {
+        let root = self.indexer.root(&tree.merkle_tree)?;
+        let index = match self
+            .state_merkle_trees
+            .iter()
+            .position(|x| x.accounts.merkle_tree == tree.merkle_tree)
+        {
+            Some(i) if self.state_merkle_trees[i].merkle_tree.root() == root => return Ok(()),
+            Some(i) => i,
+            None => {
+                self.state_merkle_trees.push(StateMerkleTreeBundle {
+                    rollover_fee: FeeConfig::default().state_merkle_tree_rollover as i64,
+                    merkle_tree: Box::new(MerkleTree::new(
+                        tree.height as usize,
+                        tree.canopy_depth as usize,
+                    )),
+                    accounts: StateMerkleTreeAccounts {
+                        merkle_tree: tree.merkle_tree,
+                        nullifier_queue: tree.queue,
+                        cpi_context: tree.cpi_context.unwrap_or_default(),
+                    },
+                });
+                self.state_merkle_trees.len() - 1
+            }
+        };
+        let leaves = self.indexer.state_tree_leaves(&tree.merkle_tree)?;
+        let merkle_tree = &mut self.state_merkle_trees[index].merkle_tree;
+        if merkle_tree.leaves().len() > leaves.len() {
+            **merkle_tree = MerkleTree::new(tree.height as usize, tree.canopy_depth as usize);
+        }
+        // Only changed leaves are updated, new leaves are appended.
+        for (leaf_index, leaf) in leaves.iter().enumerate() {
+            let result = if leaf_index >= merkle_tree.leaves().len() {
+                merkle_tree.append(leaf).map_err(|e| e.to_string())
+            } else if merkle_tree.leaf(leaf_index) != *leaf {
+                merkle_tree
+                    .update(leaf, leaf_index)
+                    .map_err(|e| e.to_string())
+            } else {
+                Ok(())
+            };
+            result.map_err(IndexerError::Custom)?;
+        }
+        Ok(())
+    }
+
+    fn sync_address_tree(&mut self, tree: &TreeInfo) -> Result<(), IndexerError> {
+        let root = self.indexer.root(&tree.merkle_tree)?;
+        let address_tree = match self
+            .address_merkle_trees
+            .iter()
+            .position(|x| x.accounts.merkle_tree == tree.merkle_tree)
+        {
+            Some(i) if self.address_merkle_trees[i].merkle_tree.root() == root => return Ok(()),
+            Some(i) => {
+                let address_tree = self.indexer.address_tree(&tree.merkle_tree)?;
+                let bundle = &mut self.address_merkle_trees[i];
+                bundle.merkle_tree = address_tree.merkle_tree;
+                bundle.indexed_array = address_tree.indexed_array;
+                return Ok(());
+            }
+            None => self.indexer.address_tree(&tree.merkle_tree)?,
+        };
+        self.address_merkle_trees.push(AddressMerkleTreeBundle {
+            rollover_fee: FeeConfig::default().address_queue_rollover as i64,
+            merkle_tree: address_tree.merkle_tree,
+            indexed_array: address_tree.indexed_array,
+            accounts: AddressMerkleTreeAccounts {
+                merkle_tree: tree.merkle_tree,
+                queue: tree.queue,
+            },
+        });
+        Ok(())
+    }
+
+    fn canopy_depth(&self, merkle_tree: &Pubkey) -> Result<usize, IndexerError> {
+        Ok(self
+            .indexer
+            .tree(merkle_tree)
+            .ok_or(LightIndexerError::UnknownMerkleTree(*merkle_tree))?
+            .canopy_depth as usize)
+    }
+
+    /// Returns inclusion proof inputs of the accounts `hashes` and the
+    /// current root indices of their trees.
+    async fn inclusion_proof_inputs<R: RpcConnection>(
+        &self,
+        hashes: &[[u8; 32]],
+        rpc: &mut R,
+    ) -> Result<(Vec<InclusionMerkleProofInputs>, Vec<u16>, Vec<Pubkey>), IndexerError> {
+        let mut inputs = Vec::with_capacity(hashes.len());
+        let mut root_indices = Vec::with_capacity(hashes.len());
+        let mut merkle_trees = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            let proof = self.indexer.get_account_proof(hash)?;
+            let fetched_merkle_tree =
+                get_concurrent_merkle_tree::<StateMerkleTreeAccount, R, Poseidon, 26>(
+                    rpc,
+                    proof.merkle_tree,
+                )
+                .await;
+            if fetched_merkle_tree.root() != proof.root {
+                return Err(IndexerError::Custom(format!(
+                    "indexed root of Merkle tree {} doesn't match the account",
+                    proof.merkle_tree
+                )));
+            }
+            self.prover
+                .update_root_history(proof.merkle_tree, fetched_merkle_tree.roots.iter().copied());
+            inputs.push(InclusionMerkleProofInputs {
+                root: BigInt::from_be_bytes(proof.root.as_slice()),
+                leaf: BigInt::from_be_bytes(hash),
+                path_index: BigInt::from(proof.leaf_index),
+                path_elements: proof
+                    .proof
+                    .iter()
+                    .map(|x| BigInt::from_be_bytes(x))
+                    .collect(),
+            });
+            root_indices.push(fetched_merkle_tree.root_index() as u16);
+            merkle_trees.push(proof.merkle_tree);
+        }
+        Ok((inputs, root_indices, merkle_trees))
+    }
+
+    /// Returns non-inclusion proof inputs of `addresses` in the address
+    /// trees `merkle_trees` and the current root indices of the trees.
+    async fn non_inclusion_proof_inputs<R: RpcConnection>(
+        &self,
+        addresses: &[[u8; 32]],
+        merkle_trees: &[Pubkey],
+        rpc: &mut R,
+    ) -> Result<(Vec<NonInclusionMerkleProofInputs>, Vec<u16>), IndexerError> {
+        if addresses.len() != merkle_trees.len() {
+            return Err(IndexerError::Custom(
+                "every address needs an address Merkle tree".to_string(),
+            ));
+        }
+        let mut inputs = Vec::with_capacity(addresses.len());
+        let mut root_indices = Vec::with_capacity(addresses.len());
+        for (address, merkle_tree) in addresses.iter().zip(merkle_trees.iter()) {
+            let proof = self.indexer.get_new_address_proof(merkle_tree, address)?;
+            let fetched_merkle_tree =
+                get_indexed_merkle_tree::<AddressMerkleTreeAccount, R, Poseidon, usize, 26, 16>(
+                    rpc,
+                    *merkle_tree,
+                )
+                .await;
+            if fetched_merkle_tree.root() != proof.root {
+                return Err(IndexerError::Custom(format!(
+                    "indexed root of address Merkle tree {} doesn't match the account",
+                    merkle_tree
+                )));
+            }
+            self.prover
+                .update_root_history(*merkle_tree, fetched_merkle_tree.roots.iter().copied());
+            inputs.push(NonInclusionMerkleProofInputs {
+                root: BigInt::from_be_bytes(proof.root.as_slice()),
+                value: BigInt::from_be_bytes(address),
+                leaf_lower_range_value: BigInt::from(proof.low_element.value.clone()),
+                leaf_higher_range_value: BigInt::from(proof.low_element_next_value.clone()),
+                next_index: BigInt::from(proof.low_element.next_index),
+                merkle_proof_hashed_indexed_element_leaf: proof
+                    .low_element_proof
+                    .iter()
+                    .map(|x| BigInt::from_be_bytes(x))
+                    .collect(),
+                index_hashed_indexed_element_leaf: BigInt::from(proof.low_element.index),
+            });
+            root_indices.push(fetched_merkle_tree.root_index() as u16);
+        }
+        Ok((inputs, root_indices))
+    }
+
+    /// Creates a validity proof of the accounts `compressed_accounts` and
+    /// the new addresses `new_addresses` with their current root indices.
+    pub async fn create_proof<R: RpcConnection>(
+        &self,
+        compressed_accounts: &[[u8; 32]],
+        new_addresses: &[[u8; 32]],
+        address_merkle_tree_pubkeys: &[Pubkey],
+        rpc: &mut R,
+    ) -> Result<ProofRpcResult, IndexerError> {
+        let (inclusion_inputs, root_indices, state_merkle_trees) = self
+            .inclusion_proof_inputs(compressed_accounts, rpc)
+            .await?;
+        let (non_inclusion_inputs, address_root_indices) = self
+            .non_inclusion_proof_inputs(new_addresses, address_merkle_tree_pubkeys, rpc)
+            .await?;
+        let request = ProofRequest::new(inclusion_inputs, non_inclusion_inputs)
+            .with_trees(state_merkle_trees, address_merkle_tree_pubkeys.to_vec());
+        let result = self
+            .prover
+            .prove(&request)
+            .await
+            .map_err(|e| IndexerError::Custom(e.to_string()))?;
+        Ok(ProofRpcResult {
+            proof: CompressedProof {
+                a: result.proof.a,
+                b: result.proof.b,
+                c: result.proof.c,
+            },
+            root_indices,
+            address_root_indices,
+        })
+    }
+
+    fn index_event(
+        &mut self,
+        slot: u64,
+        event: &PublicTransactionEvent,
+    ) -> Result<
+        (
+            Vec<CompressedAccountWithMerkleContext>,
+            Vec<TokenDataWithContext>,
+        ),
+        IndexerError,
+    > {
+        let accounts = self.indexer.index_transaction_event(slot, event)?;
+        self.indexer.flush()?;
+        let (compressed_accounts, token_accounts) = split_token_accounts(accounts);
+        self.token_compressed_accounts.retain(|account| {
+            account
+                .compressed_account
+                .hash()
+                .map(|hash| !event.input_compressed_account_hashes.contains(&hash))
+                .unwrap_or(true)
+        });
+        self.token_compressed_accounts
+            .extend(token_accounts.iter().cloned());
+        self.sync()?;
+        Ok((compressed_accounts, token_accounts))
+    }
+}
+
+impl<R: RpcConnection, S: IndexerStore + 'static> Indexer<R> for LightTestIndexer<S> {
+    async fn get_multiple_compressed_account_proofs(
+        &self,
+        hashes: Vec<String>,
+    ) -> Result<Vec<MerkleProof>, IndexerError> {
+        let mut proofs = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            let mut proof = self.indexer.get_account_proof(&decode_hash(&hash)?)?;
+            let canopy_depth = self.canopy_depth(&proof.merkle_tree)?;
+            proof.proof.truncate(proof.proof.len() - canopy_depth);
+            proofs.push(MerkleProof {
+                hash,
+                leaf_index: proof.leaf_index,
+                merkle_tree: proof.merkle_tree.to_string(),
+                proof: proof.proof,
+                root_seq: proof.root_seq,
+            });
+        }
+        Ok(proofs)
+    }
+
+    async fn get_rpc_compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<String>, IndexerError> {
+        Ok(self
+            .indexer
+            .get_compressed_accounts_by_owner(owner)?
+            .into_iter()
+            .filter(|account| account.token_data.is_none())
+            .map(|account| bs58::encode(account.hash).into_string())
+            .collect())
+    }
+
+    async fn get_multiple_new_address_proofs(
+        &self,
+        merkle_tree_pubkey: [u8; 32],
+        addresses: Vec<[u8; 32]>,
+    ) -> Result<Vec<NewAddressProofWithContext>, IndexerError> {
+        let merkle_tree = Pubkey::from(merkle_tree_pubkey);
+        let canopy_depth = self.canopy_depth(&merkle_tree)?;
+        let mut proofs = Vec::with_capacity(addresses.len());
+        for address in addresses.iter() {
+            let proof = self.indexer.get_new_address_proof(&merkle_tree, address)?;
+            let low_address_proof = proof.low_element_proof
+                [..proof.low_element_proof.len() - canopy_depth]
+                .try_into()
+                .map_err(|_| IndexerError::Custom("invalid address tree height".to_string()))?;
+            let to_bytes = |value: &BigUint| {
+                bigint_to_be_bytes_array::<32>(value)
+                    .map_err(|e| IndexerError::Custom(e.to_string()))
+            };
+            proofs.push(NewAddressProofWithContext {
+                merkle_tree: merkle_tree_pubkey,
+                root: proof.root,
+                root_seq: proof.root_seq,
+                low_address_index: proof.low_element.index as u64,
+                low_address_value: to_bytes(&proof.low_element.value)?,
+                low_address_next_index: proof.low_element.next_index as u64,
+                low_address_next_value: to_bytes(&proof.low_element_next_value)?,
+                low_address_proof,
+                new_low_element: Some(proof.new_element.new_low_element),
+                new_element: Some(proof.new_element.new_element),
+                new_element_next_value: Some(proof.new_element.new_element_next_value),
+            });
+        }
+        Ok(proofs)
+    }
+
+    fn account_nullified(&mut self, merkle_tree_pubkey: Pubkey, account_hash: &str) {
+        let result = decode_hash(account_hash).and_then(|hash| {
+            self.indexer
+                .nullify_leaf(&merkle_tree_pubkey, &hash)
+                .and_then(|_| self.indexer.flush())
+                .map_err(IndexerError::from)?;
+            self.sync()
+        });
+        if let Err(e) = result {
+            warn!("Failed to nullify account {}: {}", account_hash, e);
+        }
+    }
+
+    fn address_tree_updated(
+        &mut self,
+        merkle_tree_pubkey: [u8; 32],
+        context: &NewAddressProofWithContext,
+    ) {
+        let Some(new_element) = context.new_element.as_ref() else {
+            return;
+        };
+        let result = bigint_to_be_bytes_array::<32>(&new_element.value)
+            .map_err(|e| IndexerError::Custom(e.to_string()))
+            .and_then(|address| {
+                self.indexer
+                    .insert_address(&Pubkey::from(merkle_tree_pubkey), &address)
+                    .and_then(|_| self.indexer.flush())
+                    .map_err(IndexerError::from)?;
+                self.sync()
+            });
+        if let Err(e) = result {
+            warn!("Failed to insert address {:?}: {}", new_element.value, e);
+        }
+    }
+
+    fn get_state_merkle_tree_accounts(&self, pubkeys: &[Pubkey]) -> Vec<StateMerkleTreeAccounts> {
+        pubkeys
+            .iter()
+            .map(|pubkey| {
+                self.state_merkle_trees
+                    .iter()
+                    .find(|x| x.accounts.merkle_tree == *pubkey)
+                    .expect("state Merkle tree is not indexed")
+                    .accounts
+            })
+            .collect()
+    }
+
+    async fn add_event_and_compressed_accounts(
+        &mut self,
+        rpc: &mut R,
+        event: &PublicTransactionEvent,
+    ) -> Result<
+        (
+            Vec<CompressedAccountWithMerkleContext>,
+            Vec<TokenDataWithContext>,
+        ),
+        IndexerError,
+    > {
+        let slot = rpc.get_slot().await?;
+        self.index_event(slot, event)
+    }
+
+    fn get_state_merkle_trees(&self) -> &Vec<StateMerkleTreeBundle> {
+        &self.state_merkle_trees
+    }
+
+    fn get_state_merkle_trees_mut(&mut self) -> &mut Vec<StateMerkleTreeBundle> {
+        &mut self.state_merkle_trees
+    }
+
+    fn get_address_merkle_trees(&self) -> &Vec<AddressMerkleTreeBundle> {
+        &self.address_merkle_trees
+    }
+
+    fn get_address_merkle_trees_mut(&mut self) -> &mut Vec<AddressMerkleTreeBundle> {
+        &mut self.address_merkle_trees
+    }
+
+    fn get_token_compressed_accounts(&self) -> &Vec<TokenDataWithContext> {
+        &self.token_compressed_accounts
+    }
+
+    fn get_payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    fn get_group_pda(&self) -> &Pubkey {
+        &self.group_pda
+    }
+
+    async fn create_proof_for_compressed_accounts(
+        &mut self,
+        compressed_accounts: Option<&[[u8; 32]]>,
+        _state_merkle_tree_pubkeys: Option<&[Pubkey]>,
+        new_addresses: Option<&[[u8; 32]]>,
+        address_merkle_tree_pubkeys: Option<Vec<Pubkey>>,
+        rpc: &mut R,
+    ) -> Result<ProofRpcResult, IndexerError> {
+        // Registers trees of bundles pushed since the last change.
+        self.sync()?;
+        self.create_proof(
+            compressed_accounts.unwrap_or_default(),
+            new_addresses.unwrap_or_default(),
+            address_merkle_tree_pubkeys.as_deref().unwrap_or_default(),
+            rpc,
+        )
+        .await
+    }
+
+    fn add_address_merkle_tree_accounts(
+        &mut self,
+        merkle_tree_keypair: &Keypair,
+        queue_keypair: &Keypair,
+        _owning_program_id: Option<Pubkey>,
+    ) -> AddressMerkleTreeAccounts {
+        let accounts = AddressMerkleTreeAccounts {
+            merkle_tree: merkle_tree_keypair.pubkey(),
+            queue: queue_keypair.pubkey(),
+        };
+        self.indexer
+            .add_tree(accounts.into())
+            .map_err(IndexerError::from)
+            .and_then(|_| self.sync())
+            .expect("failed to add address Merkle tree");
+        accounts
+    }
+
+    async fn get_compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<CompressedAccountWithMerkleContext>, IndexerError> {
+        let accounts = self.indexer.get_compressed_accounts_by_owner(owner)?;
+        Ok(split_token_accounts(accounts).0)
+    }
+
+    async fn get_compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<TokenDataWithContext>, IndexerError> {
+        let accounts = self.indexer.get_compressed_token_accounts_by_owner(owner)?;
+        Ok(split_token_accounts(accounts).1)
+    }
+}
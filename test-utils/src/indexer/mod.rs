@@ -1,9 +1,14 @@
+pub mod light_indexer;
+pub mod photon_server;
+pub mod snapshot;
 pub mod test_indexer;
 
 use num_bigint::BigUint;
 use solana_sdk::signature::Keypair;
 use std::fmt::Debug;
 
+pub use light_indexer::LightTestIndexer;
+pub use photon_server::PhotonServer;
 pub use test_indexer::AddressMerkleTreeAccounts;
 pub use test_indexer::AddressMerkleTreeBundle;
 pub use test_indexer::StateMerkleTreeAccounts;
@@ -12,6 +17,7 @@ pub use test_indexer::TestIndexer;
 pub use test_indexer::TokenDataWithContext;
 
 use crate::indexer::test_indexer::ProofRpcResult;
+use crate::rpc::errors::RpcError;
 use crate::rpc::rpc_connection::RpcConnection;
 use crate::spl::create_mint_helper;
 use account_compression::initialize_address_merkle_tree::{
@@ -21,7 +27,14 @@ use light_hash_set::HashSetError;
 use light_indexed_merkle_tree::array::IndexedElement;
 use light_system_program::sdk::compressed_account::CompressedAccountWithMerkleContext;
 use light_system_program::sdk::event::PublicTransactionEvent;
-use photon_api::apis::{default_api::GetCompressedAccountProofPostError, Error as PhotonApiError};
+use photon_api::apis::{
+    default_api::{
+        GetCompressedAccountProofPostError, GetCompressedAccountsByOwnerPostError,
+        GetCompressedTokenAccountsByOwnerPostError, GetMultipleCompressedAccountProofsPostError,
+        GetMultipleNewAddressProofsPostError, GetValidityProofPostError,
+    },
+    Error as PhotonApiError,
+};
 use thiserror::Error;
 
 pub trait Indexer<R: RpcConnection>: Sync + Send + Clone + Debug + 'static {
@@ -56,14 +69,22 @@ pub trait Indexer<R: RpcConnection>: Sync + Send + Clone + Debug + 'static {
         unimplemented!()
     }
 
+    /// Applies the event of a transaction, at the current slot of `rpc`.
+    /// Returns the created compressed accounts and token accounts.
     fn add_event_and_compressed_accounts(
         &mut self,
+        _rpc: &mut R,
         _event: &PublicTransactionEvent,
-    ) -> (
-        Vec<CompressedAccountWithMerkleContext>,
-        Vec<TokenDataWithContext>,
-    ) {
-        unimplemented!()
+    ) -> impl std::future::Future<
+        Output = Result<
+            (
+                Vec<CompressedAccountWithMerkleContext>,
+                Vec<TokenDataWithContext>,
+            ),
+            IndexerError,
+        >,
+    > + Send {
+        async { Err(IndexerError::MethodNotImplemented) }
     }
 
     fn get_state_merkle_trees(&self) -> &Vec<StateMerkleTreeBundle> {
@@ -94,16 +115,15 @@ pub trait Indexer<R: RpcConnection>: Sync + Send + Clone + Debug + 'static {
         unimplemented!()
     }
 
-    #[allow(async_fn_in_trait)]
-    async fn create_proof_for_compressed_accounts(
+    fn create_proof_for_compressed_accounts(
         &mut self,
         _compressed_accounts: Option<&[[u8; 32]]>,
         _state_merkle_tree_pubkeys: Option<&[Pubkey]>,
         _new_addresses: Option<&[[u8; 32]]>,
         _address_merkle_tree_pubkeys: Option<Vec<Pubkey>>,
         _rpc: &mut R,
-    ) -> ProofRpcResult {
-        unimplemented!()
+    ) -> impl std::future::Future<Output = Result<ProofRpcResult, IndexerError>> + Send {
+        async { Err(IndexerError::MethodNotImplemented) }
     }
 
     fn add_address_merkle_tree_accounts(
@@ -118,12 +138,18 @@ pub trait Indexer<R: RpcConnection>: Sync + Send + Clone + Debug + 'static {
     fn get_compressed_accounts_by_owner(
         &self,
         _owner: &Pubkey,
-    ) -> Vec<CompressedAccountWithMerkleContext> {
-        unimplemented!()
+    ) -> impl std::future::Future<
+        Output = Result<Vec<CompressedAccountWithMerkleContext>, IndexerError>,
+    > + Send {
+        async { Err(IndexerError::MethodNotImplemented) }
     }
 
-    fn get_compressed_token_accounts_by_owner(&self, _owner: &Pubkey) -> Vec<TokenDataWithContext> {
-        unimplemented!()
+    fn get_compressed_token_accounts_by_owner(
+        &self,
+        _owner: &Pubkey,
+    ) -> impl std::future::Future<Output = Result<Vec<TokenDataWithContext>, IndexerError>> + Send
+    {
+        async { Err(IndexerError::MethodNotImplemented) }
     }
 }
 
@@ -166,6 +192,25 @@ pub enum IndexerError {
     HashSetError(#[from] HashSetError),
     #[error(transparent)]
     PhotonApiError(PhotonApiErrorWrapper),
+    #[error("indexer returned error {code:?}: {message:?}")]
+    PhotonError {
+        code: Option<i32>,
+        message: Option<String>,
+    },
+    #[error("indexer response has no result")]
+    MissingResult,
+    #[error("invalid indexer response: {0}")]
+    InvalidResponse(String),
+    #[error("RPC connection error: {0}")]
+    RpcConnectionError(#[from] RpcError),
+    #[error("Merkle tree account {0} not found")]
+    MerkleTreeNotFound(Pubkey),
+    #[error("slot {0} is already finalized")]
+    SlotFinalized(u64),
+    #[error("snapshot error: {0}")]
+    SnapshotError(String),
+    #[error("method not implemented by this indexer")]
+    MethodNotImplemented,
     #[error("error: {0:?}")]
     Custom(String),
     #[error("unknown error")]
@@ -176,12 +221,43 @@ pub enum IndexerError {
 pub enum PhotonApiErrorWrapper {
     #[error(transparent)]
     GetCompressedAccountProofPostError(#[from] PhotonApiError<GetCompressedAccountProofPostError>),
+    #[error(transparent)]
+    GetCompressedAccountsByOwnerPostError(
+        #[from] PhotonApiError<GetCompressedAccountsByOwnerPostError>,
+    ),
+    #[error(transparent)]
+    GetCompressedTokenAccountsByOwnerPostError(
+        #[from] PhotonApiError<GetCompressedTokenAccountsByOwnerPostError>,
+    ),
+    #[error(transparent)]
+    GetMultipleCompressedAccountProofsPostError(
+        #[from] PhotonApiError<GetMultipleCompressedAccountProofsPostError>,
+    ),
+    #[error(transparent)]
+    GetMultipleNewAddressProofsPostError(
+        #[from] PhotonApiError<GetMultipleNewAddressProofsPostError>,
+    ),
+    #[error(transparent)]
+    GetValidityProofPostError(#[from] PhotonApiError<GetValidityProofPostError>),
 }
 
-impl From<PhotonApiError<GetCompressedAccountProofPostError>> for IndexerError {
-    fn from(err: PhotonApiError<GetCompressedAccountProofPostError>) -> Self {
-        IndexerError::PhotonApiError(PhotonApiErrorWrapper::GetCompressedAccountProofPostError(
-            err,
-        ))
-    }
+macro_rules! impl_from_photon_api_error {
+    ($($error:ident),*) => {
+        $(
+            impl From<PhotonApiError<$error>> for IndexerError {
+                fn from(err: PhotonApiError<$error>) -> Self {
+                    IndexerError::PhotonApiError(PhotonApiErrorWrapper::$error(err))
+                }
+            }
+        )*
+    };
 }
+
+impl_from_photon_api_error!(
+    GetCompressedAccountProofPostError,
+    GetCompressedAccountsByOwnerPostError,
+    GetCompressedTokenAccountsByOwnerPostError,
+    GetMultipleCompressedAccountProofsPostError,
+    GetMultipleNewAddressProofsPostError,
+    GetValidityProofPostError
+);
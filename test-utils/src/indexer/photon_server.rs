@@ -0,0 +1,1319 @@
+//! JSON-RPC server implementing the Photon API on top of [`TestIndexer`].
+//!
+//! The server answers the methods modeled in `photon-api`, so the TypeScript
+//! clients and `PhotonIndexer` can run against a local stand-in instead of
+//! Photon and Postgres. Requests are dispatched on their `method`, the path is
+//! ignored, so both the `/<method>` paths used by `photon-api` and a single
+//! endpoint used by the TypeScript clients work.
+//!
+//! The server shares the indexer of a test through an `Arc<Mutex<_>>`, so
+//! accounts indexed by the test helpers are served without copying the
+//! indexer. `TestIndexer` doesn't keep signatures and slots, so transactions
+//! have to be reported with [`PhotonServer::add_transaction`] to be returned by
+//! the signature methods.
+//!
+//! New addresses of `getMultipleNewAddressProofs` and `getValidityProof` are
+//! either base58 strings, which are proven in the first address tree of the
+//! indexer, or `{ "address", "tree" }` objects, which are proven in `tree`.
+//! Balances are returned as `u64` and proof elements as byte arrays, as by
+//! Photon, although `photon-api` models them as `i32` and binary strings.
+//!
+//! Differences to Photon:
+//!
+//! * `seq` and `slotCreated` of accounts which weren't created by a reported
+//!   transaction are 0,
+//! * `getTransactionWithCompressionInfo` doesn't return the transaction,
+//! * root indices are derived from sequence numbers, assuming trees with the
+//!   default number of roots.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use account_compression::utils::constants::{ADDRESS_MERKLE_TREE_ROOTS, STATE_MERKLE_TREE_ROOTS};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use light_compressed_token::{token_data::AccountState, TokenData};
+use light_prover_client::{
+    inclusion::merkle_inclusion_proof_inputs::InclusionMerkleProofInputs,
+    non_inclusion::merkle_non_inclusion_proof_inputs::get_non_inclusion_proof_inputs,
+    prover::ProofRequest,
+};
+use light_system_program::sdk::{
+    compressed_account::CompressedAccountWithMerkleContext, event::PublicTransactionEvent,
+};
+use light_utils::bigint::bigint_to_be_bytes_array;
+use log::debug;
+use num_bigint::{BigInt, BigUint};
+use num_traits::ops::bytes::FromBytes;
+use photon_api::models;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{bs58, pubkey::Pubkey, signature::Signature};
+use thiserror::Error;
+use tokio::{
+    sync::{oneshot, Mutex},
+    task::JoinHandle,
+};
+
+use crate::{
+    indexer::{AddressMerkleTreeBundle, TestIndexer},
+    rpc::rpc_connection::RpcConnection,
+};
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+
+#[derive(Error, Debug)]
+pub enum PhotonServerError {
+    #[error("HTTP server error: {0}")]
+    Hyper(#[from] hyper::Error),
+    #[error("server task failed: {0}")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+/// Error of a method, returned as the JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MethodError {
+    code: i32,
+    message: String,
+}
+
+impl MethodError {
+    fn invalid_params<T: ToString>(message: T) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+
+    fn internal<T: ToString>(message: T) -> Self {
+        Self {
+            code: INTERNAL_ERROR,
+            message: message.to_string(),
+        }
+    }
+}
+
+type MethodResult = Result<Value, MethodError>;
+
+#[derive(Deserialize)]
+struct JsonRpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SignatureParams {
+    signature: String,
+}
+
+/// New address, proven in the first address tree of the indexer unless its
+/// tree is given.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NewAddress {
+    Address(String),
+    WithTree { address: String, tree: String },
+}
+
+/// Params of `getValidityProof`, with new addresses which can name their
+/// tree.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidityProofParams {
+    #[serde(default)]
+    hashes: Vec<String>,
+    #[serde(default)]
+    new_addresses: Vec<NewAddress>,
+}
+
+/// Result of the balance methods. `photon-api` models balances as `i32`,
+/// while Photon returns them as `u64`, so they are serialized with these
+/// types.
+#[derive(Serialize)]
+struct BalanceResult<T> {
+    context: Box<models::Context>,
+    value: T,
+}
+
+#[derive(Serialize)]
+struct TokenAccountBalance {
+    amount: u64,
+}
+
+#[derive(Serialize)]
+struct TokenBalance {
+    balance: u64,
+    mint: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenBalanceList {
+    cursor: Option<String>,
+    token_balances: Vec<TokenBalance>,
+}
+
+/// Transaction reported with [`PhotonServer::add_transaction`].
+#[derive(Debug, Clone)]
+pub struct TransactionInfo {
+    pub signature: Signature,
+    pub slot: u64,
+    pub block_time: i64,
+    pub event: PublicTransactionEvent,
+}
+
+#[derive(Debug, Default)]
+struct ServerState {
+    slot: u64,
+    /// Reported transactions, the oldest first.
+    transactions: Vec<TransactionInfo>,
+    /// Slots and sequence numbers of accounts created by the reported
+    /// transactions.
+    created_accounts: HashMap<[u8; 32], (u64, u64)>,
+}
+
+/// Indexer and server state a request is answered from.
+struct RequestContext<'a, R: RpcConnection> {
+    indexer: &'a TestIndexer<R>,
+    slot: u64,
+    transactions: &'a [TransactionInfo],
+    created_accounts: &'a HashMap<[u8; 32], (u64, u64)>,
+}
+
+impl<'a, R: RpcConnection> RequestContext<'a, R> {
+    fn new(indexer: &'a TestIndexer<R>, state: &'a ServerState) -> Self {
+        Self {
+            indexer,
+            slot: state.slot,
+            transactions: &state.transactions,
+            created_accounts: &state.created_accounts,
+        }
+    }
+}
+
+/// Account of the indexer, live or nullified.
+#[derive(Clone, Copy)]
+struct IndexedAccount<'a> {
+    account: &'a CompressedAccountWithMerkleContext,
+    token_data: Option<&'a TokenData>,
+    nullified: bool,
+}
+
+/// Photon API server. Clones share the served state.
+#[derive(Debug)]
+pub struct PhotonServer<R: RpcConnection> {
+    indexer: Arc<Mutex<TestIndexer<R>>>,
+    state: Arc<RwLock<ServerState>>,
+}
+
+impl<R: RpcConnection> Clone for PhotonServer<R> {
+    fn clone(&self) -> Self {
+        Self {
+            indexer: self.indexer.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Handle of a running server, see [`PhotonServer::serve`].
+#[derive(Debug)]
+pub struct PhotonServerHandle {
+    url: String,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<Result<(), hyper::Error>>,
+}
+
+impl PhotonServerHandle {
+    /// URL of the server, which can be used as the `base_path` of the
+    /// `photon-api` configuration.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Stops the server after the requests in progress are answered.
+    pub async fn shutdown(self) -> Result<(), PhotonServerError> {
+        // The server is already stopped if the receiver is dropped.
+        let _ = self.shutdown.send(());
+        self.task.await??;
+        Ok(())
+    }
+}
+
+impl<R: RpcConnection> PhotonServer<R> {
+    /// Creates a server which serves `indexer`. Changes made through the
+    /// shared indexer, e.g. by test helpers, are served immediately.
+    pub fn new(indexer: Arc<Mutex<TestIndexer<R>>>) -> Self {
+        Self {
+            indexer,
+            state: Arc::new(RwLock::new(ServerState::default())),
+        }
+    }
+
+    /// Reports a transaction, which `event` has already been added to the
+    /// served indexer, and advances the slot of the server to `slot`.
+    pub async fn add_transaction(
+        &self,
+        signature: Signature,
+        slot: u64,
+        event: &PublicTransactionEvent,
+    ) {
+        let indexer = self.indexer.lock().await;
+        let mut state = self.state.write().unwrap();
+        let block_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        // Outputs of a tree were appended in one batch, at the current
+        // sequence number of the tree.
+        for (i, account) in event.output_compressed_accounts.iter().enumerate() {
+            let merkle_tree = event.pubkey_array[account.merkle_tree_index as usize];
+            let seq = indexer
+                .state_merkle_trees
+                .iter()
+                .find(|tree| tree.accounts.merkle_tree == merkle_tree)
+                .map(|tree| tree.merkle_tree.sequence_number as u64)
+                .unwrap_or_default();
+            state
+                .created_accounts
+                .insert(event.output_compressed_account_hashes[i], (slot, seq));
+        }
+        state.slot = state.slot.max(slot);
+        state.transactions.push(TransactionInfo {
+            signature,
+            slot,
+            block_time,
+            event: event.clone(),
+        });
+    }
+
+    /// Sets the slot returned by `getIndexerSlot` and in the contexts of
+    /// responses.
+    pub fn set_slot(&self, slot: u64) {
+        self.state.write().unwrap().slot = slot;
+    }
+
+    /// Starts serving on `addr` in the background. Has to be called within a
+    /// Tokio runtime.
+    pub fn serve(&self, addr: SocketAddr) -> Result<PhotonServerHandle, PhotonServerError> {
+        let server = self.clone();
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { server.handle_http_request(request).await }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        let (shutdown, receiver) = oneshot::channel::<()>();
+        let task = tokio::spawn(server.with_graceful_shutdown(async {
+            receiver.await.ok();
+        }));
+        Ok(PhotonServerHandle {
+            url,
+            shutdown,
+            task,
+        })
+    }
+
+    async fn handle_http_request(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, Infallible> {
+        if request.method() != Method::POST {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty())
+                .unwrap());
+        }
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(e.to_string()))
+                    .unwrap())
+            }
+        };
+        let response = self.handle_request(&body).await;
+        Ok(Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(response.to_string()))
+            .unwrap())
+    }
+
+    /// Answers a JSON-RPC request.
+    pub async fn handle_request(&self, body: &[u8]) -> Value {
+        let request = match serde_json::from_slice::<JsonRpcRequest>(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(
+                    Value::Null,
+                    MethodError {
+                        code: PARSE_ERROR,
+                        message: e.to_string(),
+                    },
+                )
+            }
+        };
+        debug!("Photon request {} {}", request.method, request.params);
+        let result = match request.method.as_str() {
+            "getValidityProof" => self.get_validity_proof(request.params).await,
+            method => {
+                let indexer = self.indexer.lock().await;
+                let state = self.state.read().unwrap();
+                RequestContext::new(&indexer, &state).call(method, request.params)
+            }
+        };
+        match result {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "result": result,
+            }),
+            Err(error) => error_response(request.id, error),
+        }
+    }
+
+    async fn get_validity_proof(&self, params: Value) -> MethodResult {
+        let params: ValidityProofParams = parse_params(params)?;
+        // The locks are released before proving.
+        let (request, proof_context, prover) = {
+            let indexer = self.indexer.lock().await;
+            let state = self.state.read().unwrap();
+            let (request, proof_context) =
+                RequestContext::new(&indexer, &state).validity_proof_request(&params)?;
+            (request, proof_context, indexer.prover.clone())
+        };
+        let result = prover
+            .prove(&request)
+            .await
+            .map_err(|e| MethodError::internal(format!("failed to prove: {:?}", e)))?;
+        let mut value = to_result(proof_context)?;
+        value["compressedProof"] = json!({
+            "a": result.proof.a.to_vec(),
+            "b": result.proof.b.to_vec(),
+            "c": result.proof.c.to_vec(),
+        });
+        Ok(value)
+    }
+}
+
+impl<R: RpcConnection> RequestContext<'_, R> {
+    fn call(&self, method: &str, params: Value) -> MethodResult {
+        match method {
+            "getCompressedAccount" => self.get_compressed_account(parse_params(params)?),
+            "getCompressedAccountProof" => self.get_compressed_account_proof(parse_params(params)?),
+            "getCompressedAccountsByOwner" => {
+                self.get_compressed_accounts_by_owner(parse_params(params)?)
+            }
+            "getCompressedBalance" => self.get_compressed_balance(parse_params(params)?),
+            "getCompressedBalanceByOwner" => {
+                self.get_compressed_balance_by_owner(parse_params(params)?)
+            }
+            "getCompressedTokenAccountBalance" => {
+                self.get_compressed_token_account_balance(parse_params(params)?)
+            }
+            "getCompressedTokenAccountsByOwner" => {
+                self.get_compressed_token_accounts_by_owner(parse_params(params)?)
+            }
+            "getCompressedTokenAccountsByDelegate" => {
+                self.get_compressed_token_accounts_by_delegate(parse_params(params)?)
+            }
+            "getCompressedTokenBalancesByOwner" => {
+                self.get_compressed_token_balances_by_owner(parse_params(params)?)
+            }
+            "getCompressionSignaturesForAccount" => {
+                self.get_compression_signatures_for_account(parse_params(params)?)
+            }
+            "getCompressionSignaturesForAddress" => {
+                self.get_compression_signatures_for_address(parse_params(params)?)
+            }
+            "getCompressionSignaturesForOwner" => {
+                self.get_compression_signatures_for_owner(parse_params(params)?, false)
+            }
+            "getCompressionSignaturesForTokenOwner" => {
+                self.get_compression_signatures_for_owner(parse_params(params)?, true)
+            }
+            "getIndexerHealth" => to_result("ok"),
+            "getIndexerSlot" => to_result(self.slot),
+            "getLatestCompressionSignatures" => {
+                self.get_latest_compression_signatures(parse_params(params)?)
+            }
+            "getLatestNonVotingSignatures" => {
+                self.get_latest_non_voting_signatures(parse_params(params)?)
+            }
+            "getMultipleCompressedAccountProofs" => {
+                self.get_multiple_compressed_account_proofs(parse_params(params)?)
+            }
+            "getMultipleCompressedAccounts" => {
+                self.get_multiple_compressed_accounts(parse_params(params)?)
+            }
+            "getMultipleNewAddressProofs" => {
+                self.get_multiple_new_address_proofs(parse_params(params)?)
+            }
+            "getTransactionWithCompressionInfo" => {
+                self.get_transaction_with_compression_info(parse_params(params)?)
+            }
+            method => Err(MethodError {
+                code: METHOD_NOT_FOUND,
+                message: format!("method {} not found", method),
+            }),
+        }
+    }
+
+    fn context(&self) -> Result<Box<models::Context>, MethodError> {
+        Ok(Box::new(models::Context {
+            slot: to_i32(self.slot)?,
+        }))
+    }
+
+    /// Live accounts, the newest first, followed by nullified accounts.
+    fn accounts(&self) -> impl Iterator<Item = IndexedAccount<'_>> {
+        let indexer = &self.indexer;
+        let live = indexer
+            .compressed_accounts
+            .iter()
+            .map(|account| IndexedAccount {
+                account,
+                token_data: None,
+                nullified: false,
+            })
+            .chain(
+                indexer
+                    .token_compressed_accounts
+                    .iter()
+                    .map(|account| IndexedAccount {
+                        account: &account.compressed_account,
+                        token_data: Some(&account.token_data),
+                        nullified: false,
+                    }),
+            );
+        let nullified = indexer
+            .nullified_compressed_accounts
+            .iter()
+            .map(|account| IndexedAccount {
+                account,
+                token_data: None,
+                nullified: true,
+            })
+            .chain(
+                indexer
+                    .token_nullified_compressed_accounts
+                    .iter()
+                    .map(|account| IndexedAccount {
+                        account: &account.compressed_account,
+                        token_data: Some(&account.token_data),
+                        nullified: true,
+                    }),
+            );
+        live.chain(nullified)
+    }
+
+    fn live_accounts(&self) -> impl Iterator<Item = IndexedAccount<'_>> {
+        self.accounts().filter(|account| !account.nullified)
+    }
+
+    fn accounts_by_hash(&self) -> HashMap<[u8; 32], IndexedAccount<'_>> {
+        self.accounts()
+            .filter_map(|account| Some((account.account.hash().ok()?, account)))
+            .collect()
+    }
+
+    /// Finds a live account by its address or hash.
+    fn find_account(
+        &self,
+        params: &models::GetCompressedAccountPostRequestParams,
+    ) -> Result<Option<IndexedAccount<'_>>, MethodError> {
+        match (
+            params.address.clone().flatten(),
+            params.hash.clone().flatten(),
+        ) {
+            (Some(address), None) => {
+                let address = decode_bytes(&address)?;
+                Ok(self
+                    .live_accounts()
+                    .find(|account| account.account.compressed_account.address == Some(address)))
+            }
+            (None, Some(hash)) => {
+                let hash = decode_bytes(&hash)?;
+                Ok(self
+                    .live_accounts()
+                    .find(|account| account.account.hash().ok() == Some(hash)))
+            }
+            _ => Err(MethodError::invalid_params(
+                "exactly one of address and hash is required",
+            )),
+        }
+    }
+
+    fn to_account(
+        &self,
+        account: &CompressedAccountWithMerkleContext,
+    ) -> Result<models::Account, MethodError> {
+        let hash = account.hash().map_err(MethodError::internal)?;
+        let (slot_created, seq) = self
+            .created_accounts
+            .get(&hash)
+            .copied()
+            .unwrap_or_default();
+        let compressed_account = &account.compressed_account;
+        Ok(models::Account {
+            address: compressed_account.address.map(encode),
+            data: compressed_account.data.as_ref().map(|data| {
+                Box::new(models::AccountData {
+                    data: STANDARD.encode(&data.data),
+                    data_hash: encode(data.data_hash),
+                    discriminator: u64::from_le_bytes(data.discriminator) as i64,
+                })
+            }),
+            hash: encode(hash),
+            lamports: to_i64(compressed_account.lamports)?,
+            leaf_index: account.merkle_context.leaf_index as i64,
+            owner: compressed_account.owner.to_string(),
+            seq: to_i64(seq)?,
+            slot_created: to_i64(slot_created)?,
+            tree: account.merkle_context.merkle_tree_pubkey.to_string(),
+        })
+    }
+
+    fn to_token_account(
+        &self,
+        account: &CompressedAccountWithMerkleContext,
+        token_data: &TokenData,
+    ) -> Result<models::TokenAcccount, MethodError> {
+        Ok(models::TokenAcccount {
+            account: Box::new(self.to_account(account)?),
+            token_data: Box::new(to_token_data(token_data)?),
+        })
+    }
+
+    fn to_account_with_optional_token_data(
+        &self,
+        account: IndexedAccount<'_>,
+    ) -> Result<models::AccountWithOptionalTokenData, MethodError> {
+        Ok(models::AccountWithOptionalTokenData {
+            account: Box::new(self.to_account(account.account)?),
+            optional_token_data: account
+                .token_data
+                .map(to_token_data)
+                .transpose()?
+                .map(Box::new),
+        })
+    }
+
+    fn get_compressed_account(
+        &self,
+        params: models::GetCompressedAccountPostRequestParams,
+    ) -> MethodResult {
+        let value = self
+            .find_account(&params)?
+            .map(|account| self.to_account(account.account))
+            .transpose()?
+            .map(Box::new);
+        to_result(models::GetCompressedAccountPost200ResponseResult {
+            context: self.context()?,
+            value,
+        })
+    }
+
+    fn get_compressed_account_proof(
+        &self,
+        params: models::GetCompressedAccountProofPostRequestParams,
+    ) -> MethodResult {
+        let proof = self.merkle_proof(&params.hash)?;
+        to_result(models::GetCompressedAccountProofPost200ResponseResult {
+            context: self.context()?,
+            value: Box::new(proof),
+        })
+    }
+
+    fn get_compressed_accounts_by_owner(
+        &self,
+        params: models::GetCompressedAccountsByOwnerPostRequestParams,
+    ) -> MethodResult {
+        let owner = decode_pubkey(&params.owner)?;
+        let accounts = self
+            .live_accounts()
+            .filter(|account| account.account.compressed_account.owner == owner)
+            .map(|account| self.to_account(account.account))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (items, cursor) = paginate(
+            accounts,
+            params.cursor.flatten(),
+            params.limit.flatten(),
+            |account| account.hash.clone(),
+        )?;
+        to_result(models::GetCompressedAccountsByOwnerPost200ResponseResult {
+            context: self.context()?,
+            value: Box::new(models::PaginatedAccountList { cursor, items }),
+        })
+    }
+
+    fn get_compressed_balance(
+        &self,
+        params: models::GetCompressedAccountPostRequestParams,
+    ) -> MethodResult {
+        let lamports = self
+            .find_account(&params)?
+            .map(|account| account.account.compressed_account.lamports)
+            .unwrap_or_default();
+        to_result(BalanceResult {
+            context: self.context()?,
+            value: lamports,
+        })
+    }
+
+    fn get_compressed_balance_by_owner(
+        &self,
+        params: models::GetCompressedBalanceByOwnerPostRequestParams,
+    ) -> MethodResult {
+        let owner = decode_pubkey(&params.owner)?;
+        let lamports = self
+            .live_accounts()
+            .filter(|account| account.account.compressed_account.owner == owner)
+            .map(|account| account.account.compressed_account.lamports)
+            .sum::<u64>();
+        to_result(BalanceResult {
+            context: self.context()?,
+            value: lamports,
+        })
+    }
+
+    fn get_compressed_token_account_balance(
+        &self,
+        params: models::GetCompressedAccountPostRequestParams,
+    ) -> MethodResult {
+        let amount = self
+            .find_account(&params)?
+            .and_then(|account| account.token_data)
+            .map(|token_data| token_data.amount)
+            .unwrap_or_default();
+        to_result(BalanceResult {
+            context: self.context()?,
+            value: TokenAccountBalance { amount },
+        })
+    }
+
+    /// Live token accounts matching `filter` and `mint`, paginated by hash.
+    fn token_accounts<F: Fn(&TokenData) -> bool>(
+        &self,
+        filter: F,
+        mint: Option<String>,
+        cursor: Option<String>,
+        limit: Option<i32>,
+    ) -> MethodResult {
+        let mint = mint.as_deref().map(decode_pubkey).transpose()?;
+        let token_accounts = self
+            .live_accounts()
+            .filter_map(|account| Some((account.account, account.token_data?)))
+            .filter(|(_, token_data)| filter(token_data))
+            .filter(|(_, token_data)| mint.map_or(true, |mint| token_data.mint == mint))
+            .map(|(account, token_data)| self.to_token_account(account, token_data))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (items, cursor) = paginate(token_accounts, cursor, limit, |token_account| {
+            token_account.account.hash.clone()
+        })?;
+        to_result(
+            models::GetCompressedTokenAccountsByDelegatePost200ResponseResult {
+                context: self.context()?,
+                value: Box::new(models::TokenAccountList { cursor, items }),
+            },
+        )
+    }
+
+    fn get_compressed_token_accounts_by_owner(
+        &self,
+        params: models::GetCompressedTokenAccountsByOwnerPostRequestParams,
+    ) -> MethodResult {
+        let owner = decode_pubkey(&params.owner)?;
+        self.token_accounts(
+            |token_data| token_data.owner == owner,
+            params.mint.flatten(),
+            params.cursor.flatten(),
+            params.limit.flatten(),
+        )
+    }
+
+    fn get_compressed_token_accounts_by_delegate(
+        &self,
+        params: models::GetCompressedTokenAccountsByDelegatePostRequestParams,
+    ) -> MethodResult {
+        let delegate = decode_pubkey(&params.delegate)?;
+        self.token_accounts(
+            |token_data| token_data.delegate == Some(delegate),
+            params.mint.flatten(),
+            params.cursor.flatten(),
+            params.limit.flatten(),
+        )
+    }
+
+    fn get_compressed_token_balances_by_owner(
+        &self,
+        params: models::GetCompressedTokenAccountsByOwnerPostRequestParams,
+    ) -> MethodResult {
+        let owner = decode_pubkey(&params.owner)?;
+        let mint = params
+            .mint
+            .flatten()
+            .as_deref()
+            .map(decode_pubkey)
+            .transpose()?;
+        // Balances in the order in which the mints are first found.
+        let mut balances: Vec<(Pubkey, u64)> = Vec::new();
+        for token_data in self
+            .live_accounts()
+            .filter_map(|account| account.token_data)
+            .filter(|token_data| token_data.owner == owner)
+            .filter(|token_data| mint.map_or(true, |mint| token_data.mint == mint))
+        {
+            match balances
+                .iter_mut()
+                .find(|(mint, _)| *mint == token_data.mint)
+            {
+                Some((_, balance)) => *balance += token_data.amount,
+                None => balances.push((token_data.mint, token_data.amount)),
+            }
+        }
+        let token_balances = balances
+            .into_iter()
+            .map(|(mint, balance)| TokenBalance {
+                balance,
+                mint: mint.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let (token_balances, cursor) = paginate(
+            token_balances,
+            params.cursor.flatten(),
+            params.limit.flatten(),
+            |balance| balance.mint.clone(),
+        )?;
+        to_result(BalanceResult {
+            context: self.context()?,
+            value: TokenBalanceList {
+                cursor,
+                token_balances,
+            },
+        })
+    }
+
+    /// Signatures of the reported transactions, the newest first, which
+    /// input or output accounts match `filter`.
+    fn signatures<F: Fn(&IndexedAccount<'_>) -> bool>(
+        &self,
+        filter: F,
+    ) -> Result<Vec<models::SignatureInfo>, MethodError> {
+        let accounts = self.accounts_by_hash();
+        self.transactions
+            .iter()
+            .rev()
+            .filter(|transaction| {
+                let event = &transaction.event;
+                event
+                    .input_compressed_account_hashes
+                    .iter()
+                    .chain(event.output_compressed_account_hashes.iter())
+                    .filter_map(|hash| accounts.get(hash))
+                    .any(&filter)
+            })
+            .map(to_signature_info)
+            .collect()
+    }
+
+    fn get_compression_signatures_for_account(
+        &self,
+        params: models::GetCompressedAccountProofPostRequestParams,
+    ) -> MethodResult {
+        let hash = decode_bytes(&params.hash)?;
+        let items = self
+            .transactions
+            .iter()
+            .rev()
+            .filter(|transaction| {
+                transaction
+                    .event
+                    .input_compressed_account_hashes
+                    .contains(&hash)
+                    || transaction
+                        .event
+                        .output_compressed_account_hashes
+                        .contains(&hash)
+            })
+            .map(to_signature_info)
+            .collect::<Result<Vec<_>, _>>()?;
+        to_result(
+            models::GetCompressionSignaturesForAccountPost200ResponseResult {
+                context: self.context()?,
+                value: Box::new(models::SignatureInfoList { items }),
+            },
+        )
+    }
+
+    fn paginated_signatures(
+        &self,
+        signatures: Vec<models::SignatureInfo>,
+        cursor: Option<String>,
+        limit: Option<i32>,
+    ) -> MethodResult {
+        let (items, cursor) = paginate(signatures, cursor, limit, |signature| {
+            signature.signature.clone()
+        })?;
+        to_result(
+            models::GetCompressionSignaturesForAddressPost200ResponseResult {
+                context: self.context()?,
+                value: Box::new(models::PaginatedSignatureInfoList {
+                    cursor: Some(cursor),
+                    items,
+                }),
+            },
+        )
+    }
+
+    fn get_compression_signatures_for_address(
+        &self,
+        params: models::GetCompressionSignaturesForAddressPostRequestParams,
+    ) -> MethodResult {
+        let address = decode_bytes(&params.address)?;
+        let signatures =
+            self.signatures(|account| account.account.compressed_account.address == Some(address))?;
+        self.paginated_signatures(signatures, params.cursor.flatten(), params.limit.flatten())
+    }
+
+    fn get_compression_signatures_for_owner(
+        &self,
+        params: models::GetCompressionSignaturesForOwnerPostRequestParams,
+        token_owner: bool,
+    ) -> MethodResult {
+        let owner = decode_pubkey(&params.owner)?;
+        let signatures = if token_owner {
+            self.signatures(|account| {
+                account
+                    .token_data
+                    .is_some_and(|token_data| token_data.owner == owner)
+            })?
+        } else {
+            self.signatures(|account| account.account.compressed_account.owner == owner)?
+        };
+        self.paginated_signatures(signatures, params.cursor.flatten(), params.limit.flatten())
+    }
+
+    fn get_latest_compression_signatures(
+        &self,
+        params: models::GetLatestCompressionSignaturesPostRequestParams,
+    ) -> MethodResult {
+        let signatures = self
+            .transactions
+            .iter()
+            .rev()
+            .map(to_signature_info)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.paginated_signatures(signatures, params.cursor.flatten(), params.limit.flatten())
+    }
+
+    fn get_latest_non_voting_signatures(
+        &self,
+        params: models::GetLatestCompressionSignaturesPostRequestParams,
+    ) -> MethodResult {
+        let signatures = self
+            .transactions
+            .iter()
+            .rev()
+            .map(to_signature_info)
+            .collect::<Result<Vec<_>, _>>()?;
+        let (items, _) = paginate(
+            signatures,
+            params.cursor.flatten(),
+            params.limit.flatten(),
+            |signature| signature.signature.clone(),
+        )?;
+        to_result(
+            models::GetCompressionSignaturesForAccountPost200ResponseResult {
+                context: self.context()?,
+                value: Box::new(models::SignatureInfoList { items }),
+            },
+        )
+    }
+
+    /// Proof of the leaf `hash` in one of the state trees, including the
+    /// nodes of the canopy.
+    fn merkle_proof(&self, hash: &str) -> Result<models::MerkleProofWithContext, MethodError> {
+        let leaf = decode_bytes(hash)?;
+        let (tree, leaf_index) = self
+            .indexer
+            .state_merkle_trees
+            .iter()
+            .find_map(|tree| Some((tree, tree.merkle_tree.get_leaf_index(&leaf)?)))
+            .ok_or_else(|| MethodError::invalid_params(format!("account {} not found", hash)))?;
+        let proof = tree
+            .merkle_tree
+            .get_proof_of_leaf(leaf_index, true)
+            .map_err(|e| MethodError::internal(format!("{:?}", e)))?;
+        Ok(models::MerkleProofWithContext {
+            hash: hash.to_string(),
+            leaf_index: leaf_index as u32,
+            merkle_tree: tree.accounts.merkle_tree.to_string(),
+            proof: proof.iter().map(encode).collect(),
+            root: encode(tree.merkle_tree.root()),
+            root_seq: tree.merkle_tree.sequence_number as u64,
+        })
+    }
+
+    fn get_multiple_compressed_account_proofs(&self, hashes: Vec<String>) -> MethodResult {
+        let value = hashes
+            .iter()
+            .map(|hash| self.merkle_proof(hash))
+            .collect::<Result<Vec<_>, _>>()?;
+        to_result(
+            models::GetMultipleCompressedAccountProofsPost200ResponseResult {
+                context: self.context()?,
+                value,
+            },
+        )
+    }
+
+    fn get_multiple_compressed_accounts(
+        &self,
+        params: models::GetMultipleCompressedAccountsPostRequestParams,
+    ) -> MethodResult {
+        let requests = match (params.addresses.flatten(), params.hashes.flatten()) {
+            (Some(addresses), None) => addresses
+                .into_iter()
+                .map(|address| models::GetCompressedAccountPostRequestParams {
+                    address: Some(Some(address)),
+                    hash: None,
+                })
+                .collect::<Vec<_>>(),
+            (None, Some(hashes)) => hashes
+                .into_iter()
+                .map(|hash| models::GetCompressedAccountPostRequestParams {
+                    address: None,
+                    hash: Some(Some(hash)),
+                })
+                .collect(),
+            _ => {
+                return Err(MethodError::invalid_params(
+                    "exactly one of addresses and hashes is required",
+                ))
+            }
+        };
+        let items = requests
+            .iter()
+            .map(|request| {
+                let account = self.find_account(request)?.ok_or_else(|| {
+                    MethodError::invalid_params(format!("account {:?} not found", request))
+                })?;
+                self.to_account(account.account)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        to_result(models::GetMultipleCompressedAccountsPost200ResponseResult {
+            context: self.context()?,
+            value: Box::new(models::AccountList { items }),
+        })
+    }
+
+    /// Address tree with the pubkey `tree`, the first address tree of the
+    /// indexer if `tree` isn't given.
+    fn address_tree(&self, tree: Option<&str>) -> Result<&AddressMerkleTreeBundle, MethodError> {
+        match tree {
+            Some(tree) => {
+                let pubkey = decode_pubkey(tree)?;
+                self.indexer
+                    .address_merkle_trees
+                    .iter()
+                    .find(|bundle| bundle.accounts.merkle_tree == pubkey)
+                    .ok_or_else(|| {
+                        MethodError::invalid_params(format!(
+                            "address Merkle tree {} not found",
+                            tree
+                        ))
+                    })
+            }
+            None => self
+                .indexer
+                .address_merkle_trees
+                .first()
+                .ok_or_else(|| MethodError::internal("no address Merkle tree")),
+        }
+    }
+
+    /// Proof of `address` not existing in its address tree, and the tree.
+    fn new_address_proof(
+        &self,
+        address: &NewAddress,
+    ) -> Result<
+        (
+            models::MerkleContextWithNewAddressProof,
+            &AddressMerkleTreeBundle,
+        ),
+        MethodError,
+    > {
+        let (address, tree) = match address {
+            NewAddress::Address(address) => (address, self.address_tree(None)?),
+            NewAddress::WithTree { address, tree } => (address, self.address_tree(Some(tree))?),
+        };
+        let address_value = BigUint::from_bytes_be(&decode_bytes(address)?);
+        let (low_element, low_element_next_value) = tree
+            .indexed_array
+            .find_low_element_for_nonexistent(&address_value)
+            .map_err(|_| {
+                MethodError::invalid_params(format!("address {} already exists", address))
+            })?;
+        let proof = tree
+            .merkle_tree
+            .get_proof_of_leaf(low_element.index, true)
+            .map_err(|e| MethodError::internal(format!("{:?}", e)))?;
+        let proof = models::MerkleContextWithNewAddressProof {
+            address: address.clone(),
+            higher_range_address: encode_biguint(&low_element_next_value)?,
+            low_element_leaf_index: low_element.index as i32,
+            lower_range_address: encode_biguint(&low_element.value)?,
+            merkle_tree: tree.accounts.merkle_tree.to_string(),
+            next_index: low_element.next_index as i32,
+            proof: proof.iter().map(encode).collect(),
+            root: encode(tree.merkle_tree.root()),
+            root_seq: tree.merkle_tree.merkle_tree.sequence_number as u64,
+        };
+        Ok((proof, tree))
+    }
+
+    fn get_multiple_new_address_proofs(&self, addresses: Vec<NewAddress>) -> MethodResult {
+        let value = addresses
+            .iter()
+            .map(|address| Ok(self.new_address_proof(address)?.0))
+            .collect::<Result<Vec<_>, MethodError>>()?;
+        to_result(models::GetMultipleNewAddressProofsPost200ResponseResult {
+            context: self.context()?,
+            value,
+        })
+    }
+
+    fn get_transaction_with_compression_info(&self, params: SignatureParams) -> MethodResult {
+        let signature = Signature::from_str(&params.signature)
+            .map_err(|e| MethodError::invalid_params(format!("invalid signature: {}", e)))?;
+        let transaction = self
+            .transactions
+            .iter()
+            .find(|transaction| transaction.signature == signature)
+            .ok_or_else(|| {
+                MethodError::invalid_params(format!("transaction {} not found", signature))
+            })?;
+        let accounts = self.accounts_by_hash();
+        let to_accounts = |hashes: &[[u8; 32]]| {
+            hashes
+                .iter()
+                .map(|hash| {
+                    let account = accounts.get(hash).ok_or_else(|| {
+                        MethodError::internal(format!("account {} not found", encode(hash)))
+                    })?;
+                    self.to_account_with_optional_token_data(*account)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        to_result(
+            models::GetTransactionWithCompressionInfoPost200ResponseResult {
+                compression_info: Some(Box::new(
+                    models::GetTransactionWithCompressionInfoPost200ResponseResultCompressionInfo {
+                        closed_accounts: to_accounts(
+                            &transaction.event.input_compressed_account_hashes,
+                        )?,
+                        opened_accounts: to_accounts(
+                            &transaction.event.output_compressed_account_hashes,
+                        )?,
+                    },
+                )),
+                transaction: None,
+            },
+        )
+    }
+
+    /// Returns the proof request for `params` and the result without the
+    /// proof. Entries of the new addresses follow the entries of the
+    /// accounts, with the index of the low element as the leaf index.
+    fn validity_proof_request(
+        &self,
+        params: &ValidityProofParams,
+    ) -> Result<(ProofRequest, models::GetValidityProofPost200ResponseResult), MethodError> {
+        if ![0, 1, 2, 3, 4, 8].contains(&params.hashes.len()) {
+            return Err(MethodError::invalid_params(
+                "number of hashes has to be 1, 2, 3, 4 or 8",
+            ));
+        }
+        if ![0, 1, 2].contains(&params.new_addresses.len()) {
+            return Err(MethodError::invalid_params(
+                "number of new addresses has to be 1 or 2",
+            ));
+        }
+        if params.hashes.is_empty() && params.new_addresses.is_empty() {
+            return Err(MethodError::invalid_params(
+                "hashes or new addresses are required",
+            ));
+        }
+        let mut result = models::GetValidityProofPost200ResponseResult::default();
+        let mut inclusion = Vec::with_capacity(params.hashes.len());
+        for hash in params.hashes.iter() {
+            let proof = self.merkle_proof(hash)?;
+            let leaf = decode_bytes(hash)?;
+            let root = decode_bytes(&proof.root)?;
+            inclusion.push(InclusionMerkleProofInputs {
+                root: BigInt::from_be_bytes(root.as_slice()),
+                leaf: BigInt::from_be_bytes(leaf.as_slice()),
+                path_index: BigInt::from(proof.leaf_index),
+                path_elements: proof
+                    .proof
+                    .iter()
+                    .map(|node| Ok(BigInt::from_be_bytes(decode_bytes(node)?.as_slice())))
+                    .collect::<Result<Vec<_>, MethodError>>()?,
+            });
+            result.leaf_indices.push(proof.leaf_index as i32);
+            result.leaves.push(proof.hash);
+            result.merkle_trees.push(proof.merkle_tree);
+            result
+                .root_indices
+                .push((proof.root_seq % STATE_MERKLE_TREE_ROOTS) as i32);
+            result.roots.push(proof.root);
+        }
+        let mut non_inclusion = Vec::with_capacity(params.new_addresses.len());
+        for address in params.new_addresses.iter() {
+            let (proof, tree) = self.new_address_proof(address)?;
+            non_inclusion.push(get_non_inclusion_proof_inputs(
+                &decode_bytes(&proof.address)?,
+                &tree.merkle_tree,
+                &tree.indexed_array,
+            ));
+            result.leaf_indices.push(proof.low_element_leaf_index);
+            result.leaves.push(proof.address);
+            result.merkle_trees.push(proof.merkle_tree);
+            result
+                .root_indices
+                .push((proof.root_seq % ADDRESS_MERKLE_TREE_ROOTS) as i32);
+            result.roots.push(proof.root);
+        }
+        Ok((ProofRequest::new(inclusion, non_inclusion), result))
+    }
+}
+
+fn error_response(id: Value, error: MethodError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": error.code,
+            "message": error.message,
+        },
+    })
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, MethodError> {
+    serde_json::from_value(params).map_err(MethodError::invalid_params)
+}
+
+fn to_result<T: Serialize>(result: T) -> MethodResult {
+    serde_json::to_value(result).map_err(MethodError::internal)
+}
+
+/// Returns the page of `items` following the item with the key `cursor` and
+/// the cursor of the next page, if there are more items.
+fn paginate<T, F: Fn(&T) -> String>(
+    mut items: Vec<T>,
+    cursor: Option<String>,
+    limit: Option<i32>,
+    key: F,
+) -> Result<(Vec<T>, Option<String>), MethodError> {
+    let start = match cursor {
+        Some(cursor) => {
+            items
+                .iter()
+                .position(|item| key(item) == cursor)
+                .ok_or_else(|| MethodError::invalid_params(format!("invalid cursor {}", cursor)))?
+                + 1
+        }
+        None => 0,
+    };
+    let limit = match limit {
+        Some(limit) if limit <= 0 => {
+            return Err(MethodError::invalid_params("limit has to be positive"))
+        }
+        Some(limit) => limit as usize,
+        None => usize::MAX,
+    };
+    let mut page = items.split_off(start);
+    let cursor = if page.len() > limit {
+        page.truncate(limit);
+        page.last().map(&key)
+    } else {
+        None
+    };
+    Ok((page, cursor))
+}
+
+fn to_signature_info(transaction: &TransactionInfo) -> Result<models::SignatureInfo, MethodError> {
+    Ok(models::SignatureInfo {
+        block_time: to_i32(transaction.block_time as u64)?,
+        signature: transaction.signature.to_string(),
+        slot: to_i32(transaction.slot)?,
+    })
+}
+
+fn to_token_data(token_data: &TokenData) -> Result<models::TokenData, MethodError> {
+    Ok(models::TokenData {
+        amount: to_i64(token_data.amount)?,
+        delegate: token_data.delegate.map(|delegate| delegate.to_string()),
+        mint: token_data.mint.to_string(),
+        owner: token_data.owner.to_string(),
+        state: match token_data.state {
+            AccountState::Initialized => models::AccountState::Initialized,
+            AccountState::Frozen => models::AccountState::Frozen,
+        },
+    })
+}
+
+fn to_i32(value: u64) -> Result<i32, MethodError> {
+    i32::try_from(value)
+        .map_err(|_| MethodError::internal(format!("{} exceeds the range of the API", value)))
+}
+
+fn to_i64(value: u64) -> Result<i64, MethodError> {
+    i64::try_from(value)
+        .map_err(|_| MethodError::internal(format!("{} exceeds the range of the API", value)))
+}
+
+fn encode<T: AsRef<[u8]>>(bytes: T) -> String {
+    bs58::encode(bytes).into_string()
+}
+
+fn encode_biguint(value: &BigUint) -> Result<String, MethodError> {
+    bigint_to_be_bytes_array::<32>(value)
+        .map(encode)
+        .map_err(|e| MethodError::internal(e.to_string()))
+}
+
+fn decode_bytes(value: &str) -> Result<[u8; 32], MethodError> {
+    bs58::decode(value)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| MethodError::invalid_params(format!("invalid base58 value {}", value)))
+}
+
+fn decode_pubkey(value: &str) -> Result<Pubkey, MethodError> {
+    Pubkey::from_str(value)
+        .map_err(|e| MethodError::invalid_params(format!("invalid pubkey {}: {}", value, e)))
+}
@@ -0,0 +1,332 @@
+//! Snapshots of the [`TestIndexer`] state together with the accounts of its
+//! trees and queues. Restoring a snapshot into a fresh test environment skips
+//! the transactions which filled the trees.
+
+use std::{fs, path::Path};
+
+use anchor_lang::{prelude::borsh, AnchorDeserialize, AnchorSerialize};
+use light_compressed_token::TokenData;
+use light_hasher::Poseidon;
+use light_indexed_merkle_tree::{
+    array::{IndexedArray, IndexedElement},
+    reference::IndexedMerkleTree,
+};
+use light_merkle_tree_reference::MerkleTree;
+use light_prover_client::prover::ProverBackend;
+use light_system_program::sdk::{
+    compressed_account::CompressedAccountWithMerkleContext, event::PublicTransactionEvent,
+};
+use light_utils::bigint::bigint_to_be_bytes_array;
+use num_bigint::BigUint;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    pubkey::Pubkey,
+    signature::Keypair,
+};
+
+use crate::indexer::{
+    AddressMerkleTreeAccounts, AddressMerkleTreeBundle, IndexerError, StateMerkleTreeAccounts,
+    StateMerkleTreeBundle, TestIndexer, TokenDataWithContext,
+};
+use crate::rpc::rpc_connection::RpcConnection;
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct IndexerSnapshot {
+    pub group_pda: Pubkey,
+    pub state_merkle_trees: Vec<StateMerkleTreeSnapshot>,
+    pub address_merkle_trees: Vec<AddressMerkleTreeSnapshot>,
+    pub compressed_accounts: Vec<CompressedAccountWithMerkleContext>,
+    pub nullified_compressed_accounts: Vec<CompressedAccountWithMerkleContext>,
+    pub token_compressed_accounts: Vec<(TokenData, CompressedAccountWithMerkleContext)>,
+    pub token_nullified_compressed_accounts: Vec<(TokenData, CompressedAccountWithMerkleContext)>,
+    pub events: Vec<PublicTransactionEvent>,
+    pub closed_addresses: Vec<[u8; 32]>,
+    /// Tree, queue and CPI context accounts, followed by the additional
+    /// accounts the snapshot was written with.
+    pub accounts: Vec<AccountSnapshot>,
+}
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct AccountSnapshot {
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct MerkleTreeSnapshot {
+    pub height: u64,
+    pub canopy_depth: u64,
+    pub layers: Vec<Vec<[u8; 32]>>,
+    pub roots: Vec<[u8; 32]>,
+    pub rightmost_index: u64,
+    pub sequence_number: u64,
+}
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct StateMerkleTreeSnapshot {
+    pub merkle_tree_pubkey: Pubkey,
+    pub nullifier_queue_pubkey: Pubkey,
+    pub cpi_context_pubkey: Pubkey,
+    pub rollover_fee: i64,
+    pub merkle_tree: MerkleTreeSnapshot,
+}
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct AddressMerkleTreeSnapshot {
+    pub merkle_tree_pubkey: Pubkey,
+    pub queue_pubkey: Pubkey,
+    pub rollover_fee: i64,
+    pub merkle_tree: MerkleTreeSnapshot,
+    pub elements: Vec<IndexedElementSnapshot>,
+    pub current_node_index: u64,
+    pub highest_element_index: u64,
+}
+
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct IndexedElementSnapshot {
+    pub index: u64,
+    pub value: [u8; 32],
+    pub next_index: u64,
+}
+
+impl MerkleTreeSnapshot {
+    fn new(merkle_tree: &MerkleTree<Poseidon>) -> Self {
+        Self {
+            height: merkle_tree.height as u64,
+            canopy_depth: merkle_tree.canopy_depth as u64,
+            layers: merkle_tree.layers.clone(),
+            roots: merkle_tree.roots.clone(),
+            rightmost_index: merkle_tree.rightmost_index as u64,
+            sequence_number: merkle_tree.sequence_number as u64,
+        }
+    }
+
+    fn restore(self) -> MerkleTree<Poseidon> {
+        let mut merkle_tree =
+            MerkleTree::<Poseidon>::new(self.height as usize, self.canopy_depth as usize);
+        merkle_tree.layers = self.layers;
+        merkle_tree.roots = self.roots;
+        merkle_tree.rightmost_index = self.rightmost_index as usize;
+        merkle_tree.sequence_number = self.sequence_number as usize;
+        merkle_tree
+    }
+}
+
+impl StateMerkleTreeSnapshot {
+    fn new(bundle: &StateMerkleTreeBundle) -> Self {
+        Self {
+            merkle_tree_pubkey: bundle.accounts.merkle_tree,
+            nullifier_queue_pubkey: bundle.accounts.nullifier_queue,
+            cpi_context_pubkey: bundle.accounts.cpi_context,
+            rollover_fee: bundle.rollover_fee,
+            merkle_tree: MerkleTreeSnapshot::new(&bundle.merkle_tree),
+        }
+    }
+
+    fn restore(self) -> StateMerkleTreeBundle {
+        StateMerkleTreeBundle {
+            rollover_fee: self.rollover_fee,
+            merkle_tree: Box::new(self.merkle_tree.restore()),
+            accounts: StateMerkleTreeAccounts {
+                merkle_tree: self.merkle_tree_pubkey,
+                nullifier_queue: self.nullifier_queue_pubkey,
+                cpi_context: self.cpi_context_pubkey,
+            },
+        }
+    }
+}
+
+impl AddressMerkleTreeSnapshot {
+    fn new(bundle: &AddressMerkleTreeBundle) -> Result<Self, IndexerError> {
+        let elements = bundle
+            .indexed_array
+            .elements
+            .iter()
+            .map(|element| {
+                Ok(IndexedElementSnapshot {
+                    index: element.index as u64,
+                    value: bigint_to_be_bytes_array::<32>(&element.value)
+                        .map_err(|e| IndexerError::SnapshotError(e.to_string()))?,
+                    next_index: element.next_index as u64,
+                })
+            })
+            .collect::<Result<Vec<_>, IndexerError>>()?;
+        Ok(Self {
+            merkle_tree_pubkey: bundle.accounts.merkle_tree,
+            queue_pubkey: bundle.accounts.queue,
+            rollover_fee: bundle.rollover_fee,
+            merkle_tree: MerkleTreeSnapshot::new(&bundle.merkle_tree.merkle_tree),
+            elements,
+            current_node_index: bundle.indexed_array.current_node_index as u64,
+            highest_element_index: bundle.indexed_array.highest_element_index as u64,
+        })
+    }
+
+    fn restore(self) -> AddressMerkleTreeBundle {
+        let merkle_tree = self.merkle_tree.restore();
+        let mut indexed_merkle_tree =
+            IndexedMerkleTree::<Poseidon, usize>::new(merkle_tree.height, merkle_tree.canopy_depth)
+                .unwrap();
+        indexed_merkle_tree.merkle_tree = merkle_tree;
+
+        let mut indexed_array = Box::<IndexedArray<Poseidon, usize>>::default();
+        indexed_array.elements = self
+            .elements
+            .into_iter()
+            .map(|element| IndexedElement {
+                index: element.index as usize,
+                value: BigUint::from_bytes_be(&element.value),
+                next_index: element.next_index as usize,
+            })
+            .collect();
+        indexed_array.current_node_index = self.current_node_index as usize;
+        indexed_array.highest_element_index = self.highest_element_index as usize;
+
+        AddressMerkleTreeBundle {
+            rollover_fee: self.rollover_fee,
+            merkle_tree: Box::new(indexed_merkle_tree),
+            indexed_array,
+            accounts: AddressMerkleTreeAccounts {
+                merkle_tree: self.merkle_tree_pubkey,
+                queue: self.queue_pubkey,
+            },
+        }
+    }
+}
+
+impl<R: RpcConnection> TestIndexer<R> {
+    /// Writes the indexer state and the accounts of its trees, queues and CPI
+    /// contexts to `path`. `additional_accounts`, e.g. the SOL pool or token
+    /// pools, are written as well.
+    pub async fn write_snapshot<P: AsRef<Path>>(
+        &self,
+        rpc: &mut R,
+        additional_accounts: &[Pubkey],
+        path: P,
+    ) -> Result<(), IndexerError> {
+        let mut pubkeys = Vec::new();
+        for bundle in self.state_merkle_trees.iter() {
+            pubkeys.push(bundle.accounts.merkle_tree);
+            pubkeys.push(bundle.accounts.nullifier_queue);
+            pubkeys.push(bundle.accounts.cpi_context);
+        }
+        for bundle in self.address_merkle_trees.iter() {
+            pubkeys.push(bundle.accounts.merkle_tree);
+            pubkeys.push(bundle.accounts.queue);
+        }
+        pubkeys.extend_from_slice(additional_accounts);
+
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for pubkey in pubkeys {
+            let account = rpc.get_account(pubkey).await?.ok_or_else(|| {
+                IndexerError::SnapshotError(format!("account {} not found", pubkey))
+            })?;
+            accounts.push(AccountSnapshot {
+                pubkey,
+                lamports: account.lamports,
+                data: account.data,
+                owner: account.owner,
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+            });
+        }
+
+        let snapshot = IndexerSnapshot {
+            group_pda: self.group_pda,
+            state_merkle_trees: self
+                .state_merkle_trees
+                .iter()
+                .map(StateMerkleTreeSnapshot::new)
+                .collect(),
+            address_merkle_trees: self
+                .address_merkle_trees
+                .iter()
+                .map(AddressMerkleTreeSnapshot::new)
+                .collect::<Result<Vec<_>, _>>()?,
+            compressed_accounts: self.compressed_accounts.clone(),
+            nullified_compressed_accounts: self.nullified_compressed_accounts.clone(),
+            token_compressed_accounts: self
+                .token_compressed_accounts
+                .iter()
+                .map(|x| (x.token_data, x.compressed_account.clone()))
+                .collect(),
+            token_nullified_compressed_accounts: self
+                .token_nullified_compressed_accounts
+                .iter()
+                .map(|x| (x.token_data, x.compressed_account.clone()))
+                .collect(),
+            events: self.events.clone(),
+            closed_addresses: self.closed_addresses.clone(),
+            accounts,
+        };
+        let data = snapshot
+            .try_to_vec()
+            .map_err(|e| IndexerError::SnapshotError(e.to_string()))?;
+        fs::write(path, data).map_err(|e| IndexerError::SnapshotError(e.to_string()))
+    }
+
+    /// Restores a snapshot written by [`TestIndexer::write_snapshot`]. Its
+    /// accounts are set with `rpc`, the returned indexer generates proofs with
+    /// `prover` and has no changes to roll back.
+    pub fn restore_snapshot<P: AsRef<Path>, B: Into<ProverBackend>>(
+        rpc: &mut R,
+        path: P,
+        payer: Keypair,
+        prover: B,
+    ) -> Result<Self, IndexerError> {
+        let data = fs::read(path).map_err(|e| IndexerError::SnapshotError(e.to_string()))?;
+        let snapshot = IndexerSnapshot::deserialize(&mut data.as_slice())
+            .map_err(|e| IndexerError::SnapshotError(e.to_string()))?;
+
+        for account in snapshot.accounts {
+            rpc.set_account(
+                &account.pubkey,
+                &AccountSharedData::from(Account {
+                    lamports: account.lamports,
+                    data: account.data,
+                    owner: account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                }),
+            );
+        }
+
+        let mut indexer =
+            Self::new_with_prover(Vec::new(), Vec::new(), payer, snapshot.group_pda, prover);
+        indexer.state_merkle_trees = snapshot
+            .state_merkle_trees
+            .into_iter()
+            .map(StateMerkleTreeSnapshot::restore)
+            .collect();
+        indexer.address_merkle_trees = snapshot
+            .address_merkle_trees
+            .into_iter()
+            .map(AddressMerkleTreeSnapshot::restore)
+            .collect();
+        indexer.compressed_accounts = snapshot.compressed_accounts;
+        indexer.nullified_compressed_accounts = snapshot.nullified_compressed_accounts;
+        indexer.token_compressed_accounts = snapshot
+            .token_compressed_accounts
+            .into_iter()
+            .map(|(token_data, compressed_account)| TokenDataWithContext {
+                token_data,
+                compressed_account,
+            })
+            .collect();
+        indexer.token_nullified_compressed_accounts = snapshot
+            .token_nullified_compressed_accounts
+            .into_iter()
+            .map(|(token_data, compressed_account)| TokenDataWithContext {
+                token_data,
+                compressed_account,
+            })
+            .collect();
+        indexer.events = snapshot.events;
+        indexer.closed_addresses = snapshot.closed_addresses;
+        Ok(indexer)
+    }
+}
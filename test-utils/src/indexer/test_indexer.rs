@@ -1,8 +1,9 @@
 use light_registry::sdk::get_registered_program_pda;
-use log::{debug, info, warn};
+use log::{debug, info};
 use num_bigint::BigUint;
 use solana_sdk::bs58;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
 
 use account_compression::{
@@ -23,24 +24,20 @@ use {
     },
     anchor_lang::AnchorDeserialize,
     light_hasher::Poseidon,
-    light_indexed_merkle_tree::{array::IndexedArray, reference::IndexedMerkleTree},
+    light_indexed_merkle_tree::{
+        array::{IndexedArray, IndexedElement},
+        reference::IndexedMerkleTree,
+    },
     light_merkle_tree_reference::MerkleTree,
     light_prover_client::{
-        gnark::{
-            combined_json_formatter::CombinedJsonStruct,
-            constants::{PROVE_PATH, SERVER_ADDRESS},
-            helpers::{spawn_prover, ProofType},
-            inclusion_json_formatter::BatchInclusionJsonStruct,
-            non_inclusion_json_formatter::BatchNonInclusionJsonStruct,
-            proof_helpers::{compress_proof, deserialize_gnark_proof_json, proof_from_json_struct},
-        },
-        inclusion::merkle_inclusion_proof_inputs::{
-            InclusionMerkleProofInputs, InclusionProofInputs,
-        },
+        gnark::helpers::ProofType,
+        inclusion::merkle_inclusion_proof_inputs::InclusionMerkleProofInputs,
         non_inclusion::merkle_non_inclusion_proof_inputs::{
-            get_non_inclusion_proof_inputs, NonInclusionProofInputs,
+            get_non_inclusion_proof_inputs, NonInclusionMerkleProofInputs,
         },
+        prover::{GnarkProver, ProofRequest, ProofService, ProverBackend},
     },
+    light_sdk::event::DecodeEvent,
     light_system_program::{
         invoke::processor::CompressedProof,
         sdk::{
@@ -50,13 +47,11 @@ use {
     },
     num_bigint::BigInt,
     num_traits::ops::bytes::FromBytes,
-    reqwest::Client,
     solana_sdk::{
-        instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signature::Keypair,
-        signer::Signer,
+        commitment_config::CommitmentConfig, instruction::Instruction, program_pack::Pack,
+        pubkey::Pubkey, signature::Keypair, signer::Signer,
     },
     spl_token::instruction::initialize_mint,
-    std::time::Duration,
 };
 
 use crate::e2e_test_env::KeypairActionConfig;
@@ -115,10 +110,56 @@ pub struct TestIndexer<R: RpcConnection> {
     pub token_compressed_accounts: Vec<TokenDataWithContext>,
     pub token_nullified_compressed_accounts: Vec<TokenDataWithContext>,
     pub events: Vec<PublicTransactionEvent>,
+    /// Addresses of closed compressed accounts.
+    pub closed_addresses: Vec<[u8; 32]>,
     pub proof_types: Vec<ProofType>,
+    /// Proof service shared by clones of the indexer.
+    pub prover: Arc<ProofService<ProverBackend>>,
+    /// Slot read from the rpc when the latest change was applied.
+    pub slot: u64,
+    /// Changes up to this slot are final and can't be rolled back.
+    pub finalized_slot: u64,
+    /// Commitment at which slots are finalized by `finalize_with_rpc`.
+    pub finalization_commitment: CommitmentConfig,
+    /// Changes which aren't final yet, with the slots they were applied at.
+    journal: Vec<(u64, IndexerChange)>,
     phantom: PhantomData<R>,
 }
 
+/// List of compressed accounts an account was moved from or inserted into.
+#[derive(Debug, Clone, Copy)]
+enum AccountList {
+    Compressed,
+    Token,
+}
+
+/// Change applied by the indexer, which is kept until its slot is finalized
+/// so that it can be reverted if the slot is dropped.
+#[derive(Debug, Clone)]
+enum IndexerChange {
+    Event {
+        /// Input accounts moved to the nullified accounts, with their
+        /// previous positions.
+        nullified_accounts: Vec<(AccountList, usize)>,
+        /// Trees the output accounts were appended to, and the lists they
+        /// were inserted into.
+        output_accounts: Vec<(Pubkey, Option<AccountList>)>,
+        /// Addresses the event appended to the closed addresses.
+        closed_addresses: Vec<[u8; 32]>,
+    },
+    AccountNullified {
+        merkle_tree: Pubkey,
+        leaf_index: usize,
+        leaf: [u8; 32],
+    },
+    AddressInserted {
+        merkle_tree: Pubkey,
+        low_element: IndexedElement<usize>,
+        low_leaf: [u8; 32],
+        highest_element_index: usize,
+    },
+}
+
 impl<R: RpcConnection> Clone for TestIndexer<R> {
     fn clone(&self) -> Self {
         Self {
@@ -131,7 +172,13 @@ impl<R: RpcConnection> Clone for TestIndexer<R> {
             token_compressed_accounts: self.token_compressed_accounts.clone(),
             token_nullified_compressed_accounts: self.token_nullified_compressed_accounts.clone(),
             events: self.events.clone(),
+            closed_addresses: self.closed_addresses.clone(),
             proof_types: self.proof_types.clone(),
+            prover: self.prover.clone(),
+            slot: self.slot,
+            finalized_slot: self.finalized_slot,
+            finalization_commitment: self.finalization_commitment,
+            journal: self.journal.clone(),
             phantom: Default::default(),
         }
     }
@@ -271,6 +318,14 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                     .merkle_tree
                     .update(&[0u8; 32], leaf_index)
                     .unwrap();
+                self.journal.push((
+                    self.slot,
+                    IndexerChange::AccountNullified {
+                        merkle_tree: merkle_tree_pubkey,
+                        leaf_index,
+                        leaf: decoded_hash,
+                    },
+                ));
             }
         }
     }
@@ -290,6 +345,15 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
         let new_low_element = context.new_low_element.clone().unwrap();
         let new_element = context.new_element.clone().unwrap();
         let new_element_next_value = context.new_element_next_value.clone().unwrap();
+        let change = IndexerChange::AddressInserted {
+            merkle_tree: pubkey,
+            low_element: address_tree_bundle.indexed_array.elements[new_low_element.index].clone(),
+            low_leaf: address_tree_bundle
+                .merkle_tree
+                .merkle_tree
+                .leaf(new_low_element.index),
+            highest_element_index: address_tree_bundle.indexed_array.highest_element_index,
+        };
         address_tree_bundle
             .merkle_tree
             .update(&new_low_element, &new_element, &new_element_next_value)
@@ -298,6 +362,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
             .indexed_array
             .append_with_low_element_index(new_low_element.index, &new_element.value)
             .unwrap();
+        self.journal.push((self.slot, change));
     }
 
     fn get_state_merkle_tree_accounts(&self, pubkeys: &[Pubkey]) -> Vec<StateMerkleTreeAccounts> {
@@ -313,13 +378,20 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
             .collect::<Vec<_>>()
     }
 
-    fn add_event_and_compressed_accounts(
+    async fn add_event_and_compressed_accounts(
         &mut self,
+        rpc: &mut R,
         event: &PublicTransactionEvent,
-    ) -> (
-        Vec<CompressedAccountWithMerkleContext>,
-        Vec<TokenDataWithContext>,
-    ) {
+    ) -> Result<
+        (
+            Vec<CompressedAccountWithMerkleContext>,
+            Vec<TokenDataWithContext>,
+        ),
+        IndexerError,
+    > {
+        self.update_slot(rpc).await?;
+        let mut nullified_accounts = Vec::new();
+        let mut output_accounts = Vec::new();
         for hash in event.input_compressed_account_hashes.iter() {
             let index = self.compressed_accounts.iter().position(|x| {
                 x.compressed_account
@@ -334,6 +406,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                 self.nullified_compressed_accounts
                     .push(self.compressed_accounts[index].clone());
                 self.compressed_accounts.remove(index);
+                nullified_accounts.push((AccountList::Compressed, index));
                 continue;
             };
             if index.is_none() {
@@ -354,9 +427,14 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                 self.token_nullified_compressed_accounts
                     .push(self.token_compressed_accounts[index].clone());
                 self.token_compressed_accounts.remove(index);
+                nullified_accounts.push((AccountList::Token, index));
             }
         }
 
+        // Closed addresses cannot be used again.
+        self.closed_addresses
+            .extend_from_slice(&event.closed_addresses);
+
         let mut compressed_accounts = Vec::new();
         let mut token_compressed_accounts = Vec::new();
         for (i, compressed_account) in event.output_compressed_accounts.iter().enumerate() {
@@ -374,6 +452,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
             // if data is some, try to deserialize token data, if it fails, add to compressed_accounts
             // if data is none add to compressed_accounts
             // new accounts are inserted in front so that the newest accounts are found first
+            let mut account_list = None;
             match compressed_account.compressed_account.data.as_ref() {
                 Some(data) => {
                     if compressed_account.compressed_account.owner == light_compressed_token::ID
@@ -398,6 +477,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                             };
                             token_compressed_accounts.push(token_account.clone());
                             self.token_compressed_accounts.insert(0, token_account);
+                            account_list = Some(AccountList::Token);
                         }
                     } else {
                         let compressed_account = CompressedAccountWithMerkleContext {
@@ -413,6 +493,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                         };
                         compressed_accounts.push(compressed_account.clone());
                         self.compressed_accounts.insert(0, compressed_account);
+                        account_list = Some(AccountList::Compressed);
                     }
                 }
                 None => {
@@ -427,34 +508,45 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
                     };
                     compressed_accounts.push(compressed_account.clone());
                     self.compressed_accounts.insert(0, compressed_account);
+                    account_list = Some(AccountList::Compressed);
                 }
             };
-            let merkle_tree = &mut self
-                .state_merkle_trees
-                .iter_mut()
-                .find(|x| {
-                    x.accounts.merkle_tree
-                        == event.pubkey_array
-                            [event.output_compressed_accounts[i].merkle_tree_index as usize]
+            output_accounts.push((
+                event.pubkey_array[event.output_compressed_accounts[i].merkle_tree_index as usize],
+                account_list,
+            ));
+        }
+
+        // Outputs of one tree are appended in a single batch, with one
+        // sequence number.
+        for (merkle_tree_pubkey, range) in Self::output_batches(&output_accounts) {
+            let leaves = range
+                .map(|i| {
+                    event.output_compressed_accounts[i]
+                        .compressed_account
+                        .hash::<Poseidon>(&merkle_tree_pubkey, &event.output_leaf_indices[i])
+                        .unwrap()
                 })
+                .collect::<Vec<_>>();
+            self.state_merkle_trees
+                .iter_mut()
+                .find(|x| x.accounts.merkle_tree == merkle_tree_pubkey)
                 .unwrap()
-                .merkle_tree;
-            merkle_tree
-                .append(
-                    &compressed_account
-                        .compressed_account
-                        .hash::<Poseidon>(
-                            &event.pubkey_array
-                                [event.output_compressed_accounts[i].merkle_tree_index as usize],
-                            &event.output_leaf_indices[i],
-                        )
-                        .unwrap(),
-                )
+                .merkle_tree
+                .append_batch_subtrees(&leaves.iter().collect::<Vec<_>>())
                 .expect("insert failed");
         }
 
         self.events.push(event.clone());
-        (compressed_accounts, token_compressed_accounts)
+        self.journal.push((
+            self.slot,
+            IndexerChange::Event {
+                nullified_accounts,
+                output_accounts,
+                closed_addresses: event.closed_addresses.clone(),
+            },
+        ));
+        Ok((compressed_accounts, token_compressed_accounts))
     }
 
     fn get_state_merkle_trees(&self) -> &Vec<StateMerkleTreeBundle> {
@@ -492,7 +584,7 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
         new_addresses: Option<&[[u8; 32]]>,
         address_merkle_tree_pubkeys: Option<Vec<Pubkey>>,
         rpc: &mut R,
-    ) -> ProofRpcResult {
+    ) -> Result<ProofRpcResult, IndexerError> {
         if compressed_accounts.is_some()
             && ![1usize, 2usize, 3usize, 4usize, 8usize]
                 .contains(&compressed_accounts.unwrap().len())
@@ -505,101 +597,82 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
         if new_addresses.is_some() && ![1usize, 2usize].contains(&new_addresses.unwrap().len()) {
             panic!("new_addresses must be of length 1, 2")
         }
-        let client = Client::new();
-        let (root_indices, address_root_indices, json_payload) =
+        let (root_indices, address_root_indices, inclusion_inputs, non_inclusion_inputs) =
             match (compressed_accounts, new_addresses) {
                 (Some(accounts), None) => {
-                    let (payload, indices) = self
+                    let (inputs, indices) = self
                         .process_inclusion_proofs(state_merkle_tree_pubkeys.unwrap(), accounts, rpc)
                         .await;
-                    (indices, Vec::new(), payload.to_string())
+                    (indices, Vec::<u16>::new(), inputs, Vec::new())
                 }
                 (None, Some(addresses)) => {
-                    let (payload, indices) = self
+                    let (inputs, indices) = self
                         .process_non_inclusion_proofs(
-                            address_merkle_tree_pubkeys.unwrap().as_slice(),
+                            address_merkle_tree_pubkeys.as_deref().unwrap(),
                             addresses,
                             rpc,
                         )
                         .await;
-                    (Vec::<u16>::new(), indices, payload.to_string())
+                    (Vec::<u16>::new(), indices, Vec::new(), inputs)
                 }
                 (Some(accounts), Some(addresses)) => {
-                    let (inclusion_payload, inclusion_indices) = self
+                    let (inclusion_inputs, inclusion_indices) = self
                         .process_inclusion_proofs(state_merkle_tree_pubkeys.unwrap(), accounts, rpc)
                         .await;
-                    let (non_inclusion_payload, non_inclusion_indices) = self
+                    let (non_inclusion_inputs, non_inclusion_indices) = self
                         .process_non_inclusion_proofs(
-                            address_merkle_tree_pubkeys.unwrap().as_slice(),
+                            address_merkle_tree_pubkeys.as_deref().unwrap(),
                             addresses,
                             rpc,
                         )
                         .await;
-
-                    let combined_payload = CombinedJsonStruct {
-                        inclusion: inclusion_payload.inputs,
-                        non_inclusion: non_inclusion_payload.inputs,
-                    }
-                    .to_string();
-                    (inclusion_indices, non_inclusion_indices, combined_payload)
+                    (
+                        inclusion_indices,
+                        non_inclusion_indices,
+                        inclusion_inputs,
+                        non_inclusion_inputs,
+                    )
                 }
                 _ => {
                     panic!("At least one of compressed_accounts or new_addresses must be provided")
                 }
             };
-
-        let mut retries = 3;
-        while retries > 0 {
-            let response_result = client
-                .post(&format!("{}{}", SERVER_ADDRESS, PROVE_PATH))
-                .header("Content-Type", "text/plain; charset=utf-8")
-                .body(json_payload.clone())
-                .send()
-                .await
-                .expect("Failed to execute request.");
-            if response_result.status().is_success() {
-                let body = response_result.text().await.unwrap();
-                let proof_json = deserialize_gnark_proof_json(&body).unwrap();
-                let (proof_a, proof_b, proof_c) = proof_from_json_struct(proof_json);
-                let (proof_a, proof_b, proof_c) = compress_proof(&proof_a, &proof_b, &proof_c);
-                return ProofRpcResult {
-                    root_indices,
-                    address_root_indices,
-                    proof: CompressedProof {
-                        a: proof_a,
-                        b: proof_b,
-                        c: proof_c,
-                    },
-                };
-            } else {
-                warn!("Error: {}", response_result.text().await.unwrap());
-                tokio::time::sleep(Duration::from_secs(1)).await;
-                spawn_prover(true, self.proof_types.as_slice()).await;
-                retries -= 1;
-            }
-        }
-        panic!("Failed to get proof from server");
+        let request = ProofRequest::new(inclusion_inputs, non_inclusion_inputs).with_trees(
+            state_merkle_tree_pubkeys
+                .map(|pubkeys| pubkeys.to_vec())
+                .unwrap_or_default(),
+            address_merkle_tree_pubkeys.unwrap_or_default(),
+        );
+        let result = self
+            .prover
+            .prove(&request)
+            .await
+            .map_err(|e| IndexerError::Custom(e.to_string()))?;
+        Ok(ProofRpcResult {
+            root_indices,
+            address_root_indices,
+            proof: CompressedProof {
+                a: result.proof.a,
+                b: result.proof.b,
+                c: result.proof.c,
+            },
+        })
     }
 
-    /// returns compressed_accounts with the owner pubkey
-    /// does not return token accounts.
-    fn get_compressed_accounts_by_owner(
+    async fn get_compressed_accounts_by_owner(
         &self,
         owner: &Pubkey,
-    ) -> Vec<CompressedAccountWithMerkleContext> {
-        self.compressed_accounts
-            .iter()
-            .filter(|x| x.compressed_account.owner == *owner)
-            .cloned()
-            .collect()
+    ) -> Result<Vec<CompressedAccountWithMerkleContext>, IndexerError> {
+        Ok(TestIndexer::get_compressed_accounts_by_owner(self, owner))
     }
 
-    fn get_compressed_token_accounts_by_owner(&self, owner: &Pubkey) -> Vec<TokenDataWithContext> {
-        self.token_compressed_accounts
-            .iter()
-            .filter(|x| x.token_data.owner == *owner)
-            .cloned()
-            .collect()
+    async fn get_compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Result<Vec<TokenDataWithContext>, IndexerError> {
+        Ok(TestIndexer::get_compressed_token_accounts_by_owner(
+            self, owner,
+        ))
     }
 
     fn add_address_merkle_tree_accounts(
@@ -621,6 +694,30 @@ impl<R: RpcConnection + Send + Sync + 'static> Indexer<R> for TestIndexer<R> {
 }
 
 impl<R: RpcConnection> TestIndexer<R> {
+    /// returns compressed_accounts with the owner pubkey
+    /// does not return token accounts.
+    pub fn get_compressed_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Vec<CompressedAccountWithMerkleContext> {
+        self.compressed_accounts
+            .iter()
+            .filter(|x| x.compressed_account.owner == *owner)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_compressed_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+    ) -> Vec<TokenDataWithContext> {
+        self.token_compressed_accounts
+            .iter()
+            .filter(|x| x.token_data.owner == *owner)
+            .cloned()
+            .collect()
+    }
+
     fn count_matching_hashes(&self, query_hashes: &[String]) -> usize {
         self.nullified_compressed_accounts
             .iter()
@@ -665,6 +762,27 @@ impl<R: RpcConnection> TestIndexer<R> {
         .await
     }
 
+    pub fn init_from_env_with_prover<P: Into<ProverBackend>>(
+        payer: &Keypair,
+        env: &EnvAccounts,
+        prover: P,
+    ) -> Self {
+        Self::new_with_prover(
+            vec![StateMerkleTreeAccounts {
+                merkle_tree: env.merkle_tree_pubkey,
+                nullifier_queue: env.nullifier_queue_pubkey,
+                cpi_context: env.cpi_context_account_pubkey,
+            }],
+            vec![AddressMerkleTreeAccounts {
+                merkle_tree: env.address_merkle_tree_pubkey,
+                queue: env.address_merkle_tree_queue_pubkey,
+            }],
+            payer.insecure_clone(),
+            env.group_pda,
+            prover,
+        )
+    }
+
     pub async fn new(
         state_merkle_tree_accounts: Vec<StateMerkleTreeAccounts>,
         address_merkle_tree_accounts: Vec<AddressMerkleTreeAccounts>,
@@ -680,9 +798,31 @@ impl<R: RpcConnection> TestIndexer<R> {
         if non_inclusion {
             vec_proof_types.push(ProofType::NonInclusion);
         }
-        if !vec_proof_types.is_empty() {
-            spawn_prover(true, vec_proof_types.as_slice()).await;
-        }
+        let prover = if vec_proof_types.is_empty() {
+            GnarkProver::default()
+        } else {
+            GnarkProver::spawn_local(vec_proof_types.as_slice()).await
+        };
+        let mut indexer = Self::new_with_prover(
+            state_merkle_tree_accounts,
+            address_merkle_tree_accounts,
+            payer,
+            group_pda,
+            prover,
+        );
+        indexer.proof_types = vec_proof_types;
+        indexer
+    }
+
+    /// Creates an indexer which generates proofs with the given `prover`,
+    /// without spawning the `light-prover` server.
+    pub fn new_with_prover<P: Into<ProverBackend>>(
+        state_merkle_tree_accounts: Vec<StateMerkleTreeAccounts>,
+        address_merkle_tree_accounts: Vec<AddressMerkleTreeAccounts>,
+        payer: Keypair,
+        group_pda: Pubkey,
+        prover: P,
+    ) -> Self {
         let mut state_merkle_trees = Vec::new();
         for state_merkle_tree_account in state_merkle_tree_accounts.iter() {
             let merkle_tree = Box::new(MerkleTree::<Poseidon>::new(
@@ -710,14 +850,174 @@ impl<R: RpcConnection> TestIndexer<R> {
             compressed_accounts: vec![],
             nullified_compressed_accounts: vec![],
             events: vec![],
+            closed_addresses: vec![],
             token_compressed_accounts: vec![],
             token_nullified_compressed_accounts: vec![],
-            proof_types: vec_proof_types,
+            proof_types: Vec::new(),
+            prover: Arc::new(ProofService::new(prover.into(), Default::default())),
+            slot: 0,
+            finalized_slot: 0,
+            finalization_commitment: CommitmentConfig::finalized(),
+            journal: Vec::new(),
             phantom: Default::default(),
             group_pda,
         }
     }
 
+    /// Reads the slot of the changes applied next from `rpc`. Events read
+    /// it when they are applied, nullifications and address inserts are
+    /// recorded at the slot read last.
+    pub async fn update_slot(&mut self, rpc: &mut R) -> Result<u64, IndexerError> {
+        self.slot = rpc.get_slot().await?;
+        Ok(self.slot)
+    }
+
+    /// Reverts the changes applied after `slot`, newest first. Used when
+    /// their slots were dropped by a fork. Only changes applied through the
+    /// indexer are reverted.
+    pub fn rollback(&mut self, slot: u64) -> Result<(), IndexerError> {
+        if slot < self.finalized_slot {
+            return Err(IndexerError::SlotFinalized(slot));
+        }
+        while self
+            .journal
+            .last()
+            .is_some_and(|(change_slot, _)| *change_slot > slot)
+        {
+            let (_, change) = self.journal.pop().unwrap();
+            self.revert(change);
+        }
+        self.slot = slot;
+        Ok(())
+    }
+
+    /// Finalizes the changes up to `slot`, they can't be rolled back
+    /// afterwards.
+    pub fn finalize(&mut self, slot: u64) {
+        if slot <= self.finalized_slot {
+            return;
+        }
+        self.finalized_slot = slot;
+        self.journal.retain(|(change_slot, _)| *change_slot > slot);
+    }
+
+    /// Finalizes the changes up to the slot `rpc` reports at the
+    /// `finalization_commitment`. Returns the finalized slot.
+    pub async fn finalize_with_rpc(&mut self, rpc: &mut R) -> Result<u64, IndexerError> {
+        let slot = rpc
+            .get_slot_with_commitment(self.finalization_commitment)
+            .await?;
+        self.finalize(slot);
+        Ok(self.finalized_slot)
+    }
+
+    /// Returns the number of changes which can still be rolled back.
+    pub fn pending_changes(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Returns the trees of output accounts with the ranges of outputs
+    /// appended to them in one batch. Outputs are ordered by their trees.
+    fn output_batches(
+        output_accounts: &[(Pubkey, Option<AccountList>)],
+    ) -> Vec<(Pubkey, Range<usize>)> {
+        let mut batches: Vec<(Pubkey, Range<usize>)> = Vec::new();
+        for (i, (merkle_tree_pubkey, _)) in output_accounts.iter().enumerate() {
+            match batches.last_mut() {
+                Some((pubkey, range)) if pubkey == merkle_tree_pubkey => range.end = i + 1,
+                _ => batches.push((*merkle_tree_pubkey, i..i + 1)),
+            }
+        }
+        batches
+    }
+
+    fn revert(&mut self, change: IndexerChange) {
+        match change {
+            IndexerChange::Event {
+                nullified_accounts,
+                output_accounts,
+                closed_addresses,
+            } => {
+                self.events.pop();
+                for (merkle_tree_pubkey, range) in
+                    Self::output_batches(&output_accounts).into_iter().rev()
+                {
+                    self.state_merkle_trees
+                        .iter_mut()
+                        .find(|x| x.accounts.merkle_tree == merkle_tree_pubkey)
+                        .unwrap()
+                        .merkle_tree
+                        .revert_append_batch_subtrees(range.len())
+                        .expect("revert append failed");
+                }
+                for (_, account_list) in output_accounts.iter().rev() {
+                    // Output accounts were inserted in front.
+                    match account_list {
+                        Some(AccountList::Compressed) => {
+                            self.compressed_accounts.remove(0);
+                        }
+                        Some(AccountList::Token) => {
+                            self.token_compressed_accounts.remove(0);
+                        }
+                        None => {}
+                    }
+                }
+                self.closed_addresses
+                    .truncate(self.closed_addresses.len() - closed_addresses.len());
+                for (account_list, index) in nullified_accounts.into_iter().rev() {
+                    match account_list {
+                        AccountList::Compressed => {
+                            let account = self.nullified_compressed_accounts.pop().unwrap();
+                            self.compressed_accounts.insert(index, account);
+                        }
+                        AccountList::Token => {
+                            let account = self.token_nullified_compressed_accounts.pop().unwrap();
+                            self.token_compressed_accounts.insert(index, account);
+                        }
+                    }
+                }
+            }
+            IndexerChange::AccountNullified {
+                merkle_tree,
+                leaf_index,
+                leaf,
+            } => {
+                self.state_merkle_trees
+                    .iter_mut()
+                    .find(|x| x.accounts.merkle_tree == merkle_tree)
+                    .unwrap()
+                    .merkle_tree
+                    .revert_update(&leaf, leaf_index)
+                    .expect("revert update failed");
+            }
+            IndexerChange::AddressInserted {
+                merkle_tree,
+                low_element,
+                low_leaf,
+                highest_element_index,
+            } => {
+                let address_tree_bundle = self
+                    .address_merkle_trees
+                    .iter_mut()
+                    .find(|x| x.accounts.merkle_tree == merkle_tree)
+                    .unwrap();
+                let reference_tree = &mut address_tree_bundle.merkle_tree.merkle_tree;
+                reference_tree
+                    .revert_append()
+                    .expect("revert append failed");
+                reference_tree
+                    .revert_update(&low_leaf, low_element.index)
+                    .expect("revert update failed");
+
+                let indexed_array = &mut address_tree_bundle.indexed_array;
+                indexed_array.elements.pop();
+                indexed_array.current_node_index = indexed_array.elements.len() - 1;
+                indexed_array.highest_element_index = highest_element_index;
+                indexed_array.elements[low_element.index] = low_element;
+            }
+        }
+    }
+
     pub fn add_address_merkle_tree_bundle(
         address_merkle_tree_accounts: AddressMerkleTreeAccounts,
         // TODO: add config here
@@ -812,7 +1112,7 @@ impl<R: RpcConnection> TestIndexer<R> {
         merkle_tree_pubkeys: &[Pubkey],
         accounts: &[[u8; 32]],
         rpc: &mut R,
-    ) -> (BatchInclusionJsonStruct, Vec<u16>) {
+    ) -> (Vec<InclusionMerkleProofInputs>, Vec<u16>) {
         let mut inclusion_proofs = Vec::new();
         let mut root_indices = Vec::new();
 
@@ -854,14 +1154,14 @@ impl<R: RpcConnection> TestIndexer<R> {
                 "Merkle tree root mismatch"
             );
 
+            self.prover.update_root_history(
+                merkle_tree_pubkeys[i],
+                fetched_merkle_tree.roots.iter().copied(),
+            );
             root_indices.push(fetched_merkle_tree.root_index() as u16);
         }
 
-        let inclusion_proof_inputs = InclusionProofInputs(inclusion_proofs.as_slice());
-        let batch_inclusion_proof_inputs =
-            BatchInclusionJsonStruct::from_inclusion_proof_inputs(&inclusion_proof_inputs);
-
-        (batch_inclusion_proof_inputs, root_indices)
+        (inclusion_proofs, root_indices)
     }
 
     async fn process_non_inclusion_proofs(
@@ -869,7 +1169,7 @@ impl<R: RpcConnection> TestIndexer<R> {
         address_merkle_tree_pubkeys: &[Pubkey],
         addresses: &[[u8; 32]],
         rpc: &mut R,
-    ) -> (BatchNonInclusionJsonStruct, Vec<u16>) {
+    ) -> (Vec<NonInclusionMerkleProofInputs>, Vec<u16>) {
         let mut non_inclusion_proofs = Vec::new();
         let mut address_root_indices = Vec::new();
         for (i, address) in addresses.iter().enumerate() {
@@ -891,25 +1191,25 @@ impl<R: RpcConnection> TestIndexer<R> {
                 )
                 .await
             };
+            self.prover.update_root_history(
+                address_merkle_tree_pubkeys[i],
+                fetched_address_merkle_tree.roots.iter().copied(),
+            );
             address_root_indices.push(fetched_address_merkle_tree.root_index() as u16);
         }
 
-        let non_inclusion_proof_inputs = NonInclusionProofInputs(non_inclusion_proofs.as_slice());
-        let batch_non_inclusion_proof_inputs =
-            BatchNonInclusionJsonStruct::from_non_inclusion_proof_inputs(
-                &non_inclusion_proof_inputs,
-            );
-        (batch_non_inclusion_proof_inputs, address_root_indices)
+        (non_inclusion_proofs, address_root_indices)
     }
 
-    /// deserializes an event
+    /// decodes an event (enveloped or legacy)
     /// adds the output_compressed_accounts to the compressed_accounts
     /// removes the input_compressed_accounts from the compressed_accounts
     /// adds the input_compressed_accounts to the nullified_compressed_accounts
-    pub fn add_lamport_compressed_accounts(&mut self, event_bytes: Vec<u8>) {
-        let event_bytes = event_bytes.clone();
-        let event = PublicTransactionEvent::deserialize(&mut event_bytes.as_slice()).unwrap();
-        self.add_event_and_compressed_accounts(&event);
+    pub async fn add_lamport_compressed_accounts(&mut self, rpc: &mut R, event_bytes: Vec<u8>) {
+        let event = PublicTransactionEvent::decode(event_bytes.as_slice()).unwrap();
+        self.add_event_and_compressed_accounts(rpc, &event)
+            .await
+            .unwrap();
     }
 
     /// deserializes an event
@@ -918,8 +1218,14 @@ impl<R: RpcConnection> TestIndexer<R> {
     /// adds the input_compressed_accounts to the nullified_compressed_accounts
     /// deserialiazes token data from the output_compressed_accounts
     /// adds the token_compressed_accounts to the token_compressed_accounts
-    pub fn add_compressed_accounts_with_token_data(&mut self, event: &PublicTransactionEvent) {
-        self.add_event_and_compressed_accounts(event);
+    pub async fn add_compressed_accounts_with_token_data(
+        &mut self,
+        rpc: &mut R,
+        event: &PublicTransactionEvent,
+    ) {
+        self.add_event_and_compressed_accounts(rpc, event)
+            .await
+            .unwrap();
     }
 
     /// returns the compressed sol balance of the owner pubkey
@@ -27,6 +27,7 @@ pub mod assert_token_tx;
 pub mod e2e_test_env;
 #[allow(unused)]
 pub mod indexer;
+pub mod queue_migration;
 pub mod registry;
 pub mod rpc;
 pub mod spl;
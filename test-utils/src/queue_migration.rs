@@ -0,0 +1,98 @@
+use crate::create_account_instruction;
+use crate::rpc::errors::RpcError;
+use crate::rpc::rpc_connection::RpcConnection;
+use account_compression::{
+    sdk::{create_migrate_queue_elements_instruction, create_migrate_queue_instruction},
+    state::QueueAccount,
+    ID,
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+
+/// Creates a queue account with the given capacity and starts migrating the
+/// elements of the old queue into it.
+pub async fn migrate_queue<R: RpcConnection>(
+    rpc: &mut R,
+    authority: &Keypair,
+    merkle_tree_pubkey: &Pubkey,
+    old_queue_pubkey: &Pubkey,
+    new_queue_keypair: &Keypair,
+    capacity: u16,
+) -> Result<Signature, RpcError> {
+    let payer = rpc.get_payer().insecure_clone();
+    let size = QueueAccount::size(capacity as usize).unwrap();
+    let create_queue_instruction = create_account_instruction(
+        &payer.pubkey(),
+        size,
+        rpc.get_minimum_balance_for_rent_exemption(size)
+            .await
+            .unwrap(),
+        &ID,
+        Some(new_queue_keypair),
+    );
+    let instruction = create_migrate_queue_instruction(
+        payer.pubkey(),
+        authority.pubkey(),
+        None,
+        *merkle_tree_pubkey,
+        *old_queue_pubkey,
+        new_queue_keypair.pubkey(),
+        capacity,
+    );
+    rpc.create_and_send_transaction(
+        &[create_queue_instruction, instruction],
+        &payer.pubkey(),
+        &[&payer, authority, new_queue_keypair],
+    )
+    .await
+}
+
+pub async fn migrate_queue_elements<R: RpcConnection>(
+    rpc: &mut R,
+    authority: &Keypair,
+    merkle_tree_pubkey: &Pubkey,
+    old_queue_pubkey: &Pubkey,
+    new_queue_pubkey: &Pubkey,
+    num_buckets: u16,
+) -> Result<Signature, RpcError> {
+    let payer = rpc.get_payer().insecure_clone();
+    let instruction = create_migrate_queue_elements_instruction(
+        payer.pubkey(),
+        authority.pubkey(),
+        None,
+        *merkle_tree_pubkey,
+        *old_queue_pubkey,
+        *new_queue_pubkey,
+        num_buckets,
+    );
+    rpc.create_and_send_transaction(&[instruction], &payer.pubkey(), &[&payer, authority])
+        .await
+}
+
+/// Migrates elements until the old queue is closed and returns the number
+/// of transactions.
+pub async fn migrate_all_queue_elements<R: RpcConnection>(
+    rpc: &mut R,
+    authority: &Keypair,
+    merkle_tree_pubkey: &Pubkey,
+    old_queue_pubkey: &Pubkey,
+    new_queue_pubkey: &Pubkey,
+    num_buckets: u16,
+) -> Result<usize, RpcError> {
+    let mut num_transactions = 0;
+    while rpc.get_account(*old_queue_pubkey).await?.is_some() {
+        migrate_queue_elements(
+            rpc,
+            authority,
+            merkle_tree_pubkey,
+            old_queue_pubkey,
+            new_queue_pubkey,
+            num_buckets,
+        )
+        .await?;
+        num_transactions += 1;
+    }
+    Ok(num_transactions)
+}
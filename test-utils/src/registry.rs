@@ -6,15 +6,21 @@ use account_compression::{
     AddressMerkleTreeConfig, AddressQueueConfig, NullifierQueueConfig, QueueAccount,
     StateMerkleTreeConfig,
 };
+use anchor_lang::AnchorSerialize;
 use light_registry::sdk::{
     create_rollover_address_merkle_tree_instruction, create_rollover_state_merkle_tree_instruction,
     CreateRolloverMerkleTreeInstructionInputs,
 };
 use light_registry::{
-    get_forester_epoch_pda_address,
-    sdk::{create_register_forester_instruction, create_update_forester_instruction},
-    ForesterEpoch,
+    get_forester_epoch_pda_address, get_verifying_key_pda_address,
+    sdk::{
+        create_activate_verifying_key_instruction, create_initialize_verifying_key_instruction,
+        create_register_forester_instruction, create_update_forester_instruction,
+        create_write_verifying_key_instructions,
+    },
+    CircuitShape, ForesterEpoch, VerifyingKeyAccount, VerifyingKeyData,
 };
+use light_verifier::client::{VerifyingKeyId, TREE_HEIGHT};
 use solana_sdk::{
     instruction::Instruction,
     pubkey::Pubkey,
@@ -81,6 +87,72 @@ pub async fn assert_registered_forester<R: RpcConnection>(
     Ok(())
 }
 
+/// Uploads and activates the verifying key of `shape`, creating its account
+/// if it doesn't exist yet.
+pub async fn register_test_verifying_key<R: RpcConnection>(
+    rpc: &mut R,
+    governance_authority: &Keypair,
+    shape: CircuitShape,
+    verifying_key: &VerifyingKeyData,
+) -> Result<Pubkey, RpcError> {
+    let pda = get_verifying_key_pda_address(&shape).0;
+    let authority = governance_authority.pubkey();
+    if rpc.get_account(pda).await?.is_none() {
+        let ix = create_initialize_verifying_key_instruction(&authority, shape);
+        rpc.create_and_send_transaction(&[ix], &authority, &[governance_authority])
+            .await?;
+    }
+    let payload = verifying_key.try_to_vec().unwrap();
+    for ix in create_write_verifying_key_instructions(&authority, shape, &payload) {
+        rpc.create_and_send_transaction(&[ix], &authority, &[governance_authority])
+            .await?;
+    }
+    let ix = create_activate_verifying_key_instruction(&authority, shape);
+    rpc.create_and_send_transaction(&[ix], &authority, &[governance_authority])
+        .await?;
+
+    let account = rpc.get_anchor_account::<VerifyingKeyAccount>(&pda).await;
+    if account.shape != shape || account.key.as_ref() != Some(verifying_key) {
+        return Err(RpcError::AssertRpcError(format!(
+            "Expected verifying key of {:?}, got: {:?}",
+            shape, account
+        )));
+    }
+    Ok(pda)
+}
+
+/// Circuit shapes of the verifying keys compiled into `light-verifier`.
+pub fn compiled_verifying_key_shapes() -> Vec<(VerifyingKeyId, CircuitShape)> {
+    let mut shapes = Vec::new();
+    for num_leaves in 0..=8 {
+        for num_addresses in 0..=2 {
+            if let Ok(id) = VerifyingKeyId::select(num_leaves, num_addresses) {
+                let shape =
+                    CircuitShape::new(TREE_HEIGHT as u8, num_leaves as u8, num_addresses as u8)
+                        .unwrap();
+                shapes.push((id, shape));
+            }
+        }
+    }
+    shapes
+}
+
+/// Registers the verifying keys compiled into `light-verifier`, returns
+/// their accounts.
+pub async fn register_test_verifying_keys<R: RpcConnection>(
+    rpc: &mut R,
+    governance_authority: &Keypair,
+) -> Result<Vec<Pubkey>, RpcError> {
+    let mut pdas = Vec::new();
+    for (id, shape) in compiled_verifying_key_shapes() {
+        let verifying_key = VerifyingKeyData::from(id.verifying_key().unwrap());
+        pdas.push(
+            register_test_verifying_key(rpc, governance_authority, shape, &verifying_key).await?,
+        );
+    }
+    Ok(pdas)
+}
+
 pub struct RentExemption {
     pub size: usize,
     pub lamports: u64,
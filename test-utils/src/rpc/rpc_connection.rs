@@ -3,6 +3,7 @@ use crate::transaction_params::TransactionParams;
 use account_compression::initialize_address_merkle_tree::{AnchorDeserialize, Pubkey};
 use anchor_lang::solana_program::clock::Slot;
 use anchor_lang::solana_program::instruction::Instruction;
+use light_sdk::event::DecodeEvent;
 use solana_sdk::account::{Account, AccountSharedData};
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::hash::Hash;
@@ -23,7 +24,7 @@ pub trait RpcConnection: Clone + Send + Sync + Debug + 'static {
         transaction_params: Option<TransactionParams>,
     ) -> impl std::future::Future<Output = Result<Option<(T, Signature)>, RpcError>> + Send
     where
-        T: AnchorDeserialize + Send + Debug;
+        T: DecodeEvent + Send + Debug;
 
     fn create_and_send_transaction(
         &mut self,
@@ -57,6 +58,10 @@ pub trait RpcConnection: Clone + Send + Sync + Debug + 'static {
         transaction: Transaction,
     ) -> impl std::future::Future<Output = Result<Signature, RpcError>> + Send;
     fn get_slot(&mut self) -> impl std::future::Future<Output = Result<u64, RpcError>> + Send;
+    fn get_slot_with_commitment(
+        &mut self,
+        commitment_config: CommitmentConfig,
+    ) -> impl std::future::Future<Output = Result<u64, RpcError>> + Send;
     fn airdrop_lamports(
         &mut self,
         to: &Pubkey,
@@ -4,6 +4,7 @@ use anchor_lang::prelude::Pubkey;
 use anchor_lang::solana_program::clock::Slot;
 use anchor_lang::solana_program::hash::Hash;
 use anchor_lang::AnchorDeserialize;
+use light_sdk::event::DecodeEvent;
 use log::debug;
 use solana_client::rpc_client::RpcClient;
 use solana_client::rpc_config::RpcTransactionConfig;
@@ -55,7 +56,7 @@ impl Debug for SolanaRpcConnection {
 }
 
 impl SolanaRpcConnection {
-    fn parse_inner_instructions<T: AnchorDeserialize>(
+    fn parse_inner_instructions<T: DecodeEvent>(
         &self,
         signature: Signature,
     ) -> Result<T, RpcError> {
@@ -103,7 +104,7 @@ impl SolanaRpcConnection {
                                 )
                             })?;
 
-                        if let Ok(parsed_data) = T::try_from_slice(data.as_slice()) {
+                        if let Ok(parsed_data) = T::decode(data.as_slice()) {
                             return Ok(parsed_data);
                         }
                     }
@@ -141,7 +142,7 @@ impl RpcConnection for SolanaRpcConnection {
         transaction_params: Option<TransactionParams>,
     ) -> Result<Option<(T, Signature)>, RpcError>
     where
-        T: AnchorDeserialize + Debug,
+        T: DecodeEvent + Debug,
     {
         let pre_balance = self.client.get_balance(payer)?;
         let latest_blockhash = self.client.get_latest_blockhash()?;
@@ -163,7 +164,7 @@ impl RpcConnection for SolanaRpcConnection {
             .message
             .instructions
             .iter()
-            .find_map(|instruction| T::try_from_slice(instruction.data.as_slice()).ok());
+            .find_map(|instruction| T::decode(instruction.data.as_slice()).ok());
 
         if event.is_none() {
             let parsed_event: Result<T, RpcError> = self.parse_inner_instructions::<T>(signature);
@@ -286,6 +287,15 @@ impl RpcConnection for SolanaRpcConnection {
         self.client.get_slot().map_err(RpcError::from)
     }
 
+    async fn get_slot_with_commitment(
+        &mut self,
+        commitment_config: CommitmentConfig,
+    ) -> Result<u64, RpcError> {
+        self.client
+            .get_slot_with_commitment(commitment_config)
+            .map_err(RpcError::from)
+    }
+
     async fn airdrop_lamports(
         &mut self,
         to: &Pubkey,
@@ -3,8 +3,10 @@ use anchor_lang::solana_program::clock::Slot;
 use anchor_lang::solana_program::hash::Hash;
 use anchor_lang::solana_program::system_instruction;
 use anchor_lang::AnchorDeserialize;
+use light_sdk::event::DecodeEvent;
 use solana_program_test::{BanksClientError, ProgramTestContext};
 use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::instruction::{Instruction, InstructionError};
 use solana_sdk::signature::{Keypair, Signature};
 use solana_sdk::signer::Signer;
@@ -40,7 +42,7 @@ impl RpcConnection for ProgramTestRpcConnection {
         transaction_params: Option<TransactionParams>,
     ) -> Result<Option<(T, solana_sdk::signature::Signature)>, RpcError>
     where
-        T: AnchorDeserialize,
+        T: DecodeEvent,
     {
         let pre_balance = self
             .context
@@ -81,7 +83,7 @@ impl RpcConnection for ProgramTestRpcConnection {
             .and_then(|details| details.inner_instructions)
             .and_then(|instructions| {
                 instructions.iter().flatten().find_map(|inner_instruction| {
-                    T::try_from_slice(inner_instruction.instruction.data.as_slice()).ok()
+                    T::decode(inner_instruction.instruction.data.as_slice()).ok()
                 })
             });
         // If transaction was successful, execute it.
@@ -229,6 +231,15 @@ impl RpcConnection for ProgramTestRpcConnection {
             .map_err(RpcError::from)
     }
 
+    /// The program test bank roots every slot it warps to, so all
+    /// commitments report the root slot.
+    async fn get_slot_with_commitment(
+        &mut self,
+        _commitment_config: CommitmentConfig,
+    ) -> Result<u64, RpcError> {
+        self.get_slot().await
+    }
+
     async fn airdrop_lamports(
         &mut self,
         to: &Pubkey,
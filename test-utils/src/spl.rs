@@ -81,7 +81,10 @@ pub async fn mint_tokens_helper<R: RpcConnection, I: Indexer<R>>(
         .unwrap()
         .unwrap();
 
-    let (_, created_token_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_token_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     assert_mint_to(
         rpc,
         test_indexer,
@@ -331,7 +334,8 @@ pub async fn compressed_transfer_test<R: RpcConnection, I: Indexer<R>>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     output_compressed_accounts.sort_by(|a, b| a.merkle_tree.cmp(&b.merkle_tree));
 
     let delegate_pubkey = if delegate_change_account_index.is_some() {
@@ -381,7 +385,10 @@ pub async fn compressed_transfer_test<R: RpcConnection, I: Indexer<R>>(
         .unwrap()
         .unwrap();
 
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     let delegates = if let Some(index) = delegate_change_account_index {
         let mut delegates = vec![None; created_output_accounts.len()];
         delegates[index as usize] = Some(payer.pubkey());
@@ -444,7 +451,8 @@ pub async fn decompress_test<R: RpcConnection, I: Indexer<R>>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let mint = input_compressed_accounts[0].token_data.mint;
     let instruction = create_transfer_instruction(
         &rpc.get_payer().pubkey(),
@@ -500,7 +508,10 @@ pub async fn decompress_test<R: RpcConnection, I: Indexer<R>>(
         .unwrap()
         .unwrap();
 
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     assert_transfer(
         rpc,
         test_indexer,
@@ -603,7 +614,10 @@ pub async fn compress_test<R: RpcConnection, I: Indexer<R>>(
         .unwrap()
         .unwrap();
 
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
 
     assert_transfer(
         rpc,
@@ -664,7 +678,8 @@ pub async fn approve_test<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let mint = input_compressed_accounts[0].token_data.mint;
     let inputs = CreateApproveInstructionInputs {
         fee_payer: rpc.get_payer().pubkey(),
@@ -710,7 +725,10 @@ pub async fn approve_test<R: RpcConnection>(
         .await
         .unwrap()
         .unwrap();
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     let input_amount = input_compressed_accounts
         .iter()
         .map(|x| x.token_data.amount)
@@ -793,7 +811,8 @@ pub async fn revoke_test<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let mint = input_compressed_accounts[0].token_data.mint;
     let inputs = CreateRevokeInstructionInputs {
         fee_payer: rpc.get_payer().pubkey(),
@@ -833,7 +852,10 @@ pub async fn revoke_test<R: RpcConnection>(
         .await
         .unwrap()
         .unwrap();
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     let input_amount = input_compressed_accounts
         .iter()
         .map(|x| x.token_data.amount)
@@ -929,7 +951,8 @@ pub async fn freeze_or_thaw_test<R: RpcConnection, const FREEZE: bool>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let mint = input_compressed_accounts[0].token_data.mint;
     let inputs = CreateInstructionInputs {
         fee_payer: rpc.get_payer().pubkey(),
@@ -969,7 +992,10 @@ pub async fn freeze_or_thaw_test<R: RpcConnection, const FREEZE: bool>(
         .await
         .unwrap()
         .unwrap();
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
 
     let mut delegates = Vec::new();
     let mut expected_output_accounts = Vec::new();
@@ -1039,7 +1065,8 @@ pub async fn burn_test<R: RpcConnection>(
             None,
             rpc,
         )
-        .await;
+        .await
+        .unwrap();
     let mint = input_compressed_accounts[0].token_data.mint;
     let inputs = CreateBurnInstructionInputs {
         fee_payer: rpc.get_payer().pubkey(),
@@ -1090,7 +1117,10 @@ pub async fn burn_test<R: RpcConnection>(
         .await
         .unwrap()
         .unwrap();
-    let (_, created_output_accounts) = test_indexer.add_event_and_compressed_accounts(&event);
+    let (_, created_output_accounts) = test_indexer
+        .add_event_and_compressed_accounts(rpc, &event)
+        .await
+        .unwrap();
     let mut delegates = Vec::new();
     let mut expected_output_accounts = Vec::new();
 
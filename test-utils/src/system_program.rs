@@ -349,7 +349,8 @@ pub async fn compressed_transaction_test<R: RpcConnection, I: Indexer<R>>(
                 address_merkle_tree_pubkeys,
                 inputs.rpc,
             )
-            .await;
+            .await
+            .map_err(|e| RpcError::CustomError(e.to_string()))?;
         root_indices = proof_rpc_res.root_indices;
         proof = Some(proof_rpc_res.proof);
         let input_merkle_tree_accounts = inputs
@@ -431,7 +432,9 @@ pub async fn compressed_transaction_test<R: RpcConnection, I: Indexer<R>>(
 
     let (created_output_compressed_accounts, _) = inputs
         .test_indexer
-        .add_event_and_compressed_accounts(&event.0);
+        .add_event_and_compressed_accounts(inputs.rpc, &event.0)
+        .await
+        .map_err(|e| RpcError::CustomError(e.to_string()))?;
     let input = AssertCompressedTransactionInputs {
         rpc: inputs.rpc,
         test_indexer: inputs.test_indexer,
@@ -1,9 +1,11 @@
 use std::cmp;
+use std::path::Path;
 
 use crate::assert_address_merkle_tree::assert_address_merkle_tree_initialized;
 use crate::assert_queue::assert_address_queue_initialized;
 use crate::create_account_instruction;
-use crate::registry::register_test_forester;
+use crate::indexer::TestIndexer;
+use crate::registry::{register_test_forester, register_test_verifying_keys};
 use crate::rpc::rpc_connection::RpcConnection;
 use crate::rpc::test_rpc::ProgramTestRpcConnection;
 use account_compression::sdk::create_initialize_address_merkle_tree_and_queue_instruction;
@@ -16,6 +18,7 @@ use account_compression::{NullifierQueueConfig, StateMerkleTreeConfig};
 use anchor_lang::{system_program, InstructionData, ToAccountMetas};
 use light_hasher::Poseidon;
 use light_macros::pubkey;
+use light_prover_client::prover::ProverBackend;
 use light_registry::get_forester_epoch_pda_address;
 use light_registry::sdk::{
     create_initialize_governance_authority_instruction,
@@ -32,6 +35,10 @@ use solana_sdk::{
 pub const CPI_CONTEXT_ACCOUNT_RENT: u64 = 143487360; // lamports of the cpi context account
 pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
 
+/// Name of the test build of the system program, which additionally accepts
+/// proofs of `MockProver` (built by `scripts/build-mock-proofs-program.sh`).
+pub const MOCK_PROOFS_SYSTEM_PROGRAM: &str = "light_system_program_mock_proofs";
+
 /// Setup test programs
 /// deploys:
 /// 1. light_registry program
@@ -40,12 +47,27 @@ pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiF
 /// 4. light_system_program program
 pub async fn setup_test_programs(
     additional_programs: Option<Vec<(String, Pubkey)>>,
+) -> ProgramTestContext {
+    deploy_test_programs("light_system_program", additional_programs).await
+}
+
+/// Deploys the test programs like `setup_test_programs`, with the test build
+/// of the system program which accepts mock proofs.
+pub async fn setup_mock_proofs_test_programs(
+    additional_programs: Option<Vec<(String, Pubkey)>>,
+) -> ProgramTestContext {
+    deploy_test_programs(MOCK_PROOFS_SYSTEM_PROGRAM, additional_programs).await
+}
+
+async fn deploy_test_programs(
+    system_program_name: &str,
+    additional_programs: Option<Vec<(String, Pubkey)>>,
 ) -> ProgramTestContext {
     let mut program_test = ProgramTest::default();
     program_test.add_program("light_registry", light_registry::ID, None);
     program_test.add_program("account_compression", account_compression::ID, None);
     program_test.add_program("light_compressed_token", light_compressed_token::ID, None);
-    program_test.add_program("light_system_program", light_system_program::ID, None);
+    program_test.add_program(system_program_name, light_system_program::ID, None);
     program_test.add_program("spl_noop", NOOP_PROGRAM_ID, None);
     if let Some(programs) = additional_programs {
         for (name, id) in programs {
@@ -153,13 +175,25 @@ pub const FORESTER_TEST_KEYPAIR: [u8; 64] = [
 /// 5. creates and initializes governance authority
 /// 6. creates and initializes group authority
 /// 7. registers the light_system_program program with the group authority
-/// 8. initializes Merkle tree owned by
+/// 8. registers the verifying keys compiled into light_verifier
+/// 9. initializes Merkle tree owned by
 
 pub async fn setup_test_programs_with_accounts(
     additional_programs: Option<Vec<(String, Pubkey)>>,
 ) -> (ProgramTestRpcConnection, EnvAccounts) {
+    setup_accounts(setup_test_programs(additional_programs).await).await
+}
+
+/// Sets up the programs and accounts like `setup_test_programs_with_accounts`,
+/// with the test build of the system program which accepts mock proofs.
+pub async fn setup_mock_proofs_test_programs_with_accounts(
+    additional_programs: Option<Vec<(String, Pubkey)>>,
+) -> (ProgramTestRpcConnection, EnvAccounts) {
+    setup_accounts(setup_mock_proofs_test_programs(additional_programs).await).await
+}
+
+async fn setup_accounts(context: ProgramTestContext) -> (ProgramTestRpcConnection, EnvAccounts) {
     use crate::airdrop_lamports;
-    let context = setup_test_programs(additional_programs).await;
     let mut context = ProgramTestRpcConnection { context };
     let cpi_authority_pda = get_cpi_authority_pda();
     let authority_pda = get_governance_authority_pda();
@@ -214,6 +248,10 @@ pub async fn setup_test_programs_with_accounts(
     .await
     .unwrap();
 
+    register_test_verifying_keys(&mut context, &payer)
+        .await
+        .unwrap();
+
     let merkle_tree_keypair = Keypair::from_bytes(&MERKLE_TREE_TEST_KEYPAIR).unwrap();
     let merkle_tree_pubkey = merkle_tree_keypair.pubkey();
     let nullifier_queue_keypair = Keypair::from_bytes(&NULLIFIER_QUEUE_TEST_KEYPAIR).unwrap();
@@ -279,6 +317,30 @@ pub async fn setup_test_programs_with_accounts(
     (context, env_accounts)
 }
 
+/// Sets up the test programs and accounts like
+/// `setup_test_programs_with_accounts` and restores the indexer snapshot at
+/// `path`, so that the trees start pre-filled. With `MockProver`, the test
+/// build of the system program which accepts mock proofs is deployed.
+pub async fn setup_test_programs_with_snapshot<P: AsRef<Path>, B: Into<ProverBackend>>(
+    additional_programs: Option<Vec<(String, Pubkey)>>,
+    path: P,
+    prover: B,
+) -> (
+    ProgramTestRpcConnection,
+    EnvAccounts,
+    TestIndexer<ProgramTestRpcConnection>,
+) {
+    let prover: ProverBackend = prover.into();
+    let (mut context, env) = if matches!(prover, ProverBackend::Mock(_)) {
+        setup_mock_proofs_test_programs_with_accounts(additional_programs).await
+    } else {
+        setup_test_programs_with_accounts(additional_programs).await
+    };
+    let payer = context.get_payer().insecure_clone();
+    let indexer = TestIndexer::restore_snapshot(&mut context, path, payer, prover).unwrap();
+    (context, env, indexer)
+}
+
 pub async fn initialize_new_group(
     group_seed_keypair: &Keypair,
     payer: &Keypair,
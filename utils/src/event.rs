@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+
+use thiserror::Error;
+
+/// Length of the discriminator of an event envelope.
+pub const EVENT_DISCRIMINATOR_LEN: usize = 8;
+/// Length of the event envelope (discriminator + version).
+pub const EVENT_HEADER_LEN: usize = EVENT_DISCRIMINATOR_LEN + 1;
+/// Maximum length of event data, 10240 = 10 * 1024 the max instruction data of
+/// a cpi into the noop program.
+pub const MAX_EVENT_DATA_LEN: usize = 10240;
+/// Maximum length of an event payload. The event envelope is part of the event
+/// data and reduces the space available for the payload.
+pub const MAX_EVENT_PAYLOAD_LEN: usize = MAX_EVENT_DATA_LEN - EVENT_HEADER_LEN;
+
+/// Discriminator of the public transaction event emitted by the system program.
+/// `sha256("event:PublicTransactionEvent")[..8]`
+pub const PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR: [u8; 8] = [107, 27, 168, 13, 217, 235, 183, 75];
+/// Current version of the public transaction event.
+pub const PUBLIC_TRANSACTION_EVENT_VERSION: u8 = 1;
+
+/// Discriminator of the Merkle tree events emitted by the account compression
+/// program.
+/// `sha256("event:MerkleTreeEvent")[..8]`
+pub const MERKLE_TREE_EVENT_DISCRIMINATOR: [u8; 8] = [111, 129, 78, 144, 222, 105, 71, 187];
+/// Current version of the Merkle tree event.
+pub const MERKLE_TREE_EVENT_VERSION: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum EventError {
+    #[error("Event data is shorter than the event header")]
+    InvalidEventHeader,
+    #[error("Unknown event discriminator {0:?}")]
+    UnknownDiscriminator([u8; EVENT_DISCRIMINATOR_LEN]),
+    #[error("Unsupported event version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Event deserialization failed: {0}")]
+    Deserialization(#[from] io::Error),
+}
+
+/// Envelope which prefixes every event emitted through the noop program.
+///
+/// Layout: `discriminator (8 bytes) || version (1 byte) || payload`.
+///
+/// The discriminator identifies the event type, the version identifies the
+/// layout of the payload. Payload layouts are append-only per version, fields
+/// are only added with a new version so that indexers can decode all versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventHeader {
+    pub discriminator: [u8; EVENT_DISCRIMINATOR_LEN],
+    pub version: u8,
+}
+
+impl EventHeader {
+    pub const fn new(discriminator: [u8; EVENT_DISCRIMINATOR_LEN], version: u8) -> Self {
+        Self {
+            discriminator,
+            version,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.discriminator)?;
+        writer.write_all(&[self.version])
+    }
+
+    /// Splits event data into the header and the payload.
+    pub fn split(data: &[u8]) -> Result<(Self, &[u8]), EventError> {
+        if data.len() < EVENT_HEADER_LEN {
+            return Err(EventError::InvalidEventHeader);
+        }
+        let mut discriminator = [0u8; EVENT_DISCRIMINATOR_LEN];
+        discriminator.copy_from_slice(&data[..EVENT_DISCRIMINATOR_LEN]);
+        let header = Self::new(discriminator, data[EVENT_DISCRIMINATOR_LEN]);
+        Ok((header, &data[EVENT_HEADER_LEN..]))
+    }
+
+    /// Splits event data into the version and the payload if the data starts
+    /// with the expected discriminator.
+    pub fn split_with_discriminator<'a>(
+        data: &'a [u8],
+        discriminator: &[u8; EVENT_DISCRIMINATOR_LEN],
+    ) -> Result<(u8, &'a [u8]), EventError> {
+        let (header, payload) = Self::split(data)?;
+        if header.discriminator != *discriminator {
+            return Err(EventError::UnknownDiscriminator(header.discriminator));
+        }
+        Ok((header.version, payload))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_header() {
+        let header = EventHeader::new(PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR, 1);
+        let mut data = Vec::new();
+        header.write(&mut data).unwrap();
+        data.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(data.len(), EVENT_HEADER_LEN + 3);
+        assert_eq!(MAX_EVENT_PAYLOAD_LEN, 10231);
+
+        let (split_header, payload) = EventHeader::split(&data).unwrap();
+        assert_eq!(split_header, header);
+        assert_eq!(payload, &[1, 2, 3]);
+
+        let (version, payload) =
+            EventHeader::split_with_discriminator(&data, &PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR)
+                .unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(payload, &[1, 2, 3]);
+
+        assert!(matches!(
+            EventHeader::split_with_discriminator(&data, &MERKLE_TREE_EVENT_DISCRIMINATOR),
+            Err(EventError::UnknownDiscriminator(
+                PUBLIC_TRANSACTION_EVENT_DISCRIMINATOR
+            ))
+        ));
+        assert!(matches!(
+            EventHeader::split(&data[..EVENT_HEADER_LEN - 1]),
+            Err(EventError::InvalidEventHeader)
+        ));
+    }
+}
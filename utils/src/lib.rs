@@ -12,6 +12,7 @@ use solana_program::keccak::hashv;
 use thiserror::Error;
 
 pub mod bigint;
+pub mod event;
 pub mod fee;
 pub mod offset;
 pub mod prime;
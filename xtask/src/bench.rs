@@ -20,6 +20,11 @@ pub struct Options {
     /// Select to run account compression program tests.
     #[clap(long, action = ArgAction::SetTrue)]
     account_commpression: bool,
+    /// Runs the tests of this package (e.g. `account-compression-test`)
+    /// instead of the tests of the selected program. The program is expected
+    /// to be built with the bench-sbf feature (see `--build`).
+    #[clap(long)]
+    package: Option<String>,
     /// Builds all programs with the bench-sbf feature.
     #[clap(long, action = ArgAction::SetTrue)]
     build: bool,
@@ -33,6 +38,8 @@ pub struct Options {
 
 /// cargo xtask bench --t test_8_transfer  --compressed-token --build --start-ix Transfer --verbose
 /// cargo xtask bench --t 1_mint_to  --compressed-token --build
+/// cargo xtask bench --t test_append_batch_compute_units --account-commpression --package account-compression-test --build
+/// cargo xtask bench --t test_insert_into_nullifier_queue_compute_units --account-commpression --package account-compression-test --build
 pub fn bench(opts: Options) -> anyhow::Result<()> {
     let (program, program_id) = if opts.compressed_token {
         (
@@ -62,8 +69,16 @@ pub fn bench(opts: Options) -> anyhow::Result<()> {
     for test_name in opts.t {
         println!("Running test: {}", test_name);
         println!("program: {}", program);
-        let mut command_output = Command::new("cargo")
-            .args([
+        let args = match opts.package.as_ref() {
+            Some(package) => vec![
+                "test-sbf",
+                "-p",
+                package,
+                "--",
+                "--test",
+                test_name.as_str(),
+            ],
+            None => vec![
                 "test-sbf",
                 "-p",
                 program,
@@ -72,7 +87,10 @@ pub fn bench(opts: Options) -> anyhow::Result<()> {
                 "--",
                 "--test",
                 test_name.as_str(),
-            ])
+            ],
+        };
+        let mut command_output = Command::new("cargo")
+            .args(args)
             // SVM logs are emitted via sdt err
             .stderr(Stdio::piped())
             .spawn()?;
@@ -104,6 +122,9 @@ pub fn create_bench_report(
 ) -> anyhow::Result<()> {
     // HashMap to store the start and end benchmark details
     let mut benchmarks = HashMap::<String, (u64, u64, u64, u64, u64)>::new();
+    // Benchmarks which run several times (e.g. once per transaction of a
+    // test) get a row per run, numbered from the second run on.
+    let mut runs = HashMap::<String, usize>::new();
     let mut expect_sol_log = false;
     let mut start = false;
     let mut end = false;
@@ -156,8 +177,10 @@ pub fn create_bench_report(
                 .unwrap();
             expect_sol_log = true;
             start = true;
-            current_name = name.to_string();
-            benchmarks.insert(name.to_string(), (0, mem_start, 0, 0, counter));
+            let run = runs.entry(name.to_string()).or_default();
+            *run += 1;
+            current_name = run_name(name, *run);
+            benchmarks.insert(current_name.clone(), (0, mem_start, 0, 0, counter));
             counter += 1;
         } else if line.contains("_end_bench_cu:") {
             let suffix = "_end_bench_cu:";
@@ -168,7 +191,7 @@ pub fn create_bench_report(
                 .unwrap();
             expect_sol_log = true;
             end = true;
-            current_name = name.to_string();
+            current_name = run_name(name, runs.get(name).copied().unwrap_or(1));
             let mem_end_pos_minus_one = parts.iter().position(|&s| s == "used:").unwrap();
 
             let mem_end = parts
@@ -176,7 +199,7 @@ pub fn create_bench_report(
                 .unwrap()
                 .parse::<u64>()
                 .unwrap();
-            if let Some(value) = benchmarks.get_mut(name) {
+            if let Some(value) = benchmarks.get_mut(&current_name) {
                 value.3 = mem_end;
             }
         }
@@ -221,7 +244,7 @@ pub fn create_bench_report(
 
     #[allow(clippy::clone_on_copy)]
     sorted_benchmarks.sort_by_key(|(_, (_, _, _, _, position))| position.clone());
-    for (name, (cu_pre, mem_start, cu_post, mem_end, _)) in benchmarks {
+    for (name, (cu_pre, mem_start, cu_post, mem_end, _)) in sorted_benchmarks {
         let cu_used = cu_pre - cu_post;
         let memory_used = match mem_end.checked_sub(mem_start) {
             Some(val) => val,
@@ -248,6 +271,14 @@ pub fn create_bench_report(
     println!("Writing report to: {}", path);
     Ok(())
 }
+fn run_name(name: &str, run: usize) -> String {
+    if run > 1 {
+        format!("{} {}", name, run)
+    } else {
+        name.to_string()
+    }
+}
+
 #[derive(Tabled)]
 struct RowData {
     name: String,
@@ -0,0 +1,188 @@
+use std::{fs, mem, path::PathBuf};
+
+use account_compression::{AddressMerkleTreeAccount, StateMerkleTreeAccount};
+use anchor_lang::Discriminator;
+use clap::Parser;
+use light_concurrent_merkle_tree::{
+    copy::ConcurrentMerkleTreeCopy, zero_copy::ConcurrentMerkleTreeZeroCopyMut,
+};
+use light_hasher::Poseidon;
+use light_indexed_merkle_tree::{
+    array::{IndexedArray, IndexedElement},
+    copy::IndexedMerkleTreeCopy,
+    zero_copy::IndexedMerkleTreeZeroCopyMut,
+};
+use light_merkle_tree_integrity::{
+    check_concurrent_merkle_tree, check_indexed_merkle_tree, find_divergence,
+    find_indexed_divergence, repair_concurrent_merkle_tree, repair_indexed_merkle_tree, Divergence,
+};
+use num_bigint::BigUint;
+use serde::Deserialize;
+
+#[derive(Debug, Parser)]
+pub struct Options {
+    /// Path to the data of a state or address Merkle tree account, e.g.
+    /// written by `solana account <PUBKEY> --output-file <PATH>`.
+    #[clap(long)]
+    account: PathBuf,
+    /// Path to a JSON snapshot of the tree (e.g. from an indexer) to
+    /// recompute the root from. For state Merkle trees, an array of leaves
+    /// (32-byte arrays). For address Merkle trees, an array of indexed
+    /// elements (`{ "value": "<decimal>", "next_index": <index> }`) ordered
+    /// by index.
+    #[clap(long)]
+    snapshot: Option<PathBuf>,
+    /// Repairs the state derived from the leaves (filled subtrees, rightmost
+    /// leaf, canopy) with the snapshot, which has to match the root of the
+    /// tree, and writes the repaired account data to the given path. The
+    /// account itself is not modified.
+    #[clap(long, requires = "snapshot")]
+    repair: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotElement {
+    value: String,
+    next_index: usize,
+}
+
+pub fn check_merkle_tree(opts: Options) -> anyhow::Result<()> {
+    let data = fs::read(&opts.account)?;
+    if data.len() < 8 {
+        anyhow::bail!("Account data is too short");
+    }
+    if let Some(output) = opts.repair {
+        let snapshot = opts
+            .snapshot
+            .ok_or_else(|| anyhow::anyhow!("Repair requires a snapshot"))?;
+        return repair_merkle_tree(data, snapshot, output);
+    }
+    let divergence = if data[..8] == StateMerkleTreeAccount::DISCRIMINATOR {
+        check_state_merkle_tree(&data, opts.snapshot)?
+    } else if data[..8] == AddressMerkleTreeAccount::DISCRIMINATOR {
+        check_address_merkle_tree(&data, opts.snapshot)?
+    } else {
+        anyhow::bail!("The account is neither a state nor an address Merkle tree");
+    };
+
+    match divergence {
+        Some(divergence) => anyhow::bail!("Snapshot diverges from the tree: {}", divergence),
+        None => {
+            println!("Merkle tree is valid");
+            Ok(())
+        }
+    }
+}
+
+fn check_state_merkle_tree(
+    data: &[u8],
+    snapshot: Option<PathBuf>,
+) -> anyhow::Result<Option<Divergence>> {
+    let merkle_tree = ConcurrentMerkleTreeCopy::<Poseidon, 26>::from_bytes_copy(
+        &data[8 + mem::size_of::<StateMerkleTreeAccount>()..],
+    )?;
+    print_tree_info(
+        "State",
+        merkle_tree.next_index(),
+        merkle_tree.sequence_number(),
+        &merkle_tree.root(),
+    );
+    check_concurrent_merkle_tree(&*merkle_tree)?;
+    println!("Invariants: OK");
+
+    match snapshot {
+        Some(snapshot) => {
+            let leaves: Vec<[u8; 32]> = serde_json::from_slice(&fs::read(snapshot)?)?;
+            Ok(find_divergence(&*merkle_tree, &leaves)?)
+        }
+        None => Ok(None),
+    }
+}
+
+fn check_address_merkle_tree(
+    data: &[u8],
+    snapshot: Option<PathBuf>,
+) -> anyhow::Result<Option<Divergence>> {
+    let merkle_tree = IndexedMerkleTreeCopy::<Poseidon, usize, 26, 16>::from_bytes_copy(
+        &data[8 + mem::size_of::<AddressMerkleTreeAccount>()..],
+    )?;
+    print_tree_info(
+        "Address",
+        merkle_tree.merkle_tree.next_index(),
+        merkle_tree.merkle_tree.sequence_number(),
+        &merkle_tree.merkle_tree.root(),
+    );
+    check_indexed_merkle_tree(&*merkle_tree)?;
+    println!("Invariants: OK");
+
+    match snapshot {
+        Some(snapshot) => {
+            let indexed_array = read_indexed_array(snapshot)?;
+            Ok(find_indexed_divergence(&*merkle_tree, &indexed_array)?)
+        }
+        None => Ok(None),
+    }
+}
+
+fn repair_merkle_tree(mut data: Vec<u8>, snapshot: PathBuf, output: PathBuf) -> anyhow::Result<()> {
+    let repairs = if data[..8] == StateMerkleTreeAccount::DISCRIMINATOR {
+        let mut merkle_tree =
+            ConcurrentMerkleTreeZeroCopyMut::<Poseidon, 26>::from_bytes_zero_copy_mut(
+                &mut data[8 + mem::size_of::<StateMerkleTreeAccount>()..],
+            )?;
+        let leaves: Vec<[u8; 32]> = serde_json::from_slice(&fs::read(snapshot)?)?;
+        repair_concurrent_merkle_tree(&mut merkle_tree, &leaves)?
+    } else if data[..8] == AddressMerkleTreeAccount::DISCRIMINATOR {
+        let mut merkle_tree =
+            IndexedMerkleTreeZeroCopyMut::<Poseidon, usize, 26, 16>::from_bytes_zero_copy_mut(
+                &mut data[8 + mem::size_of::<AddressMerkleTreeAccount>()..],
+            )?;
+        let indexed_array = read_indexed_array(snapshot)?;
+        repair_indexed_merkle_tree(&mut merkle_tree, &indexed_array)?
+    } else {
+        anyhow::bail!("The account is neither a state nor an address Merkle tree");
+    };
+
+    if repairs.is_empty() {
+        println!("Merkle tree is valid, nothing to repair");
+        return Ok(());
+    }
+    println!("Repaired:");
+    for repair in repairs.iter() {
+        println!("  {}", repair);
+    }
+    fs::write(&output, &data)?;
+    println!("Repaired account data written to {}", output.display());
+    Ok(())
+}
+
+fn read_indexed_array(snapshot: PathBuf) -> anyhow::Result<IndexedArray<Poseidon, usize>> {
+    let elements: Vec<SnapshotElement> = serde_json::from_slice(&fs::read(snapshot)?)?;
+    let mut indexed_array = IndexedArray::<Poseidon, usize>::default();
+    indexed_array.elements = elements
+        .into_iter()
+        .enumerate()
+        .map(|(index, element)| {
+            Ok(IndexedElement {
+                index,
+                value: element.value.parse::<BigUint>()?,
+                next_index: element.next_index,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    indexed_array.current_node_index = indexed_array.elements.len().saturating_sub(1);
+    indexed_array.highest_element_index = indexed_array
+        .elements
+        .iter()
+        .find(|element| element.next_index == 0)
+        .map(|element| element.index)
+        .unwrap_or_default();
+    Ok(indexed_array)
+}
+
+fn print_tree_info(kind: &str, next_index: usize, sequence_number: usize, root: &[u8; 32]) {
+    println!("{} Merkle tree", kind);
+    println!("  next index: {}", next_index);
+    println!("  sequence number: {}", sequence_number);
+    println!("  root: {:?}", root);
+}
@@ -1,5 +1,7 @@
+use anchor_lang::AnchorSerialize;
 use clap::Parser;
 use groth16_solana::groth16::Groth16Verifyingkey;
+use light_registry::VerifyingKeyData;
 use light_utils::rustfmt;
 use quote::quote;
 use std::{
@@ -14,6 +16,10 @@ pub struct Options {
     output_path: PathBuf,
     #[clap(long)]
     input_path: PathBuf,
+    /// Path of the verifying key account payload, uploaded to the verifying
+    /// key registry with the `write_verifying_key` instruction.
+    #[clap(long)]
+    payload_path: Option<PathBuf>,
 }
 
 pub fn create_vkeyrs_from_gnark_key(opts: Options) -> anyhow::Result<()> {
@@ -47,6 +53,11 @@ pub fn create_vkeyrs_from_gnark_key(opts: Options) -> anyhow::Result<()> {
     let mut file = File::create(&opts.output_path)?;
     file.write_all(b"// This file is generated by xtask. Do not edit it manually.\n\n")?;
     file.write_all(&rustfmt(code.to_string())?)?;
+
+    if let Some(payload_path) = opts.payload_path {
+        let payload = VerifyingKeyData::from(&vk).try_to_vec()?;
+        fs::write(payload_path, payload)?;
+    }
     Ok(())
 }
 
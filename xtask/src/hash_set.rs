@@ -1,10 +1,11 @@
 use ark_bn254::Fr;
-use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
 use clap::Parser;
-use light_hash_set::HashSet;
+use light_hash_set::{probe_index, value_index, HashSet};
 use light_utils::prime::find_next_prime_with_load_factor;
-use num_bigint::BigUint;
+use num_bigint::{BigUint, ToBigUint};
 use rand::thread_rng;
+use std::{hint::black_box, time::Instant};
 use tabled::{Table, Tabled};
 
 #[derive(Parser)]
@@ -17,6 +18,9 @@ pub struct HashSetOptions {
 enum Command {
     /// Benchmarks a hash set with given parameters.
     Bench(BenchOptions),
+    /// Compares the probing with big integer (`BigUint`) arithmetic, used
+    /// by earlier versions, with the fixed-width integer arithmetic.
+    BenchProbing(BenchProbingOptions),
 }
 
 #[derive(Parser)]
@@ -31,6 +35,26 @@ struct BenchOptions {
     rounds: usize,
 }
 
+#[derive(Parser)]
+struct BenchProbingOptions {
+    #[clap(long, default_values_t = [4800, 6857, 9601])]
+    capacities: Vec<usize>,
+    #[clap(long, default_value_t = 10_000)]
+    values: usize,
+    /// Number of probing iterations per value, `HashSet::insert` performs
+    /// up to 20.
+    #[clap(long, default_value_t = 20)]
+    iterations: usize,
+}
+
+#[derive(Tabled)]
+struct BenchProbingData {
+    capacity: usize,
+    iterations: usize,
+    biguint_ns_per_value: u128,
+    fixed_width_ns_per_value: u128,
+}
+
 #[derive(Tabled)]
 struct BenchData {
     expected_capacity: u32,
@@ -44,6 +68,7 @@ struct BenchData {
 pub(crate) fn hash_set(opts: HashSetOptions) -> anyhow::Result<()> {
     match opts.command {
         Command::Bench(opts) => bench(opts),
+        Command::BenchProbing(opts) => bench_probing(opts),
     }
 }
 
@@ -69,7 +94,11 @@ fn bench(opts: BenchOptions) -> anyhow::Result<()> {
             let sequence_number = opts.sequence_threshold * round;
 
             for element_i in 0..capacity_with_load_factor {
-                let value = BigUint::from(Fr::rand(&mut rng));
+                let value: [u8; 32] = Fr::rand(&mut rng)
+                    .into_bigint()
+                    .to_bytes_be()
+                    .try_into()
+                    .unwrap();
                 match hs.insert(&value, sequence_number) {
                     Ok(index) => hs.mark_with_sequence_number(index, sequence_number)?,
                     Err(_) => {
@@ -96,3 +125,72 @@ fn bench(opts: BenchOptions) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Probing of `HashSet` before switching to fixed-width integers.
+fn probe_index_biguint(value: &BigUint, iteration: usize, capacity: usize) -> usize {
+    let probe_index = (value.clone()
+        + iteration.to_biguint().unwrap() * iteration.to_biguint().unwrap())
+        % capacity.to_biguint().unwrap();
+    usize::try_from(&probe_index).unwrap()
+}
+
+fn bench_probing(opts: BenchProbingOptions) -> anyhow::Result<()> {
+    let mut rng = thread_rng();
+    let values = (0..opts.values)
+        .map(|_| {
+            Fr::rand(&mut rng)
+                .into_bigint()
+                .to_bytes_be()
+                .try_into()
+                .unwrap()
+        })
+        .collect::<Vec<[u8; 32]>>();
+
+    let mut result = Vec::new();
+
+    for capacity in opts.capacities {
+        // Both methods are run on the same values and have to visit the same
+        // buckets.
+        let start = Instant::now();
+        let biguint_indices = values
+            .iter()
+            .map(|value| {
+                let value = BigUint::from_bytes_be(value);
+                (0..opts.iterations)
+                    .map(|i| probe_index_biguint(black_box(&value), i, capacity))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let biguint_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let fixed_width_indices = values
+            .iter()
+            .map(|value| {
+                let value_index = value_index(black_box(value), capacity);
+                (0..opts.iterations)
+                    .map(|i| probe_index(value_index, i, capacity))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let fixed_width_elapsed = start.elapsed();
+
+        if biguint_indices != fixed_width_indices {
+            Err(anyhow::anyhow!(
+                "Probe sequences differ for capacity {capacity}"
+            ))?;
+        }
+
+        result.push(BenchProbingData {
+            capacity,
+            iterations: opts.iterations,
+            biguint_ns_per_value: biguint_elapsed.as_nanos() / values.len() as u128,
+            fixed_width_ns_per_value: fixed_width_elapsed.as_nanos() / values.len() as u128,
+        });
+    }
+
+    let table = Table::new(result);
+    println!("{table}");
+
+    Ok(())
+}
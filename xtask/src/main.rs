@@ -1,6 +1,7 @@
 use clap::{Parser, ValueEnum};
 
 mod bench;
+mod check_merkle_tree;
 mod create_vkeyrs_from_gnark_key;
 mod fee;
 mod hash_set;
@@ -38,6 +39,9 @@ enum Command {
     Fee,
     /// Hash set utilities.
     HashSet(hash_set::HashSetOptions),
+    /// Checks the integrity of a state or address Merkle tree account,
+    /// optionally compares it with a snapshot of leaves and repairs it.
+    CheckMerkleTree(check_merkle_tree::Options),
 }
 
 fn main() -> Result<(), anyhow::Error> {
@@ -55,5 +59,6 @@ fn main() -> Result<(), anyhow::Error> {
         Command::Bench(opts) => bench::bench(opts),
         Command::Fee => fee::fees(),
         Command::HashSet(opts) => hash_set::hash_set(opts),
+        Command::CheckMerkleTree(opts) => check_merkle_tree::check_merkle_tree(opts),
     }
 }